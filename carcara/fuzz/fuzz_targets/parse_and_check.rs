@@ -0,0 +1,16 @@
+//! Fuzzes the parser and checker with arbitrary problem and proof inputs. The input is split into
+//! the problem and the proof at the first null byte. Any panic is a bug: malformed inputs must
+//! always be reported as errors.
+
+#![no_main]
+
+use carcara::{check, CarcaraOptions};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let (problem, proof) = match data.iter().position(|&b| b == 0) {
+        Some(i) => (&data[..i], &data[i + 1..]),
+        None => (&data[..0], data),
+    };
+    let _ = check(problem, proof, CarcaraOptions::new());
+});
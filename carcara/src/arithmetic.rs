@@ -0,0 +1,405 @@
+//! Utilities for linear arithmetic over terms.
+//!
+//! These are used by the linear arithmetic rules of the checker, but are independent of them, so
+//! they can also be used by other tools that need to reason about linear arithmetic terms.
+
+use crate::ast::{eval_op, Operator, Rc, Term, Value};
+use indexmap::{map::Entry, IndexMap};
+use rug::{ops::NegAssign, Integer, Rational};
+use std::ops;
+
+/// A linear combination, represented by a hash map from non-constant terms to their coefficients,
+/// plus a constant term. This is also used to represent a disequality, in which case the left side
+/// is the non-constant terms and their coefficients, and the right side is the constant term.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LinearComb(pub(crate) IndexMap<Rc<Term>, Rational>, pub(crate) Rational);
+
+impl LinearComb {
+    /// Constructs an empty linear combination, which is equal to zero.
+    pub fn new() -> Self {
+        Self(IndexMap::new(), Rational::new())
+    }
+
+    /// Returns the non-constant terms of the linear combination, and their coefficients. Terms
+    /// whose coefficient is zero are not included.
+    pub fn coefficients(&self) -> &IndexMap<Rc<Term>, Rational> {
+        &self.0
+    }
+
+    /// Returns the constant term of the linear combination.
+    pub fn constant(&self) -> &Rational {
+        &self.1
+    }
+
+    /// Returns `true` if the linear combination has no non-constant terms.
+    pub fn is_constant(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Flattens a term and adds it to the linear combination, multiplying by the coefficient
+    /// `coeff`. This method is only intended to be used in `LinearComb::from_term`.
+    fn add_term(&mut self, term: &Rc<Term>, coeff: &Rational) {
+        // A note on performance: this function traverses the term recursively without making use
+        // of a cache, which means sometimes it has to recompute the result for the same term more
+        // than once. However, an old implementation of this method that could use a cache showed
+        // that making use of one can actually make the performance of this function worse.
+        // Benchmarks showed that it would more than double the average time of the `la_generic`
+        // rule, which makes extensive use of `LinerComb`s. Because of that, we prefer to not use
+        // a cache here, and traverse the term naively.
+
+        match term.as_ref() {
+            Term::Op(Operator::Add, args) => {
+                for a in args {
+                    self.add_term(a, coeff);
+                }
+            }
+            Term::Op(Operator::Sub, args) if args.len() == 1 => {
+                self.add_term(&args[0], &coeff.as_neg());
+            }
+            Term::Op(Operator::Sub, args) => {
+                self.add_term(&args[0], coeff);
+                for a in &args[1..] {
+                    self.add_term(a, &coeff.as_neg());
+                }
+            }
+            Term::Op(Operator::Mult, args) if args.len() == 2 => {
+                let (var, mut inner_coeff) = match (args[0].as_fraction(), args[1].as_fraction()) {
+                    (None, Some(coeff)) => (&args[0], coeff),
+                    (Some(coeff), _) => (&args[1], coeff),
+                    (None, None) => return self.insert(term.clone(), coeff.clone()),
+                };
+                inner_coeff *= coeff;
+                self.add_term(var, &inner_coeff);
+            }
+            // Since `to_real` doesn't change the value of its argument, we can look through it
+            Term::Op(Operator::ToReal, args) if args.len() == 1 => {
+                self.add_term(&args[0], coeff);
+            }
+            Term::Op(op @ (Operator::IntDiv | Operator::Mod | Operator::Abs), args) => {
+                // These operators are not linear, but if all of their arguments are constants we
+                // can evaluate them
+                let value = args
+                    .iter()
+                    .map(|a| a.as_signed_number().map(Value::Rational))
+                    .collect::<Option<Vec<_>>>()
+                    .and_then(|args| eval_op(*op, &args));
+                match value {
+                    Some(Value::Rational(mut r)) => {
+                        r *= coeff;
+                        self.1 += r;
+                    }
+                    _ => self.insert(term.clone(), coeff.clone()),
+                }
+            }
+            _ => {
+                if let Some(mut r) = term.as_fraction() {
+                    r *= coeff;
+                    self.1 += r;
+                } else {
+                    self.insert(term.clone(), coeff.clone());
+                }
+            }
+        }
+    }
+
+    /// Builds a linear combination from a term. Takes a term with nested additions, subtractions
+    /// and multiplications, and flattens it to linear combination, calculating the coefficient of
+    /// each atom. Applications of `to_real` are treated transparently, so `(to_real x)` and `x`
+    /// are considered the same atom. Applications of `div`, `mod` and `abs` are evaluated if all
+    /// their arguments are integer constants, and are otherwise considered atoms.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use carcara::{arithmetic::LinearComb, ast::*, parser::{Config, Parser}};
+    /// # use rug::Rational;
+    /// let mut pool = PrimitivePool::new();
+    /// let definitions = "(declare-fun x () Int)";
+    /// let mut parser = Parser::new(&mut pool, Config::new(), definitions.as_bytes())?;
+    /// parser.parse_problem()?;
+    /// parser.reset("(+ (* 2 x) 3 (- x 1))".as_bytes())?;
+    /// let term = parser.parse_term()?;
+    ///
+    /// let comb = LinearComb::from_term(&term);
+    /// assert_eq!(comb.coefficients().values().collect::<Vec<_>>(), [&Rational::from(3)]);
+    /// assert_eq!(*comb.constant(), 2);
+    /// # Ok::<(), carcara::Error>(())
+    /// ```
+    pub fn from_term(term: &Rc<Term>) -> Self {
+        let mut result = Self::new();
+        result.add_term(term, &Rational::from(1));
+        result
+    }
+
+    /// Adds `value` to the coefficient of the non-constant term `key`, removing the term if its
+    /// coefficient becomes zero.
+    pub fn insert(&mut self, key: Rc<Term>, value: Rational) {
+        match self.0.entry(key) {
+            Entry::Occupied(mut e) => {
+                *e.get_mut() += value;
+                if *e.get() == 0 {
+                    e.remove();
+                }
+            }
+            Entry::Vacant(e) => {
+                e.insert(value);
+            }
+        }
+    }
+
+    /// Finds the greatest common divisor of the coefficients in the linear combination. Returns
+    /// 1 if the linear combination is empty, or if any of the coefficients is not an integer.
+    pub fn coefficients_gcd(&self) -> Integer {
+        if !self.1.is_integer() {
+            return Integer::from(1);
+        }
+
+        let mut result = self.1.numer().clone();
+        for (_, coeff) in &self.0 {
+            if result == 1 {
+                return Integer::from(1);
+            }
+            if coeff.is_integer() {
+                result.gcd_mut(coeff.numer());
+            } else {
+                return Integer::from(1);
+            }
+        }
+
+        // If the linear combination is all zeros, the result would also be zero. In that case, we
+        // have to return one instead
+        std::cmp::max(Integer::from(1), result)
+    }
+
+    /// Applies the strengthening rules of the `la_generic` rule to the disequality represented by
+    /// the linear combination, whose right side is the constant term. The operator of the
+    /// disequality is `op`, which must be `>`, `>=` or `=`, and `a` is the coefficient by which
+    /// the disequality will be multiplied. Returns the operator of the strengthened disequality.
+    ///
+    /// If the disequality is over integers, a strict disequality is made non-strict by rounding
+    /// down its constant, and adding the greatest common divisor of its coefficients to it.
+    pub fn strengthen(&mut self, op: Operator, a: &Rational) -> Operator {
+        // Multiplications are expensive, so we avoid them if we can
+        let is_integer = if *a == 0 {
+            true
+        } else if *a == 1 {
+            self.1.is_integer()
+        } else {
+            (self.1.clone() * a).is_integer()
+        };
+
+        match op {
+            Operator::GreaterEq if is_integer => op,
+
+            // In some cases, when the disequality is over integers, we can make the
+            // strengthening rules even stronger. Consider for instance the following example:
+            // ```
+            //     (step t1 (cl
+            //         (not (<= (- 1) n))
+            //         (not (<= (- 1) (+ n m)))
+            //         (<= (- 2) (* 2 n))
+            //         (not (<= m 1))
+            //     ) :rule la_generic :args (1 1 1 1))
+            // ```
+            // After the third disequality is negated and flipped, it becomes:
+            //     -2 * n > 2
+            // If nothing fancy is done, this would strengthen to:
+            //     -2 * n >= 3
+            // However, in this case, we can divide the disequality by 2 before strengthening, and
+            // then multiply it by 2 to get back. This would result in:
+            //     -2 * n > 2
+            //     -1 * n > 1
+            //     -1 * n >= 2
+            //     -2 * n >= 4
+            // This is a stronger statement, and follows from the original disequality. Importantly,
+            // this strengthening is sometimes necessary to check some `la_generic` steps. To find
+            // the value by which we should divide we have to take the greatest common divisor of
+            // the coefficients (including the constant value on the right-hand side), as this makes
+            // sure all coefficients will continue being integers after the division. This
+            // strengthening is still valid because, since the variables are integers, the result of
+            // their linear combination will always be a multiple of their GCD.
+            Operator::GreaterThan if is_integer => {
+                // Instead of dividing and then multiplying back, we just multiply the "+ 1"
+                // that is added by the strengthening rule
+                self.1.floor_mut();
+                self.1 += self.coefficients_gcd();
+                Operator::GreaterEq
+            }
+            Operator::GreaterThan | Operator::GreaterEq => {
+                self.1.floor_mut();
+                self.1 += 1;
+                Operator::GreaterEq
+            }
+            Operator::LessThan | Operator::LessEq => unreachable!(),
+            _ => op,
+        }
+    }
+}
+
+impl ops::Add for LinearComb {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        for (var, coeff) in other.0 {
+            self.insert(var, coeff);
+        }
+        self.1 += other.1;
+        self
+    }
+}
+
+impl ops::Sub for LinearComb {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self + -other
+    }
+}
+
+impl ops::Neg for LinearComb {
+    type Output = Self;
+
+    fn neg(mut self) -> Self {
+        for coeff in self.0.values_mut() {
+            coeff.neg_assign();
+        }
+        self.1.neg_assign();
+        self
+    }
+}
+
+impl ops::MulAssign<&Rational> for LinearComb {
+    fn mul_assign(&mut self, scalar: &Rational) {
+        if *scalar == 0 {
+            self.0.clear();
+            self.1 = Rational::new();
+            return;
+        }
+
+        if *scalar == 1 {
+            return;
+        }
+
+        for coeff in self.0.values_mut() {
+            *coeff *= scalar;
+        }
+        self.1 *= scalar;
+    }
+}
+
+impl ops::Mul<&Rational> for LinearComb {
+    type Output = Self;
+
+    fn mul(mut self, scalar: &Rational) -> Self {
+        self *= scalar;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::PrimitivePool, parser::tests::parse_terms};
+
+    const DEFINITIONS: &str = "
+        (declare-fun x () Int)
+        (declare-fun y () Int)
+        (declare-fun r () Real)
+    ";
+
+    fn comb(pool: &mut PrimitivePool, term: &str) -> LinearComb {
+        let [term] = parse_terms(pool, DEFINITIONS, [term]);
+        LinearComb::from_term(&term)
+    }
+
+    #[test]
+    fn test_from_term() {
+        let mut pool = PrimitivePool::new();
+        let cases = [
+            ("(+ x x y)", "(+ (* 2 x) y)"),
+            ("(- x (- y) 3)", "(+ x y (- 3))"),
+            ("(* 2 (+ x 1))", "(+ (* 2 x) 2)"),
+            ("(- (* 3 x) (* x 3))", "0"),
+            ("(* 0.5 (+ r r))", "r"),
+            ("(+ (to_real x) (* 2.0 (to_real x)))", "(* 3 x)"),
+            ("(to_real (- x 1))", "(+ x (- 1))"),
+            ("(+ x (div 7 2))", "(+ x 3)"),
+            ("(- (mod (- 7) 2) (abs (- 3)))", "(- 2)"),
+            ("(+ (div x 2) (div x 2))", "(* 2 (div x 2))"),
+            ("(+ y (mod 3 0))", "(+ (mod 3 0) y)"),
+        ];
+        for (a, b) in cases {
+            assert_eq!(comb(&mut pool, a), comb(&mut pool, b), "{}", a);
+        }
+
+        let c = comb(&mut pool, "(- (+ x 2) x)");
+        assert!(c.is_constant());
+        assert_eq!(*c.constant(), 2);
+    }
+
+    #[test]
+    fn test_operations() {
+        let mut pool = PrimitivePool::new();
+        let a = comb(&mut pool, "(+ (* 2 x) y 1)");
+        let b = comb(&mut pool, "(- y 3)");
+
+        assert_eq!(
+            a.clone() + b.clone(),
+            comb(&mut pool, "(+ (* 2 x) (* 2 y) (- 2))")
+        );
+        assert_eq!(a.clone() - b.clone(), comb(&mut pool, "(+ (* 2 x) 4)"));
+        assert_eq!(-b.clone(), comb(&mut pool, "(- 3 y)"));
+        assert_eq!(
+            a.clone() * &Rational::from(3),
+            comb(&mut pool, "(+ (* 6 x) (* 3 y) 3)")
+        );
+        assert_eq!(a * &Rational::new(), LinearComb::new());
+    }
+
+    #[test]
+    fn test_coefficients_gcd() {
+        let mut pool = PrimitivePool::new();
+        let cases = [
+            ("(+ (* 4 x) (* 6 y) 8)", 2),
+            ("(+ (* 3 x) 9)", 3),
+            ("(+ (* 4 x) 1)", 1),
+            ("(+ (* 4.0 r) 0.5)", 1),
+            ("0", 1),
+        ];
+        for (term, expected) in cases {
+            assert_eq!(
+                comb(&mut pool, term).coefficients_gcd(),
+                expected,
+                "{}",
+                term
+            );
+        }
+    }
+
+    #[test]
+    fn test_strengthen() {
+        let mut pool = PrimitivePool::new();
+        let one = Rational::from(1);
+
+        // -2 * x > 2 is strengthened to -2 * x >= 4
+        let mut c = comb(&mut pool, "(+ (* (- 2) x) 2)");
+        assert_eq!(
+            c.strengthen(Operator::GreaterThan, &one),
+            Operator::GreaterEq
+        );
+        assert_eq!(*c.constant(), 4);
+
+        // r > 0.5 is strengthened to r >= 1
+        let mut c = comb(&mut pool, "(+ r 0.5)");
+        assert_eq!(
+            c.strengthen(Operator::GreaterThan, &one),
+            Operator::GreaterEq
+        );
+        assert_eq!(*c.constant(), 1);
+
+        // Equalities are left unchanged
+        let mut c = comb(&mut pool, "(+ x 1)");
+        assert_eq!(c.strengthen(Operator::Equals, &one), Operator::Equals);
+        assert_eq!(*c.constant(), 1);
+    }
+}
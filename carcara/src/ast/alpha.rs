@@ -0,0 +1,242 @@
+//! Comparing and hashing terms modulo alpha-equivalence, that is, modulo renaming of bound
+//! variables.
+
+use super::{IndexMap, Operator, Polyeq, PolyeqComparator, Rc, SortedVar, Symbol, Term};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+/// The kinds of binders whose variables may be renamed when comparing terms for
+/// alpha-equivalence. The variables of the other binders must have the same names in both terms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Binders {
+    /// `forall` and `exists` terms.
+    pub quantifiers: bool,
+
+    /// `let` terms.
+    pub lets: bool,
+
+    /// `choice` terms.
+    pub choices: bool,
+
+    /// `lambda` terms.
+    pub lambdas: bool,
+}
+
+impl Binders {
+    /// All binders may have their variables renamed.
+    pub const ALL: Self = Self {
+        quantifiers: true,
+        lets: true,
+        choices: true,
+        lambdas: true,
+    };
+
+    /// No binder may have its variables renamed, so terms are only alpha-equivalent if they are
+    /// equal.
+    pub const NONE: Self = Self {
+        quantifiers: false,
+        lets: false,
+        choices: false,
+        lambdas: false,
+    };
+}
+
+impl Default for Binders {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// Compares and hashes terms modulo alpha-equivalence.
+///
+/// Unlike [`alpha_equiv`](super::alpha_equiv), this keeps the results of previous comparisons and
+/// hashes, so comparing many terms that share subterms is cheaper. The hash given by
+/// [`AlphaEquiv::hash`] respects alpha-equivalence, that is, alpha-equivalent terms always have
+/// the same hash. This allows finding alpha-equivalent terms in a large set by only comparing the
+/// terms with the same hash.
+///
+/// By default, the variables of every binder may be renamed, and equalities are compared as
+/// written. These can be changed using [`AlphaEquiv::binders`] and [`AlphaEquiv::mod_reordering`].
+///
+/// # Examples
+///
+/// ```
+/// # use carcara::{ast::*, parser::{Config, Parser}};
+/// let mut pool = PrimitivePool::new();
+/// let input = "(forall ((x Int)) (= x 0)) (forall ((y Int)) (= y 0)) (forall ((y Int)) (= 0 y))";
+/// let mut parser = Parser::new(&mut pool, Config::new(), input.as_bytes())?;
+/// let [a, b, c] = [(); 3].map(|_| parser.parse_term().unwrap());
+///
+/// let mut alpha = AlphaEquiv::new();
+/// assert!(alpha.eq(&a, &b));
+/// assert_eq!(alpha.hash(&a), alpha.hash(&b));
+/// assert!(!alpha.eq(&a, &c));
+///
+/// let mut alpha = AlphaEquiv::new().mod_reordering(true);
+/// assert!(alpha.eq(&a, &c));
+/// assert_eq!(alpha.hash(&a), alpha.hash(&c));
+///
+/// let mut alpha = AlphaEquiv::new().binders(Binders::NONE);
+/// assert!(!alpha.eq(&a, &b));
+/// # Ok::<(), carcara::Error>(())
+/// ```
+pub struct AlphaEquiv {
+    comparator: PolyeqComparator,
+    is_mod_reordering: bool,
+    binders: Binders,
+
+    // Only the hashes of terms outside of any binder are cached, since the hash of a term inside a
+    // binder depends on which variables are bound
+    hashes: IndexMap<Rc<Term>, u64>,
+
+    // The variables bound by the binders we are currently in, in the order they were bound. Each
+    // bound variable is hashed by its position in this stack, which is the same index that the
+    // comparator gives it
+    bound: Vec<Symbol>,
+}
+
+impl Default for AlphaEquiv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AlphaEquiv {
+    pub fn new() -> Self {
+        Self {
+            comparator: PolyeqComparator::with_binders(false, Binders::ALL),
+            is_mod_reordering: false,
+            binders: Binders::ALL,
+            hashes: IndexMap::new(),
+            bound: Vec::new(),
+        }
+    }
+
+    /// Sets the kinds of binders whose variables may be renamed. See [`Binders`].
+    pub fn binders(self, binders: Binders) -> Self {
+        Self::with_config(self.is_mod_reordering, binders)
+    }
+
+    /// If `true`, terms are also compared modulo reordering of equalities, like in
+    /// [`polyeq`](super::polyeq). That is, `(= a b)` and `(= b a)` are considered equivalent.
+    pub fn mod_reordering(self, value: bool) -> Self {
+        Self::with_config(value, self.binders)
+    }
+
+    fn with_config(is_mod_reordering: bool, binders: Binders) -> Self {
+        Self {
+            comparator: PolyeqComparator::with_binders(is_mod_reordering, binders),
+            is_mod_reordering,
+            binders,
+            ..Self::new()
+        }
+    }
+
+    /// Returns `true` if the two terms are alpha-equivalent.
+    pub fn eq(&mut self, a: &Rc<Term>, b: &Rc<Term>) -> bool {
+        // Inside the comparator, identical terms may not be alpha-equivalent, since their bound
+        // variables may have different meanings. At the root, this can't happen
+        a == b || Polyeq::eq(&mut self.comparator, a, b)
+    }
+
+    /// Returns a hash of the term that respects alpha-equivalence. That is, if `eq(a, b)` is
+    /// `true`, `hash(a)` and `hash(b)` are the same. Since sorts are hashed by their address, this
+    /// only holds for terms added to the same pool.
+    pub fn hash(&mut self, term: &Rc<Term>) -> u64 {
+        if !self.bound.is_empty() {
+            return self.compute_hash(term);
+        }
+        if let Some(&h) = self.hashes.get(term) {
+            return h;
+        }
+        let h = self.compute_hash(term);
+        self.hashes.insert(term.clone(), h);
+        h
+    }
+
+    fn compute_hash(&mut self, term: &Rc<Term>) -> u64 {
+        let mut state = DefaultHasher::new();
+        std::mem::discriminant(term.as_ref()).hash(&mut state);
+        match term.as_ref() {
+            Term::Const(c) => c.hash(&mut state),
+            Term::Sort(s) => s.hash(&mut state),
+            Term::Var(name, sort) => {
+                match self.bound.iter().rposition(|v| v == name) {
+                    Some(i) => i.hash(&mut state),
+                    None => name.hash(&mut state),
+                }
+                sort.hash(&mut state);
+            }
+            Term::App(func, args) => {
+                self.hash(func).hash(&mut state);
+                self.hash_all(args, &mut state);
+            }
+            Term::Op(Operator::Equals, args) if self.is_mod_reordering && args.len() == 2 => {
+                // The hash of the sides must not depend on their order
+                Operator::Equals.hash(&mut state);
+                let (a, b) = (self.hash(&args[0]), self.hash(&args[1]));
+                (a.min(b), a.max(b)).hash(&mut state);
+            }
+            Term::Op(op, args) => {
+                op.hash(&mut state);
+                self.hash_all(args, &mut state);
+            }
+            Term::IndexedOp { op, op_args, args } => {
+                op.hash(&mut state);
+                op_args.hash(&mut state);
+                self.hash_all(args, &mut state);
+            }
+            Term::ParamOp { op, op_args, args } => {
+                op.hash(&mut state);
+                self.hash_all(op_args, &mut state);
+                self.hash_all(args, &mut state);
+            }
+            Term::Quant(q, bindings, inner) => {
+                q.hash(&mut state);
+                self.hash_binder(bindings, inner, self.binders.quantifiers, &mut state);
+            }
+            Term::Let(bindings, inner) => {
+                self.hash_binder(bindings, inner, self.binders.lets, &mut state);
+            }
+            Term::Lambda(bindings, inner) => {
+                self.hash_binder(bindings, inner, self.binders.lambdas, &mut state);
+            }
+            Term::Choice(var, inner) => {
+                let bindings = std::slice::from_ref(var);
+                self.hash_binder(bindings, inner, self.binders.choices, &mut state);
+            }
+        }
+        state.finish()
+    }
+
+    fn hash_all(&mut self, terms: &[Rc<Term>], state: &mut DefaultHasher) {
+        terms.len().hash(state);
+        for t in terms {
+            self.hash(t).hash(state);
+        }
+    }
+
+    fn hash_binder(
+        &mut self,
+        bindings: &[SortedVar],
+        inner: &Rc<Term>,
+        renamed: bool,
+        state: &mut DefaultHasher,
+    ) {
+        // Like in the comparator, each variable is bound before the value of the next one is
+        // hashed
+        let depth = self.bound.len();
+        bindings.len().hash(state);
+        for (name, value) in bindings {
+            if !renamed {
+                name.hash(state);
+            }
+            self.hash(value).hash(state);
+            self.bound.push(*name);
+        }
+        self.hash(inner).hash(state);
+        self.bound.truncate(depth);
+    }
+}
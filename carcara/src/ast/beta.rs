@@ -0,0 +1,109 @@
+//! Beta reduction of applications of `lambda` terms.
+
+use super::{BindingList, IndexMap, Rc, Substitution, Term, TermPool};
+
+/// Beta-reduces a term to its normal form, that is, replaces every application of a `lambda` term
+/// to some arguments, anywhere in the term, by the body of the `lambda` term with the bound
+/// variables substituted by the arguments. Since the result of a reduction may itself have new
+/// redexes (for example, when the arguments are also `lambda` terms), reductions are repeated
+/// until none are left. Substitutions are capture-avoiding, so bound variables may be renamed.
+///
+/// # Examples
+///
+/// ```
+/// # use carcara::{ast::*, parser::{Config, Parser}};
+/// let mut pool = PrimitivePool::new();
+/// let input = "(= ((lambda ((x Int) (y Int)) (+ x y)) 1 2) 3)";
+/// let term = Parser::new(&mut pool, Config::new(), input.as_bytes())?.parse_term()?;
+/// assert_eq!(format!("{:#}", beta_reduce(&mut pool, &term)), "(= (+ 1 2) 3)");
+/// # Ok::<(), carcara::Error>(())
+/// ```
+pub fn beta_reduce(pool: &mut dyn TermPool, term: &Rc<Term>) -> Rc<Term> {
+    BetaReducer::default().reduce(pool, term)
+}
+
+#[derive(Default)]
+struct BetaReducer {
+    cache: IndexMap<Rc<Term>, Rc<Term>>,
+}
+
+impl BetaReducer {
+    fn reduce(&mut self, pool: &mut dyn TermPool, term: &Rc<Term>) -> Rc<Term> {
+        if let Some(t) = self.cache.get(term) {
+            return t.clone();
+        }
+        let result = match term.as_ref() {
+            Term::Const(_) | Term::Var(..) | Term::Sort(_) => term.clone(),
+            Term::App(func, args) => {
+                let args = self.reduce_all(pool, args);
+                let func = self.reduce(pool, func);
+                match func.as_ref() {
+                    Term::Lambda(bindings, body) => {
+                        let substitution = bindings
+                            .iter()
+                            .zip(args)
+                            .map(|(var, arg)| (pool.add(var.clone().into()), arg))
+                            .collect();
+
+                        // Since the application was sort checked, the sorts of the arguments match
+                        // the sorts of the bound variables, and this substitution can't fail
+                        let body = Substitution::new(pool, substitution)
+                            .unwrap()
+                            .apply(pool, body);
+                        self.reduce(pool, &body)
+                    }
+                    _ => pool.add(Term::App(func, args)),
+                }
+            }
+            Term::Op(op, args) => {
+                let args = self.reduce_all(pool, args);
+                pool.add(Term::Op(*op, args))
+            }
+            Term::IndexedOp { op, op_args, args } => {
+                let args = self.reduce_all(pool, args);
+                pool.add(Term::IndexedOp {
+                    op: *op,
+                    op_args: op_args.clone(),
+                    args,
+                })
+            }
+            Term::ParamOp { op, op_args, args } => {
+                let args = self.reduce_all(pool, args);
+                pool.add(Term::ParamOp {
+                    op: *op,
+                    op_args: op_args.clone(),
+                    args,
+                })
+            }
+            Term::Quant(q, bindings, inner) => {
+                let inner = self.reduce(pool, inner);
+                pool.add(Term::Quant(*q, bindings.clone(), inner))
+            }
+            Term::Choice(var, inner) => {
+                let inner = self.reduce(pool, inner);
+                pool.add(Term::Choice(var.clone(), inner))
+            }
+            Term::Lambda(bindings, inner) => {
+                let inner = self.reduce(pool, inner);
+                pool.add(Term::Lambda(bindings.clone(), inner))
+            }
+            Term::Let(bindings, inner) => {
+                let values: Vec<_> = bindings.iter().map(|(_, v)| v.clone()).collect();
+                let values = self.reduce_all(pool, &values);
+                let bindings = bindings
+                    .iter()
+                    .zip(values)
+                    .map(|((name, _), value)| (*name, value))
+                    .collect();
+                let inner = self.reduce(pool, inner);
+                pool.add(Term::Let(BindingList(bindings), inner))
+            }
+        };
+        self.cache.insert(term.clone(), result.clone());
+        result
+    }
+
+    fn reduce_all(&mut self, pool: &mut dyn TermPool, terms: &[Rc<Term>]) -> Vec<Rc<Term>> {
+        terms.iter().map(|t| self.reduce(pool, t)).collect()
+    }
+}
@@ -69,13 +69,13 @@ impl ContextStack {
         self.len() == 0
     }
 
-    pub fn last(&self) -> Option<RwLockReadGuard<Option<Context>>> {
+    pub fn last(&self) -> Option<RwLockReadGuard<'_, Option<Context>>> {
         self.stack
             .last()
             .map(|id| self.context_vec[*id].1.read().unwrap())
     }
 
-    pub fn last_mut(&mut self) -> Option<RwLockWriteGuard<Option<Context>>> {
+    pub fn last_mut(&mut self) -> Option<RwLockWriteGuard<'_, Option<Context>>> {
         self.stack
             .last_mut()
             .map(|id| self.context_vec[*id].1.write().unwrap())
@@ -97,7 +97,7 @@ impl ContextStack {
     pub fn push(
         &mut self,
         pool: &mut dyn TermPool,
-        assignment_args: &[(String, Rc<Term>)],
+        assignment_args: &[(Symbol, Rc<Term>)],
         variable_args: &[SortedVar],
         context_id: usize,
     ) -> Result<(), SubstitutionError> {
@@ -119,7 +119,7 @@ impl ContextStack {
                 // we use the current state of the hash map to transform `(f y)` into `(f z)`. The
                 // resulting hash map will then contain `(:= y z)` and `(:= x (f z))`
                 for (var, value) in assignment_args {
-                    let var_term = Term::new_var(var, pool.sort(value));
+                    let var_term = Term::new_var(*var, pool.sort(value));
                     let var_term = pool.add(var_term);
                     substitution.insert(pool, var_term.clone(), value.clone())?;
                     let new_value = substitution_until_fixed_point.apply(pool, value);
@@ -129,7 +129,7 @@ impl ContextStack {
                 let mappings = assignment_args
                     .iter()
                     .map(|(var, value)| {
-                        let var_term = (var.clone(), pool.sort(value)).into();
+                        let var_term = (*var, pool.sort(value)).into();
                         (pool.add(var_term), value.clone())
                     })
                     .collect();
@@ -185,6 +185,14 @@ impl ContextStack {
             let simultaneous = build_simultaneous_substitution(pool, &curr_context.mappings).map;
             let mut cumulative_substitution = simultaneous.clone();
 
+            // Variables that are bound again in the current context, either by an assignment or by
+            // a variable argument, shadow the substitutions of the previous contexts
+            let rebound: IndexSet<Rc<Term>> = curr_context
+                .bindings
+                .iter()
+                .map(|var| pool.add(var.clone().into()))
+                .collect();
+
             if i > 0 {
                 // Waits until OS allows to read this previous context. The code structure
                 // makes sure that this context, when released for reading, will be already
@@ -204,6 +212,9 @@ impl ContextStack {
                         previous_context.cumulative_substitution.as_ref().unwrap();
 
                     for (k, v) in &previous_substitution.map {
+                        if simultaneous.contains_key(k) || rebound.contains(k) {
+                            continue;
+                        }
                         let value = match simultaneous.get(v) {
                             Some(new_value) => new_value,
                             None => v,
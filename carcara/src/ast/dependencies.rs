@@ -0,0 +1,205 @@
+//! This module implements `ProofDependencies`, which maps each proof command to the assumptions it
+//! transitively depends on.
+
+use super::*;
+use indexmap::{IndexMap, IndexSet};
+
+/// A lazily computed map from each command in a proof to the set of root assumptions it
+/// transitively depends on.
+///
+/// The root assumptions are the `assume` commands in the root proof, that is, the ones that
+/// introduce the problem premises. Assumptions that are local to a subproof are discharged by the
+/// step that ends it, so they are never included in the result. The dependencies of a subproof,
+/// identified by the id of its last step, are those of its conclusion, including any commands from
+/// outer subproofs that are referenced inside it.
+///
+/// The dependencies of each command are only computed the first time they are requested, and are
+/// then cached, along with the dependencies of every command they needed. Since commands are
+/// looked up using a [`ProofIndex`], the proof must not be modified while this struct is in use.
+///
+/// This struct is created by the [`dependencies`](Proof::dependencies) method on proofs.
+///
+/// # Examples
+///
+/// ```
+/// # use carcara::*;
+/// # fn main() -> CarcaraResult<()> {
+/// let proof = "
+///     (assume h1 (= a b))
+///     (assume h2 (= b c))
+///     (assume h3 (= c a))
+///     (step t4 (cl (= a c)) :rule trans :premises (h1 h2))
+///     (anchor :step t5)
+///     (assume t5.h1 (= a b))
+///     (step t5.t2 (cl (= a c)) :rule trans :premises (t5.h1 h2))
+///     (step t5 (cl (not (= a b)) (= a c)) :rule subproof :discharge (t5.h1))
+///     (step t6 (cl (= c c)) :rule trans :premises (h3 t4))
+/// "
+/// .as_bytes();
+/// let problem = "
+///     (declare-fun a () Int)
+///     (declare-fun b () Int)
+///     (declare-fun c () Int)
+/// "
+/// .as_bytes();
+/// let (_, proof, _) = parser::parse_instance(problem, proof, parser::Config::new())?;
+/// let mut dependencies = proof.dependencies();
+/// let of = |deps: Option<&indexmap::IndexSet<ast::StepId>>| {
+///     let mut deps: Vec<_> = deps.unwrap().iter().cloned().collect();
+///     deps.sort();
+///     deps
+/// };
+/// assert_eq!(of(dependencies.get("h1")), ["h1"]);
+/// assert_eq!(of(dependencies.get("t5")), ["h2"]);
+/// assert_eq!(of(dependencies.get("t6")), ["h1", "h2", "h3"]);
+/// assert!(dependencies.get("t7").is_none());
+///
+/// let premises = dependencies.transitive_premises("t5").unwrap();
+/// let mut premises: Vec<_> = premises.into_iter().collect();
+/// premises.sort();
+/// assert_eq!(premises, ["h2", "t5", "t5.h1", "t5.t2"]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProofDependencies<'a> {
+    commands: &'a [ProofCommand],
+    index: ProofIndex,
+    cache: IndexMap<StepId, IndexSet<StepId>>,
+}
+
+impl<'a> ProofDependencies<'a> {
+    /// Creates a new dependency map for a slice of proof commands. No dependencies are computed
+    /// until they are requested.
+    pub fn new(commands: &'a [ProofCommand]) -> Self {
+        Self {
+            commands,
+            index: ProofIndex::new(commands),
+            cache: IndexMap::new(),
+        }
+    }
+
+    /// Returns the ids of the root assumptions that the command with the given id transitively
+    /// depends on, or `None` if no such command exists.
+    pub fn get(&mut self, id: &str) -> Option<&IndexSet<StepId>> {
+        if !self.cache.contains_key(id) {
+            let path = self.index.path(id)?.to_vec();
+            self.compute(path);
+        }
+        self.cache.get(id)
+    }
+
+    /// Returns the ids of every command that the command with the given id transitively depends
+    /// on, including the command itself, or `None` if no such command exists. Unlike `get`, this
+    /// includes steps and local assumptions, and is not cached.
+    pub fn transitive_premises(&self, id: &str) -> Option<IndexSet<StepId>> {
+        let mut visited = IndexSet::new();
+        let mut result = IndexSet::new();
+        let mut stack = vec![self.index.path(id)?.to_vec()];
+        while let Some(path) = stack.pop() {
+            if !visited.insert(path.clone()) {
+                continue;
+            }
+            result.insert(self.command_at(&path).id().clone());
+            stack.extend(self.direct_dependencies(&path));
+        }
+        Some(result)
+    }
+
+    /// Returns `true` if the command with the given id transitively depends on the root assumption
+    /// with id `assumption`.
+    pub fn depends_on(&mut self, id: &str, assumption: &str) -> bool {
+        self.get(id).is_some_and(|deps| deps.contains(assumption))
+    }
+
+    /// Returns the command at the given path.
+    fn command_at(&self, path: &[usize]) -> &'a ProofCommand {
+        let (last, init) = path.split_last().unwrap();
+        let mut commands = self.commands;
+        for &i in init {
+            match &commands[i] {
+                ProofCommand::Subproof(s) => commands = &s.commands,
+                _ => unreachable!(),
+            }
+        }
+        &commands[*last]
+    }
+
+    /// Returns the paths of the commands that the command at the given path directly depends on.
+    fn direct_dependencies(&self, path: &[usize]) -> Vec<Vec<usize>> {
+        let (path, step) = match self.command_at(path) {
+            ProofCommand::Assume { .. } => return Vec::new(),
+            ProofCommand::Step(s) => (path.to_vec(), s),
+            ProofCommand::Subproof(s) => {
+                let mut path = path.to_vec();
+                path.push(s.commands.len() - 1);
+                let Some(ProofCommand::Step(step)) = s.commands.last() else {
+                    unreachable!()
+                };
+                (path, step)
+            }
+        };
+
+        let mut result: Vec<_> = step
+            .premises
+            .iter()
+            .map(|&(depth, i)| {
+                let mut premise_path = path[..depth].to_vec();
+                premise_path.push(i);
+                premise_path
+            })
+            .collect();
+
+        // The last step in a subproof implicitly references the previous command in the subproof
+        let (&last, init) = path.split_last().unwrap();
+        if !init.is_empty() && last > 0 {
+            let ProofCommand::Subproof(s) = self.command_at(init) else {
+                unreachable!()
+            };
+            if last == s.commands.len() - 1 {
+                let mut previous = init.to_vec();
+                previous.push(last - 1);
+                result.push(previous);
+            }
+        }
+        result
+    }
+
+    /// Computes the dependencies of the command at the given path, and of every command it needs.
+    fn compute(&mut self, path: Vec<usize>) {
+        // To avoid stack overflows in very long proofs, this is done iteratively. Each command is
+        // only computed after all of its direct dependencies have been
+        let mut stack = vec![path];
+        while let Some(current) = stack.last() {
+            let command = self.command_at(current);
+            if self.cache.contains_key(command.id()) {
+                stack.pop();
+                continue;
+            }
+
+            let dependencies = self.direct_dependencies(current);
+            let pending: Vec<_> = dependencies
+                .iter()
+                .filter(|p| !self.cache.contains_key(self.command_at(p).id()))
+                .cloned()
+                .collect();
+            if !pending.is_empty() {
+                stack.extend(pending);
+                continue;
+            }
+
+            let result = match command {
+                ProofCommand::Assume { id, .. } if current.len() == 1 => {
+                    IndexSet::from([id.clone()])
+                }
+                _ => dependencies
+                    .iter()
+                    .flat_map(|p| &self.cache[self.command_at(p).id()])
+                    .cloned()
+                    .collect(),
+            };
+            self.cache.insert(command.id().clone(), result);
+            stack.pop();
+        }
+    }
+}
@@ -0,0 +1,797 @@
+//! An evaluator for ground terms, following the semantics of the SMT-LIB theories.
+
+use super::{Constant, IndexedOperator, Operator, Rc, Substitution, Term, TermPool};
+use indexmap::IndexMap;
+use rug::{Integer, Rational};
+use std::cmp::Ordering;
+
+/// The maximum code point allowed in SMT-LIB strings.
+const MAX_CODE_POINT: u32 = 0x2FFFF;
+
+/// The value of a ground term.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Value {
+    /// A boolean value.
+    Bool(bool),
+
+    /// A numerical value. Both integers and reals are represented by rationals. An integer value
+    /// always has denominator one.
+    Rational(Rational),
+
+    /// A bitvector value. The first integer is the value, which is always non-negative and less
+    /// than `2^width`, and the second is the width.
+    BitVec(Integer, Integer),
+
+    /// A string value. Unlike string constants, all escape sequences are already interpreted, so
+    /// each `char` is a character of the string.
+    String(String),
+}
+
+impl Value {
+    /// Returns the inner boolean, if this is a boolean value.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Returns the inner rational, if this is a numerical value.
+    pub fn as_rational(&self) -> Option<&Rational> {
+        match self {
+            Value::Rational(r) => Some(r),
+            _ => None,
+        }
+    }
+
+    /// Returns the inner integer, if this is a numerical value with no fractional part.
+    fn as_integer(&self) -> Option<Integer> {
+        self.as_rational()
+            .filter(|r| r.is_integer())
+            .map(|r| r.numer().clone())
+    }
+
+    /// Returns the value and width of this bitvector, if this is a bitvector value.
+    fn as_bitvec(&self) -> Option<(&Integer, u32)> {
+        match self {
+            Value::BitVec(value, width) => Some((value, width.to_u32()?)),
+            _ => None,
+        }
+    }
+
+    /// Returns the inner string, if this is a string value.
+    pub fn as_string(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Evaluates a ground term, returning its value.
+///
+/// This returns `None` if the term is not ground, or if its value can't be determined by the
+/// evaluator. That includes terms with free variables or uninterpreted functions, quantifiers,
+/// arrays, regular expressions, and partial operations applied outside their domain, like division
+/// by zero. `let` terms are evaluated by substituting their bindings, which is why a term pool is
+/// needed.
+///
+/// # Examples
+///
+/// ```
+/// # use carcara::{ast::*, parser::{Config, Parser}};
+/// # use rug::Rational;
+/// let mut pool = PrimitivePool::new();
+/// let input = "(+ (div 7 2) (str.len \"abc\"))";
+/// let term = Parser::new(&mut pool, Config::new(), input.as_bytes())?.parse_term()?;
+/// assert_eq!(evaluate(&mut pool, &term), Some(Value::Rational(Rational::from(6))));
+/// # Ok::<(), carcara::Error>(())
+/// ```
+pub fn evaluate(pool: &mut dyn TermPool, term: &Rc<Term>) -> Option<Value> {
+    Evaluator { pool, cache: IndexMap::new() }.eval(term)
+}
+
+struct Evaluator<'a> {
+    pool: &'a mut dyn TermPool,
+    cache: IndexMap<Rc<Term>, Option<Value>>,
+}
+
+impl Evaluator<'_> {
+    fn eval(&mut self, term: &Rc<Term>) -> Option<Value> {
+        if let Some(value) = self.cache.get(term) {
+            return value.clone();
+        }
+        let result = match term.as_ref() {
+            Term::Const(c) => eval_constant(c),
+            Term::Op(op, args) => {
+                let args = args
+                    .iter()
+                    .map(|a| self.eval(a))
+                    .collect::<Option<Vec<_>>>()?;
+                eval_op(*op, &args)
+            }
+            Term::IndexedOp { op, op_args, args } => {
+                let op_args = op_args
+                    .iter()
+                    .map(Constant::as_integer)
+                    .collect::<Option<Vec<_>>>()?;
+                let args = args
+                    .iter()
+                    .map(|a| self.eval(a))
+                    .collect::<Option<Vec<_>>>()?;
+                eval_indexed_op(*op, &op_args, &args)
+            }
+            Term::Let(bindings, inner) => {
+                let map = bindings
+                    .iter()
+                    .map(|(name, value)| {
+                        let sort = self.pool.sort(value);
+                        let var = self.pool.add(Term::new_var(*name, sort));
+                        (var, value.clone())
+                    })
+                    .collect();
+                let substituted = Substitution::new(self.pool, map)
+                    .ok()?
+                    .apply(self.pool, inner);
+                self.eval(&substituted)
+            }
+            Term::Var(..) if term.is_bool_true() => Some(Value::Bool(true)),
+            Term::Var(..) if term.is_bool_false() => Some(Value::Bool(false)),
+            Term::Var(..)
+            | Term::App(..)
+            | Term::Sort(_)
+            | Term::Quant(..)
+            | Term::Choice(..)
+            | Term::Lambda(..)
+            | Term::ParamOp { .. } => None,
+        };
+        self.cache.insert(term.clone(), result.clone());
+        result
+    }
+}
+
+fn eval_constant(constant: &Constant) -> Option<Value> {
+    Some(match constant {
+        Constant::Integer(i) => Value::Rational(i.clone().into()),
+        Constant::Real(r) => Value::Rational(r.clone()),
+        Constant::String(s) => Value::String(unescape_string(s)?),
+        Constant::BitVec(value, width) => {
+            let w = width.to_u32()?;
+            Value::BitVec(value.clone().keep_bits(w), width.clone())
+        }
+    })
+}
+
+/// Interprets the escape sequences in a string constant, as defined by SMT-LIB. Returns `None` if
+/// the string contains a code point that can't be represented by a `char`.
+fn unescape_string(s: &str) -> Option<String> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut result = String::with_capacity(s.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if let Some((c, len)) = read_escape_sequence(&chars[i..]) {
+            result.push(char::from_u32(c)?);
+            i += len;
+        } else {
+            result.push(chars[i]);
+            i += 1;
+        }
+    }
+    Some(result)
+}
+
+/// If `chars` starts with a valid escape sequence, either `\ud₃d₂d₁d₀` or `\u{d₀}` to
+/// `\u{d₄d₃d₂d₁d₀}`, returns its code point and length.
+fn read_escape_sequence(chars: &[char]) -> Option<(u32, usize)> {
+    let rest = match chars {
+        ['\\', 'u', rest @ ..] => rest,
+        _ => return None,
+    };
+    let (digits, len) = match rest {
+        ['{', rest @ ..] => {
+            let end = rest.iter().position(|&c| c == '}')?;
+            if !(1..=5).contains(&end) {
+                return None;
+            }
+            (&rest[..end], end + 4)
+        }
+        _ if rest.len() >= 4 => (&rest[..4], 6),
+        _ => return None,
+    };
+    if !digits.iter().all(char::is_ascii_hexdigit) {
+        return None;
+    }
+    let digits: String = digits.iter().collect();
+    let code = u32::from_str_radix(&digits, 16).ok()?;
+    (code <= MAX_CODE_POINT).then_some((code, len))
+}
+
+/// Evaluates an application of `op` to the given values. Returns `None` if the operator is not
+/// supported or if the application is undefined.
+pub fn eval_op(op: Operator, args: &[Value]) -> Option<Value> {
+    let bools = || args.iter().map(Value::as_bool).collect::<Option<Vec<_>>>();
+    let rationals = || {
+        args.iter()
+            .map(Value::as_rational)
+            .collect::<Option<Vec<_>>>()
+    };
+    let integers = || {
+        args.iter()
+            .map(Value::as_integer)
+            .collect::<Option<Vec<_>>>()
+    };
+    let strings = || {
+        args.iter()
+            .map(Value::as_string)
+            .collect::<Option<Vec<_>>>()
+    };
+    let bitvecs = || {
+        args.iter()
+            .map(Value::as_bitvec)
+            .collect::<Option<Vec<_>>>()
+    };
+
+    let result = match op {
+        Operator::Not => Value::Bool(!*bools()?.first()?),
+        Operator::Implies => {
+            let bools = bools()?;
+            let (conclusion, premises) = bools.split_last()?;
+            Value::Bool(*conclusion || premises.iter().any(|p| !p))
+        }
+        Operator::And => Value::Bool(bools()?.iter().all(|&b| b)),
+        Operator::Or => Value::Bool(bools()?.iter().any(|&b| b)),
+        Operator::Xor => Value::Bool(bools()?.iter().fold(false, |acc, &b| acc ^ b)),
+        Operator::Equals => Value::Bool(args.windows(2).all(|w| w[0] == w[1])),
+        Operator::Distinct => Value::Bool(
+            (0..args.len()).all(|i| args[i + 1..].iter().all(|other| *other != args[i])),
+        ),
+        Operator::Ite => match args {
+            [Value::Bool(cond), then, else_] => {
+                if *cond {
+                    then.clone()
+                } else {
+                    else_.clone()
+                }
+            }
+            _ => return None,
+        },
+
+        Operator::Add => Value::Rational(rationals()?.into_iter().sum()),
+        Operator::Mult => Value::Rational(rationals()?.into_iter().product()),
+        Operator::Sub => match rationals()?.as_slice() {
+            [x] => Value::Rational(-(*x).clone()),
+            [first, rest @ ..] => {
+                Value::Rational(rest.iter().fold((*first).clone(), |acc, &x| acc - x))
+            }
+            [] => return None,
+        },
+        Operator::RealDiv => {
+            let rationals = rationals()?;
+            let (first, rest) = rationals.split_first()?;
+            let mut result = (*first).clone();
+            for &x in rest {
+                if *x == 0 {
+                    return None;
+                }
+                result /= x;
+            }
+            Value::Rational(result)
+        }
+        Operator::IntDiv | Operator::Mod => {
+            // Both `div` and `mod` follow euclidean division, so the remainder is always
+            // non-negative
+            let integers = integers()?;
+            let (first, rest) = integers.split_first()?;
+            let mut result = first.clone();
+            for x in rest {
+                if *x == 0 {
+                    return None;
+                }
+                let (quotient, remainder) = result.div_rem_euc(x.clone());
+                result = if op == Operator::IntDiv {
+                    quotient
+                } else {
+                    remainder
+                };
+            }
+            Value::Rational(result.into())
+        }
+        Operator::Abs => Value::Rational((*rationals()?.first()?).clone().abs()),
+        Operator::LessThan | Operator::GreaterThan | Operator::LessEq | Operator::GreaterEq => {
+            let rationals = rationals()?;
+            Value::Bool(rationals.windows(2).all(|w| holds(op, w[0].cmp(w[1]))))
+        }
+        Operator::ToReal => Value::Rational((*rationals()?.first()?).clone()),
+        Operator::ToInt => Value::Rational((*rationals()?.first()?).clone().floor()),
+        Operator::IsInt => Value::Bool(rationals()?.first()?.is_integer()),
+
+        Operator::StrConcat => Value::String(strings()?.concat()),
+        Operator::StrLen => Value::Rational(strings()?.first()?.chars().count().into()),
+        Operator::StrLessThan | Operator::StrLessEq => {
+            let strings = strings()?;
+            let ordering = strings.first()?.cmp(strings.get(1)?);
+            let op = if op == Operator::StrLessThan {
+                Operator::LessThan
+            } else {
+                Operator::LessEq
+            };
+            Value::Bool(holds(op, ordering))
+        }
+        Operator::CharAt => match args {
+            [Value::String(s), i] => Value::String(substring(s, &i.as_integer()?, &1.into())),
+            _ => return None,
+        },
+        Operator::Substring => match args {
+            [Value::String(s), i, n] => {
+                Value::String(substring(s, &i.as_integer()?, &n.as_integer()?))
+            }
+            _ => return None,
+        },
+        Operator::PrefixOf => match strings()?.as_slice() {
+            [s, t] => Value::Bool(t.starts_with(s)),
+            _ => return None,
+        },
+        Operator::SuffixOf => match strings()?.as_slice() {
+            [s, t] => Value::Bool(t.ends_with(s)),
+            _ => return None,
+        },
+        Operator::Contains => match strings()?.as_slice() {
+            [s, t] => Value::Bool(s.contains(t)),
+            _ => return None,
+        },
+        Operator::IndexOf => match args {
+            [Value::String(s), Value::String(t), i] => {
+                Value::Rational(index_of(s, t, &i.as_integer()?).into())
+            }
+            _ => return None,
+        },
+        Operator::Replace => match strings()?.as_slice() {
+            [s, "", u] => Value::String(format!("{}{}", u, s)),
+            [s, t, u] => Value::String(s.replacen(t, u, 1)),
+            _ => return None,
+        },
+        Operator::ReplaceAll => match strings()?.as_slice() {
+            [s, "", _] => Value::String((*s).to_owned()),
+            [s, t, u] => Value::String(s.replace(t, u)),
+            _ => return None,
+        },
+        Operator::StrIsDigit => {
+            let s = *strings()?.first()?;
+            Value::Bool(s.len() == 1 && s.chars().all(|c| c.is_ascii_digit()))
+        }
+        Operator::StrToCode => {
+            let s = *strings()?.first()?;
+            let mut chars = s.chars();
+            let code = match (chars.next(), chars.next()) {
+                (Some(c), None) => Integer::from(c as u32),
+                _ => Integer::from(-1),
+            };
+            Value::Rational(code.into())
+        }
+        Operator::StrFromCode => {
+            let code = integers()?
+                .first()?
+                .to_u32()
+                .filter(|&c| c <= MAX_CODE_POINT);
+            let s = match code {
+                Some(c) => char::from_u32(c)?.to_string(),
+                None => String::new(),
+            };
+            Value::String(s)
+        }
+        Operator::StrToInt => {
+            let s = *strings()?.first()?;
+            let n = if !s.is_empty() && s.chars().all(|c| c.is_ascii_digit()) {
+                s.parse::<Integer>().ok()?
+            } else {
+                Integer::from(-1)
+            };
+            Value::Rational(n.into())
+        }
+        Operator::StrFromInt => {
+            let n = integers()?.first()?.clone();
+            Value::String(if n < 0 { String::new() } else { n.to_string() })
+        }
+
+        Operator::BvNot => {
+            let [(x, w)] = bitvecs()?[..] else {
+                return None;
+            };
+            bitvec(!x.clone(), w)
+        }
+        Operator::BvNeg => {
+            let [(x, w)] = bitvecs()?[..] else {
+                return None;
+            };
+            bitvec(-x.clone(), w)
+        }
+        Operator::BvAnd | Operator::BvOr | Operator::BvXor | Operator::BvAdd | Operator::BvMul => {
+            let bitvecs = bitvecs()?;
+            let (&(first, w), rest) = bitvecs.split_first()?;
+            let mut result = first.clone();
+            for &(x, _) in rest {
+                result = match op {
+                    Operator::BvAnd => result & x,
+                    Operator::BvOr => result | x,
+                    Operator::BvXor => result ^ x,
+                    Operator::BvAdd => result + x,
+                    _ => result * x,
+                };
+            }
+            bitvec(result, w)
+        }
+        Operator::BvConcat => {
+            let bitvecs = bitvecs()?;
+            let mut result = Integer::ZERO;
+            let mut total_width = 0u32;
+            for (x, w) in bitvecs {
+                result = (result << w) | x;
+                total_width = total_width.checked_add(w)?;
+            }
+            bitvec(result, total_width)
+        }
+        Operator::BvBbTerm => {
+            let bools = bools()?;
+            let mut result = Integer::ZERO;
+            for (i, b) in bools.iter().enumerate() {
+                result.set_bit(i as u32, *b);
+            }
+            bitvec(result, bools.len() as u32)
+        }
+        Operator::BvToNat => {
+            let [(x, _)] = bitvecs()?[..] else {
+                return None;
+            };
+            Value::Rational(x.clone().into())
+        }
+        Operator::BvUDiv
+        | Operator::BvURem
+        | Operator::BvShl
+        | Operator::BvLShr
+        | Operator::BvULt
+        | Operator::BvNAnd
+        | Operator::BvNOr
+        | Operator::BvXNor
+        | Operator::BvComp
+        | Operator::BvSub
+        | Operator::BvSDiv
+        | Operator::BvSRem
+        | Operator::BvSMod
+        | Operator::BvAShr
+        | Operator::BvULe
+        | Operator::BvUGt
+        | Operator::BvUGe
+        | Operator::BvSLt
+        | Operator::BvSLe
+        | Operator::BvSGt
+        | Operator::BvSGe => {
+            let [(x, w), (y, _)] = bitvecs()?[..] else {
+                return None;
+            };
+            eval_binary_bv_op(op, x, y, w)?
+        }
+
+        // Arrays, regular expressions and floating-point terms are not evaluated
+        Operator::Select
+        | Operator::Store
+        | Operator::StrToRe
+        | Operator::StrInRe
+        | Operator::ReplaceRe
+        | Operator::ReplaceReAll
+        | Operator::ReNone
+        | Operator::ReAll
+        | Operator::ReAllChar
+        | Operator::ReConcat
+        | Operator::ReUnion
+        | Operator::ReIntersection
+        | Operator::ReKleeneClosure
+        | Operator::ReComplement
+        | Operator::ReDiff
+        | Operator::ReKleeneCross
+        | Operator::ReOption
+        | Operator::ReRange
+        | Operator::Fp
+        | Operator::RoundNearestTiesToEven
+        | Operator::RoundNearestTiesToAway
+        | Operator::RoundTowardPositive
+        | Operator::RoundTowardNegative
+        | Operator::RoundTowardZero
+        | Operator::FpAbs
+        | Operator::FpNeg
+        | Operator::FpAdd
+        | Operator::FpSub
+        | Operator::FpMul
+        | Operator::FpDiv
+        | Operator::FpFma
+        | Operator::FpSqrt
+        | Operator::FpRem
+        | Operator::FpRoundToIntegral
+        | Operator::FpMin
+        | Operator::FpMax
+        | Operator::FpLessEq
+        | Operator::FpLessThan
+        | Operator::FpGreaterEq
+        | Operator::FpGreaterThan
+        | Operator::FpEquals
+        | Operator::FpIsNormal
+        | Operator::FpIsSubnormal
+        | Operator::FpIsZero
+        | Operator::FpIsInfinite
+        | Operator::FpIsNaN
+        | Operator::FpIsNegative
+        | Operator::FpIsPositive
+        | Operator::FpToReal => return None,
+    };
+    Some(result)
+}
+
+fn eval_binary_bv_op(op: Operator, x: &Integer, y: &Integer, w: u32) -> Option<Value> {
+    let (sx, sy) = (to_signed(x, w), to_signed(y, w));
+    let result = match op {
+        Operator::BvSub => bitvec(x.clone() - y, w),
+        Operator::BvNAnd => bitvec(!(x.clone() & y), w),
+        Operator::BvNOr => bitvec(!(x.clone() | y), w),
+        Operator::BvXNor => bitvec(!(x.clone() ^ y), w),
+        Operator::BvComp => bitvec(Integer::from(u8::from(x == y)), 1),
+
+        // Division by zero results in a vector of all ones, and the remainder of a division by
+        // zero is the dividend
+        Operator::BvUDiv if *y == 0 => bitvec(Integer::from(-1), w),
+        Operator::BvUDiv => bitvec(x.clone() / y, w),
+        Operator::BvURem if *y == 0 => bitvec(x.clone(), w),
+        Operator::BvURem => bitvec(x.clone() % y, w),
+        Operator::BvSDiv if sy == 0 => bitvec(Integer::from(if sx < 0 { 1 } else { -1 }), w),
+        Operator::BvSDiv => bitvec(sx / sy, w),
+        Operator::BvSRem if sy == 0 => bitvec(x.clone(), w),
+        Operator::BvSRem => bitvec(sx % sy, w),
+        Operator::BvSMod if sy == 0 => bitvec(x.clone(), w),
+        Operator::BvSMod => {
+            // The result of `bvsmod` has the same sign as the divisor
+            let remainder = sx % &sy;
+            if remainder != 0 && (remainder < 0) != (sy < 0) {
+                bitvec(remainder + sy, w)
+            } else {
+                bitvec(remainder, w)
+            }
+        }
+
+        Operator::BvShl | Operator::BvLShr | Operator::BvAShr => {
+            let shift = y.to_u32().filter(|&s| s < w);
+            match (op, shift) {
+                (Operator::BvShl, Some(s)) => bitvec(x.clone() << s, w),
+                (Operator::BvLShr, Some(s)) => bitvec(x.clone() >> s, w),
+                (Operator::BvAShr, Some(s)) => bitvec(sx >> s, w),
+                (Operator::BvAShr, None) => bitvec(Integer::from(if sx < 0 { -1 } else { 0 }), w),
+                _ => bitvec(Integer::ZERO, w),
+            }
+        }
+
+        Operator::BvULt => Value::Bool(x < y),
+        Operator::BvULe => Value::Bool(x <= y),
+        Operator::BvUGt => Value::Bool(x > y),
+        Operator::BvUGe => Value::Bool(x >= y),
+        Operator::BvSLt => Value::Bool(sx < sy),
+        Operator::BvSLe => Value::Bool(sx <= sy),
+        Operator::BvSGt => Value::Bool(sx > sy),
+        Operator::BvSGe => Value::Bool(sx >= sy),
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn eval_indexed_op(op: IndexedOperator, op_args: &[Integer], args: &[Value]) -> Option<Value> {
+    let indices = op_args
+        .iter()
+        .map(Integer::to_u32)
+        .collect::<Option<Vec<_>>>()?;
+    let bv_arg = || match args {
+        [arg] => arg.as_bitvec(),
+        _ => None,
+    };
+    let result = match (op, indices.as_slice()) {
+        (IndexedOperator::BvExtract, &[i, j]) => {
+            let (x, _) = bv_arg()?;
+            bitvec(x.clone() >> j, i.checked_sub(j)? + 1)
+        }
+        (IndexedOperator::BvBitOf, &[i]) => Value::Bool(bv_arg()?.0.get_bit(i)),
+        (IndexedOperator::ZeroExtend, &[n]) => {
+            let (x, w) = bv_arg()?;
+            bitvec(x.clone(), w.checked_add(n)?)
+        }
+        (IndexedOperator::SignExtend, &[n]) => {
+            let (x, w) = bv_arg()?;
+            bitvec(to_signed(x, w), w.checked_add(n)?)
+        }
+        (IndexedOperator::Repeat, &[n]) => {
+            let (x, w) = bv_arg()?;
+            let mut result = Integer::ZERO;
+            for _ in 0..n {
+                result = (result << w) | x;
+            }
+            bitvec(result, w.checked_mul(n)?)
+        }
+        (IndexedOperator::RotateLeft | IndexedOperator::RotateRight, &[n]) => {
+            let (x, w) = bv_arg()?;
+            let n = n % w;
+            let n = if op == IndexedOperator::RotateLeft {
+                n
+            } else {
+                (w - n) % w
+            };
+            bitvec((x.clone() << n) | (x.clone() >> (w - n)), w)
+        }
+        (IndexedOperator::IntToBv, &[w]) => match args {
+            [n] => bitvec(n.as_integer()?, w),
+            _ => return None,
+        },
+        (IndexedOperator::Divisible, _) => match args {
+            [n] => Value::Bool(n.as_integer()?.is_divisible(op_args.first()?)),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// Constructs a bitvector value, taking the given integer modulo `2^width`.
+fn bitvec(value: Integer, width: u32) -> Value {
+    Value::BitVec(value.keep_bits(width), width.into())
+}
+
+/// Interprets a bitvector value as a two's complement signed integer.
+fn to_signed(value: &Integer, width: u32) -> Integer {
+    if width > 0 && value.get_bit(width - 1) {
+        value.clone() - (Integer::from(1) << width)
+    } else {
+        value.clone()
+    }
+}
+
+/// Returns whether the comparison operator `op` holds for two values with the given ordering.
+fn holds(op: Operator, ordering: Ordering) -> bool {
+    match op {
+        Operator::LessThan => ordering.is_lt(),
+        Operator::GreaterThan => ordering.is_gt(),
+        Operator::LessEq => ordering.is_le(),
+        Operator::GreaterEq => ordering.is_ge(),
+        _ => unreachable!(),
+    }
+}
+
+/// Returns the substring of `s` with at most `length` characters, starting at `start`, following
+/// the semantics of `str.substr`.
+fn substring(s: &str, start: &Integer, length: &Integer) -> String {
+    let len = s.chars().count();
+    match (start.to_usize(), length.to_usize()) {
+        (Some(start), Some(length)) if start < len => s.chars().skip(start).take(length).collect(),
+        _ => String::new(),
+    }
+}
+
+/// Returns the index, in characters, of the first occurrence of `t` in `s` at or after `start`, or
+/// -1 if there is none, following the semantics of `str.indexof`.
+fn index_of(s: &str, t: &str, start: &Integer) -> Integer {
+    let chars: Vec<char> = s.chars().collect();
+    let pattern: Vec<char> = t.chars().collect();
+    let Some(start) = start.to_usize().filter(|&i| i <= chars.len()) else {
+        return Integer::from(-1);
+    };
+    let found = (start..=chars.len())
+        .find(|&i| chars[i..].starts_with(&pattern))
+        .map(Integer::from);
+    found.unwrap_or_else(|| Integer::from(-1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::PrimitivePool, parser::tests::parse_terms};
+
+    fn run_tests(definitions: &str, cases: &[(&str, &str)]) {
+        let mut pool = PrimitivePool::new();
+        for &(term, expected) in cases {
+            let [term, expected] = parse_terms(&mut pool, definitions, [term, expected]);
+            let got = evaluate(&mut pool, &term);
+            assert!(got.is_some(), "could not evaluate '{:#}'", term);
+            let want = evaluate(&mut pool, &expected);
+            assert!(want.is_some(), "could not evaluate '{:#}'", expected);
+            assert_eq!(got, want, "{:#}", term);
+        }
+    }
+
+    #[test]
+    fn test_core_and_arithmetic() {
+        run_tests(
+            "",
+            &[
+                ("(and true (or false (not false)))", "true"),
+                ("(=> true true false)", "false"),
+                ("(xor true true true)", "true"),
+                ("(distinct 1 2 1)", "false"),
+                ("(ite (< 1 2 3) 4 5)", "4"),
+                ("(let ((x 3) (y 4)) (* x y))", "12"),
+                ("(- 5 2 1)", "2"),
+                ("(- 3)", "(- 3)"),
+                ("(/ 1.0 4.0)", "0.25"),
+                ("(div (- 7) 2)", "(- 4)"),
+                ("(mod (- 7) 2)", "1"),
+                ("(div 7 (- 2))", "(- 3)"),
+                ("(to_int (- 1.5))", "(- 2)"),
+                ("(is_int 2.0)", "true"),
+                ("(abs (- 3))", "3"),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_strings() {
+        run_tests(
+            "",
+            &[
+                ("(str.++ \"ab\" \"c\")", "\"abc\""),
+                ("(str.len \"\\u{61}b\")", "2"),
+                ("(= \"\\u0061\" \"a\")", "true"),
+                ("(str.at \"abc\" 1)", "\"b\""),
+                ("(str.at \"abc\" 3)", "\"\""),
+                ("(str.substr \"abcde\" 1 3)", "\"bcd\""),
+                ("(str.indexof \"abcabc\" \"c\" 3)", "5"),
+                ("(str.indexof \"abc\" \"\" 3)", "3"),
+                ("(str.indexof \"abc\" \"d\" 0)", "(- 1)"),
+                ("(str.replace \"abab\" \"b\" \"x\")", "\"axab\""),
+                ("(str.replace_all \"abab\" \"b\" \"x\")", "\"axax\""),
+                ("(str.replace \"ab\" \"\" \"x\")", "\"xab\""),
+                ("(str.< \"ab\" \"b\")", "true"),
+                ("(str.to_int \"012\")", "12"),
+                ("(str.to_int \"1a\")", "(- 1)"),
+                ("(str.from_int 42)", "\"42\""),
+                ("(str.to_code \"a\")", "97"),
+                ("(str.from_code 98)", "\"b\""),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_bitvectors() {
+        run_tests(
+            "",
+            &[
+                ("(bvadd #xff #x02)", "#x01"),
+                ("(bvneg #x01)", "#xff"),
+                ("(bvudiv #x07 #x00)", "#xff"),
+                ("(bvsdiv #xf9 #x02)", "#xfd"),
+                ("(bvsrem #xf9 #x02)", "#xff"),
+                ("(bvsmod #xf9 #x02)", "#x01"),
+                ("(bvashr #x80 #x01)", "#xc0"),
+                ("(bvshl #x01 #x08)", "#x00"),
+                ("(bvslt #xff #x00)", "true"),
+                ("(concat #b10 #b01)", "#b1001"),
+                ("((_ extract 3 0) #xab)", "#xb"),
+                ("((_ sign_extend 4) #x8)", "#xf8"),
+                ("((_ repeat 2) #b10)", "#b1010"),
+                ("((_ rotate_left 1) #b1001)", "#b0011"),
+                ("((_ rotate_right 1) #b1001)", "#b1100"),
+                ("(bv2nat #xff)", "255"),
+                ("((_ int2bv 4) (- 1))", "#xf"),
+                ("((_ divisible 3) 9)", "true"),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_non_ground_terms() {
+        let mut pool = PrimitivePool::new();
+        let terms = parse_terms(
+            &mut pool,
+            "(declare-fun x () Int) (declare-fun f (Int) Int)",
+            [
+                "(+ x 1)",
+                "(f 1)",
+                "(div 1 0)",
+                "(forall ((y Int)) (= y y))",
+            ],
+        );
+        for t in terms {
+            assert_eq!(evaluate(&mut pool, &t), None, "{}", t);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! Restoring the terms that were flattened by the parser to the form in which they were written.
+
+use super::{BindingList, IndexMap, Proof, ProofArg, ProofCommand, Rc, Term, TermPool};
+
+impl Proof {
+    /// Replaces every term that was flattened by the parser, anywhere in the proof, by the term as
+    /// it was written in the input, and clears [`Proof::flattened`]. This is useful to print a
+    /// proof that was parsed with the `flatten_assoc_ops` option in its original form.
+    ///
+    /// If different terms in the input were flattened to the same term, all of its occurrences are
+    /// restored to the first of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use carcara::{ast::*, parser::*};
+    /// let problem = "(declare-fun p () Bool) (declare-fun q () Bool) (assert (and p (and q p)))";
+    /// let proof = "(assume h1 (and p (and q p)))";
+    /// let config = Config { flatten_assoc_ops: true, ..Config::new() };
+    /// let (_, mut proof, mut pool) = parse_instance(problem.as_bytes(), proof.as_bytes(), config)?;
+    /// assert_eq!(format!("{:#}", proof.premises[0]), "(and p q p)");
+    ///
+    /// proof.restore_flattened(&mut pool);
+    /// assert_eq!(format!("{:#}", proof.premises[0]), "(and p (and q p))");
+    /// # Ok::<(), carcara::Error>(())
+    /// ```
+    pub fn restore_flattened(&mut self, pool: &mut dyn TermPool) {
+        if self.flattened.is_empty() {
+            return;
+        }
+        let mut restorer = Restorer {
+            originals: std::mem::take(&mut self.flattened),
+            cache: IndexMap::new(),
+        };
+        self.premises = self
+            .premises
+            .iter()
+            .map(|t| restorer.restore(pool, t))
+            .collect();
+        restorer.restore_commands(pool, &mut self.commands);
+    }
+}
+
+struct Restorer {
+    originals: IndexMap<Rc<Term>, Rc<Term>>,
+    cache: IndexMap<Rc<Term>, Rc<Term>>,
+}
+
+impl Restorer {
+    fn restore_commands(&mut self, pool: &mut dyn TermPool, commands: &mut [ProofCommand]) {
+        for command in commands {
+            match command {
+                ProofCommand::Assume { term, .. } => *term = self.restore(pool, term),
+                ProofCommand::Step(s) => {
+                    s.clause = self.restore_all(pool, &s.clause);
+                    for arg in &mut s.args {
+                        match arg {
+                            ProofArg::Term(t) | ProofArg::Assign(_, t) => {
+                                *t = self.restore(pool, t);
+                            }
+                            ProofArg::List(ts) => *ts = self.restore_all(pool, ts),
+                        }
+                    }
+                }
+                ProofCommand::Subproof(s) => {
+                    for (_, value) in &mut s.assignment_args {
+                        *value = self.restore(pool, value);
+                    }
+                    self.restore_commands(pool, &mut s.commands);
+                }
+            }
+        }
+    }
+
+    fn restore(&mut self, pool: &mut dyn TermPool, term: &Rc<Term>) -> Rc<Term> {
+        if let Some(t) = self.cache.get(term) {
+            return t.clone();
+        }
+
+        // The arguments of the original term were themselves flattened, so they must also be
+        // restored
+        let current = self.originals.get(term).unwrap_or(term).clone();
+        let result = match current.as_ref() {
+            Term::Const(_) | Term::Var(..) | Term::Sort(_) => current.clone(),
+            Term::App(func, args) => {
+                let func = self.restore(pool, func);
+                let args = self.restore_all(pool, args);
+                pool.add(Term::App(func, args))
+            }
+            Term::Op(op, args) => {
+                let args = self.restore_all(pool, args);
+                pool.add(Term::Op(*op, args))
+            }
+            Term::IndexedOp { op, op_args, args } => {
+                let args = self.restore_all(pool, args);
+                pool.add(Term::IndexedOp {
+                    op: *op,
+                    op_args: op_args.clone(),
+                    args,
+                })
+            }
+            Term::ParamOp { op, op_args, args } => {
+                let args = self.restore_all(pool, args);
+                pool.add(Term::ParamOp {
+                    op: *op,
+                    op_args: op_args.clone(),
+                    args,
+                })
+            }
+            Term::Quant(q, bindings, inner) => {
+                let inner = self.restore(pool, inner);
+                pool.add(Term::Quant(*q, bindings.clone(), inner))
+            }
+            Term::Choice(var, inner) => {
+                let inner = self.restore(pool, inner);
+                pool.add(Term::Choice(var.clone(), inner))
+            }
+            Term::Lambda(bindings, inner) => {
+                let inner = self.restore(pool, inner);
+                pool.add(Term::Lambda(bindings.clone(), inner))
+            }
+            Term::Let(bindings, inner) => {
+                let bindings = bindings
+                    .iter()
+                    .map(|(name, value)| (*name, self.restore(pool, value)))
+                    .collect();
+                let inner = self.restore(pool, inner);
+                pool.add(Term::Let(BindingList(bindings), inner))
+            }
+        };
+        self.cache.insert(term.clone(), result.clone());
+        result
+    }
+
+    fn restore_all(&mut self, pool: &mut dyn TermPool, terms: &[Rc<Term>]) -> Vec<Rc<Term>> {
+        terms.iter().map(|t| self.restore(pool, t)).collect()
+    }
+}
@@ -0,0 +1,101 @@
+//! This module implements `ProofIndex`, a lookup table from command ids to the commands in a proof.
+
+use super::*;
+use indexmap::IndexMap;
+
+/// An index of the commands in a proof, allowing them to be looked up by id.
+///
+/// Internally, commands are referenced in the AST by their depth and position, which depend on the
+/// shape of the proof and change whenever commands are added or removed. This struct lets users
+/// refer to commands by their id instead. For each id, the index stores the path of positions that
+/// leads to the command, starting from the root proof. A subproof is identified by the id of its
+/// last step, so looking up that id yields the `ProofCommand::Subproof` itself.
+///
+/// Since the index refers to positions, it must be rebuilt if the proof is modified, for example
+/// after elaboration.
+///
+/// This struct is created by the [`index`](Proof::index) method on proofs.
+///
+/// # Examples
+///
+/// ```
+/// # use carcara::*;
+/// # fn main() -> CarcaraResult<()> {
+/// let proof = "
+///     (assume h1 (= a b))
+///     (anchor :step t2)
+///     (step t2.t1 (cl (= a a)) :rule refl)
+///     (step t2 (cl (= a a)) :rule subproof)
+///     (step t3 (cl (= b a)) :rule symm :premises (h1))
+/// "
+/// .as_bytes();
+/// let problem = "(declare-fun a () Int) (declare-fun b () Int)".as_bytes();
+/// let (_, proof, _) = parser::parse_instance(problem, proof, parser::Config::new())?;
+/// let index = proof.index();
+/// assert_eq!(index.path("t2.t1"), Some([1, 0].as_slice()));
+/// assert!(matches!(index.get(&proof.commands, "t2"), Some(ast::ProofCommand::Subproof(_))));
+/// assert_eq!(index.get(&proof.commands, "t3").unwrap().id(), "t3");
+/// assert!(index.get(&proof.commands, "t4").is_none());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ProofIndex {
+    paths: IndexMap<StepId, Vec<usize>>,
+}
+
+impl ProofIndex {
+    /// Builds the index for a slice of proof commands.
+    pub fn new(commands: &[ProofCommand]) -> Self {
+        fn build(index: &mut ProofIndex, commands: &[ProofCommand], prefix: &mut Vec<usize>) {
+            for (i, c) in commands.iter().enumerate() {
+                prefix.push(i);
+                if let ProofCommand::Subproof(s) = c {
+                    build(index, &s.commands, prefix);
+                }
+                // If the command is a subproof, this overrides the entry for its last step
+                index.paths.insert(c.id().clone(), prefix.clone());
+                prefix.pop();
+            }
+        }
+
+        let mut index = Self::default();
+        build(&mut index, commands, &mut Vec::new());
+        index
+    }
+
+    /// Returns the path of positions leading to the command with the given id, or `None` if no such
+    /// command exists.
+    pub fn path(&self, id: &str) -> Option<&[usize]> {
+        self.paths.get(id).map(Vec::as_slice)
+    }
+
+    /// Returns `true` if the index contains a command with the given id.
+    pub fn contains(&self, id: &str) -> bool {
+        self.paths.contains_key(id)
+    }
+
+    /// Returns the command with the given id, or `None` if no such command exists. `commands` must
+    /// be the same commands that were used to build the index.
+    pub fn get<'a>(&self, commands: &'a [ProofCommand], id: &str) -> Option<&'a ProofCommand> {
+        let (last, init) = self.path(id)?.split_last()?;
+        let mut commands = commands;
+        for &i in init {
+            match commands.get(i)? {
+                ProofCommand::Subproof(s) => commands = &s.commands,
+                _ => return None,
+            }
+        }
+        commands.get(*last)
+    }
+
+    /// Returns the number of commands in the index.
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// Returns `true` if the index is empty.
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+}
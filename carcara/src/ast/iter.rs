@@ -44,6 +44,12 @@ impl<'a> ProofIter<'a> {
         Self { stack: vec![(0, commands)] }
     }
 
+    /// Constructs a new `ProofIter` that starts at the command with index `start` in the given
+    /// slice. The commands before it can still be referenced as premises.
+    pub(crate) fn starting_at(commands: &'a [ProofCommand], start: usize) -> Self {
+        Self { stack: vec![(start, commands)] }
+    }
+
     /// Returns the current nesting depth of the iterator, or more precisely, the nesting depth of
     /// the last command that was returned. This depth starts at zero, for commands in the root
     /// proof.
@@ -154,6 +154,7 @@ macro_rules! match_term {
     (@GET_VARIANT div)      => { $crate::ast::Operator::IntDiv };
     (@GET_VARIANT /)        => { $crate::ast::Operator::RealDiv };
     (@GET_VARIANT mod)      => { $crate::ast::Operator::Mod };
+    (@GET_VARIANT abs)      => { $crate::ast::Operator::Abs };
     (@GET_VARIANT <)        => { $crate::ast::Operator::LessThan };
     (@GET_VARIANT >)        => { $crate::ast::Operator::GreaterThan };
     (@GET_VARIANT <=)       => { $crate::ast::Operator::LessEq };
@@ -166,6 +167,7 @@ macro_rules! match_term {
     (@GET_VARIANT bit_of)      => { $crate::ast::IndexedOperator::BvBitOf };
     (@GET_VARIANT zero_extend) => { $crate::ast::IndexedOperator::ZeroExtend };
     (@GET_VARIANT sign_extend) => { $crate::ast::IndexedOperator::SignExtend };
+    (@GET_VARIANT repeat)      => { $crate::ast::IndexedOperator::Repeat };
 }
 
 /// A variant of `match_term` that returns a `Result<_, CheckerError>` instead of an `Option`.
@@ -224,7 +226,8 @@ macro_rules! build_term {
 }
 
 /// Implements `FromStr` and `Display` for an enum, given a mapping from each variant to a string
-/// literal.
+/// literal. A variant may also be given additional aliases, separated by `|`, that are accepted by
+/// `FromStr`. `Display` always uses the first string.
 ///
 /// This macros only supports enums that don't hold any data in any of their variants. The error
 /// type for the implementation of `FromStr` will be `()`.
@@ -246,23 +249,25 @@ macro_rules! build_term {
 /// impl_str_conversion_traits!(Foo {
 ///     A: "a",
 ///     B: "b",
-///     C: "c",
+///     C: "c" | "cc",
 /// });
 ///
 /// fn main() {
 ///     assert_eq!(Foo::from_str("a"), Ok(Foo::A));
+///     assert_eq!(Foo::from_str("cc"), Ok(Foo::C));
 ///     assert_eq!(format!("{}", Foo::B), "b");
+///     assert_eq!(format!("{}", Foo::C), "c");
 ///     assert_eq!(Foo::from_str("d"), Err(()));
 /// }
 /// ```
 macro_rules! impl_str_conversion_traits {
-    ($enum_name:ident { $($variant:ident: $str:literal),* $(,)? }) => {
+    ($enum_name:ident { $($variant:ident: $str:literal $(| $alias:literal)*),* $(,)? }) => {
         impl std::str::FromStr for $enum_name {
             type Err = ();
 
             fn from_str(s: &str) -> Result<Self, Self::Err> {
                 match s {
-                    $($str => Ok($enum_name::$variant),)*
+                    $($str $(| $alias)* => Ok($enum_name::$variant),)*
                     _ => Err(()),
                 }
             }
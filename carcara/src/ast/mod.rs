@@ -4,45 +4,420 @@
 
 #[macro_use]
 mod macros;
+mod alpha;
+mod beta;
 mod context;
+mod dependencies;
+mod evaluate;
+mod flatten;
+mod index;
 mod iter;
 mod polyeq;
 pub mod pool;
 pub(crate) mod printer;
 mod rc;
+mod serialize;
+mod step_id;
 mod substitution;
+mod symbol;
 #[cfg(test)]
 mod tests;
 
+pub use alpha::{AlphaEquiv, Binders};
+pub use beta::beta_reduce;
 pub use context::{Context, ContextStack};
+pub use dependencies::ProofDependencies;
+pub use evaluate::{evaluate, Value};
+pub use index::ProofIndex;
 pub use iter::ProofIter;
 pub use polyeq::{alpha_equiv, polyeq, tracing_polyeq};
 pub use pool::{PrimitivePool, TermPool};
-pub use printer::print_proof;
+#[cfg(feature = "exporters")]
+pub use printer::{print_proof, print_proof_for_version, StreamingPrinter};
+pub use printer::{AletheVersion, NegativeNumberStyle, NumberFormat, PrettyPrinter, RationalStyle};
 pub use rc::Rc;
+pub use step_id::StepId;
 pub use substitution::{Substitution, SubstitutionError};
+pub use symbol::Symbol;
 
+pub(crate) use evaluate::eval_op;
 pub(crate) use polyeq::{Polyeq, PolyeqComparator};
 
 use crate::checker::error::CheckerError;
-use indexmap::IndexSet;
+use crate::parser::Span;
+use indexmap::{IndexMap, IndexSet};
 use rug::Integer;
 use rug::Rational;
 use std::{hash::Hash, ops::Deref};
 
 /// The prelude of an SMT-LIB problem instance.
 ///
-/// This stores the sort declarations, function declarations and the problem's logic string.
+/// This stores the sort declarations, datatype declarations, function declarations and the
+/// problem's logic string.
+///
+/// A prelude is usually obtained by parsing a problem, but it can also be built in memory, which
+/// allows checking proofs of problems that are never written as SMT-LIB text. In that case, the
+/// proof can be parsed using [`Parser::declare_prelude`](crate::parser::Parser::declare_prelude).
+///
+/// # Examples
+///
+/// ```
+/// # use carcara::ast::*;
+/// let mut pool = PrimitivePool::new();
+/// let int_sort = pool.add(Term::Sort(Sort::Int));
+/// let s_sort = pool.add(Term::Sort(Sort::Atom("S".into(), Vec::new())));
+/// let f_sort = pool.add(Term::Sort(Sort::Function(vec![s_sort, int_sort.clone()])));
+///
+/// let mut prelude = ProblemPrelude::new();
+/// prelude
+///     .set_logic("UFLIA")
+///     .declare_sort("S", 0)
+///     .declare_fun("f", f_sort)
+///     .declare_fun("x", int_sort);
+/// assert_eq!(prelude.logic(), Some("UFLIA"));
+/// assert_eq!(prelude.function_arity("f"), Some(1));
+/// let printed = prelude.to_string();
+/// assert!(printed.starts_with("(set-logic UFLIA)\n(declare-sort S 0)\n"));
+/// assert!(printed.ends_with("(declare-fun f (S) Int)\n(declare-fun x () Int)\n"));
+/// ```
 #[derive(Debug, Clone, Default)]
 pub struct ProblemPrelude {
     /// The sort declarations, each represented by its name and arity.
     pub(crate) sort_declarations: Vec<(String, usize)>,
 
+    /// The sort definitions, from `define-sort` commands. These are expanded during parsing, and
+    /// are only kept so they can be printed.
+    pub(crate) sort_definitions: Vec<SortDefinition>,
+
+    /// The datatype declarations. Each element corresponds to one `declare-datatypes` or
+    /// `declare-datatype` command, since datatypes declared in the same command may be mutually
+    /// recursive.
+    pub(crate) datatype_declarations: Vec<Vec<DatatypeDef>>,
+
     /// The function declarations, each represented by its name and body.
     pub(crate) function_declarations: Vec<(String, Rc<Term>)>,
 
     /// The problem's logic string, if it exists.
     pub(crate) logic: Option<String>,
+
+    /// The terms given a name using the `:named` attribute in the problem, indexed by name.
+    pub(crate) named_terms: IndexMap<String, Rc<Term>>,
+}
+
+impl ProblemPrelude {
+    /// Constructs an empty prelude, with no declarations and no logic.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the problem's logic string, as if by a `set-logic` command.
+    pub fn set_logic(&mut self, logic: impl Into<String>) -> &mut Self {
+        self.logic = Some(logic.into());
+        self
+    }
+
+    /// Declares a sort with the given name and arity, as if by a `declare-sort` command.
+    pub fn declare_sort(&mut self, name: impl Into<String>, arity: usize) -> &mut Self {
+        self.sort_declarations.push((name.into(), arity));
+        self
+    }
+
+    /// Declares a function or constant with the given name and sort, as if by a `declare-fun`
+    /// command. For functions that take arguments, the sort must be a function sort, whose last
+    /// element is the return sort. If a function with the same name was already declared, its
+    /// sort is replaced.
+    pub fn declare_fun(&mut self, name: impl Into<String>, sort: Rc<Term>) -> &mut Self {
+        let name = name.into();
+        match self
+            .function_declarations
+            .iter_mut()
+            .find(|(n, _)| *n == name)
+        {
+            Some((_, s)) => *s = sort,
+            None => self.function_declarations.push((name, sort)),
+        }
+        self
+    }
+
+    /// Returns the sort declarations, in the order they were declared. Each declaration consists
+    /// of the sort name and its arity.
+    pub fn sort_declarations(&self) -> &[(String, usize)] {
+        &self.sort_declarations
+    }
+
+    /// Returns the sort definitions introduced by `define-sort` commands, in the order they were
+    /// defined. Sort aliases are always expanded when parsing, so these do not appear in terms.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use carcara::{ast::*, parser::{Config, Parser}};
+    /// let problem = "(define-sort Word () (_ BitVec 8)) (declare-fun w () Word)";
+    /// let mut pool = PrimitivePool::new();
+    /// let mut parser = Parser::new(&mut pool, Config::new(), problem.as_bytes())?;
+    /// let (prelude, _) = parser.parse_problem()?;
+    /// assert_eq!(prelude.sort_definitions()[0].name, "Word");
+    /// assert_eq!(prelude.function_sort("w").unwrap().to_string(), "(_ BitVec 8)");
+    /// # Ok::<(), carcara::Error>(())
+    /// ```
+    pub fn sort_definitions(&self) -> &[SortDefinition] {
+        &self.sort_definitions
+    }
+
+    /// Returns the datatype declarations, grouped by the command that declared them.
+    pub fn datatype_declarations(&self) -> &[Vec<DatatypeDef>] {
+        &self.datatype_declarations
+    }
+
+    /// Returns the function and constant declarations, in the order they were declared. Each
+    /// declaration consists of the function name and its sort. For functions that take arguments,
+    /// the sort is a function sort, whose last element is the return sort.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use carcara::{ast::*, parser::{Config, Parser}};
+    /// let problem = "(declare-sort S 1) (declare-fun f ((S Int) Bool) Int)
+    ///     (declare-const c Real)";
+    /// let mut pool = PrimitivePool::new();
+    /// let mut parser = Parser::new(&mut pool, Config::new(), problem.as_bytes())?;
+    /// let (prelude, _) = parser.parse_problem()?;
+    /// assert_eq!(prelude.sort_declarations(), [("S".to_owned(), 1)]);
+    ///
+    /// let names: Vec<_> = prelude.function_declarations().iter().map(|(n, _)| n).collect();
+    /// assert_eq!(names, ["f", "c"]);
+    /// assert_eq!(prelude.function_sort("c").unwrap().to_string(), "Real");
+    /// assert_eq!(prelude.function_arity("f"), Some(2));
+    /// assert_eq!(prelude.function_arity("c"), Some(0));
+    /// assert_eq!(prelude.function_arity("g"), None);
+    /// # Ok::<(), carcara::Error>(())
+    /// ```
+    pub fn function_declarations(&self) -> &[(String, Rc<Term>)] {
+        &self.function_declarations
+    }
+
+    /// Returns the sort of the function or constant with the given name, if it was declared.
+    pub fn function_sort(&self, name: &str) -> Option<&Rc<Term>> {
+        self.function_declarations
+            .iter()
+            .find_map(|(n, sort)| (n == name).then_some(sort))
+    }
+
+    /// Returns the number of arguments of the function or constant with the given name, if it was
+    /// declared. Constants have arity zero.
+    pub fn function_arity(&self, name: &str) -> Option<usize> {
+        self.function_sort(name).map(|sort| match sort.as_sort() {
+            Some(Sort::Function(sorts)) => sorts.len() - 1,
+            _ => 0,
+        })
+    }
+
+    /// Returns the terms that were given a name in the problem using the `:named` attribute,
+    /// indexed by name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use carcara::{ast::*, parser::{Config, Parser}};
+    /// let problem = "(declare-fun a () Bool) (assert (! (not a) :named foo))";
+    /// let mut pool = PrimitivePool::new();
+    /// let mut parser = Parser::new(&mut pool, Config::new(), problem.as_bytes())?;
+    /// let (prelude, _) = parser.parse_problem()?;
+    /// assert_eq!(prelude.named_terms()["foo"].to_string(), "(not a)");
+    /// # Ok::<(), carcara::Error>(())
+    /// ```
+    pub fn named_terms(&self) -> &IndexMap<String, Rc<Term>> {
+        &self.named_terms
+    }
+
+    /// Returns the problem's logic string, if it has a `set-logic` command.
+    pub fn logic(&self) -> Option<&str> {
+        self.logic.as_deref()
+    }
+
+    /// Returns a copy of this prelude that only contains the sort and function declarations that
+    /// are referenced by the given terms. Sort declarations that are only referenced by the sort of
+    /// a used function are also kept. Datatype declarations are always kept, together with any
+    /// sort declarations referenced by them.
+    pub fn restricted_to(&self, terms: &[Rc<Term>]) -> Self {
+        let mut names: IndexSet<&str> = IndexSet::new();
+        let mut visited: IndexSet<&Rc<Term>> = IndexSet::new();
+        let mut stack: Vec<&Rc<Term>> = terms.iter().collect();
+        let selector_sorts = self
+            .datatype_declarations
+            .iter()
+            .flatten()
+            .flat_map(|d| &d.constructors)
+            .flat_map(|c| c.selectors.iter().map(|(_, sort)| sort));
+        stack.extend(selector_sorts);
+        stack.extend(self.sort_definitions.iter().map(|d| &d.body));
+        while let Some(term) = stack.pop() {
+            if !visited.insert(term) {
+                continue;
+            }
+            match term.as_ref() {
+                Term::Const(_) => (),
+                Term::Var(name, sort) => {
+                    names.insert(name.as_str());
+                    stack.push(sort);
+                }
+                Term::App(func, args) => {
+                    stack.push(func);
+                    stack.extend(args);
+                }
+                Term::Op(_, args) | Term::IndexedOp { args, .. } => stack.extend(args),
+                Term::ParamOp { op_args, args, .. } => {
+                    stack.extend(op_args);
+                    stack.extend(args);
+                }
+                Term::Sort(sort) => match sort {
+                    Sort::Atom(name, args) => {
+                        names.insert(name.as_str());
+                        stack.extend(args);
+                    }
+                    Sort::Datatype(_, args) | Sort::Function(args) => stack.extend(args),
+                    Sort::Array(x, y) => stack.extend([x, y]),
+                    _ => (),
+                },
+                Term::Quant(_, bindings, inner)
+                | Term::Let(bindings, inner)
+                | Term::Lambda(bindings, inner) => {
+                    stack.extend(bindings.iter().map(|(_, t)| t));
+                    stack.push(inner);
+                }
+                Term::Choice((_, sort), inner) => {
+                    stack.push(sort);
+                    stack.push(inner);
+                }
+            }
+        }
+
+        Self {
+            sort_declarations: self
+                .sort_declarations
+                .iter()
+                .filter(|(name, _)| names.contains(name.as_str()))
+                .cloned()
+                .collect(),
+            sort_definitions: self.sort_definitions.clone(),
+            function_declarations: self
+                .function_declarations
+                .iter()
+                .filter(|(name, _)| names.contains(name.as_str()))
+                .cloned()
+                .collect(),
+            datatype_declarations: self.datatype_declarations.clone(),
+            logic: self.logic.clone(),
+            named_terms: self.named_terms.clone(),
+        }
+    }
+}
+
+/// A sort alias, from a `define-sort` command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortDefinition {
+    /// The name of the alias.
+    pub name: String,
+
+    /// The sort parameters of the alias. In the body, each parameter is represented as a
+    /// user-declared sort of arity zero.
+    pub params: Vec<String>,
+
+    /// The sort the alias expands to.
+    pub body: Rc<Term>,
+}
+
+impl SortDefinition {
+    /// If the given sort is an instance of this alias, returns the sorts that its parameters take,
+    /// in order. Aliases whose body is a single parameter, or that don't use all of their
+    /// parameters, never match, since they can't be recovered from their expansion.
+    pub fn match_sort(&self, sort: &Rc<Term>) -> Option<Vec<Rc<Term>>> {
+        if matches!(self.body.as_sort(), Some(Sort::Atom(name, _)) if self.params.contains(name)) {
+            return None;
+        }
+        let mut substitution = IndexMap::new();
+        if !match_sort_params(&self.body, sort, &self.params, &mut substitution) {
+            return None;
+        }
+        self.params
+            .iter()
+            .map(|p| substitution.get(p).cloned())
+            .collect()
+    }
+}
+
+/// Matches a sort that may contain sort parameters against a concrete sort, extending the
+/// substitution with the values of the parameters. Returns `false` if the sorts don't match, or if
+/// a parameter would need to take two different values.
+pub(crate) fn match_sort_params(
+    pattern: &Rc<Term>,
+    sort: &Rc<Term>,
+    params: &[String],
+    substitution: &mut IndexMap<String, Rc<Term>>,
+) -> bool {
+    let (pattern_args, sort_args) = match (pattern.as_sort().unwrap(), sort.as_sort().unwrap()) {
+        (Sort::Atom(name, args), _) if args.is_empty() && params.contains(name) => {
+            return match substitution.get(name) {
+                Some(value) => value == sort,
+                None => {
+                    substitution.insert(name.clone(), sort.clone());
+                    true
+                }
+            };
+        }
+        (Sort::Function(a), Sort::Function(b)) => (a.clone(), b.clone()),
+        (Sort::Atom(a_name, a), Sort::Atom(b_name, b))
+        | (Sort::Datatype(a_name, a), Sort::Datatype(b_name, b))
+            if a_name == b_name =>
+        {
+            (a.clone(), b.clone())
+        }
+        (Sort::Array(a_x, a_y), Sort::Array(b_x, b_y)) => (
+            vec![a_x.clone(), a_y.clone()],
+            vec![b_x.clone(), b_y.clone()],
+        ),
+        (a, b) => return a == b,
+    };
+    pattern_args.len() == sort_args.len()
+        && (pattern_args.iter())
+            .zip(&sort_args)
+            .all(|(a, b)| match_sort_params(a, b, params, substitution))
+}
+
+/// An algebraic datatype, from a `declare-datatypes` or `declare-datatype` command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatatypeDef {
+    /// The datatype name.
+    pub name: String,
+
+    /// The sort parameters of the datatype, if it is parametric. In the selector sorts, each sort
+    /// parameter is represented as a user-declared sort of arity zero.
+    pub sort_params: Vec<String>,
+
+    /// The datatype constructors, in the order they were declared.
+    pub constructors: Vec<DatatypeConstructor>,
+}
+
+/// A constructor of an algebraic datatype.
+///
+/// For each constructor `C`, a tester function, that checks whether a term was built using `C`,
+/// is also declared. It can be used as `((_ is C) t)`, and is represented by a function named
+/// `is-C`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatatypeConstructor {
+    /// The constructor name.
+    pub name: String,
+
+    /// The constructor selectors, each represented by its name and the sort of the field it
+    /// selects.
+    pub selectors: Vec<SortedVar>,
+}
+
+impl DatatypeConstructor {
+    /// Returns the name of the tester function of this constructor.
+    pub fn tester_name(&self) -> String {
+        format!("is-{}", self.name)
+    }
 }
 
 /// A proof in the Alethe format.
@@ -50,25 +425,97 @@ pub struct ProblemPrelude {
 pub struct Proof {
     /// The proof's premises.
     ///
-    /// Those are the terms introduced in the original problem's `assert` commands.
+    /// Those are the terms introduced in the original problem's `assert` commands, as well as the
+    /// assumption literals of its last `check-sat-assuming` command.
     pub premises: IndexSet<Rc<Term>>,
 
     /// The proof commands.
     pub commands: Vec<ProofCommand>,
+
+    /// The attributes of `step` commands that are not interpreted by Carcara, indexed by step id.
+    /// Steps with no such attributes are not included.
+    pub attributes: IndexMap<String, Vec<Attribute>>,
+
+    /// The attributes of annotated terms, of the form `(! <term> <attribute>+)`, indexed by the
+    /// term that is annotated. This includes all attributes, like `:pattern` and `:qid`, except
+    /// for `:named`, which is recorded in [`ProblemPrelude::named_terms`] instead. Terms with no
+    /// such attributes are not included.
+    pub term_attributes: IndexMap<Rc<Term>, Vec<Attribute>>,
+
+    /// The span in the source input of each `assume` and `step` command, indexed by id. This is
+    /// empty if the proof was not parsed from a text input.
+    pub spans: IndexMap<String, Span>,
+
+    /// The terms that were flattened by the parser, mapped to the terms as they were written in
+    /// the input. This is empty unless the proof was parsed with the `flatten_assoc_ops` option.
+    /// See [`Proof::restore_flattened`].
+    pub flattened: IndexMap<Rc<Term>, Rc<Term>>,
+}
+
+/// An attribute of a `step` command or of an annotated term that is not interpreted by Carcara,
+/// such as `:origin "arith"` or `:pattern ((f x))`. These are kept so that tools can carry
+/// metadata, like the solver module that produced a lemma or the triggers of a quantifier, through
+/// checking and elaboration unchanged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attribute {
+    /// The attribute keyword, without the leading `:`.
+    pub keyword: String,
+
+    /// The attribute value, as written in the input, if it has one.
+    pub value: Option<String>,
 }
 
 impl Proof {
+    /// Returns the annotations of the step with the given id, that is, the attributes
+    /// of the step that are not interpreted by Carcara, written as in the input. Proof producers
+    /// may use these to record where each step came from, like the id of the proof node that
+    /// produced the step inside the solver. Returns `None` if the step has no such attributes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use carcara::{ast::*, parser::*};
+    /// let problem = "(declare-fun p () Bool) (assert p)";
+    /// let proof = "(assume h1 p) (step t2 (cl p) :rule hole :node 17 :origin (pre p))";
+    /// let (_, proof, _) = parse_instance(problem.as_bytes(), proof.as_bytes(), Config::new())?;
+    /// assert_eq!(proof.annotations("t2").as_deref(), Some(":node 17 :origin (pre p)"));
+    /// assert_eq!(proof.annotations("h1"), None);
+    /// # Ok::<(), carcara::Error>(())
+    /// ```
+    pub fn annotations(&self, step: &str) -> Option<String> {
+        let attributes = self.attributes.get(step)?;
+        let parts: Vec<_> = attributes
+            .iter()
+            .map(|a| match &a.value {
+                Some(value) => format!(":{} {}", a.keyword, value),
+                None => format!(":{}", a.keyword),
+            })
+            .collect();
+        Some(parts.join(" "))
+    }
+
     /// Returns an iterator over the proof commands. See [`ProofIter`].
-    pub fn iter(&self) -> ProofIter {
+    pub fn iter(&self) -> ProofIter<'_> {
         ProofIter::new(&self.commands)
     }
+
+    /// Builds an index that allows looking up the proof commands by id. See [`ProofIndex`].
+    pub fn index(&self) -> ProofIndex {
+        ProofIndex::new(&self.commands)
+    }
+
+    /// Returns a map from each command to the root assumptions it transitively depends on. See
+    /// [`ProofDependencies`].
+    pub fn dependencies(&self) -> ProofDependencies<'_> {
+        ProofDependencies::new(&self.commands)
+    }
 }
 
 /// A proof command.
 #[derive(Debug, Clone, PartialEq)]
 pub enum ProofCommand {
     /// An `assume` command.
-    Assume { id: String, term: Rc<Term> },
+    Assume { id: StepId, term: Rc<Term> },
 
     /// A `step` command.
     Step(ProofStep),
@@ -81,7 +528,7 @@ impl ProofCommand {
     /// Returns the unique id of this command.
     ///
     /// For subproofs, this is the id of the last step in the subproof.
-    pub fn id(&self) -> &str {
+    pub fn id(&self) -> &StepId {
         match self {
             ProofCommand::Assume { id, .. } => id,
             ProofCommand::Step(s) => &s.id,
@@ -122,13 +569,13 @@ impl ProofCommand {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ProofStep {
     /// The step id.
-    pub id: String,
+    pub id: StepId,
 
     /// The conclusion clause.
     pub clause: Vec<Rc<Term>>,
 
     /// The rule used by the step.
-    pub rule: String,
+    pub rule: Symbol,
 
     /// The premises of the step, given via the `:premises` attribute.
     ///
@@ -145,6 +592,35 @@ pub struct ProofStep {
     pub discharge: Vec<(usize, usize)>,
 }
 
+impl ProofStep {
+    /// If this is a `rare_rewrite` step, returns the RARE rule it uses and the arguments that
+    /// instantiate that rule.
+    pub fn as_rare_rewrite(&self) -> Option<RareRewrite<'_>> {
+        if self.rule != "rare_rewrite" {
+            return None;
+        }
+        let (name, args) = self.args.split_first()?;
+        match name.as_term().ok()?.as_ref() {
+            Term::Const(Constant::String(rule)) => Some(RareRewrite { rule, args }),
+            _ => None,
+        }
+    }
+}
+
+/// The arguments of a `rare_rewrite` step, as emitted by cvc5.
+///
+/// The first argument of these steps is a string with the name of the RARE rewrite rule that is
+/// applied, and the remaining arguments instantiate the variables of that rule, in order. Variables
+/// that stand for a list of terms are instantiated with a [`ProofArg::List`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RareRewrite<'a> {
+    /// The name of the RARE rule.
+    pub rule: &'a str,
+
+    /// The arguments that instantiate the variables of the rule.
+    pub args: &'a [ProofArg],
+}
+
 /// A subproof.
 ///
 /// Subproofs are started by `anchor` commands, and contain a series of steps, possibly including
@@ -156,7 +632,7 @@ pub struct Subproof {
     pub commands: Vec<ProofCommand>,
 
     /// The "assignment" style arguments of the subproof, of the form `(:= <symbol> <term>)`.
-    pub assignment_args: Vec<(String, Rc<Term>)>,
+    pub assignment_args: Vec<(Symbol, Rc<Term>)>,
 
     /// The "variable" style arguments of the subproof, of the form `(<symbol> <sort>)`.
     pub variable_args: Vec<SortedVar>,
@@ -172,7 +648,12 @@ pub enum ProofArg {
     Term(Rc<Term>),
 
     /// An argument of the form `(:= <symbol> <term>)`.
-    Assign(String, Rc<Term>),
+    Assign(Symbol, Rc<Term>),
+
+    /// A list of terms, of the form `(@list <term>*)`. These are used by the arguments of
+    /// `rare_rewrite` steps, to instantiate variables of the RARE rule that stand for a list of
+    /// terms.
+    List(Vec<Rc<Term>>),
 }
 
 impl ProofArg {
@@ -181,16 +662,27 @@ impl ProofArg {
     pub fn as_term(&self) -> Result<&Rc<Term>, CheckerError> {
         match self {
             ProofArg::Term(t) => Ok(t),
-            ProofArg::Assign(s, t) => Err(CheckerError::ExpectedTermStyleArg(s.clone(), t.clone())),
+            ProofArg::Assign(s, t) => Err(CheckerError::ExpectedTermStyleArg(*s, t.clone())),
+            ProofArg::List(_) => Err(CheckerError::UnexpectedListArg),
         }
     }
 
     /// If this argument is an "assign style" argument, extracts the variable name and the value
     /// term from it. Otherwise, returns an error.
-    pub fn as_assign(&self) -> Result<(&String, &Rc<Term>), CheckerError> {
+    pub fn as_assign(&self) -> Result<(&Symbol, &Rc<Term>), CheckerError> {
         match self {
             ProofArg::Assign(s, t) => Ok((s, t)),
             ProofArg::Term(t) => Err(CheckerError::ExpectedAssignStyleArg(t.clone())),
+            ProofArg::List(_) => Err(CheckerError::UnexpectedListArg),
+        }
+    }
+
+    /// Returns the terms in this argument. For "term style" and "assign style" arguments, this is
+    /// the single term in them.
+    pub fn terms(&self) -> &[Rc<Term>] {
+        match self {
+            ProofArg::Term(t) | ProofArg::Assign(_, t) => std::slice::from_ref(t),
+            ProofArg::List(ts) => ts,
         }
     }
 }
@@ -409,6 +901,104 @@ pub enum Operator {
     BvSGt,
     BvSGe,
     BvBbTerm,
+
+    /// The `bv2nat` operator, that converts a bitvector to the non-negative integer it represents.
+    BvToNat,
+
+    // Floating-point
+    /// The `fp` operator, that builds a floating-point literal from its sign, exponent and
+    /// significand bitvectors.
+    Fp,
+
+    /// The `RNE` rounding mode, also written as `roundNearestTiesToEven`.
+    RoundNearestTiesToEven,
+
+    /// The `RNA` rounding mode, also written as `roundNearestTiesToAway`.
+    RoundNearestTiesToAway,
+
+    /// The `RTP` rounding mode, also written as `roundTowardPositive`.
+    RoundTowardPositive,
+
+    /// The `RTN` rounding mode, also written as `roundTowardNegative`.
+    RoundTowardNegative,
+
+    /// The `RTZ` rounding mode, also written as `roundTowardZero`.
+    RoundTowardZero,
+
+    /// The `fp.abs` operator.
+    FpAbs,
+
+    /// The `fp.neg` operator.
+    FpNeg,
+
+    /// The `fp.add` operator.
+    FpAdd,
+
+    /// The `fp.sub` operator.
+    FpSub,
+
+    /// The `fp.mul` operator.
+    FpMul,
+
+    /// The `fp.div` operator.
+    FpDiv,
+
+    /// The `fp.fma` operator.
+    FpFma,
+
+    /// The `fp.sqrt` operator.
+    FpSqrt,
+
+    /// The `fp.rem` operator.
+    FpRem,
+
+    /// The `fp.roundToIntegral` operator.
+    FpRoundToIntegral,
+
+    /// The `fp.min` operator.
+    FpMin,
+
+    /// The `fp.max` operator.
+    FpMax,
+
+    /// The `fp.leq` operator.
+    FpLessEq,
+
+    /// The `fp.lt` operator.
+    FpLessThan,
+
+    /// The `fp.geq` operator.
+    FpGreaterEq,
+
+    /// The `fp.gt` operator.
+    FpGreaterThan,
+
+    /// The `fp.eq` operator.
+    FpEquals,
+
+    /// The `fp.isNormal` operator.
+    FpIsNormal,
+
+    /// The `fp.isSubnormal` operator.
+    FpIsSubnormal,
+
+    /// The `fp.isZero` operator.
+    FpIsZero,
+
+    /// The `fp.isInfinite` operator.
+    FpIsInfinite,
+
+    /// The `fp.isNaN` operator.
+    FpIsNaN,
+
+    /// The `fp.isNegative` operator.
+    FpIsNegative,
+
+    /// The `fp.isPositive` operator.
+    FpIsPositive,
+
+    /// The `fp.to_real` operator.
+    FpToReal,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -417,15 +1007,92 @@ pub enum IndexedOperator {
     BvBitOf,
     ZeroExtend,
     SignExtend,
+    Repeat,
+    RotateLeft,
+    RotateRight,
     BvConst,
+
+    /// The `int2bv` operator, that converts an integer to a bitvector of the given width.
+    IntToBv,
+
+    /// The `divisible` operator, from the SMT-LIB `Ints` theory.
+    Divisible,
+
+    /// The `char` indexed constant, that represents a string with a single character, given by
+    /// its code point.
+    StrChar,
+
+    /// The `re.loop` operator.
+    ReLoop,
+
+    /// The `re.^` operator.
+    RePower,
+
+    /// The `+oo` floating-point constant, indexed by the exponent and significand widths.
+    FpPlusInfinity,
+
+    /// The `-oo` floating-point constant, indexed by the exponent and significand widths.
+    FpMinusInfinity,
+
+    /// The `+zero` floating-point constant, indexed by the exponent and significand widths.
+    FpPlusZero,
+
+    /// The `-zero` floating-point constant, indexed by the exponent and significand widths.
+    FpMinusZero,
+
+    /// The `NaN` floating-point constant, indexed by the exponent and significand widths.
+    FpNaN,
+
+    /// The `to_fp` operator, that converts a bitvector, a real or another floating-point number to
+    /// a floating-point number with the given exponent and significand widths.
+    ToFp,
+
+    /// The `to_fp_unsigned` operator, that converts an unsigned bitvector to a floating-point
+    /// number with the given exponent and significand widths.
+    ToFpUnsigned,
+
+    /// The `fp.to_ubv` operator.
+    FpToUbv,
+
+    /// The `fp.to_sbv` operator.
+    FpToSbv,
+}
+
+/// An operator that takes terms, such as sorts, as parameters, in addition to its arguments. These
+/// come from qualified identifiers, of the form `(as <identifier> <sort>)`, whose meaning depends
+/// on the sort ascription.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParamOperator {
+    /// The constant array operator, written `(as const (Array I E))`. Its only parameter is the
+    /// array sort, and its only argument is the value of every element in the array.
+    ArrayConst,
 }
 
+impl_str_conversion_traits!(ParamOperator { ArrayConst: "const" });
+
 impl_str_conversion_traits!(IndexedOperator {
     BvExtract: "extract",
     BvBitOf: "bit_of",
     ZeroExtend: "zero_extend",
     SignExtend: "sign_extend",
+    Repeat: "repeat",
+    RotateLeft: "rotate_left",
+    RotateRight: "rotate_right",
     BvConst: "bv",
+    IntToBv: "int2bv",
+    Divisible: "divisible",
+    StrChar: "char",
+    ReLoop: "re.loop",
+    RePower: "re.^",
+    FpPlusInfinity: "+oo",
+    FpMinusInfinity: "-oo",
+    FpPlusZero: "+zero",
+    FpMinusZero: "-zero",
+    FpNaN: "NaN",
+    ToFp: "to_fp",
+    ToFpUnsigned: "to_fp_unsigned",
+    FpToUbv: "fp.to_ubv",
+    FpToSbv: "fp.to_sbv",
 });
 
 impl_str_conversion_traits!(Operator {
@@ -522,10 +1189,66 @@ impl_str_conversion_traits!(Operator {
     BvSGt: "bvsgt",
     BvSGe: "bvsge",
     BvBbTerm: "bbterm",
+    BvToNat: "bv2nat",
+
+    Fp: "fp",
+    RoundNearestTiesToEven: "RNE" | "roundNearestTiesToEven",
+    RoundNearestTiesToAway: "RNA" | "roundNearestTiesToAway",
+    RoundTowardPositive: "RTP" | "roundTowardPositive",
+    RoundTowardNegative: "RTN" | "roundTowardNegative",
+    RoundTowardZero: "RTZ" | "roundTowardZero",
+    FpAbs: "fp.abs",
+    FpNeg: "fp.neg",
+    FpAdd: "fp.add",
+    FpSub: "fp.sub",
+    FpMul: "fp.mul",
+    FpDiv: "fp.div",
+    FpFma: "fp.fma",
+    FpSqrt: "fp.sqrt",
+    FpRem: "fp.rem",
+    FpRoundToIntegral: "fp.roundToIntegral",
+    FpMin: "fp.min",
+    FpMax: "fp.max",
+    FpLessEq: "fp.leq",
+    FpLessThan: "fp.lt",
+    FpGreaterEq: "fp.geq",
+    FpGreaterThan: "fp.gt",
+    FpEquals: "fp.eq",
+    FpIsNormal: "fp.isNormal",
+    FpIsSubnormal: "fp.isSubnormal",
+    FpIsZero: "fp.isZero",
+    FpIsInfinite: "fp.isInfinite",
+    FpIsNaN: "fp.isNaN",
+    FpIsNegative: "fp.isNegative",
+    FpIsPositive: "fp.isPositive",
+    FpToReal: "fp.to_real",
 });
 
+impl Operator {
+    /// Returns `true` if the operator is associative, that is, if nested applications of it, like
+    /// `(and a (and b c))`, are equivalent to a single application to all of their arguments, like
+    /// `(and a b c)`.
+    pub fn is_associative(self) -> bool {
+        matches!(
+            self,
+            Operator::And
+                | Operator::Or
+                | Operator::Xor
+                | Operator::Add
+                | Operator::Mult
+                | Operator::StrConcat
+                | Operator::ReConcat
+                | Operator::BvAnd
+                | Operator::BvOr
+                | Operator::BvXor
+                | Operator::BvAdd
+                | Operator::BvMul
+        )
+    }
+}
+
 /// A variable and an associated sort.
-pub type SortedVar = (String, Rc<Term>);
+pub type SortedVar = (Symbol, Rc<Term>);
 
 /// The sort of a term.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -557,6 +1280,12 @@ pub enum Sort {
     /// The `RegLan` primitive sort.
     RegLan,
 
+    /// An algebraic datatype sort, from a `declare-datatypes` or `declare-datatype` command.
+    ///
+    /// The associated string is the datatype name. For parametric datatypes, the associated terms
+    /// are the sort arguments.
+    Datatype(String, Vec<Rc<Term>>),
+
     /// An `Array` sort.
     ///
     /// The two associated terms are the sort arguments for this sort.
@@ -565,6 +1294,15 @@ pub enum Sort {
     ///
     /// The associated term is the BV width of this sort.
     BitVec(Integer),
+
+    /// A `FloatingPoint` sort.
+    ///
+    /// The associated integers are the exponent and significand widths of this sort, in that
+    /// order. The significand width includes the hidden bit.
+    FloatingPoint(Integer, Integer),
+
+    /// The `RoundingMode` sort, from the floating-point theory.
+    RoundingMode,
 }
 
 /// A quantifier, either `forall` or `exists`.
@@ -633,7 +1371,7 @@ pub enum Term {
     Const(Constant),
 
     /// A variable, consisting of an identifier and a sort.
-    Var(String, Rc<Term>),
+    Var(Symbol, Rc<Term>),
 
     /// An application of a function to one or more terms.
     App(Rc<Term>, Vec<Rc<Term>>),
@@ -662,6 +1400,14 @@ pub enum Term {
         op_args: Vec<Constant>,
         args: Vec<Rc<Term>>,
     },
+
+    /// An application of an operator that takes terms as parameters, like the constant array
+    /// `((as const (Array Int Int)) 0)`.
+    ParamOp {
+        op: ParamOperator,
+        op_args: Vec<Rc<Term>>,
+        args: Vec<Rc<Term>>,
+    },
 }
 
 impl From<SortedVar> for Term {
@@ -692,7 +1438,7 @@ impl Term {
     }
 
     /// Constructs a new variable term.
-    pub fn new_var(name: impl Into<String>, sort: Rc<Term>) -> Self {
+    pub fn new_var(name: impl Into<Symbol>, sort: Rc<Term>) -> Self {
         Term::Var(name.into(), sort)
     }
 
@@ -709,6 +1455,25 @@ impl Term {
         matches!(self, Term::Const(_) | Term::Var(..))
     }
 
+    /// Returns the direct subterms of this term, not including sorts.
+    pub fn children(&self) -> Vec<&Rc<Term>> {
+        match self {
+            Term::Const(_) | Term::Var(..) | Term::Sort(_) => Vec::new(),
+            Term::App(func, args) => std::iter::once(func).chain(args).collect(),
+            Term::Op(_, args) | Term::IndexedOp { args, .. } | Term::ParamOp { args, .. } => {
+                args.iter().collect()
+            }
+            Term::Let(bindings, inner) => bindings
+                .iter()
+                .map(|(_, value)| value)
+                .chain(std::iter::once(inner))
+                .collect(),
+            Term::Quant(_, _, inner) | Term::Choice(_, inner) | Term::Lambda(_, inner) => {
+                vec![inner]
+            }
+        }
+    }
+
     /// Returns `true` if the term is an integer or real constant.
     pub fn is_number(&self) -> bool {
         matches!(self, Term::Const(Constant::Real(_) | Constant::Integer(_)))
@@ -771,9 +1536,9 @@ impl Term {
     }
 
     /// Tries to extract the variable name from a term. Returns `Some` if the term is a variable.
-    pub fn as_var(&self) -> Option<&str> {
+    pub fn as_var(&self) -> Option<Symbol> {
         match self {
-            Term::Var(var, _) => Some(var.as_str()),
+            Term::Var(var, _) => Some(*var),
             _ => None,
         }
     }
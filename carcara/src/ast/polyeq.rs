@@ -7,7 +7,10 @@
 //! - `alpha_equiv` compares terms by alpha-equivalence, meaning it implements equality of terms
 //! modulo renaming of bound variables.
 
-use super::{BindingList, Operator, ProofArg, ProofCommand, ProofStep, Rc, Sort, Subproof, Term};
+use super::{
+    alpha::Binders, BindingList, Operator, ProofArg, ProofCommand, ProofStep, Rc, Sort, Subproof,
+    Symbol, Term,
+};
 use crate::utils::HashMapStack;
 use std::time::{Duration, Instant};
 
@@ -94,6 +97,9 @@ pub struct PolyeqComparator {
     is_mod_reordering: bool,
     de_bruijn_map: Option<DeBruijnMap>,
 
+    // The kinds of binders whose variables may be renamed, when checking for alpha-equivalence
+    binders: Binders,
+
     current_depth: usize,
     max_depth: usize,
 }
@@ -113,26 +119,43 @@ impl PolyeqComparator {
             } else {
                 None
             },
+            binders: Binders::ALL,
             current_depth: 0,
             max_depth: 0,
         }
     }
 
+    /// Constructs a new `PolyeqComparator` that checks for alpha equivalence, but only allows
+    /// renaming the variables of the given kinds of binders.
+    pub fn with_binders(is_mod_reordering: bool, binders: Binders) -> Self {
+        Self {
+            binders,
+            ..Self::new(is_mod_reordering, true)
+        }
+    }
+
+    /// Compares two binder terms. If `renamed` is `false`, the bound variables must have the same
+    /// names, even when checking for alpha equivalence.
     fn compare_binder(
         &mut self,
         a_binds: &BindingList,
         b_binds: &BindingList,
         a_inner: &Rc<Term>,
         b_inner: &Rc<Term>,
+        renamed: bool,
     ) -> bool {
         if let Some(de_bruijn_map) = self.de_bruijn_map.as_mut() {
+            if a_binds.len() != b_binds.len() {
+                return false;
+            }
+
             // First, we push new scopes into the De Bruijn map and the cache stack
             de_bruijn_map.push();
             self.cache.push_scope();
 
             // Then, we check that the binding lists and the inner terms are equivalent
             for (a_var, b_var) in a_binds.iter().zip(b_binds.iter()) {
-                if !Polyeq::eq(self, &a_var.1, &b_var.1) {
+                if (!renamed && a_var.0 != b_var.0) || !Polyeq::eq(self, &a_var.1, &b_var.1) {
                     // We must remember to pop the frames from the De Bruijn map and cache stack
                     // here, so as not to leave them in a corrupted state
                     self.de_bruijn_map.as_mut().unwrap().pop();
@@ -143,7 +166,7 @@ impl PolyeqComparator {
                 self.de_bruijn_map
                     .as_mut()
                     .unwrap()
-                    .insert(a_var.0.clone(), b_var.0.clone());
+                    .insert(a_var.0, b_var.0);
             }
             let result = Polyeq::eq(self, a_inner, b_inner);
 
@@ -214,6 +237,22 @@ impl Polyeq for Term {
                     args: args_b,
                 },
             ) => op_a == op_b && op_args_a == op_args_b && Polyeq::eq(comp, args_a, args_b),
+            (
+                Term::ParamOp {
+                    op: op_a,
+                    op_args: op_args_a,
+                    args: args_a,
+                },
+                Term::ParamOp {
+                    op: op_b,
+                    op_args: op_args_b,
+                    args: args_b,
+                },
+            ) => {
+                op_a == op_b
+                    && Polyeq::eq(comp, op_args_a, op_args_b)
+                    && Polyeq::eq(comp, args_a, args_b)
+            }
             (Term::Op(op_a, args_a), Term::Op(op_b, args_b)) => {
                 if comp.is_mod_reordering {
                     if let (Operator::Equals, [a_1, a_2], Operator::Equals, [b_1, b_2]) =
@@ -230,15 +269,19 @@ impl Polyeq for Term {
             }
             (Term::Sort(a), Term::Sort(b)) => Polyeq::eq(comp, a, b),
             (Term::Quant(q_a, _, _), Term::Quant(q_b, _, _)) if q_a != q_b => false,
-            (Term::Quant(_, a_binds, a), Term::Quant(_, b_binds, b))
-            | (Term::Let(a_binds, a), Term::Let(b_binds, b))
-            | (Term::Lambda(a_binds, a), Term::Lambda(b_binds, b)) => {
-                comp.compare_binder(a_binds, b_binds, a, b)
+            (Term::Quant(_, a_binds, a), Term::Quant(_, b_binds, b)) => {
+                comp.compare_binder(a_binds, b_binds, a, b, comp.binders.quantifiers)
+            }
+            (Term::Let(a_binds, a), Term::Let(b_binds, b)) => {
+                comp.compare_binder(a_binds, b_binds, a, b, comp.binders.lets)
+            }
+            (Term::Lambda(a_binds, a), Term::Lambda(b_binds, b)) => {
+                comp.compare_binder(a_binds, b_binds, a, b, comp.binders.lambdas)
             }
             (Term::Choice(a_var, a), Term::Choice(b_var, b)) => {
                 let a_binds = BindingList(vec![a_var.clone()]);
                 let b_binds = BindingList(vec![b_var.clone()]);
-                comp.compare_binder(&a_binds, &b_binds, a, b)
+                comp.compare_binder(&a_binds, &b_binds, a, b, comp.binders.choices)
             }
             _ => false,
         }
@@ -260,10 +303,17 @@ impl Polyeq for Sort {
             (Sort::Atom(a, sorts_a), Sort::Atom(b, sorts_b)) => {
                 a == b && Polyeq::eq(comp, sorts_a, sorts_b)
             }
+            (Sort::Datatype(a, sorts_a), Sort::Datatype(b, sorts_b)) => {
+                a == b && Polyeq::eq(comp, sorts_a, sorts_b)
+            }
+            (Sort::FloatingPoint(e_a, s_a), Sort::FloatingPoint(e_b, s_b)) => {
+                e_a == e_b && s_a == s_b
+            }
             (Sort::Bool, Sort::Bool)
             | (Sort::Int, Sort::Int)
             | (Sort::Real, Sort::Real)
-            | (Sort::String, Sort::String) => true,
+            | (Sort::String, Sort::String)
+            | (Sort::RoundingMode, Sort::RoundingMode) => true,
             (Sort::Array(x_a, y_a), Sort::Array(x_b, y_b)) => {
                 Polyeq::eq(comp, x_a, x_b) && Polyeq::eq(comp, y_a, y_b)
             }
@@ -296,7 +346,7 @@ impl<T: Polyeq, U: Polyeq> Polyeq for (T, U) {
     }
 }
 
-impl Polyeq for String {
+impl Polyeq for Symbol {
     fn eq(_: &mut PolyeqComparator, a: &Self, b: &Self) -> bool {
         a == b
     }
@@ -309,6 +359,7 @@ impl Polyeq for ProofArg {
             (ProofArg::Assign(sa, ta), ProofArg::Assign(sb, tb)) => {
                 sa == sb && Polyeq::eq(comp, ta, tb)
             }
+            (ProofArg::List(a), ProofArg::List(b)) => Polyeq::eq(comp, a, b),
             _ => false,
         }
     }
@@ -366,7 +417,7 @@ struct DeBruijnMap {
     // that is bound second are assigned `$1`, etc. The given term would then be represented like
     // this:
     //     `(forall ((x Int)) (and (exists ((y Int)) (> $0 $1)) (> $0 5)))`
-    indices: (HashMapStack<String, usize>, HashMapStack<String, usize>),
+    indices: (HashMapStack<Symbol, usize>, HashMapStack<Symbol, usize>),
 
     // Holds the count of how many variables were bound before each depth
     counter: Vec<usize>,
@@ -396,14 +447,14 @@ impl DeBruijnMap {
         self.counter.pop();
     }
 
-    fn insert(&mut self, a: String, b: String) {
+    fn insert(&mut self, a: Symbol, b: Symbol) {
         let current = self.counter.last_mut().unwrap();
         self.indices.0.insert(a, *current);
         self.indices.1.insert(b, *current);
         *current += 1;
     }
 
-    fn compare(&self, a: &str, b: &str) -> bool {
+    fn compare(&self, a: &Symbol, b: &Symbol) -> bool {
         match (self.indices.0.get(a), self.indices.1.get(b)) {
             // If both a and b are free variables, they need to have the same name
             (None, None) => a == b,
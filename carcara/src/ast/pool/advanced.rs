@@ -73,6 +73,10 @@ impl TermPool for ContextPool {
             .unwrap()
             .free_vars_with_priorities(term, [&self.global_pool])
     }
+
+    fn contains(&self, term: &Rc<Term>) -> bool {
+        self.global_pool.storage.contains(term) || self.inner.read().unwrap().storage.contains(term)
+    }
 }
 
 // =========================================================================
@@ -149,4 +153,67 @@ impl TermPool for LocalPool {
             ],
         )
     }
+
+    fn contains(&self, term: &Rc<Term>) -> bool {
+        self.ctx_pool.contains(term) || self.inner.contains(term)
+    }
+}
+
+// =========================================================================
+
+/// A term pool that can be shared between many threads, each of them holding a clone of it. All
+/// clones share the same underlying hash-consing table, so equal terms added by different threads
+/// still result in the same allocation.
+///
+/// Looking up terms that are already in the pool only requires a read lock, so many threads can
+/// do it concurrently. The write lock is only taken when a new term must be allocated.
+#[derive(Clone)]
+pub struct ConcurrentPool {
+    inner: Arc<RwLock<PrimitivePool>>,
+}
+
+impl ConcurrentPool {
+    /// Constructs a new `ConcurrentPool` that starts with all the terms in the given pool.
+    pub fn new(pool: PrimitivePool) -> Self {
+        Self { inner: Arc::new(RwLock::new(pool)) }
+    }
+
+    /// Returns the underlying `PrimitivePool`, containing all the terms added by any of the clones
+    /// of this pool. Returns `None` if there are other clones of this pool still alive.
+    pub fn into_inner(self) -> Option<PrimitivePool> {
+        Arc::try_unwrap(self.inner)
+            .ok()
+            .map(|lock| lock.into_inner().unwrap())
+    }
+}
+
+impl TermPool for ConcurrentPool {
+    fn bool_true(&self) -> Rc<Term> {
+        self.inner.read().unwrap().bool_true.clone()
+    }
+
+    fn bool_false(&self) -> Rc<Term> {
+        self.inner.read().unwrap().bool_false.clone()
+    }
+
+    fn add(&mut self, term: Term) -> Rc<Term> {
+        if let Some(entry) = self.inner.read().unwrap().storage.get(&term) {
+            return entry.clone();
+        }
+        // Another thread may have added the term between releasing the read lock and acquiring
+        // the write lock, but `PrimitivePool::add` already handles that case
+        self.inner.write().unwrap().add(term)
+    }
+
+    fn sort(&self, term: &Rc<Term>) -> Rc<Term> {
+        self.inner.read().unwrap().sort(term)
+    }
+
+    fn free_vars(&mut self, term: &Rc<Term>) -> IndexSet<Rc<Term>> {
+        self.inner.write().unwrap().free_vars(term)
+    }
+
+    fn contains(&self, term: &Rc<Term>) -> bool {
+        self.inner.read().unwrap().contains(term)
+    }
 }
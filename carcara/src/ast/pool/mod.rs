@@ -4,7 +4,7 @@ pub mod advanced;
 mod storage;
 
 use super::{Rc, Sort, Term};
-use crate::ast::{Constant, IndexedOperator};
+use crate::ast::{Constant, IndexedOperator, ParamOperator};
 use indexmap::{IndexMap, IndexSet};
 use rug::Integer;
 use storage::Storage;
@@ -41,6 +41,38 @@ pub trait TermPool {
     /// This method uses a cache, so there is no additional cost to computing the free variables of
     /// a term multiple times.
     fn free_vars(&mut self, term: &Rc<Term>) -> IndexSet<Rc<Term>>;
+    /// Returns `true` if the given term is interned in the pool, that is, if it is the same
+    /// allocation that the pool returns when an equal term is added to it.
+    fn contains(&self, term: &Rc<Term>) -> bool;
+}
+
+/// Checks that the given terms, and all of their subterms, are interned in the pool. A term that
+/// is not interned may be equal by value to a term in the pool while being a different allocation,
+/// which breaks the comparisons by reference that hash consing allows. This usually happens when
+/// terms built in a different pool, such as the pool used to parse an external proof, are mixed
+/// with the terms of the active pool.
+///
+/// This check is only done in debug builds, and panics if a term is not interned.
+pub(crate) fn debug_assert_interned<'t>(
+    pool: &dyn TermPool,
+    terms: impl IntoIterator<Item = &'t Rc<Term>>,
+) {
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    let mut seen = IndexSet::new();
+    let mut stack: Vec<_> = terms.into_iter().collect();
+    while let Some(term) = stack.pop() {
+        if !seen.insert(term) {
+            continue;
+        }
+        assert!(
+            pool.contains(term),
+            "term is not interned in the active pool: '{}'",
+            term
+        );
+        stack.extend(term.children());
+    }
 }
 
 /// A structure to store and manage all allocated terms.
@@ -135,7 +167,19 @@ impl PrimitivePool {
                 | Operator::BvSGt
                 | Operator::BvSGe
                 | Operator::BvShl
-                | Operator::BvLShr => Sort::Bool,
+                | Operator::BvLShr
+                | Operator::FpLessEq
+                | Operator::FpLessThan
+                | Operator::FpGreaterEq
+                | Operator::FpGreaterThan
+                | Operator::FpEquals
+                | Operator::FpIsNormal
+                | Operator::FpIsSubnormal
+                | Operator::FpIsZero
+                | Operator::FpIsInfinite
+                | Operator::FpIsNaN
+                | Operator::FpIsNegative
+                | Operator::FpIsPositive => Sort::Bool,
                 Operator::BvAdd
                 | Operator::BvSub
                 | Operator::BvNot
@@ -186,7 +230,11 @@ impl PrimitivePool {
                     }
                 }
                 Operator::RealDiv | Operator::ToReal => Sort::Real,
-                Operator::IntDiv | Operator::Mod | Operator::Abs | Operator::ToInt => Sort::Int,
+                Operator::IntDiv
+                | Operator::Mod
+                | Operator::Abs
+                | Operator::ToInt
+                | Operator::BvToNat => Sort::Int,
                 Operator::Select => match self.compute_sort(&args[0]).as_sort().unwrap() {
                     Sort::Array(_, y) => y.as_sort().unwrap().clone(),
                     _ => unreachable!(),
@@ -217,6 +265,40 @@ impl PrimitivePool {
                 | Operator::ReKleeneCross
                 | Operator::ReOption
                 | Operator::ReRange => Sort::RegLan,
+                Operator::Fp => {
+                    let [exponent, significand] = [&args[1], &args[2]].map(|a| {
+                        let Sort::BitVec(width) = self.compute_sort(a).as_sort().unwrap().clone()
+                        else {
+                            unreachable!()
+                        };
+                        width
+                    });
+
+                    // The significand width of the sort also counts the hidden bit
+                    Sort::FloatingPoint(exponent, significand + Integer::ONE)
+                }
+                Operator::RoundNearestTiesToEven
+                | Operator::RoundNearestTiesToAway
+                | Operator::RoundTowardPositive
+                | Operator::RoundTowardNegative
+                | Operator::RoundTowardZero => Sort::RoundingMode,
+                Operator::FpAbs
+                | Operator::FpNeg
+                | Operator::FpRem
+                | Operator::FpMin
+                | Operator::FpMax => self.compute_sort(&args[0]).as_sort().unwrap().clone(),
+
+                // These operators take a rounding mode as their first argument
+                Operator::FpAdd
+                | Operator::FpSub
+                | Operator::FpMul
+                | Operator::FpDiv
+                | Operator::FpFma
+                | Operator::FpSqrt
+                | Operator::FpRoundToIntegral => {
+                    self.compute_sort(&args[1]).as_sort().unwrap().clone()
+                }
+                Operator::FpToReal => Sort::Real,
             },
             Term::App(f, _) => {
                 match self.compute_sort(f).as_sort().unwrap() {
@@ -250,13 +332,47 @@ impl PrimitivePool {
                         };
                         Sort::BitVec(extension_width + bv_width)
                     }
+                    IndexedOperator::Repeat => {
+                        let times = op_args[0].as_integer().unwrap();
+                        let Sort::BitVec(bv_width) =
+                            self.compute_sort(&args[0]).as_sort().unwrap().clone()
+                        else {
+                            unreachable!()
+                        };
+                        Sort::BitVec(times * bv_width)
+                    }
+                    IndexedOperator::RotateLeft | IndexedOperator::RotateRight => {
+                        self.compute_sort(&args[0]).as_sort().unwrap().clone()
+                    }
                     IndexedOperator::BvConst => unreachable!(
                         "bv const should be handled by the parser and transfromed into a constant"
                     ),
-                    IndexedOperator::BvBitOf => Sort::Bool,
+                    IndexedOperator::StrChar => unreachable!(
+                        "char constants should be handled by the parser and transformed into a \
+                        string constant"
+                    ),
+                    IndexedOperator::BvBitOf | IndexedOperator::Divisible => Sort::Bool,
+                    IndexedOperator::IntToBv => Sort::BitVec(op_args[0].as_integer().unwrap()),
+                    IndexedOperator::ReLoop | IndexedOperator::RePower => Sort::RegLan,
+                    IndexedOperator::FpPlusInfinity
+                    | IndexedOperator::FpMinusInfinity
+                    | IndexedOperator::FpPlusZero
+                    | IndexedOperator::FpMinusZero
+                    | IndexedOperator::FpNaN
+                    | IndexedOperator::ToFp
+                    | IndexedOperator::ToFpUnsigned => Sort::FloatingPoint(
+                        op_args[0].as_integer().unwrap(),
+                        op_args[1].as_integer().unwrap(),
+                    ),
+                    IndexedOperator::FpToUbv | IndexedOperator::FpToSbv => {
+                        Sort::BitVec(op_args[0].as_integer().unwrap())
+                    }
                 };
                 sort
             }
+            Term::ParamOp { op, op_args, .. } => match op {
+                ParamOperator::ArrayConst => op_args[0].as_sort().unwrap().clone(),
+            },
         };
         let sort = self.storage.add(Term::Sort(result));
         self.sorts_cache.insert(term.clone(), sort);
@@ -333,7 +449,7 @@ impl PrimitivePool {
                 let mut vars = self.free_vars_with_priorities(inner, prior_pools);
                 for (var, value) in bindings {
                     let sort = self.sort_with_priorities(value, prior_pools);
-                    let term = self.add_with_priorities((var.clone(), sort).into(), prior_pools);
+                    let term = self.add_with_priorities((*var, sort).into(), prior_pools);
                     vars.remove(&term);
                 }
                 vars
@@ -350,7 +466,8 @@ impl PrimitivePool {
                 set
             }
             Term::Const(_) | Term::Sort(_) => IndexSet::new(),
-            Term::IndexedOp { op: _, op_args: _, args } => {
+            Term::IndexedOp { op: _, op_args: _, args }
+            | Term::ParamOp { op: _, op_args: _, args } => {
                 let mut set = IndexSet::new();
                 for a in args {
                     set.extend(self.free_vars_with_priorities(a, prior_pools).into_iter());
@@ -385,4 +502,8 @@ impl TermPool for PrimitivePool {
     fn free_vars(&mut self, term: &Rc<Term>) -> IndexSet<Rc<Term>> {
         self.free_vars_with_priorities(term, [])
     }
+
+    fn contains(&self, term: &Rc<Term>) -> bool {
+        self.storage.contains(term)
+    }
 }
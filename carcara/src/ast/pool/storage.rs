@@ -58,6 +58,13 @@ impl Storage {
         self.0.get(term).map(|t| &t.0)
     }
 
+    /// Returns `true` if the given `Rc` is the allocation stored for its term. This is `false` if
+    /// the term is not in the storage, but also if an equal term is stored in a different
+    /// allocation.
+    pub fn contains(&self, term: &Rc<Term>) -> bool {
+        self.get(term) == Some(term)
+    }
+
     // This method is only necessary for the hash consing tests
     #[cfg(test)]
     pub fn into_vec(self) -> Vec<Rc<Term>> {
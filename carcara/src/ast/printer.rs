@@ -5,7 +5,7 @@ use crate::{
     parser::Token,
     utils::{is_symbol_character, DedupIterator},
 };
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 use std::{borrow::Cow, fmt, io};
 
 /// Prints a proof to the standard output.
@@ -13,16 +13,198 @@ use std::{borrow::Cow, fmt, io};
 /// If `use_sharing` is `true`, terms that are used multiple times will make use of sharing. The
 /// first time a novel term appears, it receives a unique name using the `:named` attribute. After
 /// that, any occurrence of that term will simply use this name, instead of printing the whole term.
+#[cfg(feature = "exporters")]
 pub fn print_proof(commands: &[ProofCommand], use_sharing: bool) -> io::Result<()> {
+    print_proof_for_version(
+        commands,
+        &IndexMap::new(),
+        &IndexMap::new(),
+        use_sharing,
+        AletheVersion::default(),
+        NumberFormat::default(),
+    )
+}
+
+/// Prints a proof to the standard output, using the syntax of the given version of the Alethe
+/// format. Any attributes in `attributes` are printed after the attributes of the step with the
+/// corresponding id, and any term in `term_attributes` is printed as an annotated term with the
+/// corresponding attributes (see [`Proof::term_attributes`]). Numeric constants are written
+/// according to `number_format`.
+///
+/// If the proof uses any construct that is not supported by that version, an error is returned and
+/// nothing is printed.
+#[cfg(feature = "exporters")]
+pub fn print_proof_for_version(
+    commands: &[ProofCommand],
+    attributes: &IndexMap<String, Vec<Attribute>>,
+    term_attributes: &IndexMap<Rc<Term>, Vec<Attribute>>,
+    use_sharing: bool,
+    version: AletheVersion,
+    number_format: NumberFormat,
+) -> io::Result<()> {
+    version.validate_for_printing(commands)?;
     let mut stdout = io::stdout();
     let mut printer = AlethePrinter {
         inner: &mut stdout,
         term_indices: use_sharing.then(IndexMap::new),
         term_sharing_variable_prefix: "@p_",
+        version,
+        attributes,
+        term_attributes,
+        number_format,
     };
     printer.write_proof(commands)
 }
 
+/// A printer that writes a proof one command at a time, as the commands become available, instead
+/// of requiring the whole proof to be in memory. Only the ids of the commands in the root proof are
+/// kept, so they can be referenced as premises by later commands.
+///
+/// Unlike [`print_proof_for_version`], commands are only validated against the Alethe version when
+/// they are written, so an error may be returned after part of the proof was already printed.
+#[cfg(feature = "exporters")]
+pub struct StreamingPrinter<'a> {
+    printer: AlethePrinter<'a>,
+    sort_pool: Option<PrimitivePool>,
+    root_ids: Vec<StepId>,
+}
+
+#[cfg(feature = "exporters")]
+impl<'a> StreamingPrinter<'a> {
+    /// Constructs a new streaming printer that writes to `dest`. The `attributes`,
+    /// `term_attributes`, `use_sharing`, `version` and `number_format` arguments have the same
+    /// meaning as in [`print_proof_for_version`].
+    pub fn new(
+        dest: &'a mut dyn io::Write,
+        attributes: &'a IndexMap<String, Vec<Attribute>>,
+        term_attributes: &'a IndexMap<Rc<Term>, Vec<Attribute>>,
+        use_sharing: bool,
+        version: AletheVersion,
+        number_format: NumberFormat,
+    ) -> Self {
+        Self {
+            printer: AlethePrinter {
+                inner: dest,
+                term_indices: use_sharing.then(IndexMap::new),
+                term_sharing_variable_prefix: "@p_",
+                version,
+                attributes,
+                term_attributes,
+                number_format,
+            },
+            sort_pool: (version == AletheVersion::Current).then(PrimitivePool::new),
+            root_ids: Vec::new(),
+        }
+    }
+
+    /// Writes the next command of the root proof. If the command is a subproof, all of its
+    /// commands are written.
+    pub fn write_command(&mut self, command: &ProofCommand) -> io::Result<()> {
+        let commands = std::slice::from_ref(command);
+        self.printer.version.validate_for_printing(commands)?;
+        self.printer.write_commands(
+            commands,
+            Some(self.root_ids.as_slice()),
+            &mut self.sort_pool,
+        )?;
+        self.root_ids.push(command.id().clone());
+        Ok(())
+    }
+}
+
+/// A version of the Alethe proof format. This determines the syntax used when printing proofs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AletheVersion {
+    /// The original syntax, in which assignments in `anchor` arguments are of the form
+    /// `(:= <symbol> <term>)`.
+    #[default]
+    Legacy,
+
+    /// The current syntax, in which assignments in `anchor` arguments are of the form
+    /// `(:= (<symbol> <sort>) <term>)`. In this version, step arguments must always be terms, so
+    /// assign style arguments such as `(:= <symbol> <term>)` are not supported.
+    Current,
+}
+
+impl AletheVersion {
+    /// Checks that the given proof only uses constructs supported by this version. If not, returns
+    /// the id of the first step that uses an unsupported construct.
+    pub fn validate(self, commands: &[ProofCommand]) -> Result<(), &str> {
+        if self == AletheVersion::Legacy {
+            return Ok(());
+        }
+        for command in ProofIter::new(commands) {
+            if let ProofCommand::Step(s) = command {
+                if s.args.iter().any(|a| matches!(a, ProofArg::Assign(..))) {
+                    return Err(&s.id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Similar to `AletheVersion::validate`, but returns an IO error that can be reported by the
+    /// printing functions.
+    #[cfg(feature = "exporters")]
+    fn validate_for_printing(self, commands: &[ProofCommand]) -> io::Result<()> {
+        self.validate(commands).map_err(|id| {
+            let message = format!(
+                "step '{id}' uses assign style arguments, which are not supported in Alethe \
+                version '{self}'"
+            );
+            io::Error::new(io::ErrorKind::InvalidInput, message)
+        })
+    }
+}
+
+impl fmt::Display for AletheVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AletheVersion::Legacy => write!(f, "legacy"),
+            AletheVersion::Current => write!(f, "current"),
+        }
+    }
+}
+
+/// How numeric constants are written when printing terms. Different tools that consume Alethe
+/// proofs or SMT-LIB problems expect numbers in different forms, so this allows the output to be
+/// adapted to the tool that will read it. The default format writes negative numbers as `(- 1)`
+/// and non-integer rationals as decimals, like `0.5`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NumberFormat {
+    /// How negative numbers are written.
+    pub negative_numbers: NegativeNumberStyle,
+
+    /// How rational constants that are not integers are written.
+    pub rationals: RationalStyle,
+}
+
+/// How negative numbers are written by the printer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NegativeNumberStyle {
+    /// Negative numbers are written as an application of the unary `-` operator, as in `(- 1)`.
+    /// This is the only form allowed by the SMT-LIB standard.
+    #[default]
+    Application,
+
+    /// Negative numbers are written as a single literal, as in `-1`. In this style, applications
+    /// of the unary `-` operator to a numeric constant are also written as a literal.
+    Literal,
+}
+
+/// How rational constants that are not integers are written by the printer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RationalStyle {
+    /// Rationals are written as decimals, as in `0.5`. If the rational has no finite decimal
+    /// representation, the written value is rounded.
+    #[default]
+    Decimal,
+
+    /// Rationals are written as a division of two integers, as in `(/ 1 2)`. This never loses
+    /// precision.
+    Division,
+}
+
 /// Given the conclusion clause of a `lia_generic` step, this method will write to `dest` the
 /// corresponding SMT problem instance.
 pub fn write_lia_smt_instance(
@@ -34,10 +216,15 @@ pub fn write_lia_smt_instance(
         inner: dest,
         term_indices: use_sharing.then(IndexMap::new),
         term_sharing_variable_prefix: "p_",
+        version: AletheVersion::default(),
+        attributes: &IndexMap::new(),
+        term_attributes: &IndexMap::new(),
+        number_format: NumberFormat::default(),
     };
     printer.write_lia_smt_instance(clause)
 }
 
+#[cfg(feature = "exporters")]
 trait PrintProof {
     fn write_proof(&mut self, commands: &[ProofCommand]) -> io::Result<()>;
 }
@@ -57,15 +244,18 @@ impl PrintWithSharing for Rc<Term> {
         if let Some(indices) = &mut p.term_indices {
             // There are three cases where we don't use sharing when printing a term:
             //
-            // - Terminal terms (i.e., constants or variables) could in theory be shared,
-            // but, since they are very small, it's not worth it to give them a name.
+            // - Terminal terms (i.e., constants or variables) and nullary operators, like rounding
+            // modes, could in theory be shared, but, since they are very small, it's not worth it
+            // to give them a name.
             //
             // - Sorts are represented as terms, but they are not actually terms in the grammar, so
             // we can't use the `(! ... :named ...)` syntax to give them a name.
             //
             // - If a term is only used once in the proof, there is no reason to give it a name. We
             // detect this case by checking if the number of references to it's `Rc` is exactly 1.
-            if !self.is_terminal() && !self.is_sort() && Rc::strong_count(self) > 1 {
+            let is_small =
+                self.is_terminal() || matches!(self.as_ref(), Term::Op(_, args) if args.is_empty());
+            if !is_small && !self.is_sort() && Rc::strong_count(self) > 1 {
                 return if let Some(i) = indices.get(self) {
                     write!(p.inner, "{}{}", p.term_sharing_variable_prefix, i)
                 } else {
@@ -73,11 +263,19 @@ impl PrintWithSharing for Rc<Term> {
                     indices.insert(self.clone(), i);
                     write!(p.inner, "(! ")?;
                     p.write_raw_term(self)?;
+                    p.write_term_attributes(self)?;
                     write!(p.inner, " :named {}{})", p.term_sharing_variable_prefix, i)
                 };
             }
         }
-        p.write_raw_term(self)
+        if p.term_attributes.contains_key(self) {
+            write!(p.inner, "(! ")?;
+            p.write_raw_term(self)?;
+            p.write_term_attributes(self)?;
+            write!(p.inner, ")")
+        } else {
+            p.write_raw_term(self)
+        }
     }
 }
 
@@ -121,10 +319,36 @@ struct AlethePrinter<'a> {
     inner: &'a mut dyn io::Write,
     term_indices: Option<IndexMap<Rc<Term>, usize>>,
     term_sharing_variable_prefix: &'static str,
+    #[cfg_attr(not(feature = "exporters"), allow(dead_code))]
+    version: AletheVersion,
+    #[cfg_attr(not(feature = "exporters"), allow(dead_code))]
+    attributes: &'a IndexMap<String, Vec<Attribute>>,
+    term_attributes: &'a IndexMap<Rc<Term>, Vec<Attribute>>,
+    number_format: NumberFormat,
 }
 
+#[cfg(feature = "exporters")]
 impl<'a> PrintProof for AlethePrinter<'a> {
     fn write_proof(&mut self, commands: &[ProofCommand]) -> io::Result<()> {
+        // In the current version of the format, assignments in `anchor` arguments include the sort
+        // of the variable. Since the printer has no access to the original term pool, we use a new
+        // one to compute these sorts
+        let mut sort_pool = (self.version == AletheVersion::Current).then(PrimitivePool::new);
+        self.write_commands(commands, None, &mut sort_pool)
+    }
+}
+
+impl<'a> AlethePrinter<'a> {
+    /// Writes the given commands, and all commands in their subproofs. If `root_ids` is `Some`,
+    /// `commands` must contain a single command of the root proof, and premises in the root proof
+    /// are referenced using the given ids.
+    #[cfg(feature = "exporters")]
+    fn write_commands(
+        &mut self,
+        commands: &[ProofCommand],
+        root_ids: Option<&[StepId]>,
+        sort_pool: &mut Option<PrimitivePool>,
+    ) -> io::Result<()> {
         let mut iter = ProofIter::new(commands);
         while let Some(command) = iter.next() {
             match command {
@@ -133,7 +357,12 @@ impl<'a> PrintProof for AlethePrinter<'a> {
                     term.print_with_sharing(self)?;
                     write!(self.inner, ")")?;
                 }
-                ProofCommand::Step(s) => self.write_step(&mut iter, s)?,
+                ProofCommand::Step(s) => {
+                    self.write_step(s, |premise| match (premise, root_ids) {
+                        ((0, i), Some(ids)) => ids[i].clone(),
+                        _ => iter.get_premise(premise).id().clone(),
+                    })?;
+                }
                 ProofCommand::Subproof(s) => {
                     write!(self.inner, "(anchor :step {}", quote_symbol(command.id()))?;
 
@@ -152,7 +381,13 @@ impl<'a> PrintProof for AlethePrinter<'a> {
                                 write!(self.inner, " ")?;
                             }
                             is_first = false;
-                            write!(self.inner, "(:= {} ", name)?;
+                            if let Some(pool) = sort_pool {
+                                let term = pool.add(value.as_ref().clone());
+                                let sort = pool.sort(&term);
+                                write!(self.inner, "(:= ({} {}) ", quote_symbol(name), sort)?;
+                            } else {
+                                write!(self.inner, "(:= {} ", name)?;
+                            }
                             value.print_with_sharing(self)?;
                             write!(self.inner, ")")?;
                         }
@@ -166,9 +401,26 @@ impl<'a> PrintProof for AlethePrinter<'a> {
 
         Ok(())
     }
-}
 
-impl<'a> AlethePrinter<'a> {
+    /// Writes a list of attributes, each preceded by a space.
+    fn write_attributes(&mut self, attributes: &[Attribute]) -> io::Result<()> {
+        for attribute in attributes {
+            write!(self.inner, " :{}", attribute.keyword)?;
+            if let Some(value) = &attribute.value {
+                write!(self.inner, " {}", value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the attributes given to `term` in `term_attributes`, if there are any.
+    fn write_term_attributes(&mut self, term: &Rc<Term>) -> io::Result<()> {
+        match self.term_attributes.get(term) {
+            Some(attributes) => self.write_attributes(attributes),
+            None => Ok(()),
+        }
+    }
+
     fn write_s_expr<H, T>(&mut self, head: &H, tail: &[T]) -> io::Result<()>
     where
         H: PrintWithSharing + ?Sized,
@@ -187,11 +439,52 @@ impl<'a> AlethePrinter<'a> {
         write!(self.inner, ")")
     }
 
+    /// Writes a constant, using the printer's number format if it is numeric. If `negate` is
+    /// `true`, the negation of the constant is written instead.
+    fn write_constant(&mut self, constant: &Constant, negate: bool) -> io::Result<()> {
+        let (is_negative, magnitude) = match constant {
+            Constant::Integer(i) => ((*i < 0) != negate, Constant::Integer(i.clone().abs())),
+            Constant::Real(r) => ((*r < 0) != negate, Constant::Real(r.clone().abs())),
+            _ => return write!(self.inner, "{}", constant),
+        };
+        let NumberFormat { negative_numbers, rationals } = self.number_format;
+        let sign = match negative_numbers {
+            NegativeNumberStyle::Literal if is_negative => "-",
+            _ => "",
+        };
+        let wrap = is_negative && negative_numbers == NegativeNumberStyle::Application;
+        if wrap {
+            write!(self.inner, "(- ")?;
+        }
+        match &magnitude {
+            Constant::Real(r) if !r.is_integer() && rationals == RationalStyle::Division => {
+                // In the literal style, the sign is attached to the numerator
+                write!(self.inner, "(/ {}{} {})", sign, r.numer(), r.denom())?;
+            }
+            _ => write!(self.inner, "{}{}", sign, magnitude)?,
+        }
+        if wrap {
+            write!(self.inner, ")")?;
+        }
+        Ok(())
+    }
+
     fn write_raw_term(&mut self, term: &Term) -> io::Result<()> {
         match term {
-            Term::Const(c) => write!(self.inner, "{}", c),
+            Term::Const(c) => self.write_constant(c, false),
+            Term::Op(Operator::Sub, args)
+                if args.len() == 1
+                    && args[0].is_number()
+                    && self.number_format.negative_numbers == NegativeNumberStyle::Literal =>
+            {
+                match args[0].as_ref() {
+                    Term::Const(c) => self.write_constant(c, true),
+                    _ => unreachable!(),
+                }
+            }
             Term::Var(name, _) => write!(self.inner, "{}", quote_symbol(name)),
             Term::App(func, args) => self.write_s_expr(func, args),
+            Term::Op(op, args) if args.is_empty() => write!(self.inner, "{}", op),
             Term::Op(op, args) => self.write_s_expr(op, args),
             Term::Sort(sort) => write!(self.inner, "{}", sort),
             Term::Quant(quantifier, bindings, term) => {
@@ -233,10 +526,26 @@ impl<'a> AlethePrinter<'a> {
                 }
                 Ok(())
             }
+            Term::ParamOp { op, op_args, args } => {
+                if !args.is_empty() {
+                    write!(self.inner, "(")?;
+                }
+                write!(self.inner, "(as {}", op)?;
+                self.write_s_expr_tail(op_args)?;
+                if !args.is_empty() {
+                    self.write_s_expr_tail(args)?;
+                }
+                Ok(())
+            }
         }
     }
 
-    fn write_step(&mut self, iter: &mut ProofIter, step: &ProofStep) -> io::Result<()> {
+    #[cfg(feature = "exporters")]
+    fn write_step(
+        &mut self,
+        step: &ProofStep,
+        premise_id: impl Fn((usize, usize)) -> StepId,
+    ) -> io::Result<()> {
         write!(self.inner, "(step {} (cl", quote_symbol(&step.id))?;
 
         for t in &step.clause {
@@ -248,11 +557,11 @@ impl<'a> AlethePrinter<'a> {
         write!(self.inner, " :rule {}", step.rule)?;
 
         if let [head, tail @ ..] = step.premises.as_slice() {
-            let id = iter.get_premise(*head).id();
-            write!(self.inner, " :premises ({}", quote_symbol(id))?;
+            let id = premise_id(*head);
+            write!(self.inner, " :premises ({}", quote_symbol(&id))?;
             for premise in tail {
-                let id = iter.get_premise(*premise).id();
-                write!(self.inner, " {}", quote_symbol(id))?;
+                let id = premise_id(*premise);
+                write!(self.inner, " {}", quote_symbol(&id))?;
             }
             write!(self.inner, ")")?;
         }
@@ -268,19 +577,24 @@ impl<'a> AlethePrinter<'a> {
         }
 
         if let [head, tail @ ..] = step.discharge.as_slice() {
-            let id = iter.get_premise(*head).id();
+            let id = premise_id(*head);
             write!(self.inner, " :discharge ({}", id)?;
             for discharge in tail {
-                let id = iter.get_premise(*discharge).id();
-                write!(self.inner, " {}", quote_symbol(id))?;
+                let id = premise_id(*discharge);
+                write!(self.inner, " {}", quote_symbol(&id))?;
             }
             write!(self.inner, ")")?;
         }
 
+        if let Some(attributes) = self.attributes.get(step.id.as_str()) {
+            self.write_attributes(attributes)?;
+        }
+
         write!(self.inner, ")")?;
         Ok(())
     }
 
+    #[cfg(feature = "exporters")]
     fn write_proof_arg(&mut self, arg: &ProofArg) -> io::Result<()> {
         match arg {
             ProofArg::Term(t) => t.print_with_sharing(self),
@@ -289,6 +603,14 @@ impl<'a> AlethePrinter<'a> {
                 value.print_with_sharing(self)?;
                 write!(self.inner, ")")
             }
+            ProofArg::List(terms) => {
+                write!(self.inner, "(@list")?;
+                for t in terms {
+                    write!(self.inner, " ")?;
+                    t.print_with_sharing(self)?;
+                }
+                write!(self.inner, ")")
+            }
         }
     }
 
@@ -302,6 +624,301 @@ impl<'a> AlethePrinter<'a> {
     }
 }
 
+/// A pretty printer for terms and clauses, meant to produce human readable output even for very
+/// large terms.
+///
+/// Any s-expression that does not fit in the maximum line width is broken into multiple lines, with
+/// each of its arguments on its own line, indented relative to the opening parenthesis. In
+/// addition, subterms that appear more than once and whose size is at least the sharing threshold
+/// are abbreviated: the first occurrence receives a name using the `:named` attribute, and any
+/// further occurrences simply use this name.
+///
+/// # Examples
+///
+/// ```
+/// # use carcara::{ast::*, parser::{Config, Parser}};
+/// let mut pool = PrimitivePool::new();
+/// let definitions = "(declare-fun a () Bool) (declare-fun b () Bool)";
+/// let mut parser = Parser::new(&mut pool, Config::new(), definitions.as_bytes())?;
+/// parser.parse_problem()?;
+/// parser.reset("(or (and a b) (not (and a b)))".as_bytes())?;
+/// let term = parser.parse_term()?;
+///
+/// let printer = PrettyPrinter::new().max_width(30).sharing_threshold(Some(3));
+/// let expected = "(or\n  (! (and a b) :named @p_0)\n  (not @p_0))";
+/// assert_eq!(printer.term_to_string(&term), expected);
+/// # Ok::<(), carcara::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrettyPrinter {
+    max_width: usize,
+    indent: usize,
+    sharing_threshold: Option<usize>,
+}
+
+impl Default for PrettyPrinter {
+    fn default() -> Self {
+        Self {
+            max_width: 100,
+            indent: 2,
+            sharing_threshold: Some(16),
+        }
+    }
+}
+
+impl PrettyPrinter {
+    /// Constructs a new `PrettyPrinter` using the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum line width. S-expressions that don't fit in this width are broken into
+    /// multiple lines. Atoms that are themselves longer than this width are never broken.
+    pub fn max_width(mut self, value: usize) -> Self {
+        self.max_width = value;
+        self
+    }
+
+    /// Sets how many spaces are used to indent the arguments of an s-expression that was broken
+    /// into multiple lines.
+    pub fn indent(mut self, value: usize) -> Self {
+        self.indent = value;
+        self
+    }
+
+    /// Sets the minimum size a repeated subterm must have to be abbreviated. The size of a term is
+    /// the number of nodes in its tree representation. If `None`, no subterm is abbreviated.
+    pub fn sharing_threshold(mut self, value: Option<usize>) -> Self {
+        self.sharing_threshold = value;
+        self
+    }
+
+    /// Pretty prints a term.
+    pub fn term_to_string(&self, term: &Rc<Term>) -> String {
+        let mut builder = DocBuilder::new(self.find_shared_terms(std::slice::from_ref(term)));
+        let doc = builder.build(term);
+        self.render(&doc)
+    }
+
+    /// Pretty prints a clause, using the `(cl ...)` notation.
+    pub fn clause_to_string(&self, clause: &[Rc<Term>]) -> String {
+        let mut builder = DocBuilder::new(self.find_shared_terms(clause));
+        let mut items = vec![Doc::Atom("cl".to_owned())];
+        items.extend(clause.iter().map(|t| builder.build(t)));
+        self.render(&Doc::list(items))
+    }
+
+    fn render(&self, doc: &Doc) -> String {
+        let mut result = String::new();
+        doc.render(&mut result, 0, self);
+        result
+    }
+
+    /// Finds all subterms that should be abbreviated, that is, that are at least as large as the
+    /// sharing threshold and that appear more than once.
+    fn find_shared_terms(&self, roots: &[Rc<Term>]) -> IndexSet<Rc<Term>> {
+        fn visit(
+            term: &Rc<Term>,
+            threshold: usize,
+            sizes: &mut IndexMap<Rc<Term>, usize>,
+            seen: &mut IndexSet<Rc<Term>>,
+            shared: &mut IndexSet<Rc<Term>>,
+        ) {
+            if term.is_terminal() || term.is_sort() {
+                return;
+            }
+            // Terms smaller than the threshold may be visited more than once, but since the
+            // traversal doesn't go past a term that was already seen, this is still linear on the
+            // size of the DAG
+            if term_size(term, sizes) >= threshold && !seen.insert(term.clone()) {
+                shared.insert(term.clone());
+                return;
+            }
+            for child in term.children() {
+                visit(child, threshold, sizes, seen, shared);
+            }
+        }
+
+        let mut shared = IndexSet::new();
+        if let Some(threshold) = self.sharing_threshold {
+            let mut sizes = IndexMap::new();
+            let mut seen = IndexSet::new();
+            for t in roots {
+                visit(t, threshold, &mut sizes, &mut seen, &mut shared);
+            }
+        }
+        shared
+    }
+}
+
+/// Computes the number of nodes in the tree representation of a term, caching the results in
+/// `cache`.
+fn term_size(term: &Rc<Term>, cache: &mut IndexMap<Rc<Term>, usize>) -> usize {
+    if let Some(&size) = cache.get(term) {
+        return size;
+    }
+    let size = 1 + term
+        .children()
+        .into_iter()
+        .map(|t| term_size(t, cache))
+        .sum::<usize>();
+    cache.insert(term.clone(), size);
+    size
+}
+
+/// An intermediate representation of a pretty printed term, consisting of atoms and lists. Each
+/// list stores the width it would occupy if printed in a single line.
+enum Doc {
+    Atom(String),
+    List(Vec<Doc>, usize),
+}
+
+impl Doc {
+    fn list(items: Vec<Doc>) -> Self {
+        // The width of a list is the sum of the widths of its items, plus the spaces between them
+        // and the two parentheses
+        let width = items.iter().map(Doc::width).sum::<usize>() + items.len().saturating_sub(1) + 2;
+        Doc::List(items, width)
+    }
+
+    fn width(&self) -> usize {
+        match self {
+            Doc::Atom(s) => s.len(),
+            Doc::List(_, width) => *width,
+        }
+    }
+
+    fn render_flat(&self, out: &mut String) {
+        match self {
+            Doc::Atom(s) => out.push_str(s),
+            Doc::List(items, _) => {
+                out.push('(');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(' ');
+                    }
+                    item.render_flat(out);
+                }
+                out.push(')');
+            }
+        }
+    }
+
+    /// Renders the document, assuming it starts at column `column`.
+    fn render(&self, out: &mut String, column: usize, printer: &PrettyPrinter) {
+        match self {
+            Doc::List(items, width) if column + width > printer.max_width && items.len() > 1 => {
+                out.push('(');
+                items[0].render(out, column + 1, printer);
+                let child_column = column + printer.indent;
+                for item in &items[1..] {
+                    out.push('\n');
+                    out.extend(std::iter::repeat(' ').take(child_column));
+                    item.render(out, child_column, printer);
+                }
+                out.push(')');
+            }
+            _ => self.render_flat(out),
+        }
+    }
+}
+
+struct DocBuilder {
+    shared: IndexSet<Rc<Term>>,
+    names: IndexMap<Rc<Term>, usize>,
+}
+
+impl DocBuilder {
+    fn new(shared: IndexSet<Rc<Term>>) -> Self {
+        Self { shared, names: IndexMap::new() }
+    }
+
+    fn build(&mut self, term: &Rc<Term>) -> Doc {
+        if !self.shared.contains(term) {
+            return self.build_raw(term);
+        }
+        if let Some(i) = self.names.get(term) {
+            return Doc::Atom(format!("@p_{}", i));
+        }
+        let i = self.names.len();
+        self.names.insert(term.clone(), i);
+        let inner = self.build_raw(term);
+        Doc::list(vec![
+            Doc::Atom("!".to_owned()),
+            inner,
+            Doc::Atom(":named".to_owned()),
+            Doc::Atom(format!("@p_{}", i)),
+        ])
+    }
+
+    fn build_list(&mut self, head: Doc, tail: &[Rc<Term>]) -> Doc {
+        let mut items = vec![head];
+        items.extend(tail.iter().map(|t| self.build(t)));
+        Doc::list(items)
+    }
+
+    fn build_raw(&mut self, term: &Term) -> Doc {
+        match term {
+            Term::Const(_) | Term::Var(..) | Term::Sort(_) => Doc::Atom(format!("{:#}", term)),
+            Term::App(func, args) => {
+                let head = self.build(func);
+                self.build_list(head, args)
+            }
+            Term::Op(op, args) if args.is_empty() => Doc::Atom(op.to_string()),
+            Term::Op(op, args) => self.build_list(Doc::Atom(op.to_string()), args),
+            Term::Quant(quantifier, bindings, inner) => Doc::list(vec![
+                Doc::Atom(quantifier.to_string()),
+                Doc::Atom(bindings.to_string()),
+                self.build(inner),
+            ]),
+            Term::Choice((name, sort), inner) => Doc::list(vec![
+                Doc::Atom("choice".to_owned()),
+                Doc::Atom(format!("(({} {}))", quote_symbol(name), sort)),
+                self.build(inner),
+            ]),
+            Term::Let(bindings, inner) => {
+                let bindings = bindings
+                    .iter()
+                    .map(|(name, value)| {
+                        Doc::list(vec![
+                            Doc::Atom(quote_symbol(name).into_owned()),
+                            self.build(value),
+                        ])
+                    })
+                    .collect();
+                Doc::list(vec![
+                    Doc::Atom("let".to_owned()),
+                    Doc::list(bindings),
+                    self.build(inner),
+                ])
+            }
+            Term::Lambda(bindings, inner) => Doc::list(vec![
+                Doc::Atom("lambda".to_owned()),
+                Doc::Atom(bindings.to_string()),
+                self.build(inner),
+            ]),
+            Term::IndexedOp { op, op_args, args } => {
+                let op_args: String = op_args.iter().map(|a| format!(" {}", a)).collect();
+                let head = format!("(_ {}{})", op, op_args);
+                if args.is_empty() {
+                    Doc::Atom(head)
+                } else {
+                    self.build_list(Doc::Atom(head), args)
+                }
+            }
+            Term::ParamOp { op, op_args, args } => {
+                let op_args: String = op_args.iter().map(|a| format!(" {}", a)).collect();
+                let head = format!("(as {}{})", op, op_args);
+                if args.is_empty() {
+                    Doc::Atom(head)
+                } else {
+                    self.build_list(Doc::Atom(head), args)
+                }
+            }
+        }
+    }
+}
+
 fn write_s_expr<H, T>(f: &mut fmt::Formatter, head: H, tail: &[T]) -> fmt::Result
 where
     H: fmt::Display,
@@ -314,7 +931,7 @@ where
     write!(f, ")")
 }
 
-fn quote_symbol(symbol: &str) -> Cow<str> {
+pub fn quote_symbol(symbol: &str) -> Cow<'_, str> {
     use crate::parser::Reserved;
     use std::str::FromStr;
 
@@ -337,7 +954,7 @@ fn quote_symbol(symbol: &str) -> Cow<str> {
     }
 }
 
-fn escape_string(string: &str) -> Cow<str> {
+fn escape_string(string: &str) -> Cow<'_, str> {
     if string.contains('"') {
         Cow::Owned(string.replace('"', "\"\""))
     } else {
@@ -354,6 +971,10 @@ impl fmt::Display for Term {
             inner: &mut buf,
             term_indices: use_sharing.then(IndexMap::new),
             term_sharing_variable_prefix: "@p_",
+            version: AletheVersion::default(),
+            attributes: &IndexMap::new(),
+            term_attributes: &IndexMap::new(),
+            number_format: NumberFormat::default(),
         };
         printer.write_raw_term(self).unwrap();
         let result = std::str::from_utf8(&buf).unwrap();
@@ -417,7 +1038,7 @@ impl fmt::Display for Sort {
         match self {
             // Function sorts should never be displayed, so the exact format we use is of little
             // importance
-            Sort::Function(args) => write_s_expr(f, "Func", args),
+            Sort::Function(args) => write_s_expr(f, "->", args),
             Sort::Atom(name, args) => match args.len() {
                 0 => write!(f, "{}", quote_symbol(name)),
                 _ => write_s_expr(f, quote_symbol(name), args),
@@ -427,8 +1048,14 @@ impl fmt::Display for Sort {
             Sort::Real => write!(f, "Real"),
             Sort::String => write!(f, "String"),
             Sort::RegLan => write!(f, "RegLan"),
+            Sort::Datatype(name, args) => match args.len() {
+                0 => write!(f, "{}", quote_symbol(name)),
+                _ => write_s_expr(f, quote_symbol(name), args),
+            },
             Sort::Array(x, y) => write_s_expr(f, "Array", &[x, y]),
             Sort::BitVec(w) => write!(f, "(_ BitVec {})", w),
+            Sort::FloatingPoint(e, s) => write!(f, "(_ FloatingPoint {} {})", e, s),
+            Sort::RoundingMode => write!(f, "RoundingMode"),
         }
     }
 }
@@ -452,6 +1079,49 @@ impl fmt::Display for Token {
     }
 }
 
+/// A sort that is displayed using the given sort aliases where possible, instead of its expanded
+/// form. If more than one alias matches, the one defined last is used.
+struct AliasedSort<'a> {
+    sort: &'a Rc<Term>,
+    aliases: &'a [SortDefinition],
+}
+
+impl<'a> AliasedSort<'a> {
+    fn new(sort: &'a Rc<Term>, aliases: &'a [SortDefinition]) -> Self {
+        Self { sort, aliases }
+    }
+
+    fn with_same_aliases<'b>(&'b self, sorts: &'b [Rc<Term>]) -> Vec<AliasedSort<'b>> {
+        sorts
+            .iter()
+            .map(|s| AliasedSort::new(s, self.aliases))
+            .collect()
+    }
+}
+
+impl fmt::Display for AliasedSort<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for def in self.aliases.iter().rev() {
+            if let Some(args) = def.match_sort(self.sort) {
+                return match args.as_slice() {
+                    [] => write!(f, "{}", quote_symbol(&def.name)),
+                    args => write_s_expr(f, quote_symbol(&def.name), &self.with_same_aliases(args)),
+                };
+            }
+        }
+        match self.sort.as_sort().unwrap() {
+            Sort::Atom(name, args) | Sort::Datatype(name, args) if !args.is_empty() => {
+                write_s_expr(f, quote_symbol(name), &self.with_same_aliases(args))
+            }
+            Sort::Array(x, y) => {
+                let args = [x.clone(), y.clone()];
+                write_s_expr(f, "Array", &self.with_same_aliases(&args))
+            }
+            sort => write!(f, "{}", sort),
+        }
+    }
+}
+
 impl fmt::Display for ProblemPrelude {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "(set-logic {})", self.logic.as_deref().unwrap_or("ALL"))?;
@@ -460,13 +1130,62 @@ impl fmt::Display for ProblemPrelude {
             writeln!(f, "(declare-sort {} {})", name, arity)?;
         }
 
+        for datatypes in &self.datatype_declarations {
+            write!(f, "(declare-datatypes (")?;
+            for (i, d) in datatypes.iter().enumerate() {
+                let sep = if i == 0 { "" } else { " " };
+                write!(
+                    f,
+                    "{}({} {})",
+                    sep,
+                    quote_symbol(&d.name),
+                    d.sort_params.len()
+                )?;
+            }
+            write!(f, ") (")?;
+            for (i, d) in datatypes.iter().enumerate() {
+                let sep = if i == 0 { "" } else { " " };
+                write!(f, "{}(", sep)?;
+                if !d.sort_params.is_empty() {
+                    let params: Vec<_> = d.sort_params.iter().map(|p| quote_symbol(p)).collect();
+                    write!(f, "par ({}) (", params.join(" "))?;
+                }
+                for (j, c) in d.constructors.iter().enumerate() {
+                    let sep = if j == 0 { "" } else { " " };
+                    write!(f, "{}({}", sep, quote_symbol(&c.name))?;
+                    for (selector, sort) in &c.selectors {
+                        write!(f, " ({} {})", quote_symbol(selector), sort)?;
+                    }
+                    write!(f, ")")?;
+                }
+                if !d.sort_params.is_empty() {
+                    write!(f, ")")?;
+                }
+                write!(f, ")")?;
+            }
+            writeln!(f, "))")?;
+        }
+
+        for (i, def) in self.sort_definitions.iter().enumerate() {
+            // The body of each definition may use the aliases defined before it
+            let body = AliasedSort::new(&def.body, &self.sort_definitions[..i]);
+            write!(f, "(define-sort {} (", quote_symbol(&def.name))?;
+            for (j, param) in def.params.iter().enumerate() {
+                let sep = if j == 0 { "" } else { " " };
+                write!(f, "{}{}", sep, quote_symbol(param))?;
+            }
+            writeln!(f, ") {})", body)?;
+        }
+
+        let aliases = &self.sort_definitions;
         for (name, sort) in &self.function_declarations {
             write!(f, "(declare-fun {} ", name)?;
             if let Sort::Function(sorts) = sort.as_sort().unwrap() {
+                let sorts: Vec<_> = sorts.iter().map(|s| AliasedSort::new(s, aliases)).collect();
                 write_s_expr(f, &sorts[0], &sorts[1..sorts.len() - 1])?;
                 writeln!(f, " {})", sorts.last().unwrap())?;
             } else {
-                writeln!(f, "() {})", sort)?;
+                writeln!(f, "() {})", AliasedSort::new(sort, aliases))?;
             }
         }
         Ok(())
@@ -0,0 +1,839 @@
+//! A compact binary format for proofs, used to store parsed proofs so they can be loaded again
+//! without being re-parsed.
+//!
+//! Terms are written only once: each term is given an index the first time it is written, and any
+//! later occurrence of it is written as a reference to that index. Since terms are hash consed,
+//! this preserves all of the sharing in the term pool, and the size of the output is linear in the
+//! number of distinct terms. When the proof is read back, each term is added to the given pool, so
+//! it can be used along with other terms of the pool.
+
+use super::*;
+use crate::parser::{
+    sort_check::{check_app, check_indexed_op, check_op, check_param_op},
+    ParserError, SortError,
+};
+use std::{
+    io::{self, Read, Write},
+    str::FromStr,
+};
+
+/// The bytes that start every serialized proof. The last byte is the format version, which must be
+/// changed whenever the format changes.
+const MAGIC: &[u8; 8] = b"CARCARA\x04";
+
+mod tags {
+    pub const CONST_INTEGER: u8 = 0;
+    pub const CONST_REAL: u8 = 1;
+    pub const CONST_STRING: u8 = 2;
+    pub const CONST_BITVEC: u8 = 3;
+    pub const VAR: u8 = 4;
+    pub const APP: u8 = 5;
+    pub const OP: u8 = 6;
+    pub const SORT: u8 = 7;
+    pub const QUANT: u8 = 8;
+    pub const CHOICE: u8 = 9;
+    pub const LET: u8 = 10;
+    pub const LAMBDA: u8 = 11;
+    pub const INDEXED_OP: u8 = 12;
+    pub const PARAM_OP: u8 = 13;
+
+    pub const SORT_FUNCTION: u8 = 0;
+    pub const SORT_ATOM: u8 = 1;
+    pub const SORT_BOOL: u8 = 2;
+    pub const SORT_INT: u8 = 3;
+    pub const SORT_REAL: u8 = 4;
+    pub const SORT_STRING: u8 = 5;
+    pub const SORT_REGLAN: u8 = 6;
+    pub const SORT_DATATYPE: u8 = 7;
+    pub const SORT_ARRAY: u8 = 8;
+    pub const SORT_BITVEC: u8 = 9;
+    pub const SORT_FLOATING_POINT: u8 = 10;
+    pub const SORT_ROUNDING_MODE: u8 = 11;
+
+    pub const ASSUME: u8 = 0;
+    pub const STEP: u8 = 1;
+    pub const SUBPROOF: u8 = 2;
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn ill_sorted(error: ParserError) -> io::Error {
+    invalid_data(format!("ill-sorted term: {error}"))
+}
+
+impl Proof {
+    /// Writes the proof in a compact binary format, that can be read back using
+    /// [`Proof::deserialize`]. This is much faster than parsing the proof again, so it can be used
+    /// to cache parsed proofs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use carcara::{ast::*, parser::*};
+    /// let problem = "(declare-fun p () Bool) (assert p) (assert (not p))";
+    /// let proof = "(assume h1 p) (assume h2 (not p))
+    ///     (step t3 (cl) :rule resolution :premises (h1 h2))";
+    /// let (_, proof, mut pool) =
+    ///     parse_instance(problem.as_bytes(), proof.as_bytes(), Config::new())?;
+    ///
+    /// let mut bytes = Vec::new();
+    /// proof.serialize(&mut bytes)?;
+    /// let loaded = Proof::deserialize(&mut pool, bytes.as_slice())?;
+    /// assert_eq!(proof.commands, loaded.commands);
+    /// # Ok::<(), carcara::Error>(())
+    /// ```
+    pub fn serialize<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut s = Serializer { writer, terms: IndexMap::new() };
+        s.writer.write_all(MAGIC)?;
+
+        s.usize(self.premises.len())?;
+        for premise in &self.premises {
+            s.term(premise)?;
+        }
+        s.commands(&self.commands)?;
+
+        s.usize(self.attributes.len())?;
+        for (id, attributes) in &self.attributes {
+            s.str(id)?;
+            s.attributes(attributes)?;
+        }
+
+        s.usize(self.term_attributes.len())?;
+        for (term, attributes) in &self.term_attributes {
+            s.term(term)?;
+            s.attributes(attributes)?;
+        }
+
+        s.usize(self.spans.len())?;
+        for (id, span) in &self.spans {
+            s.str(id)?;
+            let (start, end) = (span.start, span.end);
+            for n in [span.bytes.0, span.bytes.1, start.0, start.1, end.0, end.1] {
+                s.usize(n)?;
+            }
+        }
+
+        s.usize(self.flattened.len())?;
+        for (term, original) in &self.flattened {
+            s.term(term)?;
+            s.term(original)?;
+        }
+        s.writer.flush()
+    }
+
+    /// Reads a proof written by [`Proof::serialize`], adding its terms to the given pool. Returns
+    /// an error with kind `InvalidData` if the input is not a serialized proof.
+    ///
+    /// Each term is sort checked before it is added to the pool, in the same way as when it is
+    /// parsed, so a corrupted or crafted input can't introduce ill-sorted terms in the pool. Since
+    /// the parser configuration used for the original proof is not known, `Int` and `Real`
+    /// arguments are allowed to be mixed in arithmetic operations.
+    pub fn deserialize<R: Read>(pool: &mut dyn TermPool, reader: R) -> io::Result<Proof> {
+        let mut d = Deserializer { reader, pool, terms: Vec::new() };
+        let mut magic = [0; MAGIC.len()];
+        d.reader.read_exact(&mut magic)?;
+        if magic != *MAGIC {
+            return Err(invalid_data(
+                "input is not a serialized proof, or uses another version",
+            ));
+        }
+
+        let mut premises = IndexSet::new();
+        for _ in 0..d.usize()? {
+            premises.insert(d.bool_term()?);
+        }
+        let commands = d.commands()?;
+
+        let mut attributes = IndexMap::new();
+        for _ in 0..d.usize()? {
+            let id = d.string()?;
+            attributes.insert(id, d.attributes()?);
+        }
+
+        let mut term_attributes = IndexMap::new();
+        for _ in 0..d.usize()? {
+            let term = d.term()?;
+            term_attributes.insert(term, d.attributes()?);
+        }
+
+        let mut spans = IndexMap::new();
+        for _ in 0..d.usize()? {
+            let id = d.string()?;
+            let span = Span {
+                bytes: (d.usize()?, d.usize()?),
+                start: (d.usize()?, d.usize()?),
+                end: (d.usize()?, d.usize()?),
+            };
+            spans.insert(id, span);
+        }
+
+        let mut flattened = IndexMap::new();
+        for _ in 0..d.usize()? {
+            let term = d.term()?;
+            flattened.insert(term, d.term()?);
+        }
+
+        Ok(Proof {
+            premises,
+            commands,
+            attributes,
+            term_attributes,
+            spans,
+            flattened,
+        })
+    }
+}
+
+struct Serializer<W> {
+    writer: W,
+
+    /// The index given to each term that was already written.
+    terms: IndexMap<Rc<Term>, usize>,
+}
+
+impl<W: Write> Serializer<W> {
+    fn byte(&mut self, b: u8) -> io::Result<()> {
+        self.writer.write_all(&[b])
+    }
+
+    /// Writes an unsigned integer using a variable-length encoding, with seven bits in each byte.
+    fn usize(&mut self, mut n: usize) -> io::Result<()> {
+        loop {
+            let low = (n & 0x7f) as u8;
+            n >>= 7;
+            if n == 0 {
+                return self.byte(low);
+            }
+            self.byte(low | 0x80)?;
+        }
+    }
+
+    fn str(&mut self, s: &str) -> io::Result<()> {
+        self.usize(s.len())?;
+        self.writer.write_all(s.as_bytes())
+    }
+
+    fn attributes(&mut self, attributes: &[Attribute]) -> io::Result<()> {
+        self.usize(attributes.len())?;
+        for Attribute { keyword, value } in attributes {
+            self.str(keyword)?;
+            match value {
+                Some(value) => {
+                    self.byte(1)?;
+                    self.str(value)?;
+                }
+                None => self.byte(0)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn integer(&mut self, i: &Integer) -> io::Result<()> {
+        self.str(&i.to_string_radix(16))
+    }
+
+    fn constant(&mut self, c: &Constant) -> io::Result<()> {
+        match c {
+            Constant::Integer(i) => {
+                self.byte(tags::CONST_INTEGER)?;
+                self.integer(i)
+            }
+            Constant::Real(r) => {
+                self.byte(tags::CONST_REAL)?;
+                self.integer(r.numer())?;
+                self.integer(r.denom())
+            }
+            Constant::String(s) => {
+                self.byte(tags::CONST_STRING)?;
+                self.str(s)
+            }
+            Constant::BitVec(value, width) => {
+                self.byte(tags::CONST_BITVEC)?;
+                self.integer(value)?;
+                self.integer(width)
+            }
+        }
+    }
+
+    /// Writes a term. A term that was already written is written as `i + 1`, where `i` is its
+    /// index. Otherwise, a zero is written, followed by the term itself. The term is only given
+    /// an index after all of its subterms, so the indices follow the order in which the terms are
+    /// read back.
+    fn term(&mut self, term: &Rc<Term>) -> io::Result<()> {
+        if let Some(i) = self.terms.get(term) {
+            return self.usize(i + 1);
+        }
+        self.usize(0)?;
+        match term.as_ref() {
+            Term::Const(c) => self.constant(c)?,
+            Term::Var(name, sort) => {
+                self.byte(tags::VAR)?;
+                self.str(name)?;
+                self.term(sort)?;
+            }
+            Term::App(f, args) => {
+                self.byte(tags::APP)?;
+                self.term(f)?;
+                self.terms(args)?;
+            }
+            Term::Op(op, args) => {
+                self.byte(tags::OP)?;
+                self.str(&op.to_string())?;
+                self.terms(args)?;
+            }
+            Term::Sort(sort) => {
+                self.byte(tags::SORT)?;
+                self.sort(sort)?;
+            }
+            Term::Quant(quantifier, bindings, inner) => {
+                self.byte(tags::QUANT)?;
+                self.byte(u8::from(*quantifier == Quantifier::Exists))?;
+                self.bindings(bindings)?;
+                self.term(inner)?;
+            }
+            Term::Choice((name, sort), inner) => {
+                self.byte(tags::CHOICE)?;
+                self.str(name)?;
+                self.term(sort)?;
+                self.term(inner)?;
+            }
+            Term::Let(bindings, inner) => {
+                self.byte(tags::LET)?;
+                self.bindings(bindings)?;
+                self.term(inner)?;
+            }
+            Term::Lambda(bindings, inner) => {
+                self.byte(tags::LAMBDA)?;
+                self.bindings(bindings)?;
+                self.term(inner)?;
+            }
+            Term::IndexedOp { op, op_args, args } => {
+                self.byte(tags::INDEXED_OP)?;
+                self.str(&op.to_string())?;
+                self.usize(op_args.len())?;
+                for c in op_args {
+                    self.constant(c)?;
+                }
+                self.terms(args)?;
+            }
+            Term::ParamOp { op, op_args, args } => {
+                self.byte(tags::PARAM_OP)?;
+                self.str(&op.to_string())?;
+                self.terms(op_args)?;
+                self.terms(args)?;
+            }
+        }
+        self.terms.insert(term.clone(), self.terms.len());
+        Ok(())
+    }
+
+    fn terms(&mut self, terms: &[Rc<Term>]) -> io::Result<()> {
+        self.usize(terms.len())?;
+        terms.iter().try_for_each(|t| self.term(t))
+    }
+
+    fn sort(&mut self, sort: &Sort) -> io::Result<()> {
+        match sort {
+            Sort::Function(sorts) => {
+                self.byte(tags::SORT_FUNCTION)?;
+                self.terms(sorts)
+            }
+            Sort::Atom(name, args) => {
+                self.byte(tags::SORT_ATOM)?;
+                self.str(name)?;
+                self.terms(args)
+            }
+            Sort::Bool => self.byte(tags::SORT_BOOL),
+            Sort::Int => self.byte(tags::SORT_INT),
+            Sort::Real => self.byte(tags::SORT_REAL),
+            Sort::String => self.byte(tags::SORT_STRING),
+            Sort::RegLan => self.byte(tags::SORT_REGLAN),
+            Sort::Datatype(name, args) => {
+                self.byte(tags::SORT_DATATYPE)?;
+                self.str(name)?;
+                self.terms(args)
+            }
+            Sort::Array(x, y) => {
+                self.byte(tags::SORT_ARRAY)?;
+                self.term(x)?;
+                self.term(y)
+            }
+            Sort::BitVec(width) => {
+                self.byte(tags::SORT_BITVEC)?;
+                self.integer(width)
+            }
+            Sort::FloatingPoint(e, s) => {
+                self.byte(tags::SORT_FLOATING_POINT)?;
+                self.integer(e)?;
+                self.integer(s)
+            }
+            Sort::RoundingMode => self.byte(tags::SORT_ROUNDING_MODE),
+        }
+    }
+
+    fn sorted_vars(&mut self, vars: &[SortedVar]) -> io::Result<()> {
+        self.usize(vars.len())?;
+        for (name, value) in vars {
+            self.str(name)?;
+            self.term(value)?;
+        }
+        Ok(())
+    }
+
+    fn bindings(&mut self, bindings: &BindingList) -> io::Result<()> {
+        self.sorted_vars(bindings)
+    }
+
+    fn indices(&mut self, indices: &[(usize, usize)]) -> io::Result<()> {
+        self.usize(indices.len())?;
+        for &(depth, i) in indices {
+            self.usize(depth)?;
+            self.usize(i)?;
+        }
+        Ok(())
+    }
+
+    fn commands(&mut self, commands: &[ProofCommand]) -> io::Result<()> {
+        self.usize(commands.len())?;
+        for command in commands {
+            match command {
+                ProofCommand::Assume { id, term } => {
+                    self.byte(tags::ASSUME)?;
+                    self.str(id)?;
+                    self.term(term)?;
+                }
+                ProofCommand::Step(step) => {
+                    self.byte(tags::STEP)?;
+                    self.str(&step.id)?;
+                    self.terms(&step.clause)?;
+                    self.str(&step.rule)?;
+                    self.indices(&step.premises)?;
+                    self.usize(step.args.len())?;
+                    for arg in &step.args {
+                        match arg {
+                            ProofArg::Term(t) => {
+                                self.byte(0)?;
+                                self.term(t)?;
+                            }
+                            ProofArg::Assign(name, value) => {
+                                self.byte(1)?;
+                                self.str(name)?;
+                                self.term(value)?;
+                            }
+                            ProofArg::List(terms) => {
+                                self.byte(2)?;
+                                self.terms(terms)?;
+                            }
+                        }
+                    }
+                    self.indices(&step.discharge)?;
+                }
+                ProofCommand::Subproof(subproof) => {
+                    self.byte(tags::SUBPROOF)?;
+                    self.commands(&subproof.commands)?;
+                    self.sorted_vars(&subproof.assignment_args)?;
+                    self.sorted_vars(&subproof.variable_args)?;
+                    self.usize(subproof.context_id)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Deserializer<'a, R> {
+    reader: R,
+    pool: &'a mut dyn TermPool,
+
+    /// The terms read so far, in the order in which they were given their indices.
+    terms: Vec<Rc<Term>>,
+}
+
+impl<R: Read> Deserializer<'_, R> {
+    fn byte(&mut self) -> io::Result<u8> {
+        let mut buf = [0];
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn usize(&mut self) -> io::Result<usize> {
+        let mut result = 0usize;
+        let mut shift = 0;
+        loop {
+            let b = self.byte()?;
+            let low = usize::from(b & 0x7f);
+            if shift >= usize::BITS || (low << shift) >> shift != low {
+                return Err(invalid_data("integer is too large"));
+            }
+            result |= low << shift;
+            if b & 0x80 == 0 {
+                return Ok(result);
+            }
+            shift += 7;
+        }
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.usize()?;
+        let mut buf = Vec::new();
+        (&mut self.reader).take(len as u64).read_to_end(&mut buf)?;
+        if buf.len() != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        String::from_utf8(buf).map_err(|_| invalid_data("string is not valid UTF-8"))
+    }
+
+    fn symbol(&mut self) -> io::Result<Symbol> {
+        self.string().map(Symbol::from)
+    }
+
+    fn attributes(&mut self) -> io::Result<Vec<Attribute>> {
+        let mut result = Vec::new();
+        for _ in 0..self.usize()? {
+            let keyword = self.string()?;
+            let value = match self.byte()? {
+                0 => None,
+                _ => Some(self.string()?),
+            };
+            result.push(Attribute { keyword, value });
+        }
+        Ok(result)
+    }
+
+    fn integer(&mut self) -> io::Result<Integer> {
+        let s = self.string()?;
+        Integer::from_str_radix(&s, 16).map_err(|_| invalid_data(format!("invalid integer: {s}")))
+    }
+
+    fn constant_with_tag(&mut self, tag: u8) -> io::Result<Constant> {
+        Ok(match tag {
+            tags::CONST_INTEGER => Constant::Integer(self.integer()?),
+            tags::CONST_REAL => {
+                let (numer, denom) = (self.integer()?, self.integer()?);
+                if denom == 0 {
+                    return Err(invalid_data("rational has a zero denominator"));
+                }
+                Constant::Real(Rational::from((numer, denom)))
+            }
+            tags::CONST_STRING => Constant::String(self.string()?),
+            tags::CONST_BITVEC => Constant::BitVec(self.integer()?, self.integer()?),
+            other => return Err(invalid_data(format!("invalid constant tag: {other}"))),
+        })
+    }
+
+    fn parse_name<T: FromStr>(&mut self) -> io::Result<T> {
+        let name = self.string()?;
+        name.parse()
+            .map_err(|_| invalid_data(format!("unknown operator: {name}")))
+    }
+
+    fn term(&mut self) -> io::Result<Rc<Term>> {
+        let reference = self.usize()?;
+        if reference > 0 {
+            return self
+                .terms
+                .get(reference - 1)
+                .cloned()
+                .ok_or_else(|| invalid_data(format!("invalid term reference: {reference}")));
+        }
+        let term = match self.byte()? {
+            tag @ (tags::CONST_INTEGER
+            | tags::CONST_REAL
+            | tags::CONST_STRING
+            | tags::CONST_BITVEC) => Term::Const(self.constant_with_tag(tag)?),
+            tags::VAR => Term::Var(self.symbol()?, self.sort_term()?),
+            tags::APP => {
+                let (function, args) = (self.value_term()?, self.value_terms()?);
+                check_app(self.pool, &function, &args).map_err(ill_sorted)?;
+                Term::App(function, args)
+            }
+            tags::OP => {
+                let (op, args) = (self.parse_name()?, self.value_terms()?);
+                check_op(self.pool, op, &args, true).map_err(ill_sorted)?;
+                Term::Op(op, args)
+            }
+            tags::SORT => Term::Sort(self.sort()?),
+            tags::QUANT => {
+                let quantifier = match self.byte()? {
+                    0 => Quantifier::Forall,
+                    _ => Quantifier::Exists,
+                };
+                Term::Quant(quantifier, self.binders()?, self.bool_term()?)
+            }
+            tags::CHOICE => Term::Choice((self.symbol()?, self.sort_term()?), self.bool_term()?),
+            tags::LET => {
+                let bindings = self.assignments()?;
+                if bindings.is_empty() {
+                    return Err(invalid_data("binder has no variables"));
+                }
+                Term::Let(BindingList(bindings), self.value_term()?)
+            }
+            tags::LAMBDA => Term::Lambda(self.binders()?, self.value_term()?),
+            tags::INDEXED_OP => {
+                let op = self.parse_name()?;
+                if matches!(op, IndexedOperator::BvConst | IndexedOperator::StrChar) {
+                    return Err(invalid_data(format!("unexpected constant operator: {op}")));
+                }
+                let mut op_args = Vec::new();
+                for _ in 0..self.usize()? {
+                    let tag = self.byte()?;
+                    op_args.push(self.constant_with_tag(tag)?);
+                }
+                let args = self.value_terms()?;
+                check_indexed_op(self.pool, op, &op_args, &args).map_err(ill_sorted)?;
+                Term::IndexedOp { op, op_args, args }
+            }
+            tags::PARAM_OP => {
+                let op = self.parse_name()?;
+                let op_args = self.sort_terms()?;
+                if op_args.len() != 1 {
+                    return Err(invalid_data("parametrized operator must have one sort"));
+                }
+                let args = self.value_terms()?;
+                check_param_op(self.pool, op, &op_args, &args).map_err(ill_sorted)?;
+                Term::ParamOp { op, op_args, args }
+            }
+            other => return Err(invalid_data(format!("invalid term tag: {other}"))),
+        };
+        let term = self.pool.add(term);
+        self.terms.push(term.clone());
+        Ok(term)
+    }
+
+    fn terms(&mut self) -> io::Result<Vec<Rc<Term>>> {
+        (0..self.usize()?).map(|_| self.term()).collect()
+    }
+
+    /// Reads a term that must be a sort.
+    fn sort_term(&mut self) -> io::Result<Rc<Term>> {
+        let term = self.term()?;
+        if !term.is_sort() {
+            return Err(invalid_data(format!("expected sort, got term: {term}")));
+        }
+        Ok(term)
+    }
+
+    fn sort_terms(&mut self) -> io::Result<Vec<Rc<Term>>> {
+        (0..self.usize()?).map(|_| self.sort_term()).collect()
+    }
+
+    /// Reads a term that must not be a sort.
+    fn value_term(&mut self) -> io::Result<Rc<Term>> {
+        let term = self.term()?;
+        if term.is_sort() {
+            return Err(invalid_data(format!("expected term, got sort: {term}")));
+        }
+        Ok(term)
+    }
+
+    fn value_terms(&mut self) -> io::Result<Vec<Rc<Term>>> {
+        (0..self.usize()?).map(|_| self.value_term()).collect()
+    }
+
+    /// Reads a term that must have sort `Bool`.
+    fn bool_term(&mut self) -> io::Result<Rc<Term>> {
+        let term = self.value_term()?;
+        let sort = self.pool.sort(&term);
+        SortError::assert_eq(&Sort::Bool, sort.as_sort().unwrap())
+            .map_err(|e| ill_sorted(e.into()))?;
+        Ok(term)
+    }
+
+    fn bool_terms(&mut self) -> io::Result<Vec<Rc<Term>>> {
+        (0..self.usize()?).map(|_| self.bool_term()).collect()
+    }
+
+    fn sort(&mut self) -> io::Result<Sort> {
+        Ok(match self.byte()? {
+            tags::SORT_FUNCTION => {
+                let sorts = self.sort_terms()?;
+                if sorts.len() < 2 {
+                    return Err(invalid_data("function sort has no parameters"));
+                }
+                Sort::Function(sorts)
+            }
+            tags::SORT_ATOM => Sort::Atom(self.string()?, self.sort_terms()?),
+            tags::SORT_BOOL => Sort::Bool,
+            tags::SORT_INT => Sort::Int,
+            tags::SORT_REAL => Sort::Real,
+            tags::SORT_STRING => Sort::String,
+            tags::SORT_REGLAN => Sort::RegLan,
+            tags::SORT_DATATYPE => Sort::Datatype(self.string()?, self.sort_terms()?),
+            tags::SORT_ARRAY => Sort::Array(self.sort_term()?, self.sort_term()?),
+            tags::SORT_BITVEC => Sort::BitVec(self.integer()?),
+            tags::SORT_FLOATING_POINT => Sort::FloatingPoint(self.integer()?, self.integer()?),
+            tags::SORT_ROUNDING_MODE => Sort::RoundingMode,
+            other => return Err(invalid_data(format!("invalid sort tag: {other}"))),
+        })
+    }
+
+    fn sorted_vars(&mut self) -> io::Result<Vec<SortedVar>> {
+        (0..self.usize()?)
+            .map(|_| Ok((self.symbol()?, self.sort_term()?)))
+            .collect()
+    }
+
+    fn assignments(&mut self) -> io::Result<Vec<(Symbol, Rc<Term>)>> {
+        (0..self.usize()?)
+            .map(|_| Ok((self.symbol()?, self.value_term()?)))
+            .collect()
+    }
+
+    /// Reads the variables bound by a quantifier or `lambda` term, which can't be empty.
+    fn binders(&mut self) -> io::Result<BindingList> {
+        let vars = self.sorted_vars()?;
+        if vars.is_empty() {
+            return Err(invalid_data("binder has no variables"));
+        }
+        Ok(BindingList(vars))
+    }
+
+    fn indices(&mut self) -> io::Result<Vec<(usize, usize)>> {
+        (0..self.usize()?)
+            .map(|_| Ok((self.usize()?, self.usize()?)))
+            .collect()
+    }
+
+    fn commands(&mut self) -> io::Result<Vec<ProofCommand>> {
+        let mut commands = Vec::new();
+        for _ in 0..self.usize()? {
+            let command = match self.byte()? {
+                tags::ASSUME => ProofCommand::Assume {
+                    id: self.symbol()?.into(),
+                    term: self.bool_term()?,
+                },
+                tags::STEP => {
+                    let id = self.symbol()?.into();
+                    let clause = self.bool_terms()?;
+                    let rule = self.symbol()?;
+                    let premises = self.indices()?;
+                    let mut args = Vec::new();
+                    for _ in 0..self.usize()? {
+                        args.push(match self.byte()? {
+                            0 => ProofArg::Term(self.term()?),
+                            1 => ProofArg::Assign(self.symbol()?, self.term()?),
+                            2 => ProofArg::List(self.terms()?),
+                            other => {
+                                return Err(invalid_data(format!("invalid argument tag: {other}")))
+                            }
+                        });
+                    }
+                    let discharge = self.indices()?;
+                    ProofCommand::Step(ProofStep {
+                        id,
+                        clause,
+                        rule,
+                        premises,
+                        args,
+                        discharge,
+                    })
+                }
+                tags::SUBPROOF => {
+                    let commands = self.commands()?;
+                    if commands.last().map_or(true, |c| !c.is_step()) {
+                        return Err(invalid_data("subproof does not end in a step"));
+                    }
+                    ProofCommand::Subproof(Subproof {
+                        commands,
+                        assignment_args: self.assignments()?,
+                        variable_args: self.sorted_vars()?,
+                        context_id: self.usize()?,
+                    })
+                }
+                other => return Err(invalid_data(format!("invalid command tag: {other}"))),
+            };
+            commands.push(command);
+        }
+        Ok(commands)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::tests::parse_proof;
+
+    #[test]
+    fn test_round_trip() {
+        let mut pool = PrimitivePool::new();
+        let proof = parse_proof(
+            &mut pool,
+            "(assume h1 (forall ((x Int)) (> x (- 1))))
+            (assume h2 (= (let ((y 2.5)) (+ y y)) 5.0))
+            (anchor :step t3 :args ((z Int) (:= (w Int) z)))
+            (step t3.t1 (cl (= w z)) :rule refl)
+            (step t3 (cl (= w z)) :rule bind)
+            (step t4 (cl (= ((_ extract 1 0) #b0101) #b01)) :rule hole :args (1 (:= a 2)))
+            (step t5 (cl) :rule resolution :premises (h1 h2))",
+        );
+
+        let mut bytes = Vec::new();
+        proof.serialize(&mut bytes).unwrap();
+        let loaded = Proof::deserialize(&mut pool, bytes.as_slice()).unwrap();
+        assert_eq!(proof.commands, loaded.commands);
+        assert_eq!(proof.premises, loaded.premises);
+
+        // Each term is only written once
+        let mut twice = Vec::new();
+        Proof {
+            commands: [&proof.commands[..], &proof.commands[..]].concat(),
+            ..proof.clone()
+        }
+        .serialize(&mut twice)
+        .unwrap();
+        assert!(twice.len() < bytes.len() * 2);
+
+        // When loaded into another pool, the terms are added to that pool
+        let mut other_pool = PrimitivePool::new();
+        let loaded = Proof::deserialize(&mut other_pool, bytes.as_slice()).unwrap();
+        for (a, b) in proof.iter().zip(loaded.iter()) {
+            assert_eq!(a.id(), b.id());
+            let to_strings = |c: &[Rc<Term>]| c.iter().map(ToString::to_string).collect::<Vec<_>>();
+            assert_eq!(to_strings(a.clause()), to_strings(b.clause()));
+            assert!(b.clause().iter().all(|t| other_pool.contains(t)));
+        }
+
+        for invalid in [&bytes[..bytes.len() / 2], b"not a proof"] {
+            assert!(Proof::deserialize(&mut pool, invalid).is_err());
+        }
+    }
+
+    #[test]
+    fn test_ill_sorted_terms() {
+        use tags::*;
+
+        // A serialized proof with a single `assume` command, whose term is given as bytes
+        let assume = |term: &[u8]| [MAGIC.as_slice(), &[0, 1, ASSUME, 2], b"h1", term].concat();
+        let p = [0, VAR, 1, b'p', 0, SORT, SORT_BOOL].as_slice();
+        let one = [0, CONST_INTEGER, 1, b'1'].as_slice();
+
+        let mut pool = PrimitivePool::new();
+        let valid = [
+            assume(&[&[0, OP, 3][..], b"not", &[1], p].concat()),
+            vec![0; 4],
+        ]
+        .concat();
+        assert!(Proof::deserialize(&mut pool, valid.as_slice()).is_ok());
+
+        let cases: [&[u8]; 6] = [
+            // `p`, whose sort is not a sort
+            &[&[0, VAR, 1, b'p'][..], one].concat(),
+            // `(not)`
+            &[&[0, OP, 3][..], b"not", &[0]].concat(),
+            // `(not 1)`
+            &[&[0, OP, 3][..], b"not", &[1], one].concat(),
+            // `(and p Bool)`, where `Bool` is written as a reference to the sort of `p`
+            &[&[0, OP, 3][..], b"and", &[2], p, &[1]].concat(),
+            // `(p 1)`
+            &[&[0, APP][..], p, &[1], one].concat(),
+            // `1`, which is not a valid conclusion for an `assume`
+            one,
+        ];
+        for term in cases {
+            let err = Proof::deserialize(&mut pool, assume(term).as_slice()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
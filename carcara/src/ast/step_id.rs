@@ -0,0 +1,300 @@
+//! This module implements structured step ids.
+
+use super::Symbol;
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    str::Split,
+    sync::Arc,
+};
+
+/// The id of a proof command.
+///
+/// Step ids are written as a series of components separated by dots, like `t5.t2.3`. By
+/// convention, the commands inside a subproof have ids that extend the id of the step that ends
+/// the subproof, so in this example the command `t5.t2.3` is in a subproof nested in the subproof
+/// that ends with `t5.t2`, which itself is in the subproof that ends with `t5`. Each component is
+/// usually an alphabetic prefix followed by an index, like `t2`, but any symbol is allowed.
+///
+/// Unlike [`Symbol`]s, step ids are not interned, since most of them are unique and appear only
+/// once in the proof, so interning them would only grow the interner. Instead, the id is a shared
+/// string, so step ids are still cheap to clone. They are hashed by their contents, so a map keyed
+/// by step ids can be queried directly with a `&str`. They are also ordered by their structure,
+/// comparing the indices of each component numerically, so `t9` comes before `t10`, which comes
+/// before `t10.t1`. The ids nested in a subproof always come right after the id of the step that
+/// ends it.
+///
+/// # Examples
+///
+/// ```
+/// # use carcara::ast::StepId;
+/// let id = StepId::new("t5.t2.3");
+/// assert_eq!(id.prefix(), "t5");
+/// assert_eq!(id.depth(), 2);
+/// assert_eq!(id.index(), Some(3));
+/// assert_eq!(id.parent(), Some(StepId::new("t5.t2")));
+/// assert!(id.is_nested_in(&StepId::new("t5")));
+/// assert!(!id.is_nested_in(&StepId::new("t")));
+/// assert!(StepId::new("t9") < StepId::new("t10"));
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct StepId(Arc<str>);
+
+impl StepId {
+    /// Creates a step id from a string.
+    pub fn new(s: &str) -> Self {
+        Self(s.into())
+    }
+
+    /// Returns the string this id refers to.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns an iterator over the dot-separated components of this id.
+    pub fn components(&self) -> Split<'_, char> {
+        self.as_str().split('.')
+    }
+
+    /// Returns the first component of this id, that is, the id of the root proof command that
+    /// contains this command.
+    pub fn prefix(&self) -> &str {
+        self.components().next().unwrap()
+    }
+
+    /// Returns the last component of this id.
+    pub fn last(&self) -> &str {
+        self.components().next_back().unwrap()
+    }
+
+    /// Returns the number of subproofs this id is nested in, that is, the number of components
+    /// minus one.
+    pub fn depth(&self) -> usize {
+        self.as_str().matches('.').count()
+    }
+
+    /// Returns the index at the end of the last component, if it has one. For example, the index
+    /// of `t5.t2` is 2.
+    pub fn index(&self) -> Option<usize> {
+        let (_, digits) = split_index(self.last());
+        digits.parse().ok()
+    }
+
+    /// Returns the id of the step that ends the subproof this id is nested in, if there is one.
+    pub fn parent(&self) -> Option<Self> {
+        self.as_str().rsplit_once('.').map(|(p, _)| Self::new(p))
+    }
+
+    /// Returns `true` if this id is nested in the subproof that ends with the step `other`, at any
+    /// depth.
+    pub fn is_nested_in(&self, other: &StepId) -> bool {
+        self.as_str()
+            .strip_prefix(other.as_str())
+            .is_some_and(|rest| rest.starts_with('.'))
+    }
+
+    /// Returns the id obtained by adding a component to the end of this id.
+    pub fn child(&self, component: &str) -> Self {
+        format!("{}.{}", self, component).into()
+    }
+}
+
+/// Splits a component into its prefix and the digits at its end.
+fn split_index(component: &str) -> (&str, &str) {
+    let start = component
+        .trim_end_matches(|c: char| c.is_ascii_digit())
+        .len();
+    component.split_at(start)
+}
+
+fn compare_components(a: &str, b: &str) -> Ordering {
+    let (a_prefix, a_digits) = split_index(a);
+    let (b_prefix, b_digits) = split_index(b);
+
+    // Indices are compared without parsing them, so they can't overflow. Leading zeros are only
+    // used to break ties between components, so `t010` comes after `t10` and all ids nested in it
+    let a_index = a_digits.trim_start_matches('0');
+    let b_index = b_digits.trim_start_matches('0');
+    a_prefix
+        .cmp(b_prefix)
+        .then(a_index.len().cmp(&b_index.len()))
+        .then(a_index.cmp(b_index))
+        .then(a_digits.len().cmp(&b_digits.len()))
+}
+
+impl Default for StepId {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl Hash for StepId {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+// Two ids are equal if and only if they have the same contents, so this is consistent with the `Eq`
+// and `Hash` implementations
+impl Borrow<str> for StepId {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialOrd for StepId {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for StepId {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if Arc::ptr_eq(&self.0, &other.0) {
+            return Ordering::Equal;
+        }
+        let mut a = self.components();
+        let mut b = other.components();
+        loop {
+            match (a.next(), b.next()) {
+                (Some(x), Some(y)) => match compare_components(x, y) {
+                    Ordering::Equal => continue,
+                    ord => return ord,
+                },
+                (None, Some(_)) => return Ordering::Less,
+                (Some(_), None) => return Ordering::Greater,
+                (None, None) => return Ordering::Equal,
+            }
+        }
+    }
+}
+
+impl PartialEq<str> for StepId {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for StepId {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for StepId {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<StepId> for str {
+    fn eq(&self, other: &StepId) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<StepId> for &str {
+    fn eq(&self, other: &StepId) -> bool {
+        *self == other.as_str()
+    }
+}
+
+impl Deref for StepId {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for StepId {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<Symbol> for StepId {
+    fn from(s: Symbol) -> Self {
+        Self::new(&s)
+    }
+}
+
+impl From<&str> for StepId {
+    fn from(s: &str) -> Self {
+        Self::new(s)
+    }
+}
+
+impl From<&String> for StepId {
+    fn from(s: &String) -> Self {
+        Self::new(s)
+    }
+}
+
+impl From<String> for StepId {
+    fn from(s: String) -> Self {
+        Self(s.into())
+    }
+}
+
+impl From<StepId> for String {
+    fn from(id: StepId) -> Self {
+        id.as_str().to_owned()
+    }
+}
+
+impl fmt::Debug for StepId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for StepId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_structure() {
+        let id = StepId::new("t5.t2.h13");
+        assert_eq!(vec!["t5", "t2", "h13"], id.components().collect::<Vec<_>>());
+        assert_eq!("t5", id.prefix());
+        assert_eq!("h13", id.last());
+        assert_eq!(2, id.depth());
+        assert_eq!(Some(13), id.index());
+        assert_eq!(Some(StepId::new("t5.t2")), id.parent());
+        assert_eq!(id, StepId::new("t5.t2").child("h13"));
+
+        let id = StepId::new("a");
+        assert_eq!(0, id.depth());
+        assert_eq!(None, id.index());
+        assert_eq!(None, id.parent());
+
+        assert!(StepId::new("t5.t2").is_nested_in(&StepId::new("t5")));
+        assert!(!StepId::new("t5").is_nested_in(&StepId::new("t5")));
+        assert!(!StepId::new("t52").is_nested_in(&StepId::new("t5")));
+    }
+
+    #[test]
+    fn test_ordering() {
+        let mut ids: Vec<_> = [
+            "t10", "t9.t1", "t10.t2", "a1", "t9", "t10.t10", "t10.t9", "t010",
+        ]
+        .into_iter()
+        .map(StepId::new)
+        .collect();
+        ids.sort();
+        let expected = [
+            "a1", "t9", "t9.t1", "t10", "t10.t2", "t10.t9", "t10.t10", "t010",
+        ];
+        assert_eq!(expected.as_slice(), ids);
+    }
+}
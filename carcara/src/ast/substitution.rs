@@ -1,6 +1,6 @@
 //! Algorithms for creating and applying capture-avoiding substitutions over terms.
 
-use super::{BindingList, Rc, SortedVar, Term, TermPool};
+use super::{BindingList, Rc, SortedVar, Symbol, Term, TermPool};
 use indexmap::{IndexMap, IndexSet};
 use thiserror::Error;
 
@@ -197,7 +197,7 @@ impl Substitution {
                 term,
                 std::slice::from_ref(var),
                 t,
-                true,
+                false,
                 |mut b, t| Term::Choice(b.0.pop().unwrap(), t),
             ),
             Term::Let(b, t) => self.apply_to_binder(pool, term, b.as_ref(), t, true, Term::Let),
@@ -213,6 +213,14 @@ impl Substitution {
                     args: new_args,
                 })
             }
+            Term::ParamOp { op, op_args, args } => {
+                let new_args = apply_to_sequence!(args);
+                pool.add(Term::ParamOp {
+                    op: *op,
+                    op_args: op_args.clone(),
+                    args: new_args,
+                })
+            }
         };
 
         // Since frequently a term will have more than one identical subterms, we insert the
@@ -247,7 +255,7 @@ impl Substitution {
         // In order for skipping to be possible, there should be only one variable in the binding
         // list that would be renamed, and that variable must be the variable in the domain of the
         // substitution
-        should_be_renamed.next().map(|(var, _)| var.as_ref()) == Some(x)
+        should_be_renamed.next().map(|(var, _)| *var) == Some(x)
             && should_be_renamed.next().is_none()
     }
 
@@ -318,30 +326,30 @@ impl Substitution {
                 };
 
                 let mut changed = false;
-                let mut new_var = var.clone();
+                let mut new_var = *var;
 
                 // We keep adding `'`s to the variable name as long as it is necessary
                 loop {
                     if !new_vars.contains(&new_var) {
-                        let new_term = pool.add((new_var.clone(), sort.clone()).into());
+                        let new_term = pool.add((new_var, sort.clone()).into());
                         if !self.should_be_renamed.as_ref().unwrap().contains(&new_term) {
                             break;
                         }
                     }
-                    new_var.push('\'');
+                    new_var = Symbol::from(format!("{}'", new_var));
                     changed = true;
                 }
 
                 if changed {
                     // If the variable was renamed, we have to add this renaming to the resulting
                     // substitution
-                    let old = pool.add((var.clone(), sort.clone()).into());
-                    let new = pool.add((new_var.clone(), sort).into());
+                    let old = pool.add((*var, sort.clone()).into());
+                    let new = pool.add((new_var, sort).into());
 
                     // We can safely unwrap here because `old` and `new` are guaranteed to have the
                     // same sort
                     new_substitution.insert(pool, old, new).unwrap();
-                    new_vars.insert(new_var.clone());
+                    new_vars.insert(new_var);
                 }
 
                 // If the binding list is a "value" list, we need to apply the current substitution
@@ -433,7 +441,12 @@ mod tests {
             // In theory, since x does not appear in this term, renaming y to y' is unnecessary
             "(forall ((y Int)) (> y 0))" [x -> y] => "(forall ((y' Int)) (> y' 0))",
 
-            // TODO: Add tests for `choice`, `let`, and `lambda` terms
+            // Capture-avoidance in `choice` terms
+            "(choice ((y Int)) (> y x))" [x -> y] => "(choice ((y' Int)) (> y' y))",
+            "(choice ((x Int)) (> x 0))" [x -> y] => "(choice ((x Int)) (> x 0))",
+            "(= y (choice ((y Int)) (> y x)))" [x -> y] => "(= y (choice ((y' Int)) (> y' y)))",
+
+            // TODO: Add tests for `let` and `lambda` terms
         }
     }
 }
@@ -0,0 +1,216 @@
+//! This module implements interned symbols, used for variable names and rule names.
+
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{hash_map::DefaultHasher, HashSet},
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    ptr,
+    sync::{Mutex, OnceLock},
+};
+
+/// The number of shards in the global interner.
+const NUM_SHARDS: usize = 32;
+
+/// A shard of the global interner.
+type Shard = Mutex<HashSet<&'static str>>;
+
+/// The global interner. Each distinct string is allocated once, and is never freed. The interner is
+/// split into shards, each with its own lock, and each string is always interned in the same shard,
+/// chosen by its hash. This way, threads that are parsing or checking in parallel only contend
+/// when they intern strings that land in the same shard.
+static INTERNER: OnceLock<[Shard; NUM_SHARDS]> = OnceLock::new();
+
+/// Returns the shard of the global interner that is responsible for a string.
+fn shard_of(s: &str) -> &'static Shard {
+    let shards = INTERNER.get_or_init(|| std::array::from_fn(|_| Mutex::default()));
+    let mut hasher = DefaultHasher::new();
+    s.hash(&mut hasher);
+    &shards[hasher.finish() as usize % NUM_SHARDS]
+}
+
+/// An interned string.
+///
+/// Each distinct string is only allocated once, and a `Symbol` is just a reference to that
+/// allocation. This means that symbols are cheap to copy, and that equality and hashing can be done
+/// by reference, in constant time, regardless of the length of the string. Much like terms in the
+/// term pool, two symbols are equal if and only if they point to the same allocation.
+///
+/// Interned strings are never freed, so symbols should only be used for strings that are repeated
+/// throughout the proof, like variable names and rule names. Step ids, which are mostly unique, are
+/// not interned (see [`StepId`](super::StepId)).
+///
+/// # Examples
+///
+/// ```
+/// # use carcara::ast::Symbol;
+/// let a = Symbol::new("x");
+/// let b = Symbol::from(String::from("x"));
+/// assert_eq!(a, b);
+/// assert_eq!(a, "x");
+/// assert_eq!(a.len(), 1);
+/// ```
+#[derive(Clone, Copy)]
+pub struct Symbol(&'static str);
+
+impl Symbol {
+    /// Interns a string, returning its symbol.
+    pub fn new(s: &str) -> Self {
+        Self::intern(Cow::Borrowed(s))
+    }
+
+    fn intern(s: Cow<str>) -> Self {
+        let mut interner = shard_of(&s).lock().unwrap();
+        if let Some(interned) = interner.get(s.as_ref()) {
+            return Self(interned);
+        }
+        let interned: &'static str = Box::leak(s.into_owned().into_boxed_str());
+        interner.insert(interned);
+        Self(interned)
+    }
+
+    /// Returns the string this symbol refers to.
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
+}
+
+impl Default for Symbol {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self.0, other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.as_ptr().hash(state);
+    }
+}
+
+// Symbols are ordered by their contents, so the order doesn't depend on when they were interned
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(other.0)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<String> for Symbol {
+    fn eq(&self, other: &String) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<Symbol> for str {
+    fn eq(&self, other: &Symbol) -> bool {
+        self == other.0
+    }
+}
+
+impl PartialEq<Symbol> for &str {
+    fn eq(&self, other: &Symbol) -> bool {
+        *self == other.0
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.0
+    }
+}
+
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str {
+        self.0
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(s: &str) -> Self {
+        Self::new(s)
+    }
+}
+
+impl From<&String> for Symbol {
+    fn from(s: &String) -> Self {
+        Self::new(s)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(s: String) -> Self {
+        Self::intern(Cow::Owned(s))
+    }
+}
+
+impl From<Symbol> for String {
+    fn from(s: Symbol) -> Self {
+        s.0.to_owned()
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.0, f)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.0, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_interning() {
+        let a = Symbol::new("foo");
+        let b = Symbol::from(String::from("foo"));
+        let c = Symbol::new("bar");
+        assert_eq!(a, b);
+        assert!(ptr::eq(a.as_str(), b.as_str()));
+        assert_ne!(a, c);
+        assert_eq!(a, "foo");
+        assert!(c < a);
+
+        let mut map = HashMap::new();
+        map.insert(a, 1);
+        assert_eq!(Some(&1), map.get(&b));
+        assert_eq!(None, map.get(&c));
+        assert_eq!("foo", format!("{}", a));
+        assert_eq!("\"foo\"", format!("{:?}", a));
+    }
+}
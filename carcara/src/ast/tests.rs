@@ -1,6 +1,9 @@
 use crate::{
-    ast::{pool::PrimitivePool, TermPool},
-    parser::tests::parse_terms,
+    ast::{pool::PrimitivePool, AletheVersion, AlphaEquiv, Binders, TermPool},
+    parser::{
+        self,
+        tests::{parse_proof, parse_terms},
+    },
 };
 use indexmap::IndexSet;
 
@@ -12,7 +15,7 @@ fn test_free_vars() {
             let [root] = parse_terms(&mut pool, definitions, [term]);
             let expected: IndexSet<_> = expected.iter().copied().collect();
             let set = pool.free_vars(&root);
-            let got: IndexSet<_> = set.iter().map(|t| t.as_var().unwrap()).collect();
+            let got: IndexSet<_> = set.iter().map(|t| t.as_var().unwrap().as_str()).collect();
 
             assert_eq!(expected, got);
         }
@@ -36,6 +39,318 @@ fn test_free_vars() {
     );
 }
 
+#[test]
+fn test_prelude_restricted_to() {
+    let mut pool = PrimitivePool::new();
+    let definitions = "
+        (declare-sort T 0)
+        (declare-sort U 0)
+        (declare-fun f (T) Int)
+        (declare-fun g (U) Int)
+        (declare-fun t () T)
+        (declare-fun a () Int)
+        (declare-fun b () Int)
+    ";
+    let mut parser =
+        parser::Parser::new(&mut pool, parser::Config::new(), definitions.as_bytes()).unwrap();
+    let (prelude, _) = parser.parse_problem().unwrap();
+    parser.reset("(= (f t) a)".as_bytes()).unwrap();
+    let term = parser.parse_term().unwrap();
+
+    let restricted = prelude.restricted_to(&[term]);
+    let sorts: Vec<_> = restricted
+        .sort_declarations
+        .iter()
+        .map(|(s, _)| s.as_str())
+        .collect();
+    let functions: Vec<_> = restricted
+        .function_declarations
+        .iter()
+        .map(|(f, _)| f.as_str())
+        .collect();
+    assert_eq!(sorts, ["T"]);
+    assert_eq!(functions, ["f", "t", "a"]);
+}
+
+#[test]
+fn test_prelude_sort_aliases() {
+    let mut pool = PrimitivePool::new();
+    let problem = "(declare-sort T 0)
+        (define-sort Word () (_ BitVec 8))
+        (define-sort Map (K V) (Array K V))
+        (define-sort Memory () (Map Word Word))
+        (declare-fun m () (Map Int Word))
+        (declare-fun f (Word T) (Array T Bool))
+        (declare-fun mem () (Array (_ BitVec 8) (_ BitVec 8)))";
+    let mut parser =
+        parser::Parser::new(&mut pool, parser::Config::new(), problem.as_bytes()).unwrap();
+    let (prelude, _) = parser.parse_problem().unwrap();
+    let names: Vec<_> = prelude.sort_definitions().iter().map(|d| &d.name).collect();
+    assert_eq!(names, ["Word", "Map", "Memory"]);
+
+    // When printing the prelude, sorts are written using the aliases where possible
+    let expected = "(set-logic ALL)
+(declare-sort T 0)
+(define-sort Word () (_ BitVec 8))
+(define-sort Map (K V) (Array K V))
+(define-sort Memory () (Map Word Word))
+(declare-fun m () (Map Int Word))
+(declare-fun f (Word T) (Map T Bool))
+(declare-fun mem () Memory)
+";
+    assert_eq!(prelude.to_string(), expected);
+}
+
+#[test]
+fn test_beta_reduce() {
+    use crate::ast::beta_reduce;
+
+    let cases = [
+        ("((lambda ((x Int)) (+ x 1)) 2)", "(+ 2 1)"),
+        (
+            "(= a ((lambda ((x Int) (y Int)) (- y x)) a b))",
+            "(= a (- b a))",
+        ),
+        // Terms without redexes are unchanged
+        ("(lambda ((x Int)) (+ x a))", "(lambda ((x Int)) (+ x a))"),
+        // Reductions are repeated until no redexes are left
+        (
+            "((lambda ((f (-> Int Int))) (f a)) (lambda ((x Int)) (+ x 1)))",
+            "(+ a 1)",
+        ),
+        // Substitutions are capture-avoiding
+        (
+            "((lambda ((x Int)) (forall ((b Int)) (= x b))) b)",
+            "(forall ((b' Int)) (= b b'))",
+        ),
+    ];
+    for (term, expected) in cases {
+        let mut pool = PrimitivePool::new();
+        let [term, expected] = parse_terms(
+            &mut pool,
+            "(declare-fun a () Int) (declare-fun b () Int)",
+            [term, expected],
+        );
+        assert_eq!(beta_reduce(&mut pool, &term), expected);
+    }
+}
+
+#[test]
+fn test_pool_contains() {
+    use crate::ast::Term;
+
+    let mut pool = PrimitivePool::new();
+    let mut other = PrimitivePool::new();
+    let [a, b] = [&mut pool, &mut other].map(|p| p.add(Term::new_int(42)));
+
+    // The terms are equal by value, but only the allocation returned by each pool is interned in it
+    assert!(pool.contains(&a) && !pool.contains(&b));
+    assert!(other.contains(&b) && !other.contains(&a));
+    assert!(pool.contains(&pool.bool_true()));
+    assert!(!pool.contains(&other.bool_true()));
+}
+
+#[test]
+fn test_context_shadowing() {
+    use crate::ast::{ContextStack, Rc, Sort, SortedVar, Symbol, Term};
+
+    let mut pool = PrimitivePool::new();
+    let int = pool.add(Term::Sort(Sort::Int));
+    let [x, y, z] = ["x", "y", "z"].map(|n| pool.add(Term::new_var(n, int.clone())));
+    let outer = [(Symbol::new("x"), y.clone()), (Symbol::new("z"), y.clone())];
+
+    let mut run = |assignments: &[(Symbol, Rc<Term>)], variables: &[SortedVar], term: &Rc<Term>| {
+        let mut stack = ContextStack::new();
+        for (assignments, variables) in [(&outer[..], &[][..]), (assignments, variables)] {
+            let id = stack.force_new_context();
+            stack.push(&mut pool, assignments, variables, id).unwrap();
+        }
+        stack.apply(&mut pool, term)
+    };
+
+    // Assignments in the inner context take precedence over the ones in the outer context
+    assert_eq!(run(&[(Symbol::new("x"), z.clone())], &[], &x), z);
+    assert_eq!(run(&[(Symbol::new("x"), z.clone())], &[], &z), y);
+
+    // Variables bound in the inner context are not substituted by the outer context
+    assert_eq!(run(&[], &[(Symbol::new("z"), int.clone())], &z), z);
+    assert_eq!(run(&[], &[(Symbol::new("z"), int.clone())], &x), y);
+}
+
+#[test]
+fn test_alethe_version_validate() {
+    let mut pool = PrimitivePool::new();
+    let proof = parse_proof(
+        &mut pool,
+        "(anchor :step t1 :args ((:= x 1)))
+        (step t1.t1 (cl) :rule rule-name :args ((:= y 2)))
+        (step t1 (cl) :rule rule-name :args (1))",
+    );
+    assert_eq!(AletheVersion::Legacy.validate(&proof.commands), Ok(()));
+    assert_eq!(
+        AletheVersion::Current.validate(&proof.commands),
+        Err("t1.t1")
+    );
+}
+
+#[test]
+fn test_pretty_printer() {
+    use crate::ast::PrettyPrinter;
+
+    let mut pool = PrimitivePool::new();
+    let definitions = "(declare-fun a () Int) (declare-fun b () Int) (declare-fun c () Int)";
+    let [term, x, y] = parse_terms(
+        &mut pool,
+        definitions,
+        [
+            "(and (= (+ a b c) 0) (< (+ a b c) 1))",
+            "(= a b)",
+            "(= b c)",
+        ],
+    );
+
+    // Terms that fit in the line width are printed in a single line
+    let printer = PrettyPrinter::new().sharing_threshold(None);
+    assert_eq!(
+        printer.term_to_string(&term),
+        "(and (= (+ a b c) 0) (< (+ a b c) 1))"
+    );
+    assert_eq!(printer.clause_to_string(&[x, y]), "(cl (= a b) (= b c))");
+
+    // Otherwise, they are broken into multiple lines, but only as much as necessary
+    let printer = printer.max_width(20);
+    let expected = "(and\n  (= (+ a b c) 0)\n  (< (+ a b c) 1))";
+    assert_eq!(printer.term_to_string(&term), expected);
+    let expected = "(and\n    (= (+ a b c) 0)\n    (< (+ a b c) 1))";
+    assert_eq!(printer.indent(4).term_to_string(&term), expected);
+
+    // Repeated subterms are only abbreviated if they are at least as large as the threshold
+    let printer = PrettyPrinter::new().sharing_threshold(Some(5));
+    assert_eq!(
+        printer.term_to_string(&term),
+        "(and (= (+ a b c) 0) (< (+ a b c) 1))"
+    );
+    let printer = PrettyPrinter::new().sharing_threshold(Some(4));
+    assert_eq!(
+        printer.term_to_string(&term),
+        "(and (= (! (+ a b c) :named @p_0) 0) (< @p_0 1))"
+    );
+}
+
+#[test]
+#[cfg(feature = "exporters")]
+fn test_streaming_printer() {
+    use crate::ast::{NumberFormat, StreamingPrinter};
+    use indexmap::IndexMap;
+
+    let input = "(assume h1 (= 1 1))
+(anchor :step t2)
+(step t2.t1 (cl (= 1 1)) :rule hole :premises (h1))
+(step t2 (cl (= 1 1)) :rule hole :premises (t2.t1))
+(step t3 (cl) :rule hole :premises (h1 t2))
+";
+    let mut pool = PrimitivePool::new();
+    let proof = parse_proof(&mut pool, input);
+    let mut output = Vec::new();
+    let attributes = IndexMap::new();
+    let term_attributes = IndexMap::new();
+    let mut printer = StreamingPrinter::new(
+        &mut output,
+        &attributes,
+        &term_attributes,
+        false,
+        AletheVersion::Legacy,
+        NumberFormat::default(),
+    );
+    for command in &proof.commands {
+        printer.write_command(command).unwrap();
+    }
+    assert_eq!(String::from_utf8(output).unwrap(), input);
+}
+
+#[test]
+#[cfg(feature = "exporters")]
+fn test_print_term_attributes() {
+    use crate::ast::{NumberFormat, StreamingPrinter};
+    use indexmap::IndexMap;
+
+    let input = "(assume h1 (forall ((x Int)) (! (> x 0) :pattern ((+ x 1)) :qid q1)))
+(step t2 (cl (forall ((x Int)) (! (> x 0) :pattern ((+ x 1)) :qid q1))) :rule hole)
+";
+    let mut pool = PrimitivePool::new();
+    let proof = parse_proof(&mut pool, input);
+    let attributes = IndexMap::new();
+    for use_sharing in [false, true] {
+        let mut output = Vec::new();
+        let mut printer = StreamingPrinter::new(
+            &mut output,
+            &attributes,
+            &proof.term_attributes,
+            use_sharing,
+            AletheVersion::Legacy,
+            NumberFormat::default(),
+        );
+        for command in &proof.commands {
+            printer.write_command(command).unwrap();
+        }
+        let output = String::from_utf8(output).unwrap();
+        if use_sharing {
+            assert!(output.contains("(! (> x 0) :pattern ((+ x 1)) :qid q1 :named @p_"));
+            assert!(output.contains("(cl @p_0)"));
+        } else {
+            assert_eq!(output, input);
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "exporters")]
+fn test_number_format() {
+    use crate::ast::{NegativeNumberStyle, NumberFormat, RationalStyle, StreamingPrinter};
+    use indexmap::IndexMap;
+
+    let mut pool = PrimitivePool::new();
+    let proof = parse_proof(&mut pool, "(assume h1 (< (- 2) 0.5 (- 0.25) 3.0))");
+    let cases = [
+        (
+            NegativeNumberStyle::Application,
+            RationalStyle::Decimal,
+            "(assume h1 (< (- 2) 0.5 (- 0.25) 3.0))\n",
+        ),
+        (
+            NegativeNumberStyle::Literal,
+            RationalStyle::Decimal,
+            "(assume h1 (< -2 0.5 -0.25 3.0))\n",
+        ),
+        (
+            NegativeNumberStyle::Application,
+            RationalStyle::Division,
+            "(assume h1 (< (- 2) (/ 1 2) (- (/ 1 4)) 3.0))\n",
+        ),
+        (
+            NegativeNumberStyle::Literal,
+            RationalStyle::Division,
+            "(assume h1 (< -2 (/ 1 2) (/ -1 4) 3.0))\n",
+        ),
+    ];
+    let attributes = IndexMap::new();
+    let term_attributes = IndexMap::new();
+    for (negative_numbers, rationals, expected) in cases {
+        let mut output = Vec::new();
+        let format = NumberFormat { negative_numbers, rationals };
+        let mut printer = StreamingPrinter::new(
+            &mut output,
+            &attributes,
+            &term_attributes,
+            false,
+            AletheVersion::Legacy,
+            format,
+        );
+        printer.write_command(&proof.commands[0]).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+}
+
 #[test]
 fn test_polyeq() {
     enum TestType {
@@ -104,3 +419,82 @@ fn test_polyeq() {
         TestType::AlphaEquiv,
     );
 }
+
+#[test]
+fn test_alpha_equiv_hash() {
+    let definitions = "
+            (declare-sort T 0)
+            (declare-fun a () T)
+            (declare-fun b () T)
+            (declare-fun f (Int) Int)
+            (declare-fun x () Int)
+        ";
+    let equivalent = [
+        ("(= a b)", "(= b a)"),
+        ("(forall ((x Int)) (= x 0))", "(forall ((y Int)) (= 0 y))"),
+        (
+            "(exists ((x Int) (y Int)) (< x (f y)))",
+            "(exists ((y Int) (z Int)) (< y (f z)))",
+        ),
+        ("(choice ((y Int)) (= y x))", "(choice ((z Int)) (= x z))"),
+        (
+            "(let ((y 0) (z (+ x 2))) (< y z x))",
+            "(let ((z 0) (w (+ x 2))) (< z w x))",
+        ),
+    ];
+    let different = [
+        ("(forall ((y Int)) (= y 0))", "(forall ((y Int)) (= x 0))"),
+        ("(forall ((y Int)) (= y 0))", "(exists ((y Int)) (= y 0))"),
+        (
+            "(forall ((y Int) (z Int)) (= y 0))",
+            "(forall ((y Int)) (= y 0))",
+        ),
+        (
+            "(forall ((y Int) (z Int)) (< y z))",
+            "(forall ((y Int) (z Int)) (< z y))",
+        ),
+    ];
+
+    let mut pool = PrimitivePool::new();
+    let mut alpha = AlphaEquiv::new().mod_reordering(true);
+    for (a, b) in equivalent {
+        let [a, b] = parse_terms(&mut pool, definitions, [a, b]);
+        assert!(
+            alpha.eq(&a, &b),
+            "{} and {} should be alpha-equivalent",
+            a,
+            b
+        );
+        assert_eq!(alpha.hash(&a), alpha.hash(&b));
+    }
+    for (a, b) in different {
+        let [a, b] = parse_terms(&mut pool, definitions, [a, b]);
+        assert!(
+            !alpha.eq(&a, &b),
+            "{} and {} should not be alpha-equivalent",
+            a,
+            b
+        );
+    }
+
+    let [a, b] = parse_terms(&mut pool, definitions, ["(= a b)", "(= b a)"]);
+    assert!(!AlphaEquiv::new().eq(&a, &b));
+
+    // With only some binders renamed, the variables of the others must keep their names
+    let binders = Binders { lets: false, ..Binders::ALL };
+    let mut alpha = AlphaEquiv::new().binders(binders);
+    let [a, b, c, d] = parse_terms(
+        &mut pool,
+        definitions,
+        [
+            "(forall ((y Int)) (let ((z y)) (= z 0)))",
+            "(forall ((w Int)) (let ((z w)) (= z 0)))",
+            "(forall ((w Int)) (let ((v w)) (= v 0)))",
+            "(let ((z x)) (= z 0))",
+        ],
+    );
+    assert!(alpha.eq(&a, &b));
+    assert_eq!(alpha.hash(&a), alpha.hash(&b));
+    assert!(!alpha.eq(&a, &c));
+    assert!(!alpha.eq(&d, &a));
+}
@@ -0,0 +1,178 @@
+use super::*;
+use indexmap::IndexSet;
+use std::{io, sync::Arc};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InternedStepId {
+    pub(crate) file: Arc<str>,
+    pub(crate) step_id: Arc<str>,
+    pub(crate) rule: Arc<str>,
+}
+
+impl fmt::Display for InternedStepId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{} ({})", self.file, self.step_id, self.rule)
+    }
+}
+
+type InternedRunId = (Arc<str>, usize);
+
+#[derive(Default)]
+pub struct CsvBenchmarkResults {
+    strings: IndexSet<Arc<str>>,
+    runs: IndexMap<InternedRunId, RunMeasurement>,
+    step_time_by_rule: IndexMap<Arc<str>, OfflineMetrics<InternedStepId>>,
+    is_holey: bool,
+    failures: Vec<(InternedRunId, String)>,
+}
+
+impl CsvBenchmarkResults {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn is_holey(&self) -> bool {
+        self.is_holey
+    }
+
+    pub fn num_errors(&self) -> usize {
+        self.failures.len()
+    }
+
+    /// Returns the runs that failed, each given by the proof file and run index, with the message
+    /// of the error that caused it.
+    pub fn failures(&self) -> impl Iterator<Item = (&str, usize, &str)> {
+        (self.failures.iter()).map(|((file, i), error)| (file.as_ref(), *i, error.as_str()))
+    }
+
+    fn intern(&mut self, s: &str) -> Arc<str> {
+        match self.strings.get(s) {
+            Some(interned) => interned.clone(),
+            None => {
+                let result: Arc<str> = Arc::from(s);
+                self.strings.insert(result.clone());
+                result
+            }
+        }
+    }
+
+    pub fn write_csv(
+        self,
+        runs_dest: &mut dyn io::Write,
+        by_rule_dest: &mut dyn io::Write,
+    ) -> io::Result<()> {
+        Self::write_runs_csv(self.runs, runs_dest)?;
+        Self::write_by_rule_csv(self.step_time_by_rule, by_rule_dest)
+    }
+
+    fn write_runs_csv(
+        data: IndexMap<InternedRunId, RunMeasurement>,
+        dest: &mut dyn io::Write,
+    ) -> io::Result<()> {
+        writeln!(
+            dest,
+            "proof_file,run_id,parsing,checking,elaboration,total_accounted_for,\
+            total,polyeq,polyeq_ratio,assume,assume_ratio"
+        )?;
+
+        for (id, m) in data {
+            let total_accounted_for = m.parsing + m.checking;
+            let polyeq_ratio = m.polyeq.as_secs_f64() / m.checking.as_secs_f64();
+            let assume_ratio = m.assume.as_secs_f64() / m.checking.as_secs_f64();
+            writeln!(
+                dest,
+                "{},{},{},{},{},{},{},{},{},{},{}",
+                id.0,
+                id.1,
+                m.parsing.as_nanos(),
+                m.checking.as_nanos(),
+                m.elaboration.as_nanos(),
+                total_accounted_for.as_nanos(),
+                m.total.as_nanos(),
+                m.polyeq.as_nanos(),
+                polyeq_ratio,
+                m.assume.as_nanos(),
+                assume_ratio,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn write_by_rule_csv(
+        data: IndexMap<Arc<str>, OfflineMetrics<InternedStepId>>,
+        dest: &mut dyn io::Write,
+    ) -> io::Result<()> {
+        let mut data: Vec<_> = data.into_iter().collect();
+        data.sort_unstable_by_key(|m| m.1.total());
+
+        writeln!(
+            dest,
+            "rule,count,total,mean,lower_whisker,first_quartile,median,third_quartile,\
+            upper_whisker,theory"
+        )?;
+        for (rule, mut m) in data {
+            let [lower_whisker, first_quartile, median, third_quartile, upper_whisker] =
+                m.quartiles().map(|(_, t)| t.as_nanos());
+            writeln!(
+                dest,
+                "{},{},{},{},{},{},{},{},{},{}",
+                rule,
+                m.count(),
+                m.total().as_nanos(),
+                m.mean().as_nanos(),
+                lower_whisker,
+                first_quartile,
+                median,
+                third_quartile,
+                upper_whisker,
+                Theory::of_rule(&rule),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl CollectResults for CsvBenchmarkResults {
+    fn add_step_measurement(&mut self, file: &str, step_id: &str, rule: &str, time: Duration) {
+        let rule = self.intern(rule);
+        let id = InternedStepId {
+            file: self.intern(file),
+            step_id: self.intern(step_id),
+            rule: rule.clone(),
+        };
+        self.step_time_by_rule
+            .entry(rule)
+            .or_default()
+            .add_sample(&id, time);
+    }
+
+    fn add_assume_measurement(&mut self, file: &str, id: &str, _: bool, time: Duration) {
+        self.add_step_measurement(file, id, "assume", time);
+    }
+
+    fn add_polyeq_depth(&mut self, _: usize) {}
+
+    fn add_run_measurement(&mut self, (file, i): &RunId, measurement: RunMeasurement) {
+        let id = (self.intern(file), *i);
+        self.runs.insert(id, measurement);
+    }
+
+    fn register_holey(&mut self) {
+        self.is_holey = true;
+    }
+
+    fn register_error(&mut self, (file, i): &RunId, error: &crate::Error) {
+        let id = (self.intern(file), *i);
+        self.failures.push((id, error.to_string()));
+    }
+
+    fn combine(mut a: Self, b: Self) -> Self {
+        // This assumes that the same run never appears in both `a` and `b`. This should be the case
+        // in benchmarks anyway
+        a.runs.extend(b.runs);
+        a.step_time_by_rule = combine_map(a.step_time_by_rule, b.step_time_by_rule);
+        a.failures.extend(b.failures);
+        a
+    }
+}
@@ -98,6 +98,30 @@ impl MetricsUnit for usize {
     }
 }
 
+impl MetricsUnit for u64 {
+    type MeanType = f64;
+
+    fn as_f64(&self) -> f64 {
+        *self as f64
+    }
+
+    fn from_f64(x: f64) -> Self::MeanType {
+        x
+    }
+
+    fn div_u32(self, rhs: u32) -> Self::MeanType {
+        self as f64 / rhs as f64
+    }
+
+    fn mean_diff(self, mean: Self::MeanType) -> Self::MeanType {
+        (self as f64) - mean
+    }
+
+    fn display(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
 struct DisplayUnit<T: MetricsUnit>(T);
 
 impl<T: MetricsUnit> fmt::Display for DisplayUnit<T> {
@@ -1,12 +1,24 @@
+#[cfg(all(feature = "benchmarking", feature = "exporters"))]
+mod csv;
+#[cfg(feature = "benchmarking")]
 mod metrics;
-#[cfg(test)]
+mod sampling;
+#[cfg(all(test, feature = "benchmarking"))]
 mod tests;
 
+#[cfg(all(feature = "benchmarking", feature = "exporters"))]
+pub use csv::{CsvBenchmarkResults, InternedStepId};
+#[cfg(feature = "benchmarking")]
 pub use metrics::*;
+pub use sampling::{run_sampled, StepProbe, StepSamples};
 
-use indexmap::{map::Entry, IndexMap, IndexSet};
-use std::{fmt, hash::Hash, io, sync::Arc, time::Duration};
+#[cfg(feature = "benchmarking")]
+use indexmap::{map::Entry, IndexMap};
+#[cfg(feature = "benchmarking")]
+use std::hash::Hash;
+use std::{fmt, time::Duration};
 
+#[cfg(feature = "benchmarking")]
 fn combine_map<S, K, V, M>(mut a: IndexMap<S, M>, b: IndexMap<S, M>) -> IndexMap<S, M>
 where
     S: Eq + Hash,
@@ -29,6 +41,103 @@ where
     a
 }
 
+/// The theory a rule belongs to, used to aggregate the benchmark results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Theory {
+    Propositional,
+
+    /// Rules about equality and uninterpreted functions, and about `ite` terms of any sort.
+    Equality,
+    Arithmetic,
+    Quantifiers,
+    BitVectors,
+    Strings,
+    Arrays,
+
+    /// Rules that may apply rewrites from any theory, like `all_simplify` and `rare_rewrite`.
+    Rewrites,
+
+    /// Rules that don't belong to any particular theory, like `assume`, `subproof` and `hole`, and
+    /// unknown rules.
+    Other,
+}
+
+impl Theory {
+    /// Returns the theory the rule with the given name belongs to. Measurements of `all_simplify`
+    /// steps may be recorded as `all_simplify(<rewrite>)`, in which case the theory of the rewrite
+    /// is returned, if it has one.
+    pub fn of_rule(rule: &str) -> Self {
+        if let Some(rewrite) = rule
+            .strip_prefix("all_simplify(")
+            .and_then(|r| r.strip_suffix(')'))
+        {
+            return match Theory::of_rule(rewrite) {
+                Theory::Other => Theory::Rewrites,
+                theory => theory,
+            };
+        }
+        match rule {
+            "true" | "false" | "not_not" | "and_pos" | "and_neg" | "or_pos" | "or_neg"
+            | "xor_pos1" | "xor_pos2" | "xor_neg1" | "xor_neg2" | "implies_pos"
+            | "implies_neg1" | "implies_neg2" | "equiv_pos1" | "equiv_pos2" | "equiv_neg1"
+            | "equiv_neg2" | "ite_pos1" | "ite_pos2" | "ite_neg1" | "ite_neg2" | "resolution"
+            | "th_resolution" | "strict_resolution" | "and" | "tautology" | "not_or" | "or"
+            | "not_and" | "xor1" | "xor2" | "not_xor1" | "not_xor2" | "implies"
+            | "not_implies1" | "not_implies2" | "equiv1" | "equiv2" | "not_equiv1"
+            | "not_equiv2" | "ite1" | "ite2" | "not_ite1" | "not_ite2" | "contraction"
+            | "connective_def" | "and_simplify" | "or_simplify" | "not_simplify"
+            | "implies_simplify" | "equiv_simplify" | "bool_simplify" | "nary_elim" | "ac_simp"
+            | "reordering" | "or_intro" => Theory::Propositional,
+
+            "eq_reflexive" | "eq_transitive" | "eq_congruent" | "eq_congruent_pred"
+            | "distinct_elim" | "refl" | "trans" | "cong" | "ho_cong" | "eq_simplify" | "symm"
+            | "not_symm" | "eq_symmetric" | "ite_intro" | "ite_simplify" => Theory::Equality,
+
+            "div_simplify"
+            | "prod_simplify"
+            | "unary_minus_simplify"
+            | "minus_simplify"
+            | "sum_simplify"
+            | "comp_simplify"
+            | "mod_simplify"
+            | "lia_generic" => Theory::Arithmetic,
+            r if r.starts_with("la_") => Theory::Arithmetic,
+
+            "forall_inst" | "qnt_join" | "qnt_rm_unused" | "qnt_simplify" | "qnt_cnf" | "bind"
+            | "bind_let" | "let" | "onepoint" | "sko_ex" | "sko_forall" | "bfun_elim" => {
+                Theory::Quantifiers
+            }
+
+            r if r.starts_with("bitblast_") => Theory::BitVectors,
+            r if r.starts_with("concat_") || r.starts_with("string_") => Theory::Strings,
+            r if r.starts_with("re_") => Theory::Strings,
+            r if r.starts_with("arrays_") => Theory::Arrays,
+
+            "all_simplify" | "rare_rewrite" => Theory::Rewrites,
+
+            _ => Theory::Other,
+        }
+    }
+}
+
+impl fmt::Display for Theory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Theory::Propositional => "propositional",
+            Theory::Equality => "equality",
+            Theory::Arithmetic => "arithmetic",
+            Theory::Quantifiers => "quantifiers",
+            Theory::BitVectors => "bit-vectors",
+            Theory::Strings => "strings",
+            Theory::Arrays => "arrays",
+            Theory::Rewrites => "rewrites",
+            Theory::Other => "other",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[cfg(feature = "benchmarking")]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct StepId {
     pub(crate) file: Box<str>,
@@ -36,6 +145,7 @@ pub struct StepId {
     pub(crate) rule: Box<str>,
 }
 
+#[cfg(feature = "benchmarking")]
 impl fmt::Display for StepId {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}:{} ({})", self.file, self.step_id, self.rule)
@@ -56,6 +166,7 @@ pub struct RunMeasurement {
     pub assume_core: Duration,
 }
 
+#[cfg(feature = "benchmarking")]
 #[derive(Debug, Default, Clone)]
 pub struct OnlineBenchmarkResults {
     pub parsing: OnlineMetrics<RunId>,
@@ -67,6 +178,10 @@ pub struct OnlineBenchmarkResults {
     pub step_time: OnlineMetrics<StepId>,
     pub step_time_by_file: IndexMap<String, OnlineMetrics<StepId>>,
     pub step_time_by_rule: IndexMap<String, OnlineMetrics<StepId>>,
+    pub step_time_by_theory: IndexMap<Theory, OnlineMetrics<StepId>>,
+
+    /// The theory of each rule that was measured, so it is only computed once per rule.
+    rule_theories: IndexMap<String, Theory>,
 
     pub polyeq_time: OnlineMetrics<RunId>,
     pub polyeq_time_ratio: OnlineMetrics<RunId, f64>,
@@ -80,8 +195,13 @@ pub struct OnlineBenchmarkResults {
 
     pub is_holey: bool,
     pub had_error: bool,
+
+    /// The runs that failed, each with the message of the error that caused it, in the order in
+    /// which they were registered.
+    pub failures: Vec<(RunId, String)>,
 }
 
+#[cfg(feature = "benchmarking")]
 impl OnlineBenchmarkResults {
     pub fn new() -> Self {
         Default::default()
@@ -137,6 +257,11 @@ impl OnlineBenchmarkResults {
         &self.step_time_by_rule
     }
 
+    /// For each theory, the time spent checking each step that uses a rule from that theory.
+    pub fn step_time_by_theory(&self) -> &IndexMap<Theory, OnlineMetrics<StepId>> {
+        &self.step_time_by_theory
+    }
+
     /// Prints the benchmark results
     pub fn print(&self, sort_by_total: bool) {
         let [parsing, checking, elaborating, scheduling, accounted_for, total, assume_time, assume_core_time, polyeq_time] =
@@ -197,6 +322,15 @@ impl OnlineBenchmarkResults {
             }
         }
 
+        let mut data_by_theory: Vec<_> = self.step_time_by_theory().iter().collect();
+        data_by_theory.sort_by_key(|(_, m)| m.total());
+
+        println!("by theory:");
+        for (theory, data) in data_by_theory {
+            print!("    {: <18}", theory.to_string());
+            println!("{:#} ({} steps)", data, data.count());
+        }
+
         println!("worst cases:");
         if !self.step_time().is_empty() {
             let worst_step = self.step_time().max();
@@ -262,142 +396,39 @@ impl OnlineBenchmarkResults {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct InternedStepId {
-    pub(crate) file: Arc<str>,
-    pub(crate) step_id: Arc<str>,
-    pub(crate) rule: Arc<str>,
-}
-
-impl fmt::Display for InternedStepId {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}:{} ({})", self.file, self.step_id, self.rule)
-    }
-}
-
-type InternedRunId = (Arc<str>, usize);
-
-#[derive(Default)]
-pub struct CsvBenchmarkResults {
-    strings: IndexSet<Arc<str>>,
-    runs: IndexMap<InternedRunId, RunMeasurement>,
-    step_time_by_rule: IndexMap<Arc<str>, OfflineMetrics<InternedStepId>>,
-    is_holey: bool,
-    num_errors: usize,
-}
-
-impl CsvBenchmarkResults {
-    pub fn new() -> Self {
-        Default::default()
-    }
-
-    pub fn is_holey(&self) -> bool {
-        self.is_holey
-    }
-
-    pub fn num_errors(&self) -> usize {
-        self.num_errors
-    }
-
-    fn intern(&mut self, s: &str) -> Arc<str> {
-        match self.strings.get(s) {
-            Some(interned) => interned.clone(),
-            None => {
-                let result: Arc<str> = Arc::from(s);
-                self.strings.insert(result.clone());
-                result
-            }
-        }
-    }
-
-    pub fn write_csv(
-        self,
-        runs_dest: &mut dyn io::Write,
-        by_rule_dest: &mut dyn io::Write,
-    ) -> io::Result<()> {
-        Self::write_runs_csv(self.runs, runs_dest)?;
-        Self::write_by_rule_csv(self.step_time_by_rule, by_rule_dest)
-    }
-
-    fn write_runs_csv(
-        data: IndexMap<InternedRunId, RunMeasurement>,
-        dest: &mut dyn io::Write,
-    ) -> io::Result<()> {
-        writeln!(
-            dest,
-            "proof_file,run_id,parsing,checking,elaboration,total_accounted_for,\
-            total,polyeq,polyeq_ratio,assume,assume_ratio"
-        )?;
-
-        for (id, m) in data {
-            let total_accounted_for = m.parsing + m.checking;
-            let polyeq_ratio = m.polyeq.as_secs_f64() / m.checking.as_secs_f64();
-            let assume_ratio = m.assume.as_secs_f64() / m.checking.as_secs_f64();
-            writeln!(
-                dest,
-                "{},{},{},{},{},{},{},{},{},{},{}",
-                id.0,
-                id.1,
-                m.parsing.as_nanos(),
-                m.checking.as_nanos(),
-                m.elaboration.as_nanos(),
-                total_accounted_for.as_nanos(),
-                m.total.as_nanos(),
-                m.polyeq.as_nanos(),
-                polyeq_ratio,
-                m.assume.as_nanos(),
-                assume_ratio,
-            )?;
-        }
-
-        Ok(())
-    }
-
-    fn write_by_rule_csv(
-        data: IndexMap<Arc<str>, OfflineMetrics<InternedStepId>>,
-        dest: &mut dyn io::Write,
-    ) -> io::Result<()> {
-        let mut data: Vec<_> = data.into_iter().collect();
-        data.sort_unstable_by_key(|m| m.1.total());
-
-        writeln!(
-            dest,
-            "rule,count,total,mean,lower_whisker,first_quartile,median,third_quartile,upper_whisker"
-        )?;
-        for (rule, mut m) in data {
-            let [lower_whisker, first_quartile, median, third_quartile, upper_whisker] =
-                m.quartiles().map(|(_, t)| t.as_nanos());
-            writeln!(
-                dest,
-                "{},{},{},{},{},{},{},{},{}",
-                rule,
-                m.count(),
-                m.total().as_nanos(),
-                m.mean().as_nanos(),
-                lower_whisker,
-                first_quartile,
-                median,
-                third_quartile,
-                upper_whisker,
-            )?;
-        }
-        Ok(())
-    }
-}
-
 pub trait CollectResults {
     fn add_step_measurement(&mut self, file: &str, step_id: &str, rule: &str, time: Duration);
     fn add_assume_measurement(&mut self, file: &str, id: &str, is_easy: bool, time: Duration);
     fn add_polyeq_depth(&mut self, depth: usize);
     fn add_run_measurement(&mut self, id: &RunId, measurement: RunMeasurement);
     fn register_holey(&mut self);
-    fn register_error(&mut self, error: &crate::Error);
+
+    /// Registers that a run failed, either because the proof is invalid or because it couldn't be
+    /// checked, for example, because of a parser error.
+    fn register_error(&mut self, id: &RunId, error: &crate::Error);
 
     fn combine(a: Self, b: Self) -> Self
     where
         Self: Sized;
 }
 
+/// A collector that discards all results, used when checking without statistics.
+#[derive(Debug, Default)]
+pub struct NullResults;
+
+impl CollectResults for NullResults {
+    fn add_step_measurement(&mut self, _: &str, _: &str, _: &str, _: Duration) {}
+    fn add_assume_measurement(&mut self, _: &str, _: &str, _: bool, _: Duration) {}
+    fn add_polyeq_depth(&mut self, _: usize) {}
+    fn add_run_measurement(&mut self, _: &RunId, _: RunMeasurement) {}
+    fn register_holey(&mut self) {}
+    fn register_error(&mut self, _: &RunId, _: &crate::Error) {}
+    fn combine(_: Self, _: Self) -> Self {
+        Self
+    }
+}
+
+#[cfg(feature = "benchmarking")]
 impl CollectResults for OnlineBenchmarkResults {
     fn add_step_measurement(&mut self, file: &str, step_id: &str, rule: &str, time: Duration) {
         let file = file.to_owned();
@@ -412,6 +443,17 @@ impl CollectResults for OnlineBenchmarkResults {
             .entry(file)
             .or_default()
             .add_sample(&id, time);
+        let theory = match self.rule_theories.get(&rule) {
+            Some(&theory) => theory,
+            None => *self
+                .rule_theories
+                .entry(rule.clone())
+                .or_insert_with(|| Theory::of_rule(&rule)),
+        };
+        self.step_time_by_theory
+            .entry(theory)
+            .or_default()
+            .add_sample(&id, time);
         self.step_time_by_rule
             .entry(rule)
             .or_default()
@@ -468,6 +510,8 @@ impl CollectResults for OnlineBenchmarkResults {
             step_time: a.step_time.combine(b.step_time),
             step_time_by_file: combine_map(a.step_time_by_file, b.step_time_by_file),
             step_time_by_rule: combine_map(a.step_time_by_rule, b.step_time_by_rule),
+            step_time_by_theory: combine_map(a.step_time_by_theory, b.step_time_by_theory),
+            rule_theories: a.rule_theories.into_iter().chain(b.rule_theories).collect(),
 
             polyeq_time: a.polyeq_time.combine(b.polyeq_time),
             polyeq_time_ratio: a.polyeq_time_ratio.combine(b.polyeq_time_ratio),
@@ -480,6 +524,7 @@ impl CollectResults for OnlineBenchmarkResults {
             num_easy_assumes: a.num_easy_assumes + b.num_easy_assumes,
             is_holey: a.is_holey || b.is_holey,
             had_error: a.had_error || b.had_error,
+            failures: [a.failures, b.failures].concat(),
         }
     }
 
@@ -487,50 +532,8 @@ impl CollectResults for OnlineBenchmarkResults {
         self.is_holey = true;
     }
 
-    fn register_error(&mut self, _: &crate::Error) {
+    fn register_error(&mut self, id: &RunId, error: &crate::Error) {
         self.had_error = true;
-    }
-}
-
-impl CollectResults for CsvBenchmarkResults {
-    fn add_step_measurement(&mut self, file: &str, step_id: &str, rule: &str, time: Duration) {
-        let rule = self.intern(rule);
-        let id = InternedStepId {
-            file: self.intern(file),
-            step_id: self.intern(step_id),
-            rule: rule.clone(),
-        };
-        self.step_time_by_rule
-            .entry(rule)
-            .or_default()
-            .add_sample(&id, time);
-    }
-
-    fn add_assume_measurement(&mut self, file: &str, id: &str, _: bool, time: Duration) {
-        self.add_step_measurement(file, id, "assume", time);
-    }
-
-    fn add_polyeq_depth(&mut self, _: usize) {}
-
-    fn add_run_measurement(&mut self, (file, i): &RunId, measurement: RunMeasurement) {
-        let id = (self.intern(file), *i);
-        self.runs.insert(id, measurement);
-    }
-
-    fn register_holey(&mut self) {
-        self.is_holey = true;
-    }
-
-    fn register_error(&mut self, _: &crate::Error) {
-        self.num_errors += 1;
-    }
-
-    fn combine(mut a: Self, b: Self) -> Self {
-        // This assumes that the same run never appears in both `a` and `b`. This should be the case
-        // in benchmarks anyway
-        a.runs.extend(b.runs);
-        a.step_time_by_rule = combine_map(a.step_time_by_rule, b.step_time_by_rule);
-        a.num_errors += b.num_errors;
-        a
+        self.failures.push((id.clone(), error.to_string()));
     }
 }
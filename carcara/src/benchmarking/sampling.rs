@@ -0,0 +1,122 @@
+use super::CollectResults;
+use crate::ast::{Proof, ProofCommand};
+use indexmap::IndexMap;
+use std::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    thread,
+    time::Duration,
+};
+
+/// The value stored in a `StepProbe` while no command is being checked.
+const IDLE: usize = usize::MAX;
+
+/// A shared slot in which the checker publishes which proof command it is currently checking, so
+/// it can be read by a sampling thread.
+///
+/// Publishing a command is a single relaxed atomic store, which is much cheaper than measuring the
+/// time taken by each step. The commands are identified by their index in the order they are
+/// visited by `Proof::iter`.
+#[derive(Debug)]
+pub struct StepProbe(AtomicUsize);
+
+impl Default for StepProbe {
+    fn default() -> Self {
+        Self(AtomicUsize::new(IDLE))
+    }
+}
+
+impl StepProbe {
+    pub(crate) fn enter(&self, index: usize) {
+        self.0.store(index, Ordering::Relaxed);
+    }
+
+    fn current(&self) -> Option<usize> {
+        match self.0.load(Ordering::Relaxed) {
+            IDLE => None,
+            i => Some(i),
+        }
+    }
+}
+
+/// The samples collected while running a function with `run_sampled`.
+#[derive(Debug, Clone)]
+pub struct StepSamples {
+    interval: Duration,
+    counts: IndexMap<usize, usize>,
+    num_samples: usize,
+}
+
+impl StepSamples {
+    /// Returns the interval between samples.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Returns the total number of samples taken, including the ones taken while no command was
+    /// being checked.
+    pub fn num_samples(&self) -> usize {
+        self.num_samples
+    }
+
+    /// Returns the number of samples taken while the command with the given index was being
+    /// checked.
+    pub fn samples_of(&self, index: usize) -> usize {
+        self.counts.get(&index).copied().unwrap_or(0)
+    }
+
+    /// Adds the sampled commands of `proof` to `results`, as if they were measured directly. The
+    /// time of each command is estimated as the number of samples it received multiplied by the
+    /// sampling interval. Commands that received no samples are not reported.
+    ///
+    /// The given proof must be the same that was checked while sampling, since the samples refer to
+    /// commands by their index.
+    pub fn report<CR: CollectResults>(&self, proof: &Proof, file: &str, results: &mut CR) {
+        for (i, command) in proof.iter().enumerate() {
+            let samples = self.samples_of(i);
+            if samples == 0 {
+                continue;
+            }
+            let time = self.interval * samples as u32;
+            match command {
+                ProofCommand::Assume { id, .. } => {
+                    results.add_assume_measurement(file, id, false, time);
+                }
+                ProofCommand::Step(step) => {
+                    results.add_step_measurement(file, &step.id, &step.rule, time);
+                }
+                ProofCommand::Subproof(_) => {
+                    results.add_step_measurement(file, command.id(), "anchor", time);
+                }
+            }
+        }
+    }
+}
+
+/// Runs `f` while a separate thread samples, every `interval`, which proof command was published
+/// to the given probe. Returns the result of `f` and the collected samples.
+///
+/// The probe should be passed to the checker through `CheckerStatistics::probe`.
+pub fn run_sampled<T, F>(probe: &StepProbe, interval: Duration, f: F) -> (T, StepSamples)
+where
+    F: FnOnce() -> T,
+{
+    let done = AtomicBool::new(false);
+    thread::scope(|s| {
+        let sampler = s.spawn(|| {
+            let mut counts: IndexMap<usize, usize> = IndexMap::new();
+            let mut num_samples = 0;
+            while !done.load(Ordering::Relaxed) {
+                thread::sleep(interval);
+                num_samples += 1;
+                if let Some(i) = probe.current() {
+                    *counts.entry(i).or_default() += 1;
+                }
+            }
+            StepSamples { interval, counts, num_samples }
+        });
+        let result = f();
+        probe.0.store(IDLE, Ordering::Relaxed);
+        done.store(true, Ordering::Relaxed);
+        (result, sampler.join().unwrap())
+    })
+}
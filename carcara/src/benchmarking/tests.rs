@@ -1,4 +1,7 @@
-use super::{Duration, Metrics, MetricsUnit, OfflineMetrics, OnlineMetrics};
+use super::{
+    run_sampled, Duration, Metrics, MetricsUnit, OfflineMetrics, OnlineBenchmarkResults,
+    OnlineMetrics, StepProbe, Theory,
+};
 use rand::{prelude::ThreadRng, Rng};
 use std::fmt;
 
@@ -31,10 +34,20 @@ impl IsClose for usize {
     }
 }
 
+impl IsClose for u64 {
+    fn is_close(&self, other: Self) -> bool {
+        *self == other
+    }
+}
+
 fn usize_generator(max_value: usize) -> impl Fn(&mut ThreadRng) -> usize {
     move |rng| rng.gen_range(0..max_value)
 }
 
+fn u64_generator(max_value: u64) -> impl Fn(&mut ThreadRng) -> u64 {
+    move |rng| rng.gen_range(0..max_value)
+}
+
 fn duration_generator(max_value: u64) -> impl Fn(&mut ThreadRng) -> Duration {
     move |rng| Duration::from_nanos(rng.gen_range(0..max_value))
 }
@@ -86,6 +99,10 @@ fn test_metrics_add() {
     run_tests(1_000_000, usize_generator(10));
     run_tests(1_000_000, usize_generator(100));
     run_tests(1_000_000, usize_generator(100_000));
+
+    run_tests(100, u64_generator(1_000));
+    run_tests(10_000, u64_generator(1_000));
+    run_tests(1_000_000, u64_generator(100_000));
 }
 
 #[test]
@@ -135,3 +152,95 @@ fn test_metrics_combine() {
     // `Metrics::add` with that entry, which makes the numerical error small again
     run_tests(10_000, 1, 1.0e-6);
 }
+
+#[test]
+fn test_theory_of_rule() {
+    let cases = [
+        ("resolution", Theory::Propositional),
+        ("and_pos", Theory::Propositional),
+        ("trans", Theory::Equality),
+        ("eq_congruent", Theory::Equality),
+        ("la_generic", Theory::Arithmetic),
+        ("lia_generic", Theory::Arithmetic),
+        ("sum_simplify", Theory::Arithmetic),
+        ("forall_inst", Theory::Quantifiers),
+        ("sko_ex", Theory::Quantifiers),
+        ("bitblast_bvadd", Theory::BitVectors),
+        ("ite_simplify", Theory::Equality),
+        ("concat_eq", Theory::Strings),
+        ("string_decompose", Theory::Strings),
+        ("re_unfold_pos", Theory::Strings),
+        ("arrays_read_over_write", Theory::Arrays),
+        ("all_simplify", Theory::Rewrites),
+        ("rare_rewrite", Theory::Rewrites),
+        ("all_simplify(sum_simplify)", Theory::Arithmetic),
+        ("all_simplify(evaluate)", Theory::Rewrites),
+        ("assume", Theory::Other),
+        ("hole", Theory::Other),
+        ("unknown_rule", Theory::Other),
+    ];
+    for (rule, expected) in cases {
+        assert_eq!(expected, Theory::of_rule(rule), "rule: {}", rule);
+    }
+}
+
+#[test]
+fn test_sampling() {
+    let mut pool = crate::ast::PrimitivePool::new();
+    let proof = crate::parser::tests::parse_proof(
+        &mut pool,
+        "(assume h1 false)
+        (step t2 (cl) :rule false :premises (h1))",
+    );
+
+    let probe = StepProbe::default();
+    let (_, samples) = run_sampled(&probe, Duration::from_micros(100), || {
+        probe.enter(1);
+        std::thread::sleep(Duration::from_millis(20));
+    });
+    assert!(samples.num_samples() > 0);
+    assert_eq!(0, samples.samples_of(0));
+    assert!(samples.samples_of(1) > 0);
+
+    let mut results = OnlineBenchmarkResults::new();
+    samples.report(&proof, "file", &mut results);
+    let by_rule = results.step_time_by_rule();
+    assert!(!by_rule.contains_key("assume"));
+    assert_eq!(1, by_rule["false"].count());
+    assert_eq!(
+        samples.interval() * samples.samples_of(1) as u32,
+        by_rule["false"].total()
+    );
+}
+
+#[test]
+fn test_register_error() {
+    use super::CollectResults;
+
+    let error = crate::Error::DoesNotReachEmptyClause;
+    let mut a = OnlineBenchmarkResults::new();
+    a.register_error(&("a.alethe".to_owned(), 0), &error);
+    let mut b = OnlineBenchmarkResults::new();
+    b.register_error(&("b.alethe".to_owned(), 1), &error);
+    let combined = OnlineBenchmarkResults::combine(a, b);
+    assert!(combined.had_error);
+    let failed: Vec<_> = combined.failures.iter().map(|(id, _)| id.clone()).collect();
+    assert_eq!(
+        vec![("a.alethe".to_owned(), 0), ("b.alethe".to_owned(), 1)],
+        failed
+    );
+    assert_eq!(error.to_string(), combined.failures[0].1);
+}
+
+#[cfg(feature = "exporters")]
+#[test]
+fn test_csv_register_error() {
+    use super::{CollectResults, CsvBenchmarkResults};
+
+    let error = crate::Error::DoesNotReachEmptyClause;
+    let mut csv = CsvBenchmarkResults::new();
+    csv.register_error(&("a.alethe".to_owned(), 2), &error);
+    assert_eq!(1, csv.num_errors());
+    let failures: Vec<_> = csv.failures().map(|(file, i, _)| (file, i)).collect();
+    assert_eq!(vec![("a.alethe", 2)], failures);
+}
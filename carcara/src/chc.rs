@@ -0,0 +1,191 @@
+//! Translating proofs into systems of constrained Horn clauses (CHCs).
+//!
+//! Each command in the root proof is represented by an uninterpreted predicate over the constants
+//! of the problem. A step becomes a Horn clause whose body contains the predicates of its premises
+//! and whose head is the predicate of the step, and an `assume` command becomes a Horn clause whose
+//! body is the assumed term. A step that derives the empty clause becomes a query. If the proof is
+//! valid, interpreting each predicate as the clause derived by its command satisfies all of these
+//! Horn clauses, so this interpretation is a model of the system, which can be validated by CHC
+//! solvers and related tooling.
+
+use crate::ast::{printer::quote_symbol, *};
+use indexmap::IndexSet;
+use std::fmt::Write;
+
+/// Returns the name of the predicate that represents the command with the given id.
+fn predicate_name(id: &str) -> String {
+    quote_symbol(&format!("step!{}", id)).into_owned()
+}
+
+/// Writes the disjunction of the literals in a clause. The empty clause is written as `false`, and
+/// a unit clause is written as its only literal. Like all terms in the CHC system, the literals are
+/// written without term sharing, since `:named` annotations are not part of the `HORN` logic.
+fn clause_to_string(clause: &[Rc<Term>]) -> String {
+    match clause {
+        [] => "false".to_owned(),
+        [l] => format!("{:#}", l),
+        _ => {
+            let literals: Vec<_> = clause.iter().map(|l| format!("{:#}", l)).collect();
+            format!("(or {})", literals.join(" "))
+        }
+    }
+}
+
+/// Collects the indices of the commands in the root proof that are used as premises by a command.
+/// For subproofs, these are the premises in the root proof of all the commands inside it.
+fn root_premises(command: &ProofCommand, result: &mut IndexSet<usize>) {
+    match command {
+        ProofCommand::Assume { .. } => (),
+        ProofCommand::Step(s) => {
+            let premises = s.premises.iter().chain(&s.discharge);
+            result.extend(premises.filter(|(depth, _)| *depth == 0).map(|&(_, i)| i));
+        }
+        ProofCommand::Subproof(s) => {
+            for c in &s.commands {
+                root_premises(c, result);
+            }
+        }
+    }
+}
+
+/// Writes the CHC system that represents a proof, in the SMT-LIB `HORN` logic, and its model, as a
+/// list of `define-fun` commands. The constants of the problem become universally quantified
+/// variables in every Horn clause, and all other declarations are kept unchanged.
+pub fn write_chc_system(prelude: &ProblemPrelude, proof: &Proof) -> (String, String) {
+    let (constants, functions): (Vec<_>, Vec<_>) = prelude
+        .function_declarations
+        .iter()
+        .cloned()
+        .partition(|(_, sort)| !matches!(sort.as_sort(), Some(Sort::Function(_))));
+    let horn_prelude = ProblemPrelude {
+        function_declarations: functions,
+        logic: Some("HORN".to_owned()),
+        ..prelude.clone()
+    };
+
+    let params = constants
+        .iter()
+        .map(|(name, sort)| format!("({} {})", quote_symbol(name), sort))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let param_sorts = constants
+        .iter()
+        .map(|(_, sort)| sort.to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let apply = |id: &str| {
+        if constants.is_empty() {
+            predicate_name(id)
+        } else {
+            let args: Vec<_> = constants.iter().map(|(n, _)| quote_symbol(n)).collect();
+            format!("({} {})", predicate_name(id), args.join(" "))
+        }
+    };
+    let quantify = |body: String| {
+        if constants.is_empty() {
+            body
+        } else {
+            format!("(forall ({}) {})", params, body)
+        }
+    };
+
+    let mut system = String::new();
+    let mut model = String::new();
+    write!(&mut system, "{}", horn_prelude).unwrap();
+    for command in &proof.commands {
+        writeln!(
+            &mut system,
+            "(declare-fun {} ({}) Bool)",
+            predicate_name(command.id()),
+            param_sorts
+        )
+        .unwrap();
+        writeln!(
+            &mut model,
+            "(define-fun {} ({}) Bool {})",
+            predicate_name(command.id()),
+            params,
+            clause_to_string(command.clause())
+        )
+        .unwrap();
+    }
+
+    for command in &proof.commands {
+        let head = apply(command.id());
+        let clause = match command {
+            ProofCommand::Assume { term, .. } => format!("(=> {:#} {})", term, head),
+            _ => {
+                let mut premises = IndexSet::new();
+                root_premises(command, &mut premises);
+                let body: Vec<_> = premises
+                    .iter()
+                    .map(|&i| apply(proof.commands[i].id()))
+                    .collect();
+                match body.as_slice() {
+                    [] => head,
+                    [p] => format!("(=> {} {})", p, head),
+                    _ => format!("(=> (and {}) {})", body.join(" "), head),
+                }
+            }
+        };
+        writeln!(&mut system, "(assert {})", quantify(clause)).unwrap();
+        if command.clause().is_empty() {
+            let query = format!("(=> {} false)", apply(command.id()));
+            writeln!(&mut system, "(assert {})", quantify(query)).unwrap();
+        }
+    }
+    writeln!(&mut system, "(check-sat)").unwrap();
+    (system, model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    fn run(problem: &str, proof: &str) -> (String, String) {
+        let (prelude, proof, _) =
+            parser::parse_instance(problem.as_bytes(), proof.as_bytes(), parser::Config::new())
+                .unwrap();
+        write_chc_system(&prelude, &proof)
+    }
+
+    #[test]
+    fn test_write_chc_system() {
+        let (system, model) = run(
+            "(declare-fun f (Int) Int) (declare-fun a () Int)
+            (assert (not (= (f a) (f a))))",
+            "(assume h1 (not (= (f a) (f a))))
+            (step t2 (cl (= (f a) (f a))) :rule refl)
+            (step t3 (cl) :rule resolution :premises (h1 t2))",
+        );
+        let expected = "(set-logic HORN)
+(declare-fun f (Int) Int)
+(declare-fun step!h1 (Int) Bool)
+(declare-fun step!t2 (Int) Bool)
+(declare-fun step!t3 (Int) Bool)
+(assert (forall ((a Int)) (=> (not (= (f a) (f a))) (step!h1 a))))
+(assert (forall ((a Int)) (step!t2 a)))
+(assert (forall ((a Int)) (=> (and (step!h1 a) (step!t2 a)) (step!t3 a))))
+(assert (forall ((a Int)) (=> (step!t3 a) false)))
+(check-sat)
+";
+        assert_eq!(system, expected);
+        assert!(model.contains("(define-fun step!t2 ((a Int)) Bool (= (f a) (f a)))\n"));
+
+        // The premises of a subproof are the premises in the root proof of the commands inside it.
+        // Problems without constants result in nullary predicates
+        let (system, _) = run(
+            "(assert (not (forall ((x Int)) (= x x))))",
+            "(assume h1 (not (forall ((x Int)) (= x x))))
+            (step t2 (cl (= 1 1)) :rule refl)
+            (anchor :step t3 :args ((x Int)))
+            (step t3.t1 (cl (= x x)) :rule resolution :premises (t2 t2))
+            (step t3 (cl (= (forall ((x Int)) (= x x)) true)) :rule hole)
+            (step t4 (cl) :rule hole :premises (h1 t3))",
+        );
+        assert!(system.contains("(declare-fun step!t3 () Bool)\n"));
+        assert!(system.contains("(assert (=> step!t2 step!t3))\n"));
+        assert!(system.contains("(assert (=> step!t4 false))\n"));
+    }
+}
@@ -0,0 +1,93 @@
+use super::{error::CheckerError, UnknownRuleReport};
+use indexmap::IndexMap;
+
+/// The number of steps of a rule with each outcome, in a coverage report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RuleCounts {
+    /// The number of steps that were checked and found valid.
+    pub checked: usize,
+
+    /// The number of steps that were accepted without being checked, like `hole` steps or steps
+    /// skipped using the `only_steps` option.
+    pub skipped: usize,
+
+    /// The number of steps that were found invalid.
+    pub failed: usize,
+
+    /// The number of steps whose rule is not implemented by Carcara.
+    pub unimplemented: usize,
+}
+
+impl RuleCounts {
+    pub fn total(&self) -> usize {
+        self.checked + self.skipped + self.failed + self.unimplemented
+    }
+}
+
+/// A report of how many steps of each rule were checked, skipped or failed, over one or more
+/// proofs. See [`ProofChecker::check_coverage`](super::ProofChecker::check_coverage).
+#[derive(Debug, Clone, Default)]
+pub struct RuleCoverage {
+    /// The counts for each rule, indexed by the rule name as written in the proofs. `assume`
+    /// commands are counted under the `assume` rule.
+    pub rules: IndexMap<String, RuleCounts>,
+
+    /// The steps whose rule is not implemented by Carcara, with a few example step ids for each
+    /// rule. When reports of different proofs are combined, the examples may come from any of
+    /// them.
+    pub unknown_rules: UnknownRuleReport,
+}
+
+impl RuleCoverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(super) fn register(
+        &mut self,
+        rule: &str,
+        result: &Result<(), CheckerError>,
+        skipped: bool,
+    ) {
+        let counts = self.rules.entry(rule.to_owned()).or_default();
+        match result {
+            Err(CheckerError::UnknownRule) => counts.unimplemented += 1,
+            Err(_) => counts.failed += 1,
+            Ok(()) if skipped => counts.skipped += 1,
+            Ok(()) => counts.checked += 1,
+        }
+    }
+
+    /// Adds the counts of another report to this one.
+    pub fn combine(&mut self, other: &Self) {
+        for (rule, other) in &other.rules {
+            let counts = self.rules.entry(rule.clone()).or_default();
+            counts.checked += other.checked;
+            counts.skipped += other.skipped;
+            counts.failed += other.failed;
+            counts.unimplemented += other.unimplemented;
+        }
+        self.unknown_rules.combine(&other.unknown_rules);
+    }
+
+    /// Returns the counts for each rule, sorted by the total number of steps, in descending order.
+    pub fn by_frequency(&self) -> Vec<(&str, RuleCounts)> {
+        let mut result: Vec<_> = self.rules.iter().map(|(r, c)| (r.as_str(), *c)).collect();
+        result
+            .sort_by(|(a_rule, a), (b_rule, b)| b.total().cmp(&a.total()).then(a_rule.cmp(b_rule)));
+        result
+    }
+
+    /// Returns the rules that are not implemented, with the number of steps that use each of them,
+    /// sorted by the number of steps, in descending order.
+    pub fn unimplemented(&self) -> Vec<(&str, usize)> {
+        let mut result: Vec<_> = self
+            .rules
+            .iter()
+            .filter(|(_, c)| c.unimplemented > 0)
+            .map(|(r, c)| (r.as_str(), c.unimplemented))
+            .collect();
+        result.sort_by(|(a_rule, a), (b_rule, b)| b.cmp(a).then(a_rule.cmp(b_rule)));
+        result
+    }
+}
@@ -1,12 +1,50 @@
 use crate::{
+    arithmetic::LinearComb,
     ast::*,
-    checker::rules::linear_arithmetic::LinearComb,
     utils::{Range, TypeName},
 };
 use rug::Rational;
 use std::{fmt, io};
 use thiserror::Error;
 
+/// The kinds of limits that can be imposed on the size of a step. See [`crate::StepLimits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitKind {
+    ClauseLength,
+    TermNodes,
+}
+
+impl fmt::Display for LimitKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LimitKind::ClauseLength => write!(f, "literals in the conclusion clause"),
+            LimitKind::TermNodes => write!(f, "distinct term nodes"),
+        }
+    }
+}
+
+/// The reasons why a term may not be allowed in the logic declared by the problem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogicViolation {
+    Quantifier,
+    UninterpretedFunction,
+    NonLinear,
+    Sort(Sort),
+}
+
+impl fmt::Display for LogicViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LogicViolation::Quantifier => write!(f, "the logic is quantifier-free"),
+            LogicViolation::UninterpretedFunction => {
+                write!(f, "the logic has no uninterpreted functions")
+            }
+            LogicViolation::NonLinear => write!(f, "the logic only allows linear arithmetic"),
+            LogicViolation::Sort(s) => write!(f, "the logic does not include the sort '{}'", s),
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum CheckerError {
     #[error("unspecified error")]
@@ -15,7 +53,7 @@ pub enum CheckerError {
     #[error(transparent)]
     Substitution(#[from] SubstitutionError),
 
-    #[error("could not match term to any of the original problem premises: {0}")]
+    #[error("could not match term to any of the original problem premises: {}", DisplayPretty(.0))]
     Assume(Rc<Term>),
 
     // Rule specific errors
@@ -37,38 +75,50 @@ pub enum CheckerError {
     #[error(transparent)]
     Subproof(#[from] SubproofError),
 
-    #[error("reflexivity failed with terms '{0}' and '{1}'")]
+    #[error("reflexivity failed with terms '{}' and '{}'", DisplayPretty(.0), DisplayPretty(.1))]
     ReflexivityFailed(Rc<Term>, Rc<Term>),
 
-    #[error("simplifying '{original}' resulted in '{result}', expected result to be '{target}'")]
+    #[error(
+        "simplifying '{}' resulted in '{}', expected result to be '{}'",
+        DisplayPretty(.original), DisplayPretty(.result), DisplayPretty(.target)
+    )]
     SimplificationFailed {
         original: Rc<Term>,
         result: Rc<Term>,
         target: Rc<Term>,
     },
 
-    #[error("encountered cycle when simplifying term: '{0}'")]
+    #[error("encountered cycle when simplifying term: '{}'", DisplayPretty(.0))]
     CycleInSimplification(Rc<Term>),
 
-    #[error("'{0}' is not a valid simplification result for this rule")]
+    #[error(
+        "could not show that '{}' and '{}' are equal using any simplification rule",
+        DisplayPretty(.0), DisplayPretty(.1)
+    )]
+    AllSimplifyFailed(Rc<Term>, Rc<Term>),
+
+    #[error("'{}' is not a valid simplification result for this rule", DisplayPretty(.0))]
     SumProdSimplifyInvalidConclusion(Rc<Term>),
 
-    #[error("term '{0}' is not a connective")]
+    #[error("term '{}' is not a connective", DisplayPretty(.0))]
     TermIsNotConnective(Rc<Term>),
 
-    #[error("term '{0}' does not have the correct form for `ite_intro`")]
+    #[error("term '{}' does not have the correct form for `ite_intro`", DisplayPretty(.0))]
     IsNotValidIteIntro(Rc<Term>),
 
-    #[error("broken transitivity chain: can't prove '(= {0} {1})'")]
+    #[error(
+        "broken transitivity chain: can't prove '(= {} {})'",
+        DisplayPretty(.0), DisplayPretty(.1)
+    )]
     BrokenTransitivityChain(Rc<Term>, Rc<Term>),
 
-    #[error("term '{0}' is missing in conclusion clause")]
+    #[error("term '{}' is missing in conclusion clause", DisplayPretty(.0))]
     ContractionMissingTerm(Rc<Term>),
 
-    #[error("term '{0}' was not expected in conclusion clause")]
+    #[error("term '{}' was not expected in conclusion clause", DisplayPretty(.0))]
     ContractionExtraTerm(Rc<Term>),
 
-    #[error("term '{0}' is not a valid n-ary operation")]
+    #[error("term '{}' is not a valid n-ary operation", DisplayPretty(.0))]
     NotValidNaryTerm(Rc<Term>),
 
     // General errors
@@ -84,51 +134,69 @@ pub enum CheckerError {
     #[error("expected {1} terms in '{0}' term, got {2}")]
     WrongNumberOfTermsInOp(Operator, Range, usize),
 
-    #[error("expected term '{1}' to appear in '{0}' term")]
+    #[error("expected term '{}' to appear in '{0}' term", DisplayPretty(.1))]
     TermDoesntApperInOp(Operator, Rc<Term>),
 
     #[error("expected {1} terms in clause of step '{0}', got {2}")]
     WrongLengthOfPremiseClause(String, Range, usize),
 
-    #[error("term '{1}' is of the wrong form, expected '{0}'")]
+    #[error("term '{}' is of the wrong form, expected '{0}'", DisplayPretty(.1))]
     TermOfWrongForm(&'static str, Rc<Term>),
 
-    #[error("expected term '{0}' to be boolean constant '{1}'")]
+    #[error("expected term '{0}' to be boolean constant '{}'", DisplayPretty(.1))]
     ExpectedBoolConstant(bool, Rc<Term>),
 
-    #[error("expected term '{0}' to be a boolean constant")]
+    #[error("expected term '{}' to be a boolean constant", DisplayPretty(.0))]
     ExpectedAnyBoolConstant(Rc<Term>),
 
-    #[error("expected term '{1}' to be numerical constant {:?}", .0.to_f64())]
+    #[error(
+        "expected term '{}' to be numerical constant {:?}",
+        DisplayPretty(.1), .0.to_f64()
+    )]
     ExpectedNumber(Rational, Rc<Term>),
 
-    #[error("expected term '{0}' to be a numerical constant")]
+    #[error("expected term '{}' to be a numerical constant", DisplayPretty(.0))]
     ExpectedAnyNumber(Rc<Term>),
 
-    #[error("expected term '{0}' to be an integer constant")]
+    #[error("expected term '{}' to be an integer constant", DisplayPretty(.0))]
     ExpectedInteger(Rc<Term>),
 
-    #[error("expected operation term, got '{0}'")]
+    #[error("expected operation term, got '{}'", DisplayPretty(.0))]
     ExpectedOperationTerm(Rc<Term>),
 
-    #[error("expected quantifier term, got '{0}'")]
+    #[error("expected quantifier term, got '{}'", DisplayPretty(.0))]
     ExpectedQuantifierTerm(Rc<Term>),
 
-    #[error("expected 'let' term, got '{0}'")]
+    #[error("expected 'let' term, got '{}'", DisplayPretty(.0))]
     ExpectedLetTerm(Rc<Term>),
 
-    #[error("expected term style argument, got assign style argument: '(:= {0} {1})'")]
-    ExpectedTermStyleArg(String, Rc<Term>),
+    #[error(
+        "expected term style argument, got assign style argument: '(:= {0} {})'",
+        DisplayPretty(.1)
+    )]
+    ExpectedTermStyleArg(Symbol, Rc<Term>),
 
-    #[error("expected assign style '(:= ...)' argument, got term style argument: '{0}'")]
+    #[error(
+        "expected assign style '(:= ...)' argument, got term style argument: '{}'",
+        DisplayPretty(.0)
+    )]
     ExpectedAssignStyleArg(Rc<Term>),
 
+    #[error("expected term style or assign style argument, got '(@list ...)' argument")]
+    UnexpectedListArg,
+
     #[error("this rule can only be used in the last step of a subproof")]
     MustBeLastStepInSubproof,
 
     #[error("division or modulo by zero")]
     DivOrModByZero,
 
+    #[error("step exceeds the limit of {1} {0}")]
+    LimitExceeded(LimitKind, usize),
+
+    #[error("term '{}' is not allowed in the logic '{0}': {2}", DisplayPretty(.1))]
+    OutsideLogic(String, Rc<Term>, LogicViolation),
+
     // Equality errors
     #[error(transparent)]
     TermEquality(#[from] EqualityError<Rc<Term>>),
@@ -146,10 +214,16 @@ pub enum CheckerError {
 /// Errors in which we expected two things to be equal but they weren't.
 #[derive(Debug, Error)]
 pub enum EqualityError<T: TypeName> {
-    #[error("expected {}s to be equal: '{0}' and '{1}'", T::NAME)]
+    #[error(
+        "expected {}s to be equal: '{}' and '{}'",
+        T::NAME, DisplayPretty(.0), DisplayPretty(.1)
+    )]
     ExpectedEqual(T, T),
 
-    #[error("expected {} '{got}' to be '{expected}'", T::NAME)]
+    #[error(
+        "expected {} '{}' to be '{}'",
+        T::NAME, DisplayPretty(.got), DisplayPretty(.expected)
+    )]
     ExpectedToBe { expected: T, got: T },
 }
 
@@ -158,19 +232,30 @@ pub enum ResolutionError {
     #[error("couldn't find tautology in clause")]
     TautologyFailed,
 
-    #[error("pivot was not eliminated: '{0}'")]
+    #[error("pivot was not eliminated: '{}'", DisplayPretty(.0))]
     RemainingPivot(Rc<Term>),
 
-    #[error("term in conclusion was not produced by resolution: '{0}'")]
+    #[error("term in conclusion was not produced by resolution: '{}'", DisplayPretty(.0))]
     ExtraTermInConclusion(Rc<Term>),
 
-    #[error("term produced by resolution is missing in conclusion: '{0}'")]
+    #[error("term produced by resolution is missing in conclusion: '{}'", DisplayPretty(.0))]
     MissingTermInConclusion(Rc<Term>),
 
-    #[error("pivot was not found in clause: '{0}'")]
+    #[error("pivot was not found in clause: '{}'", DisplayPretty(.0))]
     PivotNotFound(Rc<Term>),
 }
 
+/// A wrapper struct that implements `fmt::Display` for values in error messages. Terms are printed
+/// using a [`PrettyPrinter`], so large terms are broken into multiple lines and their repeated
+/// subterms are abbreviated, instead of being printed in a single unreadable line.
+struct DisplayPretty<'a, T>(&'a T);
+
+impl<'a, T: TypeName> fmt::Display for DisplayPretty<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt_in_error(f)
+    }
+}
+
 struct DisplayIndexedOp<'a>(&'a IndexedOperator, &'a Vec<Constant>);
 
 impl<'a> fmt::Display for DisplayIndexedOp<'a> {
@@ -188,19 +273,23 @@ pub enum CongruenceError {
     #[error("too many premises")]
     TooManyPremises,
 
-    #[error("no premise to justify equality of arguments '{0}' and '{1}'")]
+    #[error(
+        "no premise to justify equality of arguments '{}' and '{}'",
+        DisplayPretty(.0), DisplayPretty(.1)
+    )]
     MissingPremise(Rc<Term>, Rc<Term>),
 
     #[error(
         "premise '(= {} {})' doesn't justify conclusion arguments '{}' and '{}'",
-        .premise.0, .premise.1, .args.0, .args.1
+        DisplayPretty(&.premise.0), DisplayPretty(&.premise.1),
+        DisplayPretty(&.args.0), DisplayPretty(&.args.1)
     )]
     PremiseDoesntJustifyArgs {
         args: (Rc<Term>, Rc<Term>),
         premise: (Rc<Term>, Rc<Term>),
     },
 
-    #[error("functions don't match: '{0}' and '{1}'")]
+    #[error("functions don't match: '{}' and '{}'", DisplayPretty(.0), DisplayPretty(.1))]
     DifferentFunctions(Rc<Term>, Rc<Term>),
 
     #[error("operators don't match: '{0}' and '{1}'")]
@@ -209,7 +298,7 @@ pub enum CongruenceError {
     #[error("different numbers of arguments: {0} and {1}")]
     DifferentNumberOfArguments(usize, usize),
 
-    #[error("term is not an application or operation: '{0}'")]
+    #[error("term is not an application or operation: '{}'", DisplayPretty(.0))]
     NotApplicationOrOperation(Rc<Term>),
 
     #[error(
@@ -226,10 +315,17 @@ pub enum CongruenceError {
 #[derive(Debug, Error)]
 pub enum QuantifierError {
     #[error("argument doesn't match any binding: '{0}'")]
-    NoBindingMatchesArg(String),
+    NoBindingMatchesArg(Symbol),
 
     #[error("no argument was given for binding '{0}'")]
-    NoArgGivenForBinding(String),
+    NoArgGivenForBinding(Symbol),
+
+    #[error("argument for binding '{var}' has sort '{got}', but binding has sort '{expected}'")]
+    ArgSortMismatch {
+        var: Symbol,
+        expected: Rc<Term>,
+        got: Rc<Term>,
+    },
 
     #[error("union of bindings '{left_outer}' and '{left_inner}' does not equal '{right}'")]
     JoinFailed {
@@ -239,40 +335,88 @@ pub enum QuantifierError {
     },
 
     #[error("unknown binding introduced in right-hand side: '{0}'")]
-    CnfNewBindingIntroduced(String),
+    CnfNewBindingIntroduced(Symbol),
 
     #[error("binding is missing in right-hand side: '{0}'")]
-    CnfBindingIsMissing(String),
+    CnfBindingIsMissing(Symbol),
 
-    #[error("result clause doensn't appear in CNF of original term: '{0}'")]
+    #[error("result clause doensn't appear in CNF of original term: '{}'", DisplayPretty(.0))]
     ClauseDoesntAppearInCnf(Rc<Term>),
 }
 
 /// Errors relevant to the linear arithmetic rules.
 #[derive(Debug, Error)]
 pub enum LinearArithmeticError {
-    #[error("term '{0}' doesn't match any tautology case")]
+    #[error("term '{}' doesn't match any tautology case", DisplayPretty(.0))]
     NotValidTautologyCase(Rc<Term>),
 
-    #[error("term '{0}' is not a valid disequality operation")]
+    #[error("term '{}' is not a valid disequality operation", DisplayPretty(.0))]
     InvalidDisequalityOp(Rc<Term>),
 
-    #[error("too many arguments in disequality '{0}'")]
+    #[error("too many arguments in disequality '{}'", DisplayPretty(.0))]
     TooManyArgsInDisequality(Rc<Term>),
 
     #[error("final disequality is not contradictory: '{}'", DisplayLinearComb(.0, .1))]
     DisequalityIsNotContradiction(Operator, LinearComb),
 
+    #[error(
+        "final disequality is not contradictory: coefficient of '{}' is not zero",
+        DisplayPretty(.0)
+    )]
+    NonZeroCoefficient(Rc<Term>),
+
+    #[error("final disequality is not contradictory: {0}")]
+    ContradictionCertificate(Box<LaGenericCertificate>),
+
     #[error("final disequality is not tautological: '{}'", DisplayLinearComb(.0, .1))]
     DisequalityIsNotTautology(Operator, LinearComb),
 
-    #[error("expected term '{0}' to be less than term '{1}'")]
+    #[error("expected term '{}' to be less than term '{}'", DisplayPretty(.0), DisplayPretty(.1))]
     ExpectedLessThan(Rc<Term>, Rc<Term>),
 
-    #[error("expected term '{0}' to be less than or equal to term '{1}'")]
+    #[error(
+        "expected term '{}' to be less than or equal to term '{}'",
+        DisplayPretty(.0), DisplayPretty(.1)
+    )]
     ExpectedLessEq(Rc<Term>, Rc<Term>),
 }
 
+/// The disequality contributed by a single clause literal of a `la_generic` step, after it was
+/// negated, strengthened and multiplied by its coefficient.
+#[derive(Debug)]
+pub struct LaGenericContribution {
+    pub literal: Rc<Term>,
+    pub coefficient: Rational,
+    pub disequality: (Operator, LinearComb),
+}
+
+/// The computation that led a `la_generic` step to be rejected: the contribution of each clause
+/// literal, and the final disequality obtained by adding them, which was not contradictory.
+#[derive(Debug)]
+pub struct LaGenericCertificate {
+    pub contributions: Vec<LaGenericContribution>,
+    pub result: (Operator, LinearComb),
+}
+
+impl fmt::Display for LaGenericCertificate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (op, result) = &self.result;
+        write!(f, "'{}'", DisplayLinearComb(op, result))?;
+        for (i, c) in self.contributions.iter().enumerate() {
+            let (op, disequality) = &c.disequality;
+            write!(
+                f,
+                "\n  [{}] '{}' with coefficient {} contributes '{}'",
+                i,
+                DisplayPretty(&c.literal),
+                c.coefficient,
+                DisplayLinearComb(op, disequality),
+            )?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum LiaGenericError {
     #[error("failed to spawn solver process")]
@@ -293,6 +437,9 @@ pub enum LiaGenericError {
     #[error("solver timed out when solving problem")]
     SolverTimeout,
 
+    #[error("Carcara was built without support for external solvers")]
+    ExternalSolversDisabled,
+
     #[error(
         "solver returned non-zero exit code: {}",
         if let Some(i) = .0 { format!("{}", i) } else { "none".to_owned() }
@@ -315,28 +462,35 @@ pub enum SubproofError {
     #[error("only the `subproof` rule may discharge local assumptions")]
     DischargeInWrongRule,
 
+    #[error("id '{0}' is not nested in the id of its subproof, '{1}'")]
+    IdNotNested(StepId, StepId),
+
     #[error("binding '{0}' appears as free variable in phi")]
-    BindBindingIsFreeVarInPhi(String),
+    BindBindingIsFreeVarInPhi(Symbol),
 
     #[error("right and left quantifiers have different number of bindings: {0} and {1}")]
     BindDifferentNumberOfBindings(usize, usize),
 
     #[error("binding '{0}' was not introduced in context")]
-    BindingIsNotInContext(String),
+    BindingIsNotInContext(Symbol),
 
     #[error("expected {0} bindings in 'let' term, got {1}")]
     WrongNumberOfLetBindings(usize, usize),
 
     #[error(
         "premise '(= {} {})' doesn't justify substitution of '{}' for '{}'",
-        .premise.0, .premise.1, .substitution.0, .substitution.1
+        DisplayPretty(&.premise.0), DisplayPretty(&.premise.1),
+        DisplayPretty(&.substitution.0), DisplayPretty(&.substitution.1)
     )]
     PremiseDoesntJustifyLet {
         substitution: (Rc<Term>, Rc<Term>),
         premise: (Rc<Term>, Rc<Term>),
     },
 
-    #[error("substitution '(:= {0} {1})' doesn't appear as a point in phi")]
+    #[error(
+        "substitution '(:= {} {})' doesn't appear as a point in phi",
+        DisplayPretty(.0), DisplayPretty(.1)
+    )]
     NoPointForSubstitution(Rc<Term>, Rc<Term>),
 
     #[error("expected binding list in right-hand side to be '{0}'")]
@@ -0,0 +1,71 @@
+//! Importing of Alethe proof fragments produced by external tools, like SMT solvers.
+
+use super::{Config, ProofChecker};
+use crate::{ast::*, parser, CarcaraResult};
+
+/// Parses and checks an Alethe proof produced by an external tool, and returns its commands so
+/// they can be spliced into another proof.
+///
+/// `problem` is the SMT-LIB problem that was given to the external tool, and `proof` is the proof
+/// it produced for that problem. The imported proof is checked in isolation, using its own problem
+/// prelude and premises. The ids of all returned commands are prefixed with `root_id`, so they
+/// don't collide with the ids in the proof they will be inserted into. Premise indices are left
+/// unchanged, that is, they are still relative to the imported proof.
+///
+/// # Examples
+///
+/// ```
+/// # use carcara::{ast::*, checker::import_external_proof, CarcaraResult};
+/// # fn main() -> CarcaraResult<()> {
+/// let problem = "(declare-fun p () Bool) (assert p) (assert (not p))".as_bytes();
+/// let proof = "
+///     (assume h1 p)
+///     (assume h2 (not p))
+///     (step t3 (cl) :rule resolution :premises (h1 h2))
+/// "
+/// .as_bytes();
+/// let mut pool = PrimitivePool::new();
+/// let commands = import_external_proof(&mut pool, problem, proof, "t5")?;
+/// let ids: Vec<_> = commands.iter().map(|c| c.id()).collect();
+/// assert_eq!(ids, ["t5.h1", "t5.h2", "t5.t3"]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn import_external_proof(
+    pool: &mut PrimitivePool,
+    problem: &[u8],
+    proof: &[u8],
+    root_id: &str,
+) -> CarcaraResult<Vec<ProofCommand>> {
+    let mut parser = parser::Parser::new(pool, parser::Config::new(), problem)?;
+    let (prelude, premises) = parser.parse_problem()?;
+    parser.reset(proof)?;
+    let commands = parser.parse_proof()?;
+    let attributes = parser.take_attributes();
+    let term_attributes = parser.take_term_attributes();
+    let spans = parser.take_spans();
+    let flattened = parser.take_flattened();
+    let mut proof = Proof {
+        premises,
+        commands,
+        attributes,
+        term_attributes,
+        spans,
+        flattened,
+    };
+
+    ProofChecker::new(pool, Config::new(), &prelude).check(&proof)?;
+
+    prefix_ids(&mut proof.commands, root_id);
+    Ok(proof.commands)
+}
+
+fn prefix_ids(commands: &mut [ProofCommand], root_id: &str) {
+    for c in commands {
+        match c {
+            ProofCommand::Assume { id, .. } => *id = format!("{}.{}", root_id, id).into(),
+            ProofCommand::Step(s) => s.id = format!("{}.{}", root_id, s.id).into(),
+            ProofCommand::Subproof(s) => prefix_ids(&mut s.commands, root_id),
+        }
+    }
+}
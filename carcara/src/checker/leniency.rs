@@ -0,0 +1,89 @@
+use crate::{ast::StepId, parser::Span};
+use indexmap::IndexMap;
+use std::fmt;
+
+/// A kind of lenient reasoning that the checker may apply to accept a step, but that a strict
+/// checker would reject.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Leniency {
+    /// The sides of an equality were implicitly flipped, as in an `assume` command that matches a
+    /// problem premise only up to the order of its equalities, or a `refl` step.
+    EqualityFlip,
+
+    /// A double negation was implicitly added to the conclusion of a `resolution` step, that
+    /// concludes `(not (not c))` instead of `c`.
+    DoubleNegation,
+
+    /// The premises had to be used in a different order than the one given, as in `cong` steps
+    /// whose premises are not in the order of the arguments, or `resolution` steps whose pivots
+    /// can only be found if the premises are reordered or deduplicated.
+    Reordering,
+
+    /// A `resolution` step was only accepted by reverse unit propagation, without finding its
+    /// pivots.
+    UnitPropagation,
+
+    /// An `assume` command matches a problem premise only after one of the normalizations given
+    /// by the `assume_normalizations` option.
+    AssumeNormalization,
+}
+
+impl fmt::Display for Leniency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Leniency::EqualityFlip => write!(f, "equality flip"),
+            Leniency::DoubleNegation => write!(f, "double negation"),
+            Leniency::Reordering => write!(f, "reordering"),
+            Leniency::UnitPropagation => write!(f, "unit propagation"),
+            Leniency::AssumeNormalization => write!(f, "assume normalization"),
+        }
+    }
+}
+
+/// A command that was only accepted using lenient reasoning.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LenientStep {
+    pub id: StepId,
+
+    /// The rule of the step, as written in the proof. For `assume` commands, this is `assume`.
+    pub rule: String,
+
+    pub leniency: Leniency,
+
+    /// The span of the command in the source input, if the proof was parsed from a text input.
+    pub span: Option<Span>,
+}
+
+/// A report of every command that the checker only accepted using lenient reasoning. See
+/// [`ProofChecker::check_leniency`](super::ProofChecker::check_leniency).
+#[derive(Debug, Clone, Default)]
+pub struct LeniencyReport {
+    /// The lenient commands, in the order they were checked.
+    pub steps: Vec<LenientStep>,
+}
+
+impl LeniencyReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(super) fn register(&mut self, id: StepId, rule: &str, leniency: Leniency) {
+        self.steps.push(LenientStep {
+            id,
+            rule: rule.to_owned(),
+            leniency,
+            span: None,
+        });
+    }
+
+    /// Returns the number of lenient commands of each rule, for each kind of lenient reasoning.
+    pub fn summary(&self) -> IndexMap<(&str, Leniency), usize> {
+        let mut result = IndexMap::new();
+        for step in &self.steps {
+            *result
+                .entry((step.rule.as_str(), step.leniency))
+                .or_default() += 1;
+        }
+        result
+    }
+}
@@ -1,17 +1,18 @@
 use super::*;
-use crate::{checker::error::LiaGenericError, parser, LiaGenericOptions};
-use indexmap::IndexMap;
-use std::{
-    io::{BufRead, Write},
-    process::{Command, Stdio},
-};
+#[cfg(feature = "elaborator")]
+use crate::utils::DedupIterator;
+use crate::{checker::error::LiaGenericError, LiaGenericOptions};
+#[cfg(feature = "elaborator")]
+use indexmap::{IndexMap, IndexSet};
 
 fn get_problem_string(conclusion: &[Rc<Term>], prelude: &ProblemPrelude) -> String {
     use std::fmt::Write;
 
     let mut problem = String::new();
     write!(&mut problem, "(set-option :produce-proofs true)").unwrap();
-    write!(&mut problem, "{}", prelude).unwrap();
+    // We only include the declarations that are actually used in the conclusion, to avoid sending
+    // huge queries to the solver when the original problem has many declarations
+    write!(&mut problem, "{}", prelude.restricted_to(conclusion)).unwrap();
 
     let mut bytes = Vec::new();
     printer::write_lia_smt_instance(&mut bytes, conclusion, true).unwrap();
@@ -24,17 +25,29 @@ fn get_problem_string(conclusion: &[Rc<Term>], prelude: &ProblemPrelude) -> Stri
     problem
 }
 
+#[cfg(feature = "elaborator")]
 pub fn lia_generic_single_thread(
     pool: &mut PrimitivePool,
     conclusion: &[Rc<Term>],
     prelude: &ProblemPrelude,
-    elaborator: Option<&mut Elaborator>,
+    mut elaborator: Option<&mut Elaborator>,
     root_id: &str,
     options: &LiaGenericOptions,
 ) -> bool {
     let problem = get_problem_string(conclusion, prelude);
-    let commands = match get_solver_proof(pool, problem, options) {
-        Ok(c) => c,
+
+    // The ids of the solver proof commands are prefixed with the id of the subproof that will contain
+    // them, so they can only be imported after this id is computed. To avoid taking a new id for a
+    // step that will be left unchanged, this is only done after the solver succeeds
+    let result = run_solver(&problem, options).and_then(|proof| {
+        let subproof_id = match &mut elaborator {
+            Some(elaborator) => elaborator.get_new_id(root_id),
+            None => StepId::new(root_id),
+        };
+        import_solver_proof(pool, &problem, &proof, &subproof_id).map(|c| (c, subproof_id))
+    });
+    let (commands, subproof_id) = match result {
+        Ok(r) => r,
         Err(e) => {
             log::warn!("failed to check `lia_generic` step: {}", e);
             if let Some(elaborator) = elaborator {
@@ -44,8 +57,12 @@ pub fn lia_generic_single_thread(
         }
     };
 
+    // The solver proof is parsed into the same pool, so its terms can be compared by reference with
+    // the terms in the original proof
+    debug_assert_commands_interned(&*pool, &commands);
+
     if let Some(elaborator) = elaborator {
-        insert_solver_proof(pool, elaborator, commands, conclusion, root_id);
+        insert_solver_proof(pool, elaborator, commands, conclusion, root_id, subproof_id);
     }
     false
 }
@@ -57,7 +74,9 @@ pub fn lia_generic_multi_thread(
 ) -> bool {
     let mut pool = PrimitivePool::new();
     let problem = get_problem_string(conclusion, prelude);
-    if let Err(e) = get_solver_proof(&mut pool, problem, options) {
+    let result = run_solver(&problem, options)
+        .and_then(|proof| import_solver_proof(&mut pool, &problem, &proof, "lia_generic"));
+    if let Err(e) = result {
         log::warn!("failed to check `lia_generic` step using: {}", e);
         true
     } else {
@@ -65,11 +84,14 @@ pub fn lia_generic_multi_thread(
     }
 }
 
-fn get_solver_proof(
-    pool: &mut PrimitivePool,
-    problem: String,
-    options: &LiaGenericOptions,
-) -> Result<Vec<ProofCommand>, LiaGenericError> {
+/// Runs the solver on the given problem, and returns its proof, without the leading "unsat" line.
+#[cfg(feature = "external-solvers")]
+fn run_solver(problem: &str, options: &LiaGenericOptions) -> Result<Vec<u8>, LiaGenericError> {
+    use std::{
+        io::{BufRead, Write},
+        process::{Command, Stdio},
+    };
+
     let mut process = Command::new(options.solver.as_ref())
         .args(options.arguments.iter().map(AsRef::as_ref))
         .stdin(Stdio::piped())
@@ -109,33 +131,42 @@ fn get_solver_proof(
         return Err(LiaGenericError::OutputNotUnsat);
     }
 
-    parse_and_check_solver_proof(pool, problem.as_bytes(), proof)
-        .map_err(|e| LiaGenericError::InnerProofError(Box::new(e)))
+    Ok(proof.to_vec())
+}
+
+#[cfg(not(feature = "external-solvers"))]
+fn run_solver(_: &str, _: &LiaGenericOptions) -> Result<Vec<u8>, LiaGenericError> {
+    Err(LiaGenericError::ExternalSolversDisabled)
 }
 
-fn parse_and_check_solver_proof(
+fn import_solver_proof(
     pool: &mut PrimitivePool,
-    problem: &[u8],
+    problem: &str,
     proof: &[u8],
-) -> CarcaraResult<Vec<ProofCommand>> {
-    let mut parser = parser::Parser::new(pool, parser::Config::new(), problem)?;
-    let (prelude, premises) = parser.parse_problem()?;
-    parser.reset(proof)?;
-    let commands = parser.parse_proof()?;
-    let proof = Proof { premises, commands };
-
-    ProofChecker::new(pool, Config::new(), &prelude).check(&proof)?;
-    Ok(proof.commands)
+    root_id: &str,
+) -> Result<Vec<ProofCommand>, LiaGenericError> {
+    import_external_proof(pool, problem.as_bytes(), proof, root_id)
+        .map_err(|e| LiaGenericError::InnerProofError(Box::new(e)))
+}
+
+/// Checks, in debug builds, that all terms in the given commands are interned in the pool.
+#[cfg(feature = "elaborator")]
+fn debug_assert_commands_interned(pool: &dyn TermPool, commands: &[ProofCommand]) {
+    for command in commands {
+        match command {
+            ProofCommand::Assume { term, .. } => pool::debug_assert_interned(pool, [term]),
+            ProofCommand::Step(s) => debug_assert_step_interned(pool, s),
+            ProofCommand::Subproof(s) => debug_assert_commands_interned(pool, &s.commands),
+        }
+    }
 }
 
-fn update_premises(commands: &mut [ProofCommand], delta: usize, root_id: &str) {
+#[cfg(feature = "elaborator")]
+fn update_premises(commands: &mut [ProofCommand], delta: usize) {
     for c in commands {
         match c {
-            ProofCommand::Assume { id, .. } => {
-                *id = format!("{}.{}", root_id, id);
-            }
+            ProofCommand::Assume { .. } => (),
             ProofCommand::Step(s) => {
-                s.id = format!("{}.{}", root_id, s.id);
                 for p in s.premises.iter_mut().chain(s.discharge.iter_mut()) {
                     if p.0 == 0 {
                         p.1 += delta;
@@ -144,12 +175,53 @@ fn update_premises(commands: &mut [ProofCommand], delta: usize, root_id: &str) {
                 }
             }
             ProofCommand::Subproof(s) => {
-                update_premises(&mut s.commands, delta, root_id);
+                update_premises(&mut s.commands, delta);
+            }
+        }
+    }
+}
+
+/// Removes any `assume` command in the top level of the solver proof whose term was already assumed
+/// by a previous command. Since all terms are added to the same term pool, this only requires
+/// comparing the terms by reference. Any premise that referenced a removed command is changed to
+/// reference the first `assume` of the same term.
+#[cfg(feature = "elaborator")]
+fn remove_duplicate_assumes(commands: Vec<ProofCommand>) -> Vec<ProofCommand> {
+    fn remap_premises(commands: &mut [ProofCommand], new_indices: &[usize]) {
+        for c in commands {
+            match c {
+                ProofCommand::Assume { .. } => (),
+                ProofCommand::Step(s) => {
+                    for p in s.premises.iter_mut().chain(s.discharge.iter_mut()) {
+                        if p.0 == 0 {
+                            p.1 = new_indices[p.1];
+                        }
+                    }
+                }
+                ProofCommand::Subproof(s) => remap_premises(&mut s.commands, new_indices),
             }
         }
     }
+
+    let mut assumed: IndexMap<Rc<Term>, usize> = IndexMap::new();
+    let mut new_indices = Vec::with_capacity(commands.len());
+    let mut result = Vec::with_capacity(commands.len());
+    for c in commands {
+        if let ProofCommand::Assume { term, .. } = &c {
+            if let Some(&i) = assumed.get(term) {
+                new_indices.push(i);
+                continue;
+            }
+            assumed.insert(term.clone(), result.len());
+        }
+        new_indices.push(result.len());
+        result.push(c);
+    }
+    remap_premises(&mut result, &new_indices);
+    result
 }
 
+#[cfg(feature = "elaborator")]
 fn insert_missing_assumes(
     pool: &mut PrimitivePool,
     elaborator: &mut Elaborator,
@@ -157,12 +229,7 @@ fn insert_missing_assumes(
     proof: &[ProofCommand],
     root_id: &str,
 ) -> (Vec<Rc<Term>>, usize) {
-    let mut count_map: IndexMap<&Rc<Term>, usize> = IndexMap::new();
-    for c in conclusion {
-        *count_map.entry(c).or_default() += 1;
-    }
-
-    let proof_premises: Vec<_> = proof
+    let proof_premises: IndexSet<_> = proof
         .iter()
         .filter_map(|c| {
             if let ProofCommand::Assume { term, .. } = c {
@@ -172,18 +239,16 @@ fn insert_missing_assumes(
             }
         })
         .collect();
-    for p in &proof_premises {
-        *count_map.get_mut(p).unwrap() -= 1;
-    }
 
+    // Since the conclusion clause is interpreted as a set, we only need to add one `assume` command
+    // for each distinct term that was not already assumed by the solver proof
     let mut all = Vec::new();
-    for (t, mut count) in count_map {
-        while count > 0 {
+    for t in conclusion.iter().dedup() {
+        if !proof_premises.contains(t) {
             let id = elaborator.get_new_id(root_id);
             all.push(t.clone());
             let term = build_term!(pool, (not {t.clone()}));
             elaborator.add_new_command(ProofCommand::Assume { id, term }, true);
-            count -= 1;
         }
     }
     let num_added = all.len();
@@ -191,14 +256,16 @@ fn insert_missing_assumes(
     (all, num_added)
 }
 
+#[cfg(feature = "elaborator")]
 fn insert_solver_proof(
     pool: &mut PrimitivePool,
     elaborator: &mut Elaborator,
-    mut commands: Vec<ProofCommand>,
+    commands: Vec<ProofCommand>,
     conclusion: &[Rc<Term>],
     root_id: &str,
+    subproof_id: StepId,
 ) {
-    let subproof_id = elaborator.get_new_id(root_id);
+    let mut commands = remove_duplicate_assumes(commands);
     elaborator.open_accumulator_subproof();
 
     let (all_premises, num_added) = insert_missing_assumes(
@@ -221,7 +288,7 @@ fn insert_solver_proof(
         .unzip();
     clause.push(pool.bool_false());
 
-    update_premises(&mut commands, num_added, &subproof_id);
+    update_premises(&mut commands, num_added);
     for c in commands {
         elaborator.add_new_command(c, true);
     }
@@ -232,7 +299,7 @@ fn insert_solver_proof(
         ProofStep {
             id: subproof_id,
             clause: clause.clone(),
-            rule: "subproof".to_owned(),
+            rule: "subproof".into(),
             premises: Vec::new(),
             args: Vec::new(),
             discharge,
@@ -255,7 +322,7 @@ fn insert_solver_proof(
             elaborator.add_new_step(ProofStep {
                 id,
                 clause,
-                rule: "not_not".to_owned(),
+                rule: "not_not".into(),
                 premises: Vec::new(),
                 args: Vec::new(),
                 discharge: Vec::new(),
@@ -266,7 +333,7 @@ fn insert_solver_proof(
     let false_step = elaborator.add_new_step(ProofStep {
         id,
         clause: vec![build_term!(pool, (not {pool.bool_false()}))],
-        rule: "false".to_owned(),
+        rule: "false".into(),
         premises: Vec::new(),
         args: Vec::new(),
         discharge: Vec::new(),
@@ -280,9 +347,59 @@ fn insert_solver_proof(
     elaborator.push_elaborated_step(ProofStep {
         id,
         clause: conclusion.to_vec(),
-        rule: "resolution".to_owned(),
+        rule: "resolution".into(),
         premises,
         args: Vec::new(),
         discharge: Vec::new(),
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::tests::{parse_proof, parse_terms};
+
+    #[cfg(feature = "elaborator")]
+    #[test]
+    fn test_remove_duplicate_assumes() {
+        let mut pool = PrimitivePool::new();
+        let proof = parse_proof(
+            &mut pool,
+            "(assume h1 (= 0 1))
+            (assume h2 (< 2 1))
+            (assume h3 (= 0 1))
+            (step t4 (cl) :rule hole :premises (h2 h3))",
+        );
+        let commands = remove_duplicate_assumes(proof.commands);
+
+        // The second `assume` of `(= 0 1)` is removed, and `t4` now references the first one
+        let ids: Vec<_> = commands.iter().map(|c| c.id().to_string()).collect();
+        assert_eq!(ids, ["h1", "h2", "t4"]);
+        let ProofCommand::Step(step) = &commands[2] else {
+            panic!("expected step command");
+        };
+        assert_eq!(step.premises, [(0, 1), (0, 0)]);
+    }
+
+    #[cfg(feature = "elaborator")]
+    #[test]
+    fn test_insert_missing_assumes() {
+        let mut pool = PrimitivePool::new();
+        let proof = parse_proof(&mut pool, "(assume h1 (not (= 0 1)))");
+        let [a, b] = parse_terms(&mut pool, "", ["(= 0 1)", "(< 2 1)"]);
+        let conclusion = [a.clone(), b.clone(), b.clone()];
+
+        // Only one `assume` is added for `(< 2 1)`, even though it appears twice in the conclusion,
+        // and none is added for `(= 0 1)`, which was already assumed by the solver proof
+        let mut elaborator = Elaborator::new();
+        let (all, num_added) = insert_missing_assumes(
+            &mut pool,
+            &mut elaborator,
+            &conclusion,
+            &proof.commands,
+            "t",
+        );
+        assert_eq!(num_added, 1);
+        assert_eq!(all, [b, a]);
+    }
+}
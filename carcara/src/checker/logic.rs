@@ -0,0 +1,263 @@
+//! Validation of the terms in a proof against the logic declared by the problem's `set-logic`
+//! command.
+
+use super::error::{CheckerError, LogicViolation};
+use crate::{ast::*, Error, LogicValidation};
+use indexmap::IndexSet;
+
+/// A component of a logic name, such as `UF` or `LIA`, and the features it enables.
+type LogicComponent = (&'static str, fn(&mut Logic));
+
+/// The theories and features allowed by an SMT-LIB logic, such as `QF_UFLIA`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Logic {
+    pub quantifiers: bool,
+    pub uninterpreted_functions: bool,
+    pub arrays: bool,
+    pub bitvectors: bool,
+    pub floating_point: bool,
+    pub datatypes: bool,
+    pub strings: bool,
+    pub integers: bool,
+    pub reals: bool,
+    pub nonlinear: bool,
+}
+
+impl Logic {
+    /// Parses the name of a logic. Returns `None` if the name is `ALL`, or if it is not a
+    /// standard logic name, in which case no restrictions are known.
+    pub fn parse(name: &str) -> Option<Self> {
+        const COMPONENTS: &[LogicComponent] = &[
+            ("AX", |l| l.arrays = true),
+            ("A", |l| l.arrays = true),
+            ("UF", |l| l.uninterpreted_functions = true),
+            ("BV", |l| l.bitvectors = true),
+            ("FP", |l| l.floating_point = true),
+            ("DT", |l| l.datatypes = true),
+            ("S", |l| l.strings = true),
+            ("LIRA", |l| (l.integers, l.reals) = (true, true)),
+            ("NIRA", |l| {
+                (l.integers, l.reals, l.nonlinear) = (true, true, true);
+            }),
+            ("LIA", |l| l.integers = true),
+            ("NIA", |l| (l.integers, l.nonlinear) = (true, true)),
+            ("LRA", |l| l.reals = true),
+            ("NRA", |l| (l.reals, l.nonlinear) = (true, true)),
+            ("IDL", |l| l.integers = true),
+            ("RDL", |l| l.reals = true),
+        ];
+
+        let (mut rest, quantifiers) = match name.strip_prefix("QF_") {
+            Some(rest) => (rest, false),
+            None => (name, true),
+        };
+        let mut logic = Logic { quantifiers, ..Default::default() };
+        while !rest.is_empty() {
+            let (prefix, apply) = COMPONENTS.iter().find(|(p, _)| rest.starts_with(p))?;
+            apply(&mut logic);
+            rest = &rest[prefix.len()..];
+        }
+        (logic != Logic { quantifiers, ..Default::default() }).then_some(logic)
+    }
+
+    /// Returns `Ok` if all subterms of the given term are allowed in this logic. Subterms that are
+    /// in `seen` are skipped, and every subterm visited is added to it.
+    fn check_term(
+        &self,
+        term: &Rc<Term>,
+        seen: &mut IndexSet<Rc<Term>>,
+    ) -> Result<(), (Rc<Term>, LogicViolation)> {
+        let mut stack = vec![term];
+        while let Some(term) = stack.pop() {
+            if !seen.insert(term.clone()) {
+                continue;
+            }
+            if let Some(violation) = self.check_node(term) {
+                return Err((term.clone(), violation));
+            }
+            match term.as_ref() {
+                Term::Var(_, sort) => stack.push(sort),
+                Term::Quant(_, bindings, _)
+                | Term::Let(bindings, _)
+                | Term::Lambda(bindings, _) => stack.extend(bindings.iter().map(|(_, t)| t)),
+                Term::Choice((_, sort), _) => stack.push(sort),
+                Term::ParamOp { op_args, .. } => stack.extend(op_args),
+                Term::Sort(
+                    Sort::Atom(_, args) | Sort::Datatype(_, args) | Sort::Function(args),
+                ) => stack.extend(args),
+                Term::Sort(Sort::Array(x, y)) => stack.extend([x, y]),
+                _ => (),
+            }
+            stack.extend(term.children());
+        }
+        Ok(())
+    }
+
+    /// Checks a single term node, without looking at its subterms.
+    fn check_node(&self, term: &Term) -> Option<LogicViolation> {
+        let is_constant = |t: &Rc<Term>| t.as_fraction().is_some();
+        match term {
+            Term::Quant(..) | Term::Choice(..) if !self.quantifiers => {
+                Some(LogicViolation::Quantifier)
+            }
+            Term::App(..) if !self.uninterpreted_functions && !self.datatypes => {
+                Some(LogicViolation::UninterpretedFunction)
+            }
+            Term::Op(Operator::Mult, args)
+                if !self.nonlinear && args.iter().filter(|a| !is_constant(a)).count() > 1 =>
+            {
+                Some(LogicViolation::NonLinear)
+            }
+            Term::Op(Operator::IntDiv | Operator::RealDiv | Operator::Mod, args)
+                if !self.nonlinear && !args.iter().skip(1).all(is_constant) =>
+            {
+                Some(LogicViolation::NonLinear)
+            }
+            Term::Const(c) if !self.allows_sort(&c.sort()) => Some(LogicViolation::Sort(c.sort())),
+            Term::Sort(sort) if !self.allows_sort(sort) => Some(LogicViolation::Sort(sort.clone())),
+            _ => None,
+        }
+    }
+
+    fn allows_sort(&self, sort: &Sort) -> bool {
+        match sort {
+            Sort::Bool | Sort::Function(_) => true,
+            Sort::Int => self.integers,
+            Sort::Real => self.reals,
+            Sort::String | Sort::RegLan => self.strings,
+            Sort::Array(..) => self.arrays,
+            Sort::BitVec(_) => self.bitvectors,
+            Sort::FloatingPoint(..) | Sort::RoundingMode => self.floating_point,
+            Sort::Datatype(..) => self.datatypes,
+            Sort::Atom(..) => self.uninterpreted_functions || self.arrays,
+        }
+    }
+}
+
+/// Checks that the terms in all the commands yielded by the iterator are allowed in the logic
+/// declared by the problem. Depending on `mode`, terms outside the logic are either reported as a
+/// warning, once for each command, or as an error. If the problem has no `set-logic` command, or
+/// uses a logic that is not recognized, nothing is checked.
+pub fn validate(
+    prelude: &ProblemPrelude,
+    iter: ProofIter,
+    mode: LogicValidation,
+) -> Result<(), Error> {
+    if mode == LogicValidation::Off {
+        return Ok(());
+    }
+    let Some((name, logic)) = prelude.logic().and_then(|l| Some((l, Logic::parse(l)?))) else {
+        return Ok(());
+    };
+
+    let mut seen = IndexSet::new();
+    for command in iter {
+        let (rule, terms): (&str, Vec<&Rc<Term>>) = match command {
+            ProofCommand::Assume { term, .. } => ("assume", vec![term]),
+            ProofCommand::Step(s) => {
+                let args = s.args.iter().flat_map(ProofArg::terms);
+                (s.rule.as_str(), s.clause.iter().chain(args).collect())
+            }
+            ProofCommand::Subproof(s) => {
+                let assigned = s.assignment_args.iter().map(|(_, t)| t);
+                let variables = s.variable_args.iter().map(|(_, sort)| sort);
+                ("anchor", assigned.chain(variables).collect())
+            }
+        };
+        for term in terms {
+            if let Err((term, violation)) = logic.check_term(term, &mut seen) {
+                let error = Error::Checker {
+                    inner: Box::new(CheckerError::OutsideLogic(name.to_owned(), term, violation)),
+                    rule: rule.to_owned(),
+                    step: command.id().to_string(),
+                    span: None,
+                    annotations: None,
+                };
+                match mode {
+                    LogicValidation::Error => return Err(error),
+                    _ => log::warn!("{}", error),
+                }
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::tests::parse_terms;
+
+    #[test]
+    fn test_parse_logic() {
+        let qf_uflia = Logic::parse("QF_UFLIA").unwrap();
+        assert!(!qf_uflia.quantifiers && qf_uflia.uninterpreted_functions && qf_uflia.integers);
+        assert!(!qf_uflia.reals && !qf_uflia.nonlinear);
+
+        let aufnira = Logic::parse("AUFNIRA").unwrap();
+        assert!(aufnira.quantifiers && aufnira.arrays && aufnira.uninterpreted_functions);
+        assert!(aufnira.integers && aufnira.reals && aufnira.nonlinear);
+
+        let qf_abvfp = Logic::parse("QF_ABVFP").unwrap();
+        assert!(qf_abvfp.arrays && qf_abvfp.bitvectors && qf_abvfp.floating_point);
+        assert!(!qf_abvfp.integers);
+
+        assert!(Logic::parse("QF_SLIA").unwrap().strings);
+        assert_eq!(None, Logic::parse("ALL"));
+        assert_eq!(None, Logic::parse("HORN"));
+        assert_eq!(None, Logic::parse("QF_"));
+    }
+
+    #[test]
+    fn test_check_term() {
+        fn run_case(logic: &str, term: &str) -> Option<LogicViolation> {
+            let mut pool = PrimitivePool::new();
+            let definitions = "(declare-fun f (Int) Int) (declare-fun x () Int)
+                (declare-fun y () Int) (declare-fun r () Real)";
+            let [term] = parse_terms(&mut pool, definitions, [term]);
+            let logic = Logic::parse(logic).unwrap();
+            logic
+                .check_term(&term, &mut IndexSet::new())
+                .err()
+                .map(|(_, v)| v)
+        }
+
+        assert_eq!(None, run_case("QF_LIA", "(< (+ x (* 2 y)) 3)"));
+        assert_eq!(None, run_case("QF_LIA", "(= (div x 2) (mod y 3))"));
+        assert_eq!(None, run_case("LIA", "(forall ((z Int)) (> z x))"));
+        assert_eq!(None, run_case("QF_UFLIA", "(= (f x) y)"));
+        assert_eq!(None, run_case("QF_NIA", "(= (* x y) 1)"));
+
+        let cases = [
+            (
+                "QF_LIA",
+                "(forall ((z Int)) (> z x))",
+                LogicViolation::Quantifier,
+            ),
+            (
+                "QF_LIA",
+                "(= (f x) y)",
+                LogicViolation::UninterpretedFunction,
+            ),
+            ("QF_LIA", "(= (* x y) 1)", LogicViolation::NonLinear),
+            ("QF_LIA", "(= (div x y) 1)", LogicViolation::NonLinear),
+            ("QF_LIA", "(= r 1.0)", LogicViolation::Sort(Sort::Real)),
+            (
+                "QF_LIA",
+                "(= x (to_int 1.5))",
+                LogicViolation::Sort(Sort::Real),
+            ),
+            ("QF_LRA", "(= x 1)", LogicViolation::Sort(Sort::Int)),
+        ];
+        for (logic, term, expected) in cases {
+            assert_eq!(
+                Some(expected),
+                run_case(logic, term),
+                "{} in {}",
+                term,
+                logic
+            );
+        }
+    }
+}
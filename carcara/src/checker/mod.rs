@@ -1,22 +1,36 @@
+mod coverage;
 pub mod error;
+mod import;
+mod leniency;
 mod lia_generic;
+mod logic;
 mod parallel;
 mod rules;
+mod unknown_rules;
 
+#[cfg(feature = "elaborator")]
+use crate::elaborator::{Elaborator, StepExplanation};
 use crate::{
     ast::*,
-    benchmarking::{CollectResults, OnlineBenchmarkResults},
-    elaborator::Elaborator,
-    CarcaraResult, Error, LiaGenericOptions,
+    benchmarking::{CollectResults, NullResults, StepProbe},
+    AssumeNormalizations, CarcaraResult, Error, LiaGenericOptions, LogicValidation, StepLimits,
 };
-use error::{CheckerError, SubproofError};
-use indexmap::IndexSet;
+pub use coverage::{RuleCounts, RuleCoverage};
+use error::{CheckerError, LimitKind, SubproofError};
+pub use import::import_external_proof;
+use indexmap::{IndexMap, IndexSet};
+pub use leniency::{Leniency, LeniencyReport, LenientStep};
 pub use parallel::{scheduler::Scheduler, ParallelProofChecker};
-use rules::{ElaborationRule, Premise, Rule, RuleArgs, RuleResult};
+pub use rules::resolution::ResolutionPivots;
+#[cfg(feature = "elaborator")]
+use rules::ElaborationRule;
+use rules::{Premise, Rule, RuleArgs, RuleResult};
 use std::{
     fmt,
+    ops::ControlFlow,
     time::{Duration, Instant},
 };
+pub use unknown_rules::{UnknownRule, UnknownRuleReport};
 
 #[derive(Clone)]
 pub struct CheckerStatistics<'s, CR: CollectResults + Send + Default> {
@@ -29,6 +43,10 @@ pub struct CheckerStatistics<'s, CR: CollectResults + Send + Default> {
     // this excludes the time spent searching for the correct `assert` premise.
     pub assume_core_time: Duration,
     pub results: CR,
+
+    // If this is set, the checker publishes each command it checks to this probe, and the
+    // per-step measurements are disabled, to avoid perturbing the sampled results
+    pub probe: Option<&'s StepProbe>,
 }
 
 impl<CR: CollectResults + Send + Default> fmt::Debug for CheckerStatistics<'_, CR> {
@@ -48,8 +66,23 @@ impl<CR: CollectResults + Send + Default> fmt::Debug for CheckerStatistics<'_, C
 #[derive(Debug, Default, Clone)]
 pub struct Config {
     strict: bool,
+    strict_rules: IndexSet<String>,
+    rule_aliases: IndexMap<String, String>,
+    trusted_rules: IndexSet<String>,
     ignore_unknown_rules: bool,
     lia_options: Option<LiaGenericOptions>,
+    numeric_precheck: bool,
+    resolution_cache: bool,
+    la_certificates: bool,
+    la_infer_args: bool,
+    skeleton_only: bool,
+    sort_premises: bool,
+    assume_normalizations: AssumeNormalizations,
+    step_limits: StepLimits,
+    rule_step_limits: IndexMap<String, StepLimits>,
+    only_steps: Option<IndexSet<StepId>>,
+    logic_validation: LogicValidation,
+    alethe_version: Option<AletheVersion>,
 }
 
 impl Config {
@@ -62,6 +95,31 @@ impl Config {
         self
     }
 
+    /// Enables strict checking only for the given rules. This has no effect if strict checking is
+    /// already enabled for all rules.
+    pub fn strict_rules(mut self, value: impl IntoIterator<Item = String>) -> Self {
+        self.strict_rules = value.into_iter().collect();
+        self
+    }
+
+    /// Adds aliases for rule names, mapping each alias to the name of a rule implemented by the
+    /// checker. These take precedence over the built-in aliases.
+    pub fn rule_aliases(mut self, value: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.rule_aliases.extend(value);
+        self
+    }
+
+    /// Sets the rules whose steps are trusted, that is, accepted without being checked, making the
+    /// proof holey. Unlike steps with unknown rules, these are accepted even if the
+    /// `ignore_unknown_rules` option is not set, and are not reported as unknown. This is useful
+    /// for rules that Carcara can't check, but whose steps are known to be sound, like the
+    /// reasoning about the cardinality of uninterpreted sorts done by cvc5's finite model finding.
+    /// Rule aliases are resolved before deciding if a rule is trusted.
+    pub fn trusted_rules(mut self, value: impl IntoIterator<Item = String>) -> Self {
+        self.trusted_rules.extend(value);
+        self
+    }
+
     pub fn ignore_unknown_rules(mut self, value: bool) -> Self {
         self.ignore_unknown_rules = value;
         self
@@ -71,36 +129,410 @@ impl Config {
         self.lia_options = value.into();
         self
     }
+
+    pub fn numeric_precheck(mut self, value: bool) -> Self {
+        self.numeric_precheck = value;
+        self
+    }
+
+    /// If `true`, the literals of each premise clause used in resolution steps are cached, so
+    /// they are not recomputed when the same premise is used by other resolution steps.
+    pub fn resolution_cache(mut self, value: bool) -> Self {
+        self.resolution_cache = value;
+        self
+    }
+
+    /// If `true`, rejected `la_generic` steps report the contribution of each clause literal to
+    /// the final disequality. If the numeric pre-check is also enabled, it is run first, and the
+    /// steps it rejects are reported without a certificate.
+    pub fn la_certificates(mut self, value: bool) -> Self {
+        self.la_certificates = value;
+        self
+    }
+
+    /// If `true`, `la_generic` steps whose coefficients are missing or wrong are accepted if valid
+    /// coefficients can be inferred. When elaborating, the arguments of these steps are replaced
+    /// by the inferred coefficients. This takes precedence over `la_certificates`, and disables
+    /// the numeric pre-check, since it would reject the steps whose coefficients are wrong.
+    pub fn la_infer_args(mut self, value: bool) -> Self {
+        self.la_infer_args = value;
+        self
+    }
+
+    /// If `true`, only the steps that make up the skeleton of the proof (that is, resolution and
+    /// subproof steps) are checked. Steps using any other rule, including unknown rules, are
+    /// considered holes. The structure of the proof, such as premise references and the discharge
+    /// of local assumptions, is still checked for all steps.
+    pub fn skeleton_only(mut self, value: bool) -> Self {
+        self.skeleton_only = value;
+        self
+    }
+
+    /// If `true`, when elaborating, the premises of `cong` and `ho_cong` steps are sorted in the
+    /// order of the arguments whose equality they justify.
+    pub fn sort_premises(mut self, value: bool) -> Self {
+        self.sort_premises = value;
+        self
+    }
+
+    /// Sets the normalizations applied to `assume` terms and problem assertions when matching them.
+    pub fn assume_normalizations(mut self, value: AssumeNormalizations) -> Self {
+        self.assume_normalizations = value;
+        self
+    }
+
+    /// Sets the limits imposed on the size of every step.
+    pub fn step_limits(mut self, value: StepLimits) -> Self {
+        self.step_limits = value;
+        self
+    }
+
+    /// Sets the limits imposed on the size of steps with specific rules. For each limit, these
+    /// take precedence over the ones set using `step_limits`. If a rule appears more than once,
+    /// the limits are combined, with later entries taking precedence.
+    pub fn rule_step_limits(
+        mut self,
+        value: impl IntoIterator<Item = (String, StepLimits)>,
+    ) -> Self {
+        for (rule, limits) in value {
+            let entry = self.rule_step_limits.entry(rule).or_default();
+            entry.max_clause_len = limits.max_clause_len.or(entry.max_clause_len);
+            entry.max_term_nodes = limits.max_term_nodes.or(entry.max_term_nodes);
+        }
+        self
+    }
+
+    /// Checks only the steps with the given ids, and the commands they transitively depend on,
+    /// considering all other steps as holes. `assume` commands are always checked. If the
+    /// iterator is empty, all steps are checked.
+    pub fn only_steps(mut self, value: impl IntoIterator<Item = String>) -> Self {
+        let ids: IndexSet<_> = value.into_iter().map(StepId::from).collect();
+        self.only_steps = (!ids.is_empty()).then_some(ids);
+        self
+    }
+
+    /// Sets whether the terms in the proof are validated against the logic declared by the
+    /// problem, before the proof is checked.
+    pub fn logic_validation(mut self, value: LogicValidation) -> Self {
+        self.logic_validation = value;
+        self
+    }
+
+    /// Sets the version of the Alethe format whose rule set is used. The rule names used by older
+    /// versions of veriT, such as `tmp_AC_simp`, are only accepted as aliases if this is `None`
+    /// or the legacy version.
+    pub fn alethe_version(mut self, value: Option<AletheVersion>) -> Self {
+        self.alethe_version = value;
+        self
+    }
+
+    /// Extends the steps selected using `only_steps` with the commands they transitively depend
+    /// on in the given proof. Returns an error if any of the selected steps doesn't exist.
+    pub(crate) fn select_steps(&mut self, proof: &Proof) -> CarcaraResult<()> {
+        let Some(ids) = &self.only_steps else {
+            return Ok(());
+        };
+        let dependencies = proof.dependencies();
+        let mut selected = IndexSet::new();
+        for id in ids {
+            let premises = dependencies
+                .transitive_premises(id)
+                .ok_or_else(|| Error::UnknownStep(id.to_string()))?;
+            selected.extend(premises);
+        }
+        self.only_steps = Some(selected);
+        Ok(())
+    }
+
+    /// Returns the canonical name of the given rule, resolving any user-defined or built-in alias.
+    /// Aliases are resolved only once, so they can't be chained.
+    pub fn canonical_rule_name<'a>(&'a self, rule: &'a str) -> &'a str {
+        /// Rule names used by some proof producers (mostly older versions of veriT) that differ
+        /// from the names implemented by the checker.
+        const BUILTIN_RULE_ALIASES: &[(&str, &str)] = &[
+            ("tmp_AC_simp", "ac_simp"),
+            ("tmp_bfun_elim", "bfun_elim"),
+            ("tmp_nary_elim", "nary_elim"),
+            ("tmp_distinct_elim", "distinct_elim"),
+            ("tmp_qnt_simplify", "qnt_simplify"),
+        ];
+
+        if let Some(canonical) = self.rule_aliases.get(rule) {
+            return canonical;
+        }
+        if self.alethe_version == Some(AletheVersion::Current) {
+            return rule;
+        }
+        BUILTIN_RULE_ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == rule)
+            .map_or(rule, |(_, canonical)| canonical)
+    }
+
+    /// Returns `true` if strict checking is enabled for the given rule. The `assume` and `anchor`
+    /// commands are also considered rules for this purpose.
+    pub fn is_strict(&self, rule: &str) -> bool {
+        self.strict || self.strict_rules.contains(rule)
+    }
+
+    /// Returns the options passed to the `la_generic` rule.
+    fn la_generic_options(&self) -> rules::linear_arithmetic::LaGenericOptions {
+        rules::linear_arithmetic::LaGenericOptions {
+            numeric_precheck: self.numeric_precheck,
+            certificates: self.la_certificates,
+            infer_args: self.la_infer_args,
+        }
+    }
+
+    /// Returns `true` if the step with the given id and rule should not be checked, either because
+    /// the rule is trusted, because only the proof skeleton is being checked, or because the step
+    /// was not selected using `only_steps`.
+    pub fn is_skipped(&self, rule: &str, step_id: &str) -> bool {
+        const SKELETON_RULES: &[&str] = &[
+            "resolution",
+            "th_resolution",
+            "strict_resolution",
+            "contraction",
+            "subproof",
+        ];
+        let is_selected = self
+            .only_steps
+            .as_ref()
+            .map_or(true, |s| s.contains(step_id));
+        !is_selected
+            || self.trusted_rules.contains(rule)
+            || (self.skeleton_only && !SKELETON_RULES.contains(&rule))
+    }
+
+    /// Returns an error if the given step, which uses the given rule, exceeds any of the limits
+    /// imposed on the size of its steps.
+    pub fn check_limits(&self, rule: &str, step: &ProofStep) -> Result<(), CheckerError> {
+        let rule_limits = self.rule_step_limits.get(rule).copied().unwrap_or_default();
+
+        if let Some(max) = rule_limits
+            .max_clause_len
+            .or(self.step_limits.max_clause_len)
+        {
+            if step.clause.len() > max {
+                return Err(CheckerError::LimitExceeded(LimitKind::ClauseLength, max));
+            }
+        }
+        if let Some(max) = rule_limits
+            .max_term_nodes
+            .or(self.step_limits.max_term_nodes)
+        {
+            let args = step.args.iter().flat_map(ProofArg::terms);
+            if count_term_nodes(step.clause.iter().chain(args), max) > max {
+                return Err(CheckerError::LimitExceeded(LimitKind::TermNodes, max));
+            }
+        }
+        Ok(())
+    }
 }
 
+/// Checks, in debug builds, that the conclusion and arguments of a step are interned in the pool.
+/// See [`pool::debug_assert_interned`].
+fn debug_assert_step_interned(pool: &dyn TermPool, step: &ProofStep) {
+    let args = step.args.iter().flat_map(ProofArg::terms);
+    pool::debug_assert_interned(pool, step.clause.iter().chain(args));
+}
+
+/// Checks that the ids of the commands in a subproof extend the id of the step that ends it, like
+/// `t5.t1` in a subproof that ends with `t5`. Commands in nested subproofs are checked when their
+/// own subproof is.
+fn check_subproof_ids(subproof: &Subproof) -> Result<(), CheckerError> {
+    let (end, inner) = subproof.commands.split_last().unwrap();
+    match inner.iter().find(|c| !c.id().is_nested_in(end.id())) {
+        Some(c) => Err(SubproofError::IdNotNested(c.id().clone(), end.id().clone()).into()),
+        None => Ok(()),
+    }
+}
+
+/// Counts the distinct subterms of the given terms, stopping as soon as the count goes over
+/// `limit`.
+fn count_term_nodes<'a>(terms: impl Iterator<Item = &'a Rc<Term>>, limit: usize) -> usize {
+    let mut seen = IndexSet::new();
+    let mut stack: Vec<_> = terms.collect();
+    while let Some(term) = stack.pop() {
+        if seen.len() > limit {
+            break;
+        }
+        if seen.insert(term) {
+            stack.extend(term.children());
+        }
+    }
+    seen.len()
+}
+
+/// The state of a streaming check, which must be kept between calls to
+/// [`ProofChecker::check_streaming_command`].
+///
+/// Any command of the root proof may be referenced as a premise by any later command, so the id
+/// and clause of every command checked so far are kept until the end of the check. Everything
+/// else, like the arguments of steps and the commands inside subproofs, is discarded. The memory
+/// used is therefore proportional to the total size of the conclusions of the root proof, and not
+/// to the size of the whole proof.
+#[derive(Debug, Default)]
+pub struct StreamingState {
+    /// The commands of the root proof that were already checked. Only their ids and clauses are
+    /// kept, so they can be referenced as premises by later commands.
+    root: Vec<ProofCommand>,
+}
+
+impl StreamingState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// The progress of the checker through a proof, reported to the callback set with
+/// [`ProofChecker::on_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckProgress {
+    /// The number of `assume` and `step` commands checked so far, including the ones inside
+    /// subproofs. This is never reset, so it keeps counting if the checker is used to check many
+    /// proofs, or in a streaming check.
+    pub commands_checked: usize,
+}
+
+/// A callback that receives the progress of the checker. See [`ProofChecker::on_progress`].
+type ProgressCallback = Box<dyn FnMut(CheckProgress) -> ControlFlow<()>>;
+
 pub struct ProofChecker<'c> {
     pool: &'c mut PrimitivePool,
     config: Config,
     prelude: &'c ProblemPrelude,
     context: ContextStack,
+    #[cfg(feature = "elaborator")]
     elaborator: Option<Elaborator>,
+    literal_cache: Option<rules::resolution::LiteralCache>,
     reached_empty_clause: bool,
     is_holey: bool,
+
+    // If this is set, invalid steps are recorded in this report instead of stopping the check
+    coverage: Option<RuleCoverage>,
+
+    // If this is set, the pivots found when checking resolution steps are recorded here, indexed
+    // by step id
+    resolution_pivots: Option<IndexMap<StepId, ResolutionPivots>>,
+
+    // If this is set, the commands that were only accepted using lenient reasoning are recorded
+    // in this report
+    leniencies: Option<LeniencyReport>,
+
+    // The steps that use rules not implemented by the checker. These are always recorded, even if
+    // the steps are skipped because of the `ignore_unknown_rules` option
+    unknown_rules: UnknownRuleReport,
+
+    progress_callback: Option<ProgressCallback>,
+    commands_checked: usize,
 }
 
 impl<'c> ProofChecker<'c> {
     pub fn new(pool: &'c mut PrimitivePool, config: Config, prelude: &'c ProblemPrelude) -> Self {
+        let literal_cache = config
+            .resolution_cache
+            .then(rules::resolution::LiteralCache::new);
         ProofChecker {
             pool,
             config,
             prelude,
             context: ContextStack::new(),
+            #[cfg(feature = "elaborator")]
             elaborator: None,
+            literal_cache,
             reached_empty_clause: false,
             is_holey: false,
+            coverage: None,
+            resolution_pivots: None,
+            leniencies: None,
+            unknown_rules: UnknownRuleReport::new(),
+            progress_callback: None,
+            commands_checked: 0,
         }
     }
 
+    /// Sets a callback that is called after each `assume` or `step` command is checked. This can
+    /// be used to report the progress of checking very large proofs, for example, in the UI of an
+    /// application that embeds the checker. If the callback returns `ControlFlow::Break`, the
+    /// check stops with an [`Error::Interrupted`] error, which allows aborting it.
+    ///
+    /// The total number of commands in a proof can be found using [`Proof::iter`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use carcara::{checker::{self, ProofChecker}, parser::{parse_instance, Config}};
+    /// # use std::ops::ControlFlow;
+    /// let problem = "(declare-fun p () Bool) (assert p) (assert (not p))";
+    /// let proof = "(assume h1 p) (assume h2 (not p)) (step t3 (cl) :rule resolution :premises (h1 h2))";
+    /// let (prelude, proof, mut pool) =
+    ///     parse_instance(problem.as_bytes(), proof.as_bytes(), Config::new())?;
+    ///
+    /// let mut checker = ProofChecker::new(&mut pool, checker::Config::new(), &prelude);
+    /// checker.on_progress(|progress| {
+    ///     if progress.commands_checked < 2 {
+    ///         ControlFlow::Continue(())
+    ///     } else {
+    ///         ControlFlow::Break(())
+    ///     }
+    /// });
+    /// let result = checker.check(&proof);
+    /// assert!(matches!(result, Err(carcara::Error::Interrupted)));
+    /// # Ok::<(), carcara::Error>(())
+    /// ```
+    pub fn on_progress(
+        &mut self,
+        callback: impl FnMut(CheckProgress) -> ControlFlow<()> + 'static,
+    ) {
+        self.progress_callback = Some(Box::new(callback));
+    }
+
+    /// Returns every rule not implemented by the checker that was found so far, with the number
+    /// of steps that use it. See [`UnknownRuleReport`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use carcara::{checker::{self, ProofChecker}, parser::{parse_instance, Config}};
+    /// let problem = "(declare-fun p () Bool) (assert p) (assert (not p))";
+    /// let proof = "(step t1 (cl p) :rule foo) (step t2 (cl) :rule foo) (step t3 (cl) :rule bar)";
+    /// let (prelude, proof, mut pool) =
+    ///     parse_instance(problem.as_bytes(), proof.as_bytes(), Config::new())?;
+    ///
+    /// let config = checker::Config::new().ignore_unknown_rules(true);
+    /// let mut checker = ProofChecker::new(&mut pool, config, &prelude);
+    /// assert!(checker.check(&proof)?);
+    /// let report = checker.unknown_rules();
+    /// assert_eq!(report.rules["foo"].count, 2);
+    /// assert_eq!(report.rules["foo"].examples, ["t1", "t2"]);
+    /// assert_eq!(report.rules["bar"].count, 1);
+    /// # Ok::<(), carcara::Error>(())
+    /// ```
+    pub fn unknown_rules(&self) -> &UnknownRuleReport {
+        &self.unknown_rules
+    }
+
+    /// Returns the term pool used by the checker.
+    pub(crate) fn pool(&mut self) -> &mut PrimitivePool {
+        self.pool
+    }
+
+    fn report_progress(&mut self) -> CarcaraResult<()> {
+        self.commands_checked += 1;
+        if let Some(callback) = &mut self.progress_callback {
+            let progress = CheckProgress {
+                commands_checked: self.commands_checked,
+            };
+            if callback(progress).is_break() {
+                return Err(Error::Interrupted);
+            }
+        }
+        Ok(())
+    }
+
     pub fn check(&mut self, proof: &Proof) -> CarcaraResult<bool> {
-        self.check_impl(
-            proof,
-            None::<&mut CheckerStatistics<OnlineBenchmarkResults>>,
-        )
+        self.check_impl(proof, None::<&mut CheckerStatistics<NullResults>>)
     }
 
     pub fn check_with_stats<CR: CollectResults + Send + Default>(
@@ -116,10 +548,152 @@ impl<'c> ProofChecker<'c> {
         proof: &Proof,
         mut stats: Option<&mut CheckerStatistics<CR>>,
     ) -> CarcaraResult<bool> {
+        let probe = stats.as_ref().and_then(|s| s.probe);
+        if probe.is_some() {
+            stats = None;
+        }
+        self.config.select_steps(proof)?;
+        logic::validate(self.prelude, proof.iter(), self.config.logic_validation)
+            .map_err(|e| e.with_step_info(proof))?;
+        self.check_commands(proof.iter(), &proof.premises, probe, &mut stats)
+            .map_err(|e| e.with_step_info(proof))?;
+        self.unknown_rules.log_warnings();
+        if self.reached_empty_clause {
+            Ok(self.is_holey)
+        } else {
+            Err(Error::DoesNotReachEmptyClause)
+        }
+    }
+
+    /// Checks all steps of a proof, without stopping at invalid ones, and reports how many steps of
+    /// each rule were checked, skipped or found invalid. Steps that use unknown rules are always
+    /// reported as unimplemented, regardless of the `ignore_unknown_rules` option. Errors that
+    /// can't be attributed to a single step, like errors in the arguments of an anchor, still stop
+    /// the check.
+    pub fn check_coverage(&mut self, proof: &Proof) -> CarcaraResult<RuleCoverage> {
+        self.config.ignore_unknown_rules = false;
+        self.config.select_steps(proof)?;
+        self.coverage = Some(RuleCoverage::new());
+        let result = self.check_commands(
+            proof.iter(),
+            &proof.premises,
+            None,
+            &mut None::<&mut CheckerStatistics<NullResults>>,
+        );
+        let mut coverage = self.coverage.take().unwrap();
+        coverage.unknown_rules.combine(&self.unknown_rules);
+        result.map_err(|e| e.with_step_info(proof))?;
+        Ok(coverage)
+    }
+
+    /// Checks a proof, like [`ProofChecker::check`], and also returns the pivots found by the
+    /// checker for each `resolution` and `th_resolution` step that doesn't already have them as
+    /// arguments, indexed by step id. This allows tools that reconstruct the proof to use these
+    /// pivots instead of searching for them again. Steps that were accepted without finding the
+    /// pivots, for example by the RUP check, are not included. See [`ResolutionPivots`].
+    pub fn check_with_resolution_pivots(
+        &mut self,
+        proof: &Proof,
+    ) -> CarcaraResult<(bool, IndexMap<StepId, ResolutionPivots>)> {
+        self.resolution_pivots = Some(IndexMap::new());
+        let result = self.check(proof);
+        let pivots = self.resolution_pivots.take().unwrap();
+        Ok((result?, pivots))
+    }
+
+    /// Checks a proof, like [`ProofChecker::check`], and also reports every command that was only
+    /// accepted using lenient reasoning that a strict checker would reject, like implicitly
+    /// flipped equalities or double negations. This allows proof producers to find the places
+    /// where their output must be tightened to pass strict checkers. Only the rules that are not
+    /// checked strictly can be lenient. See [`Leniency`] for the kinds of lenient reasoning
+    /// reported.
+    pub fn check_leniency(&mut self, proof: &Proof) -> CarcaraResult<(bool, LeniencyReport)> {
+        self.leniencies = Some(LeniencyReport::new());
+        let result = self.check(proof);
+        let mut report = self.leniencies.take().unwrap();
+        for step in &mut report.steps {
+            step.span = proof.spans.get(step.id.as_str()).copied();
+        }
+        Ok((result?, report))
+    }
+
+    /// Checks a command of the root proof, as part of a streaming check. This allows checking a
+    /// proof whose commands are parsed one at a time (see [`Parser::parse_proof_streaming`]), and
+    /// discarding each command after it is checked, instead of keeping the whole proof in memory.
+    /// The commands must be given in the order they appear in the proof.
+    ///
+    /// The same checker must be used for all commands of the proof, and
+    /// [`ProofChecker::finish_streaming`] must be called after the last one. Since the whole proof
+    /// is never available, the steps selected using the `only_steps` option are not extended with
+    /// their transitive premises, so only the selected steps themselves are checked. To check them
+    /// together with their premises, use [`ProofChecker::check`] instead.
+    ///
+    /// [`Parser::parse_proof_streaming`]: crate::parser::Parser::parse_proof_streaming
+    pub fn check_streaming_command(
+        &mut self,
+        state: &mut StreamingState,
+        command: ProofCommand,
+        premises: &IndexSet<Rc<Term>>,
+    ) -> CarcaraResult<()> {
+        state.root.push(command);
+        let index = state.root.len() - 1;
+        let iter = ProofIter::starting_at(&state.root, index);
+        logic::validate(
+            self.prelude,
+            ProofIter::starting_at(&state.root, index),
+            self.config.logic_validation,
+        )?;
+        self.check_commands(
+            iter,
+            premises,
+            None,
+            &mut None::<&mut CheckerStatistics<NullResults>>,
+        )?;
+
+        // Later commands can only reference this command by its id and clause, so we discard
+        // everything else, including any subproof commands
+        let command = &mut state.root[index];
+        if !command.is_assume() {
+            *command = ProofCommand::Step(ProofStep {
+                id: command.id().clone(),
+                clause: command.clause().to_vec(),
+                rule: Symbol::default(),
+                premises: Vec::new(),
+                args: Vec::new(),
+                discharge: Vec::new(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Finishes a streaming check after all commands were checked using
+    /// [`ProofChecker::check_streaming_command`]. Returns `true` if the proof is holey, or an error
+    /// if it does not reach the empty clause.
+    pub fn finish_streaming(&mut self) -> CarcaraResult<bool> {
+        self.unknown_rules.log_warnings();
+        if self.reached_empty_clause {
+            Ok(self.is_holey)
+        } else {
+            Err(Error::DoesNotReachEmptyClause)
+        }
+    }
+
+    /// Checks all the commands yielded by the given iterator, including the commands in subproofs.
+    fn check_commands<CR: CollectResults + Send + Default>(
+        &mut self,
+        mut iter: ProofIter,
+        premises: &IndexSet<Rc<Term>>,
+        probe: Option<&StepProbe>,
+        stats: &mut Option<&mut CheckerStatistics<CR>>,
+    ) -> CarcaraResult<()> {
         // Similarly to the parser, to avoid stack overflows in proofs with many nested subproofs,
         // we check the subproofs iteratively, instead of recursively
-        let mut iter = proof.iter();
+        let mut command_index = 0;
         while let Some(command) = iter.next() {
+            if let Some(probe) = probe {
+                probe.enter(command_index);
+                command_index += 1;
+            }
             match command {
                 ProofCommand::Step(step) => {
                     let is_end_of_subproof = iter.is_end_step();
@@ -135,18 +709,32 @@ impl<'c> ProofChecker<'c> {
                     } else {
                         None
                     };
-                    self.check_step(step, previous_command, &iter, &mut stats)
-                        .map_err(|e| Error::Checker {
-                            inner: e,
-                            rule: step.rule.clone(),
-                            step: step.id.clone(),
-                        })?;
+                    // To tell whether this step was skipped, we check if it alone makes the proof
+                    // holey
+                    let was_holey = std::mem::take(&mut self.is_holey);
+                    let result = self.check_step(step, previous_command, &iter, stats);
+                    let is_skipped = self.is_holey;
+                    self.is_holey |= was_holey;
+                    match &mut self.coverage {
+                        Some(coverage) => coverage.register(&step.rule, &result, is_skipped),
+                        None => result.map_err(|e| Error::Checker {
+                            inner: Box::new(e),
+                            rule: step.rule.to_string(),
+                            step: step.id.to_string(),
+                            span: None,
+                            annotations: None,
+                        })?,
+                    }
 
                     // If this is the last command of a subproof, we have to pop the subproof
                     // commands off of the stack. The parser already ensures that the last command
                     // in a subproof is always a `step` command
                     if is_end_of_subproof {
                         self.context.pop();
+                        if let Some(cache) = &mut self.literal_cache {
+                            cache.close_subproof(iter.depth());
+                        }
+                        #[cfg(feature = "elaborator")]
                         if let Some(elaborator) = &mut self.elaborator {
                             elaborator.close_subproof();
                         }
@@ -160,6 +748,17 @@ impl<'c> ProofChecker<'c> {
                     let time = Instant::now();
                     let step_id = command.id();
 
+                    let anchor_error = |e: CheckerError| Error::Checker {
+                        inner: Box::new(e),
+                        rule: "anchor".into(),
+                        step: step_id.to_string(),
+                        span: None,
+                        annotations: None,
+                    };
+                    if self.config.is_strict("anchor") {
+                        check_subproof_ids(s).map_err(anchor_error)?;
+                    }
+
                     let new_context_id = self.context.force_new_context();
                     self.context
                         .push(
@@ -168,17 +767,14 @@ impl<'c> ProofChecker<'c> {
                             &s.variable_args,
                             new_context_id,
                         )
-                        .map_err(|e| Error::Checker {
-                            inner: e.into(),
-                            rule: "anchor".into(),
-                            step: step_id.to_owned(),
-                        })?;
+                        .map_err(|e| anchor_error(e.into()))?;
 
+                    #[cfg(feature = "elaborator")]
                     if let Some(elaborator) = &mut self.elaborator {
                         elaborator.open_subproof(s.commands.len());
                     }
 
-                    if let Some(stats) = &mut stats {
+                    if let Some(stats) = stats {
                         let rule_name = match s.commands.last() {
                             Some(ProofCommand::Step(step)) => format!("anchor({})", &step.rule),
                             _ => "anchor".to_owned(),
@@ -192,23 +788,33 @@ impl<'c> ProofChecker<'c> {
                     }
                 }
                 ProofCommand::Assume { id, term } => {
-                    if !self.check_assume(id, term, &proof.premises, &iter, &mut stats) {
+                    let is_valid = self.check_assume(id, term, premises, &iter, stats);
+                    if let Some(coverage) = &mut self.coverage {
+                        let result = if is_valid {
+                            Ok(())
+                        } else {
+                            Err(CheckerError::Assume(term.clone()))
+                        };
+                        coverage.register("assume", &result, false);
+                    } else if !is_valid {
                         return Err(Error::Checker {
-                            inner: CheckerError::Assume(term.clone()),
+                            inner: Box::new(CheckerError::Assume(term.clone())),
                             rule: "assume".into(),
-                            step: id.clone(),
+                            step: id.to_string(),
+                            span: None,
+                            annotations: None,
                         });
                     }
                 }
             }
+            if !matches!(command, ProofCommand::Subproof(_)) {
+                self.report_progress()?;
+            }
         }
-        if self.reached_empty_clause {
-            Ok(self.is_holey)
-        } else {
-            Err(Error::DoesNotReachEmptyClause)
-        }
+        Ok(())
     }
 
+    #[cfg(feature = "elaborator")]
     pub fn check_and_elaborate(&mut self, mut proof: Proof) -> CarcaraResult<(bool, Proof)> {
         self.elaborator = Some(Elaborator::new());
         let result = self.check(&proof);
@@ -223,6 +829,50 @@ impl<'c> ProofChecker<'c> {
         Ok((self.is_holey, proof))
     }
 
+    /// Checks and elaborates a proof, like `ProofChecker::check_and_elaborate`, but also returns a
+    /// record of each command that the elaborator changed, with the commands that replace it and
+    /// the reason for the change.
+    #[cfg(feature = "elaborator")]
+    pub fn check_and_elaborate_explained(
+        &mut self,
+        mut proof: Proof,
+    ) -> CarcaraResult<(bool, Proof, Vec<StepExplanation>)> {
+        self.elaborator = Some(Elaborator::new());
+        let result = self.check(&proof);
+
+        // We reset `self.elaborator` before returning any errors encountered while checking so we
+        // don't leave the checker in an invalid state
+        let mut elaborator = self.elaborator.take().unwrap();
+        result?;
+
+        let (commands, explanations) = elaborator.end_explained(proof.commands);
+        proof.commands = commands;
+        Ok((self.is_holey, proof, explanations))
+    }
+
+    /// Checks and elaborates a proof, like `ProofChecker::check_and_elaborate`, but passes each
+    /// command of the elaborated root proof to `emit` as soon as it is built, instead of returning
+    /// the whole elaborated proof. Unlike `ProofChecker::check_and_elaborate`, the elaborated proof
+    /// is not pruned. Returns `true` if the proof is holey.
+    #[cfg(feature = "elaborator")]
+    pub fn check_and_elaborate_streaming(
+        &mut self,
+        proof: Proof,
+        emit: impl FnMut(ProofCommand) -> std::io::Result<()>,
+    ) -> CarcaraResult<bool> {
+        self.elaborator = Some(Elaborator::new());
+        let result = self.check(&proof);
+
+        // We reset `self.elaborator` before returning any errors encountered while checking so we
+        // don't leave the checker in an invalid state
+        let mut elaborator = self.elaborator.take().unwrap();
+        result?;
+
+        elaborator.end_streaming(proof.commands, emit)?;
+        Ok(self.is_holey)
+    }
+
+    #[cfg(feature = "elaborator")]
     pub fn check_and_elaborate_with_stats<'s, CR: CollectResults + Send + Default>(
         &'s mut self,
         mut proof: Proof,
@@ -257,6 +907,7 @@ impl<'c> ProofChecker<'c> {
         // problem premises, but are instead local assumptions that are discharged by the subproof's
         // final step, so we ignore the `assume` command if it is inside a subproof.
         if iter.is_in_subproof() {
+            #[cfg(feature = "elaborator")]
             if let Some(elaborator) = &mut self.elaborator {
                 elaborator.assume(term);
             }
@@ -271,13 +922,14 @@ impl<'c> ProofChecker<'c> {
                 s.results
                     .add_assume_measurement(s.file_name, id, true, time);
             }
+            #[cfg(feature = "elaborator")]
             if let Some(elaborator) = &mut self.elaborator {
                 elaborator.assume(term);
             }
             return true;
         }
 
-        if self.config.strict {
+        if self.config.is_strict("assume") {
             return false;
         }
 
@@ -285,7 +937,14 @@ impl<'c> ProofChecker<'c> {
         let mut polyeq_time = Duration::ZERO;
         let mut core_time = Duration::ZERO;
 
-        for p in premises {
+        // If the `assume` command has the same id as the name given to a premise using the `:named`
+        // attribute, we try to match it against that premise first
+        let named = self
+            .prelude
+            .named_terms
+            .get(id)
+            .filter(|p| premises.contains(*p));
+        for p in named.into_iter().chain(premises) {
             let mut this_polyeq_time = Duration::ZERO;
             let (result, depth) = tracing_polyeq(term, p, &mut this_polyeq_time);
             polyeq_time += this_polyeq_time;
@@ -299,12 +958,45 @@ impl<'c> ProofChecker<'c> {
             }
         }
 
-        let Some(p) = found else { return false };
+        let mut normalized = false;
+        if found.is_none() && self.config.assume_normalizations.any() {
+            found = find_normalized_premise(
+                self.pool,
+                term,
+                premises,
+                self.config.assume_normalizations,
+                &mut polyeq_time,
+            );
+            normalized = found.is_some();
+        }
 
+        #[cfg_attr(not(feature = "elaborator"), allow(unused_variables))]
+        let Some(p) = found
+        else {
+            return false;
+        };
+
+        if let Some(report) = &mut self.leniencies {
+            let leniency = if normalized {
+                Leniency::AssumeNormalization
+            } else {
+                Leniency::EqualityFlip
+            };
+            report.register(StepId::new(id), "assume", leniency);
+        }
+
+        #[cfg(feature = "elaborator")]
         if let Some(elaborator) = &mut self.elaborator {
             let elaboration_time = Instant::now();
 
-            elaborator.elaborate_assume(self.pool, p, term.clone(), id);
+            if normalized {
+                // The elaborator can't justify the normalizations, so the `assume` command is kept
+                // as is, and is considered a hole
+                self.is_holey = true;
+                elaborator.assume(term);
+            } else {
+                elaborator.elaborate_assume(self.pool, p, term.clone(), id);
+            }
 
             if let Some(s) = &mut stats {
                 s.elaboration_time += elaboration_time.elapsed();
@@ -334,44 +1026,67 @@ impl<'c> ProofChecker<'c> {
         let time = Instant::now();
         let mut polyeq_time = Duration::ZERO;
 
-        if !step.discharge.is_empty() && step.rule != "subproof" {
+        let rule_name = self.config.canonical_rule_name(&step.rule).to_owned();
+        if !step.discharge.is_empty() && rule_name != "subproof" {
             return Err(CheckerError::Subproof(SubproofError::DischargeInWrongRule));
         }
+        self.config.check_limits(&rule_name, step)?;
 
+        #[cfg_attr(not(feature = "elaborator"), allow(unused_mut))]
         let mut elaborated = false;
-        if step.rule == "lia_generic" {
+        let mut measured_rule = None;
+        if self.config.is_skipped(&rule_name, &step.id) {
+            self.is_holey = true;
+            #[cfg(feature = "elaborator")]
+            if let Some(elaborator) = &mut self.elaborator {
+                elaborator.unchanged(&step.clause);
+            }
+        } else if rule_name == "lia_generic" {
             if let Some(options) = &self.config.lia_options {
-                let is_hole = lia_generic::lia_generic_single_thread(
-                    self.pool,
-                    &step.clause,
-                    self.prelude,
-                    self.elaborator.as_mut(),
-                    &step.id,
-                    options,
-                );
+                #[cfg(feature = "elaborator")]
+                let is_hole = {
+                    elaborated = self.elaborator.is_some();
+                    lia_generic::lia_generic_single_thread(
+                        self.pool,
+                        &step.clause,
+                        self.prelude,
+                        self.elaborator.as_mut(),
+                        &step.id,
+                        options,
+                    )
+                };
+                // Without the elaborator, the solver proof is only checked, so it doesn't need to
+                // be imported into the checker's pool
+                #[cfg(not(feature = "elaborator"))]
+                let is_hole =
+                    lia_generic::lia_generic_multi_thread(&step.clause, self.prelude, options);
                 self.is_holey = self.is_holey || is_hole;
-                elaborated = self.elaborator.is_some();
             } else {
                 log::warn!("encountered \"lia_generic\" rule, ignoring");
                 self.is_holey = true;
+                #[cfg(feature = "elaborator")]
                 if let Some(elaborator) = &mut self.elaborator {
                     elaborator.unchanged(&step.clause);
                 }
             }
         } else {
-            let rule = match Self::get_rule(&step.rule, self.config.strict) {
+            let rule = match Self::get_rule(&rule_name, &self.config) {
                 Some(r) => r,
-                None if self.config.ignore_unknown_rules => {
+                None => {
+                    self.unknown_rules.register(&step.rule, step.id.clone());
+                    if !self.config.ignore_unknown_rules {
+                        return Err(CheckerError::UnknownRule);
+                    }
                     self.is_holey = true;
+                    #[cfg(feature = "elaborator")]
                     if let Some(elaborator) = &mut self.elaborator {
                         elaborator.unchanged(&step.clause);
                     }
                     return Ok(());
                 }
-                None => return Err(CheckerError::UnknownRule),
             };
 
-            if step.rule == "hole" {
+            if rule_name == "hole" {
                 self.is_holey = true;
             }
 
@@ -389,6 +1104,13 @@ impl<'c> ProofChecker<'c> {
                 .map(|&i| iter.get_premise(i))
                 .collect();
 
+            // Pivots are only recorded for resolution steps checked by the non-strict rule, which
+            // searches for them
+            let is_strict = self.config.is_strict(&rule_name);
+            let finds_pivots =
+                matches!(rule_name.as_str(), "resolution" | "th_resolution") && !is_strict;
+
+            debug_assert_step_interned(&*self.pool, step);
             let rule_args = RuleArgs {
                 conclusion: &step.clause,
                 premises: &premises,
@@ -398,18 +1120,41 @@ impl<'c> ProofChecker<'c> {
                 previous_command,
                 discharge: &discharge,
                 polyeq_time: &mut polyeq_time,
+                literal_cache: self.literal_cache.as_mut(),
+                la_generic_options: self.config.la_generic_options(),
             };
 
-            if let Some(elaborator) = &mut self.elaborator {
-                if let Some(elaboration_rule) = Self::get_elaboration_rule(&step.rule) {
-                    elaboration_rule(rule_args, step.id.clone(), elaborator)?;
-                    elaborated = true;
+            #[cfg_attr(not(feature = "elaborator"), allow(unused_labels))]
+            'check: {
+                #[cfg(feature = "elaborator")]
+                if let Some(elaborator) = &mut self.elaborator {
+                    if let Some(elaboration_rule) =
+                        Self::get_elaboration_rule(&rule_name, &self.config)
+                    {
+                        elaboration_rule(rule_args, step.id.clone(), elaborator)?;
+                        elaborated = true;
+                    } else {
+                        rule(rule_args)?;
+                        elaborator.unchanged(&step.clause);
+                    }
+                    break 'check;
+                }
+
+                if let Some(pivots) = self.resolution_pivots.as_mut().filter(|_| finds_pivots) {
+                    if let Some(found) = rules::resolution::find_resolution_pivots(rule_args)? {
+                        pivots.insert(step.id.clone(), found);
+                    }
+                } else if let Some(report) = self.leniencies.as_mut().filter(|_| !is_strict) {
+                    if let Some(leniency) = rules::find_leniency(&rule_name, rule, rule_args)? {
+                        report.register(step.id.clone(), &step.rule, leniency);
+                    }
+                } else if rule_name == "all_simplify" && stats.is_some() {
+                    // When collecting statistics, we also record which rewrite closed the step
+                    let rewrite = rules::simplification::find_all_simplify_rewrite(rule_args)?;
+                    measured_rule = Some(format!("all_simplify({})", rewrite));
                 } else {
                     rule(rule_args)?;
-                    elaborator.unchanged(&step.clause);
                 }
-            } else {
-                rule(rule_args)?;
             }
         }
 
@@ -420,9 +1165,9 @@ impl<'c> ProofChecker<'c> {
 
         if let Some(s) = stats {
             let time = time.elapsed();
-
+            let rule = measured_rule.as_deref().unwrap_or(&step.rule);
             s.results
-                .add_step_measurement(s.file_name, &step.id, &step.rule, time);
+                .add_step_measurement(s.file_name, &step.id, rule, time);
             s.polyeq_time += polyeq_time;
             if elaborated {
                 s.elaboration_time += time;
@@ -443,16 +1188,18 @@ impl<'c> ProofChecker<'c> {
             .find(|&(i, command)| command.is_assume() && !discharge.contains(&(depth, i)))
         {
             Err(CheckerError::Subproof(
-                SubproofError::LocalAssumeNotDischarged(not_discharged.id().to_owned()),
+                SubproofError::LocalAssumeNotDischarged(not_discharged.id().to_string()),
             ))
         } else {
             Ok(())
         }
     }
 
-    pub fn get_rule(rule_name: &str, strict: bool) -> Option<Rule> {
+    pub fn get_rule(rule_name: &str, config: &Config) -> Option<Rule> {
         use rules::*;
 
+        let strict = config.is_strict(rule_name);
+
         Some(match rule_name {
             "true" => tautology::r#true,
             "false" => tautology::r#false,
@@ -494,7 +1241,9 @@ impl<'c> ProofChecker<'c> {
             "refl" if strict => reflexivity::strict_refl,
             "refl" => reflexivity::refl,
             "trans" => transitivity::trans,
+            "cong" if strict => congruence::strict_cong,
             "cong" => congruence::cong,
+            "ho_cong" if strict => congruence::strict_ho_cong,
             "ho_cong" => congruence::ho_cong,
             "and" => clausification::and,
             "tautology" => resolution::tautology,
@@ -538,6 +1287,7 @@ impl<'c> ProofChecker<'c> {
             "comp_simplify" => simplification::comp_simplify,
             "nary_elim" => clausification::nary_elim,
             "ac_simp" => simplification::ac_simp,
+            "all_simplify" => simplification::all_simplify,
             "bfun_elim" => clausification::bfun_elim,
             "bind" => subproof::bind,
             "qnt_cnf" => quantifier::qnt_cnf,
@@ -572,10 +1322,14 @@ impl<'c> ProofChecker<'c> {
         })
     }
 
-    fn get_elaboration_rule(rule_name: &str) -> Option<ElaborationRule> {
+    #[cfg(feature = "elaborator")]
+    fn get_elaboration_rule(rule_name: &str, config: &Config) -> Option<ElaborationRule> {
         use rules::*;
 
         Some(match rule_name {
+            "cong" if config.sort_premises => congruence::elaborate_cong,
+            "ho_cong" if config.sort_premises => congruence::elaborate_ho_cong,
+            "la_generic" if config.la_infer_args => linear_arithmetic::elaborate_la_generic,
             "eq_transitive" => transitivity::elaborate_eq_transitive,
             "resolution" | "th_resolution" => resolution::elaborate_resolution,
             "refl" => reflexivity::elaborate_refl,
@@ -584,3 +1338,509 @@ impl<'c> ProofChecker<'c> {
         })
     }
 }
+
+/// Searches for a premise that is polyequal to the given `assume` term after the given
+/// normalizations are applied to both of them.
+fn find_normalized_premise<P: TermPool + ?Sized>(
+    pool: &mut P,
+    term: &Rc<Term>,
+    premises: &IndexSet<Rc<Term>>,
+    normalizations: AssumeNormalizations,
+    polyeq_time: &mut Duration,
+) -> Option<Rc<Term>> {
+    let mut cache = IndexMap::new();
+    let term = normalize(pool, term, normalizations, &mut cache);
+    premises
+        .iter()
+        .find(|p| {
+            let p = normalize(pool, p, normalizations, &mut cache);
+            polyeq(&term, &p, polyeq_time)
+        })
+        .cloned()
+}
+
+/// Applies the given normalizations to all operator applications in a term, bottom-up.
+fn normalize<P: TermPool + ?Sized>(
+    pool: &mut P,
+    term: &Rc<Term>,
+    normalizations: AssumeNormalizations,
+    cache: &mut IndexMap<Rc<Term>, Rc<Term>>,
+) -> Rc<Term> {
+    if let Some(t) = cache.get(term) {
+        return t.clone();
+    }
+    let Term::Op(op, args) = term.as_ref() else {
+        return term.clone();
+    };
+
+    let mut args: Vec<_> = args
+        .iter()
+        .map(|a| normalize(pool, a, normalizations, cache))
+        .collect();
+
+    let result = match op {
+        Operator::Not if normalizations.normalize_negations => match args[0].remove_negation() {
+            Some(inner) => inner.clone(),
+            None => pool.add(Term::Op(*op, args)),
+        },
+        Operator::And if normalizations.flatten_and => {
+            // Since the arguments were already normalized, any nested conjunction is already flat
+            let flattened = args
+                .into_iter()
+                .flat_map(|a| match a.as_ref() {
+                    Term::Op(Operator::And, inner) => inner.clone(),
+                    _ => vec![a],
+                })
+                .collect();
+            pool.add(Term::Op(*op, flattened))
+        }
+        Operator::Or if normalizations.sort_or => {
+            // Terms are hash consed, so their addresses give a canonical order within the pool
+            args.sort_by_key(|a| a.as_ref() as *const Term);
+            pool.add(Term::Op(*op, args))
+        }
+        _ => pool.add(Term::Op(*op, args)),
+    };
+    cache.insert(term.clone(), result.clone());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::tests::parse_terms;
+
+    #[test]
+    fn test_find_normalized_premise() {
+        fn run_case(normalizations: AssumeNormalizations, assume: &str, premise: &str) -> bool {
+            let definitions = "(declare-fun a () Bool) (declare-fun b () Bool)
+                (declare-fun c () Bool) (declare-fun x () Int) (declare-fun y () Int)";
+            let mut pool = PrimitivePool::new();
+            let [assume, premise] = parse_terms(&mut pool, definitions, [assume, premise]);
+            let premises = [premise].into_iter().collect();
+            let found = find_normalized_premise(
+                &mut pool,
+                &assume,
+                &premises,
+                normalizations,
+                &mut Duration::default(),
+            );
+            found.is_some()
+        }
+
+        let all = AssumeNormalizations {
+            flatten_and: true,
+            sort_or: true,
+            normalize_negations: true,
+        };
+        let cases = [
+            ("(and a (and b c))", "(and (and a b) c)", true),
+            ("(or a (not b) c)", "(or c a (not b))", true),
+            ("(not (not (or a b)))", "(or b a)", true),
+            ("(and (or b a) (not (not c)))", "(and (or a b) c)", true),
+            ("(and a b)", "(and a b c)", false),
+            ("(or a b)", "(and a b)", false),
+            ("(not a)", "a", false),
+        ];
+        for (assume, premise, expected) in cases {
+            assert_eq!(expected, run_case(all, assume, premise));
+        }
+
+        let only_flatten = AssumeNormalizations {
+            flatten_and: true,
+            ..Default::default()
+        };
+        assert!(run_case(only_flatten, "(and a (and b c))", "(and a b c)"));
+        assert!(!run_case(only_flatten, "(or a b)", "(or b a)"));
+        assert!(!run_case(only_flatten, "(not (not a))", "a"));
+    }
+
+    #[test]
+    fn test_strict_rules() {
+        let problem = "(declare-fun a () Bool) (assert a) (assert (not a))";
+        let proof = "(assume h1 a)
+            (assume h2 (not a))
+            (step t1 (cl (= (forall ((x Int)) (> x 0)) (forall ((y Int)) (> y 0)))) :rule refl)
+            (step t2 (cl) :rule resolution :premises (h1 h2))";
+        let options = |strict_rules: &[&str]| crate::CarcaraOptions {
+            strict_rules: strict_rules.iter().map(|&s| s.to_owned()).collect(),
+            flatten_assoc_ops: true,
+            ..Default::default()
+        };
+        let run = |strict_rules: &[&str]| {
+            crate::check(problem.as_bytes(), proof.as_bytes(), options(strict_rules))
+        };
+
+        // `t1` is only valid up to alpha equivalence, and `t2` doesn't give the pivots as
+        // arguments, so each step is rejected only if its rule is checked strictly
+        assert!(matches!(run(&[]), Ok(false)));
+        assert!(matches!(run(&["resolution"]), Err(Error::Checker { step, .. }) if step == "t2"));
+        assert!(matches!(run(&["refl"]), Err(Error::Checker { step, .. }) if step == "t1"));
+
+        // Terms are not flattened if any rule is checked strictly
+        assert!(options(&[]).parser_config().flatten_assoc_ops);
+        assert!(!options(&["refl"]).parser_config().flatten_assoc_ops);
+    }
+
+    #[test]
+    fn test_la_generic_options() {
+        use crate::checker::error::LinearArithmeticError;
+
+        let problem = "(declare-fun a () Real) (assert (> a 0.0))";
+        let proof = "(assume h1 (> a 0.0))
+            (step t1 (cl (> a 0.0) (> (* 2.0 a) 0.0)) :rule la_generic :args (1.0 1.0))
+            (step t2 (cl) :rule hole :premises (h1 t1))";
+        let run = |numeric_precheck, la_certificates| {
+            let options = crate::CarcaraOptions {
+                numeric_precheck,
+                la_certificates,
+                ..Default::default()
+            };
+            match crate::check(problem.as_bytes(), proof.as_bytes(), options) {
+                Err(Error::Checker { inner, .. }) => match *inner {
+                    CheckerError::LinearArithmetic(e) => e,
+                    other => panic!("unexpected error: {:?}", other),
+                },
+                other => panic!("unexpected result: {:?}", other),
+            }
+        };
+
+        assert!(matches!(
+            run(true, false),
+            LinearArithmeticError::NonZeroCoefficient(_)
+        ));
+        assert!(matches!(
+            run(false, true),
+            LinearArithmeticError::ContradictionCertificate(_)
+        ));
+
+        // When both are enabled, the pre-check runs first
+        assert!(matches!(
+            run(true, true),
+            LinearArithmeticError::NonZeroCoefficient(_)
+        ));
+    }
+
+    #[test]
+    fn test_canonical_rule_name() {
+        let config = Config::new();
+        assert_eq!("ac_simp", config.canonical_rule_name("tmp_AC_simp"));
+        assert_eq!("resolution", config.canonical_rule_name("resolution"));
+        assert_eq!("foo", config.canonical_rule_name("foo"));
+
+        let config = Config::new().rule_aliases([
+            ("res".to_owned(), "resolution".to_owned()),
+            ("tmp_AC_simp".to_owned(), "hole".to_owned()),
+            ("a".to_owned(), "res".to_owned()),
+        ]);
+        assert_eq!("resolution", config.canonical_rule_name("res"));
+        assert_eq!("hole", config.canonical_rule_name("tmp_AC_simp"));
+        assert_eq!("bfun_elim", config.canonical_rule_name("tmp_bfun_elim"));
+
+        // Aliases are not chained
+        assert_eq!("res", config.canonical_rule_name("a"));
+
+        // The built-in aliases only apply to the legacy version
+        let config = Config::new().alethe_version(Some(AletheVersion::Legacy));
+        assert_eq!("ac_simp", config.canonical_rule_name("tmp_AC_simp"));
+        let config = Config::new()
+            .alethe_version(Some(AletheVersion::Current))
+            .rule_aliases([("res".to_owned(), "resolution".to_owned())]);
+        assert_eq!("tmp_AC_simp", config.canonical_rule_name("tmp_AC_simp"));
+        assert_eq!("resolution", config.canonical_rule_name("res"));
+    }
+
+    #[test]
+    fn test_check_limits() {
+        let mut pool = PrimitivePool::new();
+        let [a, b, big] = parse_terms(
+            &mut pool,
+            "(declare-fun a () Bool) (declare-fun b () Bool)",
+            ["a", "b", "(and (or a b) (or a b) (not (or a b)))"],
+        );
+        let step = |clause: Vec<Rc<Term>>| ProofStep {
+            id: "t1".into(),
+            clause,
+            rule: "hole".into(),
+            premises: Vec::new(),
+            args: Vec::new(),
+            discharge: Vec::new(),
+        };
+        let is_exceeded = |config: &Config, rule: &str, step: &ProofStep, kind: LimitKind| {
+            matches!(
+                config.check_limits(rule, step),
+                Err(CheckerError::LimitExceeded(k, _)) if k == kind
+            )
+        };
+
+        let short = step(vec![a.clone(), b.clone()]);
+        let long = step(vec![a.clone(), b, a]);
+        let deep = step(vec![big]);
+
+        // No limits are imposed by default
+        let config = Config::new();
+        assert!(config.check_limits("hole", &long).is_ok());
+        assert!(config.check_limits("hole", &deep).is_ok());
+
+        let config = Config::new().step_limits(StepLimits {
+            max_clause_len: Some(2),
+            max_term_nodes: Some(4),
+        });
+        assert!(config.check_limits("hole", &short).is_ok());
+        assert!(is_exceeded(&config, "hole", &long, LimitKind::ClauseLength));
+
+        // Shared subterms are only counted once, so the term in `deep` has 5 distinct nodes
+        assert!(is_exceeded(&config, "hole", &deep, LimitKind::TermNodes));
+
+        // Rule-specific limits take precedence, and fall back to the default limits
+        let config = config.rule_step_limits([
+            (
+                "resolution".to_owned(),
+                StepLimits {
+                    max_clause_len: Some(3),
+                    ..Default::default()
+                },
+            ),
+            (
+                "resolution".to_owned(),
+                StepLimits {
+                    max_term_nodes: Some(5),
+                    ..Default::default()
+                },
+            ),
+        ]);
+        assert!(config.check_limits("resolution", &long).is_ok());
+        assert!(config.check_limits("resolution", &deep).is_ok());
+        assert!(is_exceeded(&config, "hole", &long, LimitKind::ClauseLength));
+    }
+
+    #[test]
+    fn test_only_steps() {
+        let problem = "(declare-fun a () Bool) (declare-fun b () Bool)
+            (assert a) (assert (not a))";
+        let proof = "(assume h1 a)
+            (assume h2 (not a))
+            (step t1 (cl b) :rule refl)
+            (step t2 (cl) :rule resolution :premises (h1 h2))";
+        let run = |only: &[&str]| {
+            let options = crate::CarcaraOptions {
+                only_steps: only.iter().map(|&s| s.to_owned()).collect(),
+                ..Default::default()
+            };
+            crate::check(problem.as_bytes(), proof.as_bytes(), options)
+        };
+
+        assert!(matches!(run(&[]), Err(Error::Checker { step, .. }) if step == "t1"));
+
+        // Since `t1` is not a premise of `t2`, it is not checked, and the proof is holey
+        assert!(matches!(run(&["t2"]), Ok(true)));
+        assert!(matches!(run(&["t1"]), Err(Error::Checker { step, .. }) if step == "t1"));
+        assert!(matches!(run(&["t3"]), Err(Error::UnknownStep(id)) if id == "t3"));
+
+        // The same holds when checking the proof while it is parsed
+        let run_streaming = |only: &[&str]| {
+            let options = crate::CarcaraOptions {
+                only_steps: only.iter().map(|&s| s.to_owned()).collect(),
+                ..Default::default()
+            };
+            crate::check_streaming(problem.as_bytes(), proof.as_bytes(), options)
+        };
+        assert!(matches!(run_streaming(&["t2"]), Ok(true)));
+        assert!(matches!(
+            run_streaming(&["t1"]),
+            Err(Error::Checker { step, .. }) if step == "t1"
+        ));
+        assert!(matches!(run_streaming(&["t3"]), Err(Error::UnknownStep(id)) if id == "t3"));
+    }
+
+    #[test]
+    fn test_subproof_ids() {
+        let problem = "(declare-fun a () Bool) (assert a) (assert (not a))";
+        let run = |assume_id: &str, strict_rules: &[&str]| {
+            let proof = format!(
+                "(assume h1 a)
+                (assume h2 (not a))
+                (anchor :step t3)
+                (assume {0} a)
+                (step t3 (cl (not a) a) :rule subproof :discharge ({0}))
+                (step t4 (cl) :rule resolution :premises (h1 h2))",
+                assume_id
+            );
+            let options = crate::CarcaraOptions {
+                strict_rules: strict_rules.iter().map(|&s| s.to_owned()).collect(),
+                ..Default::default()
+            };
+            crate::check(problem.as_bytes(), proof.as_bytes(), options)
+        };
+
+        assert!(matches!(run("t3.h1", &["anchor"]), Ok(false)));
+        assert!(matches!(run("h3", &[]), Ok(false)));
+        assert!(matches!(
+            run("h3", &["anchor"]),
+            Err(Error::Checker { inner, .. }) if matches!(
+                &*inner,
+                CheckerError::Subproof(SubproofError::IdNotNested(id, end))
+                    if id == "h3" && end == "t3"
+            )
+        ));
+        assert!(matches!(
+            run("t34.h1", &["anchor"]),
+            Err(Error::Checker { .. })
+        ));
+    }
+
+    #[test]
+    fn test_check_coverage() {
+        let problem = "(declare-fun a () Bool) (declare-fun b () Bool)
+            (assert a) (assert (not a))";
+        let proof = "(assume h1 a)
+            (assume h2 (not b))
+            (step t1 (cl b) :rule refl)
+            (step t2 (cl b) :rule hole)
+            (step t3 (cl b) :rule foo)
+            (step t4 (cl b) :rule foo)
+            (step t5 (cl) :rule resolution :premises (h1 h2))
+            (step t6 (cl) :rule resolution :premises (t2 h2))";
+        let (prelude, proof, mut pool) = crate::parser::parse_instance(
+            problem.as_bytes(),
+            proof.as_bytes(),
+            crate::parser::Config::new(),
+        )
+        .unwrap();
+        let config = Config::new().ignore_unknown_rules(true);
+        let coverage = ProofChecker::new(&mut pool, config, &prelude)
+            .check_coverage(&proof)
+            .unwrap();
+
+        let counts = |checked, skipped, failed, unimplemented| RuleCounts {
+            checked,
+            skipped,
+            failed,
+            unimplemented,
+        };
+        assert_eq!(coverage.rules["assume"], counts(1, 0, 1, 0));
+        assert_eq!(coverage.rules["refl"], counts(0, 0, 1, 0));
+        assert_eq!(coverage.rules["hole"], counts(0, 1, 0, 0));
+        assert_eq!(coverage.rules["resolution"], counts(1, 0, 1, 0));
+        assert_eq!(coverage.unimplemented(), [("foo", 2)]);
+        assert_eq!(coverage.unknown_rules.rules["foo"].examples, ["t3", "t4"]);
+
+        let by_frequency: Vec<_> = coverage.by_frequency().iter().map(|(r, _)| *r).collect();
+        assert_eq!(
+            by_frequency,
+            ["assume", "foo", "resolution", "hole", "refl"]
+        );
+    }
+
+    #[test]
+    fn test_trusted_rules() {
+        let problem = "(declare-sort U 0) (declare-fun a () U) (declare-fun b () U)
+            (declare-fun c () U) (assert (distinct a b c))";
+        let proof = "(assume h1 (distinct a b c))
+            (step t1 (cl (not (distinct a b c))) :rule fmf_card)
+            (step t2 (cl) :rule resolution :premises (h1 t1))";
+        let (prelude, proof, mut pool) = crate::parser::parse_instance(
+            problem.as_bytes(),
+            proof.as_bytes(),
+            crate::parser::Config::new(),
+        )
+        .unwrap();
+
+        let result = ProofChecker::new(&mut pool, Config::new(), &prelude).check(&proof);
+        assert!(matches!(
+            result,
+            Err(Error::Checker { inner, .. }) if matches!(*inner, CheckerError::UnknownRule)
+        ));
+
+        let config = Config::new().trusted_rules(["fmf_card".to_owned()]);
+        let mut checker = ProofChecker::new(&mut pool, config, &prelude);
+        assert!(checker.check(&proof).unwrap());
+        assert!(checker.unknown_rules().is_empty());
+    }
+
+    #[test]
+    fn test_parallel_unknown_rules() {
+        let problem = "(declare-fun p () Bool) (declare-fun q () Bool) (assert p)";
+        let proof = "(assume h1 p)
+            (step t2 (cl q) :rule foo)
+            (step t3 (cl (not q)) :rule foo)
+            (step t4 (cl) :rule bar :premises (h1 t2 t3))";
+        let (prelude, proof, pool) = crate::parser::parse_instance(
+            problem.as_bytes(),
+            proof.as_bytes(),
+            crate::parser::Config::new(),
+        )
+        .unwrap();
+
+        let (scheduler, context_usage) = Scheduler::new(2, &proof);
+        let config = Config::new().ignore_unknown_rules(true);
+        let mut checker = ParallelProofChecker::new(
+            std::sync::Arc::new(pool),
+            config,
+            &prelude,
+            &context_usage,
+            8 * 1024 * 1024,
+        );
+        assert!(checker.check(&proof, &scheduler).unwrap());
+
+        let report = checker.unknown_rules();
+        assert_eq!(report.rules["foo"].count, 2);
+        assert_eq!(report.rules["foo"].examples, ["t2", "t3"]);
+        assert_eq!(report.rules["bar"].count, 1);
+    }
+
+    #[test]
+    fn test_error_annotations() {
+        let problem = "(declare-fun p () Bool) (declare-fun q () Bool) (assert p)";
+        let proof = "(assume h1 p)
+            (step t2 (cl q) :rule refl :cvc5-node 42)
+            (step t3 (cl) :rule resolution :premises (h1 t2))";
+        let (prelude, proof, mut pool) = crate::parser::parse_instance(
+            problem.as_bytes(),
+            proof.as_bytes(),
+            crate::parser::Config::new(),
+        )
+        .unwrap();
+
+        let err = ProofChecker::new(&mut pool, Config::new(), &prelude)
+            .check(&proof)
+            .unwrap_err();
+        match &err {
+            Error::Checker { step, annotations, .. } => {
+                assert_eq!(step, "t2");
+                assert_eq!(annotations.as_deref(), Some(":cvc5-node 42"));
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+        assert!(err.to_string().contains("annotated with :cvc5-node 42"));
+    }
+
+    #[test]
+    fn test_check_with_resolution_pivots() {
+        let problem = "(declare-fun a () Bool) (declare-fun b () Bool)
+            (assert (or a b)) (assert (not a)) (assert (not b))";
+        let proof = "(assume h1 (or a b))
+            (assume h2 (not a))
+            (assume h3 (not b))
+            (step t1 (cl a b) :rule or :premises (h1))
+            (step t2 (cl b) :rule resolution :premises (h2 t1))
+            (step t3 (cl) :rule resolution :premises (t2 h3) :args (b true))";
+        let (prelude, proof, mut pool) = crate::parser::parse_instance(
+            problem.as_bytes(),
+            proof.as_bytes(),
+            crate::parser::Config::new(),
+        )
+        .unwrap();
+        let (_, pivots) = ProofChecker::new(&mut pool, Config::new(), &prelude)
+            .check_with_resolution_pivots(&proof)
+            .unwrap();
+
+        // Steps that already have their pivots as arguments are not included
+        assert_eq!(pivots.len(), 1);
+        let found = &pivots["t2"];
+        assert_eq!(found.premises, [(0, 1), (0, 3)]);
+        assert_eq!(found.pivots.len(), 1);
+        assert_eq!(found.pivots[0].0.to_string(), "a");
+        assert!(!found.pivots[0].1);
+    }
+}
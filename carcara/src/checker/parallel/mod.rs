@@ -1,12 +1,13 @@
 pub mod scheduler;
 
 use super::{
+    check_subproof_ids, debug_assert_step_interned,
     error::{CheckerError, SubproofError},
-    lia_generic,
-    rules::{Premise, RuleArgs, RuleResult},
-    Config, ProofChecker,
+    find_normalized_premise, lia_generic, logic,
+    rules::{self, resolution::LiteralCache, Premise, RuleArgs, RuleResult},
+    Config, ProofChecker, UnknownRuleReport,
 };
-use crate::benchmarking::{CollectResults, OnlineBenchmarkResults};
+use crate::benchmarking::{CollectResults, NullResults};
 use crate::checker::CheckerStatistics;
 use crate::{
     ast::{pool::advanced::*, *},
@@ -26,8 +27,10 @@ pub struct ParallelProofChecker<'c> {
     config: Config,
     prelude: &'c ProblemPrelude,
     context: ContextStack,
+    literal_cache: Option<LiteralCache>,
     reached_empty_clause: bool,
     is_holey: bool,
+    unknown_rules: UnknownRuleReport,
     stack_size: usize,
 }
 
@@ -39,13 +42,16 @@ impl<'c> ParallelProofChecker<'c> {
         context_usage: &Vec<usize>,
         stack_size: usize,
     ) -> Self {
+        let literal_cache = config.resolution_cache.then(LiteralCache::new);
         ParallelProofChecker {
             pool,
             config,
             prelude,
             context: ContextStack::from_usage(context_usage),
+            literal_cache,
             reached_empty_clause: false,
             is_holey: false,
+            unknown_rules: UnknownRuleReport::new(),
             stack_size,
         }
     }
@@ -57,13 +63,25 @@ impl<'c> ParallelProofChecker<'c> {
             config: self.config.clone(),
             prelude: self.prelude,
             context: ContextStack::from_previous(&self.context),
+            literal_cache: self.config.resolution_cache.then(LiteralCache::new),
             reached_empty_clause: false,
             is_holey: false,
+            unknown_rules: UnknownRuleReport::new(),
             stack_size: self.stack_size,
         }
     }
 
+    /// Returns the steps that use an unknown rule, collected from every worker thread. See
+    /// [`ProofChecker::unknown_rules`].
+    pub fn unknown_rules(&self) -> &UnknownRuleReport {
+        &self.unknown_rules
+    }
+
     pub fn check(&mut self, proof: &Proof, scheduler: &Scheduler) -> CarcaraResult<bool> {
+        self.config.select_steps(proof)?;
+        logic::validate(self.prelude, proof.iter(), self.config.logic_validation)
+            .map_err(|e| e.with_step_info(proof))?;
+
         // Used to estimulate threads to abort prematurely (only happens when a
         // thread already found out an invalid step)
         let premature_abort = Arc::new(AtomicBool::new(false));
@@ -83,15 +101,20 @@ impl<'c> ParallelProofChecker<'c> {
                     thread::Builder::new()
                         .name(format!("worker-{i}"))
                         .stack_size(self.stack_size)
-                        .spawn_scoped(s, move || -> CarcaraResult<(bool, bool)> {
-                            local_self.worker_thread_check(
-                                proof,
-                                schedule,
-                                local_pool,
-                                should_abort,
-                                None::<&mut CheckerStatistics<OnlineBenchmarkResults>>,
-                            )
-                        })
+                        .spawn_scoped(
+                            s,
+                            move || -> CarcaraResult<(bool, bool, UnknownRuleReport)> {
+                                local_self
+                                    .worker_thread_check(
+                                        proof,
+                                        schedule,
+                                        local_pool,
+                                        should_abort,
+                                        None::<&mut CheckerStatistics<NullResults>>,
+                                    )
+                                    .map(|r| (r.0, r.1, local_self.unknown_rules))
+                            },
+                        )
                         .unwrap()
                 })
                 .collect();
@@ -106,7 +129,9 @@ impl<'c> ParallelProofChecker<'c> {
                 .map(|t| t.join().unwrap())
                 .try_for_each(|opt| {
                     match opt {
-                        Ok((local_reached, local_holey)) => {
+                        Ok((local_reached, local_holey, local_unknown_rules)) => {
+                            self.unknown_rules.combine(&local_unknown_rules);
+
                             // Mask the result booleans
                             (reached, holey) = (reached | local_reached, holey | local_holey);
                             ControlFlow::Continue(())
@@ -119,7 +144,8 @@ impl<'c> ParallelProofChecker<'c> {
                 });
 
             // If an error happend
-            err?;
+            err.map_err(|e| e.with_step_info(proof))?;
+            self.unknown_rules.log_warnings();
 
             if reached {
                 Ok(holey)
@@ -135,6 +161,10 @@ impl<'c> ParallelProofChecker<'c> {
         scheduler: &Scheduler,
         stats: &mut CheckerStatistics<CR>,
     ) -> CarcaraResult<bool> {
+        self.config.select_steps(proof)?;
+        logic::validate(self.prelude, proof.iter(), self.config.logic_validation)
+            .map_err(|e| e.with_step_info(proof))?;
+
         // Used to estimulate threads to abort prematurely (only happens when a
         // thread already found out an invalid step)
         let premature_abort = Arc::new(AtomicBool::new(false));
@@ -153,6 +183,7 @@ impl<'c> ParallelProofChecker<'c> {
                         assume_time: Duration::ZERO,
                         assume_core_time: Duration::ZERO,
                         results: CR::default(),
+                        probe: None,
                     };
                     // Shares the proof checker between threads
                     let mut local_self = self.share();
@@ -164,7 +195,12 @@ impl<'c> ParallelProofChecker<'c> {
                         .stack_size(self.stack_size)
                         .spawn_scoped(
                             s,
-                            move || -> CarcaraResult<(bool, bool, CheckerStatistics<CR>)> {
+                            move || -> CarcaraResult<(
+                                bool,
+                                bool,
+                                CheckerStatistics<CR>,
+                                UnknownRuleReport,
+                            )> {
                                 local_self
                                     .worker_thread_check(
                                         proof,
@@ -173,7 +209,7 @@ impl<'c> ParallelProofChecker<'c> {
                                         should_abort,
                                         Some(&mut local_stats),
                                     )
-                                    .map(|r| (r.0, r.1, local_stats))
+                                    .map(|r| (r.0, r.1, local_stats, local_self.unknown_rules))
                             },
                         )
                         .unwrap()
@@ -190,7 +226,9 @@ impl<'c> ParallelProofChecker<'c> {
                 .map(|t| t.join().unwrap())
                 .for_each(|opt| {
                     match opt {
-                        Ok((local_reached, local_holey, mut local_stats)) => {
+                        Ok((local_reached, local_holey, mut local_stats, local_unknown_rules)) => {
+                            self.unknown_rules.combine(&local_unknown_rules);
+
                             // Combine the statistics
                             // Takes the external and local benchmark results to local variables and combine them
                             let main = std::mem::take(&mut stats.results);
@@ -217,7 +255,8 @@ impl<'c> ParallelProofChecker<'c> {
                 });
 
             // If an error happend
-            err?;
+            err.map_err(|e| e.with_step_info(proof))?;
+            self.unknown_rules.log_warnings();
 
             if reached {
                 Ok(holey)
@@ -250,6 +289,9 @@ impl<'c> ParallelProofChecker<'c> {
                 // commands of the stack. The parser already ensures that the last command
                 // in a subproof is always a `step` command
                 self.context.pop();
+                if let Some(cache) = &mut self.literal_cache {
+                    cache.close_subproof(last_depth as usize);
+                }
                 last_depth -= 1;
             }
             last_depth = iter.depth() as i64;
@@ -273,9 +315,11 @@ impl<'c> ParallelProofChecker<'c> {
                             // Signalize to other threads to stop the proof checking
                             should_abort.store(true, Ordering::Release);
                             Error::Checker {
-                                inner: e,
-                                rule: step.rule.clone(),
-                                step: step.id.clone(),
+                                inner: Box::new(e),
+                                rule: step.rule.to_string(),
+                                step: step.id.to_string(),
+                                span: None,
+                                annotations: None,
                             }
                         })?;
 
@@ -287,20 +331,31 @@ impl<'c> ParallelProofChecker<'c> {
                     let time = Instant::now();
                     let step_id = command.id();
 
-                    self.context
-                        .push(
-                            &mut pool.ctx_pool,
-                            &s.assignment_args,
-                            &s.variable_args,
-                            s.context_id,
-                        )
+                    let ids_result = if self.config.is_strict("anchor") {
+                        check_subproof_ids(s)
+                    } else {
+                        Ok(())
+                    };
+                    ids_result
+                        .and_then(|()| {
+                            self.context
+                                .push(
+                                    &mut pool.ctx_pool,
+                                    &s.assignment_args,
+                                    &s.variable_args,
+                                    s.context_id,
+                                )
+                                .map_err(Into::into)
+                        })
                         .map_err(|e| {
                             // Signalize to other threads to stop the proof checking
                             should_abort.store(true, Ordering::Release);
                             Error::Checker {
-                                inner: e.into(),
+                                inner: Box::new(e),
                                 rule: "anchor".into(),
-                                step: step_id.to_owned(),
+                                step: step_id.to_string(),
+                                span: None,
+                                annotations: None,
                             }
                         })?;
 
@@ -321,13 +376,15 @@ impl<'c> ParallelProofChecker<'c> {
                     }
                 }
                 ProofCommand::Assume { id, term } => {
-                    if !self.check_assume(id, term, &proof.premises, &iter, &mut stats) {
+                    if !self.check_assume(id, term, &proof.premises, &iter, &mut pool, &mut stats) {
                         // Signalize to other threads to stop the proof checking
                         should_abort.store(true, Ordering::Release);
                         return Err(Error::Checker {
-                            inner: CheckerError::Assume(term.clone()),
+                            inner: Box::new(CheckerError::Assume(term.clone())),
                             rule: "assume".into(),
-                            step: id.clone(),
+                            step: id.to_string(),
+                            span: None,
+                            annotations: None,
                         });
                     }
                 }
@@ -352,6 +409,7 @@ impl<'c> ParallelProofChecker<'c> {
         term: &Rc<Term>,
         premises: &IndexSet<Rc<Term>>,
         iter: &ScheduleIter,
+        pool: &mut LocalPool,
         mut stats: &mut Option<&mut CheckerStatistics<CR>>,
     ) -> bool {
         let time = Instant::now();
@@ -372,7 +430,7 @@ impl<'c> ParallelProofChecker<'c> {
             return true;
         }
 
-        if self.config.strict {
+        if self.config.is_strict("assume") {
             return false;
         }
 
@@ -380,7 +438,14 @@ impl<'c> ParallelProofChecker<'c> {
         let mut polyeq_time = Duration::ZERO;
         let mut core_time = Duration::ZERO;
 
-        for p in premises {
+        // Similarly to the single-threaded checker, we first try to match the `assume` command
+        // against the premise named by its id, if there is one
+        let named = self
+            .prelude
+            .named_terms
+            .get(id)
+            .filter(|p| premises.contains(*p));
+        for p in named.into_iter().chain(premises) {
             let mut this_polyeq_time = Duration::ZERO;
             let (result, depth) = tracing_polyeq(term, p, &mut this_polyeq_time);
             polyeq_time += this_polyeq_time;
@@ -394,6 +459,16 @@ impl<'c> ParallelProofChecker<'c> {
             }
         }
 
+        if found.is_none() && self.config.assume_normalizations.any() {
+            found = find_normalized_premise(
+                pool,
+                term,
+                premises,
+                self.config.assume_normalizations,
+                &mut polyeq_time,
+            );
+        }
+
         if found.is_none() {
             return false;
         }
@@ -421,11 +496,16 @@ impl<'c> ParallelProofChecker<'c> {
         let time = Instant::now();
         let mut polyeq_time = Duration::ZERO;
 
-        if !step.discharge.is_empty() && step.rule != "subproof" {
+        let rule_name = self.config.canonical_rule_name(&step.rule).to_owned();
+        if !step.discharge.is_empty() && rule_name != "subproof" {
             return Err(CheckerError::Subproof(SubproofError::DischargeInWrongRule));
         }
+        self.config.check_limits(&rule_name, step)?;
 
-        if step.rule == "lia_generic" {
+        let mut measured_rule = None;
+        if self.config.is_skipped(&rule_name, &step.id) {
+            self.is_holey = true;
+        } else if rule_name == "lia_generic" {
             if let Some(options) = &self.config.lia_options {
                 let is_hole =
                     lia_generic::lia_generic_multi_thread(&step.clause, self.prelude, options);
@@ -435,16 +515,19 @@ impl<'c> ParallelProofChecker<'c> {
                 self.is_holey = true;
             }
         } else {
-            let rule = match ProofChecker::get_rule(&step.rule, self.config.strict) {
+            let rule = match ProofChecker::get_rule(&rule_name, &self.config) {
                 Some(r) => r,
-                None if self.config.ignore_unknown_rules => {
+                None => {
+                    self.unknown_rules.register(&step.rule, step.id.clone());
+                    if !self.config.ignore_unknown_rules {
+                        return Err(CheckerError::UnknownRule);
+                    }
                     self.is_holey = true;
                     return Ok(());
                 }
-                None => return Err(CheckerError::UnknownRule),
             };
 
-            if step.rule == "hole" {
+            if rule_name == "hole" {
                 self.is_holey = true;
             }
 
@@ -462,6 +545,7 @@ impl<'c> ParallelProofChecker<'c> {
                 .map(|&i| iter.get_premise(i))
                 .collect();
 
+            debug_assert_step_interned(&*pool, step);
             let rule_args = RuleArgs {
                 conclusion: &step.clause,
                 premises: &premises,
@@ -471,9 +555,17 @@ impl<'c> ParallelProofChecker<'c> {
                 previous_command,
                 discharge: &discharge,
                 polyeq_time: &mut polyeq_time,
+                literal_cache: self.literal_cache.as_mut(),
+                la_generic_options: self.config.la_generic_options(),
             };
 
-            rule(rule_args)?;
+            if rule_name == "all_simplify" && stats.is_some() {
+                // Similarly to the single-threaded checker, we record which rewrite closed the step
+                let rewrite = rules::simplification::find_all_simplify_rewrite(rule_args)?;
+                measured_rule = Some(format!("all_simplify({})", rewrite));
+            } else {
+                rule(rule_args)?;
+            }
         }
 
         if iter.is_end_step() {
@@ -483,8 +575,9 @@ impl<'c> ParallelProofChecker<'c> {
 
         if let Some(s) = stats {
             let time = time.elapsed();
+            let rule = measured_rule.as_deref().unwrap_or(&step.rule);
             s.results
-                .add_step_measurement(s.file_name, &step.id, &step.rule, time);
+                .add_step_measurement(s.file_name, &step.id, rule, time);
             s.polyeq_time += polyeq_time;
         }
         Ok(())
@@ -1,8 +1,13 @@
+#[cfg(feature = "elaborator")]
+use super::Elaborator;
 use super::{
-    assert_clause_len, assert_num_premises, get_premise_term, CheckerError, RuleArgs, RuleResult,
+    assert_clause_len, assert_num_premises, get_premise_term, CheckerError, Premise, RuleArgs,
+    RuleResult,
 };
 use crate::{ast::*, checker::error::CongruenceError};
 
+type CongruentArgs<'a> = (&'a [Rc<Term>], &'a [Rc<Term>]);
+
 pub fn eq_congruent(RuleArgs { conclusion, .. }: RuleArgs) -> RuleResult {
     assert_clause_len(conclusion, 2..)?;
 
@@ -45,20 +50,7 @@ where
         .collect::<Result<_, _>>()?;
 
     let (p, q) = conclusion;
-    let (f_args, g_args) = match (p.as_ref(), q.as_ref()) {
-        (Term::App(f, f_args), Term::App(g, g_args)) => match f == g {
-            true => Ok((f_args, g_args)),
-            false => Err(CongruenceError::DifferentFunctions(f.clone(), g.clone())),
-        },
-        (Term::Op(f, f_args), Term::Op(g, g_args)) => match f == g {
-            true => Ok((f_args, g_args)),
-            false => Err(CongruenceError::DifferentOperators(*f, *g)),
-        },
-        (Term::Op(..) | Term::App(..), _) => {
-            Err(CongruenceError::NotApplicationOrOperation(q.clone()))
-        }
-        _ => Err(CongruenceError::NotApplicationOrOperation(p.clone())),
-    }?;
+    let (f_args, g_args) = get_congruent_args(p, q)?;
     rassert!(
         f_args.len() == g_args.len(),
         CongruenceError::DifferentNumberOfArguments(f_args.len(), g_args.len())
@@ -87,6 +79,69 @@ where
     Ok(())
 }
 
+/// Given two terms that should be applications of the same function, operator, indexed operator or
+/// parametrized operator, returns the arguments of each of them.
+fn get_congruent_args<'a>(
+    f: &'a Rc<Term>,
+    g: &'a Rc<Term>,
+) -> Result<CongruentArgs<'a>, CongruenceError> {
+    match (f.as_ref(), g.as_ref()) {
+        (Term::App(f, f_args), Term::App(g, g_args)) => match f == g {
+            true => Ok((f_args.as_slice(), g_args.as_slice())),
+            false => Err(CongruenceError::DifferentFunctions(f.clone(), g.clone())),
+        },
+        (Term::Op(f, f_args), Term::Op(g, g_args)) => match f == g {
+            true => Ok((f_args.as_slice(), g_args.as_slice())),
+            false => Err(CongruenceError::DifferentOperators(*f, *g)),
+        },
+        (
+            Term::IndexedOp {
+                op: f_op,
+                op_args: f_op_args,
+                args: f_args,
+            },
+            Term::IndexedOp {
+                op: g_op,
+                args: g_args,
+                op_args: g_op_args,
+            },
+        ) => {
+            if f_op != g_op || f_op_args != g_op_args {
+                Err(CongruenceError::DifferentIndexedOperators(
+                    (*f_op, f_op_args.clone()),
+                    (*g_op, g_op_args.clone()),
+                ))
+            } else {
+                Ok((f_args.as_slice(), g_args.as_slice()))
+            }
+        }
+        (
+            Term::ParamOp {
+                op: f_op,
+                op_args: f_op_args,
+                args: f_args,
+            },
+            Term::ParamOp {
+                op: g_op,
+                op_args: g_op_args,
+                args: g_args,
+            },
+        ) => {
+            if f_op != g_op || f_op_args != g_op_args {
+                Err(CongruenceError::DifferentFunctions(f.clone(), g.clone()))
+            } else {
+                Ok((f_args.as_slice(), g_args.as_slice()))
+            }
+        }
+        (Term::Op(..) | Term::App(..) | Term::IndexedOp { .. } | Term::ParamOp { .. }, _) => {
+            // Note: this error also triggers when `f` is an operation and `g` an application, or
+            // vice-versa. This means the error message may be a bit confusing
+            Err(CongruenceError::NotApplicationOrOperation(g.clone()))
+        }
+        _ => Err(CongruenceError::NotApplicationOrOperation(f.clone())),
+    }
+}
+
 /// Since the semantics of the `cong` rule is slightly different from that of `eq_congruent` and
 /// `eq_congruent_pred`, we cannot just use the `generic_congruent_rule` function
 fn check_cong<'a, I>(premises: &[(&'a Rc<Term>, &'a Rc<Term>)], f_args: I, g_args: I) -> RuleResult
@@ -130,7 +185,61 @@ where
     }
 }
 
-pub fn cong(RuleArgs { conclusion, premises, .. }: RuleArgs) -> RuleResult {
+/// Tries to find, for each pair of arguments, a premise that justifies their equality, regardless
+/// of the order in which the premises are given. If all premises are used, returns the premise
+/// indices in the order of the arguments they justify.
+fn find_cong_premise_order<'a, I>(
+    premises: &[(&'a Rc<Term>, &'a Rc<Term>)],
+    f_args: I,
+    g_args: I,
+) -> Option<Vec<usize>>
+where
+    I: IntoIterator<Item = &'a Rc<Term>>,
+{
+    let mut used = vec![false; premises.len()];
+    let mut order = Vec::with_capacity(premises.len());
+    for (f_arg, g_arg) in f_args.into_iter().zip(g_args) {
+        let found = premises.iter().enumerate().position(|(i, &(t, u))| {
+            !used[i] && ((t, u) == (f_arg, g_arg) || (u, t) == (f_arg, g_arg))
+        });
+        match found {
+            Some(i) => {
+                used[i] = true;
+                order.push(i);
+            }
+            None if f_arg == g_arg => (),
+            None => return None,
+        }
+    }
+    used.iter().all(|&u| u).then_some(order)
+}
+
+/// Checks the premises of a `cong` or `ho_cong` step against the given arguments. If the premises
+/// are not in the same order as the arguments, but `any_order` is `true`, they are accepted in any
+/// order, and the premise indices in the expected order are returned.
+fn check_cong_any_order<'a, I>(
+    premises: &[(&'a Rc<Term>, &'a Rc<Term>)],
+    f_args: I,
+    g_args: I,
+    any_order: bool,
+) -> Result<Option<Vec<usize>>, CheckerError>
+where
+    I: IntoIterator<Item = &'a Rc<Term>> + Clone,
+{
+    match check_cong(premises, f_args.clone(), g_args.clone()) {
+        Ok(()) => Ok(None),
+        Err(e) if !any_order => Err(e),
+        Err(e) => find_cong_premise_order(premises, f_args, g_args)
+            .map(Some)
+            .ok_or(e),
+    }
+}
+
+fn check_cong_rule(
+    conclusion: &[Rc<Term>],
+    premises: &[Premise],
+    any_order: bool,
+) -> Result<Option<Vec<usize>>, CheckerError> {
     assert_clause_len(conclusion, 1)?;
     assert_num_premises(premises, 1..)?;
 
@@ -155,58 +264,46 @@ pub fn cong(RuleArgs { conclusion, premises, .. }: RuleArgs) -> RuleResult {
             // We store the result of the first possibility (when neither arguments are flipped),
             // because, if the checking fails in the end, we use it to get more sensible error
             // messages
-            let original_result = check_cong(&premises, f_args, g_args);
-            let any_valid = original_result.is_ok()
-                || check_cong(&premises, f_args_flipped, g_args.as_slice()).is_ok()
-                || check_cong(&premises, f_args.as_slice(), g_args_flipped).is_ok()
-                || check_cong(&premises, f_args_flipped, g_args_flipped).is_ok();
-            return if any_valid { Ok(()) } else { original_result };
-        }
-
-        (Term::App(f, f_args), Term::App(g, g_args)) => match f == g {
-            true => Ok((f_args, g_args)),
-            false => Err(CongruenceError::DifferentFunctions(f.clone(), g.clone())),
-        },
-        (Term::Op(f, f_args), Term::Op(g, g_args)) => match f == g {
-            true => Ok((f_args, g_args)),
-            false => Err(CongruenceError::DifferentOperators(*f, *g)),
-        },
-        (
-            Term::IndexedOp {
-                op: f_op,
-                op_args: f_op_args,
-                args: f_args,
-            },
-            Term::IndexedOp {
-                op: g_op,
-                args: g_args,
-                op_args: g_op_args,
-            },
-        ) => {
-            if f_op != g_op || f_op_args != g_op_args {
-                Err(CongruenceError::DifferentIndexedOperators(
-                    (*f_op, f_op_args.clone()),
-                    (*g_op, g_op_args.clone()),
-                ))
-            } else {
-                Ok((f_args, g_args))
+            let original_result = check_cong_any_order(&premises, f_args, g_args, any_order);
+            if original_result.is_ok() {
+                return original_result;
             }
+            let possibilities = [
+                (f_args_flipped, g_args.as_slice()),
+                (f_args.as_slice(), g_args_flipped),
+                (f_args_flipped, g_args_flipped),
+            ];
+            return possibilities
+                .into_iter()
+                .map(|(f_args, g_args)| check_cong_any_order(&premises, f_args, g_args, any_order))
+                .find(Result::is_ok)
+                .unwrap_or(original_result);
         }
-        (Term::Op(..) | Term::App(..), _) => {
-            // Note: this error also triggers when `f` is an operation and `g` an application, or
-            // vice-versa. This means the error message may be a bit confusing
-            Err(CongruenceError::NotApplicationOrOperation(g.clone()))
-        }
-        _ => Err(CongruenceError::NotApplicationOrOperation(f.clone())),
-    }?;
+
+        _ => get_congruent_args(f, g)?,
+    };
     rassert!(
         f_args.len() == g_args.len(),
         CongruenceError::DifferentNumberOfArguments(f_args.len(), g_args.len())
     );
-    check_cong(&premises, f_args, g_args)
+    check_cong_any_order(&premises, f_args, g_args, any_order)
 }
 
-pub fn ho_cong(RuleArgs { conclusion, premises, .. }: RuleArgs) -> RuleResult {
+pub fn cong(RuleArgs { conclusion, premises, .. }: RuleArgs) -> RuleResult {
+    check_cong_rule(conclusion, premises, true).map(|_| ())
+}
+
+/// A strict version of the `cong` rule, that requires the premises to be in the same order as the
+/// arguments whose equality they justify.
+pub fn strict_cong(RuleArgs { conclusion, premises, .. }: RuleArgs) -> RuleResult {
+    check_cong_rule(conclusion, premises, false).map(|_| ())
+}
+
+fn check_ho_cong_rule(
+    conclusion: &[Rc<Term>],
+    premises: &[Premise],
+    any_order: bool,
+) -> Result<Option<Vec<usize>>, CheckerError> {
     use std::iter::once;
 
     assert_clause_len(conclusion, 1)?;
@@ -230,7 +327,70 @@ pub fn ho_cong(RuleArgs { conclusion, premises, .. }: RuleArgs) -> RuleResult {
         _ => Err(CongruenceError::NotApplicationOrOperation(f.clone())),
     }?;
 
-    check_cong(&premises, f_args, g_args)
+    check_cong_any_order(&premises, f_args, g_args, any_order)
+}
+
+pub fn ho_cong(RuleArgs { conclusion, premises, .. }: RuleArgs) -> RuleResult {
+    check_ho_cong_rule(conclusion, premises, true).map(|_| ())
+}
+
+/// A strict version of the `ho_cong` rule, that requires the premises to be in the same order as
+/// the arguments whose equality they justify.
+pub fn strict_ho_cong(RuleArgs { conclusion, premises, .. }: RuleArgs) -> RuleResult {
+    check_ho_cong_rule(conclusion, premises, false).map(|_| ())
+}
+
+/// Pushes a step that is equal to the original `cong` or `ho_cong` step, except that its premises
+/// are sorted in the order of the arguments whose equality they justify.
+#[cfg(feature = "elaborator")]
+fn elaborate_with_sorted_premises(
+    conclusion: &[Rc<Term>],
+    premises: &[Premise],
+    order: Option<Vec<usize>>,
+    rule: &str,
+    command_id: StepId,
+    elaborator: &mut Elaborator,
+) {
+    let Some(order) = order else {
+        elaborator.unchanged(conclusion);
+        return;
+    };
+    let premises = order
+        .into_iter()
+        .map(|i| elaborator.map_index(premises[i].index))
+        .collect();
+    elaborator.push_elaborated_step(ProofStep {
+        id: command_id,
+        clause: conclusion.into(),
+        rule: rule.into(),
+        premises,
+        args: Vec::new(),
+        discharge: Vec::new(),
+    });
+}
+
+#[cfg(feature = "elaborator")]
+pub fn elaborate_cong(
+    RuleArgs { conclusion, premises, .. }: RuleArgs,
+    command_id: StepId,
+    elaborator: &mut Elaborator,
+) -> RuleResult {
+    let order = check_cong_rule(conclusion, premises, true)?;
+    elaborate_with_sorted_premises(conclusion, premises, order, "cong", command_id, elaborator);
+    Ok(())
+}
+
+#[cfg(feature = "elaborator")]
+pub fn elaborate_ho_cong(
+    RuleArgs { conclusion, premises, .. }: RuleArgs,
+    command_id: StepId,
+    elaborator: &mut Elaborator,
+) -> RuleResult {
+    let order = check_ho_cong_rule(conclusion, premises, true)?;
+    elaborate_with_sorted_premises(
+        conclusion, premises, order, "ho_cong", command_id, elaborator,
+    );
+    Ok(())
 }
 
 #[cfg(test)]
@@ -249,6 +409,8 @@ mod tests {
                 (declare-fun g (Int Int) Int)
                 (declare-fun f-1 (Int) Int)
                 (declare-fun f-3 (Int Int Int) Int)
+                (declare-fun m () (_ BitVec 4))
+                (declare-fun n () (_ BitVec 4))
             ",
             "Simple working examples" {
                 "(step t1 (cl (not (= a b)) (= (f-1 a) (f-1 b))) :rule eq_congruent)": true,
@@ -281,6 +443,19 @@ mod tests {
 
                 "(step t1 (cl (not (= a x)) (not (= b y)) (not (= c z))
                           (= (* a b c) (+ x y z))) :rule eq_congruent)": false,
+
+                "(step t1 (cl (not (= m n)) (= ((_ extract 1 0) m) ((_ extract 2 1) n)))
+                    :rule eq_congruent)": false,
+            }
+            "Indexed operators" {
+                "(step t1 (cl (not (= m n)) (= ((_ extract 1 0) m) ((_ extract 1 0) n)))
+                    :rule eq_congruent)": true,
+
+                "(step t1 (cl (not (= n m)) (= ((_ zero_extend 2) m) ((_ zero_extend 2) n)))
+                    :rule eq_congruent)": true,
+
+                "(step t1 (cl (not (= m n)) (= ((_ extract 1 0) m) ((_ extract 1 0) m)))
+                    :rule eq_congruent)": false,
             }
             "Number of function arguments is not the same as the number of inequalities" {
                 "(step t1 (cl (not (= a x)) (not (= b y)) (= (f-3 a b c) (f-3 x y z)))
@@ -421,11 +596,22 @@ mod tests {
                 (assume h2 (= r s))
                 (assume h3 (= c d))
                 (step t4 (cl (= (f a p c) (f b q d))) :rule cong :premises (h1 h2 h3))": false,
-
+            }
+            "Premises may be given in any order" {
                 "(assume h1 (= a b))
                 (assume h2 (= c d))
                 (assume h3 (= p q))
-                (step t4 (cl (= (f a p c) (f b q d))) :rule cong :premises (h1 h2 h3))": false,
+                (step t4 (cl (= (f a p c) (f b q d))) :rule cong :premises (h1 h2 h3))": true,
+
+                "(assume h1 (= c d))
+                (assume h2 (= b a))
+                (step t3 (cl (= (f a false c) (f b false d))) :rule cong :premises (h1 h2))": true,
+
+                "(assume h1 (= c d))
+                (assume h2 (= c d))
+                (assume h3 (= a b))
+                (step t4 (cl (= (f a false c) (f b false d)))
+                    :rule cong :premises (h1 h2 h3))": false,
             }
             "Should prefer consuming premise than relying on reflexivity" {
                 "(assume h1 (= false false))
@@ -481,6 +667,11 @@ mod tests {
                 "(assume h1 (= p q))
                 (step t3 (cl (= (and p true) (and q true))) :rule ho_cong :premises (h1))": false,
             }
+            "Premises may be given in any order" {
+                "(assume h1 (= a b))
+                (assume h2 (= f g))
+                (step t3 (cl (= (f a 0) (g b 0))) :rule ho_cong :premises (h1 h2))": true,
+            }
         }
     }
 }
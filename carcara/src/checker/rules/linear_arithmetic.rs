@@ -1,7 +1,12 @@
+#[cfg(feature = "elaborator")]
+use super::Elaborator;
 use super::{assert_clause_len, assert_eq, assert_num_args, RuleArgs, RuleResult};
 use crate::{
+    arithmetic::LinearComb,
     ast::*,
-    checker::error::{CheckerError, LinearArithmeticError},
+    checker::error::{
+        CheckerError, LaGenericCertificate, LaGenericContribution, LinearArithmeticError,
+    },
 };
 use indexmap::{map::Entry, IndexMap};
 use rug::{ops::NegAssign, Integer, Rational};
@@ -26,6 +31,15 @@ pub fn la_rw_eq(RuleArgs { conclusion, .. }: RuleArgs) -> RuleResult {
 /// - The negation of an application of one of these operators
 /// - The negation of an application of the `=` operator
 fn negate_disequality(term: &Rc<Term>) -> Result<(Operator, LinearComb, LinearComb), CheckerError> {
+    let (op, a, b) = negate_disequality_args(term)?;
+    Ok((op, LinearComb::from_term(a), LinearComb::from_term(b)))
+}
+
+/// Similar to `negate_disequality`, but returns the two sides of the negated disequality as terms,
+/// instead of linear combinations.
+fn negate_disequality_args(
+    term: &Rc<Term>,
+) -> Result<(Operator, &Rc<Term>, &Rc<Term>), CheckerError> {
     use Operator::*;
 
     fn negate_operator(op: Operator) -> Option<Operator> {
@@ -53,211 +67,42 @@ fn negate_disequality(term: &Rc<Term>) -> Result<(Operator, LinearComb, LinearCo
         inner(term).ok_or_else(|| LinearArithmeticError::InvalidDisequalityOp(term.clone()))?;
 
     match args {
-        [a, b] => Ok((op, LinearComb::from_term(a), LinearComb::from_term(b))),
+        [a, b] => Ok((op, a, b)),
         _ => Err(LinearArithmeticError::TooManyArgsInDisequality(term.clone()).into()),
     }
 }
 
-/// A linear combination, represented by a hash map from non-constant terms to their coefficients,
-/// plus a constant term. This is also used to represent a disequality, in which case the left side
-/// is the non-constant terms and their coefficients, and the right side is the constant term.
-#[derive(Debug)]
-pub struct LinearComb(pub(crate) IndexMap<Rc<Term>, Rational>, pub(crate) Rational);
-
-impl LinearComb {
-    fn new() -> Self {
-        Self(IndexMap::new(), Rational::new())
-    }
+/// Negates a clause literal of a `la_generic` step, and normalizes it so that all non-constant
+/// terms are on the left side, the constant is on the right side, and the operator is `>`, `>=` or
+/// `=`.
+fn normalized_negated_disequality(phi: &Rc<Term>) -> Result<(Operator, LinearComb), CheckerError> {
+    // Steps 1 and 2: Negate the disequality
+    let (mut op, s1, s2) = negate_disequality(phi)?;
 
-    /// Flattens a term and adds it to the linear combination, multiplying by the coefficient
-    /// `coeff`. This method is only intended to be used in `LinearComb::from_term`.
-    fn add_term(&mut self, term: &Rc<Term>, coeff: &Rational) {
-        // A note on performance: this function traverses the term recursively without making use
-        // of a cache, which means sometimes it has to recompute the result for the same term more
-        // than once. However, an old implementation of this method that could use a cache showed
-        // that making use of one can actually make the performance of this function worse.
-        // Benchmarks showed that it would more than double the average time of the `la_generic`
-        // rule, which makes extensive use of `LinerComb`s. Because of that, we prefer to not use
-        // a cache here, and traverse the term naively.
+    // Step 3: Move all non constant terms to the left side, and the d terms to the right.
+    // We move everything to the left side by subtracting s2 from s1
+    let mut disequality = s1 - s2;
+    disequality.1 = -disequality.1; // We negate d to move it to the other side
 
-        match term.as_ref() {
-            Term::Op(Operator::Add, args) => {
-                for a in args {
-                    self.add_term(a, coeff);
-                }
-            }
-            Term::Op(Operator::Sub, args) if args.len() == 1 => {
-                self.add_term(&args[0], &coeff.as_neg());
-            }
-            Term::Op(Operator::Sub, args) => {
-                self.add_term(&args[0], coeff);
-                for a in &args[1..] {
-                    self.add_term(a, &coeff.as_neg());
-                }
-            }
-            Term::Op(Operator::Mult, args) if args.len() == 2 => {
-                let (var, mut inner_coeff) = match (args[0].as_fraction(), args[1].as_fraction()) {
-                    (None, Some(coeff)) => (&args[0], coeff),
-                    (Some(coeff), _) => (&args[1], coeff),
-                    (None, None) => return self.insert(term.clone(), coeff.clone()),
-                };
-                inner_coeff *= coeff;
-                self.add_term(var, &inner_coeff);
-            }
-            _ => {
-                if let Some(mut r) = term.as_fraction() {
-                    r *= coeff;
-                    self.1 += r;
-                } else {
-                    self.insert(term.clone(), coeff.clone());
-                }
-            }
-        }
-    }
-
-    /// Builds a linear combination from a term. Takes a term with nested additions, subtractions
-    /// and multiplications, and flattens it to linear combination, calculating the coefficient of
-    /// each atom.
-    fn from_term(term: &Rc<Term>) -> Self {
-        let mut result = Self::new();
-        result.add_term(term, &Rational::from(1));
-        result
-    }
-
-    fn insert(&mut self, key: Rc<Term>, value: Rational) {
-        match self.0.entry(key) {
-            Entry::Occupied(mut e) => {
-                *e.get_mut() += value;
-                if *e.get() == 0 {
-                    e.remove();
-                }
-            }
-            Entry::Vacant(e) => {
-                e.insert(value);
-            }
-        }
-    }
-
-    fn add(mut self, other: Self) -> Self {
-        for (var, coeff) in other.0 {
-            self.insert(var, coeff);
-        }
-        self.1 += other.1;
-        self
-    }
-
-    fn mul(&mut self, scalar: &Rational) {
-        if *scalar == 0 {
-            self.0.clear();
-            self.1 = Rational::new();
-            return;
-        }
-
-        if *scalar == 1 {
-            return;
-        }
-
-        for coeff in self.0.values_mut() {
-            *coeff *= scalar;
-        }
-        self.1 *= scalar;
-    }
-
-    fn neg(&mut self) {
-        for coeff in self.0.values_mut() {
-            coeff.neg_assign();
-        }
-        self.1.neg_assign();
-    }
-
-    fn sub(self, mut other: Self) -> Self {
-        other.neg();
-        self.add(other)
-    }
-
-    /// Finds the greatest common divisor of the coefficients in the linear combination. Returns
-    /// 1 if the linear combination is empty, or if any of the coefficients is not an integer.
-    fn coefficients_gcd(&self) -> Integer {
-        if !self.1.is_integer() {
-            return Integer::from(1);
-        }
-
-        let mut result = self.1.numer().clone();
-        for (_, coeff) in &self.0 {
-            if result == 1 {
-                return Integer::from(1);
-            }
-            if coeff.is_integer() {
-                result.gcd_mut(coeff.numer());
-            } else {
-                return Integer::from(1);
-            }
-        }
-
-        // If the linear combination is all zeros, the result would also be zero. In that case, we
-        // have to return one instead
-        std::cmp::max(Integer::from(1), result)
+    // If the operator is < or <=, we flip the disequality so it is > or >=
+    if op == Operator::LessThan {
+        disequality = -disequality;
+        op = Operator::GreaterThan;
+    } else if op == Operator::LessEq {
+        disequality = -disequality;
+        op = Operator::GreaterEq;
     }
+    Ok((op, disequality))
 }
 
-fn strengthen(op: Operator, disequality: &mut LinearComb, a: &Rational) -> Operator {
-    // Multiplications are expensive, so we avoid them if we can
-    let is_integer = if *a == 0 {
-        true
-    } else if *a == 1 {
-        disequality.1.is_integer()
-    } else {
-        (disequality.1.clone() * a).is_integer()
-    };
-
-    match op {
-        Operator::GreaterEq if is_integer => op,
-
-        // In some cases, when the disequality is over integers, we can make the strengthening
-        // rules even stronger. Consider for instance the following example:
-        // ```
-        //     (step t1 (cl
-        //         (not (<= (- 1) n))
-        //         (not (<= (- 1) (+ n m)))
-        //         (<= (- 2) (* 2 n))
-        //         (not (<= m 1))
-        //     ) :rule la_generic :args (1 1 1 1))
-        // ```
-        // After the third disequality is negated and flipped, it becomes:
-        //     -2 * n > 2
-        // If nothing fancy is done, this would strengthen to:
-        //     -2 * n >= 3
-        // However, in this case, we can divide the disequality by 2 before strengthening, and then
-        // multiply it by 2 to get back. This would result in:
-        //     -2 * n > 2
-        //     -1 * n > 1
-        //     -1 * n >= 2
-        //     -2 * n >= 4
-        // This is a stronger statement, and follows from the original disequality. Importantly,
-        // this strengthening is sometimes necessary to check some `la_generic` steps. To find the
-        // value by which we should divide we have to take the greatest common divisor of the
-        // coefficients (including the constant value on the right-hand side), as this makes sure
-        // all coefficients will continue being integers after the division. This strengthening is
-        // still valid because, since the variables are integers, the result of their linear
-        // combination will always be a multiple of their GCD.
-        Operator::GreaterThan if is_integer => {
-            // Instead of dividing and then multiplying back, we just multiply the "+ 1"
-            // that is added by the strengthening rule
-            disequality.1.floor_mut();
-            disequality.1 += disequality.coefficients_gcd();
-            Operator::GreaterEq
-        }
-        Operator::GreaterThan | Operator::GreaterEq => {
-            disequality.1.floor_mut();
-            disequality.1 += 1;
-            Operator::GreaterEq
-        }
-        Operator::LessThan | Operator::LessEq => unreachable!(),
-        _ => op,
-    }
-}
-
-pub fn la_generic(RuleArgs { conclusion, args, .. }: RuleArgs) -> RuleResult {
+/// Computes the final disequality of a `la_generic` step. If `contributions` is given, the
+/// disequality contributed by each clause literal, after strengthening and scaling by its
+/// coefficient, is recorded in it.
+fn la_generic_final_disequality(
+    conclusion: &[Rc<Term>],
+    args: &[ProofArg],
+    contributions: Option<&mut Vec<LaGenericContribution>>,
+) -> Result<(Operator, LinearComb), CheckerError> {
     assert_num_args(args, conclusion.len())?;
 
     let args: Vec<_> = args
@@ -269,44 +114,46 @@ pub fn la_generic(RuleArgs { conclusion, args, .. }: RuleArgs) -> RuleResult {
         })
         .collect::<Result<_, _>>()?;
 
-    let final_disequality = conclusion
+    final_disequality_with_coefficients(conclusion, args, contributions)
+}
+
+/// Similar to `la_generic_final_disequality`, but takes the coefficients of each literal directly,
+/// instead of the step arguments.
+fn final_disequality_with_coefficients(
+    conclusion: &[Rc<Term>],
+    coefficients: Vec<Rational>,
+    mut contributions: Option<&mut Vec<LaGenericContribution>>,
+) -> Result<(Operator, LinearComb), CheckerError> {
+    conclusion
         .iter()
-        .zip(args)
+        .zip(coefficients)
         .map(|(phi, a)| -> Result<_, CheckerError> {
-            // Steps 1 and 2: Negate the disequality
-            let (mut op, s1, s2) = negate_disequality(phi)?;
-
-            // Step 3: Move all non constant terms to the left side, and the d terms to the right.
-            // We move everything to the left side by subtracting s2 from s1
-            let mut disequality = s1.sub(s2);
-            disequality.1 = -disequality.1; // We negate d to move it to the other side
-
-            // If the operator is < or <=, we flip the disequality so it is > or >=
-            if op == Operator::LessThan {
-                disequality.neg();
-                op = Operator::GreaterThan;
-            } else if op == Operator::LessEq {
-                disequality.neg();
-                op = Operator::GreaterEq;
-            }
+            let (op, mut disequality) = normalized_negated_disequality(phi)?;
 
             // Step 4: Apply strengthening rules
-            let op = strengthen(op, &mut disequality, &a);
+            let op = disequality.strengthen(op, &a);
 
             // Step 5: Multiply disequality by a
             let a = match op {
                 Operator::Equals => a,
                 _ => a.abs(),
             };
-            disequality.mul(&a);
+            disequality *= &a;
 
+            if let Some(contributions) = contributions.as_deref_mut() {
+                contributions.push(LaGenericContribution {
+                    literal: phi.clone(),
+                    coefficient: a,
+                    disequality: (op, disequality.clone()),
+                });
+            }
             Ok((op, disequality))
         })
         .try_fold(
             (Operator::Equals, LinearComb::new()),
             |(acc_op, acc), item| -> Result<_, CheckerError> {
                 let (op, diseq) = item?;
-                let new_acc = acc.add(diseq);
+                let new_acc = acc + diseq;
                 let new_op = match (acc_op, op) {
                     (_, Operator::GreaterEq) => Operator::GreaterEq,
                     (Operator::Equals, Operator::GreaterThan) => Operator::GreaterThan,
@@ -314,10 +161,11 @@ pub fn la_generic(RuleArgs { conclusion, args, .. }: RuleArgs) -> RuleResult {
                 };
                 Ok((new_op, new_acc))
             },
-        )?;
-
-    let (op, LinearComb(left_side, right_side)) = &final_disequality;
+        )
+}
 
+/// Returns `true` if the final disequality of a `la_generic` step is contradictory.
+fn is_contradiction((op, LinearComb(left_side, right_side)): &(Operator, LinearComb)) -> bool {
     let is_disequality_true = {
         use std::cmp::Ordering;
         use Operator::*;
@@ -333,10 +181,438 @@ pub fn la_generic(RuleArgs { conclusion, args, .. }: RuleArgs) -> RuleResult {
 
     // The left side must be empty (that is, equal to 0), and the final disequality must be
     // contradictory
-    rassert!(
-        left_side.is_empty() && !is_disequality_true,
-        LinearArithmeticError::DisequalityIsNotContradiction(*op, final_disequality.1),
-    );
+    left_side.is_empty() && !is_disequality_true
+}
+
+/// The options that change how `la_generic` steps are checked.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LaGenericOptions {
+    /// If `true`, a fast pre-check using floating-point interval arithmetic is run before the
+    /// exact check. Steps are only ever accepted by the exact check.
+    pub numeric_precheck: bool,
+
+    /// If `true`, rejected steps report the contribution of each clause literal to the final
+    /// disequality, as well as the final disequality itself. Steps rejected by the pre-check are
+    /// reported without a certificate.
+    pub certificates: bool,
+
+    /// If `true`, steps whose coefficients are missing or wrong are accepted, as long as valid
+    /// coefficients can be inferred. This takes precedence over the other options.
+    pub infer_args: bool,
+}
+
+pub fn la_generic(
+    RuleArgs {
+        conclusion,
+        args,
+        la_generic_options: options,
+        ..
+    }: RuleArgs,
+) -> RuleResult {
+    if options.infer_args {
+        return check_or_infer_la_generic_coefficients(conclusion, args).map(|_| ());
+    }
+    if options.numeric_precheck {
+        la_generic_precheck(conclusion, args)?;
+    }
+
+    let mut contributions = options
+        .certificates
+        .then(|| Vec::with_capacity(conclusion.len()));
+    let result = la_generic_final_disequality(conclusion, args, contributions.as_mut())?;
+    if is_contradiction(&result) {
+        return Ok(());
+    }
+    Err(match contributions {
+        Some(contributions) => {
+            let certificate = LaGenericCertificate { contributions, result };
+            LinearArithmeticError::ContradictionCertificate(Box::new(certificate))
+        }
+        None => LinearArithmeticError::DisequalityIsNotContradiction(result.0, result.1),
+    }
+    .into())
+}
+
+/// Finds a non-negative solution to a system of linear equations, using the first phase of the
+/// simplex method with Bland's rule. Each row is given by the coefficients of the `num_vars`
+/// variables and the right side. Returns `None` if the system has no non-negative solution.
+fn find_non_negative_solution(
+    rows: Vec<(Vec<Rational>, Rational)>,
+    num_vars: usize,
+) -> Option<Vec<Rational>> {
+    fn pivot(row: &mut [Rational], pivot_row: &[Rational], column: usize) {
+        if row[column] == 0 {
+            return;
+        }
+        let factor = row[column].clone();
+        for (x, y) in row.iter_mut().zip(pivot_row) {
+            *x -= Rational::from(&factor * y);
+        }
+    }
+
+    // An artificial variable is added to each row, so the tableau has `num_vars + rows.len()`
+    // columns, followed by the right side. Initially, the artificial variables are the basis
+    let width = num_vars + rows.len();
+    let mut tableau: Vec<Vec<Rational>> = rows
+        .into_iter()
+        .enumerate()
+        .map(|(i, (mut row, mut right_side))| {
+            if right_side < 0 {
+                row.iter_mut().for_each(NegAssign::neg_assign);
+                right_side.neg_assign();
+            }
+            row.resize(width, Rational::new());
+            row[num_vars + i] = Rational::from(1);
+            row.push(right_side);
+            row
+        })
+        .collect();
+    let mut basis: Vec<usize> = (num_vars..width).collect();
+
+    // The objective is to minimize the sum of the artificial variables. This row holds the reduced
+    // cost of each column, and the negated value of the objective
+    let mut objective: Vec<Rational> = (0..=width)
+        .map(|j| {
+            let mut cost = Rational::new();
+            if !(num_vars..width).contains(&j) {
+                for row in &tableau {
+                    cost -= &row[j];
+                }
+            }
+            cost
+        })
+        .collect();
+
+    while let Some(entering) = (0..width).find(|&j| objective[j] < 0) {
+        // Since the objective is bounded below by zero, there is always a leaving row
+        let leaving = (0..tableau.len())
+            .filter(|&r| tableau[r][entering] > 0)
+            .min_by(|&r, &s| {
+                let ratio_r = Rational::from(&tableau[r][width] / &tableau[r][entering]);
+                let ratio_s = Rational::from(&tableau[s][width] / &tableau[s][entering]);
+                ratio_r.cmp(&ratio_s).then(basis[r].cmp(&basis[s]))
+            })?;
+
+        let divisor = tableau[leaving][entering].clone();
+        for x in &mut tableau[leaving] {
+            *x /= &divisor;
+        }
+        let pivot_row = tableau[leaving].clone();
+        for (r, row) in tableau.iter_mut().enumerate() {
+            if r != leaving {
+                pivot(row, &pivot_row, entering);
+            }
+        }
+        pivot(&mut objective, &pivot_row, entering);
+        basis[leaving] = entering;
+    }
+
+    if objective[width] != 0 {
+        return None;
+    }
+    let mut solution = vec![Rational::new(); num_vars];
+    for (row, &var) in tableau.iter().zip(&basis) {
+        if var < num_vars {
+            solution[var] = row[width].clone();
+        }
+    }
+    Some(solution)
+}
+
+/// Tries to infer coefficients for the clause literals of a `la_generic` step, such that the
+/// resulting final disequality is contradictory. The coefficients are found by solving a linear
+/// program over the strengthened negated literals, and then checked as if they were given as
+/// arguments. Returns `None` if no such coefficients were found.
+fn infer_la_generic_coefficients(
+    conclusion: &[Rc<Term>],
+) -> Result<Option<Vec<Rational>>, CheckerError> {
+    let disequalities = conclusion
+        .iter()
+        .map(|phi| {
+            let (op, mut disequality) = normalized_negated_disequality(phi)?;
+            let op = disequality.strengthen(op, &Rational::from(1));
+            Ok((op, disequality))
+        })
+        .collect::<Result<Vec<_>, CheckerError>>()?;
+
+    // Each literal has a non-negative coefficient, except for equalities, whose coefficient can
+    // have any sign. Those are represented by the difference of two non-negative variables
+    let mut columns = Vec::new();
+    for (i, (op, _)) in disequalities.iter().enumerate() {
+        columns.push((i, false));
+        if *op == Operator::Equals {
+            columns.push((i, true));
+        }
+    }
+    let signed = |value: &Rational, negated: bool| match negated {
+        true => -value.clone(),
+        false => value.clone(),
+    };
+
+    // The combination of the literals must cancel out every non-constant term, and the constant
+    // must be positive. Since the coefficients can be scaled, we require it to be one
+    let mut terms: IndexMap<&Rc<Term>, Vec<Rational>> = IndexMap::new();
+    for (c, &(i, negated)) in columns.iter().enumerate() {
+        for (term, coeff) in &disequalities[i].1 .0 {
+            let row = terms
+                .entry(term)
+                .or_insert_with(|| vec![Rational::new(); columns.len()]);
+            row[c] = signed(coeff, negated);
+        }
+    }
+    let constant_row = columns
+        .iter()
+        .map(|&(i, negated)| signed(&disequalities[i].1 .1, negated))
+        .collect();
+    let rows = terms
+        .into_values()
+        .map(|row| (row, Rational::new()))
+        .chain(std::iter::once((constant_row, Rational::from(1))))
+        .collect();
+
+    let Some(solution) = find_non_negative_solution(rows, columns.len()) else {
+        return Ok(None);
+    };
+    let mut coefficients = vec![Rational::new(); conclusion.len()];
+    for ((i, negated), value) in columns.into_iter().zip(solution) {
+        if negated {
+            coefficients[i] -= value;
+        } else {
+            coefficients[i] += value;
+        }
+    }
+
+    // The strengthening of each literal depends on its coefficient, so the solution may not lead
+    // to a contradiction when checked. We try both the solution and the solution scaled so that
+    // all coefficients are integers
+    let denominators_lcm = coefficients
+        .iter()
+        .fold(Integer::from(1), |acc, a| acc.lcm(a.denom()));
+    let scaled: Vec<_> = coefficients
+        .iter()
+        .map(|a| a.clone() * &denominators_lcm)
+        .collect();
+    for candidate in [scaled, coefficients] {
+        let result = final_disequality_with_coefficients(conclusion, candidate.clone(), None)?;
+        if is_contradiction(&result) {
+            return Ok(Some(candidate));
+        }
+    }
+    Ok(None)
+}
+
+/// Checks a `la_generic` step, inferring new coefficients if the given arguments are missing or do
+/// not lead to a contradiction. Returns the inferred coefficients, or `None` if the given arguments
+/// are valid. If no valid coefficients can be inferred, the original error is returned.
+fn check_or_infer_la_generic_coefficients(
+    conclusion: &[Rc<Term>],
+    args: &[ProofArg],
+) -> Result<Option<Vec<Rational>>, CheckerError> {
+    let error = match la_generic_final_disequality(conclusion, args, None) {
+        Ok(result) if is_contradiction(&result) => return Ok(None),
+        Ok((op, result)) => LinearArithmeticError::DisequalityIsNotContradiction(op, result).into(),
+        Err(e) => e,
+    };
+    match infer_la_generic_coefficients(conclusion)? {
+        Some(coefficients) => Ok(Some(coefficients)),
+        None => Err(error),
+    }
+}
+
+#[cfg(feature = "elaborator")]
+pub fn elaborate_la_generic(
+    RuleArgs { conclusion, args, pool, .. }: RuleArgs,
+    command_id: StepId,
+    elaborator: &mut Elaborator,
+) -> RuleResult {
+    let Some(coefficients) = check_or_infer_la_generic_coefficients(conclusion, args)? else {
+        elaborator.unchanged(conclusion);
+        return Ok(());
+    };
+
+    let args = coefficients
+        .into_iter()
+        .map(|a| {
+            let (numer, denom) = Rational::from(a.abs_ref()).into_numer_denom();
+            let mut term = pool.add(Term::new_int(numer));
+            if denom != 1 {
+                let denom = pool.add(Term::new_int(denom));
+                term = pool.add(Term::Op(Operator::RealDiv, vec![term, denom]));
+            }
+            if a < 0 {
+                term = pool.add(Term::Op(Operator::Sub, vec![term]));
+            }
+            ProofArg::Term(term)
+        })
+        .collect();
+    elaborator.push_elaborated_step(ProofStep {
+        id: command_id,
+        clause: conclusion.into(),
+        rule: "la_generic".into(),
+        premises: Vec::new(),
+        args,
+        discharge: Vec::new(),
+    });
+    Ok(())
+}
+
+/// A closed interval of floating-point numbers, used to cheaply over-approximate operations on
+/// rationals. All operations round the bounds outwards, so the exact result is always contained in
+/// the resulting interval.
+#[derive(Debug, Clone, Copy)]
+struct Interval(f64, f64);
+
+impl Interval {
+    const UNBOUNDED: Self = Self(f64::NEG_INFINITY, f64::INFINITY);
+
+    fn from_rational(r: &Rational) -> Self {
+        let x = r.to_f64();
+        Self(x, x).widen()
+    }
+
+    /// Widens the interval by a small margin on both sides, to account for rounding errors. If any
+    /// of the bounds is not finite, this returns the unbounded interval.
+    fn widen(self) -> Self {
+        fn margin(x: f64) -> f64 {
+            x.abs() * (2.0 * f64::EPSILON) + f64::MIN_POSITIVE
+        }
+
+        let (lower, upper) = (self.0 - margin(self.0), self.1 + margin(self.1));
+        if lower.is_finite() && upper.is_finite() {
+            Self(lower, upper)
+        } else {
+            Self::UNBOUNDED
+        }
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self(self.0 + other.0, self.1 + other.1).widen()
+    }
+
+    fn mul(self, other: Self) -> Self {
+        let products = [
+            self.0 * other.0,
+            self.0 * other.1,
+            self.1 * other.0,
+            self.1 * other.1,
+        ];
+        if products.iter().any(|p| p.is_nan()) {
+            return Self::UNBOUNDED;
+        }
+        let lower = products.iter().copied().fold(f64::INFINITY, f64::min);
+        let upper = products.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        Self(lower, upper).widen()
+    }
+
+    fn neg(self) -> Self {
+        Self(-self.1, -self.0)
+    }
+
+    fn contains_zero(self) -> bool {
+        self.0 <= 0.0 && 0.0 <= self.1
+    }
+}
+
+/// An over-approximation of the non-constant part of a `LinearComb`, where each coefficient is
+/// represented by an interval.
+struct ApproxLinearComb(IndexMap<Rc<Term>, Interval>);
+
+impl ApproxLinearComb {
+    /// Flattens a term and adds it to the linear combination, multiplying by the coefficient
+    /// `coeff`. This mirrors `LinearComb::add_term`, but ignores constant terms.
+    fn add_term(&mut self, term: &Rc<Term>, coeff: Interval) {
+        match term.as_ref() {
+            Term::Op(Operator::Add, args) => {
+                for a in args {
+                    self.add_term(a, coeff);
+                }
+            }
+            Term::Op(Operator::Sub, args) if args.len() == 1 => {
+                self.add_term(&args[0], coeff.neg());
+            }
+            Term::Op(Operator::Sub, args) => {
+                self.add_term(&args[0], coeff);
+                for a in &args[1..] {
+                    self.add_term(a, coeff.neg());
+                }
+            }
+            Term::Op(Operator::Mult, args) if args.len() == 2 => {
+                let (var, inner_coeff) = match (args[0].as_fraction(), args[1].as_fraction()) {
+                    (None, Some(coeff)) => (&args[0], coeff),
+                    (Some(coeff), _) => (&args[1], coeff),
+                    (None, None) => return self.insert(term.clone(), coeff),
+                };
+                self.add_term(var, Interval::from_rational(&inner_coeff).mul(coeff));
+            }
+            Term::Op(Operator::ToReal, args) if args.len() == 1 => {
+                self.add_term(&args[0], coeff);
+            }
+            Term::Op(op @ (Operator::IntDiv | Operator::Mod | Operator::Abs), args) => {
+                // If all arguments are constants, the result is a constant, so it can be ignored
+                let value = args
+                    .iter()
+                    .map(|a| a.as_signed_number().map(Value::Rational))
+                    .collect::<Option<Vec<_>>>()
+                    .and_then(|args| eval_op(*op, &args));
+                if !matches!(value, Some(Value::Rational(_))) {
+                    self.insert(term.clone(), coeff);
+                }
+            }
+            _ if term.as_fraction().is_some() => (),
+            _ => self.insert(term.clone(), coeff),
+        }
+    }
+
+    fn insert(&mut self, key: Rc<Term>, value: Interval) {
+        match self.0.entry(key) {
+            Entry::Occupied(mut e) => {
+                let sum = e.get().add(value);
+                e.insert(sum);
+            }
+            Entry::Vacant(e) => {
+                e.insert(value);
+            }
+        }
+    }
+}
+
+/// Quickly checks, using interval arithmetic, if the final disequality of a `la_generic` step can
+/// possibly be contradictory. If the coefficient of any non-constant term in the final disequality
+/// is certainly not zero, the step is rejected.
+///
+/// This check can only ever reject steps. If it succeeds, the step must still be checked using
+/// exact arithmetic. Any malformed conclusion or argument is also left for the exact check to
+/// report.
+fn la_generic_precheck(conclusion: &[Rc<Term>], args: &[ProofArg]) -> RuleResult {
+    use Operator::*;
+
+    if conclusion.len() != args.len() {
+        return Ok(());
+    }
+
+    let mut result = ApproxLinearComb(IndexMap::new());
+    for (phi, a) in conclusion.iter().zip(args) {
+        let Some(a) = a.as_term().ok().and_then(|a| a.as_fraction()) else {
+            return Ok(());
+        };
+        let Ok((op, s1, s2)) = negate_disequality_args(phi) else {
+            return Ok(());
+        };
+
+        // The steps here are the same as in `la_generic`, except that strengthening only affects
+        // the constant term, so it can be skipped
+        let a = if op == Equals { a } else { a.abs() };
+        let mut coeff = Interval::from_rational(&a);
+        if matches!(op, LessThan | LessEq) {
+            coeff = coeff.neg();
+        }
+        result.add_term(s1, coeff);
+        result.add_term(s2, coeff.neg());
+    }
+
+    if let Some((var, _)) = result.0.iter().find(|(_, coeff)| !coeff.contains_zero()) {
+        return Err(LinearArithmeticError::NonZeroCoefficient(var.clone()).into());
+    }
     Ok(())
 }
 
@@ -428,15 +704,15 @@ pub fn la_tautology(RuleArgs { conclusion, .. }: RuleArgs) -> RuleResult {
         let (mut op, s1, s2) = negate_disequality(&conclusion[0])?;
 
         // Step 3: Move all non constant terms to the left side, and the d terms to the right.
-        let mut disequality = s1.sub(s2);
+        let mut disequality = s1 - s2;
         disequality.1 = -disequality.1;
 
         // If the operator is < or <=, we flip the disequality so it is > or >=
         if op == Operator::LessThan {
-            disequality.neg();
+            disequality = -disequality;
             op = Operator::GreaterThan;
         } else if op == Operator::LessEq {
-            disequality.neg();
+            disequality = -disequality;
             op = Operator::GreaterEq;
         }
 
@@ -526,6 +802,152 @@ mod tests {
         }
     }
 
+    #[test]
+    fn la_generic_with_precheck() {
+        use super::{la_generic_precheck, Interval};
+        use crate::{ast::ProofArg, parser::tests::parse_terms};
+
+        let interval = Interval::from_rational(&rug::Rational::from((1, 3)));
+        assert!(interval.0 < 1.0 / 3.0 && 1.0 / 3.0 < interval.1);
+        assert!(interval.add(interval.neg()).contains_zero());
+        assert!(!interval.mul(interval).contains_zero());
+        assert!(Interval::UNBOUNDED.mul(Interval(0.0, 0.0)).contains_zero());
+
+        let mut pool = crate::ast::PrimitivePool::new();
+        let definitions = "
+            (declare-fun a () Real)
+            (declare-fun b () Real)
+            (declare-fun n () Int)
+        ";
+        let [first, second, third, fourth, fifth, sixth, one, minus_one] = parse_terms(
+            &mut pool,
+            definitions,
+            [
+                "(< (+ a b) 1.0)",
+                "(> (+ a b) 0.0)",
+                "(> a 0.0)",
+                "(< (to_real (+ n (mod 7 2) (abs (- 3)))) 1.0)",
+                "(> (+ n (div 7 2)) 0)",
+                "(> (+ n (div n 2)) 0)",
+                "1.0",
+                "(- 1.0)",
+            ],
+        );
+        let args = [ProofArg::Term(one), ProofArg::Term(minus_one)];
+        assert!(la_generic_precheck(&[first.clone(), second], &args).is_ok());
+        assert!(la_generic_precheck(&[first, third], &args).is_err());
+
+        // Like in `LinearComb`, `to_real` is looked through, and `div`, `mod` and `abs` are
+        // evaluated if their arguments are constants
+        assert!(la_generic_precheck(&[fourth.clone(), fifth], &args).is_ok());
+        assert!(la_generic_precheck(&[fourth, sixth], &args).is_err());
+    }
+
+    #[test]
+    fn la_generic_certificate() {
+        use super::{is_contradiction, la_generic_final_disequality};
+        use crate::{ast::ProofArg, parser::tests::parse_terms};
+
+        let mut pool = crate::ast::PrimitivePool::new();
+        let definitions = "(declare-fun a () Real) (declare-fun b () Real)";
+        let [first, second, one, two] = parse_terms(
+            &mut pool,
+            definitions,
+            ["(< a 1.0)", "(> (+ a b) 0.0)", "1.0", "2.0"],
+        );
+        let args = [ProofArg::Term(one), ProofArg::Term(two)];
+        let mut contributions = Vec::new();
+        let result =
+            la_generic_final_disequality(&[first, second], &args, Some(&mut contributions))
+                .unwrap();
+        assert!(!is_contradiction(&result));
+
+        assert_eq!(contributions.len(), 2);
+        assert_eq!(contributions[1].coefficient, 2);
+        assert_eq!(contributions[1].disequality.1 .0.len(), 2);
+
+        // With a coefficient of 2 for the second literal, `a` is not cancelled out, and `b` is only
+        // contributed by the second literal, so both remain in the final disequality
+        assert_eq!(result.1 .0.len(), 2);
+    }
+
+    #[test]
+    fn la_generic_infer_args() {
+        use super::{
+            check_or_infer_la_generic_coefficients, final_disequality_with_coefficients,
+            is_contradiction,
+        };
+        use crate::{ast::ProofArg, parser::tests::parse_terms};
+
+        let definitions = "
+            (declare-fun a () Real)
+            (declare-fun b () Real)
+            (declare-fun m () Int)
+            (declare-fun n () Int)
+        ";
+        let cases: &[(&[&str], &[&str], Option<bool>)] = &[
+            // Valid arguments are kept
+            (
+                &["(not (< a 0.0))", "(not (> a 0.0))"],
+                &["1.0", "1.0"],
+                Some(false),
+            ),
+            // Wrong or missing arguments are inferred
+            (
+                &["(not (< a 0.0))", "(not (> a 0.0))"],
+                &["1.0", "0.0"],
+                Some(true),
+            ),
+            (&["(not (< a 0.0))", "(not (> a 0.0))"], &[], Some(true)),
+            (&["(not (= a b))", "(not (< b a))"], &["1.0"], Some(true)),
+            (
+                &["(not (<= (+ m n) 1))", "(not (>= m 2))", "(not (>= n 0))"],
+                &[],
+                Some(true),
+            ),
+            (
+                &[
+                    "(< (* 2.0 a) 1.0)",
+                    "(not (< b 0.0))",
+                    "(>= (- (* 2.0 a) b) 1.0)",
+                ],
+                &["2.0", "1.0", "1.0"],
+                Some(true),
+            ),
+            // Steps that are not valid are still rejected
+            (&["(< a 0.0)"], &[], None),
+            (
+                &["(not (< a 0.0))", "(not (> b 0.0))"],
+                &["1.0", "1.0"],
+                None,
+            ),
+        ];
+
+        for &(clause, args, expected) in cases {
+            let mut pool = crate::ast::PrimitivePool::new();
+            let clause: Vec<_> = clause
+                .iter()
+                .map(|t| parse_terms(&mut pool, definitions, [*t])[0].clone())
+                .collect();
+            let args: Vec<_> = args
+                .iter()
+                .map(|t| ProofArg::Term(parse_terms(&mut pool, definitions, [*t])[0].clone()))
+                .collect();
+
+            let got = check_or_infer_la_generic_coefficients(&clause, &args);
+            match (expected, got) {
+                (None, got) => assert!(got.is_err()),
+                (Some(false), got) => assert_eq!(got.unwrap(), None),
+                (Some(true), got) => {
+                    let coefficients = got.unwrap().unwrap();
+                    let result =
+                        final_disequality_with_coefficients(&clause, coefficients, None).unwrap();
+                    assert!(is_contradiction(&result));
+                }
+            }
+        }
+    }
+
     #[test]
     fn la_disequality() {
         test_cases! {
@@ -1,6 +1,8 @@
+#[cfg(feature = "elaborator")]
+use super::Elaborator;
 use super::{
     error::{CheckerError, EqualityError},
-    ContextStack, Elaborator,
+    ContextStack, Leniency,
 };
 use crate::{
     ast::*,
@@ -12,7 +14,8 @@ pub type RuleResult = Result<(), CheckerError>;
 
 pub type Rule = fn(RuleArgs) -> RuleResult;
 
-pub type ElaborationRule = fn(RuleArgs, String, &mut Elaborator) -> Result<(), CheckerError>;
+#[cfg(feature = "elaborator")]
+pub type ElaborationRule = fn(RuleArgs, StepId, &mut Elaborator) -> Result<(), CheckerError>;
 
 pub struct RuleArgs<'a> {
     pub(super) conclusion: &'a [Rc<Term>],
@@ -28,11 +31,55 @@ pub struct RuleArgs<'a> {
     pub(super) discharge: &'a [&'a ProofCommand],
 
     pub(super) polyeq_time: &'a mut Duration,
+
+    // If enabled, the cache used to avoid recomputing the literals of premise clauses in
+    // resolution steps
+    pub(super) literal_cache: Option<&'a mut resolution::LiteralCache>,
+
+    pub(super) la_generic_options: linear_arithmetic::LaGenericOptions,
+}
+
+impl RuleArgs<'_> {
+    /// Returns arguments that borrow from these ones, so the same step can be checked by more than
+    /// one rule.
+    fn reborrow(&mut self) -> RuleArgs<'_> {
+        RuleArgs {
+            conclusion: self.conclusion,
+            premises: self.premises,
+            args: self.args,
+            pool: &mut *self.pool,
+            context: &mut *self.context,
+            previous_command: self.previous_command,
+            discharge: self.discharge,
+            polyeq_time: &mut *self.polyeq_time,
+            literal_cache: self.literal_cache.as_deref_mut(),
+            la_generic_options: self.la_generic_options,
+        }
+    }
+}
+
+/// Checks a step using the non-strict version of its rule, `rule`, and returns the lenient
+/// reasoning needed to accept it, if any. Only the rules that have a strict version can be
+/// lenient, so for all other rules this always returns `None`.
+pub fn find_leniency(
+    rule_name: &str,
+    rule: Rule,
+    mut rule_args: RuleArgs,
+) -> Result<Option<Leniency>, CheckerError> {
+    let (strict_rule, leniency): (Rule, _) = match rule_name {
+        "resolution" | "th_resolution" => return resolution::resolution_leniency(rule_args),
+        "refl" => (reflexivity::strict_refl, Leniency::EqualityFlip),
+        "cong" => (congruence::strict_cong, Leniency::Reordering),
+        "ho_cong" => (congruence::strict_ho_cong, Leniency::Reordering),
+        _ => return rule(rule_args).map(|()| None),
+    };
+    rule(rule_args.reborrow())?;
+    Ok(strict_rule(rule_args).is_err().then_some(leniency))
 }
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub struct Premise<'a> {
-    pub id: &'a str,
+    pub id: &'a StepId,
     pub clause: &'a [Rc<Term>],
     pub index: (usize, usize),
 }
@@ -53,7 +100,7 @@ fn get_premise_term<'a>(premise: &Premise<'a>) -> Result<&'a Rc<Term>, CheckerEr
     match premise.clause {
         [t] => Ok(t),
         cl => Err(CheckerError::WrongLengthOfPremiseClause(
-            premise.id.to_owned(),
+            premise.id.to_string(),
             1.into(),
             cl.len(),
         )),
@@ -185,12 +232,75 @@ fn run_tests(test_name: &str, definitions: &str, cases: &[(&str, bool)]) {
 
         let mut checker = checker::ProofChecker::new(&mut pool, checker::Config::new(), &prelude);
         let got = checker.check(&proof).is_ok();
-        assert_eq!(
-            *expected, got,
-            "test case \"{}\" index {} failed",
-            test_name, i
-        );
+        if *expected != got {
+            // To help debugging, we check the proof again with `la_generic` certificates enabled,
+            // so that the error includes the intermediate disequalities of `la_generic` steps
+            let config = checker::Config::new().la_certificates(true);
+            let mut checker = checker::ProofChecker::new(&mut pool, config, &prelude);
+            let result = checker.check(&proof);
+            panic!(
+                "test case \"{}\" index {} failed: expected {}, got {}\n{}",
+                test_name,
+                i,
+                expected,
+                got,
+                explain_test_case(&proof.commands, result.err()),
+            );
+        }
+    }
+}
+
+/// Builds a description of a failed test case, including the conclusion and arguments of each
+/// step, and the checker error, if any.
+#[cfg(test)]
+fn explain_test_case(commands: &[ProofCommand], error: Option<crate::Error>) -> String {
+    use std::fmt::Write;
+
+    fn write_commands(result: &mut String, commands: &[ProofCommand], indent: usize) {
+        let pad = " ".repeat(indent);
+        for command in commands {
+            match command {
+                ProofCommand::Assume { id, term } => {
+                    writeln!(result, "{}assume {}: {}", pad, id, term).unwrap();
+                }
+                ProofCommand::Step(step) => {
+                    write!(result, "{}step {}: (cl", pad, step.id).unwrap();
+                    for term in &step.clause {
+                        write!(result, " {}", term).unwrap();
+                    }
+                    write!(result, ") :rule {}", step.rule).unwrap();
+                    if !step.args.is_empty() {
+                        let args: Vec<_> = step
+                            .args
+                            .iter()
+                            .map(|arg| match arg {
+                                ProofArg::Term(t) => t.to_string(),
+                                ProofArg::Assign(name, t) => format!("(:= {} {})", name, t),
+                                ProofArg::List(ts) => {
+                                    let ts: Vec<_> = ts.iter().map(ToString::to_string).collect();
+                                    format!("(@list {})", ts.join(" "))
+                                }
+                            })
+                            .collect();
+                        write!(result, " :args ({})", args.join(" ")).unwrap();
+                    }
+                    writeln!(result).unwrap();
+                }
+                ProofCommand::Subproof(s) => {
+                    writeln!(result, "{}subproof {}:", pad, command.id()).unwrap();
+                    write_commands(result, &s.commands, indent + 2);
+                }
+            }
+        }
+    }
+
+    let mut result = String::new();
+    write_commands(&mut result, commands, 2);
+    match error {
+        Some(e) => writeln!(result, "checker error: {}", e).unwrap(),
+        None => writeln!(result, "checker accepted the proof").unwrap(),
     }
+    result
 }
 
 #[cfg(test)]
@@ -25,12 +25,21 @@ pub fn forall_inst(
         .map(|arg| {
             let (arg_name, arg_value) = arg.as_assign()?;
             let arg_sort = pool.sort(arg_value);
-            rassert!(
-                bindings.remove(&(arg_name.clone(), arg_sort.clone())),
-                QuantifierError::NoBindingMatchesArg(arg_name.clone())
-            );
+            if !bindings.remove(&(*arg_name, arg_sort.clone())) {
+                // If a binding has the same name as the argument, but a different sort, we return
+                // a more specific error
+                let error = match bindings.iter().find(|(name, _)| name == arg_name) {
+                    Some((_, expected)) => QuantifierError::ArgSortMismatch {
+                        var: *arg_name,
+                        expected: expected.clone(),
+                        got: arg_sort,
+                    },
+                    None => QuantifierError::NoBindingMatchesArg(*arg_name),
+                };
+                return Err(error.into());
+            }
 
-            let ident_term = (arg_name.clone(), arg_sort).into();
+            let ident_term = (*arg_name, arg_sort).into();
             Ok((pool.add(ident_term), arg_value.clone()))
         })
         .collect::<Result<_, CheckerError>>()?;
@@ -39,7 +48,7 @@ pub fn forall_inst(
     // All bindings were accounted for in the arguments
     rassert!(
         bindings.is_empty(),
-        QuantifierError::NoArgGivenForBinding(bindings.iter().next().unwrap().0.clone())
+        QuantifierError::NoArgGivenForBinding(bindings.iter().next().unwrap().0)
     );
 
     // Equalities may be reordered in the final term, so we need to compare for polyequality here
@@ -289,7 +298,7 @@ pub fn qnt_cnf(RuleArgs { conclusion, pool, .. }: RuleArgs) -> RuleResult {
     // added by the prenexing step. All bindings in the right side must be in this set
     if let Some((var, _)) = r_bindings.iter().find(|&b| !new_bindings.contains(b)) {
         return Err(CheckerError::Quant(
-            QuantifierError::CnfNewBindingIntroduced(var.clone()),
+            QuantifierError::CnfNewBindingIntroduced(*var),
         ));
     }
 
@@ -360,6 +369,13 @@ mod tests {
                 "(step t1 (cl (or (not (forall ((x Real) (y Real)) (= x y))) (= a b)))
                     :rule forall_inst :args ((:= x a)))": false,
             }
+            "Argument sort does not match binding sort" {
+                "(step t1 (cl (or (not (forall ((x Real)) (= x a))) (= a a)))
+                    :rule forall_inst :args ((:= x 1)))": false,
+
+                "(step t1 (cl (or (not (forall ((x Real) (p Bool)) (= x a))) (= a a)))
+                    :rule forall_inst :args ((:= x a) (:= p 1.0)))": false,
+            }
             "Wrong type of rule argument" {
                 "(step t1 (cl (or (not (forall ((x Real) (y Real)) (= x y))) (= a b)))
                     :rule forall_inst :args ((:= x a) b))": false,
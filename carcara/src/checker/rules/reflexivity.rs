@@ -1,4 +1,6 @@
-use super::{assert_clause_len, assert_eq, CheckerError, Elaborator, RuleArgs, RuleResult};
+#[cfg(feature = "elaborator")]
+use super::Elaborator;
+use super::{assert_clause_len, assert_eq, CheckerError, RuleArgs, RuleResult};
 use crate::ast::*;
 
 pub fn eq_reflexive(RuleArgs { conclusion, .. }: RuleArgs) -> RuleResult {
@@ -73,6 +75,7 @@ pub fn strict_refl(RuleArgs { conclusion, pool, context, .. }: RuleArgs) -> Rule
     Ok(())
 }
 
+#[cfg(feature = "elaborator")]
 fn elaborate_equality(
     elaborator: &mut Elaborator,
     pool: &mut dyn TermPool,
@@ -85,6 +88,7 @@ fn elaborate_equality(
     elaborator.elaborate_polyeq(pool, id, left.clone(), right.clone(), is_alpha_equivalence)
 }
 
+#[cfg(feature = "elaborator")]
 pub fn elaborate_refl(
     RuleArgs {
         conclusion,
@@ -93,7 +97,7 @@ pub fn elaborate_refl(
         polyeq_time,
         ..
     }: RuleArgs,
-    command_id: String,
+    command_id: StepId,
     elaborator: &mut Elaborator,
 ) -> RuleResult {
     assert_clause_len(conclusion, 1)?;
@@ -133,7 +137,7 @@ pub fn elaborate_refl(
         elaborator.push_elaborated_step(ProofStep {
             id,
             clause: conclusion.to_vec(),
-            rule: "reordering".to_owned(),
+            rule: "reordering".into(),
             premises: vec![equality_step],
             args: Vec::new(),
             discharge: Vec::new(),
@@ -149,7 +153,7 @@ pub fn elaborate_refl(
             elaborator.push_elaborated_step(ProofStep {
                 id,
                 clause: conclusion.to_vec(),
-                rule: "trans".to_owned(),
+                rule: "trans".into(),
                 premises: vec![first_step, second_step],
                 args: Vec::new(),
                 discharge: Vec::new(),
@@ -164,7 +168,7 @@ pub fn elaborate_refl(
             elaborator.push_elaborated_step(ProofStep {
                 id,
                 clause: conclusion.to_vec(),
-                rule: "trans".to_owned(),
+                rule: "trans".into(),
                 premises: vec![first_step, second_step, third_step],
                 args: Vec::new(),
                 discharge: Vec::new(),
@@ -2,13 +2,15 @@ use super::{
     assert_clause_len, assert_eq, assert_is_bool_constant, assert_num_args, assert_num_premises,
     CheckerError, Premise, RuleArgs, RuleResult,
 };
+#[cfg(feature = "elaborator")]
+use crate::checker::Elaborator;
 use crate::{
     ast::*,
-    checker::{error::ResolutionError, Elaborator},
+    checker::{error::ResolutionError, Leniency},
     utils::DedupIterator,
 };
 use indexmap::{map::Entry, IndexMap, IndexSet};
-use std::iter::FromIterator;
+use std::{collections::VecDeque, iter::FromIterator};
 
 type ResolutionTerm<'a> = (u32, &'a Rc<Term>);
 
@@ -19,31 +21,116 @@ trait ClauseCollection<'a>: FromIterator<ResolutionTerm<'a>> {
     fn remove_term(&mut self, item: &ResolutionTerm<'a>) -> bool;
 }
 
-impl<'a> ClauseCollection<'a> for Vec<ResolutionTerm<'a>> {
+impl<'a> ClauseCollection<'a> for IndexSet<ResolutionTerm<'a>> {
     fn insert_term(&mut self, item: ResolutionTerm<'a>) {
-        self.push(item);
+        self.insert(item);
     }
 
     fn remove_term(&mut self, item: &ResolutionTerm<'a>) -> bool {
-        if let Some(pos) = self.iter().position(|x| x == item) {
-            self.remove(pos);
-            true
-        } else {
-            false
+        self.remove(item)
+    }
+}
+
+/// A clause that keeps its terms in the order in which they were inserted, including repeated
+/// terms, like a `Vec`. Unlike a `Vec`, removing a term doesn't require a linear search, so
+/// resolution with very wide clauses doesn't take quadratic time. When a term is removed, its
+/// first occurrence is removed.
+#[derive(Default)]
+struct OrderedClause<'a> {
+    terms: Vec<Option<ResolutionTerm<'a>>>,
+
+    /// The positions in `terms` of the occurrences of each term that were not yet removed.
+    positions: IndexMap<ResolutionTerm<'a>, VecDeque<usize>>,
+}
+
+impl<'a> OrderedClause<'a> {
+    fn into_vec(self) -> Vec<ResolutionTerm<'a>> {
+        self.terms.into_iter().flatten().collect()
+    }
+}
+
+impl<'a> FromIterator<ResolutionTerm<'a>> for OrderedClause<'a> {
+    fn from_iter<I: IntoIterator<Item = ResolutionTerm<'a>>>(iter: I) -> Self {
+        let mut clause = Self::default();
+        for item in iter {
+            clause.insert_term(item);
         }
+        clause
     }
 }
 
-impl<'a> ClauseCollection<'a> for IndexSet<ResolutionTerm<'a>> {
+impl<'a> ClauseCollection<'a> for OrderedClause<'a> {
     fn insert_term(&mut self, item: ResolutionTerm<'a>) {
-        self.insert(item);
+        self.positions
+            .entry(item)
+            .or_default()
+            .push_back(self.terms.len());
+        self.terms.push(Some(item));
     }
 
     fn remove_term(&mut self, item: &ResolutionTerm<'a>) -> bool {
-        self.remove(item)
+        match self.positions.get_mut(item).and_then(VecDeque::pop_front) {
+            Some(i) => {
+                self.terms[i] = None;
+                true
+            }
+            None => false,
+        }
     }
 }
 
+/// A cache of the literals in each premise clause, keyed by the premise id. Consecutive resolution
+/// steps often share most of their premises, so this avoids rebuilding the literal sets used by
+/// the RUP resolution check from scratch every time. The premises inside a subproof are evicted
+/// when it is closed, since they can no longer be referenced.
+#[derive(Debug, Default)]
+pub struct LiteralCache {
+    literals: IndexMap<StepId, IndexSet<(bool, Rc<Term>)>>,
+
+    /// The ids of the cached premises at each subproof depth.
+    by_depth: Vec<Vec<StepId>>,
+}
+
+impl LiteralCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn get_or_insert(&mut self, premise: &Premise) -> &IndexSet<(bool, Rc<Term>)> {
+        if !self.literals.contains_key(premise.id) {
+            self.literals
+                .insert(premise.id.clone(), premise_literals(premise));
+            let depth = premise.index.0;
+            if self.by_depth.len() <= depth {
+                self.by_depth.resize_with(depth + 1, Vec::new);
+            }
+            self.by_depth[depth].push(premise.id.clone());
+        }
+        &self.literals[premise.id]
+    }
+
+    /// Evicts the premises of a subproof whose commands are at the given depth, including those
+    /// of any subproofs nested in it. This should be called when the subproof is closed.
+    pub fn close_subproof(&mut self, depth: usize) {
+        for ids in self.by_depth.iter_mut().skip(depth) {
+            for id in ids.drain(..) {
+                self.literals.swap_remove(&id);
+            }
+        }
+    }
+}
+
+fn premise_literals(premise: &Premise) -> IndexSet<(bool, Rc<Term>)> {
+    premise
+        .clause
+        .iter()
+        .map(|t| {
+            let (p, t) = t.remove_all_negations_with_polarity();
+            (p, t.clone())
+        })
+        .collect()
+}
+
 /// Undoes the transformation done by `Rc<Term>::remove_all_negations`.
 fn unremove_all_negations(pool: &mut dyn TermPool, (n, term): ResolutionTerm) -> Rc<Term> {
     let mut term = term.clone();
@@ -59,7 +146,13 @@ pub fn resolution(rule_args: RuleArgs) -> RuleResult {
         // the pivots as arguments
         return resolution_with_args(rule_args);
     }
-    let RuleArgs { conclusion, premises, pool, .. } = rule_args;
+    let RuleArgs {
+        conclusion,
+        premises,
+        pool,
+        literal_cache,
+        ..
+    } = rule_args;
 
     // In some cases, this rule is used with a single premise `(not true)` to justify an empty
     // conclusion clause
@@ -76,7 +169,7 @@ pub fn resolution(rule_args: RuleArgs) -> RuleResult {
     greedy_resolution(conclusion, premises, pool, false)
         .map(|_| ())
         .or_else(|greedy_error| {
-            if rup_resolution(conclusion, premises) {
+            if rup_resolution(conclusion, premises, literal_cache) {
                 Ok(())
             } else {
                 // If RUP resolution also fails, we return the error originally returned by the greedy
@@ -86,11 +179,192 @@ pub fn resolution(rule_args: RuleArgs) -> RuleResult {
         })
 }
 
+/// Checks a `resolution` or `th_resolution` step, like [`resolution`], and returns the pivots found
+/// by the checker. Returns `None` if the step was accepted without finding pivots that could be
+/// given as its arguments. This happens if it already has the pivots as arguments, if it derives
+/// the empty clause from `(not true)`, if it was only accepted by the RUP check, or if it relies on
+/// one of the special cases of the rule, like an implicitly eliminated `false` literal.
+pub fn find_resolution_pivots(
+    rule_args: RuleArgs,
+) -> Result<Option<ResolutionPivots>, CheckerError> {
+    if !rule_args.args.is_empty() {
+        return resolution_with_args(rule_args).map(|()| None);
+    }
+    let RuleArgs {
+        conclusion,
+        premises,
+        pool,
+        literal_cache,
+        ..
+    } = rule_args;
+
+    if conclusion.is_empty() && premises.len() == 1 {
+        if let [t] = premises[0].clause {
+            if match_term!((not true) = t).is_some() {
+                return Ok(None);
+            }
+        }
+    }
+    assert_num_premises(premises, 2..)?;
+
+    let (premises, trace) = match traced_resolution(conclusion, premises, pool) {
+        Ok(found) => found,
+        Err(_) if rup_resolution(conclusion, premises, literal_cache) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let found = ResolutionPivots {
+        premises: premises.iter().map(|p| p.index).collect(),
+        pivots: trace.pivot_trace,
+    };
+
+    // The special cases of the rule are accepted by the greedy algorithm, but not when the pivots
+    // are given as arguments, so we only return pivots that derive exactly the conclusion
+    let args = found.to_args(pool);
+    let conclusion: IndexSet<_> = conclusion.iter().map(Rc::remove_all_negations).collect();
+    let is_exact = apply_generic_resolution::<IndexSet<_>>(&premises, &args, pool)
+        .is_ok_and(|result| result == conclusion);
+    Ok(is_exact.then_some(found))
+}
+
+/// Checks a `resolution` or `th_resolution` step, like [`resolution`], and returns the lenient
+/// reasoning needed to accept it, if any. Steps that have the pivots as arguments are checked
+/// strictly, so they are never lenient.
+pub fn resolution_leniency(rule_args: RuleArgs) -> Result<Option<Leniency>, CheckerError> {
+    if !rule_args.args.is_empty() {
+        return resolution_with_args(rule_args).map(|()| None);
+    }
+    let RuleArgs {
+        conclusion,
+        premises,
+        pool,
+        literal_cache,
+        ..
+    } = rule_args;
+
+    if conclusion.is_empty() && premises.len() == 1 {
+        if let [t] = premises[0].clause {
+            if match_term!((not true) = t).is_some() {
+                return Ok(None);
+            }
+        }
+    }
+    assert_num_premises(premises, 2..)?;
+
+    let double_negation =
+        |trace: &ResolutionTrace| trace.not_not_added.then_some(Leniency::DoubleNegation);
+    match greedy_resolution(conclusion, premises, pool, false) {
+        Ok(trace) => Ok(double_negation(&trace)),
+        Err(greedy_error) => match traced_resolution(conclusion, premises, pool) {
+            Ok((_, trace)) => Ok(double_negation(&trace).or(Some(Leniency::Reordering))),
+            Err(_) if rup_resolution(conclusion, premises, literal_cache) => {
+                Ok(Some(Leniency::UnitPropagation))
+            }
+            Err(_) => Err(greedy_error),
+        },
+    }
+}
+
+/// Tries to interpret a resolution step as a hyper-resolution step. In a hyper-resolution step,
+/// one of the premises (the "nucleus") has several pivots, each of which is eliminated by one of
+/// the other premises (the "satellites"). Some solvers emit these steps with the nucleus in an
+/// arbitrary position, for example:
+///
+/// ```text
+/// (step t1 (cl a) :rule hole)
+/// (step t2 (cl b) :rule hole)
+/// (step t3 (cl (not a) (not b) (not c) (not d) e) :rule hole)
+/// (step t4 (cl c) :rule hole)
+/// (step t5 (cl d) :rule hole)
+/// (step t6 (cl e) :rule resolution :premises (t1 t2 t3 t4 t5))
+/// ```
+///
+/// Since only one pivot may be eliminated per clause, the greedy algorithm rejects this step, even
+/// if the premises are reversed. The RUP check accepts it, but doesn't find the pivots needed to
+/// elaborate it. The step is, however, equivalent to a chain of binary resolutions that starts
+/// with the nucleus. This function tries each premise with more than one literal as the nucleus,
+/// moving it to the start of the premises, and checks the reordered step with the greedy
+/// algorithm. If it succeeds, the reordered premises are returned, together with the resolution
+/// trace.
+fn hyper_resolution<'a>(
+    conclusion: &[Rc<Term>],
+    premises: &[Premise<'a>],
+    pool: &mut dyn TermPool,
+    tracing: bool,
+) -> Option<(Vec<Premise<'a>>, ResolutionTrace)> {
+    (1..premises.len())
+        .filter(|&i| premises[i].clause.len() > 1)
+        .find_map(|i| {
+            let mut reordered = premises.to_vec();
+            let nucleus = reordered.remove(i);
+            reordered.insert(0, nucleus);
+            let trace = greedy_resolution(conclusion, &reordered, pool, tracing).ok()?;
+            Some((reordered, trace))
+        })
+}
+
 struct ResolutionTrace {
     not_not_added: bool,
     pivot_trace: Vec<(Rc<Term>, bool)>,
 }
 
+/// The pivots of a `resolution` or `th_resolution` step that were found by the checker. See
+/// [`ProofChecker::check_with_resolution_pivots`](crate::checker::ProofChecker::check_with_resolution_pivots).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolutionPivots {
+    /// The premises of the step, in the order in which they must be resolved. This may differ from
+    /// the order in the step, since the checker may reorder the premises to find the pivots.
+    pub premises: Vec<(usize, usize)>,
+
+    /// The pivot of each binary resolution, and `true` if the pivot appears in the clause obtained
+    /// so far and its negation in the next premise, or `false` if it is the other way around. This
+    /// is the same convention used by the arguments of `resolution` steps.
+    pub pivots: Vec<(Rc<Term>, bool)>,
+}
+
+impl ResolutionPivots {
+    /// Returns the pivots as step arguments, in the form expected by `resolution` steps with
+    /// explicit pivots.
+    pub fn to_args(&self, pool: &mut dyn TermPool) -> Vec<ProofArg> {
+        self.pivots
+            .iter()
+            .flat_map(|(pivot, polarity)| [pivot.clone(), pool.bool_constant(*polarity)])
+            .map(ProofArg::Term)
+            .collect()
+    }
+}
+
+/// Finds the pivots of a resolution step using the greedy algorithm, first with the premises in
+/// the given order, then reversed, and finally trying to interpret the step as a hyper-resolution
+/// step. Returns the premises in the order in which the pivots apply to them. If none of these
+/// work, the error returned by the greedy algorithm for the original order is returned.
+fn traced_resolution<'a>(
+    conclusion: &[Rc<Term>],
+    premises: &[Premise<'a>],
+    pool: &mut dyn TermPool,
+) -> Result<(Vec<Premise<'a>>, ResolutionTrace), CheckerError> {
+    let mut premises: Vec<_> = premises.iter().dedup().copied().collect();
+    let trace = match greedy_resolution(conclusion, &premises, pool, true) {
+        Ok(trace) => trace,
+        Err(greedy_error) => {
+            premises.reverse();
+            match greedy_resolution(conclusion, &premises, pool, true) {
+                Ok(trace) => trace,
+                Err(_) => {
+                    // If neither order works, the step may be a hyper-resolution step, in which
+                    // case it is normalized into a standard resolution step by moving its nucleus
+                    // to the start of the premises
+                    premises.reverse();
+                    let (reordered, trace) =
+                        hyper_resolution(conclusion, &premises, pool, true).ok_or(greedy_error)?;
+                    premises = reordered;
+                    trace
+                }
+            }
+        }
+    };
+    Ok((premises, trace))
+}
+
 fn greedy_resolution(
     conclusion: &[Rc<Term>],
     premises: &[Premise],
@@ -248,45 +522,85 @@ fn greedy_resolution(
     }
 }
 
-fn rup_resolution(conclusion: &[Rc<Term>], premises: &[Premise]) -> bool {
-    let mut clauses: Vec<IndexSet<(bool, &Rc<Term>)>> = premises
+/// Checks a resolution step using reverse unit propagation: the negation of each conclusion literal
+/// is added as a unit clause, and unit propagation is applied to these and the premise clauses.
+/// The step is valid if this results in a conflict. To handle very wide clauses, each clause keeps
+/// the number of its literals that are not yet false, and each literal keeps the clauses it occurs
+/// in, so propagation takes time linear in the total size of the clauses.
+fn rup_resolution(
+    conclusion: &[Rc<Term>],
+    premises: &[Premise],
+    cache: Option<&mut LiteralCache>,
+) -> bool {
+    let owned: Vec<_>;
+    let clauses: Vec<&IndexSet<(bool, Rc<Term>)>> = match cache {
+        Some(cache) => {
+            for p in premises {
+                cache.get_or_insert(p);
+            }
+            premises.iter().map(|p| &cache.literals[p.id]).collect()
+        }
+        None => {
+            owned = premises.iter().map(premise_literals).collect();
+            owned.iter().collect()
+        }
+    };
+
+    let mut queue: Vec<(bool, Rc<Term>)> = conclusion
         .iter()
-        .map(|p| {
-            p.clause
-                .iter()
-                .map(Rc::remove_all_negations_with_polarity)
-                .collect()
+        .map(|t| {
+            let (p, t) = t.remove_all_negations_with_polarity();
+            (!p, t.clone())
         })
         .collect();
-    clauses.extend(conclusion.iter().map(|t| {
-        let (p, t) = t.remove_all_negations_with_polarity();
-        let mut clause = IndexSet::new();
-        clause.insert((!p, t));
-        clause
-    }));
-
-    loop {
-        if clauses.is_empty() {
-            return false;
-        }
-        let smallest = clauses.iter().min_by_key(|c| c.len()).unwrap();
-        match smallest.len() {
+    let mut occurrences: IndexMap<&(bool, Rc<Term>), Vec<usize>> = IndexMap::new();
+    let mut num_unassigned = Vec::with_capacity(clauses.len());
+    for (i, clause) in clauses.iter().enumerate() {
+        match clause.len() {
             0 => return true,
-            1 => {
-                let literal = *smallest.iter().next().unwrap();
-                let negated_literal = (!literal.0, literal.1);
+            1 => queue.push(clause[0].clone()),
+            _ => (),
+        }
+        for literal in *clause {
+            occurrences.entry(literal).or_default().push(i);
+        }
+        num_unassigned.push(clause.len());
+    }
 
-                // Remove all clauses that contain the literal
-                clauses.retain(|c| !c.contains(&literal));
+    let mut satisfied = vec![false; clauses.len()];
+    let mut assigned: IndexSet<(bool, Rc<Term>)> = IndexSet::new();
+    while let Some(literal) = queue.pop() {
+        let negated_literal = (!literal.0, literal.1.clone());
+        if assigned.contains(&negated_literal) {
+            return true;
+        }
+        if !assigned.insert(literal.clone()) {
+            continue;
+        }
 
-                // Remove the negated literal from all clauses that contain it
-                for c in &mut clauses {
-                    c.remove(&negated_literal);
+        for &i in occurrences.get(&literal).into_iter().flatten() {
+            satisfied[i] = true;
+        }
+        for &i in occurrences.get(&negated_literal).into_iter().flatten() {
+            if satisfied[i] {
+                continue;
+            }
+            num_unassigned[i] -= 1;
+            match num_unassigned[i] {
+                0 => return true,
+                1 => {
+                    // The clause became a unit clause, so we propagate its only literal that is
+                    // not yet false
+                    let unit = clauses[i]
+                        .iter()
+                        .find(|(p, t)| !assigned.contains(&(!p, t.clone())));
+                    queue.extend(unit.cloned());
                 }
+                _ => (),
             }
-            _ => return false,
         }
     }
+    false
 }
 
 pub fn resolution_with_args(
@@ -316,7 +630,8 @@ pub fn strict_resolution(
 ) -> RuleResult {
     use std::cmp::Ordering;
 
-    let resolution_result = apply_generic_resolution::<Vec<_>>(premises, args, pool)?;
+    let resolution_result =
+        apply_generic_resolution::<OrderedClause>(premises, args, pool)?.into_vec();
 
     match conclusion.len().cmp(&resolution_result.len()) {
         Ordering::Less => {
@@ -410,9 +725,10 @@ fn binary_resolution<'a, C: ClauseCollection<'a>>(
     Ok(())
 }
 
+#[cfg(feature = "elaborator")]
 pub fn elaborate_resolution(
     RuleArgs { conclusion, premises, pool, .. }: RuleArgs,
-    command_id: String,
+    command_id: StepId,
     elaborator: &mut Elaborator,
 ) -> RuleResult {
     // In the cases where the rule is used to get an empty clause from `(not true)`, we add a `true`
@@ -424,7 +740,7 @@ pub fn elaborate_resolution(
                 let true_step = elaborator.add_new_step(ProofStep {
                     id,
                     clause: vec![pool.bool_true()],
-                    rule: "true".to_owned(),
+                    rule: "true".into(),
                     premises: Vec::new(),
                     args: Vec::new(),
                     discharge: Vec::new(),
@@ -433,7 +749,7 @@ pub fn elaborate_resolution(
                 elaborator.push_elaborated_step(ProofStep {
                     id: command_id,
                     clause: Vec::new(),
-                    rule: "resolution".to_owned(),
+                    rule: "resolution".into(),
                     premises,
                     args: [true, false]
                         .map(|a| ProofArg::Term(pool.bool_constant(a)))
@@ -445,12 +761,8 @@ pub fn elaborate_resolution(
         }
     }
 
-    let mut premises: Vec<_> = premises.iter().dedup().copied().collect();
-    let ResolutionTrace { not_not_added, pivot_trace } =
-        greedy_resolution(conclusion, &premises, pool, true).or_else(|_| {
-            premises.reverse();
-            greedy_resolution(conclusion, &premises, pool, true)
-        })?;
+    let (premises, ResolutionTrace { not_not_added, pivot_trace }) =
+        traced_resolution(conclusion, premises, pool)?;
 
     let pivots = pivot_trace
         .into_iter()
@@ -466,7 +778,7 @@ pub fn elaborate_resolution(
     let mut resolution_step = ProofStep {
         id: command_id.clone(),
         clause: conclusion.to_vec(),
-        rule: "resolution".to_owned(),
+        rule: "resolution".into(),
         premises,
         args: pivots,
         discharge: Vec::new(),
@@ -509,7 +821,7 @@ pub fn elaborate_resolution(
         let first_not_not_step = elaborator.add_new_step(ProofStep {
             id,
             clause: vec![quadruple_not_c.clone(), single_not_c],
-            rule: "not_not".to_owned(),
+            rule: "not_not".into(),
             premises: Vec::new(),
             args: Vec::new(),
             discharge: Vec::new(),
@@ -518,7 +830,7 @@ pub fn elaborate_resolution(
         let second_not_not_step = elaborator.add_new_step(ProofStep {
             id,
             clause: vec![quintuple_not_c, double_not_c.clone()],
-            rule: "not_not".to_owned(),
+            rule: "not_not".into(),
             premises: Vec::new(),
             args: Vec::new(),
             discharge: Vec::new(),
@@ -534,7 +846,7 @@ pub fn elaborate_resolution(
         elaborator.push_elaborated_step(ProofStep {
             id,
             clause: vec![double_not_c],
-            rule: "resolution".to_owned(),
+            rule: "resolution".into(),
             premises: vec![resolution_step, first_not_not_step, second_not_not_step],
             args,
             discharge: Vec::new(),
@@ -578,6 +890,23 @@ pub fn contraction(RuleArgs { conclusion, premises, .. }: RuleArgs) -> RuleResul
 
 #[cfg(test)]
 mod tests {
+    #[test]
+    fn literal_cache_eviction() {
+        use super::*;
+
+        let mut pool = PrimitivePool::new();
+        let bool_sort = pool.add(Term::Sort(Sort::Bool));
+        let clause = [pool.add(Term::new_var("p", bool_sort))];
+        let (outer, inner) = (StepId::new("t1"), StepId::new("t2.t1"));
+        let mut cache = LiteralCache::new();
+        for (id, index) in [(&outer, (0, 0)), (&inner, (1, 0))] {
+            cache.get_or_insert(&Premise { id, clause: &clause, index });
+        }
+        cache.close_subproof(1);
+        assert!(cache.literals.contains_key(&outer));
+        assert!(!cache.literals.contains_key(&inner));
+    }
+
     #[test]
     fn resolution() {
         test_cases! {
@@ -713,6 +1042,100 @@ mod tests {
         }
     }
 
+    #[test]
+    fn wide_clauses() {
+        // Clauses from bit-blasting may have hundreds of thousands of literals, so checking these
+        // steps must not take quadratic time
+        const N: usize = 20_000;
+        let vars: Vec<_> = (0..N).map(|i| format!("p{i}")).collect();
+        let definitions: String = vars
+            .iter()
+            .map(|v| format!("(declare-fun {v} () Bool)"))
+            .collect();
+        let negated: Vec<_> = vars.iter().map(|v| format!("(not {v})")).collect();
+        let rest = vars[1..].join(" ");
+
+        let premises = format!(
+            "(step t1 (cl {}) :rule hole)
+            (step t2 (cl (not p0)) :rule hole)",
+            vars.join(" ")
+        );
+        let resolution =
+            format!("{premises} (step t3 (cl {rest}) :rule resolution :premises (t1 t2))");
+        let strict = format!(
+            "{premises} (step t3 (cl {rest}) :rule strict_resolution
+                :premises (t1 t2) :args (p0 true))"
+        );
+
+        // The nucleus is in the middle of the premises, so only the RUP check accepts this step
+        let units: Vec<_> = (0..N)
+            .map(|i| format!("(step u{i} (cl p{i}) :rule hole)"))
+            .collect();
+        let ids: Vec<_> = (0..N).map(|i| format!("u{i}")).collect();
+        let rup = format!(
+            "{} (step n (cl {} q) :rule hole) {}
+            (step t (cl q) :rule resolution :premises ({} n {}))",
+            units[..2].join(" "),
+            negated.join(" "),
+            units[2..].join(" "),
+            ids[..2].join(" "),
+            ids[2..].join(" "),
+        );
+        let definitions = format!("{definitions} (declare-fun q () Bool)");
+        let cases = [
+            (resolution.as_str(), true),
+            (strict.as_str(), true),
+            (rup.as_str(), true),
+        ];
+        crate::checker::rules::run_tests("Wide clauses", &definitions, &cases);
+    }
+
+    #[cfg(feature = "elaborator")]
+    #[test]
+    fn elaborate_hyper_resolution() {
+        use crate::{ast::*, check_and_elaborate, CarcaraOptions};
+
+        let problem = "(declare-fun a () Bool) (declare-fun b () Bool) (declare-fun c () Bool)
+            (declare-fun d () Bool) (declare-fun e () Bool)
+            (assert a) (assert b) (assert (or (not a) (not b) (not c) (not d) e))
+            (assert c) (assert d) (assert (not e))";
+
+        // The nucleus `t1` has two satellites before and two satellites after it, so checking
+        // the premises in either order eliminates more than one pivot with it
+        let proof = "(assume h1 a)
+            (assume h2 b)
+            (assume h3 (or (not a) (not b) (not c) (not d) e))
+            (assume h4 c)
+            (assume h5 d)
+            (assume h6 (not e))
+            (step t1 (cl (not a) (not b) (not c) (not d) e) :rule or :premises (h3))
+            (step t2 (cl e) :rule resolution :premises (h1 h2 t1 h4 h5))
+            (step t3 (cl) :rule resolution :premises (t2 h6))";
+        let (_, elaborated) = check_and_elaborate(
+            problem.as_bytes(),
+            proof.as_bytes(),
+            CarcaraOptions::default(),
+        )
+        .unwrap_or_else(|e| panic!("could not check and elaborate proof: {}", e));
+
+        // The step is normalized into a standard resolution step, starting with the nucleus
+        let step = elaborated
+            .commands
+            .iter()
+            .find_map(|c| match c {
+                ProofCommand::Step(s) if s.id == "t2" => Some(s),
+                _ => None,
+            })
+            .expect("elaborated proof should contain step 't2'");
+        let premises: Vec<_> = step
+            .premises
+            .iter()
+            .map(|&(_, i)| elaborated.commands[i].id())
+            .collect();
+        assert_eq!(["t1", "h1", "h2", "h4", "h5"], premises.as_slice());
+        assert_eq!(8, step.args.len());
+    }
+
     #[test]
     fn strict_resolution() {
         test_cases! {
@@ -1,6 +1,6 @@
 use super::{
-    assert_clause_len, assert_eq, assert_is_bool_constant, CheckerError, EqualityError, RuleArgs,
-    RuleResult,
+    assert_clause_len, assert_eq, assert_is_bool_constant, extras, CheckerError, EqualityError,
+    Rule, RuleArgs, RuleResult,
 };
 use crate::{ast::*, utils::DedupIterator};
 use indexmap::{IndexMap, IndexSet};
@@ -729,6 +729,70 @@ pub fn ac_simp(RuleArgs { conclusion, pool, .. }: RuleArgs) -> RuleResult {
     )
 }
 
+/// The rules that may close an `all_simplify` step, in the order in which they are tried.
+const ALL_SIMPLIFY_RULES: &[(&str, Rule)] = &[
+    ("ite_simplify", ite_simplify),
+    ("eq_simplify", eq_simplify),
+    ("and_simplify", and_simplify),
+    ("or_simplify", or_simplify),
+    ("not_simplify", not_simplify),
+    ("implies_simplify", implies_simplify),
+    ("equiv_simplify", equiv_simplify),
+    ("bool_simplify", bool_simplify),
+    ("qnt_simplify", qnt_simplify),
+    ("div_simplify", div_simplify),
+    ("prod_simplify", prod_simplify),
+    ("minus_simplify", minus_simplify),
+    ("sum_simplify", sum_simplify),
+    ("comp_simplify", comp_simplify),
+    ("mod_simplify", extras::mod_simplify),
+    ("ac_simp", ac_simp),
+];
+
+/// Checks an `all_simplify` step, and returns the name of the rewrite that closed it. The step is
+/// closed if any of the simplification rules accepts it, or if both sides of the equality are
+/// ground terms that evaluate to the same value, in which case `"evaluate"` is returned.
+pub fn find_all_simplify_rewrite(
+    RuleArgs {
+        conclusion,
+        pool,
+        context,
+        polyeq_time,
+        ..
+    }: RuleArgs,
+) -> Result<&'static str, CheckerError> {
+    assert_clause_len(conclusion, 1)?;
+    let (left, right) = match_term_err!((= phi psi) = &conclusion[0])?;
+
+    // None of these rules use premises or arguments, so we can just try each one in turn
+    for &(name, rule) in ALL_SIMPLIFY_RULES {
+        let args = RuleArgs {
+            conclusion,
+            premises: &[],
+            args: &[],
+            pool: &mut *pool,
+            context: &mut *context,
+            previous_command: None,
+            discharge: &[],
+            polyeq_time: &mut *polyeq_time,
+            literal_cache: None,
+            la_generic_options: Default::default(),
+        };
+        if rule(args).is_ok() {
+            return Ok(name);
+        }
+    }
+
+    match (evaluate(pool, left), evaluate(pool, right)) {
+        (Some(a), Some(b)) if a == b => Ok("evaluate"),
+        _ => Err(CheckerError::AllSimplifyFailed(left.clone(), right.clone())),
+    }
+}
+
+pub fn all_simplify(args: RuleArgs) -> RuleResult {
+    find_all_simplify_rewrite(args).map(|_| ())
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -1409,4 +1473,31 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn all_simplify() {
+        test_cases! {
+            definitions = "
+                (declare-fun a () Int)
+                (declare-fun b () Int)
+                (declare-fun p () Bool)
+                (declare-fun q () Bool)
+            ",
+            "Rewrites from other simplification rules" {
+                "(step t1 (cl (= (ite true a b) a)) :rule all_simplify)": true,
+                "(step t1 (cl (= (not (not p)) p)) :rule all_simplify)": true,
+                "(step t1 (cl (= (and (and p q) q) (and p q))) :rule all_simplify)": true,
+                "(step t1 (cl (= (+ a 0) a)) :rule all_simplify)": true,
+            }
+            "Constant evaluation" {
+                "(step t1 (cl (= (* (+ 1 2) 2) 6)) :rule all_simplify)": true,
+                "(step t1 (cl (= (< (+ 1 2) 2) (= 1 2))) :rule all_simplify)": true,
+                "(step t1 (cl (= (* (+ 1 2) 2) 7)) :rule all_simplify)": false,
+            }
+            "Invalid rewrites" {
+                "(step t1 (cl (= (ite p a b) a)) :rule all_simplify)": false,
+                "(step t1 (cl (= (and p q) (or p q))) :rule all_simplify)": false,
+            }
+        }
+    }
 }
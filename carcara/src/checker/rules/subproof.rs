@@ -2,7 +2,10 @@ use super::{
     assert_clause_len, assert_eq, assert_is_expected, assert_num_premises, get_premise_term,
     CheckerError, EqualityError, RuleArgs, RuleResult,
 };
-use crate::{ast::*, checker::error::SubproofError};
+use crate::{
+    ast::*,
+    checker::error::{QuantifierError, SubproofError},
+};
 use indexmap::{IndexMap, IndexSet};
 
 pub fn subproof(
@@ -24,7 +27,9 @@ pub fn subproof(
                 let t = t.remove_negation_err()?;
                 assert_eq(term, t)?;
             }
-            other => return Err(SubproofError::DischargeMustBeAssume(other.id().to_owned()).into()),
+            other => {
+                return Err(SubproofError::DischargeMustBeAssume(other.id().to_string()).into())
+            }
         }
     }
 
@@ -35,7 +40,7 @@ pub fn subproof(
         [t] => t.clone(),
         other => {
             return Err(CheckerError::WrongLengthOfPremiseClause(
-                previous_command.id.to_owned(),
+                previous_command.id.to_string(),
                 (..2).into(),
                 other.len(),
             ))
@@ -84,7 +89,7 @@ pub fn bind(
         .iter()
         .find(|&y| free_vars.contains(y) && !l_bindings.contains(y))
     {
-        let y = y.as_var().unwrap().to_owned();
+        let y = y.as_var().unwrap();
         return Err(SubproofError::BindBindingIsFreeVarInPhi(y).into());
     }
 
@@ -117,11 +122,11 @@ pub fn bind(
 
     // `l_bindings` should be a subset of `xs` and `r_bindigns` should be a subset of `ys`
     if let Some(x) = l_bindings.iter().find(|&x| !xs.contains(x)) {
-        let x = x.as_var().unwrap().to_owned();
+        let x = x.as_var().unwrap();
         return Err(SubproofError::BindingIsNotInContext(x).into());
     }
     if let Some(y) = r_bindings.iter().find(|&y| !ys.contains(y)) {
-        let y = y.as_var().unwrap().to_owned();
+        let y = y.as_var().unwrap();
         return Err(SubproofError::BindingIsNotInContext(y).into());
     }
     Ok(())
@@ -175,10 +180,10 @@ pub fn r#let(
         .iter()
         .map(|(x, t)| {
             let sort = pool.sort(t);
-            let x_term = pool.add((x.clone(), sort).into());
+            let x_term = pool.add((*x, sort).into());
             let s = substitution
                 .get(&x_term)
-                .ok_or_else(|| SubproofError::BindingIsNotInContext(x.clone()))?;
+                .ok_or(SubproofError::BindingIsNotInContext(*x))?;
             Ok((s, t))
         })
         .collect::<Result<_, CheckerError>>()?;
@@ -280,7 +285,7 @@ pub fn onepoint(
             .iter()
             .find(|&b| !last_context.bindings.contains(b))
     } {
-        return Err(SubproofError::BindingIsNotInContext(var.clone()).into());
+        return Err(SubproofError::BindingIsNotInContext(*var).into());
     }
 
     let l_bindings_set: IndexSet<_> = l_bindings
@@ -378,9 +383,23 @@ fn generic_skolemization_rule(
         .collect();
     for (i, x) in bindings.iter().enumerate() {
         let x_term = pool.add(Term::from(x.clone()));
-        let t = substitution
-            .get(&x_term)
-            .ok_or_else(|| SubproofError::BindingIsNotInContext(x.0.clone()))?;
+        let Some(t) = substitution.get(&x_term) else {
+            // If the context assigns a term to a variable with the same name as the binding, but
+            // with a different sort, we return a more specific error
+            let assigned_sort = substitution.keys().find_map(|var| match var.as_ref() {
+                Term::Var(name, sort) if *name == x.0 => Some(sort.clone()),
+                _ => None,
+            });
+            return Err(match assigned_sort {
+                Some(got) => QuantifierError::ArgSortMismatch {
+                    var: x.0,
+                    expected: x.1.clone(),
+                    got,
+                }
+                .into(),
+                None => SubproofError::BindingIsNotInContext(x.0).into(),
+            });
+        };
 
         // To check that `t` is of the correct form, we construct the expected term and compare
         // them
@@ -680,6 +699,8 @@ mod tests {
             definitions = "
                 (declare-fun p (Int) Bool)
                 (declare-fun q (Int) Bool)
+                (declare-fun r (Real) Bool)
+                (declare-fun b () Bool)
             ",
             "Simple working examples" {
                 "(anchor :step t1 :args ((:= x (choice ((x Int)) (p x)))))
@@ -702,6 +723,11 @@ mod tests {
                        (choice ((y Int)) (= (choice ((x Int)) (exists ((y Int)) (= x y))) y)))
                 )) :rule sko_ex)": true,
             }
+            "Assigned term sort does not match binding sort" {
+                "(anchor :step t1 :args ((:= x (choice ((x Real)) (r x)))))
+                (step t1.t1 (cl (= b b)) :rule hole)
+                (step t1 (cl (= (exists ((x Int)) b) b)) :rule sko_ex)": false,
+            }
         }
     }
 
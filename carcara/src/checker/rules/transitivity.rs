@@ -1,4 +1,6 @@
-use super::{assert_clause_len, get_premise_term, CheckerError, Elaborator, RuleArgs, RuleResult};
+#[cfg(feature = "elaborator")]
+use super::Elaborator;
+use super::{assert_clause_len, get_premise_term, CheckerError, RuleArgs, RuleResult};
 use crate::ast::*;
 
 /// Function to find a transitive chain given a conclusion equality and a series of premise
@@ -47,6 +49,7 @@ fn find_chain(
 /// clause. This returns a boolean indicating whether any reordering was needed, a `usize`
 /// indicating how many premises are needed to prove the conclusion, and a vector of indices of the
 /// premise equalities that need to be flipped.
+#[cfg(feature = "elaborator")]
 fn find_and_trace_chain<'a, T>(
     mut conclusion: (&'a Rc<Term>, &'a Rc<Term>),
     premise_equalities: &mut [(&'a Rc<Term>, &'a Rc<Term>)],
@@ -105,9 +108,10 @@ pub fn eq_transitive(RuleArgs { conclusion, .. }: RuleArgs) -> RuleResult {
     find_chain(chain_conclusion, &mut premises)
 }
 
+#[cfg(feature = "elaborator")]
 pub fn elaborate_eq_transitive(
     RuleArgs { conclusion, pool, .. }: RuleArgs,
-    command_id: String,
+    command_id: StepId,
     elaborator: &mut Elaborator,
 ) -> RuleResult {
     assert_clause_len(conclusion, 3..)?;
@@ -156,7 +160,7 @@ pub fn elaborate_eq_transitive(
     let new_eq_transitive_step = ProofStep {
         id: elaborator.get_new_id(&command_id),
         clause: new_clause.clone(),
-        rule: "eq_transitive".to_owned(),
+        rule: "eq_transitive".into(),
         premises: Vec::new(),
         args: Vec::new(),
         discharge: Vec::new(),
@@ -184,7 +188,7 @@ pub fn elaborate_eq_transitive(
         let or_intro_step = ProofStep {
             id: elaborator.get_new_id(&command_id),
             clause,
-            rule: "or_intro".to_owned(),
+            rule: "or_intro".into(),
             premises: vec![latest_step_index],
             args: Vec::new(),
             discharge: Vec::new(),
@@ -195,7 +199,7 @@ pub fn elaborate_eq_transitive(
     elaborator.push_elaborated_step(ProofStep {
         id: command_id,
         clause: conclusion.to_vec(),
-        rule: "reordering".to_owned(),
+        rule: "reordering".into(),
         premises: vec![latest_step_index],
         args: Vec::new(),
         discharge: Vec::new(),
@@ -203,6 +207,7 @@ pub fn elaborate_eq_transitive(
     Ok(())
 }
 
+#[cfg(feature = "elaborator")]
 fn flip_eq_transitive_premises(
     pool: &mut dyn TermPool,
     elaborator: &mut Elaborator,
@@ -221,7 +226,7 @@ fn flip_eq_transitive_premises(
             let new_step = ProofStep {
                 id: elaborator.get_new_id(original_id),
                 clause,
-                rule: "eq_symmetric".to_owned(),
+                rule: "eq_symmetric".into(),
                 premises: Vec::new(),
                 args: Vec::new(),
                 discharge: Vec::new(),
@@ -262,7 +267,7 @@ fn flip_eq_transitive_premises(
     let final_step = ProofStep {
         id: elaborator.get_new_id(original_id),
         clause: clause.clone(),
-        rule: "strict_resolution".to_owned(),
+        rule: "strict_resolution".into(),
         premises,
         args,
         discharge: Vec::new(),
@@ -282,9 +287,10 @@ pub fn trans(RuleArgs { conclusion, premises, .. }: RuleArgs) -> RuleResult {
     find_chain(conclusion, &mut premises)
 }
 
+#[cfg(feature = "elaborator")]
 pub fn elaborate_trans(
     RuleArgs { conclusion, premises, pool, .. }: RuleArgs,
-    command_id: String,
+    command_id: StepId,
     elaborator: &mut Elaborator,
 ) -> RuleResult {
     assert_clause_len(conclusion, 1)?;
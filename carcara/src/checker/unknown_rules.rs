@@ -0,0 +1,80 @@
+use crate::ast::StepId;
+use indexmap::IndexMap;
+
+/// The maximum number of example step ids kept for each unknown rule.
+const MAX_EXAMPLES: usize = 3;
+
+/// The steps that use an unknown rule.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnknownRule {
+    /// The number of steps that use the rule.
+    pub count: usize,
+
+    /// The ids of the first few steps that use the rule, in the order they were found.
+    pub examples: Vec<StepId>,
+}
+
+/// A report of every rule that is not implemented by Carcara, with the number of steps that use
+/// each of them. Steps are recorded even if they were skipped because of the
+/// `ignore_unknown_rules` option, so a single check shows every rule that is missing, instead of
+/// only the first one. See [`ProofChecker::unknown_rules`](super::ProofChecker::unknown_rules).
+#[derive(Debug, Clone, Default)]
+pub struct UnknownRuleReport {
+    /// The steps of each unknown rule, indexed by the rule name as written in the proofs.
+    pub rules: IndexMap<String, UnknownRule>,
+}
+
+impl UnknownRuleReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub(super) fn register(&mut self, rule: &str, id: StepId) {
+        let entry = match self.rules.get_mut(rule) {
+            Some(entry) => entry,
+            None => self.rules.entry(rule.to_owned()).or_default(),
+        };
+        entry.count += 1;
+        if entry.examples.len() < MAX_EXAMPLES {
+            entry.examples.push(id);
+        }
+    }
+
+    /// Adds the steps of another report to this one. Examples are only added while there are
+    /// fewer than the maximum number of them, so the examples of this report come first.
+    pub fn combine(&mut self, other: &Self) {
+        for (rule, other) in &other.rules {
+            let entry = self.rules.entry(rule.clone()).or_default();
+            entry.count += other.count;
+            let missing = MAX_EXAMPLES.saturating_sub(entry.examples.len());
+            entry
+                .examples
+                .extend(other.examples.iter().take(missing).cloned());
+        }
+    }
+
+    /// Returns the unknown rules, sorted by the number of steps that use them, in descending
+    /// order.
+    pub fn by_frequency(&self) -> Vec<(&str, &UnknownRule)> {
+        let mut result: Vec<_> = self.rules.iter().map(|(r, u)| (r.as_str(), u)).collect();
+        result.sort_by(|(a_rule, a), (b_rule, b)| b.count.cmp(&a.count).then(a_rule.cmp(b_rule)));
+        result
+    }
+
+    /// Logs a warning for each unknown rule, in the order given by [`Self::by_frequency`].
+    pub(super) fn log_warnings(&self) {
+        for (rule, unknown) in self.by_frequency() {
+            let examples: Vec<_> = unknown.examples.iter().map(StepId::as_str).collect();
+            log::warn!(
+                "ignored {} steps with unknown rule \"{}\", like {}",
+                unknown.count,
+                rule,
+                examples.join(", ")
+            );
+        }
+    }
+}
@@ -0,0 +1,543 @@
+//! Translating DIMACS CNF formulas and DRAT refutations into Alethe proofs.
+//!
+//! Each variable of the formula becomes a boolean constant named `x<n>`, and each clause of the
+//! formula becomes an `assume` command of the disjunction of its literals, followed by an `or` step
+//! that turns it into a clause. Each lemma of the DRAT refutation becomes a `resolution` step,
+//! whose premises are the clauses used by unit propagation to show that the lemma is a reverse unit
+//! propagation (RUP) consequence of the current clauses. Lemmas that are not RUP consequences (for
+//! example, those that only have the RAT property) become `hole` steps. The translation ends at the
+//! first lemma that is the empty clause.
+//!
+//! As in `drat-trim`, deletions of unit clauses are ignored, since solvers commonly delete unit
+//! clauses that are still used as reasons. Deletions of clauses that don't exist are also ignored.
+
+use crate::ast::*;
+use indexmap::{IndexMap, IndexSet};
+use std::{collections::HashMap, io};
+use thiserror::Error;
+
+/// An error found while reading a DIMACS CNF formula or a DRAT refutation.
+#[derive(Debug, Error)]
+pub enum DratError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("missing or malformed DIMACS header")]
+    InvalidHeader,
+
+    #[error("line {0}: invalid literal: '{1}'")]
+    InvalidLiteral(usize, String),
+
+    #[error("last clause is not terminated by 0")]
+    UnterminatedClause,
+
+    #[error("binary DRAT proofs are not supported")]
+    BinaryProof,
+}
+
+type DratResult<T> = Result<T, DratError>;
+
+/// A line of a DRAT refutation.
+enum DratLine {
+    Lemma(Vec<i32>),
+    Deletion(Vec<i32>),
+}
+
+/// Reads the clauses of a sequence of lines, each clause being a sequence of non-zero literals
+/// terminated by `0`. Lines starting with `c` are comments. Clauses starting with `d` are returned
+/// with `true` as the first element of their pair, and are only allowed if `allow_deletions` is
+/// `true`.
+fn read_clauses<T: io::BufRead>(
+    input: T,
+    allow_deletions: bool,
+    mut on_header: impl FnMut(&str) -> DratResult<()>,
+) -> DratResult<Vec<(bool, Vec<i32>)>> {
+    let mut result = Vec::new();
+    let mut current = Vec::new();
+    let mut is_deletion = false;
+    for (i, line) in input.lines().enumerate() {
+        let line = line.map_err(|e| match e.kind() {
+            io::ErrorKind::InvalidData => DratError::BinaryProof,
+            _ => DratError::Io(e),
+        })?;
+        let line_number = i + 1;
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('c') {
+            continue;
+        }
+        if trimmed.starts_with('p') {
+            on_header(trimmed)?;
+            continue;
+        }
+        for token in trimmed.split_whitespace() {
+            if token == "d" && allow_deletions && current.is_empty() && !is_deletion {
+                is_deletion = true;
+                continue;
+            }
+            let literal: i32 = token
+                .parse()
+                .ok()
+                .filter(|l: &i32| *l != i32::MIN)
+                .ok_or_else(|| DratError::InvalidLiteral(line_number, token.to_owned()))?;
+            if literal == 0 {
+                result.push((is_deletion, std::mem::take(&mut current)));
+                is_deletion = false;
+            } else {
+                current.push(literal);
+            }
+        }
+    }
+    if !current.is_empty() || is_deletion {
+        return Err(DratError::UnterminatedClause);
+    }
+    Ok(result)
+}
+
+/// Reads a DIMACS CNF formula, returning its clauses.
+fn read_dimacs<T: io::BufRead>(input: T) -> DratResult<Vec<Vec<i32>>> {
+    let mut found_header = false;
+    let clauses = read_clauses(input, false, |header| {
+        let parts: Vec<_> = header.split_whitespace().collect();
+        let is_valid = matches!(parts.as_slice(), ["p", "cnf", vars, clauses]
+            if vars.parse::<u32>().is_ok() && clauses.parse::<usize>().is_ok());
+        if !is_valid || found_header {
+            return Err(DratError::InvalidHeader);
+        }
+        found_header = true;
+        Ok(())
+    })?;
+    if !found_header {
+        return Err(DratError::InvalidHeader);
+    }
+    Ok(clauses.into_iter().map(|(_, c)| c).collect())
+}
+
+/// Reads a DRAT refutation in the textual format.
+fn read_drat<T: io::BufRead>(mut input: T) -> DratResult<Vec<DratLine>> {
+    // Binary DRAT proofs start with an `a` or `d` byte followed by a non-printable byte
+    let prefix = input.fill_buf()?;
+    if prefix
+        .iter()
+        .take(2)
+        .any(|&b| !(b.is_ascii_graphic() || b.is_ascii_whitespace()))
+    {
+        return Err(DratError::BinaryProof);
+    }
+    let clauses = read_clauses(input, true, |_| Ok(()))?;
+    Ok(clauses
+        .into_iter()
+        .map(|(is_deletion, c)| {
+            if is_deletion {
+                DratLine::Deletion(c)
+            } else {
+                DratLine::Lemma(c)
+            }
+        })
+        .collect())
+}
+
+/// The clause database used while translating the refutation, with the information needed to
+/// perform unit propagation.
+///
+/// Unit propagation uses two watched literals: each clause with more than one literal watches its
+/// first two literals, and is only visited when one of them becomes false. Since the assignment is
+/// discarded after each RUP check, the watches never need to be restored.
+#[derive(Default)]
+struct ClauseDatabase {
+    /// The clauses, and the index of the command in the proof that derives each of them. Deleted
+    /// clauses are replaced by `None`. The literals of a clause may be reordered when its watches
+    /// change.
+    clauses: Vec<Option<(Vec<i32>, usize)>>,
+
+    /// For each literal, the indices of the clauses that watch it. This may include deleted
+    /// clauses.
+    watches: HashMap<i32, Vec<usize>>,
+
+    /// The indices of the clauses that are not deleted, indexed by their sorted literals.
+    by_literals: HashMap<Vec<i32>, Vec<usize>>,
+
+    /// The indices of the unit clauses. This may include deleted clauses.
+    units: Vec<usize>,
+}
+
+impl ClauseDatabase {
+    fn add(&mut self, clause: Vec<i32>, command_index: usize) {
+        let index = self.clauses.len();
+        match clause.as_slice() {
+            [] => (),
+            [_] => self.units.push(index),
+            [first, second, ..] => {
+                self.watches.entry(*first).or_default().push(index);
+                self.watches.entry(*second).or_default().push(index);
+            }
+        }
+        let mut key = clause.clone();
+        key.sort_unstable();
+        self.by_literals.entry(key).or_default().push(index);
+        self.clauses.push(Some((clause, command_index)));
+    }
+
+    fn delete(&mut self, clause: &[i32]) {
+        if clause.len() == 1 {
+            return;
+        }
+        let mut key = clause.to_vec();
+        key.sort_unstable();
+        if let Some(index) = self.by_literals.get_mut(&key).and_then(Vec::pop) {
+            self.clauses[index] = None;
+        }
+    }
+
+    /// Checks if a lemma is a RUP consequence of the clauses in the database. If it is, returns
+    /// the indices of the commands that derive the clauses used by unit propagation, in an order
+    /// in which they can be resolved to derive the lemma.
+    fn rup_premises(&mut self, lemma: &[i32]) -> Option<Vec<usize>> {
+        let mut assignment: HashMap<u32, bool> = HashMap::new();
+        let mut reasons: HashMap<u32, usize> = HashMap::new();
+        let mut trail: Vec<i32> = Vec::new();
+        let value = |assignment: &HashMap<u32, bool>, l: i32| {
+            assignment.get(&l.unsigned_abs()).map(|&v| v == (l > 0))
+        };
+
+        for &l in lemma {
+            match value(&assignment, l) {
+                // If the lemma is a tautology, it is not derived by unit propagation
+                Some(true) => return None,
+                Some(false) => (),
+                None => {
+                    assignment.insert(l.unsigned_abs(), l < 0);
+                    trail.push(-l);
+                }
+            }
+        }
+
+        // Unit clauses are not watched, so their literals are assigned before propagating
+        let mut conflict = None;
+        for &index in &self.units {
+            let Some((clause, _)) = &self.clauses[index] else {
+                continue;
+            };
+            let l = clause[0];
+            match value(&assignment, l) {
+                Some(true) => (),
+                Some(false) => {
+                    conflict = Some(index);
+                    break;
+                }
+                None => {
+                    assignment.insert(l.unsigned_abs(), l > 0);
+                    reasons.insert(l.unsigned_abs(), index);
+                    trail.push(l);
+                }
+            }
+        }
+
+        let mut queue_start = 0;
+        while conflict.is_none() && queue_start < trail.len() {
+            let falsified = -trail[queue_start];
+            queue_start += 1;
+
+            let mut watching = self.watches.remove(&falsified).unwrap_or_default();
+            let mut i = 0;
+            while i < watching.len() {
+                let index = watching[i];
+                let Some((clause, _)) = &mut self.clauses[index] else {
+                    watching.swap_remove(i);
+                    continue;
+                };
+
+                // We keep the falsified literal as the second watch. If the first watch is true,
+                // the clause is satisfied and we don't need to do anything
+                if clause[0] == falsified {
+                    clause.swap(0, 1);
+                }
+                let other = clause[0];
+                if value(&assignment, other) == Some(true) {
+                    i += 1;
+                    continue;
+                }
+
+                // Otherwise, we look for a literal that is not false to replace the falsified
+                // watch. If there is none, the clause is either unit or conflicting
+                let replacement =
+                    (2..clause.len()).find(|&k| value(&assignment, clause[k]) != Some(false));
+                if let Some(k) = replacement {
+                    clause.swap(1, k);
+                    self.watches.entry(clause[1]).or_default().push(index);
+                    watching.swap_remove(i);
+                    continue;
+                }
+                if value(&assignment, other).is_some() {
+                    conflict = Some(index);
+                    break;
+                }
+                assignment.insert(other.unsigned_abs(), other > 0);
+                reasons.insert(other.unsigned_abs(), index);
+                trail.push(other);
+                i += 1;
+            }
+            self.watches.insert(falsified, watching);
+        }
+
+        // Collect the clauses that participate in the conflict, walking back through the trail
+        let conflict = conflict?;
+        let (conflict_clause, conflict_command) = self.clauses[conflict].as_ref().unwrap();
+        let mut premises = vec![*conflict_command];
+        let mut marked: IndexSet<u32> = conflict_clause.iter().map(|l| l.unsigned_abs()).collect();
+        for l in trail.iter().rev() {
+            let var = l.unsigned_abs();
+            if !marked.contains(&var) {
+                continue;
+            }
+            if let Some(&reason) = reasons.get(&var) {
+                let (clause, command) = self.clauses[reason].as_ref().unwrap();
+                premises.push(*command);
+                marked.extend(clause.iter().map(|l| l.unsigned_abs()));
+            }
+        }
+        Some(premises)
+    }
+}
+
+/// Builds the terms of the literals in a clause, declaring the variables they use.
+fn clause_terms(
+    pool: &mut PrimitivePool,
+    variables: &mut IndexMap<u32, Rc<Term>>,
+    clause: &[i32],
+) -> Vec<Rc<Term>> {
+    let bool_sort = pool.add(Term::Sort(Sort::Bool));
+    clause
+        .iter()
+        .map(|&l| {
+            let var = variables
+                .entry(l.unsigned_abs())
+                .or_insert_with(|| {
+                    let name = format!("x{}", l.unsigned_abs());
+                    pool.add(Term::new_var(name, bool_sort.clone()))
+                })
+                .clone();
+            if l > 0 {
+                var
+            } else {
+                pool.add(Term::Op(Operator::Not, vec![var]))
+            }
+        })
+        .collect()
+}
+
+fn new_step(id: String, clause: Vec<Rc<Term>>, rule: &str, premises: Vec<usize>) -> ProofCommand {
+    ProofCommand::Step(ProofStep {
+        id: id.into(),
+        clause,
+        rule: rule.into(),
+        premises: premises.into_iter().map(|i| (0, i)).collect(),
+        args: Vec::new(),
+        discharge: Vec::new(),
+    })
+}
+
+/// Translates a DIMACS CNF formula and a DRAT refutation of it into an Alethe problem and proof.
+/// Only the textual DRAT format is supported.
+pub fn translate<T: io::BufRead>(
+    cnf: T,
+    drat: T,
+) -> DratResult<(ProblemPrelude, Proof, PrimitivePool)> {
+    let formula = read_dimacs(cnf)?;
+    let refutation = read_drat(drat)?;
+
+    let mut pool = PrimitivePool::new();
+    let mut variables = IndexMap::new();
+    let mut premises = IndexSet::new();
+    let mut commands = Vec::new();
+    let mut database = ClauseDatabase::default();
+    let mut reached_empty_clause = false;
+
+    for (i, clause) in formula.into_iter().enumerate() {
+        let terms = clause_terms(&mut pool, &mut variables, &clause);
+        let assume_id = format!("a{}", i + 1);
+        let term = match terms.as_slice() {
+            [] => pool.bool_false(),
+            [t] => t.clone(),
+            _ => pool.add(Term::Op(Operator::Or, terms.clone())),
+        };
+        premises.insert(term.clone());
+        commands.push(ProofCommand::Assume {
+            id: assume_id.into(),
+            term: term.clone(),
+        });
+        let assume_index = commands.len() - 1;
+
+        match terms.len() {
+            0 => {
+                // An empty clause in the formula is derived from `false` directly
+                let not_false = pool.add(Term::Op(Operator::Not, vec![term]));
+                commands.push(new_step(
+                    format!("f{}", i + 1),
+                    vec![not_false],
+                    "false",
+                    vec![],
+                ));
+                let false_index = commands.len() - 1;
+                let step = new_step(
+                    format!("c{}", i + 1),
+                    Vec::new(),
+                    "resolution",
+                    vec![assume_index, false_index],
+                );
+                commands.push(step);
+                reached_empty_clause = true;
+                break;
+            }
+            1 => database.add(clause, assume_index),
+            _ => {
+                let step = new_step(format!("c{}", i + 1), terms, "or", vec![assume_index]);
+                commands.push(step);
+                database.add(clause, commands.len() - 1);
+            }
+        }
+    }
+
+    let mut lemma_count = 0;
+    for line in refutation {
+        if reached_empty_clause {
+            break;
+        }
+        match line {
+            DratLine::Deletion(clause) => database.delete(&clause),
+            DratLine::Lemma(lemma) => {
+                lemma_count += 1;
+                let terms = clause_terms(&mut pool, &mut variables, &lemma);
+                let step = match database.rup_premises(&lemma) {
+                    // Resolution steps must have at least two premises
+                    Some(premises) if premises.len() >= 2 => {
+                        new_step(format!("t{}", lemma_count), terms, "resolution", premises)
+                    }
+                    _ => new_step(format!("t{}", lemma_count), terms, "hole", Vec::new()),
+                };
+                commands.push(step);
+                reached_empty_clause = lemma.is_empty();
+                database.add(lemma, commands.len() - 1);
+            }
+        }
+    }
+
+    variables.sort_keys();
+    let prelude = ProblemPrelude {
+        function_declarations: variables
+            .values()
+            .map(|v| match v.as_ref() {
+                Term::Var(name, sort) => (name.to_string(), sort.clone()),
+                _ => unreachable!(),
+            })
+            .collect(),
+        ..Default::default()
+    };
+    let proof = Proof {
+        premises,
+        commands,
+        attributes: IndexMap::new(),
+        term_attributes: IndexMap::new(),
+        spans: IndexMap::new(),
+        flattened: IndexMap::new(),
+    };
+    Ok((prelude, proof, pool))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(cnf: &str, drat: &str) -> (ProblemPrelude, Proof) {
+        let (prelude, proof, _) = translate(cnf.as_bytes(), drat.as_bytes()).unwrap();
+        (prelude, proof)
+    }
+
+    fn rules(proof: &Proof) -> Vec<&str> {
+        proof
+            .commands
+            .iter()
+            .map(|c| match c {
+                ProofCommand::Assume { .. } => "assume",
+                ProofCommand::Step(s) => s.rule.as_str(),
+                ProofCommand::Subproof(_) => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_translate() {
+        let cnf = "c example\np cnf 2 4\n1 2 0\n-1 2 0\n1 -2 0\n-1 -2 0\n";
+        let (prelude, proof) = run(cnf, "1 0\nd 1 2 0\n0\n");
+        assert_eq!(prelude.function_declarations.len(), 2);
+        assert_eq!(proof.premises.len(), 4);
+        assert_eq!(
+            rules(&proof),
+            [
+                "assume",
+                "or",
+                "assume",
+                "or",
+                "assume",
+                "or",
+                "assume",
+                "or",
+                "resolution",
+                "resolution"
+            ]
+        );
+        let ProofCommand::Step(last) = proof.commands.last().unwrap() else {
+            unreachable!()
+        };
+        assert!(last.clause.is_empty());
+        assert_eq!(last.premises.len(), 3);
+
+        // Unit clauses are used directly, and lemmas that are not RUP consequences become holes
+        let (_, proof) = run("p cnf 2 1\n1 0\n", "2 0\n-1 0\n");
+        assert_eq!(rules(&proof), ["assume", "hole", "hole"]);
+
+        // Empty clauses in the formula end the proof
+        let (_, proof) = run("p cnf 1 2\n1 0\n0\n", "");
+        assert_eq!(rules(&proof), ["assume", "assume", "false", "resolution"]);
+    }
+
+    #[test]
+    fn test_unit_propagation() {
+        // Deriving `3` needs the first clause to move its watches from `1` and `2`, and deriving
+        // the empty clause needs the clauses with `-3`, which are only visited once `3` is added
+        let cnf = "p cnf 4 5\n1 2 3 0\n3 -1 0\n3 -2 0\n-3 4 0\n-3 -4 0\n";
+        let drat = "3 0\n0\n";
+        let (_, proof) = run(cnf, drat);
+        let premises: Vec<_> = proof
+            .commands
+            .iter()
+            .filter_map(|c| match c {
+                ProofCommand::Step(s) if s.rule == "resolution" => Some(s.premises.len()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(premises, [3, 3]);
+        let options = crate::CarcaraOptions::new();
+        assert!(matches!(
+            crate::check_drat(cnf.as_bytes(), drat.as_bytes(), options),
+            Ok(false)
+        ));
+    }
+
+    #[test]
+    fn test_translate_errors() {
+        let err = |cnf: &str, drat: &[u8]| translate(cnf.as_bytes(), drat).map(|_| ()).unwrap_err();
+        assert!(matches!(err("1 2 0\n", b""), DratError::InvalidHeader));
+        assert!(matches!(
+            err("p cnf 2 1\n1 2\n", b""),
+            DratError::UnterminatedClause
+        ));
+        assert!(matches!(
+            err("p cnf 2 1\n1 x 0\n", b""),
+            DratError::InvalidLiteral(2, _)
+        ));
+        assert!(matches!(
+            err("p cnf 2 1\n1 2 0\n", &[b'a', 0x02, 0x00]),
+            DratError::BinaryProof
+        ));
+    }
+}
@@ -32,12 +32,12 @@ impl Accumulator {
         self.top_frame().commands.len()
     }
 
-    pub fn next_id(&self, root_id: &str) -> String {
+    pub fn next_id(&self, root_id: &str) -> StepId {
         let mut current = root_id.to_owned();
         for f in &self.stack {
             write!(&mut current, ".t{}", f.commands.len() + 1).unwrap();
         }
-        current
+        current.into()
     }
 
     pub fn push_command(&mut self, command: ProofCommand) {
@@ -50,7 +50,7 @@ impl Accumulator {
 
     pub fn close_subproof(
         &mut self,
-        assignment_args: Vec<(String, Rc<Term>)>,
+        assignment_args: Vec<(Symbol, Rc<Term>)>,
         variable_args: Vec<SortedVar>,
         root_id: &str,
     ) -> ProofCommand {
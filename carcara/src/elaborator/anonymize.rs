@@ -0,0 +1,304 @@
+use crate::ast::*;
+use indexmap::IndexMap;
+
+/// Renames all user symbols in a problem and its proof, so that the proof can be shared without
+/// revealing the names used in the original problem.
+///
+/// Declared sorts are renamed to `S0`, `S1`, etc., declared functions are renamed to `f0`, `f1`,
+/// etc., and any other variables, such as bound variables and anchor arguments, are renamed to
+/// `v0`, `v1`, etc. The renaming is consistent across the problem prelude, the problem assertions
+/// and the proof, and is injective, so a valid proof remains valid after renaming. Function
+/// definitions are not part of the prelude, so they should be applied when parsing the problem.
+///
+/// The attributes of the proof steps are kept as raw text, and may mention user symbols, so they
+/// are discarded. Constants and step ids are not changed.
+pub fn anonymize(
+    pool: &mut PrimitivePool,
+    prelude: &ProblemPrelude,
+    proof: &Proof,
+) -> (ProblemPrelude, Proof) {
+    let mut anonymizer = Anonymizer::new(pool, prelude);
+
+    let new_prelude = ProblemPrelude {
+        sort_declarations: prelude
+            .sort_declarations
+            .iter()
+            .map(|(name, arity)| (anonymizer.sort_name(name), *arity))
+            .collect(),
+        sort_definitions: prelude
+            .sort_definitions
+            .iter()
+            .map(|d| SortDefinition {
+                name: anonymizer.sort_name(&d.name),
+                params: d.params.iter().map(|p| anonymizer.sort_name(p)).collect(),
+                body: anonymizer.term(&d.body),
+            })
+            .collect(),
+        function_declarations: prelude
+            .function_declarations
+            .iter()
+            .map(|(name, sort)| (anonymizer.var_name(name), anonymizer.term(sort)))
+            .collect(),
+        datatype_declarations: prelude
+            .datatype_declarations
+            .iter()
+            .map(|group| group.iter().map(|d| anonymizer.datatype(d)).collect())
+            .collect(),
+        logic: prelude.logic.clone(),
+        named_terms: prelude
+            .named_terms
+            .iter()
+            .map(|(name, term)| (anonymizer.var_name(name), anonymizer.term(term)))
+            .collect(),
+    };
+    let new_proof = Proof {
+        premises: proof.premises.iter().map(|p| anonymizer.term(p)).collect(),
+        commands: anonymizer.commands(&proof.commands),
+        attributes: IndexMap::new(),
+        term_attributes: IndexMap::new(),
+        spans: IndexMap::new(),
+        flattened: IndexMap::new(),
+    };
+    (new_prelude, new_proof)
+}
+
+struct Anonymizer<'p> {
+    pool: &'p mut PrimitivePool,
+    sort_names: IndexMap<String, String>,
+    var_names: IndexMap<String, String>,
+    cache: IndexMap<Rc<Term>, Rc<Term>>,
+}
+
+impl<'p> Anonymizer<'p> {
+    fn new(pool: &'p mut PrimitivePool, prelude: &ProblemPrelude) -> Self {
+        let datatypes = prelude.datatype_declarations.iter().flatten();
+        let sort_names = prelude
+            .sort_declarations
+            .iter()
+            .map(|(name, _)| name)
+            .chain(datatypes.clone().map(|d| &d.name))
+            .enumerate()
+            .map(|(i, name)| (name.clone(), format!("S{}", i)))
+            .collect();
+        let mut var_names: IndexMap<_, _> = prelude
+            .function_declarations
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _))| (name.clone(), format!("f{}", i)))
+            .collect();
+
+        // Constructors are renamed to `c0`, `c1`, etc., and their testers must be renamed
+        // accordingly, so the tester of the renamed constructor is still called `is-c0`
+        for (i, constructor) in datatypes.flat_map(|d| &d.constructors).enumerate() {
+            let new_name = format!("c{}", i);
+            var_names.insert(constructor.tester_name(), format!("is-{}", new_name));
+            var_names.insert(constructor.name.clone(), new_name);
+        }
+        Self {
+            pool,
+            sort_names,
+            var_names,
+            cache: IndexMap::new(),
+        }
+    }
+
+    fn datatype(&mut self, datatype: &DatatypeDef) -> DatatypeDef {
+        DatatypeDef {
+            name: self.sort_name(&datatype.name),
+            sort_params: datatype
+                .sort_params
+                .iter()
+                .map(|p| self.sort_name(p))
+                .collect(),
+            constructors: datatype
+                .constructors
+                .iter()
+                .map(|c| DatatypeConstructor {
+                    name: self.var_name(&c.name),
+                    selectors: c.selectors.iter().map(|s| self.sorted_var(s)).collect(),
+                })
+                .collect(),
+        }
+    }
+
+    fn sort_name(&mut self, name: &str) -> String {
+        let next = format!("S{}", self.sort_names.len());
+        self.sort_names
+            .entry(name.to_owned())
+            .or_insert(next)
+            .clone()
+    }
+
+    fn var_name(&mut self, name: &str) -> String {
+        let next = format!("v{}", self.var_names.len());
+        self.var_names
+            .entry(name.to_owned())
+            .or_insert(next)
+            .clone()
+    }
+
+    fn sorted_var(&mut self, (name, value): &SortedVar) -> SortedVar {
+        (self.var_name(name).into(), self.term(value))
+    }
+
+    fn binding_list(&mut self, bindings: &BindingList) -> BindingList {
+        BindingList(bindings.iter().map(|b| self.sorted_var(b)).collect())
+    }
+
+    fn terms(&mut self, terms: &[Rc<Term>]) -> Vec<Rc<Term>> {
+        terms.iter().map(|t| self.term(t)).collect()
+    }
+
+    fn term(&mut self, term: &Rc<Term>) -> Rc<Term> {
+        if let Some(t) = self.cache.get(term) {
+            return t.clone();
+        }
+        let result = match term.as_ref() {
+            Term::Const(_) => term.clone(),
+            Term::Var(name, sort) => {
+                let var = self.sorted_var(&(*name, sort.clone()));
+                self.pool.add(var.into())
+            }
+            Term::App(func, args) => {
+                let new = Term::App(self.term(func), self.terms(args));
+                self.pool.add(new)
+            }
+            Term::Op(op, args) => {
+                let new = Term::Op(*op, self.terms(args));
+                self.pool.add(new)
+            }
+            Term::Sort(sort) => {
+                let new = match sort {
+                    Sort::Function(args) => Sort::Function(self.terms(args)),
+                    Sort::Atom(name, args) => Sort::Atom(self.sort_name(name), self.terms(args)),
+                    Sort::Array(x, y) => Sort::Array(self.term(x), self.term(y)),
+                    Sort::Datatype(name, args) => {
+                        Sort::Datatype(self.sort_name(name), self.terms(args))
+                    }
+                    other => other.clone(),
+                };
+                self.pool.add(Term::Sort(new))
+            }
+            Term::Quant(quantifier, bindings, inner) => {
+                let new = Term::Quant(*quantifier, self.binding_list(bindings), self.term(inner));
+                self.pool.add(new)
+            }
+            Term::Choice(var, inner) => {
+                let new = Term::Choice(self.sorted_var(var), self.term(inner));
+                self.pool.add(new)
+            }
+            Term::Let(bindings, inner) => {
+                let new = Term::Let(self.binding_list(bindings), self.term(inner));
+                self.pool.add(new)
+            }
+            Term::Lambda(bindings, inner) => {
+                let new = Term::Lambda(self.binding_list(bindings), self.term(inner));
+                self.pool.add(new)
+            }
+            Term::IndexedOp { op, op_args, args } => {
+                let new = Term::IndexedOp {
+                    op: *op,
+                    op_args: op_args.clone(),
+                    args: self.terms(args),
+                };
+                self.pool.add(new)
+            }
+            Term::ParamOp { op, op_args, args } => {
+                let new = Term::ParamOp {
+                    op: *op,
+                    op_args: self.terms(op_args),
+                    args: self.terms(args),
+                };
+                self.pool.add(new)
+            }
+        };
+        self.cache.insert(term.clone(), result.clone());
+        result
+    }
+
+    fn commands(&mut self, commands: &[ProofCommand]) -> Vec<ProofCommand> {
+        commands
+            .iter()
+            .map(|command| match command {
+                ProofCommand::Assume { id, term } => ProofCommand::Assume {
+                    id: id.clone(),
+                    term: self.term(term),
+                },
+                ProofCommand::Step(step) => ProofCommand::Step(ProofStep {
+                    clause: self.terms(&step.clause),
+                    args: step
+                        .args
+                        .iter()
+                        .map(|arg| match arg {
+                            ProofArg::Term(t) => ProofArg::Term(self.term(t)),
+                            ProofArg::Assign(name, value) => {
+                                ProofArg::Assign(self.var_name(name).into(), self.term(value))
+                            }
+                            ProofArg::List(terms) => ProofArg::List(self.terms(terms)),
+                        })
+                        .collect(),
+                    ..step.clone()
+                }),
+                ProofCommand::Subproof(subproof) => ProofCommand::Subproof(Subproof {
+                    commands: self.commands(&subproof.commands),
+                    assignment_args: subproof
+                        .assignment_args
+                        .iter()
+                        .map(|a| self.sorted_var(a))
+                        .collect(),
+                    variable_args: subproof
+                        .variable_args
+                        .iter()
+                        .map(|v| self.sorted_var(v))
+                        .collect(),
+                    context_id: subproof.context_id,
+                }),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{checker, parser};
+
+    #[test]
+    fn test_anonymize() {
+        let problem = "
+            (declare-sort Secret 0)
+            (declare-fun password () Secret)
+            (declare-fun is_valid (Secret) Bool)
+            (assert (is_valid password))
+            (assert (not (is_valid password)))
+            (assert (forall ((key Secret)) (is_valid key)))
+        ";
+        let proof = "
+            (assume h1 (is_valid password))
+            (assume h2 (not (is_valid password)))
+            (anchor :step t3 :args ((guess Secret) (:= key guess)))
+            (step t3.t1 (cl (= (is_valid key) (is_valid guess))) :rule refl)
+            (step t3 (cl (= (forall ((key Secret)) (is_valid key))
+                (forall ((guess Secret)) (is_valid guess)))) :rule bind)
+            (step t4 (cl) :rule resolution :premises (h1 h2))
+        ";
+        let (prelude, proof, mut pool) =
+            parser::parse_instance(problem.as_bytes(), proof.as_bytes(), parser::Config::new())
+                .unwrap();
+        let (new_prelude, new_proof) = anonymize(&mut pool, &prelude, &proof);
+
+        let printed = format!("{}{:?}", new_prelude, new_proof);
+        for name in ["Secret", "password", "is_valid", "key", "guess"] {
+            assert!(!printed.contains(name), "'{}' was not renamed", name);
+        }
+        assert_eq!(
+            new_prelude.to_string(),
+            "(set-logic ALL)\n(declare-sort S0 0)\n(declare-fun f0 () S0)\n\
+            (declare-fun f1 (S0) Bool)\n"
+        );
+
+        let mut checker =
+            checker::ProofChecker::new(&mut pool, checker::Config::new(), &new_prelude);
+        assert!(!checker.check(&new_proof).unwrap());
+    }
+}
@@ -1,5 +1,5 @@
 use crate::ast::*;
-use std::{iter, vec};
+use std::{convert::Infallible, iter, vec};
 
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct ProofDiff {
@@ -15,6 +15,23 @@ pub enum CommandDiff {
 }
 
 pub fn apply_diff(root: ProofDiff, proof: Vec<ProofCommand>) -> Vec<ProofCommand> {
+    let mut result = Vec::new();
+    apply_diff_with(root, proof, |command| {
+        result.push(command);
+        Ok::<_, Infallible>(())
+    })
+    .unwrap_or_else(|e| match e {});
+    result
+}
+
+/// Applies the diff to the proof, passing each resulting command of the root proof to `emit` as
+/// soon as it is complete, instead of collecting them. This stops at the first error returned by
+/// `emit`.
+pub fn apply_diff_with<E>(
+    root: ProofDiff,
+    proof: Vec<ProofCommand>,
+    mut emit: impl FnMut(ProofCommand) -> Result<(), E>,
+) -> Result<(), E> {
     struct Frame {
         result: Subproof,
         commands: iter::Enumerate<vec::IntoIter<ProofCommand>>,
@@ -29,18 +46,19 @@ pub fn apply_diff(root: ProofDiff, proof: Vec<ProofCommand>) -> Vec<ProofCommand
     }];
 
     loop {
+        let is_root = stack.len() == 1;
         let f = stack.last_mut().unwrap();
         let Some((i, mut command)) = f.commands.next() else {
             let result = stack.pop().unwrap().result;
-            if let Some(outer_frame) = stack.last_mut() {
-                outer_frame
+            match stack.as_mut_slice() {
+                [] => return Ok(()),
+                [_] => emit(ProofCommand::Subproof(result))?,
+                [.., outer_frame] => outer_frame
                     .result
                     .commands
-                    .push(ProofCommand::Subproof(result));
-                continue;
-            } else {
-                return result.commands;
+                    .push(ProofCommand::Subproof(result)),
             }
+            continue;
         };
 
         match f.diff_iter.as_slice().first() {
@@ -58,7 +76,11 @@ pub fn apply_diff(root: ProofDiff, proof: Vec<ProofCommand>) -> Vec<ProofCommand
                         stack.push(new_frame);
                     }
                     (_, CommandDiff::Step(mut elaboration)) => {
-                        f.result.commands.append(&mut elaboration);
+                        if is_root {
+                            elaboration.into_iter().try_for_each(&mut emit)?;
+                        } else {
+                            f.result.commands.append(&mut elaboration);
+                        }
                     }
                     (_, CommandDiff::Delete) => (),
                     _ => panic!("invalid diff!"),
@@ -66,12 +88,16 @@ pub fn apply_diff(root: ProofDiff, proof: Vec<ProofCommand>) -> Vec<ProofCommand
             }
             _ => {
                 if let ProofCommand::Step(s) = &mut command {
-                    for p in &mut s.premises {
+                    for p in s.premises.iter_mut().chain(&mut s.discharge) {
                         let (depth, i) = *p;
                         *p = stack[depth].new_indices[i];
                     }
                 }
-                stack.last_mut().unwrap().result.commands.push(command);
+                if is_root {
+                    emit(command)?;
+                } else {
+                    stack.last_mut().unwrap().result.commands.push(command);
+                }
             }
         }
     }
@@ -0,0 +1,123 @@
+use super::{CommandDiff, ProofDiff};
+use crate::ast::*;
+use std::fmt;
+
+/// The reason why the elaborator changed a command of the proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElaborationReason {
+    /// The step was replaced by a more detailed derivation of the same conclusion.
+    Elaborated,
+
+    /// The command was removed because its conclusion was already derived by an earlier command.
+    Duplicate,
+
+    /// The command was removed because it is not needed to derive the empty clause.
+    Pruned,
+}
+
+impl fmt::Display for ElaborationReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ElaborationReason::Elaborated => write!(f, "elaborated"),
+            ElaborationReason::Duplicate => write!(f, "conclusion already derived"),
+            ElaborationReason::Pruned => write!(f, "not needed to derive the empty clause"),
+        }
+    }
+}
+
+/// A record of how the elaborator changed a command of the proof. See
+/// [`ProofChecker::check_and_elaborate_explained`](crate::checker::ProofChecker::check_and_elaborate_explained).
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepExplanation {
+    /// The command as it was before the change. If the command is a subproof, this contains the
+    /// whole subproof.
+    pub original: ProofCommand,
+
+    /// The commands that replace the original command, in order. The last of them concludes the
+    /// same clause as the original command. This is empty if the command was removed.
+    ///
+    /// The premises of these commands are indices into the elaborated proof, not the original one.
+    pub replacement: Vec<ProofCommand>,
+
+    /// Why the command was changed.
+    pub reason: ElaborationReason,
+}
+
+/// Returns a record of each command of `proof` that is changed by applying `diff` to it. Commands
+/// that are deleted by the diff are given the reason `deleted`.
+pub fn explain_diff(
+    diff: &ProofDiff,
+    proof: &[ProofCommand],
+    deleted: ElaborationReason,
+) -> Vec<StepExplanation> {
+    let mut result = Vec::new();
+    explain_diff_rec(diff, proof, deleted, &mut result);
+    result
+}
+
+fn explain_diff_rec(
+    diff: &ProofDiff,
+    proof: &[ProofCommand],
+    deleted: ElaborationReason,
+    result: &mut Vec<StepExplanation>,
+) {
+    for (i, command_diff) in &diff.commands {
+        let original = &proof[*i];
+        let (replacement, reason) = match command_diff {
+            CommandDiff::Subproof(inner) => {
+                let ProofCommand::Subproof(s) = original else {
+                    panic!("invalid diff!")
+                };
+                explain_diff_rec(inner, &s.commands, deleted, result);
+                continue;
+            }
+            CommandDiff::Step(commands) => (commands.clone(), ElaborationReason::Elaborated),
+            CommandDiff::Delete => (Vec::new(), deleted),
+        };
+        result.push(StepExplanation {
+            original: original.clone(),
+            replacement,
+            reason,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{checker, parser};
+
+    #[test]
+    fn test_explain_elaboration() {
+        let problem = "
+            (declare-fun p () Bool)
+            (declare-fun q () Bool)
+            (assert p)
+            (assert (not p))
+            (assert q)
+        ";
+        let proof = "
+            (assume h1 p)
+            (assume h2 (not p))
+            (assume h3 q)
+            (step t4 (cl p) :rule reordering :premises (h1))
+            (step t5 (cl) :rule resolution :premises (t4 h2))
+        ";
+        let (prelude, proof, mut pool) =
+            parser::parse_instance(problem.as_bytes(), proof.as_bytes(), parser::Config::new())
+                .unwrap();
+        let mut checker = checker::ProofChecker::new(&mut pool, checker::Config::new(), &prelude);
+        let (_, elaborated, explanations) = checker.check_and_elaborate_explained(proof).unwrap();
+
+        let reason_of = |id: &str| {
+            explanations
+                .iter()
+                .find(|e| e.original.id() == id)
+                .map(|e| e.reason)
+        };
+        assert_eq!(reason_of("h1"), None);
+        assert_eq!(reason_of("t4"), Some(ElaborationReason::Duplicate));
+        assert_eq!(reason_of("h3"), Some(ElaborationReason::Pruned));
+        assert!(elaborated.commands.iter().all(|c| c.id() != "h3"));
+    }
+}
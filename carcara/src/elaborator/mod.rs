@@ -1,10 +1,14 @@
 mod accumulator;
+mod anonymize;
 mod diff;
+mod explain;
 mod polyeq;
 mod pruning;
 
-pub use diff::{apply_diff, CommandDiff, ProofDiff};
-pub use pruning::{prune_proof, slice_proof};
+pub use anonymize::anonymize;
+pub use diff::{apply_diff, apply_diff_with, CommandDiff, ProofDiff};
+pub use explain::{explain_diff, ElaborationReason, StepExplanation};
+pub use pruning::{prune_proof, slice_proof, slice_proof_at};
 
 use crate::{ast::*, utils::HashMapStack};
 use accumulator::Accumulator;
@@ -116,7 +120,7 @@ impl Elaborator {
         self.add_new_command(ProofCommand::Step(step), false)
     }
 
-    pub fn get_new_id(&mut self, root_id: &str) -> String {
+    pub fn get_new_id(&mut self, root_id: &str) -> StepId {
         self.accumulator.next_id(root_id)
     }
 
@@ -150,7 +154,7 @@ impl Elaborator {
     /// make sure it is the next `id` in the outer subproof.
     pub fn close_accumulator_subproof(
         &mut self,
-        assignment_args: Vec<(String, Rc<Term>)>,
+        assignment_args: Vec<(Symbol, Rc<Term>)>,
         variable_args: Vec<SortedVar>,
         end_step: ProofStep,
         root_id: &str,
@@ -222,7 +226,7 @@ impl Elaborator {
         pool: &mut dyn TermPool,
         original_premise: (usize, usize),
         original_equality: (Rc<Term>, Rc<Term>),
-        id: String,
+        id: StepId,
     ) -> (usize, usize) {
         let (a, b) = original_equality;
         let clause = vec![build_term!(pool, (= {b} {a}))];
@@ -243,7 +247,7 @@ impl Elaborator {
         pool: &mut dyn TermPool,
         a: Rc<Term>,
         b: Rc<Term>,
-        id: String,
+        id: StepId,
     ) -> (usize, usize) {
         let step = ProofStep {
             id,
@@ -276,7 +280,7 @@ impl Elaborator {
     ) -> (usize, usize) {
         let new_assume = self.add_new_command(
             ProofCommand::Assume {
-                id: id.to_owned(),
+                id: StepId::new(id),
                 term: premise.clone(),
             },
             false,
@@ -288,7 +292,7 @@ impl Elaborator {
             self.add_new_step(ProofStep {
                 id: new_id,
                 clause,
-                rule: "equiv1".to_owned(),
+                rule: "equiv1".into(),
                 premises: vec![equality_step],
                 args: Vec::new(),
                 discharge: Vec::new(),
@@ -299,7 +303,7 @@ impl Elaborator {
         self.push_elaborated_step(ProofStep {
             id: new_id,
             clause: vec![term],
-            rule: "resolution".to_owned(),
+            rule: "resolution".into(),
             premises: vec![new_assume, equiv1_step],
             args: vec![ProofArg::Term(premise), ProofArg::Term(pool.bool_true())],
             discharge: Vec::new(),
@@ -349,4 +353,46 @@ impl Elaborator {
         let elaborated = apply_diff(diff, original);
         apply_diff(prune_proof(&elaborated), elaborated)
     }
+
+    /// Similar to `Elaborator::end`, but also returns a record of each command that was changed by
+    /// the elaboration or removed by pruning, in that order.
+    pub fn end_explained(
+        &mut self,
+        original: Vec<ProofCommand>,
+    ) -> (Vec<ProofCommand>, Vec<StepExplanation>) {
+        assert!(
+            self.depth() == 0,
+            "trying to end proof building before closing subproof"
+        );
+        let Frame { diff, new_indices, .. } = self.stack.pop().unwrap();
+        let diff = ProofDiff { commands: diff, new_indices };
+        let mut explanations = explain_diff(&diff, &original, ElaborationReason::Duplicate);
+        let elaborated = apply_diff(diff, original);
+
+        let pruning = prune_proof(&elaborated);
+        explanations.extend(explain_diff(
+            &pruning,
+            &elaborated,
+            ElaborationReason::Pruned,
+        ));
+        (apply_diff(pruning, elaborated), explanations)
+    }
+
+    /// Similar to `Elaborator::end`, but passes each command of the elaborated root proof to `emit`
+    /// as soon as it is built, so the elaborated proof is never held in memory as a whole. Since
+    /// pruning requires the whole proof, steps that are not needed to reach the empty clause are
+    /// not removed.
+    pub fn end_streaming<E>(
+        &mut self,
+        original: Vec<ProofCommand>,
+        emit: impl FnMut(ProofCommand) -> Result<(), E>,
+    ) -> Result<(), E> {
+        assert!(
+            self.depth() == 0,
+            "trying to end proof building before closing subproof"
+        );
+        let Frame { diff, new_indices, .. } = self.stack.pop().unwrap();
+        let diff = ProofDiff { commands: diff, new_indices };
+        apply_diff_with(diff, original, emit)
+    }
 }
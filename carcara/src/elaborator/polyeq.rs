@@ -86,7 +86,7 @@ impl<'a> PolyeqElaborator<'a> {
                         let assignment_args: Vec<_> = a_bindings
                             .iter()
                             .map(|x| {
-                                let var = x.0.clone();
+                                let var = x.0;
                                 let term = pool.add(x.clone().into());
                                 (var, term)
                             })
@@ -109,7 +109,7 @@ impl<'a> PolyeqElaborator<'a> {
                         let assigment_args: Vec<_> = a_bindings
                             .iter()
                             .zip(b_bindings)
-                            .map(|((a_var, _), b)| (a_var.clone(), pool.add(b.clone().into())))
+                            .map(|((a_var, _), b)| (*a_var, pool.add(b.clone().into())))
                             .collect();
 
                         let new_context_id = c.force_new_context();
@@ -129,9 +129,9 @@ impl<'a> PolyeqElaborator<'a> {
                     assignment_args,
                     variable_args,
                     ProofStep {
-                        id: String::new(),
+                        id: StepId::default(),
                         clause: vec![build_term!(pool, (= {a.clone()} {b.clone()}))],
-                        rule: "bind".to_owned(),
+                        rule: "bind".into(),
                         premises: Vec::new(),
                         args: Vec::new(),
                         discharge: Vec::new(),
@@ -144,7 +144,7 @@ impl<'a> PolyeqElaborator<'a> {
 
                 let variable_args: Vec<_> = a_bindings
                     .iter()
-                    .map(|(name, value)| (name.clone(), pool.sort(value)))
+                    .map(|(name, value)| (*name, pool.sort(value)))
                     .collect();
 
                 self.open_subproof();
@@ -170,9 +170,9 @@ impl<'a> PolyeqElaborator<'a> {
                     Vec::new(),
                     variable_args,
                     ProofStep {
-                        id: String::new(),
+                        id: StepId::default(),
                         clause: vec![build_term!(pool, (= {a.clone()} {b.clone()}))],
-                        rule: "bind_let".to_owned(),
+                        rule: "bind_let".into(),
                         premises,
                         args: Vec::new(),
                         discharge: Vec::new(),
@@ -234,7 +234,7 @@ impl<'a> PolyeqElaborator<'a> {
         let step = ProofStep {
             id,
             clause,
-            rule: "cong".to_owned(),
+            rule: "cong".into(),
             premises,
             args: Vec::new(),
             discharge: Vec::new(),
@@ -287,7 +287,7 @@ impl<'a> PolyeqElaborator<'a> {
             let step = ProofStep {
                 id: self.inner.get_new_id(self.root_id),
                 clause: vec![build_term!(pool, (= {a} {b}))],
-                rule: "equiv_simplify".to_owned(),
+                rule: "equiv_simplify".into(),
                 premises: Vec::new(),
                 args: Vec::new(),
                 discharge: Vec::new(),
@@ -310,9 +310,9 @@ impl<'a> PolyeqElaborator<'a> {
         // according to the `equiv_simplify` specification, so we must change the rule to `refl` in
         // this case.
         let rule = if b == flipped_b {
-            "refl".to_owned()
+            "refl".into()
         } else {
-            "equiv_simplify".to_owned()
+            "equiv_simplify".into()
         };
         let id = self.inner.get_new_id(self.root_id);
         let equiv_step = self.inner.add_new_step(ProofStep {
@@ -328,7 +328,7 @@ impl<'a> PolyeqElaborator<'a> {
         self.inner.add_new_step(ProofStep {
             id,
             clause: vec![build_term!(pool, (= {a} {b}))],
-            rule: "trans".to_owned(),
+            rule: "trans".into(),
             premises: vec![cong_step, equiv_step],
             args: Vec::new(),
             discharge: Vec::new(),
@@ -342,7 +342,7 @@ impl<'a> PolyeqElaborator<'a> {
 
     fn close_subproof(
         &mut self,
-        assignment_args: Vec<(String, Rc<Term>)>,
+        assignment_args: Vec<(Symbol, Rc<Term>)>,
         variable_args: Vec<SortedVar>,
         end_step: ProofStep,
     ) -> (usize, usize) {
@@ -376,7 +376,7 @@ impl<'a> PolyeqElaborator<'a> {
                 ProofCommand::Step(ProofStep {
                     id,
                     clause,
-                    rule: "reordering".to_owned(),
+                    rule: "reordering".into(),
                     premises: vec![inner_eq],
                     args: Vec::new(),
                     discharge: Vec::new(),
@@ -17,6 +17,14 @@ struct Frame<'a> {
     /// The queue of commands to visit, represented as a tuple of (command index, distance to
     /// source)
     queue: VecDeque<(usize, usize)>,
+
+    /// If the source is inside of this frame, the index of the command that contains it, or of the
+    /// source itself.
+    path_index: Option<usize>,
+
+    /// If the command that contains the source is a subproof, the clause concluded by the step
+    /// that closes it in the slice.
+    path_clause: Option<Vec<Rc<Term>>>,
 }
 
 pub fn prune_proof(proof: &[ProofCommand]) -> ProofDiff {
@@ -33,14 +41,52 @@ pub fn slice_proof(
     source: usize,
     max_distance: Option<usize>,
 ) -> ProofDiff {
-    assert!(proof.len() > source, "invalid slice index");
+    slice_proof_at(proof, &[source], max_distance)
+}
+
+/// Computes the slice of a proof consisting of the command at the given path (as returned by
+/// [`ProofIndex::path`]) and all of its transitive premises. If `max_distance` is given, premises
+/// that are further away from the source are removed, and premises exactly one step beyond that
+/// distance are replaced by `hole` steps, except for `assume` commands, which are kept.
+///
+/// The source may be inside a subproof. In that case, the slice keeps the `anchor` of each
+/// subproof that contains it, as well as all of their `assume` commands, so the commands in the
+/// slice are checked in the same context as in the original proof. Since the original last step
+/// of these subproofs may depend on commands that are not in the slice, it is replaced by a step
+/// that closes the subproof right after the source. If the original step uses the `subproof` rule,
+/// the new step discharges the same assumptions, and concludes the clause of the source instead.
+/// Otherwise, it is replaced by a `hole` step with the same conclusion.
+pub fn slice_proof_at(
+    proof: &[ProofCommand],
+    path: &[usize],
+    max_distance: Option<usize>,
+) -> ProofDiff {
+    let (&last, init) = path.split_last().expect("empty slice path");
+    let mut commands = proof;
+    for &i in init {
+        match commands.get(i) {
+            Some(ProofCommand::Subproof(s)) => commands = &s.commands,
+            _ => panic!("invalid slice path"),
+        }
+    }
+    assert!(commands.len() > last, "invalid slice index");
+
+    // If the source is the last step of a subproof, slicing from it is the same as slicing from
+    // the subproof itself, in which case the subproof is kept as is
+    let path = if !init.is_empty() && last == commands.len() - 1 {
+        init
+    } else {
+        path
+    };
 
     let mut stack = vec![Frame {
         commands: proof,
         subproof_diffs: vec![None; proof.len()],
         distance_to_source: vec![usize::MAX; proof.len()],
         index_of_subproof: 0, // For the root proof, this value is irrelevant
-        queue: VecDeque::from([(source, 0usize)]),
+        queue: VecDeque::from([(path[0], 0usize)]),
+        path_index: Some(path[0]),
+        path_clause: None,
     }];
 
     loop {
@@ -68,8 +114,13 @@ pub fn slice_proof(
                 }
                 ProofCommand::Subproof(s) => {
                     let n = s.commands.len();
+                    let contains_source = frame.path_index == Some(current);
+                    let path_index = path.get(stack.len()).copied().filter(|_| contains_source);
+
+                    // If the source is inside this subproof, we start from it instead of from the
+                    // last step of the subproof
                     let mut new_queue = VecDeque::new();
-                    new_queue.push_back((n - 1, current_dist));
+                    new_queue.push_back((path_index.unwrap_or(n - 1), current_dist));
 
                     // Since `assume` commands in a subproof are implicitly referenced by the last
                     // step in the subproof, we must add them to the queue now
@@ -81,7 +132,7 @@ pub fn slice_proof(
 
                     // The second to last command in a subproof is also implicitly referenced by the
                     // last step, so we also add it to the queue
-                    if n >= 2 {
+                    if n >= 2 && path_index.is_none() {
                         new_queue.push_back((n - 2, current_dist + 1));
                     }
 
@@ -91,6 +142,8 @@ pub fn slice_proof(
                         distance_to_source: vec![usize::MAX; n],
                         index_of_subproof: current,
                         queue: new_queue,
+                        path_index,
+                        path_clause: None,
                     };
                     stack.push(frame);
                 }
@@ -102,17 +155,29 @@ pub fn slice_proof(
         let mut new_indices = Vec::new();
         let mut num_pruned = 0;
         let depth = stack.len();
-        for i in 0..frame.commands.len() {
+
+        // If the source is inside this subproof, its last step is replaced by a new step that
+        // closes it
+        let closed_at = frame.path_index.filter(|_| depth > 0);
+        let n = frame.commands.len();
+        for i in 0..n {
             new_indices.push((depth, i - num_pruned));
 
+            if closed_at.is_some() && i == n - 1 {
+                continue;
+            }
             if frame.distance_to_source[i] == usize::MAX {
                 result_diff.push((i, CommandDiff::Delete));
                 num_pruned += 1;
+            } else if frame.commands[i].is_assume() {
+                // `assume` commands are always kept, since they may be discharged by the last step
+                // of their subproof
+                continue;
             } else if max_distance.is_some_and(|max| frame.distance_to_source[i] == max + 1) {
                 let new_command = ProofCommand::Step(ProofStep {
-                    id: frame.commands[i].id().to_owned(),
+                    id: frame.commands[i].id().clone(),
                     clause: frame.commands[i].clause().to_vec(),
-                    rule: "hole".to_owned(),
+                    rule: "hole".into(),
                     premises: Vec::new(),
                     args: Vec::new(),
                     discharge: Vec::new(),
@@ -122,12 +187,127 @@ pub fn slice_proof(
                 result_diff.push((i, CommandDiff::Subproof(diff)));
             }
         }
+        let mut closing_clause = None;
+        if let Some(index) = closed_at {
+            let previous = match frame.path_clause.take() {
+                Some(clause) => clause,
+                None => frame.commands[index].clause().to_vec(),
+            };
+            let step = closing_step(&frame.commands[n - 1], &previous, &new_indices);
+            closing_clause = Some(step.clause.clone());
+            result_diff.push((n - 1, CommandDiff::Step(vec![ProofCommand::Step(step)])));
+        }
         let result_diff = ProofDiff { commands: result_diff, new_indices };
 
         if let Some(outer_frame) = stack.last_mut() {
             outer_frame.subproof_diffs[frame.index_of_subproof] = Some(result_diff);
+            if closing_clause.is_some() {
+                outer_frame.path_clause = closing_clause;
+            }
         } else {
             return result_diff;
         }
     }
 }
+
+/// Builds the step that replaces `end`, the last step of a subproof that contains the source of a
+/// slice. In the slice, this step comes right after the command that contains the source, whose
+/// conclusion is `previous`. The indices of the commands in the subproof are given by
+/// `new_indices`.
+fn closing_step(
+    end: &ProofCommand,
+    previous: &[Rc<Term>],
+    new_indices: &[(usize, usize)],
+) -> ProofStep {
+    let ProofCommand::Step(end) = end else {
+        unreachable!("the last command in a subproof is always a step")
+    };
+
+    // The conclusion of a `subproof` step is the negation of each discharged assumption, followed
+    // by the single literal concluded by the previous step
+    if let ("subproof", [phi], Some((_, assumptions))) =
+        (end.rule.as_str(), previous, end.clause.split_last())
+    {
+        let mut clause = assumptions.to_vec();
+        clause.push(phi.clone());
+        return ProofStep {
+            id: end.id.clone(),
+            clause,
+            rule: end.rule,
+            premises: Vec::new(),
+            args: Vec::new(),
+            discharge: end.discharge.iter().map(|&(_, i)| new_indices[i]).collect(),
+        };
+    }
+    ProofStep {
+        id: end.id.clone(),
+        clause: end.clause.clone(),
+        rule: "hole".into(),
+        premises: Vec::new(),
+        args: Vec::new(),
+        discharge: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{checker, elaborator::apply_diff, parser, Error};
+    use indexmap::IndexMap;
+
+    #[test]
+    fn test_slice_inside_subproof() {
+        let problem = "
+            (declare-fun p () Bool)
+            (declare-fun q () Bool)
+            (assert p)
+            (assert q)
+        ";
+        let proof = "
+            (assume h1 p)
+            (assume h2 q)
+            (anchor :step t3)
+            (assume t3.a0 q)
+            (step t3.t1 (cl p) :rule hole :premises (h1))
+            (step t3.t2 (cl (and p q)) :rule hole :premises (t3.t1 t3.a0))
+            (step t3 (cl (not q) (and p q)) :rule subproof :discharge (t3.a0))
+            (step t4 (cl) :rule hole :premises (t3 h2))
+        ";
+        let (prelude, proof, mut pool) =
+            parser::parse_instance(problem.as_bytes(), proof.as_bytes(), parser::Config::new())
+                .unwrap();
+        let path = proof.index().path("t3.t1").unwrap().to_vec();
+        let diff = slice_proof_at(&proof.commands, &path, None);
+        let commands = apply_diff(diff, proof.commands);
+
+        let ids: Vec<_> = commands.iter().map(ProofCommand::id).collect();
+        assert_eq!(ids, ["h1", "t3"]);
+        let ProofCommand::Subproof(subproof) = &commands[1] else {
+            panic!("expected subproof");
+        };
+        let ids: Vec<_> = subproof.commands.iter().map(ProofCommand::id).collect();
+        assert_eq!(ids, ["t3.a0", "t3.t1", "t3"]);
+
+        // The subproof is closed right after the source, discharging the same assumption
+        let ProofCommand::Step(end) = &subproof.commands[2] else {
+            panic!("expected step");
+        };
+        assert_eq!(end.rule, "subproof");
+        assert_eq!(end.clause[0].to_string(), "(not q)");
+        assert_eq!(end.clause[1].to_string(), "p");
+        assert_eq!(end.discharge, [(1, 0)]);
+
+        // All steps in the slice are valid, but it doesn't reach the empty clause
+        let sliced = Proof {
+            premises: proof.premises,
+            commands,
+            attributes: IndexMap::new(),
+            term_attributes: IndexMap::new(),
+            spans: IndexMap::new(),
+            flattened: IndexMap::new(),
+        };
+        let mut checker = checker::ProofChecker::new(&mut pool, checker::Config::new(), &prelude);
+        let result = checker.check(&sliced);
+        assert!(matches!(result, Err(Error::DoesNotReachEmptyClause)));
+    }
+}
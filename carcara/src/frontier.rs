@@ -0,0 +1,253 @@
+//! Turning partial proofs into complete ones.
+//!
+//! When a proof fails to check, the clauses derived by the commands that were checked before the
+//! failure (the "frontier" of the proof) are still valid consequences of the problem. These
+//! clauses can be written as a new problem, whose assertions are the frontier clauses, so that a
+//! solver can be asked to finish the proof. The proof it produces (the "completion") can then be
+//! spliced into the valid part of the original proof, resulting in a complete proof of the
+//! original problem.
+
+use crate::ast::*;
+use indexmap::{IndexMap, IndexSet};
+use std::fmt::Write;
+
+/// Returns the term asserted in the frontier problem for each of the given commands, together with
+/// the index of the command. A clause `(cl l1 ... ln)` is asserted as `(or l1 ... ln)`, and a unit
+/// clause is asserted as its only literal. Commands that derive the empty clause, or whose term is
+/// already a premise of the original problem, are skipped.
+pub fn frontier_terms(
+    pool: &mut PrimitivePool,
+    premises: &IndexSet<Rc<Term>>,
+    commands: &[ProofCommand],
+) -> Vec<(usize, Rc<Term>)> {
+    let mut seen = premises.clone();
+    let mut result = Vec::new();
+    for (i, command) in commands.iter().enumerate() {
+        let term = match command.clause() {
+            [] => continue,
+            [l] => l.clone(),
+            clause => pool.add(Term::Op(Operator::Or, clause.to_vec())),
+        };
+        if seen.insert(term.clone()) {
+            result.push((i, term));
+        }
+    }
+    result
+}
+
+/// Writes the frontier problem, with the declarations of the original problem, and its premises and
+/// the frontier terms as assertions.
+pub fn write_frontier_problem(
+    prelude: &ProblemPrelude,
+    premises: &IndexSet<Rc<Term>>,
+    terms: &[(usize, Rc<Term>)],
+) -> String {
+    let mut problem = String::new();
+    write!(&mut problem, "{}", prelude).unwrap();
+    for term in premises.iter().chain(terms.iter().map(|(_, t)| t)) {
+        writeln!(&mut problem, "(assert {})", term).unwrap();
+    }
+    writeln!(&mut problem, "(check-sat)").unwrap();
+    problem
+}
+
+/// Splices a completion proof, which proves the frontier problem, into the valid prefix of the
+/// original proof.
+///
+/// Each `assume` command in the root of the completion that assumes a frontier term is replaced by
+/// steps that derive it from the command of the prefix that concluded the corresponding clause.
+/// Commands of the completion whose ids are also used in the prefix are renamed.
+pub fn splice(
+    pool: &mut PrimitivePool,
+    prefix: Vec<ProofCommand>,
+    terms: &[(usize, Rc<Term>)],
+    completion: Vec<ProofCommand>,
+) -> Vec<ProofCommand> {
+    let frontier: IndexMap<_, _> = terms.iter().map(|(i, t)| (t.clone(), *i)).collect();
+    let mut prefix_ids = IndexSet::new();
+    for command in &prefix {
+        collect_ids(command, &mut prefix_ids);
+    }
+
+    let mut result = prefix;
+    let mut new_indices = Vec::with_capacity(completion.len());
+    for mut command in completion {
+        rename_clashing_ids(&mut command, &prefix_ids);
+        match command {
+            ProofCommand::Assume { id, term } if frontier.contains_key(&term) => {
+                let source = frontier[&term];
+                let glue = glue_steps(pool, id, &result[source], source, result.len());
+                result.extend(glue);
+            }
+            mut command => {
+                remap_root_premises(&mut command, &new_indices);
+                result.push(command);
+            }
+        }
+        new_indices.push(result.len() - 1);
+    }
+    result
+}
+
+/// Builds the steps that derive the frontier term of a command of the prefix, at index `source`,
+/// as a step with the given id. The steps are placed starting at index `start` in the root proof.
+fn glue_steps(
+    pool: &mut PrimitivePool,
+    id: StepId,
+    source_command: &ProofCommand,
+    source: usize,
+    start: usize,
+) -> Vec<ProofCommand> {
+    let clause = source_command.clause();
+    if let [_] = clause {
+        let step = ProofStep {
+            id,
+            clause: clause.to_vec(),
+            rule: "reordering".into(),
+            premises: vec![(0, source)],
+            args: Vec::new(),
+            discharge: Vec::new(),
+        };
+        return vec![ProofCommand::Step(step)];
+    }
+
+    // For a clause `(cl l1 ... ln)`, we introduce the tautologies `(cl (or l1 ... ln) (not li))`
+    // using the `or_neg` rule, and resolve them with the clause
+    let or_term = pool.add(Term::Op(Operator::Or, clause.to_vec()));
+    let mut result: Vec<_> = clause
+        .iter()
+        .enumerate()
+        .map(|(i, l)| {
+            let step = ProofStep {
+                id: id.child(&format!("or_neg{}", i + 1)),
+                clause: vec![or_term.clone(), build_term!(pool, (not { l.clone() }))],
+                rule: "or_neg".into(),
+                premises: Vec::new(),
+                args: Vec::new(),
+                discharge: Vec::new(),
+            };
+            ProofCommand::Step(step)
+        })
+        .collect();
+    let premises = std::iter::once((0, source))
+        .chain((start..start + clause.len()).map(|i| (0, i)))
+        .collect();
+    result.push(ProofCommand::Step(ProofStep {
+        id,
+        clause: vec![or_term],
+        rule: "resolution".into(),
+        premises,
+        args: Vec::new(),
+        discharge: Vec::new(),
+    }));
+    result
+}
+
+fn collect_ids(command: &ProofCommand, ids: &mut IndexSet<StepId>) {
+    match command {
+        ProofCommand::Subproof(s) => s.commands.iter().for_each(|c| collect_ids(c, ids)),
+        other => {
+            ids.insert(other.id().clone());
+        }
+    }
+}
+
+fn rename_clashing_ids(command: &mut ProofCommand, ids: &IndexSet<StepId>) {
+    let id = match command {
+        ProofCommand::Assume { id, .. } => id,
+        ProofCommand::Step(s) => &mut s.id,
+        ProofCommand::Subproof(s) => {
+            for c in &mut s.commands {
+                rename_clashing_ids(c, ids);
+            }
+            return;
+        }
+    };
+    if ids.contains(id) {
+        *id = format!("c.{}", id).into();
+    }
+}
+
+/// Maps the premises of a command that reference commands in the root proof to their new indices.
+/// This includes premises of steps inside subproofs.
+fn remap_root_premises(command: &mut ProofCommand, new_indices: &[usize]) {
+    match command {
+        ProofCommand::Assume { .. } => (),
+        ProofCommand::Step(s) => {
+            for (depth, i) in s.premises.iter_mut().chain(&mut s.discharge) {
+                if *depth == 0 {
+                    *i = new_indices[*i];
+                }
+            }
+        }
+        ProofCommand::Subproof(s) => {
+            for c in &mut s.commands {
+                remap_root_premises(c, new_indices);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::tests::parse_terms;
+
+    #[test]
+    fn test_splice() {
+        let mut pool = PrimitivePool::new();
+        let [p, q, p_or_q] = parse_terms(
+            &mut pool,
+            "(declare-fun p () Bool) (declare-fun q () Bool)",
+            ["p", "q", "(or p q)"],
+        );
+        let assume =
+            |id: &str, term: &Rc<Term>| ProofCommand::Assume { id: id.into(), term: term.clone() };
+        let step = |id: &str, clause: Vec<Rc<Term>>, premises: Vec<(usize, usize)>| {
+            ProofCommand::Step(ProofStep {
+                id: id.into(),
+                clause,
+                rule: "resolution".into(),
+                premises,
+                args: Vec::new(),
+                discharge: Vec::new(),
+            })
+        };
+
+        let premises = IndexSet::from([p.clone()]);
+        let prefix = vec![
+            assume("h1", &p),
+            step("t1", vec![p.clone(), q.clone()], vec![(0, 0)]),
+        ];
+        let terms = frontier_terms(&mut pool, &premises, &prefix);
+        assert_eq!(terms, [(1, p_or_q.clone())]);
+
+        let completion = vec![
+            assume("t1", &p_or_q),
+            assume("h1", &p),
+            step("t2", vec![q], vec![(0, 0), (0, 1)]),
+        ];
+        let spliced = splice(&mut pool, prefix, &terms, completion);
+        let ids: Vec<_> = spliced.iter().map(ProofCommand::id).collect();
+        assert_eq!(
+            ids,
+            [
+                "h1",
+                "t1",
+                "c.t1.or_neg1",
+                "c.t1.or_neg2",
+                "c.t1",
+                "c.h1",
+                "t2"
+            ]
+        );
+        let ProofCommand::Step(glue) = &spliced[4] else {
+            panic!();
+        };
+        assert_eq!(glue.premises, [(0, 1), (0, 2), (0, 3)]);
+        let ProofCommand::Step(last) = spliced.last().unwrap() else {
+            panic!();
+        };
+        assert_eq!(last.premises, [(0, 4), (0, 5)]);
+    }
+}
@@ -0,0 +1,270 @@
+//! Checking proofs in a separate worker process.
+//!
+//! Parsing and checking a proof that comes from an untrusted source may exhaust the memory or the
+//! stack of the process doing it, or may simply take too long. To protect a host process (for
+//! example, a proof-validation service), the check can instead be done by a worker process that
+//! runs under resource limits. If the worker crashes or is killed, this is reported as an error,
+//! and the host process is unaffected.
+//!
+//! The worker can be any program that follows the same protocol as the `carcara check --combined -`
+//! command: it reads the problem and the proof from its standard input, separated by a line
+//! containing only [`SEPARATOR`], and prints `valid` or `holey` to its standard output if the proof
+//! is valid, and `invalid` otherwise, with any error messages written to its standard error.
+
+use std::ffi::OsStr;
+use std::io::{self, Read, Write};
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// How often the host polls the worker to see if it has finished.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The line that separates the problem from the proof in the input of the worker. This is the
+/// default separator of the `--combined` option of the `carcara check` command, so a worker can
+/// read its input with [`split_combined_input`](crate::parser::split_combined_input).
+pub const SEPARATOR: &str = "unsat";
+
+/// The resource limits imposed on a worker process.
+///
+/// The memory and CPU time limits are set with `setrlimit` in the worker, before it starts running
+/// the program, and are only supported on Unix systems. The wall-clock timeout is enforced by the
+/// host process, on all platforms.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WorkerLimits {
+    /// The maximum size of the virtual memory of the worker, in megabytes.
+    pub memory_limit: Option<u64>,
+
+    /// The maximum CPU time used by the worker, in seconds.
+    pub cpu_time_limit: Option<u64>,
+
+    /// The maximum wall-clock time the host waits for the worker before killing it.
+    pub timeout: Option<Duration>,
+}
+
+#[derive(Debug, Error)]
+pub enum WorkerError {
+    #[error("failed to spawn worker process: {0}")]
+    FailedSpawn(io::Error),
+
+    #[error("IO error while communicating with worker process: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("worker process timed out")]
+    Timeout,
+
+    /// The worker reported that the proof is invalid. This holds the standard error output of the
+    /// worker, which contains the reason.
+    #[error("proof is invalid: {0}")]
+    Invalid(String),
+
+    /// The worker exited without reporting a result, either because it crashed or because it was
+    /// killed for exceeding a resource limit. This holds the exit status and the standard error
+    /// output of the worker.
+    #[error("worker process terminated abnormally ({0}): {1}")]
+    Crashed(ExitStatus, String),
+}
+
+/// Checks a proof by running `program` with `args` as a worker process, under the given resource
+/// limits. The problem and the proof are written to the standard input of the worker, separated by
+/// a [`SEPARATOR`] line. Returns `true` if the proof is valid but has holes, and `false` if it is
+/// valid and complete, in the same way as [`check`](crate::check).
+pub fn check_isolated<S, I, A>(
+    program: S,
+    args: I,
+    problem: &[u8],
+    proof: &[u8],
+    limits: WorkerLimits,
+) -> Result<bool, WorkerError>
+where
+    S: AsRef<OsStr>,
+    I: IntoIterator<Item = A>,
+    A: AsRef<OsStr>,
+{
+    let mut command = limited_command(program.as_ref(), limits);
+    let mut child = command
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(WorkerError::FailedSpawn)?;
+
+    // We write the input and read the outputs in separate threads, so neither the worker nor the
+    // host ever block on a full pipe while we wait for the worker
+    let (stdin, stdout, stderr) = (child.stdin.take(), child.stdout.take(), child.stderr.take());
+    let (status, written, stdout, stderr) = thread::scope(|s| {
+        let written = s.spawn(|| write_input(stdin, problem, proof));
+        let stdout = read_in_background(s, stdout);
+        let stderr = read_in_background(s, stderr);
+        let status = wait_with_timeout(&mut child, limits.timeout);
+        let [stdout, stderr] = [stdout, stderr].map(|h| h.join().unwrap());
+        (status, written.join().unwrap(), stdout, stderr)
+    });
+    let (status, stdout, stderr) = (status?, stdout?, stderr?);
+    match written {
+        // If the worker exits or is killed before reading all of its input, writing to it fails
+        // with a broken pipe. In that case, the exit status tells what happened
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e.into()),
+        _ => (),
+    }
+    let Some(status) = status else {
+        return Err(WorkerError::Timeout);
+    };
+
+    let stderr = stderr.trim().to_owned();
+    match stdout.lines().next().map(str::trim) {
+        Some("valid") if status.success() => Ok(false),
+        Some("holey") if status.success() => Ok(true),
+        Some("invalid") => Err(WorkerError::Invalid(stderr)),
+        _ => Err(WorkerError::Crashed(status, stderr)),
+    }
+}
+
+#[cfg(unix)]
+fn limited_command(program: &OsStr, limits: WorkerLimits) -> Command {
+    use std::os::unix::process::CommandExt;
+
+    let resource_limits = [
+        (
+            libc::RLIMIT_AS,
+            limits.memory_limit.map(|megabytes| megabytes * 1024 * 1024),
+        ),
+        (libc::RLIMIT_CPU, limits.cpu_time_limit),
+    ];
+    let mut command = Command::new(program);
+
+    // SAFETY: the closure runs in the forked process before it calls `exec`, so it may only call
+    // async-signal-safe functions. It only calls `setrlimit`, which is one, and doesn't allocate
+    unsafe {
+        command.pre_exec(move || {
+            for (resource, limit) in resource_limits {
+                let Some(limit) = limit else { continue };
+                let limit = libc::rlimit { rlim_cur: limit, rlim_max: limit };
+                if libc::setrlimit(resource, &limit) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+    command
+}
+
+#[cfg(not(unix))]
+fn limited_command(program: &OsStr, limits: WorkerLimits) -> Command {
+    if limits.memory_limit.is_some() || limits.cpu_time_limit.is_some() {
+        log::warn!("memory and CPU time limits are only supported on Unix systems");
+    }
+    Command::new(program)
+}
+
+/// Writes the problem and the proof to the standard input of the worker, and then closes it.
+fn write_input(stdin: Option<ChildStdin>, problem: &[u8], proof: &[u8]) -> io::Result<()> {
+    let Some(mut stdin) = stdin else {
+        return Ok(());
+    };
+    stdin.write_all(problem)?;
+    if !problem.is_empty() && !problem.ends_with(b"\n") {
+        stdin.write_all(b"\n")?;
+    }
+    writeln!(stdin, "{}", SEPARATOR)?;
+    stdin.write_all(proof)
+}
+
+fn read_in_background<'scope, R: Read + Send + 'scope>(
+    scope: &'scope thread::Scope<'scope, '_>,
+    source: Option<R>,
+) -> thread::ScopedJoinHandle<'scope, io::Result<String>> {
+    scope.spawn(move || {
+        let mut buf = String::new();
+        if let Some(mut source) = source {
+            source.read_to_string(&mut buf)?;
+        }
+        Ok(buf)
+    })
+}
+
+/// Waits for the child process to finish, killing it if the timeout is reached. Returns `None` if
+/// the process was killed.
+fn wait_with_timeout(
+    child: &mut Child,
+    timeout: Option<Duration>,
+) -> io::Result<Option<ExitStatus>> {
+    let Some(timeout) = timeout else {
+        return child.wait().map(Some);
+    };
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if start.elapsed() >= timeout {
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn run_script(script: &str, limits: WorkerLimits) -> Result<bool, WorkerError> {
+        check_isolated(
+            "sh",
+            ["-c", script],
+            b"(assert false)",
+            b"(step t1 (cl) :rule hole)",
+            limits,
+        )
+    }
+
+    #[test]
+    fn test_check_isolated() {
+        let limits = WorkerLimits::default();
+        assert!(matches!(run_script("echo valid", limits), Ok(false)));
+        assert!(matches!(run_script("echo holey", limits), Ok(true)));
+        assert!(matches!(
+            run_script("echo invalid; echo oops >&2; exit 1", limits),
+            Err(WorkerError::Invalid(e)) if e == "oops"
+        ));
+        assert!(matches!(
+            run_script("exit 3", limits),
+            Err(WorkerError::Crashed(_, _))
+        ));
+
+        // The problem and proof are written to the standard input of the worker
+        let script =
+            "[ \"$(cat)\" = \"$(printf '(assert false)\\nunsat\\n(step t1 (cl) :rule hole)')\" ] \
+            && echo valid";
+        assert!(matches!(run_script(script, limits), Ok(false)));
+
+        // Workers that exit without reading their input are not an error by themselves
+        assert!(matches!(
+            run_script("exec 0<&-; echo holey", limits),
+            Ok(true)
+        ));
+
+        let limits = WorkerLimits {
+            cpu_time_limit: Some(1),
+            ..WorkerLimits::default()
+        };
+        assert!(matches!(
+            run_script("while :; do :; done", limits),
+            Err(WorkerError::Crashed(_, _))
+        ));
+
+        let limits = WorkerLimits {
+            timeout: Some(Duration::from_millis(100)),
+            ..WorkerLimits::default()
+        };
+        assert!(matches!(
+            run_script("exec sleep 5", limits),
+            Err(WorkerError::Timeout)
+        ));
+    }
+}
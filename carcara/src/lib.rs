@@ -35,25 +35,42 @@
 #![warn(clippy::unnested_or_patterns)]
 #![warn(clippy::unused_self)]
 
+pub mod arithmetic;
 #[macro_use]
 pub mod ast;
 pub mod benchmarking;
+#[cfg(feature = "exporters")]
+mod chc;
 pub mod checker;
+#[cfg(feature = "drat")]
+mod drat;
+#[cfg(feature = "elaborator")]
 pub mod elaborator;
+#[cfg(feature = "frontier")]
+mod frontier;
+#[cfg(feature = "isolation")]
+pub mod isolation;
+pub mod logging;
 pub mod parser;
 mod utils;
 
-use crate::benchmarking::{CollectResults, OnlineBenchmarkResults, RunMeasurement};
+#[cfg(feature = "benchmarking")]
+use crate::benchmarking::OnlineBenchmarkResults;
+use crate::benchmarking::{CollectResults, RunMeasurement};
 use checker::{error::CheckerError, CheckerStatistics};
-use parser::{ParserError, Position};
+#[cfg(feature = "drat")]
+pub use drat::DratError;
+use parser::{ParserError, Position, Span};
 use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use thiserror::Error;
 
 pub type CarcaraResult<T> = Result<T, Error>;
 
 /// The options that control how Carcara parses, checks and elaborates a proof.
-#[derive(Default)]
+#[derive(Debug, Default, Clone)]
 pub struct CarcaraOptions {
     /// If `true`, Carcara will automatically expand function definitions introduced by `define-fun`
     /// commands in the SMT problem. If `false`, those `define-fun`s are instead interpreted as a
@@ -73,6 +90,45 @@ pub struct CarcaraOptions {
     /// to a function that expects a `Real` will still be an error.
     pub allow_int_real_subtyping: bool,
 
+    /// The index of the query in the problem against which the proof is checked, counting
+    /// `check-sat` and `check-sat-assuming` commands from zero. In incremental problems, each query
+    /// has its own set of premises, given by the assertions that were active at its `check-sat`
+    /// command. If `None`, the last query is used.
+    pub query: Option<usize>,
+
+    /// The number of threads used to parse the proof. If this is greater than one, the proof is
+    /// split in chunks at the boundaries between commands, and each chunk is parsed in its own
+    /// thread. This can be faster for very large proofs. This is ignored by `check_streaming`.
+    pub parsing_threads: usize,
+
+    /// The limits on the size of the problem and proof, used to reject adversarial inputs. See
+    /// [`parser::ParserLimits`] for more details.
+    pub parser_limits: parser::ParserLimits,
+
+    /// If `true`, nested applications of associative operators are flattened when parsing. See
+    /// [`parser::Config::flatten_assoc_ops`]. This is ignored if `strict` is enabled, or if any
+    /// rule is listed in `strict_rules`, since strict checking requires the terms as written.
+    pub flatten_assoc_ops: bool,
+
+    /// The version of the Alethe format the proof is written in. This determines which syntax the
+    /// parser accepts, and which rule names are recognized by the checker. If `None`, the
+    /// constructs of every version are accepted. See [`parser::Config::alethe_version`].
+    pub alethe_version: Option<ast::AletheVersion>,
+
+    /// If `true`, some non-standard constructs emitted by veriT are accepted when parsing, and
+    /// normalized into standard Alethe. See [`parser::Config::verit_compat`].
+    pub verit_compat: bool,
+
+    /// If `true`, some non-standard constructs emitted by `SMTInterpol` are accepted when parsing,
+    /// and normalized into standard Alethe. See [`parser::Config::smtinterpol_compat`].
+    pub smtinterpol_compat: bool,
+
+    /// If `Some`, when checking a proof without its problem using [`check_proof_only`], symbols
+    /// that are used without being declared are declared automatically. Their sorts are inferred
+    /// from the terms in which they are first used, and this sort is used when that is not
+    /// possible. The proof is then always parsed using a single thread.
+    pub infer_declarations: Option<ast::Sort>,
+
     /// If `Some`, enables the checking/elaboration of `lia_generic` steps using an external solver.
     /// When checking a proof, this means calling the solver to solve the linear integer arithmetic
     /// problem, checking the proof, and discarding it. When elaborating, the proof will instead be
@@ -84,18 +140,88 @@ pub struct CarcaraOptions {
     /// Currently, if enabled, the following rules are affected:
     /// - `assume` and `refl`: implicit reordering of equalities is not allowed
     /// - `resolution` and `th_resolution`: the pivots must be provided as arguments
+    /// - `anchor`: the ids of the commands in a subproof must be nested in the id of the subproof,
+    ///   as in `t5.t1` for the subproof that ends with `t5`
     ///
     /// In general, the invariant we aim for is that, if you are checking a proof that was
     /// elaborated by Carcara, you can safely enable this option (and possibly get a performance
     /// benefit).
     pub strict: bool,
 
+    /// Enables "strict" checking only for the rules in this list, as opposed to `strict`, which
+    /// enables it for all rules. The `assume` and `anchor` commands can also be included here. This
+    /// is useful when, for example, strict `resolution` steps are desired, but implicit reordering
+    /// of equalities in `assume` commands should still be allowed.
+    pub strict_rules: Vec<String>,
+
+    /// Aliases for rule names, given as pairs of an alias and the name of a rule implemented by
+    /// Carcara. Steps using an alias are checked as if they used the corresponding rule. This is
+    /// useful for proofs produced by solvers that use different names for some rules.
+    pub rule_aliases: Vec<(String, String)>,
+
+    /// Rules whose steps are accepted without being checked, making the proof holey. This is
+    /// useful for rules that Carcara can't check, like the ones cvc5 uses to reason about the
+    /// cardinality of uninterpreted sorts when finite model finding is enabled.
+    pub trusted_rules: Vec<String>,
+
     /// If `true`, Carcara will skip any steps with rules that it does not recognize, and will consider them as
     /// holes. Normally, using an unknown rule is considered an error.
     pub ignore_unknown_rules: bool,
 
+    /// If `true`, arithmetic side conditions of `la_generic` steps are first checked using
+    /// floating-point interval arithmetic, which can quickly reject invalid steps. Steps are still
+    /// only accepted after being checked using exact arithmetic.
+    pub numeric_precheck: bool,
+
+    /// If `true`, the literals of the premises of resolution steps are cached, so they are not
+    /// recomputed when the same premise is used in many resolution steps.
+    pub resolution_cache: bool,
+
+    /// If `true`, when a `la_generic` step is rejected, the error reports the disequality
+    /// contributed by each clause literal, as well as the final disequality obtained from them.
+    pub la_certificates: bool,
+
+    /// If `true`, when the coefficients of a `la_generic` step are missing or do not lead to a
+    /// contradiction, Carcara tries to infer valid coefficients, and accepts the step if it
+    /// succeeds. When elaborating, the step arguments are replaced by the inferred coefficients.
+    pub la_infer_args: bool,
+
+    /// If `true`, only resolution and subproof steps are checked, and all other steps are
+    /// considered holes. This is much faster than a full check, and can be used as a quick check
+    /// of the structure of the proof.
+    pub skeleton_only: bool,
+
+    /// If `true`, when elaborating, the premises of `cong` and `ho_cong` steps are sorted in the
+    /// order of the arguments whose equality they justify, since these rules accept premises given
+    /// in any order.
+    pub sort_premises: bool,
+
+    /// The normalizations applied to both `assume` terms and problem assertions when matching
+    /// them. See [`AssumeNormalizations`] for more details.
+    pub assume_normalizations: AssumeNormalizations,
+
+    /// The limits imposed on the size of every step. A step that exceeds them is rejected with a
+    /// `LimitExceeded` error instead of being checked. By default, no limits are imposed. See
+    /// [`StepLimits`] for more details.
+    pub step_limits: StepLimits,
+
+    /// Whether terms in the proof are validated against the logic declared by the problem. See
+    /// [`LogicValidation`] for more details.
+    pub logic_validation: LogicValidation,
+
+    /// If not empty, only the steps with these ids, and the commands they transitively depend on,
+    /// are checked. All other steps are considered holes. This is useful to quickly recheck steps
+    /// that were rejected in a previous run. In `check_streaming`, the commands each step depends
+    /// on are not known in advance, so only the given steps are checked.
+    pub only_steps: Vec<String>,
+
+    /// Limits for specific rules, given as pairs of a rule name and its limits. These take
+    /// precedence over the limits in `step_limits`, which still apply to any limit left unset.
+    pub rule_step_limits: Vec<(String, StepLimits)>,
+
     /// If `true`, Carcará will log the check and elaboration statistics of any
-    /// `check` or `check_and_elaborate` run. If `false` no statistics are logged.
+    /// `check` or `check_and_elaborate` run. If `false` no statistics are logged. Statistics are
+    /// only collected if the `benchmarking` feature is enabled.
     pub stats: bool,
 }
 
@@ -111,11 +237,111 @@ pub struct LiaGenericOptions {
     pub arguments: Vec<Box<str>>,
 }
 
+/// The normalizations that may be applied when matching `assume` commands with the assertions in
+/// the original problem. Different solvers preprocess their input in slightly different ways, so
+/// an `assume` term may not be syntactically equal to any assertion, even up to the implicit
+/// reordering of equalities.
+///
+/// The enabled normalizations are applied to both terms, recursively, and only after the terms
+/// failed to match without them. An `assume` command matched using a normalization is considered a
+/// hole when elaborating.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AssumeNormalizations {
+    /// Flattens nested conjunctions, such that `(and a (and b c))` is normalized to
+    /// `(and a b c)`.
+    pub flatten_and: bool,
+
+    /// Sorts the arguments of disjunctions, such that `(or a b)` and `(or b a)` have the same
+    /// normal form.
+    pub sort_or: bool,
+
+    /// Eliminates double negations, such that `(not (not a))` is normalized to `a`.
+    pub normalize_negations: bool,
+}
+
+/// Limits on the size of a step, used to reject pathological steps that would otherwise take a
+/// very long time to check. Since a rejected step makes the whole proof invalid, these should not
+/// be set when soundness is all that matters.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StepLimits {
+    /// The maximum number of literals in the conclusion clause of a step.
+    pub max_clause_len: Option<usize>,
+
+    /// The maximum number of distinct term nodes in the conclusion clause and arguments of a step.
+    /// Terms are counted as DAGs, so a subterm that occurs many times is only counted once.
+    pub max_term_nodes: Option<usize>,
+}
+
+/// How terms that are not allowed in the logic declared by the problem's `set-logic` command are
+/// handled. Terms are validated against the logic before the proof is checked, which can catch
+/// malformed proofs much earlier than a rule failure. Problems with no `set-logic` command, or
+/// that use the `ALL` logic or a non-standard one, are never validated.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LogicValidation {
+    /// Terms are not validated against the logic.
+    #[default]
+    Off,
+
+    /// A warning is logged for each command that uses a term outside the logic.
+    Warn,
+
+    /// A command that uses a term outside the logic is rejected with an error.
+    Error,
+}
+
+impl AssumeNormalizations {
+    /// Returns `true` if any normalization is enabled.
+    pub fn any(&self) -> bool {
+        self.flatten_and || self.sort_or || self.normalize_negations
+    }
+}
+
 impl CarcaraOptions {
     /// Constructs a new `CarcaraOptions` with all options set to `false`.
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Builds the parser configuration described by these options.
+    pub fn parser_config(&self) -> parser::Config {
+        parser::Config {
+            apply_function_defs: self.apply_function_defs,
+            expand_lets: self.expand_lets,
+            allow_int_real_subtyping: self.allow_int_real_subtyping,
+            query: self.query,
+            parsing_threads: self.parsing_threads,
+            limits: self.parser_limits,
+            flatten_assoc_ops: self.flatten_assoc_ops
+                && !self.strict
+                && self.strict_rules.is_empty(),
+            alethe_version: self.alethe_version,
+            verit_compat: self.verit_compat,
+            smtinterpol_compat: self.smtinterpol_compat,
+        }
+    }
+
+    /// Builds the checker configuration described by these options.
+    pub fn checker_config(&self) -> checker::Config {
+        checker::Config::new()
+            .strict(self.strict)
+            .strict_rules(self.strict_rules.clone())
+            .rule_aliases(self.rule_aliases.clone())
+            .trusted_rules(self.trusted_rules.clone())
+            .ignore_unknown_rules(self.ignore_unknown_rules)
+            .lia_options(self.lia_options.clone())
+            .numeric_precheck(self.numeric_precheck)
+            .resolution_cache(self.resolution_cache)
+            .la_certificates(self.la_certificates)
+            .la_infer_args(self.la_infer_args)
+            .skeleton_only(self.skeleton_only)
+            .sort_premises(self.sort_premises)
+            .assume_normalizations(self.assume_normalizations)
+            .step_limits(self.step_limits)
+            .rule_step_limits(self.rule_step_limits.clone())
+            .only_steps(self.only_steps.clone())
+            .logic_validation(self.logic_validation)
+            .alethe_version(self.alethe_version)
+    }
 }
 
 fn wrap_parser_error_message(e: &ParserError, pos: &Position) -> String {
@@ -127,6 +353,36 @@ fn wrap_parser_error_message(e: &ParserError, pos: &Position) -> String {
     }
 }
 
+fn wrap_checker_error_message(
+    inner: &CheckerError,
+    rule: &str,
+    step: &str,
+    span: Option<&Span>,
+    annotations: Option<&String>,
+) -> String {
+    let mut details = Vec::new();
+    if let Some(Span { start: (line, column), .. }) = span {
+        details.push(format!("on line {}, column {}", line, column));
+    }
+    if let Some(annotations) = annotations {
+        details.push(format!("annotated with {}", annotations));
+    }
+    if details.is_empty() {
+        format!(
+            "checking failed on step '{}' with rule '{}': {}",
+            step, rule, inner
+        )
+    } else {
+        format!(
+            "checking failed on step '{}' with rule '{}' ({}): {}",
+            step,
+            rule,
+            details.join("; "),
+            inner
+        )
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("IO error: {0}")]
@@ -135,74 +391,411 @@ pub enum Error {
     #[error("{}", wrap_parser_error_message(.0, .1))]
     Parser(ParserError, Position),
 
-    #[error("checking failed on step '{step}' with rule '{rule}': {inner}")]
+    /// An error found while checking a step. If the proof was parsed from a text input, this
+    /// also holds the span of the step in the input. If the proof producer annotated the step
+    /// with attributes that Carcara doesn't interpret, like the id the step has inside the
+    /// solver, this also holds them, to help tracing the step back to where it was produced. See
+    /// [`ast::Proof::annotations`]. The checker error and the span are boxed to keep this type
+    /// small, since it is returned by most functions in the crate.
+    #[error(
+        "{}",
+        wrap_checker_error_message(.inner, .rule, .step, .span.as_deref(), .annotations.as_ref())
+    )]
     Checker {
-        inner: CheckerError,
+        inner: Box<CheckerError>,
         rule: String,
         step: String,
+        span: Option<Box<Span>>,
+        annotations: Option<String>,
     },
 
     // While this is a kind of checking error, it does not happen in a specific step like all other
     // checker errors, so we model it as a different variant
     #[error("checker error: proof does not conclude empty clause")]
     DoesNotReachEmptyClause,
+
+    /// None of the proofs passed to `check_any` is valid. This holds the index of each proof, in
+    /// the order the proofs were given, and the error found in it.
+    #[error("none of the {} candidate proofs is valid", .0.len())]
+    NoValidProof(Vec<(usize, Error)>),
+
+    /// An error found in one of the parts of a proof that is split across many parts. This holds
+    /// the name of the part, as given to `check_composed`.
+    #[error("in proof part '{part}': {inner}")]
+    InPart { part: String, inner: Box<Error> },
+
+    /// One of the steps selected using the `only_steps` option does not exist in the proof.
+    #[error("step '{0}' does not exist in the proof")]
+    UnknownStep(String),
+
+    /// An error found while reading a DIMACS CNF formula or a DRAT refutation, in
+    /// `translate_drat` or `check_drat`.
+    #[cfg(feature = "drat")]
+    #[error("DIMACS/DRAT error: {0}")]
+    Drat(#[from] DratError),
+
+    /// The progress callback asked the checker to stop. See
+    /// [`checker::ProofChecker::on_progress`].
+    #[error("checking was interrupted")]
+    Interrupted,
 }
 
-pub fn check<T: io::BufRead>(problem: T, proof: T, options: CarcaraOptions) -> Result<bool, Error> {
-    let mut run_measures: RunMeasurement = RunMeasurement::default();
+impl Error {
+    /// If this is a checker error with no span, sets its span to the span of its step in `spans`.
+    pub(crate) fn with_span(self, spans: &indexmap::IndexMap<String, Span>) -> Self {
+        match self {
+            Error::Checker {
+                inner,
+                rule,
+                step,
+                span: None,
+                annotations,
+            } => {
+                let span = spans.get(&step).copied().map(Box::new);
+                Error::Checker {
+                    inner,
+                    rule,
+                    step,
+                    span,
+                    annotations,
+                }
+            }
+            other => other,
+        }
+    }
 
+    /// If this is a checker error, sets its span and annotations to the ones of its step in
+    /// `proof`, if they are not already set.
+    pub(crate) fn with_step_info(self, proof: &ast::Proof) -> Self {
+        match self.with_span(&proof.spans) {
+            Error::Checker {
+                inner,
+                rule,
+                step,
+                span,
+                annotations: None,
+            } => {
+                let annotations = proof.annotations(&step);
+                Error::Checker {
+                    inner,
+                    rule,
+                    step,
+                    span,
+                    annotations,
+                }
+            }
+            other => other,
+        }
+    }
+}
+
+pub fn check<T: io::BufRead>(problem: T, proof: T, options: CarcaraOptions) -> Result<bool, Error> {
     // Parsing
+    let total = Instant::now();
+    let config = options.parser_config();
+    let (prelude, proof, mut pool) = parser::parse_instance(problem, proof, config)?;
+    check_parsed_instance(&prelude, &proof, &mut pool, options, total)
+}
+
+/// Like [`check`], but caches the parsed proof in a binary file next to the proof file, whose path
+/// is `proof_path`, and re-uses it in later calls if the proof file was not modified. See
+/// [`parser::parse_instance_cached`].
+pub fn check_with_proof_cache<T: io::BufRead>(
+    problem: T,
+    proof: T,
+    proof_path: &std::path::Path,
+    options: CarcaraOptions,
+) -> Result<bool, Error> {
+    let total = Instant::now();
+    let config = options.parser_config();
+    let (prelude, proof, mut pool) =
+        parser::parse_instance_cached(problem, proof, proof_path, config)?;
+    check_parsed_instance(&prelude, &proof, &mut pool, options, total)
+}
+
+/// Checks a proof of a problem that was built in memory, instead of parsed from SMT-LIB text. The
+/// proof is parsed using the declarations in `prelude` (see [`parser::Parser::declare_prelude`]),
+/// and the problem's assertions are taken to be `premises`. All terms in `prelude` and `premises`
+/// must have been added to `pool`, which is also used to parse the proof. The proof is always
+/// parsed using a single thread.
+///
+/// # Examples
+///
+/// ```
+/// # use carcara::{ast::*, check_with_prelude, CarcaraOptions};
+/// let mut pool = PrimitivePool::new();
+/// let bool_sort = pool.add(Term::Sort(Sort::Bool));
+/// let p = pool.add(Term::new_var("p", bool_sort.clone()));
+/// let not_p = pool.add(Term::Op(Operator::Not, vec![p.clone()]));
+///
+/// let mut prelude = ProblemPrelude::new();
+/// prelude.set_logic("QF_UF").declare_fun("p", bool_sort);
+/// let premises = [p, not_p].into_iter().collect();
+/// let proof = "
+///     (assume h1 p)
+///     (assume h2 (not p))
+///     (step t3 (cl) :rule resolution :premises (h1 h2))
+/// ";
+/// let options = CarcaraOptions::new();
+/// let is_holey = check_with_prelude(&mut pool, &prelude, premises, proof.as_bytes(), options)?;
+/// assert!(!is_holey);
+/// # Ok::<(), carcara::Error>(())
+/// ```
+pub fn check_with_prelude<T: io::BufRead>(
+    pool: &mut ast::PrimitivePool,
+    prelude: &ast::ProblemPrelude,
+    premises: indexmap::IndexSet<ast::Rc<ast::Term>>,
+    proof: T,
+    options: CarcaraOptions,
+) -> Result<bool, Error> {
     let total = Instant::now();
     let config = parser::Config {
-        apply_function_defs: options.apply_function_defs,
-        expand_lets: options.expand_lets,
-        allow_int_real_subtyping: options.allow_int_real_subtyping,
+        parsing_threads: 1,
+        ..options.parser_config()
+    };
+    let mut parser = parser::Parser::new(pool, config, proof)?;
+    parser.declare_prelude(prelude);
+    let commands = parser.parse_proof()?;
+    let proof = ast::Proof {
+        premises,
+        commands,
+        attributes: parser.take_attributes(),
+        term_attributes: parser.take_term_attributes(),
+        spans: parser.take_spans(),
+        flattened: parser.take_flattened(),
+    };
+    drop(parser);
+    check_parsed_instance(prelude, &proof, pool, options, total)
+}
+
+/// Checks an instance that was already parsed, as part of `check`. The parsing time is measured
+/// from `total`, which is the instant the parsing started.
+fn check_parsed_instance(
+    prelude: &ast::ProblemPrelude,
+    proof: &ast::Proof,
+    pool: &mut ast::PrimitivePool,
+    options: CarcaraOptions,
+    total: Instant,
+) -> Result<bool, Error> {
+    let run_measures = RunMeasurement {
+        parsing: total.elapsed(),
+        ..RunMeasurement::default()
     };
-    let (prelude, proof, mut pool) = parser::parse_instance(problem, proof, config)?;
-    run_measures.parsing = total.elapsed();
 
-    let config = checker::Config::new()
-        .strict(options.strict)
-        .ignore_unknown_rules(options.ignore_unknown_rules)
-        .lia_options(options.lia_options);
+    let config = options.checker_config();
 
     // Checking
     let checking = Instant::now();
-    let mut checker = checker::ProofChecker::new(&mut pool, config, &prelude);
-    if options.stats {
-        let mut checker_stats = CheckerStatistics {
-            file_name: "this",
-            elaboration_time: Duration::ZERO,
-            polyeq_time: Duration::ZERO,
-            assume_time: Duration::ZERO,
-            assume_core_time: Duration::ZERO,
-            results: OnlineBenchmarkResults::new(),
+    let mut checker = checker::ProofChecker::new(pool, config, prelude);
+    run_with_stats(
+        &options,
+        run_measures,
+        total,
+        checking,
+        |stats| match stats {
+            Some(stats) => checker.check_with_stats(proof, stats),
+            None => checker.check(proof),
+        },
+    )
+}
+
+/// The results collected when the `stats` option is set. Without the `benchmarking` feature, no
+/// results are collected.
+#[cfg(feature = "benchmarking")]
+type StatsResults = OnlineBenchmarkResults;
+#[cfg(not(feature = "benchmarking"))]
+type StatsResults = benchmarking::NullResults;
+
+/// Runs `check`, passing it the statistics to collect if the `stats` option is set, and prints
+/// them afterwards. The parsing and scheduling times must already be in `run_measures`, and
+/// `total` and `checking` are the instants in which parsing and checking started.
+fn run_with_stats<T>(
+    options: &CarcaraOptions,
+    mut run_measures: RunMeasurement,
+    total: Instant,
+    checking: Instant,
+    check: impl FnOnce(Option<&mut CheckerStatistics<StatsResults>>) -> T,
+) -> T {
+    if !options.stats {
+        return check(None);
+    }
+    if cfg!(not(feature = "benchmarking")) {
+        log::warn!("statistics are only collected if the `benchmarking` feature is enabled");
+        return check(None);
+    }
+
+    let mut checker_stats = CheckerStatistics {
+        file_name: "this",
+        elaboration_time: Duration::ZERO,
+        polyeq_time: Duration::ZERO,
+        assume_time: Duration::ZERO,
+        assume_core_time: Duration::ZERO,
+        results: StatsResults::default(),
+        probe: None,
+    };
+    let res = check(Some(&mut checker_stats));
+
+    run_measures.checking = checking.elapsed();
+    run_measures.total = total.elapsed();
+    run_measures.elaboration = checker_stats.elaboration_time;
+    run_measures.polyeq = checker_stats.polyeq_time;
+    run_measures.assume = checker_stats.assume_time;
+    run_measures.assume_core = checker_stats.assume_core_time;
+    checker_stats
+        .results
+        .add_run_measurement(&("this".to_owned(), 0), run_measures);
+
+    // Print the statistics
+    #[cfg(feature = "benchmarking")]
+    checker_stats.results.print(false);
+
+    res
+}
+
+/// The result of checking a proof without its problem, using [`check_proof_only`].
+#[derive(Debug)]
+pub struct ProofOnlyResult {
+    /// `true` if the proof is holey.
+    pub is_holey: bool,
+
+    /// The ids and terms of the `assume` commands in the root proof. Since there is no problem to
+    /// check them against, these are taken at face value. The proof only shows that the problem is
+    /// unsatisfiable if the problem implies all of these terms.
+    pub unchecked_premises: Vec<(ast::StepId, ast::Rc<ast::Term>)>,
+
+    /// The symbols that were declared automatically, and their inferred sorts. This is always
+    /// empty unless [`CarcaraOptions::infer_declarations`] is set. The result is only meaningful if
+    /// the symbols have these sorts in the problem.
+    pub inferred_declarations: Vec<(String, ast::Rc<ast::Term>)>,
+}
+
+/// Checks a proof without the original problem. The `assume` commands in the root proof are
+/// taken at face value, and returned as unchecked premises, while all steps are checked as usual.
+///
+/// Since the Alethe format has no declarations, the proof may include the `declare-*`,
+/// `define-*` and `set-logic` commands needed to parse it, anywhere before the symbols they
+/// declare are used. Alternatively, if the `infer_declarations` option is set, undeclared symbols
+/// are declared automatically. Function definitions are always expanded, and the `query` option is
+/// ignored.
+///
+/// # Examples
+///
+/// ```
+/// # use carcara::*;
+/// let proof = "(declare-fun p () Bool)
+///     (assume h1 p)
+///     (assume h2 (not p))
+///     (step t3 (cl) :rule resolution :premises (h1 h2))";
+/// let result = check_proof_only(proof.as_bytes(), CarcaraOptions::new())?;
+/// assert!(!result.is_holey);
+/// let ids: Vec<_> = result.unchecked_premises.iter().map(|(id, _)| id.as_str()).collect();
+/// assert_eq!(ids, ["h1", "h2"]);
+/// # Ok::<(), Error>(())
+/// ```
+pub fn check_proof_only<T: io::BufRead>(
+    mut proof: T,
+    options: CarcaraOptions,
+) -> Result<ProofOnlyResult, Error> {
+    let mut input = Vec::new();
+    proof.read_to_end(&mut input)?;
+
+    // The proof is also parsed as a problem, which processes any declarations it has, and ignores
+    // all proof commands
+    let config = parser::Config {
+        apply_function_defs: true,
+        query: None,
+        ..options.parser_config()
+    };
+    let (prelude, mut proof, mut pool, inferred_declarations) =
+        if let Some(sort) = &options.infer_declarations {
+            let mut pool = ast::PrimitivePool::new();
+            let mut parser = parser::Parser::new(&mut pool, config, input.as_slice())?;
+            let (prelude, _) = parser.parse_problem()?;
+
+            // Since the inferred sorts depend on the order in which symbols are used, the proof
+            // is always parsed sequentially
+            parser.infer_declarations(sort.clone());
+            parser.reset(input.as_slice())?;
+            let commands = parser.parse_proof()?;
+            let proof = ast::Proof {
+                premises: indexmap::IndexSet::new(),
+                commands,
+                attributes: parser.take_attributes(),
+                term_attributes: parser.take_term_attributes(),
+                spans: parser.take_spans(),
+                flattened: parser.take_flattened(),
+            };
+            let inferred = parser.inferred_declarations();
+            (prelude, proof, pool, inferred)
+        } else {
+            let (prelude, proof, pool) =
+                parser::parse_instance(input.as_slice(), input.as_slice(), config)?;
+            (prelude, proof, pool, Vec::new())
         };
-        let res = checker.check_with_stats(&proof, &mut checker_stats);
-
-        run_measures.checking = checking.elapsed();
-        run_measures.total = total.elapsed();
-
-        checker_stats.results.add_run_measurement(
-            &("this".to_owned(), 0),
-            RunMeasurement {
-                parsing: run_measures.parsing,
-                checking: run_measures.checking,
-                elaboration: checker_stats.elaboration_time,
-                scheduling: run_measures.scheduling,
-                total: run_measures.total,
-                polyeq: checker_stats.polyeq_time,
-                assume: checker_stats.assume_time,
-                assume_core: checker_stats.assume_core_time,
-            },
-        );
-        // Print the statistics
-        checker_stats.results.print(false);
+    let unchecked_premises: Vec<_> = proof
+        .commands
+        .iter()
+        .filter_map(|c| match c {
+            ast::ProofCommand::Assume { id, term } => Some((id.clone(), term.clone())),
+            _ => None,
+        })
+        .collect();
+    proof.premises = unchecked_premises.iter().map(|(_, t)| t.clone()).collect();
 
-        res
-    } else {
-        checker.check(&proof)
+    let config = options.checker_config();
+    let is_holey = checker::ProofChecker::new(&mut pool, config, &prelude).check(&proof)?;
+    Ok(ProofOnlyResult {
+        is_holey,
+        unchecked_premises,
+        inferred_declarations,
+    })
+}
+
+/// Checks a proof while it is parsed, discarding each command of the root proof after it is
+/// checked, instead of keeping the whole proof in memory. This is useful for proofs with a very
+/// large number of steps. Returns `true` if the proof is holey.
+///
+/// This doesn't make the memory used independent of the size of the proof: the id and clause of
+/// each command of the root proof are kept until the end, since any later step may use them as
+/// premises, and all terms that were parsed stay in the term pool. What is saved is everything
+/// else, most notably the commands inside subproofs and the arguments of steps.
+///
+/// Statistics are not collected in this mode. If `only_steps` is set, the proof is instead checked
+/// as in [`check`], since selecting the transitive premises of the steps requires the whole proof.
+pub fn check_streaming<T: io::BufRead>(
+    problem: T,
+    proof: T,
+    options: CarcaraOptions,
+) -> Result<bool, Error> {
+    // A command may be a transitive premise of a selected step that only appears later in the
+    // proof, so when checking it we can't know yet if it is selected
+    if !options.only_steps.is_empty() {
+        return check(problem, proof, options);
+    }
+
+    let config = options.parser_config();
+    let mut pool = ast::PrimitivePool::new();
+    let mut parser = parser::Parser::new(&mut pool, config, problem)?;
+    let (prelude, premises) = parser.parse_problem()?;
+    parser.reset(proof)?;
+
+    // The parser and the checker must use the same term pool, but both need to borrow it mutably
+    // for as long as they exist. So the checker is created with an empty pool, and the two pools
+    // are swapped before and after each command is checked. This only moves the pool itself, and
+    // not the terms in it
+    let mut checker_pool = ast::PrimitivePool::new();
+    let config = options.checker_config();
+    let mut checker = checker::ProofChecker::new(&mut checker_pool, config, &prelude);
+    let mut state = checker::StreamingState::new();
+    let mut commands = parser.parse_proof_streaming();
+    while let Some(command) = commands.next() {
+        let command = command?;
+        std::mem::swap(commands.pool(), checker.pool());
+        let result = checker.check_streaming_command(&mut state, command, &premises);
+        std::mem::swap(commands.pool(), checker.pool());
+        result.map_err(|e| e.with_span(commands.spans()))?;
     }
+    checker.finish_streaming()
 }
 
 pub fn check_parallel<T: io::BufRead>(
@@ -213,23 +806,15 @@ pub fn check_parallel<T: io::BufRead>(
     stack_size: usize,
 ) -> Result<bool, Error> {
     use crate::checker::Scheduler;
-    use std::sync::Arc;
     let mut run_measures: RunMeasurement = RunMeasurement::default();
 
     // Parsing
     let total = Instant::now();
-    let config = parser::Config {
-        apply_function_defs: options.apply_function_defs,
-        expand_lets: options.expand_lets,
-        allow_int_real_subtyping: options.allow_int_real_subtyping,
-    };
+    let config = options.parser_config();
     let (prelude, proof, pool) = parser::parse_instance(problem, proof, config)?;
     run_measures.parsing = total.elapsed();
 
-    let config = checker::Config::new()
-        .strict(options.strict)
-        .ignore_unknown_rules(options.ignore_unknown_rules)
-        .lia_options(options.lia_options);
+    let config = options.checker_config();
 
     // Checking
     let checking = Instant::now();
@@ -243,42 +828,685 @@ pub fn check_parallel<T: io::BufRead>(
         stack_size,
     );
 
-    if options.stats {
-        let mut checker_stats = CheckerStatistics {
-            file_name: "this",
-            elaboration_time: Duration::ZERO,
-            polyeq_time: Duration::ZERO,
-            assume_time: Duration::ZERO,
-            assume_core_time: Duration::ZERO,
-            results: OnlineBenchmarkResults::new(),
+    run_with_stats(
+        &options,
+        run_measures,
+        total,
+        checking,
+        |stats| match stats {
+            Some(stats) => checker.check_with_stats(&proof, &scheduler, stats),
+            None => checker.check(&proof, &scheduler),
+        },
+    )
+}
+
+/// Checks many candidate proofs of the same problem concurrently, each one in its own thread, and
+/// returns the index of the first proof found to be valid, as well as whether that proof is holey.
+///
+/// Once any proof is found to be valid, the checks of the other proofs are interrupted, and this
+/// returns as soon as they stop. Proofs that are still being parsed at that point only stop once
+/// they are parsed. If no proof is valid, an `Error::NoValidProof` is returned. Statistics are
+/// never collected, regardless of the `stats` option.
+///
+/// # Examples
+///
+/// ```
+/// # use carcara::{check_any, CarcaraOptions, Error};
+/// let problem = "(declare-fun p () Bool) (assert p) (assert (not p))";
+/// let wrong = "(assume h1 p) (step t2 (cl) :rule resolution :premises (h1))";
+/// let right = "(assume h1 p) (assume h2 (not p))
+///     (step t3 (cl) :rule resolution :premises (h1 h2))";
+/// let stack_size = 8 * 1024 * 1024;
+///
+/// let proofs = vec![wrong.as_bytes(), right.as_bytes()];
+/// let result = check_any(problem.as_bytes(), proofs, CarcaraOptions::new(), stack_size)?;
+/// assert_eq!(result, (1, false));
+///
+/// // If no proof is valid, the error found in each proof is given along with its index
+/// let proofs = vec![wrong.as_bytes(), wrong.as_bytes()];
+/// let result = check_any(problem.as_bytes(), proofs, CarcaraOptions::new(), stack_size);
+/// let Err(Error::NoValidProof(errors)) = result else {
+///     panic!("expected no valid proof");
+/// };
+/// assert_eq!(errors.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [0, 1]);
+/// # Ok::<(), carcara::Error>(())
+/// ```
+pub fn check_any<T, P>(
+    mut problem: T,
+    proofs: Vec<P>,
+    options: CarcaraOptions,
+    stack_size: usize,
+) -> Result<(usize, bool), Error>
+where
+    T: io::BufRead,
+    P: io::BufRead + Send,
+{
+    use std::sync::mpsc;
+    use std::thread;
+
+    // Each thread parses the problem on its own, so we first read it into a shared buffer
+    let mut problem_buf = Vec::new();
+    problem.read_to_end(&mut problem_buf)?;
+
+    let options = CarcaraOptions { stats: false, ..options };
+
+    // This is set once a valid proof is found, to interrupt the checks of the other proofs
+    let found = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|s| {
+        for (i, proof) in proofs.into_iter().enumerate() {
+            let problem = problem_buf.as_slice();
+            let options = options.clone();
+            let found = found.clone();
+            let sender = sender.clone();
+            thread::Builder::new()
+                .name(format!("candidate-{i}"))
+                .stack_size(stack_size)
+                .spawn_scoped(s, move || {
+                    let result = check_candidate(problem, proof, options, found);
+                    let _ = sender.send((i, result));
+                })?;
+        }
+        drop(sender);
+
+        let mut errors = Vec::new();
+        for (i, result) in &receiver {
+            match result {
+                Ok(is_holey) => {
+                    found.store(true, Ordering::Relaxed);
+                    return Ok((i, is_holey));
+                }
+                Err(e) => errors.push((i, e)),
+            }
+        }
+        errors.sort_by_key(|(i, _)| *i);
+        Err(Error::NoValidProof(errors))
+    })
+}
+
+/// Checks one of the candidate proofs given to [`check_any`]. The check is interrupted with an
+/// `Error::Interrupted` once `found` is set.
+fn check_candidate<P: io::BufRead>(
+    problem: &[u8],
+    proof: P,
+    options: CarcaraOptions,
+    found: Arc<AtomicBool>,
+) -> Result<bool, Error> {
+    use std::ops::ControlFlow;
+
+    let problem: Box<dyn io::BufRead + '_> = Box::new(problem);
+    let proof: Box<dyn io::BufRead + '_> = Box::new(proof);
+    let (prelude, proof, mut pool) =
+        parser::parse_instance(problem, proof, options.parser_config())?;
+
+    let mut checker = checker::ProofChecker::new(&mut pool, options.checker_config(), &prelude);
+    checker.on_progress(move |_| {
+        if found.load(Ordering::Relaxed) {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    });
+    checker.check(&proof)
+}
+
+/// Checks a proof that is split across many parts, for example one file for each lemma. Each part
+/// is given by a name, used in error messages, and its contents.
+///
+/// The parts are parsed in the given order, and the commands in each part may reference the steps
+/// of the previous parts as premises. Each part must close all of the subproofs it opens. The
+/// composed proof is then checked as a whole, so only the last part needs to conclude the empty
+/// clause. Any error found while parsing or checking a part is reported as an `Error::InPart`.
+/// Statistics are never collected, regardless of the `stats` option.
+///
+/// # Examples
+///
+/// ```
+/// # use carcara::{check_composed, CarcaraOptions, Error};
+/// let problem = "
+///     (declare-fun p () Bool)
+///     (declare-fun q () Bool)
+///     (assert (and p q))
+///     (assert (not p))
+/// ";
+/// let lemma = "
+///     (assume h1 (and p q))
+///     (step lemma (cl p) :rule and :premises (h1))
+/// ";
+/// let main = "
+///     (assume h2 (not p))
+///     (step t3 (cl) :rule resolution :premises (lemma h2))
+/// ";
+/// let parts = vec![
+///     ("lemma.alethe".to_owned(), lemma.as_bytes()),
+///     ("main.alethe".to_owned(), main.as_bytes()),
+/// ];
+/// assert!(!check_composed(problem.as_bytes(), parts, CarcaraOptions::new())?);
+///
+/// // Errors are reported with the name of the part in which they were found
+/// let wrong_main = "(step t3 (cl) :rule resolution :premises (lemma))";
+/// let parts = vec![
+///     ("lemma.alethe".to_owned(), lemma.as_bytes()),
+///     ("main.alethe".to_owned(), wrong_main.as_bytes()),
+/// ];
+/// let result = check_composed(problem.as_bytes(), parts, CarcaraOptions::new());
+/// assert!(matches!(result, Err(Error::InPart { part, .. }) if part == "main.alethe"));
+/// # Ok::<(), carcara::Error>(())
+/// ```
+pub fn check_composed<T: io::BufRead>(
+    problem: T,
+    parts: Vec<(String, T)>,
+    options: CarcaraOptions,
+) -> Result<bool, Error> {
+    let config = options.parser_config();
+    let mut pool = ast::PrimitivePool::new();
+    let mut parser = parser::Parser::new(&mut pool, config, problem)?;
+    let (prelude, premises) = parser.parse_problem()?;
+
+    // For each part, we record its name and the number of top-level commands in the proof after it
+    // was parsed, so errors can later be traced back to the part they came from
+    let mut part_ends = Vec::with_capacity(parts.len());
+    let mut commands = Vec::new();
+    for (name, input) in parts {
+        let in_part = |inner| Error::InPart {
+            part: name.clone(),
+            inner: Box::new(inner),
+        };
+        parser.reset(input).map_err(in_part)?;
+        commands = parser.parse_proof_continuation(commands).map_err(in_part)?;
+        part_ends.push((name, commands.len()));
+    }
+    let attributes = parser.take_attributes();
+    let term_attributes = parser.take_term_attributes();
+    let spans = parser.take_spans();
+    let flattened = parser.take_flattened();
+    let proof = ast::Proof {
+        premises,
+        commands,
+        attributes,
+        term_attributes,
+        spans,
+        flattened,
+    };
+
+    let config = options.checker_config();
+    let mut checker = checker::ProofChecker::new(&mut pool, config, &prelude);
+    checker.check(&proof).map_err(|e| {
+        // Errors in a step are attributed to the part containing the top-level command the step
+        // belongs to. Errors not tied to any step are attributed to the last part
+        let index = match &e {
+            Error::Checker { step, .. } => proof.index().path(step).map(|path| path[0]),
+            _ => None,
         };
-        let res = checker.check_with_stats(&proof, &scheduler, &mut checker_stats);
-
-        run_measures.checking = checking.elapsed();
-        run_measures.total = total.elapsed();
-
-        checker_stats.results.add_run_measurement(
-            &("this".to_owned(), 0),
-            RunMeasurement {
-                parsing: run_measures.parsing,
-                checking: run_measures.checking,
-                elaboration: checker_stats.elaboration_time,
-                scheduling: run_measures.scheduling,
-                total: run_measures.total,
-                polyeq: checker_stats.polyeq_time,
-                assume: checker_stats.assume_time,
-                assume_core: checker_stats.assume_core_time,
+        let part = match index {
+            Some(i) => part_ends.iter().find(|(_, end)| i < *end),
+            None => part_ends.last(),
+        };
+        match part {
+            Some((name, _)) => Error::InPart {
+                part: name.clone(),
+                inner: Box::new(e),
             },
-        );
-        // Print the statistics
-        checker_stats.results.print(false);
+            None => e,
+        }
+    })
+}
 
-        res
-    } else {
-        checker.check(&proof, &scheduler)
+/// Checks a proof for each query of an incremental problem. Each query corresponds to a
+/// `check-sat` or `check-sat-assuming` command, and the `i`-th proof is checked against the
+/// assertions that were active at the `i`-th query, in the same way as if the `query` option were
+/// set to `i`. The `query` option itself is ignored.
+///
+/// Errors while parsing the problem are returned in the outer result. Otherwise, this returns the
+/// result of each proof, in the order they were given, including errors while parsing the proof. A
+/// proof that has no corresponding query results in an `UndefinedQuery` error. If the problem has
+/// no `check-sat` commands, it is considered to have a single query, containing all of its
+/// assertions.
+///
+/// # Examples
+///
+/// ```
+/// # use carcara::*;
+/// let problem = "
+///     (declare-fun p () Bool)
+///     (declare-fun q () Bool)
+///     (assert p)
+///     (push 1)
+///     (assert (not p))
+///     (check-sat)
+///     (pop 1)
+///     (check-sat-assuming ((not p) q))
+/// ";
+/// let proof = "
+///     (assume h1 p)
+///     (assume h2 (not p))
+///     (step t3 (cl) :rule resolution :premises (h1 h2))
+/// ";
+/// let proofs = vec![proof.as_bytes(), proof.as_bytes()];
+/// let results = check_queries(problem.as_bytes(), proofs, CarcaraOptions::new())?;
+/// assert!(matches!(results[..], [Ok(false), Ok(false)]));
+///
+/// // The assertion `(not p)` was popped before the third query, so this proof is invalid for it
+/// let proofs = vec![proof.as_bytes(), proof.as_bytes(), proof.as_bytes()];
+/// let problem = format!("{problem} (check-sat)");
+/// let results = check_queries(problem.as_bytes(), proofs, CarcaraOptions::new())?;
+/// assert!(matches!(results[..], [Ok(false), Ok(false), Err(_)]));
+/// # Ok::<(), Error>(())
+/// ```
+pub fn check_queries<T: io::BufRead>(
+    problem: T,
+    proofs: Vec<T>,
+    options: CarcaraOptions,
+) -> Result<Vec<Result<bool, Error>>, Error> {
+    let config = parser::Config {
+        query: None,
+        ..options.parser_config()
+    };
+    let mut pool = ast::PrimitivePool::new();
+    let mut parser = parser::Parser::new(&mut pool, config, problem)?;
+    let (prelude, premises) = parser.parse_problem()?;
+    let mut queries = parser.take_queries();
+    if queries.is_empty() {
+        queries.push(premises);
+    }
+
+    let mut parsed_proofs = Vec::with_capacity(proofs.len());
+    for (i, input) in proofs.into_iter().enumerate() {
+        let parsed = parser
+            .reset_proof(input)
+            .and_then(|()| parser.parse_proof());
+        let proof = parsed.map(|commands| ast::Proof {
+            premises: queries.get(i).cloned().unwrap_or_default(),
+            commands,
+            attributes: parser.take_attributes(),
+            term_attributes: parser.take_term_attributes(),
+            spans: parser.take_spans(),
+            flattened: parser.take_flattened(),
+        });
+        parsed_proofs.push(proof);
+    }
+    drop(parser);
+
+    let config = options.checker_config();
+    let results = parsed_proofs
+        .into_iter()
+        .enumerate()
+        .map(|(i, proof)| {
+            if i >= queries.len() {
+                let error = ParserError::UndefinedQuery(i, queries.len());
+                return Err(Error::Parser(error, (0, 0)));
+            }
+            let proof = proof?;
+            let mut checker = checker::ProofChecker::new(&mut pool, config.clone(), &prelude);
+            checker.check(&proof).map_err(|e| e.with_step_info(&proof))
+        })
+        .collect();
+    Ok(results)
+}
+
+/// A proof that failed to check, together with its frontier. See [`check_with_frontier`].
+#[cfg(feature = "frontier")]
+#[derive(Debug)]
+pub struct Frontier {
+    /// The error that made the check fail.
+    pub error: Error,
+
+    /// The number of commands in the root proof that were checked successfully before the error
+    /// was found.
+    pub num_checked: usize,
+
+    /// An SMT-LIB problem whose assertions are the clauses derived by these commands. A proof of
+    /// this problem can be spliced into the original proof using [`splice_completion`].
+    pub problem: String,
+}
+
+/// Checks a proof, and if it is invalid, returns its frontier: the clauses derived by the commands
+/// in the root proof that were checked before the error was found, written as a new problem. If
+/// the proof is valid, returns `Ok(holey)`, like [`check`].
+///
+/// Errors while parsing the problem or the proof are returned in the outer result.
+///
+/// ```
+/// # use carcara::*;
+/// let problem = "
+///     (declare-fun p () Bool)
+///     (declare-fun q () Bool)
+///     (assert (or p q))
+///     (assert (not p))
+///     (assert (not q))
+/// ";
+/// let proof = "
+///     (assume h1 (or p q))
+///     (assume h2 (not p))
+///     (step t1 (cl p q) :rule or :premises (h1))
+///     (step t2 (cl q) :rule resolution :premises (t1 h2))
+///     (step t3 (cl) :rule resolution :premises (t2))
+/// ";
+/// let options = CarcaraOptions::new();
+/// let Err(frontier) = check_with_frontier(problem.as_bytes(), proof.as_bytes(), options)? else {
+///     panic!("proof should be invalid");
+/// };
+/// assert_eq!(frontier.num_checked, 4);
+/// assert!(frontier.problem.contains("(assert q)"));
+/// # Ok::<(), carcara::Error>(())
+/// ```
+#[cfg(feature = "frontier")]
+pub fn check_with_frontier<T: io::BufRead>(
+    problem: T,
+    proof: T,
+    options: CarcaraOptions,
+) -> Result<Result<bool, Frontier>, Error> {
+    let config = options.parser_config();
+    let (prelude, proof, mut pool) = parser::parse_instance(problem, proof, config)?;
+    let (error, num_checked) = match find_frontier(&mut pool, &prelude, &proof, options)? {
+        Ok(holey) => return Ok(Ok(holey)),
+        Err(e) => e,
+    };
+    let commands = &proof.commands[..num_checked];
+    let terms = frontier::frontier_terms(&mut pool, &proof.premises, commands);
+    let problem = frontier::write_frontier_problem(&prelude, &proof.premises, &terms);
+    Ok(Err(Frontier { error, num_checked, problem }))
+}
+
+/// Splices a completion proof into an invalid proof, resulting in a complete proof of the original
+/// problem. The completion must be a proof of the frontier problem of the invalid proof, as
+/// returned by [`check_with_frontier`]. The commands of the original proof after its frontier are
+/// discarded, and each `assume` command of the completion that assumes a frontier clause is
+/// replaced by steps that derive it from the original proof.
+///
+/// If the original proof is valid, it is returned unchanged. The resulting proof is not checked.
+///
+/// ```
+/// # use carcara::*;
+/// # let problem = "
+/// #     (declare-fun p () Bool)
+/// #     (declare-fun q () Bool)
+/// #     (assert (or p q))
+/// #     (assert (not p))
+/// #     (assert (not q))
+/// # ";
+/// let proof = "
+///     (assume h1 (or p q))
+///     (assume h2 (not p))
+///     (step t1 (cl p q) :rule or :premises (h1))
+///     (step t2 (cl q) :rule resolution :premises (t1 h2))
+///     (step t3 (cl) :rule resolution :premises (t2))
+/// ";
+/// let completion = "
+///     (assume a1 q)
+///     (assume a2 (not q))
+///     (step a3 (cl) :rule resolution :premises (a1 a2))
+/// ";
+/// let options = CarcaraOptions::new();
+/// let spliced =
+///     splice_completion(problem.as_bytes(), proof.as_bytes(), completion.as_bytes(), options)?;
+/// let ids: Vec<_> = spliced.commands.iter().map(|c| c.id()).collect();
+/// assert_eq!(ids, ["h1", "h2", "t1", "t2", "a1", "a2", "a3"]);
+/// # Ok::<(), carcara::Error>(())
+/// ```
+#[cfg(feature = "frontier")]
+pub fn splice_completion<T: io::BufRead>(
+    problem: T,
+    proof: T,
+    mut completion: T,
+    options: CarcaraOptions,
+) -> Result<ast::Proof, Error> {
+    let config = options.parser_config();
+    let (prelude, mut proof, mut pool) = parser::parse_instance(problem, proof, config)?;
+    let num_checked = match find_frontier(&mut pool, &prelude, &proof, options)? {
+        Ok(_) => return Ok(proof),
+        Err((_, num_checked)) => num_checked,
+    };
+    proof.commands.truncate(num_checked);
+    let terms = frontier::frontier_terms(&mut pool, &proof.premises, &proof.commands);
+
+    // The completion is parsed in the context of the frontier problem, so its premises are the
+    // original premises and the frontier terms
+    let frontier_problem = frontier::write_frontier_problem(&prelude, &proof.premises, &terms);
+    let mut completion_input = Vec::new();
+    completion.read_to_end(&mut completion_input)?;
+    let mut parser = parser::Parser::new(&mut pool, config, frontier_problem.as_bytes())?;
+    parser.parse_problem()?;
+    parser.reset(completion_input.as_slice())?;
+    let completion = parser.parse_proof()?;
+    let mut term_attributes = proof.term_attributes;
+    for (term, list) in parser.take_term_attributes() {
+        term_attributes.entry(term).or_insert(list);
+    }
+
+    let prefix_index = ast::ProofIndex::new(&proof.commands);
+    let mut attributes = proof.attributes;
+    attributes.retain(|id, _| prefix_index.contains(id));
+    let commands = frontier::splice(&mut pool, proof.commands, &terms, completion);
+    Ok(ast::Proof {
+        premises: proof.premises,
+        commands,
+        attributes,
+        term_attributes,
+        spans: indexmap::IndexMap::new(),
+        flattened: proof.flattened,
+    })
+}
+
+/// A translation of a proof into a system of constrained Horn clauses. See [`chc_certificate`].
+#[cfg(feature = "exporters")]
+#[derive(Debug, Clone)]
+pub struct ChcCertificate {
+    /// The CHC system, as an SMT-LIB script in the `HORN` logic. Each command in the root proof is
+    /// represented by a predicate, and each step by a Horn clause that derives its predicate from
+    /// the predicates of its premises.
+    pub system: String,
+
+    /// A model of the system, as a series of `define-fun` commands that interpret each predicate as
+    /// the clause derived by its command.
+    pub model: String,
+}
+
+/// Translates a proof into a system of constrained Horn clauses (CHCs), that can be consumed by
+/// CHC solvers and software model checking tools. Alongside the system, this returns a model of it
+/// that is derived from the proof. If the proof is valid, this model satisfies all Horn clauses in
+/// the system, including the query corresponding to the empty clause. The proof is not checked.
+///
+/// ```
+/// # use carcara::*;
+/// let problem = "
+///     (declare-fun p () Bool)
+///     (assert p)
+///     (assert (not p))
+/// ";
+/// let proof = "
+///     (assume h1 p)
+///     (assume h2 (not p))
+///     (step t3 (cl) :rule resolution :premises (h1 h2))
+/// ";
+/// let options = CarcaraOptions::new();
+/// let certificate = chc_certificate(problem.as_bytes(), proof.as_bytes(), options)?;
+/// assert!(certificate.system.starts_with("(set-logic HORN)"));
+/// assert!(certificate
+///     .system
+///     .contains("(assert (forall ((p Bool)) (=> (and (step!h1 p) (step!h2 p)) (step!t3 p))))"));
+/// assert!(certificate.model.contains("(define-fun step!t3 ((p Bool)) Bool false)"));
+/// # Ok::<(), carcara::Error>(())
+/// ```
+#[cfg(feature = "exporters")]
+pub fn chc_certificate<T: io::BufRead>(
+    problem: T,
+    proof: T,
+    options: CarcaraOptions,
+) -> Result<ChcCertificate, Error> {
+    let config = options.parser_config();
+    let (prelude, proof, _) = parser::parse_instance(problem, proof, config)?;
+    let (system, model) = chc::write_chc_system(&prelude, &proof);
+    Ok(ChcCertificate { system, model })
+}
+
+/// Translates a DIMACS CNF formula and a DRAT refutation of it into an Alethe problem and proof,
+/// so that the refutation can be checked or elaborated like any other proof. The variables of the
+/// formula become boolean constants named `x<n>`, and each lemma of the refutation becomes a
+/// `resolution` step whose premises are the clauses used to derive it by unit propagation. Lemmas
+/// that can't be derived by unit propagation, such as RAT lemmas, become `hole` steps. Only the
+/// textual DRAT format is supported.
+///
+/// ```
+/// # use carcara::*;
+/// let cnf = "p cnf 1 2\n1 0\n-1 0\n";
+/// let drat = "0\n";
+/// let (prelude, proof, _) = translate_drat(cnf.as_bytes(), drat.as_bytes())?;
+/// assert_eq!(prelude.function_declarations().len(), 1);
+/// assert_eq!(proof.commands.len(), 3);
+/// # Ok::<(), carcara::Error>(())
+/// ```
+#[cfg(feature = "drat")]
+pub fn translate_drat<T: io::BufRead>(
+    cnf: T,
+    drat: T,
+) -> Result<(ast::ProblemPrelude, ast::Proof, ast::PrimitivePool), Error> {
+    Ok(drat::translate(cnf, drat)?)
+}
+
+/// Checks a DRAT refutation of a DIMACS CNF formula, by translating it into an Alethe proof using
+/// [`translate_drat`]. Returns `true` if the refutation has lemmas that can't be derived by unit
+/// propagation, which are accepted as holes.
+#[cfg(feature = "drat")]
+pub fn check_drat<T: io::BufRead>(cnf: T, drat: T, options: CarcaraOptions) -> Result<bool, Error> {
+    let (prelude, proof, mut pool) = translate_drat(cnf, drat)?;
+    let config = options.checker_config();
+    checker::ProofChecker::new(&mut pool, config, &prelude).check(&proof)
+}
+
+/// Checks a proof, returning the error found and the number of commands in the root proof that were
+/// checked before it, if the proof is invalid. Errors that can't be traced back to a command are
+/// returned in the outer result.
+#[cfg(feature = "frontier")]
+fn find_frontier(
+    pool: &mut ast::PrimitivePool,
+    prelude: &ast::ProblemPrelude,
+    proof: &ast::Proof,
+    options: CarcaraOptions,
+) -> Result<Result<bool, (Error, usize)>, Error> {
+    let config = options.checker_config();
+    let mut checker = checker::ProofChecker::new(pool, config, prelude);
+    let error = match checker.check(proof) {
+        Ok(holey) => return Ok(Ok(holey)),
+        Err(e) => e,
+    };
+
+    // If the proof does not reach the empty clause, all of its commands are valid
+    let num_checked = match &error {
+        Error::Checker { step, .. } => proof.index().path(step).map(|path| path[0]),
+        Error::DoesNotReachEmptyClause => Some(proof.commands.len()),
+        _ => None,
+    };
+    match num_checked {
+        Some(n) => Ok(Err((error, n))),
+        None => Err(error),
+    }
+}
+
+/// A record of how an elaborated proof was produced, so that consumers of the proof can audit it.
+/// This is displayed as a header of SMT-LIB comments, one for each field, which can be printed
+/// before the elaborated proof.
+///
+/// # Examples
+///
+/// ```
+/// # use carcara::*;
+/// let options = CarcaraOptions { sort_premises: true, ..Default::default() };
+/// let provenance = Provenance::new(&options, false);
+/// let header = provenance.to_string();
+/// assert!(header.starts_with("; produced-by: carcara "));
+/// assert!(header.contains("; elaboration-passes: rules, sort_premises, pruning\n"));
+/// assert!(header.contains("; external-solvers: none\n"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    /// The version of Carcara that produced the proof.
+    pub version: String,
+
+    /// A hash of all the options used when elaborating the proof. Two proofs elaborated with the
+    /// same version and the same options have the same hash.
+    pub config_hash: u64,
+
+    /// The elaboration passes that were applied to the proof, in order.
+    pub passes: Vec<String>,
+
+    /// The external solvers used when elaborating the proof, each represented by its path and its
+    /// version, if it could be determined.
+    pub solvers: Vec<(String, Option<String>)>,
+}
+
+impl Provenance {
+    /// Builds the provenance of a proof elaborated with the given options, either in one go or in
+    /// streaming mode, in which the proof is not pruned. If `lia_generic` steps are elaborated
+    /// using an external solver, the solver is run with the `--version` flag to find its version.
+    pub fn new(options: &CarcaraOptions, streaming: bool) -> Self {
+        let enabled = [
+            ("rules", true),
+            ("assume_normalizations", options.assume_normalizations.any()),
+            ("la_infer_args", options.la_infer_args),
+            ("sort_premises", options.sort_premises),
+            ("lia_generic", options.lia_options.is_some()),
+            ("pruning", !streaming),
+        ];
+        let passes = enabled
+            .into_iter()
+            .filter(|(_, is_enabled)| *is_enabled)
+            .map(|(pass, _)| pass.to_owned())
+            .collect();
+        let solvers = options
+            .lia_options
+            .iter()
+            .map(|o| (o.solver.to_string(), solver_version(&o.solver)))
+            .collect();
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            config_hash: fnv_hash(format!("{:?}", options).as_bytes()),
+            passes,
+            solvers,
+        }
+    }
+}
+
+impl std::fmt::Display for Provenance {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "; produced-by: carcara {}", self.version)?;
+        writeln!(f, "; config-hash: {:016x}", self.config_hash)?;
+        writeln!(f, "; elaboration-passes: {}", self.passes.join(", "))?;
+        if self.solvers.is_empty() {
+            return writeln!(f, "; external-solvers: none");
+        }
+        let solvers: Vec<_> = self
+            .solvers
+            .iter()
+            .map(|(path, version)| match version {
+                Some(v) => format!("{} ({})", path, v),
+                None => format!("{} (unknown version)", path),
+            })
+            .collect();
+        writeln!(f, "; external-solvers: {}", solvers.join(", "))
     }
 }
 
+/// Returns the first line printed by a solver when called with the `--version` flag.
+fn solver_version(solver: &str) -> Option<String> {
+    let output = std::process::Command::new(solver)
+        .arg("--version")
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout.lines().map(str::trim).find(|l| !l.is_empty())?;
+    Some(line.to_owned())
+}
+
+/// Computes the 64-bit FNV-1a hash of some bytes. Unlike the hasher in the standard library, this
+/// is guaranteed to give the same results in every platform and Rust version.
+fn fnv_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(feature = "elaborator")]
 pub fn check_and_elaborate<T: io::BufRead>(
     problem: T,
     proof: T,
@@ -288,54 +1516,263 @@ pub fn check_and_elaborate<T: io::BufRead>(
 
     // Parsing
     let total = Instant::now();
-    let config = parser::Config {
-        apply_function_defs: options.apply_function_defs,
-        expand_lets: options.expand_lets,
-        allow_int_real_subtyping: options.allow_int_real_subtyping,
-    };
+    let config = options.parser_config();
     let (prelude, proof, mut pool) = parser::parse_instance(problem, proof, config)?;
     run_measures.parsing = total.elapsed();
 
-    let config = checker::Config::new()
-        .strict(options.strict)
-        .ignore_unknown_rules(options.ignore_unknown_rules)
-        .lia_options(options.lia_options);
+    let config = options.checker_config();
 
     // Checking
     let checking = Instant::now();
     let mut checker = checker::ProofChecker::new(&mut pool, config, &prelude);
-    if options.stats {
-        let mut checker_stats = CheckerStatistics {
-            file_name: "this",
-            elaboration_time: Duration::ZERO,
-            polyeq_time: Duration::ZERO,
-            assume_time: Duration::ZERO,
-            assume_core_time: Duration::ZERO,
-            results: OnlineBenchmarkResults::new(),
-        };
+    let result = run_with_stats(
+        &options,
+        run_measures,
+        total,
+        checking,
+        |stats| match stats {
+            Some(stats) => checker.check_and_elaborate_with_stats(proof, stats),
+            None => checker.check_and_elaborate(proof),
+        },
+    );
 
-        let res = checker.check_and_elaborate_with_stats(proof, &mut checker_stats);
-        run_measures.checking = checking.elapsed();
-        run_measures.total = total.elapsed();
-
-        checker_stats.results.add_run_measurement(
-            &("this".to_owned(), 0),
-            RunMeasurement {
-                parsing: run_measures.parsing,
-                checking: run_measures.checking,
-                elaboration: checker_stats.elaboration_time,
-                scheduling: run_measures.scheduling,
-                total: run_measures.total,
-                polyeq: checker_stats.polyeq_time,
-                assume: checker_stats.assume_time,
-                assume_core: checker_stats.assume_core_time,
-            },
-        );
-        // Print the statistics
-        checker_stats.results.print(false);
+    // The elaborated proof is printed with the terms as they were written in the input
+    result.map(|(is_holey, mut proof)| {
+        proof.restore_flattened(&mut pool);
+        (is_holey, proof)
+    })
+}
 
-        res
-    } else {
-        checker.check_and_elaborate(proof)
+/// Checks and elaborates a proof, like [`check_and_elaborate`], but also returns a record of each
+/// command that the elaborator changed or removed, with the commands that replace it and the
+/// reason for the change. See [`elaborator::StepExplanation`].
+///
+/// Statistics are not collected in this mode.
+#[cfg(feature = "elaborator")]
+pub fn check_and_elaborate_explained<T: io::BufRead>(
+    problem: T,
+    proof: T,
+    options: CarcaraOptions,
+) -> Result<(bool, ast::Proof, Vec<elaborator::StepExplanation>), Error> {
+    let config = options.parser_config();
+    let (prelude, proof, mut pool) = parser::parse_instance(problem, proof, config)?;
+
+    let config = options.checker_config();
+    let (is_holey, mut proof, explanations) =
+        checker::ProofChecker::new(&mut pool, config, &prelude)
+            .check_and_elaborate_explained(proof)?;
+    proof.restore_flattened(&mut pool);
+    Ok((is_holey, proof, explanations))
+}
+
+/// Checks a proof, like [`check`], and returns it with the pivots found by the checker added as
+/// the arguments of each `resolution` and `th_resolution` step that didn't have them. The premises
+/// of these steps may be reordered, so the pivots apply to them in order. All other commands are
+/// unchanged. See [`checker::ProofChecker::check_with_resolution_pivots`].
+pub fn check_with_resolution_pivots<T: io::BufRead>(
+    problem: T,
+    proof: T,
+    options: CarcaraOptions,
+) -> Result<(bool, ast::Proof), Error> {
+    let config = options.parser_config();
+    let (prelude, mut proof, mut pool) = parser::parse_instance(problem, proof, config)?;
+
+    let config = options.checker_config();
+    let (is_holey, pivots) = checker::ProofChecker::new(&mut pool, config, &prelude)
+        .check_with_resolution_pivots(&proof)?;
+    add_resolution_pivots(&mut pool, &mut proof.commands, &pivots);
+    proof.restore_flattened(&mut pool);
+    Ok((is_holey, proof))
+}
+
+fn add_resolution_pivots(
+    pool: &mut dyn ast::TermPool,
+    commands: &mut [ast::ProofCommand],
+    pivots: &indexmap::IndexMap<ast::StepId, checker::ResolutionPivots>,
+) {
+    for command in commands {
+        match command {
+            ast::ProofCommand::Step(step) => {
+                if let Some(found) = pivots.get(&step.id) {
+                    step.premises = found.premises.clone();
+                    step.args = found.to_args(pool);
+                }
+            }
+            ast::ProofCommand::Subproof(s) => add_resolution_pivots(pool, &mut s.commands, pivots),
+            ast::ProofCommand::Assume { .. } => (),
+        }
     }
 }
+
+/// Checks a proof, like [`check`], and reports every command that was only accepted using lenient
+/// reasoning, like implicitly flipped equalities or double negations. Only the rules that are not
+/// checked strictly can be lenient. See [`checker::ProofChecker::check_leniency`].
+///
+/// # Examples
+///
+/// ```
+/// # use carcara::{checker::Leniency, *};
+/// let problem = "
+///     (declare-fun a () Int)
+///     (declare-fun b () Int)
+///     (assert (= a b))
+///     (assert (not (= a b)))
+/// ";
+/// let proof = "
+///     (assume h1 (= b a))
+///     (assume h2 (not (= a b)))
+///     (step t3 (cl (= a b)) :rule symm :premises (h1))
+///     (step t4 (cl) :rule resolution :premises (t3 h2))
+/// ";
+/// let (_, report) = check_with_leniency_report(
+///     problem.as_bytes(),
+///     proof.as_bytes(),
+///     CarcaraOptions::new(),
+/// )?;
+/// assert_eq!(report.steps.len(), 1);
+/// assert_eq!(report.steps[0].id, "h1");
+/// assert_eq!(report.steps[0].leniency, Leniency::EqualityFlip);
+/// # Ok::<(), Error>(())
+/// ```
+pub fn check_with_leniency_report<T: io::BufRead>(
+    problem: T,
+    proof: T,
+    options: CarcaraOptions,
+) -> Result<(bool, checker::LeniencyReport), Error> {
+    let config = options.parser_config();
+    let (prelude, proof, mut pool) = parser::parse_instance(problem, proof, config)?;
+
+    let config = options.checker_config();
+    checker::ProofChecker::new(&mut pool, config, &prelude).check_leniency(&proof)
+}
+
+/// Checks and elaborates a proof, writing each command of the elaborated proof to `dest` as soon as
+/// it is built, instead of keeping the whole elaborated proof in memory. This is useful when
+/// elaborating very large proofs. Returns `true` if the proof is holey.
+///
+/// Unlike [`check_and_elaborate`], the elaborated proof is not pruned, so it may contain steps that
+/// are not needed to reach the empty clause. The `use_sharing`, `version` and `number_format`
+/// arguments have the same meaning as in [`ast::print_proof_for_version`].
+#[cfg(all(feature = "exporters", feature = "elaborator"))]
+pub fn check_and_elaborate_streaming<T: io::BufRead>(
+    problem: T,
+    proof: T,
+    options: CarcaraOptions,
+    dest: &mut dyn io::Write,
+    use_sharing: bool,
+    version: ast::AletheVersion,
+    number_format: ast::NumberFormat,
+) -> Result<bool, Error> {
+    let config = options.parser_config();
+    let (prelude, mut proof, mut pool) = parser::parse_instance(problem, proof, config)?;
+
+    let config = options.checker_config();
+
+    let attributes = std::mem::take(&mut proof.attributes);
+    let term_attributes = std::mem::take(&mut proof.term_attributes);
+    let mut printer = ast::StreamingPrinter::new(
+        dest,
+        &attributes,
+        &term_attributes,
+        use_sharing,
+        version,
+        number_format,
+    );
+    let mut checker = checker::ProofChecker::new(&mut pool, config, &prelude);
+    checker.check_and_elaborate_streaming(proof, |command| printer.write_command(&command))
+}
+
+/// A theory lemma, that is, a clause concluded by a theory rule without using any premises.
+#[derive(Debug, Clone)]
+pub struct TheoryLemma {
+    /// The id of the step that concludes the lemma.
+    pub id: ast::StepId,
+
+    /// The rule used to conclude the lemma.
+    pub rule: ast::Symbol,
+
+    /// The theory the rule belongs to.
+    pub theory: benchmarking::Theory,
+
+    /// The lemma clause.
+    pub clause: Vec<ast::Rc<ast::Term>>,
+
+    /// An SMT-LIB problem that asserts the negation of the lemma, and is therefore unsatisfiable.
+    /// Only the declarations used in the lemma are included.
+    pub problem: String,
+}
+
+/// Checks a proof and extracts all of its theory lemmas, in the order they appear in the proof.
+///
+/// A step is considered to conclude a theory lemma if it uses a rule that does not belong to the
+/// propositional fragment (see [`benchmarking::Theory`]) and has no premises. Steps inside
+/// subproofs are ignored, since their conclusions may depend on the subproof context. If the proof
+/// is not valid, the checking error is returned instead.
+///
+/// # Examples
+///
+/// ```
+/// # use carcara::{benchmarking::Theory, extract_theory_lemmas, CarcaraOptions};
+/// let problem = "
+///     (declare-fun a () Int)
+///     (assert (< a 0))
+///     (assert (> a 0))
+/// ";
+/// let proof = "
+///     (assume h1 (< a 0))
+///     (assume h2 (> a 0))
+///     (step t3 (cl (not (< a 0)) (not (> a 0))) :rule la_generic :args (1 1))
+///     (step t4 (cl) :rule resolution :premises (h1 h2 t3))
+/// ";
+/// let lemmas =
+///     extract_theory_lemmas(problem.as_bytes(), proof.as_bytes(), CarcaraOptions::new())?;
+/// assert_eq!(lemmas.len(), 1);
+/// assert_eq!(lemmas[0].id, "t3");
+/// assert_eq!(lemmas[0].theory, Theory::Arithmetic);
+/// assert!(lemmas[0].problem.contains("(assert (not (not (< a 0))))"));
+/// # Ok::<(), carcara::Error>(())
+/// ```
+pub fn extract_theory_lemmas<T: io::BufRead>(
+    problem: T,
+    proof: T,
+    options: CarcaraOptions,
+) -> Result<Vec<TheoryLemma>, Error> {
+    use benchmarking::Theory;
+    use std::fmt::Write;
+
+    let config = options.parser_config();
+    let (prelude, proof, mut pool) = parser::parse_instance(problem, proof, config)?;
+
+    let config = options.checker_config();
+    checker::ProofChecker::new(&mut pool, config, &prelude).check(&proof)?;
+
+    let lemmas = proof
+        .commands
+        .iter()
+        .filter_map(|command| match command {
+            ast::ProofCommand::Step(step) if step.premises.is_empty() => Some(step),
+            _ => None,
+        })
+        .filter_map(|step| {
+            let theory = Theory::of_rule(&step.rule);
+            if matches!(theory, Theory::Propositional | Theory::Other) {
+                return None;
+            }
+
+            let mut problem = prelude.restricted_to(&step.clause).to_string();
+            let mut assertions = Vec::new();
+            ast::printer::write_lia_smt_instance(&mut assertions, &step.clause, false).unwrap();
+            problem.push_str(&String::from_utf8(assertions).unwrap());
+            writeln!(&mut problem, "(check-sat)").unwrap();
+
+            Some(TheoryLemma {
+                id: step.id.clone(),
+                rule: step.rule,
+                theory,
+                clause: step.clause.clone(),
+                problem,
+            })
+        })
+        .collect();
+    Ok(lemmas)
+}
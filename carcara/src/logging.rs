@@ -0,0 +1,79 @@
+//! Receiving the messages logged by Carcara through a callback.
+//!
+//! Carcara reports warnings and other diagnostic messages using the [`log`] crate. Applications
+//! that can print to the standard error usually install a logger like `env_logger` to display
+//! them, but that is not possible in some environments, like WebAssembly or a program that uses
+//! Carcara through a C interface. Instead, these applications can use [`set_message_callback`] to
+//! receive each message as a [`Message`], and surface it in their own UI.
+
+use log::{Log, Metadata, Record};
+
+pub use log::{Level, LevelFilter};
+
+/// A message logged by Carcara.
+#[derive(Debug, Clone, Copy)]
+pub struct Message<'a> {
+    /// The severity of the message.
+    pub level: Level,
+
+    /// The module that logged the message, like `carcara::checker::lia_generic`. Messages logged
+    /// by other crates are also passed to the callback, so this can be used to filter them out.
+    pub target: &'a str,
+
+    /// The text of the message.
+    pub text: &'a str,
+}
+
+struct CallbackLogger<F> {
+    callback: F,
+}
+
+impl<F: Fn(Message) + Send + Sync> Log for CallbackLogger<F> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let text = record.args().to_string();
+        (self.callback)(Message {
+            level: record.level(),
+            target: record.target(),
+            text: &text,
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs a logger that passes every message with at most the level `max_level` to `callback`.
+///
+/// Like any logger, this can only be installed once per program, so this returns an error if a
+/// logger was already installed.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use carcara::logging::*;
+/// set_message_callback(LevelFilter::Warn, |message| {
+///     if message.target.starts_with("carcara") {
+///         println!("{}: {}", message.level, message.text);
+///     }
+/// })
+/// .expect("a logger was already installed");
+/// ```
+pub fn set_message_callback<F>(
+    max_level: LevelFilter,
+    callback: F,
+) -> Result<(), log::SetLoggerError>
+where
+    F: Fn(Message) + Send + Sync + 'static,
+{
+    // The logger must live until the end of the program, so we leak it
+    let logger: &'static CallbackLogger<F> = Box::leak(Box::new(CallbackLogger { callback }));
+    log::set_logger(logger)?;
+    log::set_max_level(max_level);
+    Ok(())
+}
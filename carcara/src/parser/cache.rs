@@ -0,0 +1,196 @@
+//! A cache for parsed proofs, stored in a binary file next to the proof file (see
+//! [`Proof::serialize`]).
+//!
+//! The cache starts with a key that identifies the proof file by its modification time and size,
+//! the problem by a hash of its contents, and the parser configuration and Carcara version used to
+//! write it. The cache is only used if all of these match, so it is never used for a proof that was
+//! modified, or that would be parsed differently.
+
+use super::{parse_instance, Config, Parser};
+use crate::{
+    ast::{PrimitivePool, ProblemPrelude, Proof},
+    CarcaraResult,
+};
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// Returns the path of the cache file for the given proof file, which is the proof path with a
+/// `.cache` extension appended to it.
+pub fn proof_cache_path(proof_path: &Path) -> PathBuf {
+    let mut path = proof_path.as_os_str().to_owned();
+    path.push(".cache");
+    path.into()
+}
+
+/// Like [`parse_instance`], but caches the parsed proof in a binary file next to the proof file,
+/// at [`proof_cache_path`]. If the cache is up to date, the proof is loaded from it, and `proof` is
+/// never read. Otherwise, the proof is parsed from `proof`, which must contain the contents of
+/// the file at `proof_path`, and the cache is written.
+///
+/// Failing to read or write the cache is not an error: a warning is logged, and the proof is
+/// parsed as usual.
+pub fn parse_instance_cached<T: BufRead>(
+    mut problem: T,
+    proof: T,
+    proof_path: &Path,
+    config: Config,
+) -> CarcaraResult<(ProblemPrelude, Proof, PrimitivePool)> {
+    let mut problem_bytes = Vec::new();
+    problem.read_to_end(&mut problem_bytes)?;
+    let key = match cache_key(&problem_bytes, proof_path, config) {
+        Ok(key) => key,
+        Err(e) => {
+            log::warn!("can't cache proof '{}': {}", proof_path.display(), e);
+            return parse_boxed(&problem_bytes, proof, config);
+        }
+    };
+    let cache_path = proof_cache_path(proof_path);
+
+    let mut pool = PrimitivePool::new();
+    let (prelude, premises) = {
+        let mut parser = Parser::new(&mut pool, config, problem_bytes.as_slice())?;
+        parser.parse_problem()?
+    };
+    match read_cache(&cache_path, &key, &mut pool) {
+        Ok(Some(mut cached)) => {
+            // The premises come from the problem, so we take them from the problem that was just
+            // parsed
+            cached.premises = premises;
+            return Ok((prelude, cached, pool));
+        }
+        Ok(None) => (),
+        Err(e) => log::warn!(
+            "ignoring invalid proof cache '{}': {}",
+            cache_path.display(),
+            e
+        ),
+    }
+
+    // The problem is usually much smaller than the proof, so parsing it again is cheap
+    let (prelude, proof, pool) = parse_boxed(&problem_bytes, proof, config)?;
+    if let Err(e) = write_cache(&cache_path, &key, &proof) {
+        log::warn!(
+            "failed to write proof cache '{}': {}",
+            cache_path.display(),
+            e
+        );
+    }
+    Ok((prelude, proof, pool))
+}
+
+/// Calls `parse_instance` with the problem, that was already read, and the proof. Both are boxed,
+/// since they must be of the same type.
+fn parse_boxed<'a, T: BufRead + 'a>(
+    problem: &'a [u8],
+    proof: T,
+    config: Config,
+) -> CarcaraResult<(ProblemPrelude, Proof, PrimitivePool)> {
+    let problem: Box<dyn BufRead + 'a> = Box::new(problem);
+    parse_instance(problem, Box::new(proof), config)
+}
+
+fn cache_key(problem: &[u8], proof_path: &Path, config: Config) -> io::Result<String> {
+    let metadata = fs::metadata(proof_path)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    let mut hasher = DefaultHasher::new();
+    problem.hash(&mut hasher);
+
+    // The number of threads doesn't change the parsed proof
+    let config = Config { parsing_threads: 0, ..config };
+    Ok(format!(
+        "{} {} {} {:x} {:?}",
+        env!("CARGO_PKG_VERSION"),
+        modified.as_nanos(),
+        metadata.len(),
+        hasher.finish(),
+        config,
+    ))
+}
+
+/// Reads the cached proof, returning `None` if the cache doesn't exist or is outdated.
+fn read_cache(path: &Path, key: &str, pool: &mut PrimitivePool) -> io::Result<Option<Proof>> {
+    let file = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let mut reader = BufReader::new(file);
+    let mut cached_key = Vec::new();
+    reader.read_until(b'\n', &mut cached_key)?;
+    if cached_key.strip_suffix(b"\n") != Some(key.as_bytes()) {
+        return Ok(None);
+    }
+    Proof::deserialize(pool, reader).map(Some)
+}
+
+fn write_cache(path: &Path, key: &str, proof: &Proof) -> io::Result<()> {
+    // The cache is first written to a temporary file, so a concurrent run never reads a cache that
+    // is only partially written
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(format!(".{}.tmp", std::process::id()));
+    let result = write_cache_file(temp_path.as_ref(), key, proof)
+        .and_then(|()| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+fn write_cache_file(path: &Path, key: &str, proof: &Proof) -> io::Result<()> {
+    let mut writer = BufWriter::new(fs::File::create(path)?);
+    writeln!(writer, "{}", key)?;
+    proof.serialize(&mut writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::ProofCommand;
+
+    #[test]
+    fn test_parse_instance_cached() {
+        let dir = std::env::temp_dir().join(format!("carcara-cache-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let proof_path = dir.join("test.alethe");
+        let problem = "(declare-fun p () Bool) (assert p) (assert (not p))";
+        let proof =
+            "(assume h1 p) (assume h2 (not p)) (step t3 (cl) :rule resolution :premises (h1 h2))";
+        fs::write(&proof_path, proof).unwrap();
+
+        let parse = |proof: &str| {
+            parse_instance_cached(
+                problem.as_bytes(),
+                proof.as_bytes(),
+                &proof_path,
+                Config::new(),
+            )
+            .unwrap()
+        };
+        let (_, parsed, _) = parse(proof);
+        assert!(proof_cache_path(&proof_path).exists());
+
+        // If the cache is up to date, the given proof is never read
+        let (_, cached, _) = parse("this is not a proof");
+        let ids: Vec<_> = cached.commands.iter().map(ProofCommand::id).collect();
+        assert_eq!(vec!["h1", "h2", "t3"], ids);
+        assert_eq!(parsed.premises.len(), cached.premises.len());
+
+        // If the proof file changes, the cache is no longer used
+        let proof =
+            "(assume h1 p) (assume h2 (not p)) (step t10 (cl) :rule resolution :premises (h1 h2))";
+        fs::write(&proof_path, proof).unwrap();
+        let (_, parsed, _) = parse(proof);
+        assert_eq!("t10", parsed.commands[2].id());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,7 +1,7 @@
 //! The types for parser errors.
 
 use crate::{
-    ast::{Constant, Sort},
+    ast::{AletheVersion, Constant, Sort},
     parser::Token,
     utils::Range,
 };
@@ -53,10 +53,22 @@ pub enum ParserError {
     #[error("sort error: {0}")]
     SortError(#[from] SortError),
 
-    /// Expected BvSort
+    /// Expected `BvSort`
     #[error("expected bitvector sort, got '{0}'")]
     ExpectedBvSort(Sort),
 
+    /// Expected an array sort.
+    #[error("expected array sort, got '{0}'")]
+    ExpectedArraySort(Sort),
+
+    /// Expected a floating-point sort.
+    #[error("expected floating-point sort, got '{0}'")]
+    ExpectedFpSort(Sort),
+
+    /// The code point given to a `char` constant is not a valid character code point.
+    #[error("invalid character code point: '{0}'")]
+    InvalidCodePoint(Constant),
+
     // Expected Constant::Integer, got other Constant
     #[error("expected Constant of type Integer, got '{0}'")]
     ExpectedIntegerConstant(Constant),
@@ -69,6 +81,10 @@ pub enum ParserError {
     #[error("identifier '{0}' is not defined")]
     UndefinedIden(String),
 
+    /// The sort parameters of a polymorphic function could not be inferred from its arguments.
+    #[error("could not infer the sort parameters of '{0}', use `as` to ascribe its sort")]
+    AmbiguousSortParams(String),
+
     /// The parser encountered a sort that was not defined.
     #[error("sort '{0}' is not defined")]
     UndefinedSort(String),
@@ -77,6 +93,11 @@ pub enum ParserError {
     #[error("step id '{0}' is not defined")]
     UndefinedStepIndex(String),
 
+    /// The query selected with the `query` option does not exist. The second value is the number
+    /// of queries in the problem.
+    #[error("query {0} does not exist, the problem has {1} queries")]
+    UndefinedQuery(usize, usize),
+
     /// The wrong number of arguments was given to a function, operator or sort.
     #[error("expected {0} arguments, got {1}")]
     WrongNumberOfArgs(Range, usize),
@@ -86,7 +107,7 @@ pub enum ParserError {
     WrongValueOfArgs(Range, usize),
 
     #[error("extract arguments do not follow restrictions. Expected: {2} > {0} and {0} >= {1} and {1} >= 0")]
-    InvalidExtractArgs(usize, usize, usize),
+    InvalidExtractArgs(Integer, Integer, Integer),
 
     /// A step id was used in more than one step.
     #[error("step id '{0}' was repeated")]
@@ -108,6 +129,37 @@ pub enum ParserError {
     /// The parser encountered the end of the input while it was still inside a subproof.
     #[error("subproof '{0}' was not closed")]
     UnclosedSubproof(String),
+
+    /// The input exceeds one of the limits set in the parser configuration.
+    #[error("input exceeds the limit of {1} {0}")]
+    LimitExceeded(ParserLimitKind, usize),
+
+    /// The progress callback asked the parser to stop. See [`crate::parser::Parser::on_progress`].
+    #[error("parsing was interrupted")]
+    Interrupted,
+
+    /// The proof uses a construct that is not part of the Alethe version selected in the parser
+    /// configuration. See [`crate::parser::Config::alethe_version`].
+    #[error("{0} are not supported in Alethe version '{1}'")]
+    UnsupportedInVersion(&'static str, AletheVersion),
+}
+
+/// The kinds of limits that can be imposed on the parser input. See [`crate::parser::ParserLimits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParserLimitKind {
+    TermDepth,
+    TermCount,
+    FileSize,
+}
+
+impl fmt::Display for ParserLimitKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParserLimitKind::TermDepth => write!(f, "levels of term nesting"),
+            ParserLimitKind::TermCount => write!(f, "parsed terms"),
+            ParserLimitKind::FileSize => write!(f, "bytes"),
+        }
+    }
 }
 
 /// Returns an error if the length of `sequence` is not in the `expected` range.
@@ -129,21 +181,27 @@ where
     R: Into<Range>,
 {
     let range = range.into();
-    let mut wrong_value = usize::MAX;
     for x in sequence {
         if let Constant::Integer(i) = x {
-            let value = i.to_usize().unwrap();
-            if !range.contains(value) {
-                wrong_value = value;
-                break;
+            // Values that don't fit in a `usize` are never valid arguments
+            let value = i.to_usize().unwrap_or(usize::MAX);
+            if value == usize::MAX || !range.contains(value) {
+                return Err(ParserError::WrongValueOfArgs(range, value));
             }
         }
     }
-    if wrong_value == usize::MAX {
-        Ok(())
-    } else {
-        Err(ParserError::WrongValueOfArgs(range, wrong_value))
+    Ok(())
+}
+
+/// Returns an error if the sorts in `sequence` are not all the same floating-point sort.
+pub fn assert_same_fp_sort(sequence: &[&Sort]) -> Result<(), ParserError> {
+    if let Some(first) = sequence.first() {
+        if !matches!(first, Sort::FloatingPoint(_, _)) {
+            return Err(ParserError::ExpectedFpSort((*first).clone()));
+        }
     }
+    SortError::assert_all_eq(sequence)?;
+    Ok(())
 }
 
 /// An error in sort checking.
@@ -0,0 +1,188 @@
+//! Inference of declarations for symbols that are used without being declared. This is used to
+//! check proofs without their problem (see `crate::check_proof_only`), when the declarations in the
+//! problem are not available.
+//!
+//! A symbol is declared when it is first used. If it is applied to arguments, it is declared as a
+//! function whose parameter sorts are the sorts of these arguments. Its sort (or, for functions,
+//! its return sort) starts as the default sort, but is changed to the sort expected by the first
+//! operator or function that receives it as an argument. For example, in `(+ x 1)`, `x` is declared
+//! as an `Int`. After the command in which the symbol first appears, its sort no longer changes.
+
+use super::{error::ParserError, Parser};
+use crate::ast::{Operator, Rc, Sort, Symbol, Term, TermPool};
+use std::io::BufRead;
+
+impl<'a, R: BufRead, P: TermPool> Parser<'a, R, P> {
+    /// Enables the inference of declarations for symbols that are used without being declared.
+    /// When the sort of a symbol can't be inferred from its use, it is given `default_sort`.
+    pub fn infer_declarations(&mut self, default_sort: Sort) {
+        self.inferred_sort = Some(self.pool.add(Term::Sort(default_sort)));
+    }
+
+    /// Returns the declarations that were inferred so far, as pairs of a symbol and its sort, in
+    /// the order in which the symbols were first used.
+    pub fn inferred_declarations(&self) -> Vec<(String, Rc<Term>)> {
+        let decls = &self.state.inferred_symbols;
+        decls
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.clone()))
+            .collect()
+    }
+
+    /// Returns `true` if declarations are being inferred and `symbol` is not declared.
+    pub(super) fn should_infer(&self, symbol: &str) -> bool {
+        self.inferred_sort.is_some() && self.state.symbol_table.get(&Symbol::new(symbol)).is_none()
+    }
+
+    /// Declares an inferred symbol. The declaration is added to the outermost scope, so the symbol
+    /// remains declared after the subproof in which it first appears is closed.
+    fn declare_inferred(&mut self, name: Symbol, sort: Rc<Term>) -> Rc<Term> {
+        self.state.symbol_table.insert_global(name, sort.clone());
+        self.state.inferred_symbols.insert(name, sort.clone());
+        self.pool.add(Term::Var(name, sort))
+    }
+
+    /// Declares a symbol that is used as a constant for the first time.
+    pub(super) fn make_inferred_var(&mut self, name: Symbol) -> Rc<Term> {
+        let sort = self.inferred_sort.clone().unwrap();
+        self.state.pending_inferred.insert(name);
+        self.declare_inferred(name, sort)
+    }
+
+    /// Declares a symbol that is applied to `args` for the first time, and constructs the
+    /// application.
+    pub(super) fn make_inferred_app(
+        &mut self,
+        name: Symbol,
+        args: Vec<Rc<Term>>,
+    ) -> Result<Rc<Term>, ParserError> {
+        let mut sorts: Vec<_> = args.iter().map(|a| self.pool.sort(a)).collect();
+        sorts.push(self.inferred_sort.clone().unwrap());
+        let sort = self.pool.add(Term::Sort(Sort::Function(sorts)));
+        self.state.pending_inferred.insert(name);
+        let function = self.declare_inferred(name, sort);
+        self.make_app(function, args)
+    }
+
+    /// If `term` is an inferred symbol, or an application of one, returns the symbol and whether
+    /// it is an application. Symbols that are shadowed by a local binding are ignored.
+    fn inferred_symbol(&self, term: &Term) -> Option<(Symbol, bool)> {
+        let (name, is_app) = match term {
+            Term::Var(name, _) => (name, false),
+            Term::App(f, _) => match f.as_ref() {
+                Term::Var(name, _) => (name, true),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        if !self.state.inferred_symbols.contains_key(name) {
+            return None;
+        }
+        match self.state.symbol_table.get_with_depth(name) {
+            Some((0, _)) => Some((*name, is_app)),
+            _ => None,
+        }
+    }
+
+    /// Returns the sort of `term`, unless it is an inferred symbol whose sort may still change.
+    fn known_sort(&self, term: &Rc<Term>) -> Option<Rc<Term>> {
+        match self.inferred_symbol(term) {
+            Some((name, _)) if self.state.pending_inferred.contains(&name) => None,
+            _ => Some(self.pool.sort(term)),
+        }
+    }
+
+    /// If `arg` is an inferred symbol whose sort may still change, gives it the sort `expected`.
+    /// In any case, if `arg` uses an inferred symbol with an outdated sort, it is rebuilt using the
+    /// current sort of the symbol.
+    fn resolve_inferred_arg(&mut self, arg: &mut Rc<Term>, expected: Option<&Rc<Term>>) {
+        let (name, is_app) = match self.inferred_symbol(arg) {
+            Some(s) => s,
+            None => return,
+        };
+        let mut sort = self.state.inferred_symbols[&name].clone();
+        if let Some(expected) = expected {
+            if self.state.pending_inferred.swap_remove(&name) {
+                // For functions, the expected sort is the return sort
+                let new_sort = match sort.as_sort().unwrap() {
+                    Sort::Function(sorts) if is_app => {
+                        let mut sorts = sorts.clone();
+                        *sorts.last_mut().unwrap() = expected.clone();
+                        self.pool.add(Term::Sort(Sort::Function(sorts)))
+                    }
+                    _ => expected.clone(),
+                };
+                if new_sort != sort {
+                    self.declare_inferred(name, new_sort.clone());
+                    sort = new_sort;
+                }
+            }
+        }
+        let symbol = self.pool.add(Term::Var(name, sort));
+        *arg = match arg.as_ref() {
+            Term::App(_, args) => self.pool.add(Term::App(symbol, args.clone())),
+            _ => symbol,
+        };
+    }
+
+    /// Resolves the inferred symbols in the arguments of an application of `op`, using the sorts
+    /// the operator expects.
+    pub(super) fn resolve_inferred_op_args(&mut self, op: Operator, args: &mut [Rc<Term>]) {
+        let [bool_sort, int_sort, real_sort] =
+            [Sort::Bool, Sort::Int, Sort::Real].map(|s| self.pool.add(Term::Sort(s)));
+        let first_known = |p: &Self, args: &[Rc<Term>]| args.iter().find_map(|a| p.known_sort(a));
+        let expected: Vec<_> = match op {
+            Operator::Not | Operator::Implies | Operator::And | Operator::Or | Operator::Xor => {
+                vec![Some(bool_sort); args.len()]
+            }
+            Operator::Equals | Operator::Distinct => vec![first_known(self, args); args.len()],
+            Operator::Ite => {
+                let branch_sort = first_known(self, args.get(1..).unwrap_or_default());
+                let mut expected = vec![branch_sort; args.len()];
+                if let Some(first) = expected.first_mut() {
+                    *first = Some(bool_sort);
+                }
+                expected
+            }
+            Operator::Add
+            | Operator::Sub
+            | Operator::Mult
+            | Operator::LessThan
+            | Operator::GreaterThan
+            | Operator::LessEq
+            | Operator::GreaterEq => {
+                // If none of the arguments has a known arithmetic sort, we assume they are `Int`s
+                let sort = first_known(self, args)
+                    .filter(|s| s == &int_sort || s == &real_sort)
+                    .unwrap_or(int_sort);
+                vec![Some(sort); args.len()]
+            }
+            Operator::IntDiv | Operator::Mod | Operator::Abs | Operator::ToReal => {
+                vec![Some(int_sort); args.len()]
+            }
+            Operator::RealDiv | Operator::ToInt | Operator::IsInt => {
+                vec![Some(real_sort); args.len()]
+            }
+            _ => vec![None; args.len()],
+        };
+        for (arg, expected) in args.iter_mut().zip(expected) {
+            self.resolve_inferred_arg(arg, expected.as_ref());
+        }
+    }
+
+    /// Resolves the inferred symbols in the arguments of an application of `function`, whose
+    /// parameter and return sorts are `sorts`.
+    pub(super) fn resolve_inferred_app_args(
+        &mut self,
+        function: &Rc<Term>,
+        sorts: &[Rc<Term>],
+        args: &mut [Rc<Term>],
+    ) {
+        // If the function itself is still being inferred, its parameter sorts were taken from the
+        // arguments, so they say nothing about them
+        let is_known = self.known_sort(function).is_some();
+        for (arg, sort) in args.iter_mut().zip(sorts) {
+            self.resolve_inferred_arg(arg, is_known.then_some(sort));
+        }
+    }
+}
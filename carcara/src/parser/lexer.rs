@@ -1,6 +1,10 @@
 //! A lexer for the SMT-LIB and Alethe formats.
 
-use crate::{parser::ParserError, utils::is_symbol_character, CarcaraResult, Error};
+use crate::{
+    parser::{ParserError, ParserLimitKind},
+    utils::is_symbol_character,
+    CarcaraResult, Error,
+};
 use rug::{ops::Pow, Integer, Rational};
 use std::{
     io::{self, BufRead},
@@ -96,15 +100,42 @@ pub enum Reserved {
     /// The `declare-sort` reserved word.
     DeclareSort,
 
+    /// The `declare-datatypes` reserved word.
+    DeclareDatatypes,
+
+    /// The `declare-datatype` reserved word.
+    DeclareDatatype,
+
     /// The `define-fun` reserved word.
     DefineFun,
 
+    /// The `define-const` reserved word.
+    DefineConst,
+
+    /// The `define-fun-rec` reserved word.
+    DefineFunRec,
+
+    /// The `define-funs-rec` reserved word.
+    DefineFunsRec,
+
+    /// The `define-sort` reserved word.
+    DefineSort,
+
     /// The `assert` reserved word.
     Assert,
 
+    /// The `check-sat` reserved word.
+    CheckSat,
+
     /// The `check-sat-assuming` reserved word.
     CheckSatAssuming,
 
+    /// The `push` reserved word.
+    Push,
+
+    /// The `pop` reserved word.
+    Pop,
+
     /// The `set-logic` reserved word.
     SetLogic,
 }
@@ -126,50 +157,115 @@ impl_str_conversion_traits!(Reserved {
     DeclareFun: "declare-fun",
     DeclareConst: "declare-const",
     DeclareSort: "declare-sort",
+    DeclareDatatypes: "declare-datatypes",
+    DeclareDatatype: "declare-datatype",
     DefineFun: "define-fun",
+    DefineConst: "define-const",
+    DefineFunRec: "define-fun-rec",
+    DefineFunsRec: "define-funs-rec",
+    DefineSort: "define-sort",
     Assert: "assert",
+    CheckSat: "check-sat",
     CheckSatAssuming: "check-sat-assuming",
+    Push: "push",
+    Pop: "pop",
     SetLogic: "set-logic",
 });
 
 /// Represents a position (line and column numbers) in the source input.
 pub type Position = (usize, usize);
 
+/// Represents a region of the source input, such as the text of a proof command.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// The byte offsets where the region starts and ends. The end offset is exclusive.
+    pub bytes: (usize, usize),
+
+    /// The position of the first character in the region.
+    pub start: Position,
+
+    /// The position just after the last character in the region.
+    pub end: Position,
+}
+
+impl Span {
+    /// Returns the span that starts at the start of `self` and ends at the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            bytes: (self.bytes.0, other.bytes.1),
+            start: self.start,
+            end: other.end,
+        }
+    }
+
+    /// Shifts the span by the given number of lines and bytes. This is used for spans found in a
+    /// part of the input that was lexed separately, and that starts at the beginning of a line.
+    pub(crate) fn shift(self, lines: usize, bytes: usize) -> Span {
+        Span {
+            bytes: (self.bytes.0 + bytes, self.bytes.1 + bytes),
+            start: (self.start.0 + lines, self.start.1),
+            end: (self.end.0 + lines, self.end.1),
+        }
+    }
+}
+
 /// A lexer for the SMT-LIB and Alethe formats.
 pub struct Lexer<R> {
     input: R,
     current_line: Option<std::vec::IntoIter<char>>,
     current_char: Option<char>,
     position: Position,
+
+    /// The byte offset of the current character in the input source.
+    offset: usize,
+
+    /// The span of the last token read.
+    last_span: Span,
+
+    /// The maximum number of bytes that can be read from the input source, if any.
+    max_size: Option<usize>,
+
+    /// The number of bytes read from the input source so far.
+    read_bytes: usize,
 }
 
 impl<R: BufRead> Lexer<R> {
     /// Constructs a new `Lexer` from a type that implements `BufRead`.
     ///
     /// This operation can fail if there is an IO error on the first token.
-    pub fn new(mut input: R) -> io::Result<Self> {
-        let mut buf = String::new();
-        let read = input.read_line(&mut buf)?;
-        if read == 0 {
-            Ok(Lexer {
-                input,
-                current_line: None,
-                current_char: None,
-                position: (0, 0),
-            })
-        } else {
-            let mut line = buf.chars().collect::<Vec<_>>().into_iter();
-            let current_char = line.next();
-            Ok(Lexer {
-                input,
-                current_line: Some(line),
-                current_char,
-                position: (1, 1),
-            })
+    pub fn new(input: R) -> io::Result<Self> {
+        Self::with_max_size(input, None)
+    }
+
+    /// Constructs a new `Lexer` that reads at most `max_size` bytes from the input source. If the
+    /// input is larger than that, reading any token after the limit results in a `LimitExceeded`
+    /// error.
+    ///
+    /// This operation can fail if there is an IO error on the first token.
+    pub fn with_max_size(input: R, max_size: Option<usize>) -> io::Result<Self> {
+        let mut lexer = Lexer {
+            input,
+            current_line: None,
+            current_char: None,
+            position: (0, 0),
+            offset: 0,
+            last_span: Span::default(),
+            max_size,
+            read_bytes: 0,
+        };
+        lexer.next_line()?;
+        if let Some(line) = &mut lexer.current_line {
+            lexer.current_char = line.next();
+            lexer.position.1 = 1;
         }
+        Ok(lexer)
+    }
+
+    /// Returns `true` if the input source is larger than the maximum size.
+    fn is_size_exceeded(&self) -> bool {
+        self.max_size.is_some_and(|max| self.read_bytes > max)
     }
 
-    /// Advances the lexer by one character, and returns the previous `current_char`.
     fn next_char(&mut self) -> io::Result<Option<char>> {
         // If there are no more characters in the current line, go to the next line
         if let Some(line) = &self.current_line {
@@ -185,13 +281,33 @@ impl<R: BufRead> Lexer<R> {
             None
         };
         let old = std::mem::replace(&mut self.current_char, new);
+        if let Some(c) = old {
+            self.offset += c.len_utf8();
+        }
         Ok(old)
     }
 
     /// Advances the lexer by one line, discarding the remaining contents of the current line.
     fn next_line(&mut self) -> io::Result<()> {
-        let mut buf = String::new();
-        let read = self.input.read_line(&mut buf)?;
+        if let Some(line) = &self.current_line {
+            self.offset += line.as_slice().iter().map(|c| c.len_utf8()).sum::<usize>();
+        }
+        let mut buf = Vec::new();
+        let read = match self.max_size {
+            // We read at most one byte past the limit, so we can tell that it was exceeded
+            Some(max) => {
+                let remaining = max.saturating_add(1).saturating_sub(self.read_bytes) as u64;
+                io::Read::take(&mut self.input, remaining).read_until(b'\n', &mut buf)?
+            }
+            None => self.input.read_until(b'\n', &mut buf)?,
+        };
+        self.read_bytes += read;
+        let buf = match String::from_utf8(buf) {
+            Ok(buf) => buf,
+            // If the limit was exceeded, the line may have been cut in the middle of a character
+            Err(e) if self.is_size_exceeded() => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        };
         if read == 0 {
             self.current_line = None;
         } else {
@@ -244,10 +360,28 @@ impl<R: BufRead> Lexer<R> {
         Ok(())
     }
 
+    /// Returns the position of the current character in the input source.
+    pub(crate) fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Returns the span of the last token read.
+    pub(crate) fn last_token_span(&self) -> Span {
+        self.last_span
+    }
+
+    /// Skips the current character. This is used to recover from errors in which the lexer
+    /// didn't consume any characters, like unexpected characters.
+    pub(crate) fn skip_char(&mut self) -> io::Result<()> {
+        self.next_char()?;
+        Ok(())
+    }
+
     /// Reads a token from the input source.
     pub fn next_token(&mut self) -> CarcaraResult<(Token, Position)> {
         self.consume_whitespace()?;
         let start_position = self.position;
+        let start_offset = self.offset;
         let token = match self.current_char {
             Some('(') => {
                 self.next_char()?;
@@ -269,6 +403,16 @@ impl<R: BufRead> Lexer<R> {
                 self.position,
             )),
         }?;
+        if self.is_size_exceeded() {
+            let error =
+                ParserError::LimitExceeded(ParserLimitKind::FileSize, self.max_size.unwrap());
+            return Err(Error::Parser(error, start_position));
+        }
+        self.last_span = Span {
+            bytes: (start_offset, self.offset),
+            start: start_position,
+            end: self.position,
+        };
         Ok((token, start_position))
     }
 
@@ -1,29 +1,74 @@
 //! A parser for the Alethe proof format.
 
+mod cache;
 mod error;
+mod inference;
 mod lexer;
+mod parallel;
+pub(crate) mod sort_check;
 pub(crate) mod tests;
 
-pub use error::{ParserError, SortError};
-pub use lexer::{Lexer, Position, Reserved, Token};
+pub use cache::{parse_instance_cached, proof_cache_path};
+pub use error::{ParserError, ParserLimitKind, SortError};
+pub use lexer::{Lexer, Position, Reserved, Span, Token};
 
-use crate::{
-    ast::*,
-    utils::{HashCache, HashMapStack},
-    CarcaraResult, Error,
-};
+use crate::{ast::*, utils::HashMapStack, CarcaraResult, Error};
 use error::assert_num_args;
 use indexmap::{IndexMap, IndexSet};
 use rug::Integer;
-use std::{io::BufRead, str::FromStr};
+use std::{
+    io::{self, BufRead},
+    ops::ControlFlow,
+    str::FromStr,
+};
 
 use self::error::assert_indexed_op_args_value;
+use sort_check::{check_app, check_indexed_op, check_op, check_param_op};
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Config {
     pub apply_function_defs: bool,
     pub expand_lets: bool,
     pub allow_int_real_subtyping: bool,
+
+    /// The index of the query whose premises are returned by `Parser::parse_problem`, counting
+    /// `check-sat` and `check-sat-assuming` commands from zero. If `None`, the last query is used.
+    pub query: Option<usize>,
+
+    /// The number of threads used by `parse_instance` to parse the proof. If this is greater than
+    /// one, the proof is split in chunks that are parsed in parallel (see
+    /// [`Parser::parse_proof_parallel`]). Otherwise, the proof is parsed sequentially.
+    pub parsing_threads: usize,
+
+    /// The limits on the resources used to parse the input. See [`ParserLimits`].
+    pub limits: ParserLimits,
+
+    /// If `true`, nested applications of associative operators are flattened when parsed, so
+    /// `(and a (and b c))` becomes `(and a b c)`. The terms as they were written are recorded in
+    /// [`Proof::flattened`], so they can be restored when printing the proof. This changes the
+    /// terms that rules see, so it must be disabled when checking strictly against the
+    /// specification.
+    pub flatten_assoc_ops: bool,
+
+    /// The version of the Alethe format the proof is written in. If this is `Some`, constructs
+    /// that are not part of that version are rejected: assignments in `anchor` arguments must be
+    /// of the form `(:= <symbol> <term>)` in the legacy version, and of the form
+    /// `(:= (<symbol> <sort>) <term>)` in the current one, where assign style step arguments are
+    /// also not allowed. If this is `None`, both syntaxes are accepted.
+    pub alethe_version: Option<AletheVersion>,
+
+    /// If `true`, some non-standard constructs emitted by veriT are accepted, and normalized into
+    /// standard Alethe. Currently, these are `anchor` commands where the `:args` attribute comes
+    /// before the `:step` attribute, and `step` commands whose conclusion is written as a single
+    /// term instead of a `cl` clause, in which case `false` stands for the empty clause.
+    pub verit_compat: bool,
+
+    /// If `true`, some non-standard constructs emitted by `SMTInterpol` are accepted, and
+    /// normalized into standard Alethe. Currently, these are numerals used as command ids, as in
+    /// `(step 12 ...)`, which are kept as symbols with the same name, empty `:premises`, `:args`
+    /// and `:discharge` lists, and `step` commands where these attributes are written in any
+    /// order.
+    pub smtinterpol_compat: bool,
 }
 
 impl Config {
@@ -32,6 +77,39 @@ impl Config {
     }
 }
 
+/// Limits on the size of the parser input, used to protect services that embed the checker from
+/// adversarial inputs. An input that exceeds them is rejected with a `LimitExceeded` error. By
+/// default, no limits are imposed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ParserLimits {
+    /// The maximum nesting depth of a term, counting each application, binder or `let` term as
+    /// one level.
+    pub max_term_depth: Option<usize>,
+
+    /// The maximum number of terms parsed, counting each occurrence of a subterm separately. When
+    /// the proof is parsed in parallel, this applies to each chunk of the proof separately.
+    pub max_term_count: Option<usize>,
+
+    /// The maximum size, in bytes, of each input, that is, of the problem and of the proof.
+    pub max_file_size: Option<usize>,
+}
+
+/// The progress of the parser through a proof, reported to the callback set with
+/// [`Parser::on_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseProgress {
+    /// The number of bytes of the current input consumed so far. This restarts from zero when the
+    /// input is changed using [`Parser::reset`].
+    pub bytes_consumed: usize,
+
+    /// The number of `assume` and `step` commands parsed so far, including the ones inside
+    /// subproofs.
+    pub commands_parsed: usize,
+}
+
+/// A callback that receives the progress of the parser. See [`Parser::on_progress`].
+type ProgressCallback = Box<dyn FnMut(ParseProgress) -> ControlFlow<()>>;
+
 /// Parses an SMT problem instance (in the SMT-LIB format) and its associated proof (in the Alethe
 /// format).
 ///
@@ -39,37 +117,168 @@ impl Config {
 /// implements `BufRead`.
 pub fn parse_instance<T: BufRead>(
     problem: T,
-    proof: T,
+    mut proof: T,
     config: Config,
 ) -> CarcaraResult<(ProblemPrelude, Proof, PrimitivePool)> {
     let mut pool = PrimitivePool::new();
     let mut parser = Parser::new(&mut pool, config, problem)?;
     let (prelude, premises) = parser.parse_problem()?;
-    parser.reset(proof)?;
-    let commands = parser.parse_proof()?;
-
-    let proof = Proof { premises, commands };
+    let commands = if config.parsing_threads > 1 {
+        let mut input = Vec::new();
+        proof.read_to_end(&mut input)?;
+        parser.parse_proof_parallel(&input, config.parsing_threads)?
+    } else {
+        parser.reset(proof)?;
+        parser.parse_proof()?
+    };
+    let attributes = parser.take_attributes();
+    let term_attributes = parser.take_term_attributes();
+    let spans = parser.take_spans();
+    let flattened = parser.take_flattened();
+
+    let proof = Proof {
+        premises,
+        commands,
+        attributes,
+        term_attributes,
+        spans,
+        flattened,
+    };
     Ok((prelude, proof, pool))
 }
 
+/// Extracts the proof from a full solver log, which may also contain banners, models or
+/// statistics.
+///
+/// The proof is taken to be all lines after the first line equal to `start`, up to (but not
+/// including) the next line equal to `end`. If `end` is `None`, all remaining lines are taken.
+/// Leading and trailing whitespace is ignored when comparing lines to the markers. Returns an error
+/// if either marker is not found.
+///
+/// # Examples
+///
+/// ```
+/// # use carcara::parser::extract_proof_from_log;
+/// let log = "solver v1.0\nunsat\n(step t1 (cl) :rule hole)\n--- statistics ---\n";
+/// let proof = extract_proof_from_log(log.as_bytes(), "unsat", Some("--- statistics ---"))?;
+/// assert_eq!(proof, b"(step t1 (cl) :rule hole)\n");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn extract_proof_from_log<R: BufRead>(
+    log: R,
+    start: &str,
+    end: Option<&str>,
+) -> io::Result<Vec<u8>> {
+    fn not_found(marker: &str) -> io::Error {
+        let message = format!("marker '{}' was not found in solver log", marker);
+        io::Error::new(io::ErrorKind::InvalidData, message)
+    }
+
+    let mut lines = log.lines();
+    loop {
+        match lines.next().transpose()? {
+            Some(line) if line.trim() == start => break,
+            Some(_) => (),
+            None => return Err(not_found(start)),
+        }
+    }
+
+    let mut result = Vec::new();
+    for line in lines {
+        let line = line?;
+        if Some(line.trim()) == end {
+            return Ok(result);
+        }
+        result.extend_from_slice(line.as_bytes());
+        result.push(b'\n');
+    }
+    match end {
+        Some(end) => Err(not_found(end)),
+        None => Ok(result),
+    }
+}
+
+/// Splits an input that contains both the SMT problem and its proof, such as the concatenation of
+/// a problem file and the output of a solver. The problem is taken to be all lines before the first
+/// line equal to `separator`, ignoring leading and trailing whitespace. Returns the problem, and
+/// the input positioned at the line after the separator, from which the proof can be read without
+/// loading it all in memory. Returns an error if the separator is not found.
+///
+/// # Examples
+///
+/// ```
+/// # use carcara::parser::split_combined_input;
+/// # use std::io::Read;
+/// let input = "(assert false)\n(check-sat)\nunsat\n(assume h1 false)\n";
+/// let (problem, mut rest) = split_combined_input(input.as_bytes(), "unsat")?;
+/// assert_eq!(problem, b"(assert false)\n(check-sat)\n");
+/// let mut proof = String::new();
+/// rest.read_to_string(&mut proof)?;
+/// assert_eq!(proof, "(assume h1 false)\n");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn split_combined_input<R: BufRead>(mut input: R, separator: &str) -> io::Result<(Vec<u8>, R)> {
+    let mut problem = Vec::new();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            let message = format!("separator '{}' was not found in input", separator);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+        if line.trim() == separator {
+            return Ok((problem, input));
+        }
+        problem.extend_from_slice(line.as_bytes());
+    }
+}
+
 /// A function definition, from a `define-fun` command.
+#[derive(Clone)]
 struct FunctionDef {
+    /// The sort parameters of the definition, if it is polymorphic. In the parameters and body,
+    /// each sort parameter is represented as a user-declared sort of arity zero.
+    sort_params: Vec<String>,
     params: Vec<SortedVar>,
     body: Rc<Term>,
 }
 
+/// A polymorphic function declaration, from a `declare-fun` command that uses `par`, or a
+/// constructor, selector or tester of a parametric datatype.
+#[derive(Clone)]
+struct PolymorphicDecl {
+    /// The sort parameters of the declaration. In the function sort, each sort parameter is
+    /// represented as a user-declared sort of arity zero.
+    sort_params: Vec<String>,
+    sort: Rc<Term>,
+}
+
+/// The assignment and variable arguments of a subproof's anchor.
+type AnchorArgs = (Vec<(Symbol, Rc<Term>)>, Vec<SortedVar>);
+
+/// The data of the subproofs that are still open while parsing a command of the root proof. Each
+/// field is a stack, with one entry for each open subproof.
+#[derive(Default)]
+struct OpenSubproofs {
+    commands: Vec<Vec<ProofCommand>>,
+    end_steps: Vec<StepId>,
+    args: Vec<AnchorArgs>,
+    ids: Vec<usize>,
+}
+
 /// Represents a "raw" `anchor` command. This is only used while parsing, and does not appear in
 /// the final AST.
 struct AnchorCommand {
-    end_step_id: String,
-    assignment_args: Vec<(String, Rc<Term>)>,
+    end_step_id: StepId,
+    assignment_args: Vec<(Symbol, Rc<Term>)>,
     variable_args: Vec<SortedVar>,
 }
 
 /// Represents a "raw" `anchor` argument. This is only used while parsing, and does not appear in
 /// the final AST.
 enum AnchorArg {
-    Assign(String, Rc<Term>),
+    /// An assignment, possibly to a fresh variable that is introduced by the assignment itself.
+    Assign(Symbol, Rc<Term>, Option<SortedVar>),
     Variable(SortedVar),
 }
 
@@ -77,64 +286,207 @@ enum AnchorArg {
 ///
 /// This holds all the function, constant or sort declarations and definitions, as well as the term
 /// pool used by the parser.
-#[derive(Default)]
+#[derive(Default, Clone)]
 struct ParserState {
-    symbol_table: HashMapStack<HashCache<String>, Rc<Term>>,
+    symbol_table: HashMapStack<Symbol, Rc<Term>>,
     function_defs: IndexMap<String, FunctionDef>,
+    polymorphic_decls: IndexMap<String, PolymorphicDecl>,
     sort_declarations: IndexMap<String, usize>,
-    step_ids: HashMapStack<HashCache<String>, usize>,
+    datatypes: IndexMap<String, usize>,
+    sort_defs: IndexMap<String, SortDefinition>,
+
+    /// The index of each command in its subproof, by id. Step ids must be unique in the whole
+    /// proof, so when a subproof is closed, the ids of its commands are kept in the enclosing scope
+    /// with no index, since they can no longer be referenced.
+    step_ids: HashMapStack<StepId, Option<usize>>,
+
+    /// The number of subproofs parsed so far. This is used to give each subproof a unique context
+    /// id, even if the proof is parsed in parts.
+    num_subproofs: usize,
+
+    /// The attributes of `step` commands that are not interpreted by the parser, indexed by step
+    /// id.
+    attributes: IndexMap<String, Vec<Attribute>>,
+
+    /// The attributes of annotated terms, other than `:named`, indexed by the annotated term.
+    term_attributes: IndexMap<Rc<Term>, Vec<Attribute>>,
+
+    /// The span of each `assume` and `step` command in the source input, indexed by id.
+    spans: IndexMap<String, Span>,
+
+    /// The terms that were flattened, mapped to the terms as they were written. See
+    /// [`Config::flatten_assoc_ops`].
+    flattened: IndexMap<Rc<Term>, Rc<Term>>,
+
+    /// The premises of each query in the problem, in the order of their `check-sat` or
+    /// `check-sat-assuming` commands.
+    queries: Vec<IndexSet<Rc<Term>>>,
+
+    /// The names given to terms in the problem using the `:named` attribute, indexed by term.
+    problem_names: IndexMap<Rc<Term>, Vec<String>>,
+
+    /// The index of the `assume` command in the root proof that introduces each named term of the
+    /// problem, indexed by name. This allows steps to reference these commands by the name of
+    /// their term, instead of by their id.
+    named_assumes: IndexMap<String, usize>,
+
+    /// The symbols whose declarations were inferred from their use, and their current sorts. See
+    /// [`Parser::infer_declarations`].
+    inferred_symbols: IndexMap<Symbol, Rc<Term>>,
+
+    /// The inferred symbols whose sorts may still change, because they were first used in the
+    /// current command and haven't yet been used in a context that determines their sort.
+    pending_inferred: IndexSet<Symbol>,
 }
 
 /// A parser for the Alethe proof format.
-pub struct Parser<'a, R> {
-    pool: &'a mut PrimitivePool,
+///
+/// By default, the parser adds terms to a [`PrimitivePool`], but any other [`TermPool`] can be
+/// used instead.
+pub struct Parser<'a, R, P = PrimitivePool> {
+    pool: &'a mut P,
     config: Config,
     lexer: Lexer<R>,
     current_token: Token,
     current_position: Position,
+    current_span: Span,
+
+    /// The span of the last token consumed.
+    previous_span: Span,
     state: ParserState,
     interpret_integers_as_reals: bool,
     problem: Option<(ProblemPrelude, IndexSet<Rc<Term>>)>,
+
+    /// The number of parentheses opened by the tokens consumed so far that are still open.
+    parens_depth: usize,
+
+    /// If `Some`, the parser recovers from errors in proof commands, and records them here.
+    recovered_errors: Option<Vec<Error>>,
+
+    /// If `Some`, symbols that are used without being declared are declared automatically, and
+    /// this is the sort they are given when it can't be inferred from their use.
+    inferred_sort: Option<Rc<Term>>,
+
+    /// The nesting depth of the term currently being parsed.
+    term_depth: usize,
+
+    /// The number of terms parsed so far.
+    term_count: usize,
+
+    /// The number of `assume` and `step` commands parsed so far.
+    commands_parsed: usize,
+
+    /// If `Some`, this is called after each `assume` and `step` command is parsed.
+    progress_callback: Option<ProgressCallback>,
 }
 
-impl<'a, R: BufRead> Parser<'a, R> {
+impl<'a, R: BufRead, P: TermPool> Parser<'a, R, P> {
     /// Constructs a new `Parser` from a type that implements `BufRead`.
     ///
     /// This operation can fail if there is an IO or lexer error on the first token.
-    pub fn new(pool: &'a mut PrimitivePool, config: Config, input: R) -> CarcaraResult<Self> {
+    pub fn new(pool: &'a mut P, config: Config, input: R) -> CarcaraResult<Self> {
         let mut state = ParserState::default();
         let bool_sort = pool.add(Term::Sort(Sort::Bool));
 
         for iden in ["true", "false"] {
-            let iden = HashCache::new(iden.to_owned());
-            state.symbol_table.insert(iden, bool_sort.clone());
+            state
+                .symbol_table
+                .insert(Symbol::new(iden), bool_sort.clone());
         }
 
-        let mut lexer = Lexer::new(input)?;
+        let mut lexer = Lexer::with_max_size(input, config.limits.max_file_size)?;
         let (current_token, current_position) = lexer.next_token()?;
+        let current_span = lexer.last_token_span();
         Ok(Parser {
             pool,
             config,
             lexer,
             current_token,
             current_position,
+            current_span,
+            previous_span: Span::default(),
             state,
             interpret_integers_as_reals: false,
             problem: None,
+            parens_depth: 0,
+            recovered_errors: None,
+            inferred_sort: None,
+            term_depth: 0,
+            term_count: 0,
+            commands_parsed: 0,
+            progress_callback: None,
         })
     }
 
     /// Resets the parser position and sets its input to `input`. This keeps the parser state,
     /// including all function, constant and sort declarations.
     pub fn reset(&mut self, input: R) -> CarcaraResult<()> {
-        let mut lexer = Lexer::new(input)?;
+        let mut lexer = Lexer::with_max_size(input, self.config.limits.max_file_size)?;
         let (current_token, current_position) = lexer.next_token()?;
+        self.current_span = lexer.last_token_span();
+        self.previous_span = Span::default();
         self.lexer = lexer;
         self.current_token = current_token;
         self.current_position = current_position;
+        self.parens_depth = 0;
         Ok(())
     }
 
+    /// Like [`Parser::reset`], but also forgets the step ids, attributes, spans and flattened terms
+    /// of the proofs parsed so far. This allows parsing several independent proofs of the same
+    /// problem, for example one for each of its queries. Definitions made in earlier proofs are
+    /// kept.
+    pub fn reset_proof(&mut self, input: R) -> CarcaraResult<()> {
+        self.state.step_ids = HashMapStack::new();
+        self.state.attributes.clear();
+        self.state.spans.clear();
+        self.state.flattened.clear();
+        self.state.named_assumes.clear();
+        self.reset(input)
+    }
+
+    /// Declares all sorts, datatypes and functions of a problem prelude, and the names of its
+    /// named terms, so they can be used in the input parsed after this. This allows parsing a proof
+    /// of a problem that was built in memory instead of parsed (see [`ProblemPrelude::new`]). The
+    /// terms in the prelude must have been added to the same pool that is used by the parser.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use carcara::{ast::*, parser::{Config, Parser}};
+    /// let mut pool = PrimitivePool::new();
+    /// let bool_sort = pool.add(Term::Sort(Sort::Bool));
+    /// let mut prelude = ProblemPrelude::new();
+    /// prelude.declare_fun("p", bool_sort);
+    ///
+    /// let proof = "(assume h1 (not p))";
+    /// let mut parser = Parser::new(&mut pool, Config::new(), proof.as_bytes())?;
+    /// parser.declare_prelude(&prelude);
+    /// let commands = parser.parse_proof()?;
+    /// assert_eq!(commands[0].clause()[0].to_string(), "(not p)");
+    /// # Ok::<(), carcara::Error>(())
+    /// ```
+    pub fn declare_prelude(&mut self, prelude: &ProblemPrelude) {
+        for (name, arity) in &prelude.sort_declarations {
+            self.state.sort_declarations.insert(name.clone(), *arity);
+        }
+        for def in &prelude.sort_definitions {
+            self.state.sort_defs.insert(def.name.clone(), def.clone());
+        }
+        for datatype in prelude.datatype_declarations.iter().flatten() {
+            let arity = datatype.sort_params.len();
+            self.state.datatypes.insert(datatype.name.clone(), arity);
+            self.declare_datatype_symbols(datatype);
+        }
+        for (name, sort) in &prelude.function_declarations {
+            self.insert_sorted_var((Symbol::new(name), sort.clone()));
+        }
+        for (name, term) in &prelude.named_terms {
+            let names = self.state.problem_names.entry(term.clone()).or_default();
+            names.push(name.clone());
+        }
+    }
+
     /// Advances the parser one token, and returns the previous `current_token`.
     fn next_token(&mut self) -> CarcaraResult<(Token, Position)> {
         use std::mem::replace;
@@ -142,12 +494,18 @@ impl<'a, R: BufRead> Parser<'a, R> {
         let (new_token, new_position) = self.lexer.next_token()?;
         let old_token = replace(&mut self.current_token, new_token);
         let old_position = replace(&mut self.current_position, new_position);
+        self.previous_span = replace(&mut self.current_span, self.lexer.last_token_span());
+        match old_token {
+            Token::OpenParen => self.parens_depth += 1,
+            Token::CloseParen => self.parens_depth = self.parens_depth.saturating_sub(1),
+            _ => (),
+        }
         Ok((old_token, old_position))
     }
 
     /// Inserts a `SortedVar` into the parser symbol table.
     fn insert_sorted_var(&mut self, (symbol, sort): SortedVar) {
-        self.state.symbol_table.insert(HashCache::new(symbol), sort);
+        self.state.symbol_table.insert(symbol, sort);
     }
 
     /// Shortcut for `self.problem.as_mut().unwrap().0`
@@ -162,328 +520,39 @@ impl<'a, R: BufRead> Parser<'a, R> {
 
     /// Constructs and sort checks a variable term.
     fn make_var(&mut self, iden: String) -> Result<Rc<Term>, ParserError> {
-        let cached = HashCache::new(iden);
-        let sort = match self.state.symbol_table.get(&cached) {
+        let symbol = Symbol::from(iden);
+        let sort = match self.state.symbol_table.get(&symbol) {
             Some(s) => s.clone(),
-            None => return Err(ParserError::UndefinedIden(cached.unwrap())),
+            None if self.inferred_sort.is_some() => return Ok(self.make_inferred_var(symbol)),
+            None => return Err(ParserError::UndefinedIden(symbol.to_string())),
         };
-        Ok(self.pool.add(Term::Var(cached.unwrap(), sort)))
+        Ok(self.pool.add(Term::Var(symbol, sort)))
     }
 
     /// Constructs and sort checks an operation term.
-    fn make_op(&mut self, op: Operator, args: Vec<Rc<Term>>) -> Result<Rc<Term>, ParserError> {
-        let sorts: Vec<_> = args.iter().map(|t| self.pool.sort(t)).collect();
-        match op {
-            Operator::Not => {
-                assert_num_args(&args, 1)?;
-                SortError::assert_eq(&Sort::Bool, sorts[0].as_sort().unwrap())?;
-            }
-            Operator::Implies => {
-                assert_num_args(&args, 2..)?;
-                for s in sorts {
-                    SortError::assert_eq(&Sort::Bool, s.as_sort().unwrap())?;
-                }
-            }
-            Operator::Or | Operator::And | Operator::Xor => {
-                assert_num_args(&args, 2..)?;
-                for s in sorts {
-                    SortError::assert_eq(&Sort::Bool, s.as_sort().unwrap())?;
-                }
-            }
-            Operator::Equals | Operator::Distinct => {
-                assert_num_args(&args, 2..)?;
-                SortError::assert_all_eq(
-                    &sorts
-                        .iter()
-                        .map(|op| op.as_sort().unwrap())
-                        .collect::<Vec<&Sort>>(),
-                )?;
-            }
-            Operator::Ite => {
-                assert_num_args(&args, 3)?;
-                SortError::assert_eq(&Sort::Bool, sorts[0].as_sort().unwrap())?;
-                SortError::assert_eq(sorts[1].as_sort().unwrap(), sorts[2].as_sort().unwrap())?;
-            }
-            Operator::Add | Operator::Sub | Operator::Mult => {
-                // The `-` operator, in particular, can be called with only one argument, in which
-                // case it means negation instead of subtraction
-                if op == Operator::Sub {
-                    assert_num_args(&args, 1..)?;
-                } else {
-                    assert_num_args(&args, 2..)?;
-                }
-
-                // All the arguments must be either Int or Real. Also, if we are not allowing
-                // Int/Real subtyping, all arguments must have the same sort
-                if self.config.allow_int_real_subtyping {
-                    for s in sorts {
-                        SortError::assert_one_of(&[Sort::Int, Sort::Real], s.as_sort().unwrap())?;
-                    }
-                } else {
-                    SortError::assert_one_of(
-                        &[Sort::Int, Sort::Real],
-                        sorts[0].as_sort().unwrap(),
-                    )?;
-                    SortError::assert_all_eq(
-                        &sorts
-                            .iter()
-                            .map(|op| op.as_sort().unwrap())
-                            .collect::<Vec<&Sort>>(),
-                    )?;
-                }
-            }
-            Operator::IntDiv => {
-                assert_num_args(&args, 2..)?;
-                SortError::assert_eq(&Sort::Int, sorts[0].as_sort().unwrap())?;
-                SortError::assert_all_eq(
-                    &sorts
-                        .iter()
-                        .map(|op| op.as_sort().unwrap())
-                        .collect::<Vec<&Sort>>(),
-                )?;
-            }
-            Operator::RealDiv => {
-                assert_num_args(&args, 2..)?;
-
-                // Normally, the `/` operator may only receive Real arguments, but if we are
-                // allowing Int/Real subtyping, it may also receive Ints
-                if self.config.allow_int_real_subtyping {
-                    for s in sorts {
-                        SortError::assert_one_of(&[Sort::Int, Sort::Real], s.as_sort().unwrap())?;
-                    }
-                } else {
-                    SortError::assert_eq(&Sort::Real, sorts[0].as_sort().unwrap())?;
-                    SortError::assert_all_eq(
-                        &sorts
-                            .iter()
-                            .map(|op| op.as_sort().unwrap())
-                            .collect::<Vec<&Sort>>(),
-                    )?;
-                }
-            }
-            Operator::Mod => {
-                assert_num_args(&args, 2)?;
-                SortError::assert_eq(&Sort::Int, sorts[0].as_sort().unwrap())?;
-                SortError::assert_eq(&Sort::Int, sorts[1].as_sort().unwrap())?;
-            }
-            Operator::Abs => {
-                assert_num_args(&args, 1)?;
-                SortError::assert_eq(&Sort::Int, sorts[0].as_sort().unwrap())?;
-            }
-            Operator::LessThan | Operator::GreaterThan | Operator::LessEq | Operator::GreaterEq => {
-                assert_num_args(&args, 2..)?;
-                // All the arguments must be either Int or Real sorted, but they don't need to all
-                // have the same sort
-                for s in sorts {
-                    SortError::assert_one_of(&[Sort::Int, Sort::Real], s.as_sort().unwrap())?;
-                }
-            }
-            Operator::ToReal => {
-                assert_num_args(&args, 1)?;
-                SortError::assert_eq(&Sort::Int, sorts[0].as_sort().unwrap())?;
-            }
-            Operator::ToInt | Operator::IsInt => {
-                assert_num_args(&args, 1)?;
-                SortError::assert_eq(&Sort::Real, sorts[0].as_sort().unwrap())?;
-            }
-            Operator::Select => {
-                assert_num_args(&args, 2)?;
-                match sorts[0].as_sort().unwrap() {
-                    Sort::Array(_, _) => (),
-                    got => {
-                        // Instead of creating some special case for sort errors with parametric
-                        // sorts, we just create a sort `Y` to represent the sort parameter. We
-                        // infer the `X` sort from the second operator argument. This may be
-                        // changed later
-                        let got = got.clone();
-                        let x = sorts[1].as_sort().unwrap().clone();
-                        let x = self.pool.add(Term::Sort(x));
-                        let y = self
-                            .pool
-                            .add(Term::Sort(Sort::Atom("Y".to_owned(), Vec::new())));
-                        return Err(SortError {
-                            expected: vec![Sort::Array(x, y)],
-                            got,
-                        }
-                        .into());
-                    }
-                }
-            }
-            Operator::Store => {
-                assert_num_args(&args, 3)?;
-                match sorts[0].as_sort().unwrap() {
-                    Sort::Array(x, y) => {
-                        SortError::assert_eq(x.as_sort().unwrap(), sorts[1].as_sort().unwrap())?;
-                        SortError::assert_eq(y.as_sort().unwrap(), sorts[2].as_sort().unwrap())?;
-                    }
-                    got => {
-                        let got = got.clone();
-                        let [x, y] = [&sorts[0], &sorts[1]]
-                            .map(|s| Term::Sort(s.as_sort().unwrap().clone()));
-                        return Err(SortError {
-                            expected: vec![Sort::Array(self.pool.add(x), self.pool.add(y))],
-                            got,
-                        }
-                        .into());
-                    }
-                }
-            }
-            Operator::StrConcat => {
-                assert_num_args(&args, 2..)?;
-                for s in sorts {
-                    SortError::assert_eq(&Sort::String, s.as_sort().unwrap())?;
-                }
-            }
-            Operator::StrLen | Operator::StrIsDigit | Operator::StrToCode | Operator::StrToInt => {
-                assert_num_args(&args, 1)?;
-                SortError::assert_eq(&Sort::String, sorts[0].as_sort().unwrap())?;
-            }
-            Operator::StrLessThan
-            | Operator::StrLessEq
-            | Operator::PrefixOf
-            | Operator::SuffixOf
-            | Operator::Contains
-            | Operator::ReRange => {
-                assert_num_args(&args, 2)?;
-                SortError::assert_eq(&Sort::String, sorts[0].as_sort().unwrap())?;
-                SortError::assert_eq(&Sort::String, sorts[1].as_sort().unwrap())?;
-            }
-            Operator::CharAt => {
-                assert_num_args(&args, 2)?;
-                SortError::assert_eq(&Sort::String, sorts[0].as_sort().unwrap())?;
-                SortError::assert_eq(&Sort::Int, sorts[1].as_sort().unwrap())?;
-            }
-            Operator::Substring => {
-                assert_num_args(&args, 3)?;
-                SortError::assert_eq(&Sort::String, sorts[0].as_sort().unwrap())?;
-                SortError::assert_eq(&Sort::Int, sorts[1].as_sort().unwrap())?;
-                SortError::assert_eq(&Sort::Int, sorts[2].as_sort().unwrap())?;
-            }
-            Operator::IndexOf => {
-                assert_num_args(&args, 3)?;
-                SortError::assert_eq(&Sort::String, sorts[0].as_sort().unwrap())?;
-                SortError::assert_eq(&Sort::String, sorts[1].as_sort().unwrap())?;
-                SortError::assert_eq(&Sort::Int, sorts[2].as_sort().unwrap())?;
-            }
-            Operator::Replace | Operator::ReplaceAll => {
-                assert_num_args(&args, 3)?;
-                SortError::assert_eq(&Sort::String, sorts[0].as_sort().unwrap())?;
-                SortError::assert_eq(&Sort::String, sorts[1].as_sort().unwrap())?;
-                SortError::assert_eq(&Sort::String, sorts[2].as_sort().unwrap())?;
-            }
-            Operator::StrFromCode | Operator::StrFromInt => {
-                assert_num_args(&args, 1)?;
-                SortError::assert_eq(&Sort::Int, sorts[0].as_sort().unwrap())?;
-            }
-            Operator::StrToRe => {
-                assert_num_args(&args, 1)?;
-                SortError::assert_eq(&Sort::String, sorts[0].as_sort().unwrap())?;
-            }
-            Operator::StrInRe => {
-                assert_num_args(&args, 2)?;
-                SortError::assert_eq(&Sort::String, sorts[0].as_sort().unwrap())?;
-                SortError::assert_eq(&Sort::RegLan, sorts[1].as_sort().unwrap())?;
-            }
-            Operator::ReNone | Operator::ReAll | Operator::ReAllChar => {
-                assert_num_args(&args, 0)?;
-            }
-            Operator::ReConcat
-            | Operator::ReUnion
-            | Operator::ReIntersection
-            | Operator::ReDiff => {
-                assert_num_args(&args, 2..)?;
-                for s in sorts {
-                    SortError::assert_eq(&Sort::RegLan, s.as_sort().unwrap())?;
-                }
-            }
-            Operator::ReKleeneClosure
-            | Operator::ReComplement
-            | Operator::ReKleeneCross
-            | Operator::ReOption => {
-                assert_num_args(&args, 1)?;
-                SortError::assert_eq(&Sort::RegLan, sorts[0].as_sort().unwrap())?;
-            }
-            Operator::ReplaceRe | Operator::ReplaceReAll => {
-                assert_num_args(&args, 3)?;
-                SortError::assert_eq(&Sort::String, sorts[0].as_sort().unwrap())?;
-                SortError::assert_eq(&Sort::RegLan, sorts[1].as_sort().unwrap())?;
-                SortError::assert_eq(&Sort::String, sorts[2].as_sort().unwrap())?;
-            }
-            Operator::BvNot | Operator::BvNeg => {
-                assert_num_args(&args, 1)?;
-                for s in sorts {
-                    let s = s.as_sort().unwrap().clone();
-                    if !matches!(s, Sort::BitVec(_)) {
-                        return Err(ParserError::ExpectedBvSort(s));
-                    }
-                }
-            }
-            Operator::BvBbTerm => {
-                assert_num_args(&args, 1..)?;
-                for s in sorts {
-                    let s = s.as_sort().unwrap().clone();
-                    SortError::assert_eq(&Sort::Bool, &s)?;
-                }
-            }
-            Operator::BvConcat => {
-                assert_num_args(&args, 2..)?;
-                for s in sorts {
-                    let s = s.as_sort().unwrap().clone();
-                    if !matches!(s, Sort::BitVec(_)) {
-                        return Err(ParserError::ExpectedBvSort(s));
-                    }
-                }
-            }
-            Operator::BvAdd
-            | Operator::BvMul
-            | Operator::BvAnd
-            | Operator::BvOr
-            | Operator::BvXor => {
-                assert_num_args(&args, 2..)?;
-                let first_sort = sorts[0].as_sort().unwrap().clone();
-                if !matches!(first_sort, Sort::BitVec(_)) {
-                    return Err(ParserError::ExpectedBvSort(first_sort));
-                }
-                SortError::assert_all_eq(
-                    &sorts
-                        .iter()
-                        .map(|op| op.as_sort().unwrap())
-                        .collect::<Vec<&Sort>>(),
-                )?;
-            }
-            Operator::BvUDiv
-            | Operator::BvURem
-            | Operator::BvShl
-            | Operator::BvLShr
-            | Operator::BvULt
-            | Operator::BvNAnd
-            | Operator::BvNOr
-            | Operator::BvXNor
-            | Operator::BvComp
-            | Operator::BvSub
-            | Operator::BvSDiv
-            | Operator::BvSRem
-            | Operator::BvSMod
-            | Operator::BvAShr
-            | Operator::BvULe
-            | Operator::BvUGt
-            | Operator::BvUGe
-            | Operator::BvSLt
-            | Operator::BvSLe
-            | Operator::BvSGt
-            | Operator::BvSGe => {
-                assert_num_args(&args, 2)?;
-                let first_sort = sorts[0].as_sort().unwrap().clone();
-                if !matches!(first_sort, Sort::BitVec(_)) {
-                    return Err(ParserError::ExpectedBvSort(first_sort));
-                }
-                SortError::assert_all_eq(
-                    &sorts
-                        .iter()
-                        .map(|op| op.as_sort().unwrap())
-                        .collect::<Vec<&Sort>>(),
-                )?;
-            }
+    fn make_op(&mut self, op: Operator, mut args: Vec<Rc<Term>>) -> Result<Rc<Term>, ParserError> {
+        if self.inferred_sort.is_some() {
+            self.resolve_inferred_op_args(op, &mut args);
+        }
+        check_op(self.pool, op, &args, self.config.allow_int_real_subtyping)?;
+        let is_nested = |a: &Rc<Term>| matches!(a.as_ref(), Term::Op(inner, _) if *inner == op);
+        if self.config.flatten_assoc_ops && op.is_associative() && args.iter().any(is_nested) {
+            // Since the arguments were already parsed, any nested application in them is already
+            // flat, so we only need to flatten one level
+            let flat_args = args
+                .iter()
+                .flat_map(|a| match a.as_ref() {
+                    Term::Op(inner, inner_args) if *inner == op => inner_args.clone(),
+                    _ => vec![a.clone()],
+                })
+                .collect();
+            let original = self.pool.add(Term::Op(op, args));
+            let flattened = self.pool.add(Term::Op(op, flat_args));
+            self.state
+                .flattened
+                .entry(flattened.clone())
+                .or_insert(original);
+            return Ok(flattened);
         }
         Ok(self.pool.add(Term::Op(op, args)))
     }
@@ -492,25 +561,15 @@ impl<'a, R: BufRead> Parser<'a, R> {
     fn make_app(
         &mut self,
         function: Rc<Term>,
-        args: Vec<Rc<Term>>,
+        mut args: Vec<Rc<Term>>,
     ) -> Result<Rc<Term>, ParserError> {
-        let sort = self.pool.sort(&function);
-        let sorts = {
-            let function_sort = sort.as_sort().unwrap();
-            if let Sort::Function(sorts) = function_sort {
-                sorts
-            } else {
-                // Function does not have function sort
-                return Err(ParserError::NotAFunction(function_sort.clone()));
+        if self.inferred_sort.is_some() {
+            let sort = self.pool.sort(&function);
+            if let Sort::Function(sorts) = sort.as_sort().unwrap() {
+                self.resolve_inferred_app_args(&function, sorts, &mut args);
             }
-        };
-        assert_num_args(&args, sorts.len() - 1)?;
-        for i in 0..args.len() {
-            SortError::assert_eq(
-                sorts[i].as_sort().unwrap(),
-                self.pool.sort(&args[i]).as_sort().unwrap(),
-            )?;
         }
+        check_app(self.pool, &function, &args)?;
         Ok(self.pool.add(Term::App(function, args)))
     }
 
@@ -533,6 +592,16 @@ impl<'a, R: BufRead> Parser<'a, R> {
         }
     }
 
+    /// Consumes the current token if it is a symbol, and returns it as a step id. Returns an error
+    /// otherwise. If the `smtinterpol_compat` option is enabled, numerals are also accepted.
+    fn expect_step_id(&mut self) -> CarcaraResult<StepId> {
+        match self.next_token()? {
+            (Token::Symbol(s), _) => Ok(s.into()),
+            (Token::Numeral(n), _) if self.config.smtinterpol_compat => Ok(n.to_string().into()),
+            (other, pos) => Err(Error::Parser(ParserError::UnexpectedToken(other), pos)),
+        }
+    }
+
     /// Consumes the current token if it is a keyword, and returns the inner `String`. Returns an
     /// error otherwise.
     fn expect_keyword(&mut self) -> CarcaraResult<String> {
@@ -599,6 +668,165 @@ impl<'a, R: BufRead> Parser<'a, R> {
         Ok(())
     }
 
+    /// Sets a callback that is called with the progress of the parser after each `assume` and
+    /// `step` command is parsed, including the ones inside subproofs. This allows front-ends to
+    /// report the progress of parsing very large proofs. If the callback returns
+    /// `ControlFlow::Break`, parsing stops with an `Interrupted` error, which allows aborting it,
+    /// for example, after a deadline.
+    ///
+    /// The callback is not called for the chunks of a proof parsed in parallel by
+    /// [`Parser::parse_proof_parallel`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use carcara::{ast::*, parser::*};
+    /// # use std::ops::ControlFlow;
+    /// let proof = "(assume h1 (not true)) (step t2 (cl) :rule hole) (step t3 (cl) :rule hole)";
+    /// let mut pool = PrimitivePool::new();
+    /// let mut parser = Parser::new(&mut pool, Config::new(), proof.as_bytes())?;
+    /// parser.on_progress(|progress| {
+    ///     if progress.commands_parsed < 2 {
+    ///         ControlFlow::Continue(())
+    ///     } else {
+    ///         ControlFlow::Break(())
+    ///     }
+    /// });
+    /// let result = parser.parse_proof();
+    /// assert!(matches!(result, Err(carcara::Error::Parser(ParserError::Interrupted, _))));
+    /// # Ok::<(), carcara::Error>(())
+    /// ```
+    pub fn on_progress(
+        &mut self,
+        callback: impl FnMut(ParseProgress) -> ControlFlow<()> + 'static,
+    ) {
+        self.progress_callback = Some(Box::new(callback));
+    }
+
+    /// Returns the attributes of `step` commands that were not interpreted by the parser, indexed
+    /// by step id, leaving the parser with no recorded attributes.
+    pub fn take_attributes(&mut self) -> IndexMap<String, Vec<Attribute>> {
+        std::mem::take(&mut self.state.attributes)
+    }
+
+    /// Returns the span in the source input of each `assume` and `step` command parsed, indexed
+    /// by id, leaving the parser with no recorded spans.
+    pub fn take_spans(&mut self) -> IndexMap<String, Span> {
+        std::mem::take(&mut self.state.spans)
+    }
+
+    /// Returns the attributes of annotated terms, other than `:named`, indexed by the annotated
+    /// term, leaving the parser with no recorded attributes. This includes the terms annotated in
+    /// both the problem and the proof. See [`Proof::term_attributes`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use carcara::{ast::*, parser::*};
+    /// let input = "(declare-fun f (Int) Int)
+    ///     (assert (forall ((x Int)) (! (= (f x) x) :pattern ((f x)) :qid q1)))";
+    /// let mut pool = PrimitivePool::new();
+    /// let mut parser = Parser::new(&mut pool, Config::new(), input.as_bytes())?;
+    /// parser.parse_problem()?;
+    /// let attributes = parser.take_term_attributes();
+    /// let (term, list) = attributes.first().unwrap();
+    /// assert_eq!(format!("{:#}", term), "(= (f x) x)");
+    /// assert_eq!(list[0].value.as_deref(), Some("((f x))"));
+    /// assert_eq!(list[1].keyword, "qid");
+    /// # Ok::<(), carcara::Error>(())
+    /// ```
+    pub fn take_term_attributes(&mut self) -> IndexMap<Rc<Term>, Vec<Attribute>> {
+        std::mem::take(&mut self.state.term_attributes)
+    }
+
+    /// Returns the terms that were flattened while parsing, mapped to the terms as they were
+    /// written, leaving the parser with no recorded terms. This is always empty unless
+    /// [`Config::flatten_assoc_ops`] is enabled.
+    pub fn take_flattened(&mut self) -> IndexMap<Rc<Term>, Rc<Term>> {
+        std::mem::take(&mut self.state.flattened)
+    }
+
+    /// Returns the span in the source input of each `assume` and `step` command parsed so far,
+    /// indexed by id.
+    pub fn spans(&self) -> &IndexMap<String, Span> {
+        &self.state.spans
+    }
+
+    /// Returns the premises of each query in the last parsed problem, leaving the parser with no
+    /// recorded queries. Each query corresponds to a `check-sat` or `check-sat-assuming` command,
+    /// and its premises are the assertions that were active at that command, together with the
+    /// assumption literals of `check-sat-assuming`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use carcara::{ast::*, parser::{Config, Parser}};
+    /// let problem = "
+    ///     (declare-const p Bool)
+    ///     (declare-const q Bool)
+    ///     (assert p)
+    ///     (push 1)
+    ///     (assert q)
+    ///     (check-sat)
+    ///     (pop 1)
+    ///     (check-sat-assuming ((not p)))
+    /// ";
+    /// let mut pool = PrimitivePool::new();
+    /// let mut parser = Parser::new(&mut pool, Config::new(), problem.as_bytes())?;
+    /// parser.parse_problem()?;
+    /// let queries = parser.take_queries();
+    /// assert_eq!(queries.len(), 2);
+    /// assert_eq!(queries[0].len(), 2);
+    /// assert_eq!(queries[1].len(), 2);
+    /// assert!(parser.take_queries().is_empty());
+    /// # Ok::<(), carcara::Error>(())
+    /// ```
+    pub fn take_queries(&mut self) -> Vec<IndexSet<Rc<Term>>> {
+        std::mem::take(&mut self.state.queries)
+    }
+
+    /// Consumes attributes and their values until a closing parenthesis is reached, and returns
+    /// them. The value of each attribute is kept as it was written in the input.
+    fn parse_remaining_attributes(&mut self) -> CarcaraResult<Vec<Attribute>> {
+        let mut result = Vec::new();
+        while let Token::Keyword(_) = self.current_token {
+            let Token::Keyword(keyword) = self.next_token()?.0 else {
+                unreachable!()
+            };
+            let value = self.parse_attribute_value()?;
+            result.push(Attribute { keyword, value });
+        }
+        Ok(result)
+    }
+
+    /// Consumes the value of an attribute, if it has one, and returns it as it was written in the
+    /// input. This method assumes that the attribute keyword was already consumed.
+    fn parse_attribute_value(&mut self) -> CarcaraResult<Option<String>> {
+        Ok(match self.current_token {
+            // If there is no value for this attribute, we may encounter the closing parenthesis,
+            // or the next attribute
+            Token::CloseParen | Token::Eof | Token::Keyword(_) => None,
+
+            // If the value is an s-expression we read tokens until it's closed
+            Token::OpenParen => {
+                self.next_token()?;
+                let tokens = self.read_until_close_parens()?;
+                let mut value = "(".to_owned();
+                for (i, token) in tokens.iter().enumerate() {
+                    let after_open = i == 0 || tokens[i - 1] == Token::OpenParen;
+                    if !after_open && *token != Token::CloseParen {
+                        value.push(' ');
+                    }
+                    value += &token.to_string();
+                }
+                Some(value)
+            }
+
+            // Otherwise, the value is a single token
+            _ => Some(self.next_token()?.0.to_string()),
+        })
+    }
+
     /// Consumes and ignores attributes and their values until a closing parenthesis is reached.
     fn ignore_remaining_attributes(&mut self) -> CarcaraResult<()> {
         while let Token::Keyword(_) = self.current_token {
@@ -639,24 +867,56 @@ impl<'a, R: BufRead> Parser<'a, R> {
     /// The following commands are parsed:
     ///
     /// - `assert`
+    /// - `check-sat`
+    /// - `check-sat-assuming`
     /// - `declare-const`
     /// - `declare-fun`
     /// - `declare-sort`
+    /// - `define-const`
     /// - `define-fun`
+    /// - `define-fun-rec`
+    /// - `define-funs-rec`
+    /// - `define-sort`
+    /// - `pop`
+    /// - `push`
     /// - `set-logic`
     ///
-    /// All other commands are ignored. This method returns a hash set containing the premises
-    /// introduced in `assert` commands.
+    /// Definitions introduced by `define-const` are handled exactly like nullary `define-fun`
+    /// definitions, and sorts defined with `define-sort` are expanded wherever they are used.
+    /// Recursive definitions are never unfolded. Instead, the functions are declared, and a premise
+    /// that defines each function is added. All other commands are ignored.
+    ///
+    /// This method returns a hash set containing the premises introduced in `assert` commands that
+    /// were not removed by a `pop` command, together with the assumption literals of the last
+    /// `check-sat-assuming` command, if it is the last query. If the `query` option is set, the
+    /// premises of that query are returned instead. The premises of every query can be retrieved
+    /// with [`Parser::take_queries`].
     pub fn parse_problem(&mut self) -> CarcaraResult<(ProblemPrelude, IndexSet<Rc<Term>>)> {
         self.problem = Some((ProblemPrelude::default(), IndexSet::new()));
 
+        // In incremental problems, the proof refers to the last query, so only the assumption
+        // literals of the last `check-sat-assuming` command are premises. Likewise, assertions
+        // removed by a `pop` command are no longer premises. For each assertion level, we keep the
+        // number of premises when that level was pushed, so they can be truncated when it is popped
+        let mut assumptions = Vec::new();
+        let mut assertion_levels = Vec::new();
+        self.state.queries.clear();
+
         while self.current_token != Token::Eof {
             self.expect_token(Token::OpenParen)?;
             match self.next_token()?.0 {
                 Token::ReservedWord(Reserved::DeclareFun) => {
-                    let (name, sort) = self.parse_declare_fun()?;
-                    self.insert_sorted_var((name.clone(), sort.clone()));
-                    self.prelude().function_declarations.push((name, sort));
+                    let (name, sort_params, sort) = self.parse_declare_fun()?;
+
+                    // Polymorphic functions are not added to the symbol table, since each use of
+                    // them must instantiate their sort parameters
+                    if sort_params.is_empty() {
+                        self.insert_sorted_var((Symbol::new(&name), sort.clone()));
+                        self.prelude().function_declarations.push((name, sort));
+                    } else {
+                        let decl = PolymorphicDecl { sort_params, sort };
+                        self.state.polymorphic_decls.insert(name, decl);
+                    }
                     continue;
                 }
                 Token::ReservedWord(Reserved::DeclareConst) => {
@@ -664,7 +924,7 @@ impl<'a, R: BufRead> Parser<'a, R> {
                     let sort = self.parse_sort()?;
                     let sort = self.pool.add(sort);
                     self.expect_token(Token::CloseParen)?;
-                    self.insert_sorted_var((name.clone(), sort.clone()));
+                    self.insert_sorted_var((Symbol::new(&name), sort.clone()));
                     self.prelude().function_declarations.push((name, sort));
                     continue;
                 }
@@ -678,10 +938,30 @@ impl<'a, R: BufRead> Parser<'a, R> {
                     self.state.sort_declarations.insert(name, arity);
                     continue;
                 }
-                Token::ReservedWord(Reserved::DefineFun) => {
-                    let (name, func_def) = self.parse_define_fun()?;
+                Token::ReservedWord(Reserved::DeclareDatatypes) => {
+                    let datatypes = self.parse_declare_datatypes()?;
+                    self.prelude().datatype_declarations.push(datatypes);
+                    continue;
+                }
+                Token::ReservedWord(Reserved::DeclareDatatype) => {
+                    let pos = self.current_position;
+                    let name = self.expect_symbol()?;
+                    let datatype = self.parse_datatype_constructors(name, None, pos)?;
+                    self.expect_token(Token::CloseParen)?;
+                    self.declare_datatype_symbols(&datatype);
+                    self.prelude().datatype_declarations.push(vec![datatype]);
+                    continue;
+                }
+                Token::ReservedWord(r @ (Reserved::DefineFun | Reserved::DefineConst)) => {
+                    let (name, func_def) = if r == Reserved::DefineFun {
+                        self.parse_define_fun()?
+                    } else {
+                        self.parse_define_const()?
+                    };
 
-                    if self.config.apply_function_defs {
+                    // Polymorphic definitions can't be represented by a single premise, so they are
+                    // always applied
+                    if self.config.apply_function_defs || !func_def.sort_params.is_empty() {
                         self.state.function_defs.insert(name, func_def);
                     } else {
                         // If `self.apply_function_defs` is false, we instead add the function name
@@ -693,7 +973,7 @@ impl<'a, R: BufRead> Parser<'a, R> {
                                 .add(Term::Lambda(BindingList(func_def.params), func_def.body))
                         };
                         let sort = self.pool.sort(&lambda_term);
-                        let var = (name, sort);
+                        let var = (Symbol::from(name), sort);
                         self.insert_sorted_var(var.clone());
                         let var_term = self.pool.add(var.into());
                         let assertion_term = self
@@ -703,16 +983,77 @@ impl<'a, R: BufRead> Parser<'a, R> {
                     }
                     continue;
                 }
+                Token::ReservedWord(Reserved::DefineFunRec) => {
+                    let signature = self.parse_recursive_signature()?;
+                    self.parse_recursive_body(signature)?;
+                    self.expect_token(Token::CloseParen)?;
+                    continue;
+                }
+                Token::ReservedWord(Reserved::DefineFunsRec) => {
+                    // Since the functions may be mutually recursive, all of them must be declared
+                    // before any of the bodies are parsed
+                    self.expect_token(Token::OpenParen)?;
+                    let signatures = self.parse_sequence(
+                        |p| {
+                            p.expect_token(Token::OpenParen)?;
+                            let signature = p.parse_recursive_signature()?;
+                            p.expect_token(Token::CloseParen)?;
+                            Ok(signature)
+                        },
+                        true,
+                    )?;
+                    self.expect_token(Token::OpenParen)?;
+                    for signature in signatures {
+                        self.parse_recursive_body(signature)?;
+                    }
+                    self.expect_token(Token::CloseParen)?;
+                    self.expect_token(Token::CloseParen)?;
+                    continue;
+                }
+                Token::ReservedWord(Reserved::DefineSort) => {
+                    let sort_def = self.parse_define_sort()?;
+                    self.prelude().sort_definitions.push(sort_def.clone());
+                    self.state.sort_defs.insert(sort_def.name.clone(), sort_def);
+                    continue;
+                }
                 Token::ReservedWord(Reserved::Assert) => {
                     let term = self.parse_term()?;
                     self.expect_token(Token::CloseParen)?;
                     self.premises().insert(term);
                 }
+                Token::ReservedWord(Reserved::CheckSat) => {
+                    self.expect_token(Token::CloseParen)?;
+                    assumptions.clear();
+                    let query = self.premises().clone();
+                    self.state.queries.push(query);
+                }
                 Token::ReservedWord(Reserved::CheckSatAssuming) => {
                     self.expect_token(Token::OpenParen)?;
-                    let terms = self.parse_sequence(Self::parse_term, true)?;
+                    assumptions = self.parse_sequence(Self::parse_term, false)?;
                     self.expect_token(Token::CloseParen)?;
-                    self.premises().extend(terms);
+                    let mut query = self.premises().clone();
+                    query.extend(assumptions.iter().cloned());
+                    self.state.queries.push(query);
+                }
+                Token::ReservedWord(r @ (Reserved::Push | Reserved::Pop)) => {
+                    // Some solvers accept `push` and `pop` commands without a numeral, meaning one
+                    // level
+                    let n = if self.current_token == Token::CloseParen {
+                        1
+                    } else {
+                        self.expect_numeral()?.to_usize().unwrap_or(usize::MAX)
+                    };
+                    self.expect_token(Token::CloseParen)?;
+                    if r == Reserved::Push {
+                        let current = self.premises().len();
+                        assertion_levels.extend(std::iter::repeat(current).take(n));
+                    } else {
+                        let remaining = assertion_levels.len().saturating_sub(n);
+                        if let Some(&length) = assertion_levels.get(remaining) {
+                            self.premises().truncate(length);
+                        }
+                        assertion_levels.truncate(remaining);
+                    }
                 }
                 Token::ReservedWord(Reserved::SetLogic) => {
                     let logic = self.expect_symbol()?;
@@ -734,140 +1075,363 @@ impl<'a, R: BufRead> Parser<'a, R> {
                 }
             }
         }
+        self.premises().extend(assumptions);
+        if let Some(i) = self.config.query {
+            let Some(query) = self.state.queries.get(i).cloned() else {
+                let num_queries = self.state.queries.len();
+                return Err(Error::Parser(
+                    ParserError::UndefinedQuery(i, num_queries),
+                    self.current_position,
+                ));
+            };
+            *self.premises() = query;
+        }
         Ok(self.problem.take().unwrap())
     }
 
     /// Parses a proof in the Alethe format. All function, constant and sort declarations needed
-    /// should already be in the parser state.
+    /// should already be in the parser state. Besides `assume`, `step` and `anchor` commands, the
+    /// proof may contain `define-fun`, `define-const` and `define-sort` commands, whose
+    /// definitions are expanded in the commands that follow them.
     pub fn parse_proof(&mut self) -> CarcaraResult<Vec<ProofCommand>> {
-        // To avoid stack overflows in proofs with many nested subproofs, we parse the subproofs
-        // iteratively, instead of recursively
-        let mut commands_stack = vec![Vec::new()];
-        let mut end_step_stack = Vec::new();
-        let mut subproof_args_stack = Vec::new();
-        let mut subproof_id_stack = Vec::new();
-        let mut last_subproof_id: i64 = -1;
+        self.parse_proof_continuation(Vec::new())
+    }
 
-        let mut finished_assumes = false;
+    /// Parses a part of a proof that continues the given commands, and returns all of them
+    /// together. This is used to parse proofs that are split in many parts, possibly in different
+    /// files: the commands in each part may reference steps from the previous parts as premises.
+    ///
+    /// `commands` must be the result of previous calls to this method (or to `parse_proof`) on the
+    /// same parser, and the input must have been changed since then using [`Parser::reset`]. Each
+    /// part must close all of the subproofs it opens.
+    pub fn parse_proof_continuation(
+        &mut self,
+        mut commands: Vec<ProofCommand>,
+    ) -> CarcaraResult<Vec<ProofCommand>> {
+        let mut finished_assumes = commands.iter().any(|c| !c.is_assume());
+        self.skip_unsat_token()?;
+        while let Some(command) = self.parse_root_command(commands.len(), &mut finished_assumes)? {
+            commands.push(command);
+        }
+        Ok(commands)
+    }
+
+    /// Parses a proof in the Alethe format, returning an iterator that parses and yields the
+    /// commands of the root proof one at a time. Subproofs are yielded as a single command,
+    /// containing all of their commands. This allows processing proofs with a very large number
+    /// of commands without keeping all of them in memory.
+    ///
+    /// The parser still needs to remember the id of every command in the root proof, so they can
+    /// be referenced as premises, and the terms it parses are kept in the term pool. To avoid
+    /// also keeping data that is only useful while a command is processed, the spans and
+    /// uninterpreted attributes recorded by the parser only refer to the last command yielded.
+    ///
+    /// After the iterator yields an error, it yields no more items.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use carcara::{ast::*, parser::{Config, Parser}};
+    /// let proof = "(assume h1 (not true)) (step t2 (cl) :rule hole :premises (h1))";
+    /// let mut pool = PrimitivePool::new();
+    /// let mut parser = Parser::new(&mut pool, Config::new(), proof.as_bytes())?;
+    /// let ids = parser
+    ///     .parse_proof_streaming()
+    ///     .map(|c| c.map(|c| c.id().clone()))
+    ///     .collect::<Result<Vec<_>, _>>()?;
+    /// assert_eq!(ids, ["h1", "t2"]);
+    /// # Ok::<(), carcara::Error>(())
+    /// ```
+    pub fn parse_proof_streaming(&mut self) -> ProofStream<'_, 'a, R, P> {
+        ProofStream {
+            parser: self,
+            num_commands: 0,
+            finished_assumes: false,
+            done: false,
+        }
+    }
 
-        // Some solvers print the satisfiability result (unsat) together with the proof. To save the
-        // user from having to remove this, we consume this first "unsat" token if it exists
+    /// Some solvers print the satisfiability result (unsat) together with the proof. To save the
+    /// user from having to remove this, we consume this first "unsat" token if it exists.
+    fn skip_unsat_token(&mut self) -> CarcaraResult<()> {
         if self.current_token == Token::Symbol("unsat".into()) {
             self.next_token()?;
         }
+        Ok(())
+    }
 
+    /// Parses the next command of the root proof, including all of its commands if it is a
+    /// subproof. `index` is the index of this command in the root proof. Returns `None` if there
+    /// are no more commands.
+    fn parse_root_command(
+        &mut self,
+        index: usize,
+        finished_assumes: &mut bool,
+    ) -> CarcaraResult<Option<ProofCommand>> {
+        // To avoid stack overflows in proofs with many nested subproofs, we parse the subproofs
+        // iteratively, instead of recursively. The `OpenSubproofs` struct only holds the data of
+        // the subproofs that are still open
+        let mut open = OpenSubproofs::default();
         while self.current_token != Token::Eof {
-            self.expect_token(Token::OpenParen)?;
-            let (token, position) = self.next_token()?;
-            let (id, command) = match token {
-                Token::ReservedWord(Reserved::Assume) => {
-                    let (id, term) = self.parse_assume_command()?;
-                    if end_step_stack.is_empty() && finished_assumes {
-                        log::warn!("`assume` command '{}' appears after `step` commands", &id);
-                    }
-                    (id.clone(), ProofCommand::Assume { id, term })
-                }
-                Token::ReservedWord(Reserved::Step) => {
-                    finished_assumes = true;
-                    let step = self.parse_step_command()?;
-                    (step.id.clone(), ProofCommand::Step(step))
-                }
-                Token::ReservedWord(Reserved::DefineFun) => {
-                    let (name, func_def) = self.parse_define_fun()?;
-                    self.state.function_defs.insert(name, func_def);
-                    continue;
-                }
-                Token::ReservedWord(Reserved::Anchor) => {
-                    let anchor = self.parse_anchor_command()?;
-
-                    // When we encounter an `anchor` command, we push a new scope into the step ids
-                    // symbol table, a fresh commands vector into the commands stack for the
-                    // subproof to fill, and the `anchor` data (end step and arguments) into their
-                    // respective stacks. All of this will be popped off at the end of the subproof.
-                    // We don't need to push a new scope into the symbol table because
-                    // `Parser::parse_anchor_command` already does that for us
-                    self.state.step_ids.push_scope();
-                    commands_stack.push(Vec::new());
-                    end_step_stack.push(anchor.end_step_id);
-                    subproof_args_stack.push((anchor.assignment_args, anchor.variable_args));
-                    last_subproof_id += 1;
-                    subproof_id_stack.push(last_subproof_id as usize);
-                    continue;
-                }
-                _ => {
-                    return Err(Error::Parser(ParserError::UnexpectedToken(token), position));
+            let num_scopes = self.state.symbol_table.num_scopes();
+            match self.parse_proof_command(&mut open, index, finished_assumes) {
+                Ok(Some(command)) => return Ok(Some(command)),
+                Ok(None) => (),
+                Err(e) => self.recover_from_error(e, num_scopes)?,
+            }
+        }
+
+        // If the input ends while there are still open subproofs, they should have been closed
+        // before the proof was finished
+        match open.end_steps.pop() {
+            None => Ok(None),
+            Some(end_step) => Err(Error::Parser(
+                ParserError::UnclosedSubproof(end_step.to_string()),
+                self.current_position,
+            )),
+        }
+    }
+
+    /// Parses a single proof command, which may be inside the subproofs in `open`. If the command
+    /// finishes a command of the root proof, returns that command.
+    fn parse_proof_command(
+        &mut self,
+        open: &mut OpenSubproofs,
+        index: usize,
+        finished_assumes: &mut bool,
+    ) -> CarcaraResult<Option<ProofCommand>> {
+        let start = self.current_span;
+
+        // The sorts of inferred symbols can only change in the command where they first appear
+        self.state.pending_inferred.clear();
+
+        self.expect_token(Token::OpenParen)?;
+        let (token, position) = self.next_token()?;
+        let (id, mut command) = match token {
+            Token::ReservedWord(Reserved::Assume) => {
+                let (id, term) = self.parse_assume_command()?;
+                if open.end_steps.is_empty() && *finished_assumes {
+                    log::warn!("`assume` command '{}' appears after `step` commands", &id);
                 }
+                (id.clone(), ProofCommand::Assume { id, term })
+            }
+            Token::ReservedWord(Reserved::Step) => {
+                *finished_assumes = true;
+                let step = self.parse_step_command()?;
+                (step.id.clone(), ProofCommand::Step(step))
+            }
+            Token::ReservedWord(Reserved::DefineFun) => {
+                let (name, func_def) = self.parse_define_fun()?;
+                self.state.function_defs.insert(name, func_def);
+                return Ok(None);
+            }
+            Token::ReservedWord(Reserved::DefineConst) => {
+                let (name, func_def) = self.parse_define_const()?;
+                self.state.function_defs.insert(name, func_def);
+                return Ok(None);
+            }
+            Token::ReservedWord(Reserved::DefineSort) => {
+                let sort_def = self.parse_define_sort()?;
+                self.state.sort_defs.insert(sort_def.name.clone(), sort_def);
+                return Ok(None);
+            }
+            // Declarations are not part of the Alethe format, but may appear in proofs that are
+            // checked without their problem (see `crate::check_proof_only`). In that case, the
+            // proof is first parsed as a problem, which already processes these commands
+            Token::ReservedWord(
+                Reserved::DeclareFun
+                | Reserved::DeclareConst
+                | Reserved::DeclareSort
+                | Reserved::DeclareDatatype
+                | Reserved::DeclareDatatypes
+                | Reserved::DefineFunRec
+                | Reserved::DefineFunsRec
+                | Reserved::SetLogic,
+            ) => {
+                self.ignore_until_close_parens()?;
+                return Ok(None);
+            }
+            Token::ReservedWord(Reserved::Anchor) => {
+                let anchor = self.parse_anchor_command()?;
+
+                // When we encounter an `anchor` command, we push a new scope into the step ids
+                // symbol table, a fresh commands vector into the commands stack for the subproof to
+                // fill, and the `anchor` data (end step and arguments) into their respective
+                // stacks. All of this will be popped off at the end of the subproof. We don't need
+                // to push a new scope into the symbol table because `Parser::parse_anchor_command`
+                // already does that for us
+                self.state.step_ids.push_scope();
+                open.commands.push(Vec::new());
+                open.end_steps.push(anchor.end_step_id);
+                open.args
+                    .push((anchor.assignment_args, anchor.variable_args));
+                open.ids.push(self.state.num_subproofs);
+                self.state.num_subproofs += 1;
+                return Ok(None);
+            }
+            _ => {
+                return Err(Error::Parser(ParserError::UnexpectedToken(token), position));
+            }
+        };
+        if self.state.step_ids.get(&id).is_some() {
+            let error = ParserError::RepeatedStepIndex(id.to_string());
+            return Err(Error::Parser(error, position));
+        }
+        self.state
+            .spans
+            .insert(id.to_string(), start.to(self.previous_span));
+
+        self.commands_parsed += 1;
+        if let Some(callback) = &mut self.progress_callback {
+            let progress = ParseProgress {
+                bytes_consumed: self.previous_span.bytes.1,
+                commands_parsed: self.commands_parsed,
             };
-            let id = HashCache::new(id);
-            if self.state.step_ids.get(&id).is_some() {
+            if callback(progress).is_break() {
+                return Err(Error::Parser(ParserError::Interrupted, position));
+            }
+        }
+
+        if open.end_steps.last() == Some(&id) {
+            // If this is the last step in a subproof, we need to pop all the subproof data off of
+            // the stacks and build the subproof command with it
+            self.state.symbol_table.pop_scope();
+            for (closed, _) in self.state.step_ids.pop_scope() {
+                self.state.step_ids.insert(closed, None);
+            }
+            let mut commands = open.commands.pop().unwrap();
+            commands.push(command);
+            open.end_steps.pop().unwrap();
+            let (assignment_args, variable_args) = open.args.pop().unwrap();
+            let subproof_id = open.ids.pop().unwrap();
+
+            // The subproof must contain at least two commands: the end step and the previous
+            // command it implicitly references
+            if commands.len() < 2 {
                 return Err(Error::Parser(
-                    ParserError::RepeatedStepIndex(id.unwrap()),
+                    ParserError::EmptySubproof(id.to_string()),
                     position,
                 ));
             }
 
-            commands_stack.last_mut().unwrap().push(command);
-            if end_step_stack.last() == Some(id.as_ref()) {
-                // If this is the last step in a subproof, we need to pop all the subproof data off
-                // of the stacks and build the subproof command with it
-                self.state.symbol_table.pop_scope();
-                self.state.step_ids.pop_scope();
-                let commands = commands_stack.pop().unwrap();
-                end_step_stack.pop().unwrap();
-                let (assignment_args, variable_args) = subproof_args_stack.pop().unwrap();
-                let subproof_id = subproof_id_stack.pop().unwrap();
-
-                // The subproof must contain at least two commands: the end step and the previous
-                // command it implicitly references
-                if commands.len() < 2 {
+            // We also need to make sure that the last command is in fact a `step`
+            match commands.last() {
+                Some(ProofCommand::Step(_)) => (),
+                _ => {
                     return Err(Error::Parser(
-                        ParserError::EmptySubproof(id.unwrap()),
+                        ParserError::LastSubproofStepIsNotStep(id.to_string()),
                         position,
-                    ));
+                    ))
                 }
+            };
 
-                // We also need to make sure that the last command is in fact a `step`
-                match commands.last() {
-                    Some(ProofCommand::Step(_)) => (),
-                    _ => {
-                        return Err(Error::Parser(
-                            ParserError::LastSubproofStepIsNotStep(id.unwrap()),
-                            position,
-                        ))
-                    }
-                };
+            command = ProofCommand::Subproof(Subproof {
+                commands,
+                assignment_args,
+                variable_args,
+                context_id: subproof_id,
+            });
+        }
 
-                commands_stack
-                    .last_mut()
-                    .unwrap()
-                    .push(ProofCommand::Subproof(Subproof {
-                        commands,
-                        assignment_args,
-                        variable_args,
-                        context_id: subproof_id,
-                    }));
+        let Some(commands) = open.commands.last_mut() else {
+            // If this is an `assume` command in the root proof whose term was named in the
+            // problem, it can also be referenced by that name
+            if let ProofCommand::Assume { term, .. } = &command {
+                for name in self.state.problem_names.get(term).into_iter().flatten() {
+                    self.state
+                        .named_assumes
+                        .entry(name.clone())
+                        .or_insert(index);
+                }
+            }
+            self.state.step_ids.insert(id, Some(index));
+            return Ok(Some(command));
+        };
+        commands.push(command);
+        self.state.step_ids.insert(id, Some(commands.len() - 1));
+        Ok(None)
+    }
+
+    /// Parses a proof in the Alethe format, recovering from errors instead of stopping at the
+    /// first one. When an error is found in a command, the parser records it and skips to the
+    /// next top-level s-expression, continuing from there. Returns the commands that were parsed
+    /// successfully, together with all errors found, in the order they were found.
+    ///
+    /// Since commands with errors are dropped, later commands that reference them may also lead
+    /// to errors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use carcara::{ast::*, parser::{Config, Parser}};
+    /// let proof = "
+    ///     (assume h1 (not true))
+    ///     (step t2 (cl) :rule hole :premises (h5))
+    ///     (step t3 (cl (not 1)) :rule hole)
+    ///     (step t4 (cl) :rule hole :premises (h1))
+    /// ";
+    /// let mut pool = PrimitivePool::new();
+    /// let mut parser = Parser::new(&mut pool, Config::new(), proof.as_bytes())?;
+    /// let (commands, errors) = parser.parse_proof_recovering();
+    /// assert_eq!(commands.len(), 2);
+    /// assert_eq!(errors.len(), 2);
+    /// # Ok::<(), carcara::Error>(())
+    /// ```
+    pub fn parse_proof_recovering(&mut self) -> (Vec<ProofCommand>, Vec<Error>) {
+        self.recovered_errors = Some(Vec::new());
+        let mut commands = Vec::new();
+        let mut finished_assumes = false;
+        let mut result = self.skip_unsat_token();
+        while result.is_ok() {
+            match self.parse_root_command(commands.len(), &mut finished_assumes) {
+                Ok(Some(command)) => commands.push(command),
+                Ok(None) => break,
+                Err(e) => result = Err(e),
             }
-            self.state
-                .step_ids
-                .insert(id, commands_stack.last().unwrap().len() - 1);
         }
-        match commands_stack.len() {
-            0 => unreachable!(),
-            1 => Ok(commands_stack.pop().unwrap()),
+        let mut errors = self.recovered_errors.take().unwrap();
+        errors.extend(result.err());
+        (commands, errors)
+    }
 
-            // If there is more than one vector in the commands stack, we are inside a subproof
-            // that should be closed before the outer proof is finished
-            _ => Err(Error::Parser(
-                ParserError::UnclosedSubproof(end_step_stack.pop().unwrap()),
-                self.current_position,
-            )),
+    /// If error recovery is enabled, records the error and skips to the end of the top-level
+    /// s-expression in which it was found, so parsing can continue from the next one. Any scopes
+    /// pushed into the symbol table after it had `num_scopes` scopes are also removed. If error
+    /// recovery is disabled, this simply returns the error.
+    fn recover_from_error(&mut self, error: Error, num_scopes: usize) -> CarcaraResult<()> {
+        let Some(errors) = &mut self.recovered_errors else {
+            return Err(error);
+        };
+        if let Error::Io(_) | Error::Parser(ParserError::Interrupted, _) = error {
+            return Err(error);
+        }
+        errors.push(error);
+
+        while self.state.symbol_table.num_scopes() > num_scopes {
+            self.state.symbol_table.pop_scope();
+        }
+        while self.parens_depth > 0 || !matches!(self.current_token, Token::OpenParen | Token::Eof)
+        {
+            let position = self.lexer.position();
+            match self.next_token() {
+                Ok(_) => (),
+
+                // If the lexer fails without consuming any characters, we need to skip the
+                // character where the error was found, or we would find the same error again
+                Err(Error::Parser(..)) if self.lexer.position() == position => {
+                    self.lexer.skip_char()?;
+                }
+                Err(Error::Parser(..)) => (),
+                Err(e) => return Err(e),
+            }
         }
+        Ok(())
     }
 
     /// Parses an `assume` proof command. This method assumes that the `(` and `assume` tokens were
     /// already consumed.
-    fn parse_assume_command(&mut self) -> CarcaraResult<(String, Rc<Term>)> {
-        let id = self.expect_symbol()?;
+    fn parse_assume_command(&mut self) -> CarcaraResult<(StepId, Rc<Term>)> {
+        let id = self.expect_step_id()?;
         let term = self.parse_term_expecting_sort(&Sort::Bool)?;
         self.ignore_remaining_attributes()?;
         self.expect_token(Token::CloseParen)?;
@@ -877,52 +1441,65 @@ impl<'a, R: BufRead> Parser<'a, R> {
     /// Parses a `step` proof command. This method assumes that the `(` and `step` tokens were
     /// already consumed.
     fn parse_step_command(&mut self) -> CarcaraResult<ProofStep> {
-        let id = self.expect_symbol()?;
-        let clause = self.parse_clause()?;
+        let id = self.expect_step_id()?;
+        let clause = self.parse_step_clause()?;
         self.expect_token(Token::Keyword("rule".into()))?;
         let rule = match self.next_token()? {
-            (Token::Symbol(s), _) => s,
-            (Token::ReservedWord(r), _) => format!("{}", r),
+            (Token::Symbol(s), _) => Symbol::from(s),
+            (Token::ReservedWord(r), _) => Symbol::from(format!("{}", r)),
             (other, pos) => {
                 return Err(Error::Parser(ParserError::UnexpectedToken(other), pos));
             }
         };
 
-        let premises = if self.current_token == Token::Keyword("premises".into()) {
-            self.next_token()?;
-            self.expect_token(Token::OpenParen)?;
-            self.parse_sequence(Self::parse_step_premise, true)?
-        } else {
-            Vec::new()
-        };
+        // Normally, the `:premises`, `:args` and `:discharge` attributes must be non-empty, and
+        // come in this order. SMTInterpol may write them in any order, so in that case we keep
+        // parsing them until none is found
+        let compat = self.config.smtinterpol_compat;
+        let (mut premises, mut args, mut discharge) = (Vec::new(), Vec::new(), Vec::new());
+        loop {
+            let mut found = false;
+            if self.current_token == Token::Keyword("premises".into()) {
+                self.next_token()?;
+                self.expect_token(Token::OpenParen)?;
+                premises = self.parse_sequence(Self::parse_step_premise, !compat)?;
+                found = true;
+            }
 
-        let args = if self.current_token == Token::Keyword("args".into()) {
-            self.next_token()?;
-            self.expect_token(Token::OpenParen)?;
+            if self.current_token == Token::Keyword("args".into()) {
+                self.next_token()?;
+                self.expect_token(Token::OpenParen)?;
 
-            // If the rule is `hole`, we want to allow any invalid arguments, so we read the rest of
-            // the `:args` attribute without trying to parse anything
-            if rule == "hole" {
-                self.ignore_until_close_parens()?;
-                Vec::new()
-            } else {
-                self.parse_sequence(Self::parse_proof_arg, true)?
+                // If the rule is `hole`, we want to allow any invalid arguments, so we read the
+                // rest of the `:args` attribute without trying to parse anything
+                if rule == "hole" {
+                    self.ignore_until_close_parens()?;
+                } else if rule == "rare_rewrite" {
+                    args = self.parse_rare_rewrite_args()?;
+                } else {
+                    args = self.parse_sequence(Self::parse_proof_arg, !compat)?;
+                }
+                found = true;
             }
-        } else {
-            Vec::new()
-        };
 
-        // For some rules (notably the `subproof` rule), there is also a `:discharge` attribute that
-        // takes a series of command ids, in addition to the regular premises
-        let discharge = if self.current_token == Token::Keyword("discharge".into()) {
-            self.next_token()?;
-            self.expect_token(Token::OpenParen)?;
-            self.parse_sequence(|p| p.parse_discharge_premise(&id), true)?
-        } else {
-            Vec::new()
-        };
+            // For some rules (notably the `subproof` rule), there is also a `:discharge` attribute
+            // that takes a series of command ids, in addition to the regular premises
+            if self.current_token == Token::Keyword("discharge".into()) {
+                self.next_token()?;
+                self.expect_token(Token::OpenParen)?;
+                discharge = self.parse_sequence(|p| p.parse_discharge_premise(&id), !compat)?;
+                found = true;
+            }
 
-        self.ignore_remaining_attributes()?;
+            if !compat || !found {
+                break;
+            }
+        }
+
+        let attributes = self.parse_remaining_attributes()?;
+        if !attributes.is_empty() {
+            self.state.attributes.insert(id.to_string(), attributes);
+        }
         self.expect_token(Token::CloseParen)?;
 
         Ok(ProofStep {
@@ -937,14 +1514,23 @@ impl<'a, R: BufRead> Parser<'a, R> {
 
     /// Parses a premise for a `step` command. This already converts it into the depth and command
     /// index used to reference commands in the AST.
+    ///
+    /// If no command has the given id, the premise may also be the name given to a problem term
+    /// using the `:named` attribute, in which case it references the `assume` command in the root
+    /// proof that introduces that term.
     fn parse_step_premise(&mut self) -> CarcaraResult<(usize, usize)> {
         let position = self.current_position;
-        let id = HashCache::new(self.expect_symbol()?);
-        self.state
-            .step_ids
-            .get_with_depth(&id)
-            .map(|(d, &i)| (d, i))
-            .ok_or_else(|| Error::Parser(ParserError::UndefinedStepIndex(id.unwrap()), position))
+        let id = self.expect_step_id()?;
+        self.get_step_index(&id)
+            .or_else(|| self.state.named_assumes.get(id.as_str()).map(|&i| (0, i)))
+            .ok_or_else(|| Error::Parser(ParserError::UndefinedStepIndex(id.to_string()), position))
+    }
+
+    /// Returns the depth and index of the command with the given id, if it can be referenced from
+    /// the current scope.
+    fn get_step_index(&self, id: &str) -> Option<(usize, usize)> {
+        let (depth, &index) = self.state.step_ids.get_with_depth(id)?;
+        Some((depth, index?))
     }
 
     /// Parses an argument for the `:discharge` attribute.
@@ -953,18 +1539,13 @@ impl<'a, R: BufRead> Parser<'a, R> {
     /// id. That is, the command `t5.t4.h2` is passed as simply `h2`. This behavior is not present
     /// in other SMT solvers, like cvc5. To work around that, this function tries to find the
     /// command considering both possibilities.
-    fn parse_discharge_premise(&mut self, root_id: &str) -> CarcaraResult<(usize, usize)> {
+    fn parse_discharge_premise(&mut self, root_id: &StepId) -> CarcaraResult<(usize, usize)> {
         let position = self.current_position;
-        let id = self.expect_symbol()?;
-        let absolute_id = format!("{}.{}", root_id, &id);
-        let id = HashCache::new(id);
-        let absolute_id = HashCache::new(absolute_id);
-        self.state
-            .step_ids
-            .get_with_depth(&absolute_id)
-            .or_else(|| self.state.step_ids.get_with_depth(&id))
-            .map(|(d, &i)| (d, i))
-            .ok_or_else(|| Error::Parser(ParserError::UndefinedStepIndex(id.unwrap()), position))
+        let id = self.expect_step_id()?;
+        let absolute_id = root_id.child(&id);
+        self.get_step_index(&absolute_id)
+            .or_else(|| self.get_step_index(&id))
+            .ok_or_else(|| Error::Parser(ParserError::UndefinedStepIndex(id.to_string()), position))
     }
 
     /// Parses an `anchor` proof command. This method assumes that the `(` and `anchor` tokens were
@@ -973,24 +1554,33 @@ impl<'a, R: BufRead> Parser<'a, R> {
     /// In order to parse the subproof arguments, this method pushes a new scope into the symbol
     /// table which must be removed after parsing the subproof.
     fn parse_anchor_command(&mut self) -> CarcaraResult<AnchorCommand> {
-        self.expect_token(Token::Keyword("step".into()))?;
-        let end_step_id = self.expect_symbol()?;
-
         // We have to push a new scope into the symbol table in order to parse the subproof
         // arguments
         self.state.symbol_table.push_scope();
 
+        // veriT may write the `:args` attribute before the `:step` attribute
+        let args_first =
+            self.config.verit_compat && self.current_token == Token::Keyword("args".into());
+        let mut args = if args_first {
+            self.parse_anchor_args()?
+        } else {
+            Vec::new()
+        };
+        self.expect_token(Token::Keyword("step".into()))?;
+        let end_step_id = self.expect_step_id()?;
+        if !args_first && self.current_token == Token::Keyword("args".into()) {
+            args = self.parse_anchor_args()?;
+        }
+
         let mut assignment_args = Vec::new();
         let mut variable_args = Vec::new();
-        if self.current_token == Token::Keyword("args".into()) {
-            self.next_token()?;
-            self.expect_token(Token::OpenParen)?;
-            let args = self.parse_sequence(Parser::parse_anchor_argument, true)?;
-            for a in args {
-                match a {
-                    AnchorArg::Assign(var, value) => assignment_args.push((var.clone(), value)),
-                    AnchorArg::Variable(var) => variable_args.push(var.clone()),
+        for a in args {
+            match a {
+                AnchorArg::Assign(var, value, fresh) => {
+                    variable_args.extend(fresh);
+                    assignment_args.push((var, value));
                 }
+                AnchorArg::Variable(var) => variable_args.push(var),
             }
         }
         self.ignore_remaining_attributes()?;
@@ -1002,20 +1592,66 @@ impl<'a, R: BufRead> Parser<'a, R> {
         })
     }
 
+    /// Parses the `:args` attribute of an `anchor` proof command. This method assumes that the
+    /// current token is the `:args` keyword.
+    fn parse_anchor_args(&mut self) -> CarcaraResult<Vec<AnchorArg>> {
+        self.next_token()?;
+        self.expect_token(Token::OpenParen)?;
+        self.parse_sequence(Self::parse_anchor_argument, true)
+    }
+
     /// Parses an argument for an `anchor` proof command. This can be either a variable binding of
-    /// the form `(<symbol> <sort>)` or an assignment, of the form `(:= <symbol> <term>)`.
+    /// the form `(<symbol> <sort>)` or an assignment. Assignments can be of the form
+    /// `(:= <symbol> <term>)`, or `(:= (<symbol> <sort>) <term>)`, in which case the term must be
+    /// of the given sort. In the latter form, if the term is a symbol that is not yet defined, it
+    /// is introduced as a fresh variable of the given sort, as if it was also a variable binding.
     fn parse_anchor_argument(&mut self) -> CarcaraResult<AnchorArg> {
         self.expect_token(Token::OpenParen)?;
         Ok(if self.current_token == Token::Keyword("=".into()) {
             self.next_token()?;
-            let var = self.expect_symbol()?;
-            let value = self.parse_term()?;
+            let mut fresh = None;
+            let is_sorted = self.current_token == Token::OpenParen;
+            let expected = if is_sorted {
+                AletheVersion::Current
+            } else {
+                AletheVersion::Legacy
+            };
+            if let Some(version) = self.config.alethe_version.filter(|&v| v != expected) {
+                let error = ParserError::UnsupportedInVersion(
+                    if is_sorted {
+                        "sorted anchor assignments"
+                    } else {
+                        "unsorted anchor assignments"
+                    },
+                    version,
+                );
+                return Err(Error::Parser(error, self.current_position));
+            }
+            let (var, value) = if is_sorted {
+                let (var, sort) = self.parse_sorted_var()?;
+                let is_fresh = match &self.current_token {
+                    Token::Symbol(s) => self.state.symbol_table.get(&Symbol::new(s)).is_none(),
+                    _ => false,
+                };
+                let value = if is_fresh {
+                    let fresh_var = (Symbol::from(self.expect_symbol()?), sort);
+                    self.insert_sorted_var(fresh_var.clone());
+                    let value = self.pool.add(fresh_var.clone().into());
+                    fresh = Some(fresh_var);
+                    value
+                } else {
+                    self.parse_term_expecting_sort(sort.as_sort().unwrap())?
+                };
+                (var, value)
+            } else {
+                (Symbol::from(self.expect_symbol()?), self.parse_term()?)
+            };
             let sort = self.pool.sort(&value);
-            self.insert_sorted_var((var.clone(), sort));
+            self.insert_sorted_var((var, sort));
             self.expect_token(Token::CloseParen)?;
-            AnchorArg::Assign(var, value)
+            AnchorArg::Assign(var, value, fresh)
         } else {
-            let symbol = self.expect_symbol()?;
+            let symbol = Symbol::from(self.expect_symbol()?);
             let sort = self.parse_sort()?;
             let var = (symbol, self.pool.add(sort));
             self.insert_sorted_var(var.clone());
@@ -1026,47 +1662,328 @@ impl<'a, R: BufRead> Parser<'a, R> {
 
     /// Parses a `declare-fun` proof command. Returns the function name and a term representing its
     /// sort. This method assumes that the `(` and `declare-fun` tokens were already consumed.
-    fn parse_declare_fun(&mut self) -> CarcaraResult<(String, Rc<Term>)> {
+    fn parse_declare_fun(&mut self) -> CarcaraResult<(String, Vec<String>, Rc<Term>)> {
         let name = self.expect_symbol()?;
-        let sort = {
-            self.expect_token(Token::OpenParen)?;
-            let mut sorts = self.parse_sequence(Self::parse_sort, false)?;
-            sorts.push(self.parse_sort()?);
-            let sorts = self.pool.add_all(sorts);
-            if sorts.len() == 1 {
-                sorts.into_iter().next().unwrap()
+        self.expect_token(Token::OpenParen)?;
+        let sort_params = self.parse_par_params()?;
+        let sorts = self.with_sort_params(&sort_params, |p| {
+            if !sort_params.is_empty() {
+                p.expect_token(Token::OpenParen)?;
+            }
+            let mut sorts = p.parse_sequence(Self::parse_sort, false)?;
+            sorts.push(p.parse_sort()?);
+            if !sort_params.is_empty() {
+                p.expect_token(Token::CloseParen)?;
+            }
+            Ok(sorts)
+        })?;
+        let sorts = self.pool.add_all(sorts);
+        let sort = if sorts.len() == 1 {
+            sorts.into_iter().next().unwrap()
+        } else {
+            self.pool.add(Term::Sort(Sort::Function(sorts)))
+        };
+        self.expect_token(Token::CloseParen)?;
+        Ok((name, sort_params, sort))
+    }
+
+    /// Parses the sort parameters of a polymorphic declaration or definition, written as
+    /// `par (<symbol>+)`, if they are present. Returns an empty vector otherwise. This method
+    /// assumes that the `(` token that precedes `par` was already consumed.
+    fn parse_par_params(&mut self) -> CarcaraResult<Vec<String>> {
+        if self.current_token != Token::Symbol("par".into()) {
+            return Ok(Vec::new());
+        }
+        self.next_token()?;
+        self.expect_token(Token::OpenParen)?;
+        self.parse_sequence(Self::expect_symbol, true)
+    }
+
+    /// Calls `f` with the given sort parameters in scope. While in scope, the sort parameters are
+    /// treated as declared sorts of arity zero, shadowing any sort definitions or datatypes with
+    /// the same name. Afterwards, we restore any declarations and definitions that they may have
+    /// shadowed.
+    fn with_sort_params<T>(
+        &mut self,
+        params: &[String],
+        f: impl FnOnce(&mut Self) -> CarcaraResult<T>,
+    ) -> CarcaraResult<T> {
+        let state = &mut self.state;
+        let shadowed: Vec<_> = params
+            .iter()
+            .map(|p| {
+                let declaration = state.sort_declarations.insert(p.clone(), 0);
+                let definition = state.sort_defs.shift_remove(p);
+                let datatype = state.datatypes.shift_remove(p);
+                (declaration, definition, datatype)
+            })
+            .collect();
+        let result = f(self);
+        let state = &mut self.state;
+        for (p, (declaration, definition, datatype)) in params.iter().zip(shadowed) {
+            match declaration {
+                Some(arity) => state.sort_declarations.insert(p.clone(), arity),
+                None => state.sort_declarations.shift_remove(p),
+            };
+            if let Some(definition) = definition {
+                state.sort_defs.insert(p.clone(), definition);
+            }
+            if let Some(arity) = datatype {
+                state.datatypes.insert(p.clone(), arity);
+            }
+        }
+        result
+    }
+
+    /// Parses a declare-sort proof command. Returns the sort name and its arity. This method
+    /// assumes that the `(` and `declare-sort` tokens were already consumed.
+    fn parse_declare_sort(&mut self) -> CarcaraResult<(String, usize)> {
+        let name = self.expect_symbol()?;
+        let arity_pos = self.current_position;
+        let arity = self.expect_numeral()?;
+        self.expect_token(Token::CloseParen)?;
+        let arity = arity.to_usize().ok_or(Error::Parser(
+            ParserError::InvalidSortArity(arity),
+            arity_pos,
+        ))?;
+        Ok((name, arity))
+    }
+
+    /// Parses a `declare-datatypes` command, and adds the constructor, selector and tester symbols
+    /// of each datatype to the symbol table. This method assumes that the `(` and
+    /// `declare-datatypes` tokens were already consumed.
+    fn parse_declare_datatypes(&mut self) -> CarcaraResult<Vec<DatatypeDef>> {
+        self.expect_token(Token::OpenParen)?;
+        let declarations = self.parse_sequence(
+            |p| {
+                p.expect_token(Token::OpenParen)?;
+                let pos = p.current_position;
+                let name = p.expect_symbol()?;
+                let arity_pos = p.current_position;
+                let arity = p.expect_numeral()?;
+                p.expect_token(Token::CloseParen)?;
+                let arity = arity.to_usize().ok_or(Error::Parser(
+                    ParserError::InvalidSortArity(arity),
+                    arity_pos,
+                ))?;
+                Ok((name, arity, pos))
+            },
+            true,
+        )?;
+
+        // All datatype names must be known before the constructors are parsed, since the
+        // datatypes may be mutually recursive
+        for (name, arity, _) in &declarations {
+            self.state.datatypes.insert(name.clone(), *arity);
+        }
+
+        self.expect_token(Token::OpenParen)?;
+        let mut datatypes = Vec::with_capacity(declarations.len());
+        for (name, arity, pos) in declarations {
+            datatypes.push(self.parse_datatype_constructors(name, Some(arity), pos)?);
+        }
+        self.expect_token(Token::CloseParen)?;
+        self.expect_token(Token::CloseParen)?;
+
+        for datatype in &datatypes {
+            self.declare_datatype_symbols(datatype);
+        }
+        Ok(datatypes)
+    }
+
+    /// Parses the list of constructors of a datatype, each with its selectors. If the datatype is
+    /// parametric, the list is preceded by its sort parameters, written as `par (<symbol>+)`. The
+    /// number of parameters must match `arity`, if it is given, as in a `declare-datatypes`
+    /// command. Otherwise, as in a `declare-datatype` command, the datatype is declared with the
+    /// number of parameters as its arity.
+    fn parse_datatype_constructors(
+        &mut self,
+        name: String,
+        arity: Option<usize>,
+        pos: Position,
+    ) -> CarcaraResult<DatatypeDef> {
+        self.expect_token(Token::OpenParen)?;
+        let sort_params = self.parse_par_params()?;
+        match arity {
+            Some(arity) if arity != sort_params.len() => {
+                return Err(Error::Parser(
+                    ParserError::WrongNumberOfArgs(arity.into(), sort_params.len()),
+                    pos,
+                ));
+            }
+            Some(_) => (),
+            None => {
+                self.state.datatypes.insert(name.clone(), sort_params.len());
+            }
+        }
+        let constructors = self.with_sort_params(&sort_params, |p| {
+            if !sort_params.is_empty() {
+                p.expect_token(Token::OpenParen)?;
+            }
+            let constructors = p.parse_datatype_constructor_list()?;
+            if !sort_params.is_empty() {
+                p.expect_token(Token::CloseParen)?;
+            }
+            Ok(constructors)
+        })?;
+        Ok(DatatypeDef { name, sort_params, constructors })
+    }
+
+    /// Parses a non-empty list of datatype constructors, each with its selectors. This method
+    /// assumes that the `(` token that opens the list was already consumed.
+    fn parse_datatype_constructor_list(&mut self) -> CarcaraResult<Vec<DatatypeConstructor>> {
+        self.parse_sequence(
+            |p| {
+                // Nullary constructors may be given without parentheses
+                if let Token::Symbol(_) = p.current_token {
+                    let name = p.expect_symbol()?;
+                    return Ok(DatatypeConstructor { name, selectors: Vec::new() });
+                }
+                p.expect_token(Token::OpenParen)?;
+                let name = p.expect_symbol()?;
+                let selectors = p.parse_sequence(
+                    |p| {
+                        p.expect_token(Token::OpenParen)?;
+                        let selector = Symbol::from(p.expect_symbol()?);
+                        let sort = p.parse_sort()?;
+                        p.expect_token(Token::CloseParen)?;
+                        Ok((selector, p.pool.add(sort)))
+                    },
+                    false,
+                )?;
+                Ok(DatatypeConstructor { name, selectors })
+            },
+            true,
+        )
+    }
+
+    /// Adds the constructor, selector and tester symbols of a datatype to the symbol table. If the
+    /// datatype is parametric, these symbols are instead declared as polymorphic functions, whose
+    /// sort parameters are the parameters of the datatype.
+    fn declare_datatype_symbols(&mut self, datatype: &DatatypeDef) {
+        let sort_args = datatype
+            .sort_params
+            .iter()
+            .map(|p| self.pool.add(Term::Sort(Sort::Atom(p.clone(), Vec::new()))))
+            .collect();
+        let datatype_sort = self
+            .pool
+            .add(Term::Sort(Sort::Datatype(datatype.name.clone(), sort_args)));
+        let bool_sort = self.pool.add(Term::Sort(Sort::Bool));
+        for constructor in &datatype.constructors {
+            let constructor_sort = if constructor.selectors.is_empty() {
+                datatype_sort.clone()
             } else {
+                let mut sorts: Vec<_> = constructor
+                    .selectors
+                    .iter()
+                    .map(|(_, s)| s.clone())
+                    .collect();
+                sorts.push(datatype_sort.clone());
                 self.pool.add(Term::Sort(Sort::Function(sorts)))
+            };
+            self.declare_datatype_symbol(
+                &datatype.sort_params,
+                (Symbol::new(&constructor.name), constructor_sort),
+            );
+
+            for (selector, sort) in &constructor.selectors {
+                let selector_sort = Sort::Function(vec![datatype_sort.clone(), sort.clone()]);
+                let selector_sort = self.pool.add(Term::Sort(selector_sort));
+                self.declare_datatype_symbol(&datatype.sort_params, (*selector, selector_sort));
             }
-        };
-        self.expect_token(Token::CloseParen)?;
-        Ok((name, sort))
+
+            let tester_sort = Sort::Function(vec![datatype_sort.clone(), bool_sort.clone()]);
+            let tester_sort = self.pool.add(Term::Sort(tester_sort));
+            self.declare_datatype_symbol(
+                &datatype.sort_params,
+                (constructor.tester_name().into(), tester_sort),
+            );
+        }
     }
 
-    /// Parses a declare-sort proof command. Returns the sort name and its arity. This method
-    /// assumes that the `(` and `declare-sort` tokens were already consumed.
-    fn parse_declare_sort(&mut self) -> CarcaraResult<(String, usize)> {
-        let name = self.expect_symbol()?;
-        let arity_pos = self.current_position;
-        let arity = self.expect_numeral()?;
-        self.expect_token(Token::CloseParen)?;
-        let arity = arity.to_usize().ok_or(Error::Parser(
-            ParserError::InvalidSortArity(arity),
-            arity_pos,
-        ))?;
-        Ok((name, arity))
+    /// Declares a constructor, selector or tester symbol of a datatype with the given sort
+    /// parameters.
+    fn declare_datatype_symbol(&mut self, sort_params: &[String], (name, sort): SortedVar) {
+        if sort_params.is_empty() {
+            self.insert_sorted_var((name, sort));
+        } else {
+            let decl = PolymorphicDecl {
+                sort_params: sort_params.to_vec(),
+                sort,
+            };
+            self.state.polymorphic_decls.insert(name.to_string(), decl);
+        }
     }
 
     /// Parses a `define-fun` proof command. Returns the function name and its definition. This
     /// method assumes that the `(` and `define-fun` tokens were already consumed.
+    ///
+    /// Like in a `declare-fun` command, the signature of a polymorphic definition is preceded by
+    /// its sort parameters, as in `(define-fun f (par (X) ((x X)) X) x)`. The sort parameters are
+    /// in scope in both the signature and the body.
     fn parse_define_fun(&mut self) -> CarcaraResult<(String, FunctionDef)> {
+        let name = self.expect_symbol()?;
+        self.expect_token(Token::OpenParen)?;
+        let sort_params = self.parse_par_params()?;
+        let (params, body) = self.with_sort_params(&sort_params, |p| {
+            if !sort_params.is_empty() {
+                p.expect_token(Token::OpenParen)?;
+            }
+            let params = p.parse_sequence(Self::parse_sorted_var, false)?;
+            let return_sort = p.parse_sort()?;
+            if !sort_params.is_empty() {
+                p.expect_token(Token::CloseParen)?;
+            }
+
+            // In order to correctly parse the function body, we push a new scope to the symbol
+            // table and add the functions arguments to it.
+            p.state.symbol_table.push_scope();
+            for var in &params {
+                p.insert_sorted_var(var.clone());
+            }
+            let body = p.parse_term_expecting_sort(return_sort.as_sort().unwrap());
+            p.state.symbol_table.pop_scope();
+            Ok((params, body?))
+        })?;
+
+        self.expect_token(Token::CloseParen)?;
+
+        Ok((name, FunctionDef { sort_params, params, body }))
+    }
+
+    /// Parses the name, parameters and return sort of a recursive function definition, and
+    /// declares the function, so it can be referenced in its own body. Returns the function term,
+    /// the parameters and the return sort.
+    fn parse_recursive_signature(&mut self) -> CarcaraResult<(Rc<Term>, Vec<SortedVar>, Rc<Term>)> {
         let name = self.expect_symbol()?;
         self.expect_token(Token::OpenParen)?;
         let params = self.parse_sequence(Self::parse_sorted_var, false)?;
         let return_sort = self.parse_sort()?;
+        let return_sort = self.pool.add(return_sort);
+
+        let sort = if params.is_empty() {
+            return_sort.clone()
+        } else {
+            let mut sorts: Vec<_> = params.iter().map(|(_, sort)| sort.clone()).collect();
+            sorts.push(return_sort.clone());
+            self.pool.add(Term::Sort(Sort::Function(sorts)))
+        };
+        self.insert_sorted_var((Symbol::new(&name), sort.clone()));
+        self.prelude()
+            .function_declarations
+            .push((name.clone(), sort.clone()));
+        let function = self.pool.add(Term::new_var(name, sort));
+        Ok((function, params, return_sort))
+    }
 
-        // In order to correctly parse the function body, we push a new scope to the symbol table
-        // and add the functions arguments to it.
+    /// Parses the body of a recursive function definition, given its signature, and adds a premise
+    /// that defines the function. For a function `f` with parameters `x1, ..., xn`, the premise is
+    /// `(forall ((x1 S1) ... (xn Sn)) (= (f x1 ... xn) body))`.
+    fn parse_recursive_body(
+        &mut self,
+        (function, params, return_sort): (Rc<Term>, Vec<SortedVar>, Rc<Term>),
+    ) -> CarcaraResult<()> {
         self.state.symbol_table.push_scope();
         for var in &params {
             self.insert_sorted_var(var.clone());
@@ -1074,9 +1991,57 @@ impl<'a, R: BufRead> Parser<'a, R> {
         let body = self.parse_term_expecting_sort(return_sort.as_sort().unwrap())?;
         self.state.symbol_table.pop_scope();
 
+        let definition = if params.is_empty() {
+            self.pool
+                .add(Term::Op(Operator::Equals, vec![function, body]))
+        } else {
+            let args = params
+                .iter()
+                .map(|var| self.pool.add(var.clone().into()))
+                .collect();
+            let application = self.pool.add(Term::App(function, args));
+            let equality = self
+                .pool
+                .add(Term::Op(Operator::Equals, vec![application, body]));
+            self.pool.add(Term::Quant(
+                Quantifier::Forall,
+                BindingList(params),
+                equality,
+            ))
+        };
+        self.premises().insert(definition);
+        Ok(())
+    }
+
+    /// Parses a `define-const` command. Returns the constant name and its definition, represented
+    /// as a function definition with no parameters. This method assumes that the `(` and
+    /// `define-const` tokens were already consumed.
+    fn parse_define_const(&mut self) -> CarcaraResult<(String, FunctionDef)> {
+        let name = self.expect_symbol()?;
+        let sort = self.parse_sort()?;
+        let body = self.parse_term_expecting_sort(sort.as_sort().unwrap())?;
         self.expect_token(Token::CloseParen)?;
+        Ok((
+            name,
+            FunctionDef {
+                sort_params: Vec::new(),
+                params: Vec::new(),
+                body,
+            },
+        ))
+    }
 
-        Ok((name, FunctionDef { params, body }))
+    /// Parses a `define-sort` command. This method assumes that the `(` and `define-sort` tokens
+    /// were already consumed.
+    fn parse_define_sort(&mut self) -> CarcaraResult<SortDefinition> {
+        let name = self.expect_symbol()?;
+        self.expect_token(Token::OpenParen)?;
+        let params = self.parse_sequence(Self::expect_symbol, false)?;
+        let body = self.with_sort_params(&params, Self::parse_sort)?;
+        let body = self.pool.add(body);
+
+        self.expect_token(Token::CloseParen)?;
+        Ok(SortDefinition { name, params, body })
     }
 
     /// Parses a clause of the form `(cl <term>*)`.
@@ -1086,6 +2051,33 @@ impl<'a, R: BufRead> Parser<'a, R> {
         self.parse_sequence(|p| p.parse_term_expecting_sort(&Sort::Bool), false)
     }
 
+    /// Parses the conclusion of a `step` command. This is usually a clause of the form
+    /// `(cl <term>*)`, but if the `verit_compat` option is enabled, it may also be a single term,
+    /// in which case `false` stands for the empty clause.
+    fn parse_step_clause(&mut self) -> CarcaraResult<Vec<Rc<Term>>> {
+        if !self.config.verit_compat {
+            return self.parse_clause();
+        }
+        let pos = self.current_position;
+        let term = if self.current_token == Token::OpenParen {
+            self.next_token()?;
+            if self.current_token == Token::ReservedWord(Reserved::Cl) {
+                self.next_token()?;
+                return self.parse_sequence(|p| p.parse_term_expecting_sort(&Sort::Bool), false);
+            }
+            self.parse_application()?
+        } else {
+            self.parse_term()?
+        };
+        SortError::assert_eq(&Sort::Bool, self.pool.sort(&term).as_sort().unwrap())
+            .map_err(|e| Error::Parser(e.into(), pos))?;
+        Ok(if term.is_bool_false() {
+            Vec::new()
+        } else {
+            vec![term]
+        })
+    }
+
     /// Parses an argument for a `step` command.
     fn parse_proof_arg(&mut self) -> CarcaraResult<ProofArg> {
         if self.current_token == Token::OpenParen {
@@ -1095,8 +2087,13 @@ impl<'a, R: BufRead> Parser<'a, R> {
             // `(:= <symbol> <term>)`, or a regular term that starts with `(`. Note that the
             // lexer reads `:=` as a keyword with contents `=`.
             if self.current_token == Token::Keyword("=".into()) {
+                if let Some(version @ AletheVersion::Current) = self.config.alethe_version {
+                    let error =
+                        ParserError::UnsupportedInVersion("assign style arguments", version);
+                    return Err(Error::Parser(error, self.current_position));
+                }
                 self.next_token()?; // Consume `:=` token
-                let name = self.expect_symbol()?;
+                let name = Symbol::from(self.expect_symbol()?);
                 let value = self.parse_term()?;
                 self.expect_token(Token::CloseParen)?;
                 Ok(ProofArg::Assign(name, value))
@@ -1113,10 +2110,41 @@ impl<'a, R: BufRead> Parser<'a, R> {
         }
     }
 
+    /// Parses the arguments of a `rare_rewrite` step, after the `(` token. The first argument is
+    /// the name of the RARE rule, which cvc5 writes as a string, but which may also be written as
+    /// a symbol. In that case, it is converted to a string constant. The remaining arguments may be
+    /// regular terms or lists of terms, of the form `(@list <term>*)`.
+    fn parse_rare_rewrite_args(&mut self) -> CarcaraResult<Vec<ProofArg>> {
+        let mut args = Vec::new();
+        if let Token::Symbol(name) = &self.current_token {
+            let name = Term::Const(Constant::String(name.clone()));
+            args.push(ProofArg::Term(self.pool.add(name)));
+            self.next_token()?;
+        }
+        let rest = self.parse_sequence(Self::parse_rare_rewrite_arg, args.is_empty())?;
+        args.extend(rest);
+        Ok(args)
+    }
+
+    /// Parses an argument of a `rare_rewrite` step, other than the rule name.
+    fn parse_rare_rewrite_arg(&mut self) -> CarcaraResult<ProofArg> {
+        if self.current_token != Token::OpenParen {
+            return Ok(ProofArg::Term(self.parse_term()?));
+        }
+        self.next_token()?; // Consume `(` token
+        if self.current_token == Token::Symbol("@list".into()) {
+            self.next_token()?; // Consume `@list` token
+            let terms = self.parse_sequence(Self::parse_term, false)?;
+            Ok(ProofArg::List(terms))
+        } else {
+            Ok(ProofArg::Term(self.parse_application()?))
+        }
+    }
+
     /// Parses a sorted variable of the form `(<symbol> <sort>)`.
     fn parse_sorted_var(&mut self) -> CarcaraResult<SortedVar> {
         self.expect_token(Token::OpenParen)?;
-        let symbol = self.expect_symbol()?;
+        let symbol = Symbol::from(self.expect_symbol()?);
         let sort = self.parse_sort()?;
         self.expect_token(Token::CloseParen)?;
         Ok((symbol, self.pool.add(sort)))
@@ -1124,12 +2152,24 @@ impl<'a, R: BufRead> Parser<'a, R> {
 
     /// Parses a term.
     pub fn parse_term(&mut self) -> CarcaraResult<Rc<Term>> {
+        self.term_count += 1;
+        if let Some(max) = self.config.limits.max_term_count {
+            if self.term_count > max {
+                let error = ParserError::LimitExceeded(ParserLimitKind::TermCount, max);
+                return Err(Error::Parser(error, self.current_position));
+            }
+        }
         let term = match self.next_token()? {
             (Token::Bitvector { value, width }, _) => Term::new_bv(value, width),
             (Token::Numeral(n), _) if self.interpret_integers_as_reals => Term::new_real(n),
             (Token::Numeral(n), _) => Term::new_int(n),
             (Token::Decimal(r), _) => Term::new_real(r),
             (Token::String(s), _) => Term::new_string(s),
+            (Token::Symbol(s), pos) if self.is_polymorphic(&s) => {
+                // Without arguments, the sort parameters can only be inferred from a sort
+                // ascription, using `as`
+                return Err(Error::Parser(ParserError::AmbiguousSortParams(s), pos));
+            }
             (Token::Symbol(s), pos) => {
                 // Check to see if there is a nullary function defined with this name
                 return Ok(if let Some(func_def) = self.state.function_defs.get(&s) {
@@ -1150,7 +2190,18 @@ impl<'a, R: BufRead> Parser<'a, R> {
                     self.make_var(s).map_err(|err| Error::Parser(err, pos))?
                 });
             }
-            (Token::OpenParen, _) => return self.parse_application(),
+            (Token::OpenParen, pos) => {
+                if let Some(max) = self.config.limits.max_term_depth {
+                    if self.term_depth >= max {
+                        let error = ParserError::LimitExceeded(ParserLimitKind::TermDepth, max);
+                        return Err(Error::Parser(error, pos));
+                    }
+                }
+                self.term_depth += 1;
+                let result = self.parse_application();
+                self.term_depth -= 1;
+                return result;
+            }
             (other, pos) => {
                 return Err(Error::Parser(ParserError::UnexpectedToken(other), pos));
             }
@@ -1205,10 +2256,12 @@ impl<'a, R: BufRead> Parser<'a, R> {
     /// consumed.
     fn parse_choice_term(&mut self) -> CarcaraResult<Rc<Term>> {
         self.expect_token(Token::OpenParen)?;
+        self.state.symbol_table.push_scope();
         let var = self.parse_sorted_var()?;
         self.insert_sorted_var(var.clone());
         self.expect_token(Token::CloseParen)?;
-        let inner = self.parse_term()?;
+        let inner = self.parse_term_expecting_sort(&Sort::Bool)?;
+        self.state.symbol_table.pop_scope();
         self.expect_token(Token::CloseParen)?;
         Ok(self.pool.add(Term::Choice(var, inner)))
     }
@@ -1240,10 +2293,10 @@ impl<'a, R: BufRead> Parser<'a, R> {
         let bindings = self.parse_sequence(
             |p| {
                 p.expect_token(Token::OpenParen)?;
-                let name = p.expect_symbol()?;
+                let name = Symbol::from(p.expect_symbol()?);
                 let value = p.parse_term()?;
                 let sort = p.pool.sort(&value);
-                p.insert_sorted_var((name.clone(), sort));
+                p.insert_sorted_var((name, sort));
                 p.expect_token(Token::CloseParen)?;
                 Ok((name, value))
             },
@@ -1275,10 +2328,12 @@ impl<'a, R: BufRead> Parser<'a, R> {
     /// Parses an annotated term, of the form `(! <term> <attribute>+)`. This method assumes that
     /// the `(` and `!` tokens were already consumed.
     ///
-    /// The two supported attributes are `:named` and `:pattern`, though the latter is ignored. If
-    /// any other attribute is present, an error will be returned.
+    /// The only attribute interpreted by the parser is `:named`. All other attributes, like
+    /// `:pattern`, are recorded with their values as they were written, so they can be recovered
+    /// using [`Parser::take_term_attributes`].
     fn parse_annotated_term(&mut self) -> CarcaraResult<Rc<Term>> {
         let inner = self.parse_term()?;
+        let mut attributes = Vec::new();
         self.parse_sequence(
             |p| {
                 let attribute = p.expect_keyword()?;
@@ -1287,7 +2342,13 @@ impl<'a, R: BufRead> Parser<'a, R> {
                         // If the term has a `:named` attribute, we introduce a new nullary function
                         // definition that maps the name to the term
                         let name = p.expect_symbol()?;
+                        if let Some((prelude, _)) = &mut p.problem {
+                            prelude.named_terms.insert(name.clone(), inner.clone());
+                            let names = p.state.problem_names.entry(inner.clone()).or_default();
+                            names.push(name.clone());
+                        }
                         let func_def = FunctionDef {
+                            sort_params: Vec::new(),
                             params: Vec::new(),
                             body: inner.clone(),
                         };
@@ -1295,39 +2356,38 @@ impl<'a, R: BufRead> Parser<'a, R> {
                         Ok(())
                     }
 
-                    // We allow unknown attributes, and just ignore them
-                    _ => match p.current_token {
-                        // If the argument is a list, we consume it until the `)` token
-                        Token::OpenParen => {
-                            p.next_token()?;
-                            p.ignore_until_close_parens()
-                        }
-
-                        // If the attribute has no argument, we don't do anything
-                        Token::Keyword(_) | Token::CloseParen | Token::Eof => Ok(()),
-
-                        // If the argument is a single token, we consume it
-                        _ => {
-                            p.next_token()?;
-                            Ok(())
-                        }
-                    },
+                    _ => {
+                        let value = p.parse_attribute_value()?;
+                        attributes.push(Attribute { keyword: attribute, value });
+                        Ok(())
+                    }
                 }
             },
             true,
         )?;
+        // Since terms are hash consed, the same term may be annotated many times in the input, as
+        // is common in proofs that repeat the problem's quantifiers. We only keep the attributes
+        // of the first annotation
+        if !attributes.is_empty() {
+            self.state
+                .term_attributes
+                .entry(inner.clone())
+                .or_insert(attributes);
+        }
         Ok(inner)
     }
 
     fn parse_indexed_operator(&mut self) -> CarcaraResult<(IndexedOperator, Vec<Constant>)> {
+        let pos = self.current_position;
         let bv_symbol = self.expect_symbol()?;
-        if let Some(value) = bv_symbol.strip_prefix("bv") {
-            let parsed_value = value.parse::<Integer>().unwrap();
+        let bv_value = bv_symbol.strip_prefix("bv").map(str::parse::<Integer>);
+        if let Some(Ok(parsed_value)) = bv_value {
             let mut args = self.parse_sequence(Self::parse_constant, true)?;
             args.insert(0, Constant::Integer(parsed_value));
             return Ok((IndexedOperator::BvConst, args));
         }
-        let op = IndexedOperator::from_str(bv_symbol.as_str()).unwrap();
+        let op = IndexedOperator::from_str(bv_symbol.as_str())
+            .map_err(|_| Error::Parser(ParserError::UndefinedIden(bv_symbol), pos))?;
         let args = self.parse_sequence(Self::parse_constant, true)?;
         Ok((op, args))
     }
@@ -1339,8 +2399,7 @@ impl<'a, R: BufRead> Parser<'a, R> {
         op_args: Vec<Constant>,
         args: Vec<Rc<Term>>,
     ) -> Result<Rc<Term>, ParserError> {
-        let sorts: Vec<_> = args.iter().map(|t| self.pool.sort(t)).collect();
-        match &op {
+        match op {
             IndexedOperator::BvConst => {
                 assert_num_args(&op_args, 2)?;
                 assert_num_args(&args, 0)?;
@@ -1354,52 +2413,278 @@ impl<'a, R: BufRead> Parser<'a, R> {
                 assert_indexed_op_args_value(&[op_args[1].clone()], 1..)?;
                 return Ok(self.pool.add(Term::Const(Constant::BitVec(value, width))));
             }
-            IndexedOperator::BvExtract => {
-                /*
-                ((_ extract i j) (_ BitVec m) (_ BitVec n))
+            IndexedOperator::StrChar => {
+                // `(_ char #xd)`, where `#xd` is a hexadecimal code point in the range from 0 to
+                // 0x2FFFF
+                assert_num_args(&op_args, 1)?;
+                assert_num_args(&args, 0)?;
+                let c = match &op_args[0] {
+                    Constant::BitVec(value, _) if *value <= 0x2FFFF => value.to_u32(),
+                    _ => None,
+                };
+                let Some(c) = c else {
+                    return Err(ParserError::InvalidCodePoint(op_args[0].clone()));
+                };
 
-                where
-                - i, j, m, n are numerals
-                - m > i ≥ j ≥ 0,
-                - n = i - j + 1
-                 */
-                assert_num_args(&op_args, 2)?;
-                assert_num_args(&args, 1)?;
-                let s = sorts[0].as_sort().unwrap().clone();
-                if !matches!(s, Sort::BitVec(_)) {
-                    return Err(ParserError::ExpectedBvSort(s));
-                }
-                for arg in &op_args {
-                    SortError::assert_eq(&Sort::Int, &arg.sort())?;
-                }
-                assert_indexed_op_args_value(&op_args, 0..)?;
-                let i = op_args[0].as_integer().unwrap();
-                let j = op_args[1].as_integer().unwrap();
-                let Sort::BitVec(m) = sorts[0].as_sort().unwrap().clone() else {
-                    unreachable!()
+                // Printable ASCII characters are represented by themselves, and any other
+                // characters use the escape sequence defined by SMT-LIB
+                let value = match char::from_u32(c) {
+                    Some(c) if (' '..='~').contains(&c) => c.to_string(),
+                    _ => format!("\\u{{{:x}}}", c),
                 };
-                if !(m > i && i >= j && j >= Integer::ZERO) {
-                    return Err(ParserError::InvalidExtractArgs(
-                        i.to_usize().unwrap(),
-                        j.to_usize().unwrap(),
-                        m.to_usize().unwrap(),
-                    ));
-                }
+                return Ok(self.pool.add(Term::new_string(value)));
             }
-            IndexedOperator::BvBitOf
-            | IndexedOperator::ZeroExtend
-            | IndexedOperator::SignExtend => {
-                assert_num_args(&op_args, 1)?;
-                assert_num_args(&args, 1)?;
-                SortError::assert_eq(&Sort::Int, &op_args[0].sort())?;
-                let s = sorts[0].as_sort().unwrap().clone();
-                if !matches!(s, Sort::BitVec(_)) {
-                    return Err(ParserError::ExpectedBvSort(s));
+            _ => check_indexed_op(self.pool, op, &op_args, &args)?,
+        }
+        Ok(self.pool.add(Term::IndexedOp { op, op_args, args }))
+    }
+
+    /// Parses a qualified identifier, of the form `(as <identifier> <sort>)`. If `has_args` is
+    /// true, the qualified identifier is the head of an application, and its arguments are parsed
+    /// after it. This method assumes that the `(` and `as` tokens were already consumed.
+    fn parse_qualified_identifier(&mut self, has_args: bool) -> CarcaraResult<Rc<Term>> {
+        let head_pos = self.current_position;
+        match &self.current_token {
+            Token::Symbol(s) if ParamOperator::from_str(s).is_ok() => {
+                let op = ParamOperator::from_str(s).unwrap();
+                self.next_token()?;
+                let sort = self.parse_sort()?;
+                let sort = self.pool.add(sort);
+                self.expect_token(Token::CloseParen)?;
+                let args = if has_args {
+                    self.parse_sequence(Self::parse_term, true)?
+                } else {
+                    Vec::new()
+                };
+                self.make_param_op(op, vec![sort], args)
+                    .map_err(|err| Error::Parser(err, head_pos))
+            }
+            // The sort parameters of a polymorphic function are inferred using the ascribed sort,
+            // which is the sort of the result of the application
+            Token::Symbol(s) if self.is_polymorphic(s) => {
+                let name = self.expect_symbol()?;
+                let sort = self.parse_sort()?;
+                let sort = self.pool.add(sort);
+                self.expect_token(Token::CloseParen)?;
+                let args = if has_args {
+                    self.parse_sequence(Self::parse_term, true)?
+                } else {
+                    Vec::new()
+                };
+                self.make_polymorphic_app(name, args, Some(&sort))
+                    .map_err(|err| Error::Parser(err, head_pos))
+            }
+            _ => {
+                let term = self.parse_term()?;
+                let sort = self.parse_sort()?;
+                self.expect_token(Token::CloseParen)?;
+                let term = if has_args {
+                    let args = self.parse_sequence(Self::parse_term, true)?;
+                    self.make_app(term, args)
+                        .map_err(|err| Error::Parser(err, head_pos))?
+                } else {
+                    term
+                };
+                self.make_ascription(term, sort)
+                    .map_err(|err| Error::Parser(err, head_pos))
+            }
+        }
+    }
+
+    /// Constructs and sort checks a parametrized operation term.
+    fn make_param_op(
+        &mut self,
+        op: ParamOperator,
+        op_args: Vec<Rc<Term>>,
+        args: Vec<Rc<Term>>,
+    ) -> Result<Rc<Term>, ParserError> {
+        check_param_op(self.pool, op, &op_args, &args)?;
+        Ok(self.pool.add(Term::ParamOp { op, op_args, args }))
+    }
+
+    /// Applies a sort ascription to a term, checking that the term has the ascribed sort. An
+    /// integer numeral ascribed the `Real` sort is interpreted as a real constant.
+    fn make_ascription(&mut self, term: Rc<Term>, sort: Term) -> Result<Rc<Term>, ParserError> {
+        let sort = sort.as_sort().unwrap();
+        if let (Term::Const(Constant::Integer(n)), Sort::Real) = (term.as_ref(), sort) {
+            return Ok(self.pool.add(Term::new_real(n.clone())));
+        }
+        SortError::assert_eq(sort, self.pool.sort(&term).as_sort().unwrap())?;
+        Ok(term)
+    }
+
+    /// Sort checks the arguments of a function definition and applies the definition by
+    /// performing a beta reduction.
+    fn apply_function_def(
+        &mut self,
+        func: &FunctionDef,
+        args: Vec<Rc<Term>>,
+    ) -> Result<Rc<Term>, ParserError> {
+        assert_num_args(&args, func.params.len())?;
+        for (arg, param) in args.iter().zip(func.params.iter()) {
+            SortError::assert_eq(
+                param.1.as_sort().unwrap(),
+                self.pool.sort(arg).as_sort().unwrap(),
+            )?;
+        }
+
+        // Build a hash map of all the parameter names and the values they will take
+        let substitution = func
+            .params
+            .iter()
+            .zip(args)
+            .map(|((n, s), arg)| (self.pool.add(Term::new_var(*n, s.clone())), arg))
+            .collect();
+
+        // Since we already checked the sorts of the arguments, creating this substitution can
+        // never fail
+        let result = Substitution::new(self.pool, substitution)
+            .unwrap()
+            .apply(self.pool, &func.body);
+        Ok(result)
+    }
+
+    /// Returns `true` if `name` is a polymorphic function, either declared or defined.
+    fn is_polymorphic(&self, name: &str) -> bool {
+        self.state.polymorphic_decls.contains_key(name)
+            || (self.state.function_defs.get(name)).is_some_and(|f| !f.sort_params.is_empty())
+    }
+
+    /// Constructs an application of a polymorphic function. The sort parameters are inferred from
+    /// the sorts of the arguments and, if given, the sort ascribed to the result of the
+    /// application.
+    fn make_polymorphic_app(
+        &mut self,
+        name: String,
+        args: Vec<Rc<Term>>,
+        result_sort: Option<&Rc<Term>>,
+    ) -> Result<Rc<Term>, ParserError> {
+        let (sort_params, signature) = match self.state.polymorphic_decls.get(&name) {
+            Some(decl) => {
+                let signature = match decl.sort.as_sort().unwrap() {
+                    Sort::Function(sorts) => sorts.clone(),
+                    _ => vec![decl.sort.clone()],
+                };
+                (decl.sort_params.clone(), signature)
+            }
+            None => {
+                let func = &self.state.function_defs[&name];
+                let mut signature: Vec<_> = func.params.iter().map(|(_, s)| s.clone()).collect();
+                signature.push(self.pool.sort(&func.body));
+                (func.sort_params.clone(), signature)
+            }
+        };
+        assert_num_args(&args, signature.len() - 1)?;
+
+        let mut substitution = IndexMap::new();
+        let arg_sorts = args.iter().map(|a| self.pool.sort(a));
+        let pairs: Vec<_> = signature
+            .iter()
+            .zip(arg_sorts.chain(result_sort.cloned()))
+            .collect();
+        for (pattern, sort) in pairs {
+            if !match_sort_params(pattern, &sort, &sort_params, &mut substitution) {
+                let pattern = self.substitute_sort_params(pattern, &substitution);
+                return Err(SortError {
+                    expected: vec![pattern.as_sort().unwrap().clone()],
+                    got: sort.as_sort().unwrap().clone(),
                 }
-                assert_indexed_op_args_value(&op_args, 0..)?;
+                .into());
             }
         }
-        Ok(self.pool.add(Term::IndexedOp { op, op_args, args }))
+        if substitution.len() != sort_params.len() {
+            return Err(ParserError::AmbiguousSortParams(name));
+        }
+
+        if let Some(decl) = self.state.polymorphic_decls.get(&name) {
+            let sort = decl.sort.clone();
+            let sort = self.substitute_sort_params(&sort, &substitution);
+            let sort = self.pool.add(sort);
+            let function = self.pool.add(Term::new_var(name, sort));
+            return if args.is_empty() {
+                Ok(function)
+            } else {
+                self.make_app(function, args)
+            };
+        }
+
+        let func = self.state.function_defs[&name].clone();
+        let params = func
+            .params
+            .iter()
+            .map(|(n, s)| {
+                let s = self.substitute_sort_params(s, &substitution);
+                (*n, self.pool.add(s))
+            })
+            .collect();
+        let body = self.instantiate_sort_params(&func.body, &substitution);
+        let func = FunctionDef {
+            sort_params: Vec::new(),
+            params,
+            body,
+        };
+        self.apply_function_def(&func, args)
+    }
+
+    /// Replaces the sort parameters in every sort that appears in a term with the given sorts.
+    fn instantiate_sort_params(
+        &mut self,
+        term: &Rc<Term>,
+        substitution: &IndexMap<String, Rc<Term>>,
+    ) -> Rc<Term> {
+        let sort = |p: &mut Self, s: &Rc<Term>| {
+            let s = p.substitute_sort_params(s, substitution);
+            p.pool.add(s)
+        };
+        let terms = |p: &mut Self, ts: &[Rc<Term>]| -> Vec<_> {
+            ts.iter()
+                .map(|t| p.instantiate_sort_params(t, substitution))
+                .collect()
+        };
+        let bindings = |p: &mut Self, bs: &BindingList| {
+            let bs = bs.iter().map(|(n, s)| (*n, sort(p, s)));
+            BindingList(bs.collect())
+        };
+        let new = match term.as_ref() {
+            Term::Const(_) => return term.clone(),
+            Term::Sort(_) => return sort(self, term),
+            Term::Var(name, s) => Term::Var(*name, sort(self, s)),
+            Term::App(f, args) => {
+                let f = self.instantiate_sort_params(f, substitution);
+                Term::App(f, terms(self, args))
+            }
+            Term::Op(op, args) => Term::Op(*op, terms(self, args)),
+            Term::Quant(q, bs, inner) => {
+                let bs = bindings(self, bs);
+                Term::Quant(*q, bs, self.instantiate_sort_params(inner, substitution))
+            }
+            Term::Choice((name, s), inner) => {
+                let var = (*name, sort(self, s));
+                Term::Choice(var, self.instantiate_sort_params(inner, substitution))
+            }
+            Term::Let(bs, inner) => {
+                let bs = bs
+                    .iter()
+                    .map(|(n, value)| (*n, self.instantiate_sort_params(value, substitution)));
+                let bs = BindingList(bs.collect());
+                Term::Let(bs, self.instantiate_sort_params(inner, substitution))
+            }
+            Term::Lambda(bs, inner) => {
+                let bs = bindings(self, bs);
+                Term::Lambda(bs, self.instantiate_sort_params(inner, substitution))
+            }
+            Term::IndexedOp { op, op_args, args } => Term::IndexedOp {
+                op: *op,
+                op_args: op_args.clone(),
+                args: terms(self, args),
+            },
+            Term::ParamOp { op, op_args, args } => Term::ParamOp {
+                op: *op,
+                op_args: terms(self, op_args),
+                args: terms(self, args),
+            },
+        };
+        self.pool.add(new)
     }
 
     /// Parses any term that starts with `(`, that is, any term that is not a constant or a
@@ -1421,6 +2706,7 @@ impl<'a, R: BufRead> Parser<'a, R> {
                     Reserved::Lambda => self.parse_lambda_term(),
                     Reserved::Bang => self.parse_annotated_term(),
                     Reserved::Let => self.parse_let_term(),
+                    Reserved::As => self.parse_qualified_identifier(false),
                     _ => Err(Error::Parser(
                         ParserError::UnexpectedToken(Token::ReservedWord(reserved)),
                         head_pos,
@@ -1440,49 +2726,54 @@ impl<'a, R: BufRead> Parser<'a, R> {
                 self.make_op(operator, args)
                     .map_err(|err| Error::Parser(err, head_pos))
             }
+            Token::Symbol(s) if self.is_polymorphic(s) => {
+                let func_name = self.expect_symbol()?;
+                let args = self.parse_sequence(Self::parse_term, true)?;
+                self.make_polymorphic_app(func_name, args, None)
+                    .map_err(|err| Error::Parser(err, head_pos))
+            }
             Token::Symbol(s) if self.state.function_defs.get(s).is_some() => {
-                let head_pos = self.current_position;
                 let func_name = self.expect_symbol()?;
                 let args = self.parse_sequence(Self::parse_term, true)?;
-                let func = self.state.function_defs.get(&func_name).unwrap();
-
-                // If there is a function definition with this function name, we sort check
-                // the arguments and apply the definition by performing a beta reduction.
-                assert_num_args(&args, func.params.len())
-                    .map_err(|err| Error::Parser(err, head_pos))?;
-                for (arg, param) in args.iter().zip(func.params.iter()) {
-                    SortError::assert_eq(
-                        param.1.as_sort().unwrap(),
-                        self.pool.sort(arg).as_sort().unwrap(),
-                    )
-                    .map_err(|err| Error::Parser(err.into(), head_pos))?;
-                }
-
-                // Build a hash map of all the parameter names and the values they will
-                // take
-                let substitution = func
-                    .params
-                    .iter()
-                    .zip(args)
-                    .map(|((n, s), arg)| (self.pool.add(Term::new_var(n, s.clone())), arg))
-                    .collect();
-
-                // Since we already checked the sorts of the arguments, creating this substitution
-                // can never fail
-                let result = Substitution::new(self.pool, substitution)
-                    .unwrap()
-                    .apply(self.pool, &func.body);
-
-                Ok(result)
+                let func = self.state.function_defs.get(&func_name).unwrap().clone();
+                self.apply_function_def(&func, args)
+                    .map_err(|err| Error::Parser(err, head_pos))
             }
             Token::OpenParen => {
                 self.next_token()?;
                 if self.current_token == Token::ReservedWord(Reserved::Underscore) {
                     self.next_token()?;
+
+                    // Datatype testers are written as `(_ is C)`, and are represented by a
+                    // function named `is-C`
+                    if self.current_token == Token::Symbol("is".into()) {
+                        self.next_token()?;
+                        let constructor = self.expect_symbol()?;
+                        self.expect_token(Token::CloseParen)?;
+                        let tester_name = format!("is-{}", constructor);
+                        let args = self.parse_sequence(Self::parse_term, true)?;
+
+                        // The testers of parametric datatypes are polymorphic
+                        if self.is_polymorphic(&tester_name) {
+                            return self
+                                .make_polymorphic_app(tester_name, args, None)
+                                .map_err(|err| Error::Parser(err, head_pos));
+                        }
+                        let tester = self
+                            .make_var(tester_name)
+                            .map_err(|err| Error::Parser(err, head_pos))?;
+                        return self
+                            .make_app(tester, args)
+                            .map_err(|err| Error::Parser(err, head_pos));
+                    }
+
                     let (op, op_args) = self.parse_indexed_operator()?;
                     let args = self.parse_sequence(Self::parse_term, true)?;
                     self.make_indexed_op(op, op_args, args)
                         .map_err(|err| Error::Parser(err, head_pos))
+                } else if self.current_token == Token::ReservedWord(Reserved::As) {
+                    self.next_token()?;
+                    self.parse_qualified_identifier(true)
                 } else {
                     let func = self.parse_application()?;
                     let args = self.parse_sequence(Self::parse_term, true)?;
@@ -1490,6 +2781,12 @@ impl<'a, R: BufRead> Parser<'a, R> {
                         .map_err(|err| Error::Parser(err, head_pos))
                 }
             }
+            Token::Symbol(s) if self.should_infer(s) => {
+                let func_name = Symbol::from(self.expect_symbol()?);
+                let args = self.parse_sequence(Self::parse_term, true)?;
+                self.make_inferred_app(func_name, args)
+                    .map_err(|err| Error::Parser(err, head_pos))
+            }
             _ => {
                 let func = self.parse_term()?;
                 let args = self.parse_sequence(Self::parse_term, true)?;
@@ -1507,14 +2804,40 @@ impl<'a, R: BufRead> Parser<'a, R> {
             Token::OpenParen if self.current_token == Token::ReservedWord(Reserved::Underscore) => {
                 self.next_token()?;
                 let name = self.expect_symbol()?;
-                assert_eq!(name, "BitVec"); // TODO: Add proper error handling
-                let width = self.expect_numeral()?;
+                let sort = match name.as_str() {
+                    "BitVec" => {
+                        let width = self.expect_numeral()?;
+                        if width == 0 {
+                            return Err(Error::Parser(
+                                ParserError::WrongValueOfArgs((1..).into(), 0),
+                                pos,
+                            ));
+                        }
+                        Sort::BitVec(width)
+                    }
+                    "FloatingPoint" => {
+                        // Both the exponent and the significand widths must be greater than one
+                        let exponent = self.expect_numeral()?;
+                        let significand = self.expect_numeral()?;
+                        for width in [&exponent, &significand] {
+                            if *width < 2 {
+                                let width = width.to_usize().unwrap_or(usize::MAX);
+                                return Err(Error::Parser(
+                                    ParserError::WrongValueOfArgs((2..).into(), width),
+                                    pos,
+                                ));
+                            }
+                        }
+                        Sort::FloatingPoint(exponent, significand)
+                    }
+                    _ => return Err(Error::Parser(ParserError::UndefinedSort(name), pos)),
+                };
                 self.expect_token(Token::CloseParen)?;
-                return Ok(Term::Sort(Sort::BitVec(width)));
+                return Ok(Term::Sort(sort));
             }
             Token::OpenParen => {
                 let name = self.expect_symbol()?;
-                let args = self.parse_sequence(Parser::parse_sort, true)?;
+                let args = self.parse_sequence(Self::parse_sort, true)?;
                 (name, self.pool.add_all(args))
             }
             other => {
@@ -1523,14 +2846,25 @@ impl<'a, R: BufRead> Parser<'a, R> {
         };
 
         let sort = match name.as_str() {
-            "Bool" | "Int" | "Real" | "String" | "RegLan" if !args.is_empty() => Err(
-                Error::Parser(ParserError::WrongNumberOfArgs(0.into(), args.len()), pos),
-            ),
+            "Bool" | "Int" | "Real" | "String" | "RegLan" | "RoundingMode" | "Float16"
+            | "Float32" | "Float64" | "Float128"
+                if !args.is_empty() =>
+            {
+                Err(Error::Parser(
+                    ParserError::WrongNumberOfArgs(0.into(), args.len()),
+                    pos,
+                ))
+            }
             "Bool" => Ok(Sort::Bool),
             "Int" => Ok(Sort::Int),
             "Real" => Ok(Sort::Real),
             "String" => Ok(Sort::String),
             "RegLan" => Ok(Sort::RegLan),
+            "RoundingMode" => Ok(Sort::RoundingMode),
+            "Float16" => Ok(Sort::FloatingPoint(5.into(), 11.into())),
+            "Float32" => Ok(Sort::FloatingPoint(8.into(), 24.into())),
+            "Float64" => Ok(Sort::FloatingPoint(11.into(), 53.into())),
+            "Float128" => Ok(Sort::FloatingPoint(15.into(), 113.into())),
             "Array" => match args.as_slice() {
                 [x, y] => Ok(Sort::Array(x.clone(), y.clone())),
                 _ => Err(Error::Parser(
@@ -1538,6 +2872,34 @@ impl<'a, R: BufRead> Parser<'a, R> {
                     pos,
                 )),
             },
+            // Function sorts, used in higher-order logics, are written as `(-> <sort>+ <sort>)`
+            "->" if args.len() < 2 => Err(Error::Parser(
+                ParserError::WrongNumberOfArgs((2..).into(), args.len()),
+                pos,
+            )),
+            "->" => Ok(Sort::Function(args)),
+            _ if self.state.sort_defs.contains_key(&name) => {
+                let def = &self.state.sort_defs[&name];
+                if def.params.len() != args.len() {
+                    return Err(Error::Parser(
+                        ParserError::WrongNumberOfArgs(def.params.len().into(), args.len()),
+                        pos,
+                    ));
+                }
+                let substitution: IndexMap<_, _> = def.params.iter().cloned().zip(args).collect();
+                let body = def.body.clone();
+                return Ok(self.substitute_sort_params(&body, &substitution));
+            }
+            _ if self.state.datatypes.contains_key(&name) => {
+                let arity = self.state.datatypes[&name];
+                if arity != args.len() {
+                    return Err(Error::Parser(
+                        ParserError::WrongNumberOfArgs(arity.into(), args.len()),
+                        pos,
+                    ));
+                }
+                Ok(Sort::Datatype(name, args))
+            }
             _ => match self.state.sort_declarations.get(&name) {
                 Some(arity) if *arity == args.len() => Ok(Sort::Atom(name, args)),
                 Some(arity) => Err(Error::Parser(
@@ -1549,4 +2911,95 @@ impl<'a, R: BufRead> Parser<'a, R> {
         }?;
         Ok(Term::Sort(sort))
     }
+
+    /// Replaces the parameters of a sort definition with the given sorts.
+    fn substitute_sort_params(
+        &mut self,
+        sort: &Rc<Term>,
+        substitution: &IndexMap<String, Rc<Term>>,
+    ) -> Term {
+        let apply = |p: &mut Self, s: &Rc<Term>| {
+            let s = p.substitute_sort_params(s, substitution);
+            p.pool.add(s)
+        };
+        match sort.as_sort().unwrap() {
+            Sort::Atom(name, args) if args.is_empty() && substitution.contains_key(name) => {
+                substitution[name].as_ref().clone()
+            }
+            Sort::Atom(name, args) => {
+                let args = args.iter().map(|a| apply(self, a)).collect();
+                Term::Sort(Sort::Atom(name.clone(), args))
+            }
+            Sort::Datatype(name, args) => {
+                let args = args.iter().map(|a| apply(self, a)).collect();
+                Term::Sort(Sort::Datatype(name.clone(), args))
+            }
+            Sort::Function(args) => {
+                let args = args.iter().map(|a| apply(self, a)).collect();
+                Term::Sort(Sort::Function(args))
+            }
+            Sort::Array(x, y) => Term::Sort(Sort::Array(apply(self, x), apply(self, y))),
+            other => Term::Sort(other.clone()),
+        }
+    }
+}
+
+/// An iterator that parses and yields the commands of the root proof one at a time.
+///
+/// This struct is created by the [`parse_proof_streaming`](Parser::parse_proof_streaming) method
+/// on parsers.
+pub struct ProofStream<'p, 'a, R, P = PrimitivePool> {
+    parser: &'p mut Parser<'a, R, P>,
+    num_commands: usize,
+    finished_assumes: bool,
+    done: bool,
+}
+
+impl<'p, 'a, R: BufRead, P: TermPool> ProofStream<'p, 'a, R, P> {
+    /// Returns the term pool used by the parser. This can be used to check each command after it
+    /// is parsed, before parsing the next one.
+    pub fn pool(&mut self) -> &mut P {
+        self.parser.pool
+    }
+
+    /// Returns the span in the source input of each `assume` and `step` command in the last
+    /// command yielded, including the commands in it if it is a subproof, indexed by id.
+    pub fn spans(&self) -> &IndexMap<String, Span> {
+        self.parser.spans()
+    }
+}
+
+impl<'p, 'a, R: BufRead, P: TermPool> Iterator for ProofStream<'p, 'a, R, P> {
+    type Item = CarcaraResult<ProofCommand>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        self.parser.state.spans.clear();
+        self.parser.state.attributes.clear();
+        let result = if self.num_commands == 0 {
+            self.parser.skip_unsat_token()
+        } else {
+            Ok(())
+        }
+        .and_then(|()| {
+            self.parser
+                .parse_root_command(self.num_commands, &mut self.finished_assumes)
+        });
+        match result {
+            Ok(Some(command)) => {
+                self.num_commands += 1;
+                Some(Ok(command))
+            }
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
 }
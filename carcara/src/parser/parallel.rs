@@ -0,0 +1,422 @@
+//! Parsing of proofs in parallel. The proof text is first scanned to find the boundaries between
+//! the commands of the root proof, without parsing any terms, and is then split in chunks at these
+//! boundaries. Each chunk is parsed in its own thread, and all threads add terms to the same
+//! `ConcurrentPool`.
+
+use super::{Config, Lexer, Parser, ParserError, ParserLimitKind, ParserState, Position, Span};
+use crate::{
+    ast::{pool::advanced::ConcurrentPool, ProofCommand, StepId, TermPool},
+    CarcaraResult, Error,
+};
+use indexmap::IndexSet;
+use std::{io, io::BufRead, thread};
+
+/// A command in the proof text, as found by `scan_commands`.
+struct CommandSpan<'t> {
+    /// The byte offsets where the command starts and ends.
+    start: usize,
+    end: usize,
+
+    /// The position of the command name.
+    position: Position,
+
+    /// Whether the command is the first thing in its line, ignoring whitespace.
+    starts_line: bool,
+
+    /// The command name, e.g. `step`.
+    name: &'t str,
+
+    /// The id introduced by the command, if it is an `assume`, `step` or `anchor` command. For
+    /// `anchor` commands, this is the id of the step that ends the subproof.
+    id: Option<&'t str>,
+}
+
+/// A point in the proof text where the proof can be split. These are always at the beginning of a
+/// line, and between commands of the root proof.
+#[derive(Clone, Copy)]
+struct Boundary {
+    offset: usize,
+    line: usize,
+
+    /// The number of root proof commands, subproofs and definitions before the boundary.
+    num_root_commands: usize,
+    num_subproofs: usize,
+    num_definitions: usize,
+}
+
+/// Finds all top-level commands in `input`. This only looks at parentheses, comments, string
+/// literals and quoted symbols, so it is much faster than actually parsing the commands.
+fn scan_commands(input: &str) -> Vec<CommandSpan<'_>> {
+    let bytes = input.as_bytes();
+    let mut result = Vec::new();
+    let mut depth = 0usize;
+    let mut line = 1;
+    let mut line_start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\n' => {
+                line += 1;
+                line_start = i + 1;
+            }
+            b';' => {
+                while i + 1 < bytes.len() && bytes[i + 1] != b'\n' {
+                    i += 1;
+                }
+            }
+            delimiter @ (b'"' | b'|') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != delimiter {
+                    if bytes[i] == b'\n' {
+                        line += 1;
+                        line_start = i + 1;
+                    }
+                    i += 1;
+                }
+            }
+            b'(' => {
+                if depth == 0 {
+                    let (name, id) = command_head(&input[i + 1..]);
+                    let column = input[line_start..i].chars().count() + 2;
+                    result.push(CommandSpan {
+                        start: i,
+                        end: bytes.len(),
+                        position: (line, column),
+                        starts_line: bytes[line_start..i].iter().all(u8::is_ascii_whitespace),
+                        name,
+                        id,
+                    });
+                }
+                depth += 1;
+            }
+            b')' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    result.last_mut().unwrap().end = i + 1;
+                }
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+    result
+}
+
+/// Returns the name of the command whose text (after the opening parenthesis) starts at the
+/// beginning of `input`, together with the id it introduces, if any.
+fn command_head(input: &str) -> (&str, Option<&str>) {
+    let mut tokens = input
+        .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+        .filter(|s| !s.is_empty());
+    let name = tokens.next().unwrap_or_default();
+    let id = match name {
+        "assume" | "step" => tokens.next(),
+        "anchor" => tokens
+            .next()
+            .filter(|&t| t == ":step")
+            .and_then(|_| tokens.next()),
+        _ => None,
+    };
+    (name, id.map(|id| id.trim_matches('|')))
+}
+
+/// The result of scanning the proof text: the ids of the root proof commands, the text of all
+/// function definitions, and the points where the proof can be split.
+struct ProofLayout<'t> {
+    root_ids: Vec<&'t str>,
+    definitions: Vec<&'t str>,
+    boundaries: Vec<Boundary>,
+
+    /// The index in `boundaries` of the boundary before the first `step` or `anchor` command in
+    /// the root proof. All `assume` commands usually come before this boundary, and they are
+    /// parsed sequentially, before the rest of the proof.
+    first_split: Option<usize>,
+}
+
+impl<'t> ProofLayout<'t> {
+    fn new(input: &'t str, spans: &[CommandSpan<'t>]) -> Self {
+        let mut result = Self {
+            root_ids: Vec::new(),
+            definitions: Vec::new(),
+            boundaries: Vec::new(),
+            first_split: None,
+        };
+        let mut end_steps = Vec::new();
+        let mut num_subproofs = 0;
+        for span in spans {
+            if end_steps.is_empty() && span.starts_line {
+                if result.first_split.is_none() && matches!(span.name, "step" | "anchor") {
+                    result.first_split = Some(result.boundaries.len());
+                }
+                result.boundaries.push(Boundary {
+                    offset: input[..span.start].rfind('\n').map_or(0, |i| i + 1),
+                    line: span.position.0,
+                    num_root_commands: result.root_ids.len(),
+                    num_subproofs,
+                    num_definitions: result.definitions.len(),
+                });
+            }
+            match (span.name, span.id) {
+                ("anchor", Some(id)) => {
+                    end_steps.push(id);
+                    num_subproofs += 1;
+                }
+                ("assume" | "step", Some(id)) => {
+                    if end_steps.last() == Some(&id) {
+                        end_steps.pop();
+                        if end_steps.is_empty() {
+                            result.root_ids.push(id);
+                        }
+                    } else if end_steps.is_empty() {
+                        result.root_ids.push(id);
+                    }
+                }
+                ("define-fun" | "define-const", _) => {
+                    result.definitions.push(&input[span.start..span.end]);
+                }
+                _ => (),
+            }
+        }
+        result
+    }
+
+    /// Chooses at most `num_chunks` boundaries to split the proof text. The text after each
+    /// boundary, up to the next one, is a chunk, and all chunks have roughly the same length.
+    fn splits(&self, input_len: usize, num_chunks: usize) -> Vec<Boundary> {
+        let Some(first) = self.first_split else {
+            return Vec::new();
+        };
+        let candidates = &self.boundaries[first..];
+        let start = candidates[0].offset;
+        let mut result = vec![candidates[0]];
+        for k in 1..num_chunks {
+            let target = start + (input_len - start) * k / num_chunks;
+            let last = result.last().unwrap().offset;
+            if let Some(b) = candidates
+                .iter()
+                .find(|b| b.offset >= target && b.offset > last)
+            {
+                result.push(*b);
+            }
+        }
+        result
+    }
+}
+
+/// Constructs a parser for `input` that starts with the given state. `config` is the configuration
+/// of the original parser, together with whether it interprets integers as reals.
+fn parser_with_state<S: BufRead, P: TermPool>(
+    pool: &mut P,
+    (config, interpret_integers_as_reals): (Config, bool),
+    state: ParserState,
+    input: S,
+) -> CarcaraResult<Parser<'_, S, P>> {
+    let mut lexer = Lexer::with_max_size(input, config.limits.max_file_size)?;
+    let (current_token, current_position) = lexer.next_token()?;
+    let current_span = lexer.last_token_span();
+    Ok(Parser {
+        pool,
+        config,
+        lexer,
+        current_token,
+        current_position,
+        current_span,
+        previous_span: Span::default(),
+        state,
+        interpret_integers_as_reals,
+        problem: None,
+        parens_depth: 0,
+        recovered_errors: None,
+        inferred_sort: None,
+        term_depth: 0,
+        term_count: 0,
+        commands_parsed: 0,
+        progress_callback: None,
+    })
+}
+
+/// Parses the commands in a chunk of the proof, which starts with the root proof command of index
+/// `first_index`.
+fn parse_chunk<'t, P: TermPool>(
+    parser: &mut Parser<'_, &'t [u8], P>,
+    text: &'t str,
+    first_index: usize,
+) -> CarcaraResult<Vec<ProofCommand>> {
+    parser.reset(text.as_bytes())?;
+    let mut finished_assumes = true;
+    let mut commands = Vec::new();
+    while let Some(command) =
+        parser.parse_root_command(first_index + commands.len(), &mut finished_assumes)?
+    {
+        commands.push(command);
+    }
+    Ok(commands)
+}
+
+/// Offsets the line numbers of parser errors found in a chunk that starts in the given line.
+fn offset_error(error: Error, first_line: usize) -> Error {
+    match error {
+        Error::Parser(e, (line, column)) if line > 0 => {
+            Error::Parser(e, (line + first_line - 1, column))
+        }
+        other => other,
+    }
+}
+
+impl<'a, R: BufRead> Parser<'a, R> {
+    /// Parses a proof in the Alethe format using up to `num_threads` threads. All function,
+    /// constant and sort declarations needed should already be in the parser state.
+    ///
+    /// The `assume` commands at the beginning of the proof are parsed first. The remaining proof
+    /// text is then split in chunks at the boundaries between commands of the root proof, which
+    /// are parsed in parallel. All threads add terms to the same hash-consing table (see
+    /// [`ConcurrentPool`]), so the result is the same as parsing the proof sequentially. Note that
+    /// the proof is only split at the beginning of lines, so a proof that is written in a single
+    /// line is parsed sequentially.
+    ///
+    /// If an `assume` command appears after the first `step` command, the name given to its term
+    /// using the `:named` attribute can only be used as a premise in the same chunk.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use carcara::{ast::*, parser::{Config, Parser}};
+    /// let proof = "(assume h1 (not true))\n(step t2 (cl) :rule hole :premises (h1))\n";
+    /// let mut pool = PrimitivePool::new();
+    /// let mut parser = Parser::new(&mut pool, Config::new(), "".as_bytes())?;
+    /// let commands = parser.parse_proof_parallel(proof.as_bytes(), 2)?;
+    /// assert_eq!(commands.len(), 2);
+    /// # Ok::<(), carcara::Error>(())
+    /// ```
+    pub fn parse_proof_parallel(
+        &mut self,
+        input: &[u8],
+        num_threads: usize,
+    ) -> CarcaraResult<Vec<ProofCommand>> {
+        // Each chunk is only checked against the size limit separately, so we check the whole
+        // input here
+        if let Some(max) = self.config.limits.max_file_size {
+            if input.len() > max {
+                let error = ParserError::LimitExceeded(ParserLimitKind::FileSize, max);
+                return Err(Error::Parser(error, (1, 1)));
+            }
+        }
+        let input = std::str::from_utf8(input)
+            .map_err(|e| Error::Io(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+        let spans = scan_commands(input);
+        let layout = ProofLayout::new(input, &spans);
+
+        // Step ids must be unique in the whole proof, so we check that before splitting it, since
+        // each chunk only knows about its own ids
+        let mut ids = IndexSet::new();
+        for span in &spans {
+            if let ("assume" | "step", Some(id)) = (span.name, span.id) {
+                if self.state.step_ids.get(id).is_some() || !ids.insert(id) {
+                    let error = ParserError::RepeatedStepIndex(id.to_owned());
+                    return Err(Error::Parser(error, span.position));
+                }
+            }
+        }
+
+        let splits = layout.splits(input.len(), num_threads);
+        let prefix_end = splits.first().map_or(input.len(), |b| b.offset);
+
+        // The prefix is parsed sequentially, and its final state is the starting point for all
+        // chunks
+        let config = (self.config, self.interpret_integers_as_reals);
+        let mut prefix_parser = parser_with_state(
+            &mut *self.pool,
+            config,
+            self.state.clone(),
+            &input.as_bytes()[..prefix_end],
+        )?;
+        let mut commands = prefix_parser.parse_proof_continuation(Vec::new())?;
+        let prefix_state = prefix_parser.state;
+        if splits.is_empty() {
+            self.state = prefix_state;
+            return Ok(commands);
+        }
+
+        let shared = ConcurrentPool::new(std::mem::take(self.pool));
+        let results: Vec<_> = thread::scope(|s| {
+            let handles: Vec<_> = splits
+                .iter()
+                .enumerate()
+                .map(|(i, split)| {
+                    let end = splits.get(i + 1).map_or(input.len(), |b| b.offset);
+                    let text = &input[split.offset..end];
+                    let mut pool = shared.clone();
+                    let mut state = prefix_state.clone();
+                    let (layout, first) = (&layout, splits[0]);
+                    s.spawn(move || -> CarcaraResult<_> {
+                        for (index, id) in layout.root_ids[..split.num_root_commands]
+                            .iter()
+                            .enumerate()
+                        {
+                            state.step_ids.insert(StepId::new(id), Some(index));
+                        }
+                        state.num_subproofs += split.num_subproofs - first.num_subproofs;
+
+                        // Function definitions may appear anywhere in the proof, so the ones
+                        // that appear in previous chunks must be parsed again in this chunk
+                        let definitions = layout.definitions
+                            [first.num_definitions..split.num_definitions]
+                            .join("\n");
+                        let mut parser =
+                            parser_with_state(&mut pool, config, state, definitions.as_bytes())?;
+                        parser.parse_proof_continuation(Vec::new())?;
+
+                        let commands = parse_chunk(&mut parser, text, split.num_root_commands)
+                            .map_err(|e| offset_error(e, split.line))?;
+                        Ok((commands, parser.state))
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        *self.pool = shared.into_inner().unwrap();
+
+        let mut state = prefix_state;
+        let (num_attributes, num_named) = (state.attributes.len(), state.named_assumes.len());
+        let (num_spans, num_flattened) = (state.spans.len(), state.flattened.len());
+        let num_term_attributes = state.term_attributes.len();
+        for (result, split) in results.into_iter().zip(&splits) {
+            let (chunk_commands, chunk_state): (_, ParserState) = result?;
+            commands.extend(chunk_commands);
+            state.step_ids = chunk_state.step_ids;
+            state.function_defs = chunk_state.function_defs;
+            state.num_subproofs = chunk_state.num_subproofs;
+            state
+                .attributes
+                .extend(chunk_state.attributes.into_iter().skip(num_attributes));
+            // The spans found in each chunk are relative to the start of the chunk
+            let chunk_spans = chunk_state.spans.into_iter().skip(num_spans);
+            state.spans.extend(
+                chunk_spans.map(|(id, span)| (id, span.shift(split.line - 1, split.offset))),
+            );
+            for (name, index) in chunk_state.named_assumes.into_iter().skip(num_named) {
+                state.named_assumes.entry(name).or_insert(index);
+            }
+            for (term, original) in chunk_state.flattened.into_iter().skip(num_flattened) {
+                state.flattened.entry(term).or_insert(original);
+            }
+            for (term, list) in chunk_state
+                .term_attributes
+                .into_iter()
+                .skip(num_term_attributes)
+            {
+                state.term_attributes.entry(term).or_insert(list);
+            }
+        }
+
+        // Each chunk only knows about the ids in its own subproofs, so the ids in subproofs closed
+        // by the other chunks are added here
+        for id in ids {
+            if state.step_ids.get(id).is_none() {
+                state.step_ids.insert_global(StepId::new(id), None);
+            }
+        }
+        self.state = state;
+        Ok(commands)
+    }
+}
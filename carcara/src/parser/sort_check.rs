@@ -0,0 +1,584 @@
+//! Sort checking of operation and application terms. These checks are used by the parser, and
+//! also when terms are built from other sources, like [`Proof::deserialize`], so that no
+//! ill-sorted term is ever added to a term pool.
+
+use super::error::{assert_indexed_op_args_value, assert_num_args, assert_same_fp_sort};
+use super::{ParserError, SortError};
+use crate::ast::*;
+use rug::Integer;
+
+/// Sort checks the arguments of an operation term. If `allow_int_real_subtyping` is `true`,
+/// arithmetic operators may receive both `Int` and `Real` arguments.
+pub fn check_op(
+    pool: &mut dyn TermPool,
+    op: Operator,
+    args: &[Rc<Term>],
+    allow_int_real_subtyping: bool,
+) -> Result<(), ParserError> {
+    let sorts: Vec<_> = args.iter().map(|t| pool.sort(t)).collect();
+    match op {
+        Operator::Not => {
+            assert_num_args(args, 1)?;
+            SortError::assert_eq(&Sort::Bool, sorts[0].as_sort().unwrap())?;
+        }
+        Operator::Implies => {
+            assert_num_args(args, 2..)?;
+            for s in sorts {
+                SortError::assert_eq(&Sort::Bool, s.as_sort().unwrap())?;
+            }
+        }
+        Operator::Or | Operator::And | Operator::Xor => {
+            assert_num_args(args, 2..)?;
+            for s in sorts {
+                SortError::assert_eq(&Sort::Bool, s.as_sort().unwrap())?;
+            }
+        }
+        Operator::Equals | Operator::Distinct => {
+            assert_num_args(args, 2..)?;
+            SortError::assert_all_eq(
+                &sorts
+                    .iter()
+                    .map(|op| op.as_sort().unwrap())
+                    .collect::<Vec<&Sort>>(),
+            )?;
+        }
+        Operator::Ite => {
+            assert_num_args(args, 3)?;
+            SortError::assert_eq(&Sort::Bool, sorts[0].as_sort().unwrap())?;
+            SortError::assert_eq(sorts[1].as_sort().unwrap(), sorts[2].as_sort().unwrap())?;
+        }
+        Operator::Add | Operator::Sub | Operator::Mult => {
+            // The `-` operator, in particular, can be called with only one argument, in which
+            // case it means negation instead of subtraction
+            if op == Operator::Sub {
+                assert_num_args(args, 1..)?;
+            } else {
+                assert_num_args(args, 2..)?;
+            }
+
+            // All the arguments must be either Int or Real. Also, if we are not allowing
+            // Int/Real subtyping, all arguments must have the same sort
+            if allow_int_real_subtyping {
+                for s in sorts {
+                    SortError::assert_one_of(&[Sort::Int, Sort::Real], s.as_sort().unwrap())?;
+                }
+            } else {
+                SortError::assert_one_of(&[Sort::Int, Sort::Real], sorts[0].as_sort().unwrap())?;
+                SortError::assert_all_eq(
+                    &sorts
+                        .iter()
+                        .map(|op| op.as_sort().unwrap())
+                        .collect::<Vec<&Sort>>(),
+                )?;
+            }
+        }
+        Operator::IntDiv => {
+            assert_num_args(args, 2..)?;
+            SortError::assert_eq(&Sort::Int, sorts[0].as_sort().unwrap())?;
+            SortError::assert_all_eq(
+                &sorts
+                    .iter()
+                    .map(|op| op.as_sort().unwrap())
+                    .collect::<Vec<&Sort>>(),
+            )?;
+        }
+        Operator::RealDiv => {
+            assert_num_args(args, 2..)?;
+
+            // Normally, the `/` operator may only receive Real arguments, but if we are
+            // allowing Int/Real subtyping, it may also receive Ints
+            if allow_int_real_subtyping {
+                for s in sorts {
+                    SortError::assert_one_of(&[Sort::Int, Sort::Real], s.as_sort().unwrap())?;
+                }
+            } else {
+                SortError::assert_eq(&Sort::Real, sorts[0].as_sort().unwrap())?;
+                SortError::assert_all_eq(
+                    &sorts
+                        .iter()
+                        .map(|op| op.as_sort().unwrap())
+                        .collect::<Vec<&Sort>>(),
+                )?;
+            }
+        }
+        Operator::Mod => {
+            assert_num_args(args, 2)?;
+            SortError::assert_eq(&Sort::Int, sorts[0].as_sort().unwrap())?;
+            SortError::assert_eq(&Sort::Int, sorts[1].as_sort().unwrap())?;
+        }
+        Operator::Abs => {
+            assert_num_args(args, 1)?;
+            SortError::assert_eq(&Sort::Int, sorts[0].as_sort().unwrap())?;
+        }
+        Operator::LessThan | Operator::GreaterThan | Operator::LessEq | Operator::GreaterEq => {
+            assert_num_args(args, 2..)?;
+            // All the arguments must be either Int or Real sorted, but they don't need to all
+            // have the same sort
+            for s in sorts {
+                SortError::assert_one_of(&[Sort::Int, Sort::Real], s.as_sort().unwrap())?;
+            }
+        }
+        Operator::ToReal => {
+            assert_num_args(args, 1)?;
+            SortError::assert_eq(&Sort::Int, sorts[0].as_sort().unwrap())?;
+        }
+        Operator::ToInt | Operator::IsInt => {
+            assert_num_args(args, 1)?;
+            SortError::assert_eq(&Sort::Real, sorts[0].as_sort().unwrap())?;
+        }
+        Operator::Select => {
+            assert_num_args(args, 2)?;
+            match sorts[0].as_sort().unwrap() {
+                Sort::Array(_, _) => (),
+                got => {
+                    // Instead of creating some special case for sort errors with parametric
+                    // sorts, we just create a sort `Y` to represent the sort parameter. We
+                    // infer the `X` sort from the second operator argument. This may be
+                    // changed later
+                    let got = got.clone();
+                    let x = sorts[1].as_sort().unwrap().clone();
+                    let x = pool.add(Term::Sort(x));
+                    let y = pool.add(Term::Sort(Sort::Atom("Y".to_owned(), Vec::new())));
+                    return Err(SortError {
+                        expected: vec![Sort::Array(x, y)],
+                        got,
+                    }
+                    .into());
+                }
+            }
+        }
+        Operator::Store => {
+            assert_num_args(args, 3)?;
+            match sorts[0].as_sort().unwrap() {
+                Sort::Array(x, y) => {
+                    SortError::assert_eq(x.as_sort().unwrap(), sorts[1].as_sort().unwrap())?;
+                    SortError::assert_eq(y.as_sort().unwrap(), sorts[2].as_sort().unwrap())?;
+                }
+                got => {
+                    let got = got.clone();
+                    let [x, y] =
+                        [&sorts[0], &sorts[1]].map(|s| Term::Sort(s.as_sort().unwrap().clone()));
+                    return Err(SortError {
+                        expected: vec![Sort::Array(pool.add(x), pool.add(y))],
+                        got,
+                    }
+                    .into());
+                }
+            }
+        }
+        Operator::StrConcat => {
+            assert_num_args(args, 2..)?;
+            for s in sorts {
+                SortError::assert_eq(&Sort::String, s.as_sort().unwrap())?;
+            }
+        }
+        Operator::StrLen | Operator::StrIsDigit | Operator::StrToCode | Operator::StrToInt => {
+            assert_num_args(args, 1)?;
+            SortError::assert_eq(&Sort::String, sorts[0].as_sort().unwrap())?;
+        }
+        Operator::StrLessThan
+        | Operator::StrLessEq
+        | Operator::PrefixOf
+        | Operator::SuffixOf
+        | Operator::Contains
+        | Operator::ReRange => {
+            assert_num_args(args, 2)?;
+            SortError::assert_eq(&Sort::String, sorts[0].as_sort().unwrap())?;
+            SortError::assert_eq(&Sort::String, sorts[1].as_sort().unwrap())?;
+        }
+        Operator::CharAt => {
+            assert_num_args(args, 2)?;
+            SortError::assert_eq(&Sort::String, sorts[0].as_sort().unwrap())?;
+            SortError::assert_eq(&Sort::Int, sorts[1].as_sort().unwrap())?;
+        }
+        Operator::Substring => {
+            assert_num_args(args, 3)?;
+            SortError::assert_eq(&Sort::String, sorts[0].as_sort().unwrap())?;
+            SortError::assert_eq(&Sort::Int, sorts[1].as_sort().unwrap())?;
+            SortError::assert_eq(&Sort::Int, sorts[2].as_sort().unwrap())?;
+        }
+        Operator::IndexOf => {
+            assert_num_args(args, 3)?;
+            SortError::assert_eq(&Sort::String, sorts[0].as_sort().unwrap())?;
+            SortError::assert_eq(&Sort::String, sorts[1].as_sort().unwrap())?;
+            SortError::assert_eq(&Sort::Int, sorts[2].as_sort().unwrap())?;
+        }
+        Operator::Replace | Operator::ReplaceAll => {
+            assert_num_args(args, 3)?;
+            SortError::assert_eq(&Sort::String, sorts[0].as_sort().unwrap())?;
+            SortError::assert_eq(&Sort::String, sorts[1].as_sort().unwrap())?;
+            SortError::assert_eq(&Sort::String, sorts[2].as_sort().unwrap())?;
+        }
+        Operator::StrFromCode | Operator::StrFromInt => {
+            assert_num_args(args, 1)?;
+            SortError::assert_eq(&Sort::Int, sorts[0].as_sort().unwrap())?;
+        }
+        Operator::StrToRe => {
+            assert_num_args(args, 1)?;
+            SortError::assert_eq(&Sort::String, sorts[0].as_sort().unwrap())?;
+        }
+        Operator::StrInRe => {
+            assert_num_args(args, 2)?;
+            SortError::assert_eq(&Sort::String, sorts[0].as_sort().unwrap())?;
+            SortError::assert_eq(&Sort::RegLan, sorts[1].as_sort().unwrap())?;
+        }
+        Operator::ReNone | Operator::ReAll | Operator::ReAllChar => {
+            assert_num_args(args, 0)?;
+        }
+        Operator::ReConcat | Operator::ReUnion | Operator::ReIntersection | Operator::ReDiff => {
+            assert_num_args(args, 2..)?;
+            for s in sorts {
+                SortError::assert_eq(&Sort::RegLan, s.as_sort().unwrap())?;
+            }
+        }
+        Operator::ReKleeneClosure
+        | Operator::ReComplement
+        | Operator::ReKleeneCross
+        | Operator::ReOption => {
+            assert_num_args(args, 1)?;
+            SortError::assert_eq(&Sort::RegLan, sorts[0].as_sort().unwrap())?;
+        }
+        Operator::ReplaceRe | Operator::ReplaceReAll => {
+            assert_num_args(args, 3)?;
+            SortError::assert_eq(&Sort::String, sorts[0].as_sort().unwrap())?;
+            SortError::assert_eq(&Sort::RegLan, sorts[1].as_sort().unwrap())?;
+            SortError::assert_eq(&Sort::String, sorts[2].as_sort().unwrap())?;
+        }
+        Operator::BvNot | Operator::BvNeg | Operator::BvToNat => {
+            assert_num_args(args, 1)?;
+            for s in sorts {
+                let s = s.as_sort().unwrap().clone();
+                if !matches!(s, Sort::BitVec(_)) {
+                    return Err(ParserError::ExpectedBvSort(s));
+                }
+            }
+        }
+        Operator::BvBbTerm => {
+            assert_num_args(args, 1..)?;
+            for s in sorts {
+                let s = s.as_sort().unwrap().clone();
+                SortError::assert_eq(&Sort::Bool, &s)?;
+            }
+        }
+        Operator::BvConcat => {
+            assert_num_args(args, 2..)?;
+            for s in sorts {
+                let s = s.as_sort().unwrap().clone();
+                if !matches!(s, Sort::BitVec(_)) {
+                    return Err(ParserError::ExpectedBvSort(s));
+                }
+            }
+        }
+        Operator::BvAdd | Operator::BvMul | Operator::BvAnd | Operator::BvOr | Operator::BvXor => {
+            assert_num_args(args, 2..)?;
+            let first_sort = sorts[0].as_sort().unwrap().clone();
+            if !matches!(first_sort, Sort::BitVec(_)) {
+                return Err(ParserError::ExpectedBvSort(first_sort));
+            }
+            SortError::assert_all_eq(
+                &sorts
+                    .iter()
+                    .map(|op| op.as_sort().unwrap())
+                    .collect::<Vec<&Sort>>(),
+            )?;
+        }
+        Operator::BvUDiv
+        | Operator::BvURem
+        | Operator::BvShl
+        | Operator::BvLShr
+        | Operator::BvULt
+        | Operator::BvNAnd
+        | Operator::BvNOr
+        | Operator::BvXNor
+        | Operator::BvComp
+        | Operator::BvSub
+        | Operator::BvSDiv
+        | Operator::BvSRem
+        | Operator::BvSMod
+        | Operator::BvAShr
+        | Operator::BvULe
+        | Operator::BvUGt
+        | Operator::BvUGe
+        | Operator::BvSLt
+        | Operator::BvSLe
+        | Operator::BvSGt
+        | Operator::BvSGe => {
+            assert_num_args(args, 2)?;
+            let first_sort = sorts[0].as_sort().unwrap().clone();
+            if !matches!(first_sort, Sort::BitVec(_)) {
+                return Err(ParserError::ExpectedBvSort(first_sort));
+            }
+            SortError::assert_all_eq(
+                &sorts
+                    .iter()
+                    .map(|op| op.as_sort().unwrap())
+                    .collect::<Vec<&Sort>>(),
+            )?;
+        }
+        Operator::Fp => {
+            // `(fp s e m)`, where `s` has width 1, and `e` has width of at least 2
+            assert_num_args(args, 3)?;
+            for s in &sorts {
+                let s = s.as_sort().unwrap().clone();
+                if !matches!(s, Sort::BitVec(_)) {
+                    return Err(ParserError::ExpectedBvSort(s));
+                }
+            }
+            SortError::assert_eq(&Sort::BitVec(1.into()), sorts[0].as_sort().unwrap())?;
+            let Sort::BitVec(exponent) = sorts[1].as_sort().unwrap() else {
+                unreachable!()
+            };
+            if *exponent < 2 {
+                return Err(ParserError::WrongValueOfArgs(
+                    (2..).into(),
+                    exponent.to_usize().unwrap_or(usize::MAX),
+                ));
+            }
+        }
+        Operator::RoundNearestTiesToEven
+        | Operator::RoundNearestTiesToAway
+        | Operator::RoundTowardPositive
+        | Operator::RoundTowardNegative
+        | Operator::RoundTowardZero => {
+            assert_num_args(args, 0)?;
+        }
+        Operator::FpAbs
+        | Operator::FpNeg
+        | Operator::FpIsNormal
+        | Operator::FpIsSubnormal
+        | Operator::FpIsZero
+        | Operator::FpIsInfinite
+        | Operator::FpIsNaN
+        | Operator::FpIsNegative
+        | Operator::FpIsPositive
+        | Operator::FpToReal => {
+            assert_num_args(args, 1)?;
+            assert_same_fp_sort(&[sorts[0].as_sort().unwrap()])?;
+        }
+        Operator::FpRem | Operator::FpMin | Operator::FpMax => {
+            assert_num_args(args, 2)?;
+            assert_same_fp_sort(
+                &sorts
+                    .iter()
+                    .map(|s| s.as_sort().unwrap())
+                    .collect::<Vec<_>>(),
+            )?;
+        }
+        Operator::FpLessEq
+        | Operator::FpLessThan
+        | Operator::FpGreaterEq
+        | Operator::FpGreaterThan
+        | Operator::FpEquals => {
+            assert_num_args(args, 2..)?;
+            assert_same_fp_sort(
+                &sorts
+                    .iter()
+                    .map(|s| s.as_sort().unwrap())
+                    .collect::<Vec<_>>(),
+            )?;
+        }
+        Operator::FpAdd
+        | Operator::FpSub
+        | Operator::FpMul
+        | Operator::FpDiv
+        | Operator::FpFma
+        | Operator::FpSqrt
+        | Operator::FpRoundToIntegral => {
+            // All of these take a rounding mode, followed by one or more floating-point
+            // arguments of the same sort
+            let expected = match op {
+                Operator::FpSqrt | Operator::FpRoundToIntegral => 2,
+                Operator::FpFma => 4,
+                _ => 3,
+            };
+            assert_num_args(args, expected)?;
+            SortError::assert_eq(&Sort::RoundingMode, sorts[0].as_sort().unwrap())?;
+            assert_same_fp_sort(
+                &sorts[1..]
+                    .iter()
+                    .map(|s| s.as_sort().unwrap())
+                    .collect::<Vec<_>>(),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks the operation arguments and sort checks the arguments of an indexed operation term.
+///
+/// The `bv` and `char` indexed operators are only syntax for constants, and are never represented
+/// as indexed operation terms, so they must be handled by the caller.
+pub fn check_indexed_op(
+    pool: &mut dyn TermPool,
+    op: IndexedOperator,
+    op_args: &[Constant],
+    args: &[Rc<Term>],
+) -> Result<(), ParserError> {
+    let sorts: Vec<_> = args.iter().map(|t| pool.sort(t)).collect();
+    match op {
+        IndexedOperator::BvExtract => {
+            /*
+            ((_ extract i j) (_ BitVec m) (_ BitVec n))
+
+            where
+            - i, j, m, n are numerals
+            - m > i ≥ j ≥ 0,
+            - n = i - j + 1
+             */
+            assert_num_args(op_args, 2)?;
+            assert_num_args(args, 1)?;
+            let s = sorts[0].as_sort().unwrap().clone();
+            if !matches!(s, Sort::BitVec(_)) {
+                return Err(ParserError::ExpectedBvSort(s));
+            }
+            for arg in op_args {
+                SortError::assert_eq(&Sort::Int, &arg.sort())?;
+            }
+            assert_indexed_op_args_value(op_args, 0..)?;
+            let i = op_args[0].as_integer().unwrap();
+            let j = op_args[1].as_integer().unwrap();
+            let Sort::BitVec(m) = sorts[0].as_sort().unwrap().clone() else {
+                unreachable!()
+            };
+            if !(m > i && i >= j && j >= Integer::ZERO) {
+                return Err(ParserError::InvalidExtractArgs(i, j, m));
+            }
+        }
+        IndexedOperator::BvBitOf
+        | IndexedOperator::ZeroExtend
+        | IndexedOperator::SignExtend
+        | IndexedOperator::Repeat
+        | IndexedOperator::RotateLeft
+        | IndexedOperator::RotateRight => {
+            assert_num_args(op_args, 1)?;
+            assert_num_args(args, 1)?;
+            SortError::assert_eq(&Sort::Int, &op_args[0].sort())?;
+            let s = sorts[0].as_sort().unwrap().clone();
+            if !matches!(s, Sort::BitVec(_)) {
+                return Err(ParserError::ExpectedBvSort(s));
+            }
+
+            // `repeat` must be given a positive number of repetitions
+            let min = if op == IndexedOperator::Repeat { 1 } else { 0 };
+            assert_indexed_op_args_value(op_args, min..)?;
+        }
+        IndexedOperator::IntToBv | IndexedOperator::Divisible => {
+            // Both `(_ int2bv n)` and `(_ divisible n)` require `n` to be positive
+            assert_num_args(op_args, 1)?;
+            assert_num_args(args, 1)?;
+            SortError::assert_eq(&Sort::Int, &op_args[0].sort())?;
+            assert_indexed_op_args_value(op_args, 1..)?;
+            SortError::assert_eq(&Sort::Int, sorts[0].as_sort().unwrap())?;
+        }
+        IndexedOperator::ReLoop | IndexedOperator::RePower => {
+            let expected = if op == IndexedOperator::ReLoop { 2 } else { 1 };
+            assert_num_args(op_args, expected)?;
+            assert_num_args(args, 1)?;
+            for arg in op_args {
+                SortError::assert_eq(&Sort::Int, &arg.sort())?;
+            }
+            assert_indexed_op_args_value(op_args, 0..)?;
+            SortError::assert_eq(&Sort::RegLan, sorts[0].as_sort().unwrap())?;
+        }
+        IndexedOperator::FpPlusInfinity
+        | IndexedOperator::FpMinusInfinity
+        | IndexedOperator::FpPlusZero
+        | IndexedOperator::FpMinusZero
+        | IndexedOperator::FpNaN => {
+            assert_num_args(op_args, 2)?;
+            assert_num_args(args, 0)?;
+            for arg in op_args {
+                SortError::assert_eq(&Sort::Int, &arg.sort())?;
+            }
+            assert_indexed_op_args_value(op_args, 2..)?;
+        }
+        IndexedOperator::ToFp | IndexedOperator::ToFpUnsigned => {
+            assert_num_args(op_args, 2)?;
+            for arg in op_args {
+                SortError::assert_eq(&Sort::Int, &arg.sort())?;
+            }
+            assert_indexed_op_args_value(op_args, 2..)?;
+            let exponent = op_args[0].as_integer().unwrap();
+            let significand = op_args[1].as_integer().unwrap();
+            let sorts: Vec<_> = sorts.iter().map(|s| s.as_sort().unwrap()).collect();
+            match (op, sorts.as_slice()) {
+                // `((_ to_fp eb sb) (_ BitVec m))`, where `m = eb + sb`, reinterprets the
+                // bitvector as a floating-point number
+                (IndexedOperator::ToFp, [s]) => {
+                    SortError::assert_eq(&Sort::BitVec(exponent + significand), s)?;
+                }
+                // `((_ to_fp eb sb) RoundingMode x)`, where `x` is a floating-point number, a
+                // real or a signed bitvector
+                (IndexedOperator::ToFp, [rm, s]) => {
+                    SortError::assert_eq(&Sort::RoundingMode, rm)?;
+                    if !matches!(s, Sort::FloatingPoint(_, _) | Sort::Real | Sort::BitVec(_)) {
+                        return Err(ParserError::ExpectedFpSort((*s).clone()));
+                    }
+                }
+                // `((_ to_fp_unsigned eb sb) RoundingMode (_ BitVec m))`
+                (IndexedOperator::ToFpUnsigned, [rm, s]) => {
+                    SortError::assert_eq(&Sort::RoundingMode, rm)?;
+                    if !matches!(s, Sort::BitVec(_)) {
+                        return Err(ParserError::ExpectedBvSort((*s).clone()));
+                    }
+                }
+                (IndexedOperator::ToFp, _) => assert_num_args(args, 1..3)?,
+                _ => assert_num_args(args, 2)?,
+            }
+        }
+        IndexedOperator::FpToUbv | IndexedOperator::FpToSbv => {
+            assert_num_args(op_args, 1)?;
+            assert_num_args(args, 2)?;
+            SortError::assert_eq(&Sort::Int, &op_args[0].sort())?;
+            assert_indexed_op_args_value(op_args, 1..)?;
+            SortError::assert_eq(&Sort::RoundingMode, sorts[0].as_sort().unwrap())?;
+            assert_same_fp_sort(&[sorts[1].as_sort().unwrap()])?;
+        }
+        IndexedOperator::BvConst | IndexedOperator::StrChar => {
+            unreachable!("constants should be handled by the caller")
+        }
+    }
+    Ok(())
+}
+
+/// Sort checks the arguments of a parametrized operation term.
+pub fn check_param_op(
+    pool: &mut dyn TermPool,
+    op: ParamOperator,
+    op_args: &[Rc<Term>],
+    args: &[Rc<Term>],
+) -> Result<(), ParserError> {
+    match op {
+        ParamOperator::ArrayConst => {
+            assert_num_args(args, 1)?;
+            let sort = op_args[0].as_sort().unwrap();
+            let Sort::Array(_, element) = sort else {
+                return Err(ParserError::ExpectedArraySort(sort.clone()));
+            };
+            SortError::assert_eq(
+                element.as_sort().unwrap(),
+                pool.sort(&args[0]).as_sort().unwrap(),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Sort checks the arguments of an application term, and checks that `function` has a function
+/// sort.
+pub fn check_app(
+    pool: &mut dyn TermPool,
+    function: &Rc<Term>,
+    args: &[Rc<Term>],
+) -> Result<(), ParserError> {
+    let sort = pool.sort(function);
+    let Sort::Function(sorts) = sort.as_sort().unwrap() else {
+        // Function does not have function sort
+        return Err(ParserError::NotAFunction(sort.as_sort().unwrap().clone()));
+    };
+    assert_num_args(args, sorts.len() - 1)?;
+    for (arg, sort) in args.iter().zip(sorts) {
+        SortError::assert_eq(sort.as_sort().unwrap(), pool.sort(arg).as_sort().unwrap())?;
+    }
+    Ok(())
+}
@@ -12,6 +12,17 @@ const TEST_CONFIG: Config = Config {
     apply_function_defs: true,
     expand_lets: false,
     allow_int_real_subtyping: false,
+    query: None,
+    parsing_threads: 1,
+    limits: ParserLimits {
+        max_term_depth: None,
+        max_term_count: None,
+        max_file_size: None,
+    },
+    flatten_assoc_ops: false,
+    alethe_version: None,
+    verit_compat: false,
+    smtinterpol_compat: false,
 };
 
 pub fn parse_terms<const N: usize>(
@@ -45,11 +56,20 @@ pub fn parse_term_err(input: &str) -> Error {
 
 /// Parses a proof from a `&str`. Panics if any error is encountered.
 pub fn parse_proof(pool: &mut PrimitivePool, input: &str) -> Proof {
-    let commands = Parser::new(pool, TEST_CONFIG, input.as_bytes())
-        .expect(ERROR_MESSAGE)
-        .parse_proof()
-        .expect(ERROR_MESSAGE);
-    Proof { premises: IndexSet::new(), commands }
+    let mut parser = Parser::new(pool, TEST_CONFIG, input.as_bytes()).expect(ERROR_MESSAGE);
+    let commands = parser.parse_proof().expect(ERROR_MESSAGE);
+    let attributes = parser.take_attributes();
+    let term_attributes = parser.take_term_attributes();
+    let spans = parser.take_spans();
+    let flattened = parser.take_flattened();
+    Proof {
+        premises: IndexSet::new(),
+        commands,
+        attributes,
+        term_attributes,
+        spans,
+        flattened,
+    }
 }
 
 fn run_parser_tests(pool: &mut PrimitivePool, cases: &[(&str, Rc<Term>)]) {
@@ -147,6 +167,52 @@ fn test_arithmetic_ops() {
     ));
 }
 
+#[test]
+fn test_arithmetic_conversion_ops() {
+    let mut p = PrimitivePool::new();
+    let two = p.add(Term::new_int(2));
+    let one_and_half = p.add(Term::new_real((3, 2)));
+    let cases = [
+        (
+            "(to_real 2)",
+            p.add(Term::Op(Operator::ToReal, vec![two.clone()])),
+        ),
+        (
+            "(to_int 1.5)",
+            p.add(Term::Op(Operator::ToInt, vec![one_and_half.clone()])),
+        ),
+        (
+            "(is_int 1.5)",
+            p.add(Term::Op(Operator::IsInt, vec![one_and_half])),
+        ),
+        (
+            "((_ divisible 3) 2)",
+            p.add(Term::IndexedOp {
+                op: IndexedOperator::Divisible,
+                op_args: vec![Constant::Integer(3.into())],
+                args: vec![two],
+            }),
+        ),
+    ];
+    run_parser_tests(&mut p, &cases);
+
+    // Mixed Int/Real arithmetic is accepted once the coercion is explicit
+    let term = parse_term(&mut p, "(< (to_real 1) 1.5)");
+    assert_eq!(p.sort(&term).as_sort(), Some(&Sort::Bool));
+
+    for term in [
+        "(to_real 1.5)",
+        "(to_int 1)",
+        "(is_int 1)",
+        "((_ divisible 3) 1.5)",
+    ] {
+        assert!(matches!(
+            parse_term_err(term),
+            Error::Parser(ParserError::SortError(_), _),
+        ));
+    }
+}
+
 #[test]
 fn test_logic_ops() {
     let mut p = PrimitivePool::new();
@@ -328,6 +394,16 @@ fn test_choice_terms() {
         parse_term_err("(choice ((x Int) (y Int)) (= x y))"),
         Error::Parser(ParserError::UnexpectedToken(_), _),
     ));
+    assert!(matches!(
+        parse_term_err("(choice ((x Int)) (+ x 1))"),
+        Error::Parser(ParserError::SortError(_), _),
+    ));
+
+    // The bound variable is not visible outside of the `choice` term
+    assert!(matches!(
+        parse_term_err("(= (choice ((x Int)) (= x 0)) x)"),
+        Error::Parser(ParserError::UndefinedIden(iden), _) if iden == "x",
+    ));
 }
 
 #[test]
@@ -392,6 +468,32 @@ fn test_lambda_terms() {
     ));
 }
 
+#[test]
+fn test_higher_order_terms() {
+    let mut p = PrimitivePool::new();
+    let [applied, arg, bound] = parse_terms(
+        &mut p,
+        "(declare-fun h ((-> Int Int)) Int)",
+        [
+            "(h (lambda ((x Int)) (+ x 1)))",
+            "(lambda ((x Int)) (+ x 1))",
+            "(forall ((f (-> Int Bool))) (f 0))",
+        ],
+    );
+    let Term::App(_, args) = applied.as_ref() else {
+        panic!();
+    };
+    assert_eq!(args, std::slice::from_ref(&arg));
+    let int_to_int = Sort::Function(vec![p.add(Term::Sort(Sort::Int)); 2]);
+    assert_eq!(p.sort(&arg).as_sort(), Some(&int_to_int));
+    assert_eq!(format!("{:#}", bound), "(forall ((f (-> Int Bool))) (f 0))");
+
+    assert!(matches!(
+        parse_term_err("(forall ((f (-> Int))) true)"),
+        Error::Parser(ParserError::WrongNumberOfArgs(_, 1), _),
+    ));
+}
+
 #[test]
 fn test_annotated_terms() {
     let mut p = PrimitivePool::new();
@@ -493,6 +595,453 @@ fn test_define_fun() {
     assert_eq!(expected, got);
 }
 
+#[test]
+fn test_define_const() {
+    let mut p = PrimitivePool::new();
+    let [got] = parse_terms(&mut p, "(define-const x Int 2)", ["(+ x 3)"]);
+    assert_eq!(parse_term(&mut p, "(+ 2 3)"), got);
+
+    let [got] = parse_terms(
+        &mut p,
+        "(declare-const a Int)
+         (define-const b Int (* a a))",
+        ["(< b a)"],
+    );
+    let [expected] = parse_terms(&mut p, "(declare-fun a () Int)", ["(< (* a a) a)"]);
+    assert_eq!(expected, got);
+}
+
+#[test]
+fn test_define_fun_rec() {
+    let mut p = PrimitivePool::new();
+    let problem = "
+        (define-fun-rec fact ((n Int)) Int (ite (<= n 0) 1 (* n (fact (- n 1)))))
+        (define-funs-rec
+            ((even ((n Int)) Bool) (odd ((n Int)) Bool))
+            ((ite (= n 0) true (odd (- n 1))) (ite (= n 0) false (even (- n 1)))))
+        (assert (even (fact 3)))
+    ";
+    let mut parser = Parser::new(&mut p, TEST_CONFIG, problem.as_bytes()).unwrap();
+    let (prelude, premises) = parser.parse_problem().unwrap();
+
+    let names: Vec<_> = prelude
+        .function_declarations()
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect();
+    assert_eq!(names, ["fact", "even", "odd"]);
+    assert_eq!(prelude.function_arity("odd"), Some(1));
+
+    // The definitions are not unfolded, and instead each one becomes a premise
+    let [fact, odd, assertion] = parse_terms(
+        &mut p,
+        "(declare-fun fact (Int) Int)
+         (declare-fun even (Int) Bool)
+         (declare-fun odd (Int) Bool)",
+        [
+            "(forall ((n Int)) (= (fact n) (ite (<= n 0) 1 (* n (fact (- n 1))))))",
+            "(forall ((n Int)) (= (odd n) (ite (= n 0) false (even (- n 1)))))",
+            "(even (fact 3))",
+        ],
+    );
+    assert_eq!(premises.len(), 4);
+    assert_eq!(premises[0], fact);
+    assert_eq!(premises[2], odd);
+    assert_eq!(premises[3], assertion);
+
+    let invalid = [
+        "(define-fun-rec f ((x Int)) Int (f true))",
+        "(define-funs-rec ((f () Int) (g () Int)) (1))",
+    ];
+    for input in invalid {
+        let mut pool = PrimitivePool::new();
+        let result = Parser::new(&mut pool, TEST_CONFIG, input.as_bytes())
+            .and_then(|mut parser| parser.parse_problem());
+        assert!(matches!(result, Err(Error::Parser(..))), "{}", input);
+    }
+}
+
+#[test]
+fn test_define_sort() {
+    let mut p = PrimitivePool::new();
+    let [got] = parse_terms(
+        &mut p,
+        "(define-sort Word () (_ BitVec 8))
+         (declare-fun x () Word)",
+        ["x"],
+    );
+    let expected_sort = p.add(Term::Sort(Sort::BitVec(rug::Integer::from(8))));
+    assert_eq!(p.add(Term::new_var("x", expected_sort)), got);
+
+    let [got] = parse_terms(
+        &mut p,
+        "(declare-sort T 0)
+         (define-sort Map (K V) (Array K V))
+         (declare-fun m () (Map Int T))",
+        ["m"],
+    );
+    let [expected] = parse_terms(
+        &mut p,
+        "(declare-sort T 0)
+         (declare-fun m () (Array Int T))",
+        ["m"],
+    );
+    assert_eq!(expected, got);
+
+    // Sort parameters shadow sort definitions with the same name, and only while in scope
+    let [got_m, got_k] = parse_terms(
+        &mut p,
+        "(define-sort K () Bool)
+         (define-sort Map (K V) (Array K V))
+         (declare-fun m () (Map Int Real))
+         (declare-fun k () K)",
+        ["m", "k"],
+    );
+    let [expected_m, expected_k] = parse_terms(
+        &mut p,
+        "(declare-fun m () (Array Int Real))
+         (declare-fun k () Bool)",
+        ["m", "k"],
+    );
+    assert_eq!(expected_m, got_m);
+    assert_eq!(expected_k, got_k);
+
+    // Sort definitions may also appear in the proof
+    let proof = parse_proof(
+        &mut p,
+        "(define-sort Word () (_ BitVec 8))
+         (step t1 (cl (forall ((x Word)) (= x x))) :rule hole)",
+    );
+    let expected = parse_term(&mut p, "(forall ((x (_ BitVec 8))) (= x x))");
+    assert_eq!(proof.commands[0].clause(), [expected]);
+}
+
+#[test]
+fn test_polymorphic_functions() {
+    let mut p = PrimitivePool::new();
+    let definitions = "
+        (declare-fun id (par (X) (X) X))
+        (declare-fun empty (par (X) () (Array Int X)))
+        (define-fun pick (par (X) ((c Bool) (a X) (b X)) X) (ite c a b))
+        (define-fun store0 (par (X) ((a (Array Int X)) (v X)) (Array Int X)) (store a 0 v))
+    ";
+    let [id_int, id_bool, empty, pick, store] = parse_terms(
+        &mut p,
+        definitions,
+        [
+            "(id 1)",
+            "(id true)",
+            "(as empty (Array Int Bool))",
+            "(pick false 1 2)",
+            "(store0 (as empty (Array Int Int)) 1)",
+        ],
+    );
+
+    // Each use of a declared polymorphic function instantiates its sort
+    let int_sort = p.add(Term::Sort(Sort::Int));
+    let bool_sort = p.add(Term::Sort(Sort::Bool));
+    let id_int_sort = p.add(Term::Sort(Sort::Function(vec![int_sort.clone(); 2])));
+    let id_bool_sort = p.add(Term::Sort(Sort::Function(vec![bool_sort.clone(); 2])));
+    match (id_int.as_ref(), id_bool.as_ref()) {
+        (Term::App(f, _), Term::App(g, _)) => {
+            assert_eq!(&id_int_sort, &p.sort(f));
+            assert_eq!(&id_bool_sort, &p.sort(g));
+        }
+        _ => panic!(),
+    }
+    let array_sort = p.add(Term::Sort(Sort::Array(int_sort.clone(), bool_sort)));
+    assert_eq!(p.add(Term::new_var("empty", array_sort)), empty);
+
+    // Polymorphic definitions are instantiated and then applied
+    assert_eq!(parse_term(&mut p, "(ite false 1 2)"), pick);
+    let array_sort = p.add(Term::Sort(Sort::Array(int_sort.clone(), int_sort)));
+    let empty = p.add(Term::new_var("empty", array_sort));
+    let [zero, one] = [0, 1].map(|n| p.add(Term::new_int(n)));
+    let expected = p.add(Term::Op(Operator::Store, vec![empty, zero, one]));
+    assert_eq!(expected, store);
+
+    // Without arguments or a sort ascription, the sort parameters can't be inferred
+    let invalid = ["empty", "(pick false 1 true)", "(as empty Int)"];
+    for input in invalid {
+        let mut pool = PrimitivePool::new();
+        let mut parser = Parser::new(&mut pool, TEST_CONFIG, definitions.as_bytes()).unwrap();
+        parser.parse_problem().unwrap();
+        parser.reset(input.as_bytes()).unwrap();
+        assert!(
+            matches!(parser.parse_term(), Err(Error::Parser(..))),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_declare_datatypes() {
+    let mut p = PrimitivePool::new();
+    let definitions = "
+        (declare-datatypes ((List 0) (Tree 0)) (
+            ((nil) (cons (head Int) (tail List)))
+            ((leaf) (node (children List)))
+        ))
+        (declare-datatype Color (red green blue))
+        (declare-fun l () List)
+        (declare-fun t () Tree)
+    ";
+    let [head, tester, children, color] = parse_terms(
+        &mut p,
+        definitions,
+        [
+            "(head (cons 1 nil))",
+            "((_ is cons) l)",
+            "(children t)",
+            "(= red green)",
+        ],
+    );
+    assert_eq!(&Sort::Int, p.sort(&head).as_sort().unwrap());
+    assert_eq!(&Sort::Bool, p.sort(&tester).as_sort().unwrap());
+    assert_eq!(
+        &Sort::Datatype("List".to_owned(), Vec::new()),
+        p.sort(&children).as_sort().unwrap()
+    );
+    assert_eq!(&Sort::Bool, p.sort(&color).as_sort().unwrap());
+
+    let list_sort = p.add(Term::Sort(Sort::Datatype("List".to_owned(), Vec::new())));
+    let bool_sort = p.add(Term::Sort(Sort::Bool));
+    let tester_sort = p.add(Term::Sort(Sort::Function(vec![
+        list_sort.clone(),
+        bool_sort,
+    ])));
+    let expected = Term::App(
+        p.add(Term::new_var("is-cons", tester_sort)),
+        vec![p.add(Term::new_var("l", list_sort))],
+    );
+    assert_eq!(p.add(expected), tester);
+
+    let mut parser = Parser::new(&mut p, TEST_CONFIG, definitions.as_bytes()).expect(ERROR_MESSAGE);
+    let (prelude, _) = parser.parse_problem().expect(ERROR_MESSAGE);
+    let names: Vec<_> = prelude
+        .datatype_declarations()
+        .iter()
+        .map(|group| group.iter().map(|d| d.name.as_str()).collect::<Vec<_>>())
+        .collect();
+    assert_eq!(vec![vec!["List", "Tree"], vec!["Color"]], names);
+}
+
+#[test]
+fn test_parametric_datatypes() {
+    let mut p = PrimitivePool::new();
+    let definitions = "
+        (declare-datatypes ((List 1)) (
+            (par (T) ((nil) (cons (head T) (tail (List T)))))
+        ))
+        (declare-datatype Pair (par (A B) ((pair (fst A) (snd B)))))
+        (declare-fun l () (List Int))
+        (declare-fun q () (Pair Bool (List Real)))
+    ";
+    let [head, tail, empty, tester, snd] = parse_terms(
+        &mut p,
+        definitions,
+        [
+            "(head (cons 1 (as nil (List Int))))",
+            "(tail l)",
+            "(as nil (List Bool))",
+            "((_ is nil) l)",
+            "(snd q)",
+        ],
+    );
+    let int_sort = p.add(Term::Sort(Sort::Int));
+    let real_sort = p.add(Term::Sort(Sort::Real));
+    let int_list = Sort::Datatype("List".to_owned(), vec![int_sort]);
+    assert_eq!(&Sort::Int, p.sort(&head).as_sort().unwrap());
+    assert_eq!(&int_list, p.sort(&tail).as_sort().unwrap());
+    assert_eq!(&Sort::Bool, p.sort(&tester).as_sort().unwrap());
+    assert_eq!(
+        &Sort::Datatype("List".to_owned(), vec![real_sort]),
+        p.sort(&snd).as_sort().unwrap()
+    );
+    let bool_sort = p.add(Term::Sort(Sort::Bool));
+    assert_eq!(
+        &Sort::Datatype("List".to_owned(), vec![bool_sort]),
+        p.sort(&empty).as_sort().unwrap()
+    );
+
+    let mut parser = Parser::new(&mut p, TEST_CONFIG, definitions.as_bytes()).expect(ERROR_MESSAGE);
+    let (prelude, _) = parser.parse_problem().expect(ERROR_MESSAGE);
+    let pair = &prelude.datatype_declarations()[1][0];
+    assert_eq!(vec!["A", "B"], pair.sort_params);
+    let printed = prelude.to_string();
+    assert!(printed.contains(
+        "(declare-datatypes ((List 1)) ((par (T) ((nil) (cons (head T) (tail (List T)))))))"
+    ));
+
+    let cases = [
+        // The sort parameters of a nullary constructor can only be inferred using `as`
+        "nil",
+        // The number of sort parameters must match the arity of the datatype
+        "(cons 1 (as nil List))",
+        "(head (cons 1 (as nil (List Real))))",
+    ];
+    for case in cases {
+        let mut parser =
+            Parser::new(&mut p, TEST_CONFIG, definitions.as_bytes()).expect(ERROR_MESSAGE);
+        parser.parse_problem().expect(ERROR_MESSAGE);
+        parser.reset(case.as_bytes()).expect(ERROR_MESSAGE);
+        assert!(parser.parse_term().is_err(), "{}", case);
+    }
+
+    let mut parser = Parser::new(
+        &mut p,
+        TEST_CONFIG,
+        "(declare-datatypes ((Pair 2)) ((par (A) ((pair (fst A))))))".as_bytes(),
+    )
+    .expect(ERROR_MESSAGE);
+    assert!(matches!(
+        parser.parse_problem(),
+        Err(Error::Parser(ParserError::WrongNumberOfArgs(..), _))
+    ));
+}
+
+#[test]
+fn test_incremental_problems() {
+    fn premises(pool: &mut PrimitivePool, problem: &str) -> Vec<String> {
+        let mut parser = Parser::new(pool, TEST_CONFIG, problem.as_bytes()).expect(ERROR_MESSAGE);
+        let (_, premises) = parser.parse_problem().expect(ERROR_MESSAGE);
+        premises.iter().map(ToString::to_string).collect()
+    }
+
+    let mut p = PrimitivePool::new();
+    let declarations = "(declare-fun a () Bool) (declare-fun b () Bool) (declare-fun c () Bool)";
+    let cases: [(&str, &[&str]); 6] = [
+        (
+            "(assert a) (check-sat-assuming (b (not c)))",
+            &["a", "b", "(not c)"],
+        ),
+        (
+            "(assert a) (check-sat-assuming (b)) (check-sat-assuming (c))",
+            &["a", "c"],
+        ),
+        ("(assert a) (check-sat-assuming (b)) (check-sat)", &["a"]),
+        (
+            "(assert a) (push 1) (assert b) (pop 1) (assert c)",
+            &["a", "c"],
+        ),
+        (
+            "(push 1) (assert a) (push 2) (assert b) (pop 1) (assert c) (check-sat)",
+            &["a", "c"],
+        ),
+        (
+            "(push) (assert a) (pop) (push) (assert b) (check-sat-assuming (c))",
+            &["b", "c"],
+        ),
+    ];
+    for (problem, expected) in cases {
+        let got = premises(&mut p, &format!("{} {}", declarations, problem));
+        assert_eq!(expected, got);
+    }
+}
+
+#[test]
+fn test_named_premises() {
+    let problem = "
+        (declare-fun a () Bool)
+        (declare-fun b () Bool)
+        (assert (! (or a b) :named foo))
+        (assert (! (not a) :named bar))
+    ";
+    let proof = "
+        (assume h1 foo)
+        (assume h2 (not a))
+        (step t3 (cl a b) :rule or :premises (foo))
+        (step t4 (cl b) :rule resolution :premises (t3 bar))
+    ";
+    let mut p = PrimitivePool::new();
+    let mut parser = Parser::new(&mut p, TEST_CONFIG, problem.as_bytes()).unwrap();
+    let (prelude, _) = parser.parse_problem().unwrap();
+    let names: Vec<_> = prelude.named_terms().keys().collect();
+    assert_eq!(names, ["foo", "bar"]);
+
+    parser.reset(proof.as_bytes()).unwrap();
+    let commands = parser.parse_proof().unwrap();
+    let [_, _, ProofCommand::Step(t3), ProofCommand::Step(t4)] = commands.as_slice() else {
+        panic!();
+    };
+    assert_eq!(t3.premises, [(0, 0)]);
+    assert_eq!(t4.premises, [(0, 2), (0, 1)]);
+
+    let proof = "(step t1 (cl a) :rule hole :premises (baz))";
+    let mut parser = Parser::new(&mut p, TEST_CONFIG, problem.as_bytes()).unwrap();
+    parser.parse_problem().unwrap();
+    parser.reset(proof.as_bytes()).unwrap();
+    assert!(matches!(
+        parser.parse_proof(),
+        Err(Error::Parser(ParserError::UndefinedStepIndex(_), _))
+    ));
+}
+
+#[test]
+fn test_queries() {
+    let problem = "
+        (declare-fun a () Bool)
+        (declare-fun b () Bool)
+        (declare-fun c () Bool)
+        (assert a)
+        (push 1)
+        (assert b)
+        (check-sat)
+        (pop 1)
+        (check-sat-assuming (c))
+        (assert b)
+    ";
+    let to_strings =
+        |set: &IndexSet<Rc<Term>>| -> Vec<String> { set.iter().map(ToString::to_string).collect() };
+
+    let mut p = PrimitivePool::new();
+    let mut parser = Parser::new(&mut p, TEST_CONFIG, problem.as_bytes()).unwrap();
+    let (_, premises) = parser.parse_problem().unwrap();
+    let queries = parser.take_queries();
+    assert_eq!(to_strings(&premises), ["a", "b", "c"]);
+    assert_eq!(queries.len(), 2);
+    assert_eq!(to_strings(&queries[0]), ["a", "b"]);
+    assert_eq!(to_strings(&queries[1]), ["a", "c"]);
+
+    for (query, expected) in [(0, ["a", "b"]), (1, ["a", "c"])] {
+        let config = Config { query: Some(query), ..TEST_CONFIG };
+        let mut parser = Parser::new(&mut p, config, problem.as_bytes()).unwrap();
+        let (_, premises) = parser.parse_problem().unwrap();
+        assert_eq!(to_strings(&premises), expected);
+    }
+
+    let config = Config { query: Some(2), ..TEST_CONFIG };
+    let mut parser = Parser::new(&mut p, config, problem.as_bytes()).unwrap();
+    assert!(matches!(
+        parser.parse_problem(),
+        Err(Error::Parser(ParserError::UndefinedQuery(2, 2), _))
+    ));
+}
+
+#[test]
+fn test_reset_proof() {
+    let mut p = PrimitivePool::new();
+    let problem = "(declare-fun a () Bool)";
+    let mut parser = Parser::new(&mut p, TEST_CONFIG, problem.as_bytes()).unwrap();
+    parser.parse_problem().unwrap();
+
+    // Step ids can be reused in an independent proof, but not in a continuation of the same proof
+    let proof = "(assume h1 a) (step t1 (cl a) :rule hole :premises (h1))";
+    parser.reset(proof.as_bytes()).unwrap();
+    parser.parse_proof().unwrap();
+    parser.reset(proof.as_bytes()).unwrap();
+    assert!(matches!(
+        parser.parse_proof(),
+        Err(Error::Parser(ParserError::RepeatedStepIndex(_), _))
+    ));
+
+    parser.reset_proof(proof.as_bytes()).unwrap();
+    let commands = parser.parse_proof().unwrap();
+    assert_eq!(commands.len(), 2);
+    assert_eq!(parser.spans().len(), 2);
+}
+
 #[test]
 fn test_assume() {
     let mut p = PrimitivePool::new();
@@ -610,6 +1159,83 @@ fn test_step() {
     );
 }
 
+#[test]
+fn test_step_attributes() {
+    let mut p = PrimitivePool::new();
+    let input = "
+        (step t1 (cl) :rule rule-name :origin \"arith\" :flag :data (1 (a b)))
+        (step t2 (cl) :rule rule-name :premises (t1))
+    ";
+    let proof = parse_proof(&mut p, input);
+    assert_eq!(proof.attributes.len(), 1);
+    assert_eq!(
+        proof.attributes["t1"],
+        [
+            Attribute {
+                keyword: "origin".into(),
+                value: Some("\"arith\"".into()),
+            },
+            Attribute { keyword: "flag".into(), value: None },
+            Attribute {
+                keyword: "data".into(),
+                value: Some("(1 (a b))".into()),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_term_attributes() {
+    let mut p = PrimitivePool::new();
+    let input = "
+        (assume h1 (forall ((x Int)) (! (> x 0) :pattern ((+ x 1)) :qid q1 :named a)))
+        (assume h2 (! (> 1 0) :weight 2))
+        (assume h3 (! (> 1 0) :other))
+    ";
+    let proof = parse_proof(&mut p, input);
+    assert_eq!(proof.term_attributes.len(), 2);
+
+    let (term, attributes) = proof.term_attributes.first().unwrap();
+    assert_eq!(term.to_string(), "(> x 0)");
+    assert_eq!(
+        attributes,
+        &[
+            Attribute {
+                keyword: "pattern".into(),
+                value: Some("((+ x 1))".into()),
+            },
+            Attribute {
+                keyword: "qid".into(),
+                value: Some("q1".into()),
+            },
+        ]
+    );
+
+    // Only the attributes of the first annotation of a term are kept
+    let one_gt_zero = parse_term(&mut p, "(> 1 0)");
+    assert_eq!(
+        proof.term_attributes[&one_gt_zero],
+        [Attribute {
+            keyword: "weight".into(),
+            value: Some("2".into()),
+        }]
+    );
+}
+
+#[test]
+fn test_command_spans() {
+    let mut p = PrimitivePool::new();
+    let input = "(assume h1 true)\n; comment\n(anchor :step t2)\n\
+        (step t2.t1 (cl) :rule hole)\n(step t2 (cl) :rule hole)\n";
+    let proof = parse_proof(&mut p, input);
+    let span = |bytes, start, end| Span { bytes, start, end };
+    assert_eq!(proof.spans.len(), 3);
+    assert_eq!(proof.spans["h1"], span((0, 16), (1, 1), (1, 17)));
+    assert_eq!(proof.spans["t2.t1"], span((45, 73), (4, 1), (4, 29)));
+    assert_eq!(proof.spans["t2"], span((74, 99), (5, 1), (5, 26)));
+    assert_eq!(&input[74..99], "(step t2 (cl) :rule hole)");
+}
+
 #[test]
 fn test_premises_in_subproofs() {
     let mut p = PrimitivePool::new();
@@ -663,6 +1289,393 @@ fn test_premises_in_subproofs() {
     );
 }
 
+#[test]
+fn test_parse_proof_streaming() {
+    let input = "
+        unsat
+        (assume h1 true)
+        (anchor :step t2)
+        (anchor :step t2.t1)
+        (step t2.t1.t1 (cl) :rule rule-name :premises (h1))
+        (step t2.t1 (cl) :rule rule-name)
+        (step t2 (cl) :rule rule-name)
+        (step t3 (cl) :rule rule-name :premises (h1 t2))
+    ";
+    let mut p = PrimitivePool::new();
+    let expected = parse_proof(&mut p, input).commands;
+    let mut parser = Parser::new(&mut p, TEST_CONFIG, input.as_bytes()).unwrap();
+    let streamed: Vec<_> = parser.parse_proof_streaming().map(Result::unwrap).collect();
+    assert_eq!(streamed, expected);
+
+    // After an error, the iterator yields no more items
+    let input = "
+        (assume h1 true)
+        (anchor :step t2)
+        (step t2.t1 (cl) :rule rule-name)
+    ";
+    let mut parser = Parser::new(&mut p, TEST_CONFIG, input.as_bytes()).unwrap();
+    let mut stream = parser.parse_proof_streaming();
+    assert!(matches!(
+        stream.next(),
+        Some(Ok(ProofCommand::Assume { .. }))
+    ));
+    assert!(matches!(
+        stream.next(),
+        Some(Err(Error::Parser(ParserError::UnclosedSubproof(id), _))) if id == "t2"
+    ));
+    assert!(stream.next().is_none());
+}
+
+#[test]
+fn test_parse_proof_parallel() {
+    let input = "
+        unsat
+        (assume h1 true)
+        (assume h2 (not true))
+        (step t3 (cl) :rule rule-name :premises (h1 h2))
+        (anchor :step t4 :args ((x Int)))
+        (anchor :step t4.t1)
+        (step t4.t1.t1 (cl (= x x)) :rule rule-name :premises (h1))
+        (step t4.t1 (cl) :rule rule-name)
+        (step t4 (cl) :rule rule-name :premises (t3))
+        (define-fun f ((a Int)) Int (+ a 1))
+        (step t5 (cl (= (f 2) 3)) :rule rule-name :premises (t3 t4) :some-attribute)
+        (anchor :step t6 :args ((y Int)))
+        (step t6.t1 (cl (= (f y) (+ y 1))) :rule rule-name :premises (h2 t5))
+        (step t6 (cl) :rule rule-name)
+        (step t7 (cl) :rule rule-name :premises (t3 t6))
+    ";
+    let mut p = PrimitivePool::new();
+    let expected = parse_proof(&mut p, input);
+    for num_threads in [2, 3, 8] {
+        let mut parser = Parser::new(&mut p, TEST_CONFIG, "".as_bytes()).unwrap();
+        let commands = parser
+            .parse_proof_parallel(input.as_bytes(), num_threads)
+            .unwrap();
+        assert_eq!(commands, expected.commands);
+        assert_eq!(parser.take_attributes(), expected.attributes);
+        assert_eq!(parser.take_spans(), expected.spans);
+    }
+
+    // Errors in later chunks are reported in their position in the whole proof
+    let input = "
+        (assume h1 true)
+        (step t2 (cl) :rule rule-name :premises (h1))
+        (step t3 (cl) :rule rule-name :premises (t2))
+        (step t4 (cl) :rule rule-name :premises (t5))
+    ";
+    let mut parser = Parser::new(&mut p, TEST_CONFIG, "".as_bytes()).unwrap();
+    assert!(matches!(
+        parser.parse_proof_parallel(input.as_bytes(), 3),
+        Err(Error::Parser(ParserError::UndefinedStepIndex(id), (5, _))) if id == "t5"
+    ));
+
+    let input = "
+        (assume h1 true)
+        (step t2 (cl) :rule rule-name)
+        (step h1 (cl) :rule rule-name)
+    ";
+    let mut parser = Parser::new(&mut p, TEST_CONFIG, "".as_bytes()).unwrap();
+    assert!(matches!(
+        parser.parse_proof_parallel(input.as_bytes(), 2),
+        Err(Error::Parser(ParserError::RepeatedStepIndex(id), (4, _))) if id == "h1"
+    ));
+}
+
+#[test]
+fn test_parse_proof_recovering() {
+    let input = "
+        (assume h1 true)
+        (step t2 (cl) :rule rule-name :premises (h5))
+        (anchor :step t3 :args ((x Int)))
+        (step t3.t1 (cl (forall ((y Int)) (not y))) :rule rule-name)
+        (step t3.t2 (cl (= x x)) :rule rule-name)
+        (step t3 (cl) :rule rule-name)
+        (step t4 (cl) [ :rule rule-name)
+        (step t5 (cl) :rule rule-name :premises (h1 t3))
+    ";
+    let mut p = PrimitivePool::new();
+    let mut parser = Parser::new(&mut p, TEST_CONFIG, input.as_bytes()).unwrap();
+    let (commands, errors) = parser.parse_proof_recovering();
+
+    let ids: Vec<_> = commands.iter().map(ProofCommand::id).collect();
+    assert_eq!(ids, ["h1", "t3", "t5"]);
+    let ProofCommand::Subproof(subproof) = &commands[1] else {
+        panic!();
+    };
+    assert_eq!(subproof.commands.len(), 2);
+    let ProofCommand::Step(t5) = &commands[2] else {
+        panic!();
+    };
+    assert_eq!(t5.premises, [(0, 0), (0, 1)]);
+
+    assert_eq!(errors.len(), 3);
+    assert!(matches!(
+        &errors[0],
+        Error::Parser(ParserError::UndefinedStepIndex(id), (3, _)) if id == "h5"
+    ));
+    assert!(matches!(
+        &errors[1],
+        Error::Parser(ParserError::SortError(_), (5, _))
+    ));
+    assert!(matches!(
+        &errors[2],
+        Error::Parser(ParserError::UnexpectedChar('['), (8, _))
+    ));
+
+    // The binding introduced by the `forall` term, where the error was found, is not visible in
+    // the following commands
+    let input = "
+        (step t1 (cl (forall ((y Int)) (not y))) :rule rule-name)
+        (step t2 (cl (= y y)) :rule rule-name)
+    ";
+    let mut parser = Parser::new(&mut p, TEST_CONFIG, input.as_bytes()).unwrap();
+    let (commands, errors) = parser.parse_proof_recovering();
+    assert!(commands.is_empty());
+    assert!(matches!(
+        &errors[1],
+        Error::Parser(ParserError::UndefinedIden(iden), _) if iden == "y"
+    ));
+}
+
+#[test]
+fn test_anchor_assignments() {
+    let mut p = PrimitivePool::new();
+    let input = "
+        (anchor :step t1 :args ((:= x 1) (:= (y Int) 2)))
+        (step t1.t1 (cl) :rule rule-name)
+        (step t1 (cl) :rule rule-name)
+    ";
+    let proof = parse_proof(&mut p, input);
+    let ProofCommand::Subproof(subproof) = &proof.commands[0] else {
+        panic!();
+    };
+    assert_eq!(
+        subproof.assignment_args,
+        vec![
+            (Symbol::new("x"), p.add(Term::new_int(1))),
+            (Symbol::new("y"), p.add(Term::new_int(2))),
+        ]
+    );
+
+    // Variable bindings and assignments can be mixed, and an assignment of an undefined symbol to
+    // a sorted variable introduces it as a fresh variable
+    let input = "
+        (anchor :step t1 :args ((:= (x Int) y) (z Real) (:= (w Real) z)))
+        (step t1.t1 (cl) :rule rule-name)
+        (step t1 (cl (= y (+ x 1))) :rule rule-name)
+    ";
+    let proof = parse_proof(&mut p, input);
+    let ProofCommand::Subproof(subproof) = &proof.commands[0] else {
+        panic!();
+    };
+    let [int, real] = [Sort::Int, Sort::Real].map(|s| p.add(Term::Sort(s)));
+    assert_eq!(
+        subproof.variable_args,
+        vec![
+            (Symbol::new("y"), int.clone()),
+            (Symbol::new("z"), real.clone())
+        ]
+    );
+    assert_eq!(
+        subproof.assignment_args,
+        vec![
+            (Symbol::new("x"), p.add(Term::new_var("y", int))),
+            (Symbol::new("w"), p.add(Term::new_var("z", real))),
+        ]
+    );
+
+    // If the variable is sorted, the assigned value must be of the same sort
+    let input = "
+        (anchor :step t1 :args ((:= (y Bool) 2)))
+        (step t1 (cl) :rule rule-name)
+    ";
+    let err = Parser::new(&mut p, TEST_CONFIG, input.as_bytes())
+        .and_then(|mut parser| parser.parse_proof())
+        .expect_err("expected error");
+    assert!(matches!(err, Error::Parser(ParserError::SortError(_), _)));
+}
+
+#[test]
+fn test_alethe_version() {
+    let parse = |input: &str, version| {
+        let mut p = PrimitivePool::new();
+        let config = Config {
+            alethe_version: Some(version),
+            ..TEST_CONFIG
+        };
+        Parser::new(&mut p, config, input.as_bytes()).and_then(|mut parser| parser.parse_proof())
+    };
+    let is_unsupported = |result: CarcaraResult<_>, expected| {
+        matches!(
+            result,
+            Err(Error::Parser(ParserError::UnsupportedInVersion(_, v), _)) if v == expected
+        )
+    };
+
+    let subproof = |assignment| {
+        format!(
+            "(anchor :step t1 :args ({}))
+            (step t1.t1 (cl) :rule rule-name)
+            (step t1 (cl) :rule rule-name)",
+            assignment
+        )
+    };
+    let legacy = &subproof("(:= x 1)");
+    let current = &subproof("(:= (x Int) 1)");
+    let assign_arg = "(step t1 (cl) :rule rule-name :args ((:= x 1)))";
+
+    // If no version is selected, both syntaxes are accepted
+    for input in [legacy, current, assign_arg] {
+        parse_proof(&mut PrimitivePool::new(), input);
+    }
+
+    assert!(parse(legacy, AletheVersion::Legacy).is_ok());
+    assert!(parse(assign_arg, AletheVersion::Legacy).is_ok());
+    assert!(is_unsupported(
+        parse(current, AletheVersion::Legacy),
+        AletheVersion::Legacy
+    ));
+
+    assert!(parse(current, AletheVersion::Current).is_ok());
+    assert!(is_unsupported(
+        parse(legacy, AletheVersion::Current),
+        AletheVersion::Current
+    ));
+    assert!(is_unsupported(
+        parse(assign_arg, AletheVersion::Current),
+        AletheVersion::Current
+    ));
+}
+
+#[test]
+fn test_verit_compat() {
+    let definitions = "(declare-fun p () Bool) (declare-fun x () Int) (declare-fun y () Int)";
+    let proof = "
+        (anchor :args ((:= (z Int) y)) :step t1)
+        (step t1.t1 (= x z) :rule hole)
+        (step t1 (cl (= x y)) :rule hole)
+        (step t2 false :rule hole)
+        (step t3 (not p) :rule hole)
+    ";
+    let parse = |config| {
+        let mut p = PrimitivePool::new();
+        let mut parser = Parser::new(&mut p, config, definitions.as_bytes())?;
+        parser.parse_problem()?;
+        parser.reset(proof.as_bytes())?;
+        parser.parse_proof()
+    };
+    assert!(parse(TEST_CONFIG).is_err());
+
+    let commands = parse(Config { verit_compat: true, ..TEST_CONFIG }).unwrap();
+    let ProofCommand::Subproof(subproof) = &commands[0] else {
+        panic!();
+    };
+    assert_eq!(subproof.assignment_args.len(), 1);
+    assert_eq!(subproof.commands[0].clause().len(), 1);
+    assert!(commands[1].clause().is_empty());
+    assert_eq!(commands[2].clause()[0].to_string(), "(not p)");
+}
+
+#[test]
+fn test_smtinterpol_compat() {
+    let definitions = "(declare-fun p () Bool) (assert p)";
+    let proof = "
+        (assume 1 p)
+        (step 2 (cl p) :rule hole :premises (1) :args ())
+        (step 3 (cl p p) :rule hole :args () :premises (1 2))
+        (step t4 (cl p) :rule contraction :premises ())
+    ";
+    let parse = |config| {
+        let mut p = PrimitivePool::new();
+        let mut parser = Parser::new(&mut p, config, definitions.as_bytes())?;
+        parser.parse_problem()?;
+        parser.reset(proof.as_bytes())?;
+        parser.parse_proof()
+    };
+    assert!(parse(TEST_CONFIG).is_err());
+
+    let commands = parse(Config {
+        smtinterpol_compat: true,
+        ..TEST_CONFIG
+    })
+    .unwrap();
+    let ids: Vec<_> = commands.iter().map(ProofCommand::id).collect();
+    assert_eq!(ids, ["1", "2", "3", "t4"]);
+    let ProofCommand::Step(step) = &commands[2] else {
+        panic!();
+    };
+    assert_eq!(step.premises, [(0, 0), (0, 1)]);
+    let ProofCommand::Step(step) = &commands[3] else {
+        panic!();
+    };
+    assert!(step.premises.is_empty());
+}
+
+#[test]
+fn test_rare_rewrite_args() {
+    let mut p = PrimitivePool::new();
+    let definitions = "(declare-fun p () Bool) (declare-fun q () Bool)";
+    let proof = "
+        (step t1 (cl (= (not (not p)) p)) :rule rare_rewrite :args (\"bool-double-not-elim\" p))
+        (step t2 (cl (= (and p q) (and q p))) :rule rare_rewrite :args (and-comm (@list p q)))
+        (step t3 (cl) :rule rare_rewrite :args (bool-and-true (@list) (not p) (@list)))
+    ";
+    let mut parser = Parser::new(&mut p, TEST_CONFIG, definitions.as_bytes()).unwrap();
+    parser.parse_problem().unwrap();
+    parser.reset(proof.as_bytes()).unwrap();
+    let commands = parser.parse_proof().unwrap();
+    let [p, q, not_p] = parse_terms(&mut p, definitions, ["p", "q", "(not p)"]);
+
+    let rewrites: Vec<_> = commands
+        .iter()
+        .map(|c| match c {
+            ProofCommand::Step(s) => s.as_rare_rewrite().unwrap(),
+            _ => panic!(),
+        })
+        .collect();
+    assert_eq!(rewrites[0].rule, "bool-double-not-elim");
+    assert_eq!(rewrites[0].args, [ProofArg::Term(p.clone())]);
+    assert_eq!(rewrites[1].rule, "and-comm");
+    assert_eq!(rewrites[1].args, [ProofArg::List(vec![p, q])]);
+    assert_eq!(rewrites[2].rule, "bool-and-true");
+    assert_eq!(
+        rewrites[2].args,
+        [
+            ProofArg::List(Vec::new()),
+            ProofArg::Term(not_p),
+            ProofArg::List(Vec::new()),
+        ]
+    );
+}
+
+#[test]
+fn test_repeated_step_ids() {
+    fn parse_proof_err(input: &str) -> Error {
+        let mut pool = PrimitivePool::new();
+        Parser::new(&mut pool, TEST_CONFIG, input.as_bytes())
+            .and_then(|mut p| p.parse_proof())
+            .expect_err("expected error")
+    }
+
+    let err = parse_proof_err(
+        "(step t1 (cl) :rule rule-name)
+        (step t1 (cl) :rule rule-name)",
+    );
+    assert!(matches!(err, Error::Parser(ParserError::RepeatedStepIndex(id), _) if id == "t1"));
+
+    // Step ids must be unique even across different subproofs
+    let err = parse_proof_err(
+        "(anchor :step t1)
+        (step t1.t1 (cl) :rule rule-name)
+        (step t1 (cl) :rule rule-name)
+        (anchor :step t2)
+        (step t1.t1 (cl) :rule rule-name)
+        (step t2 (cl) :rule rule-name)",
+    );
+    assert!(matches!(err, Error::Parser(ParserError::RepeatedStepIndex(id), _) if id == "t1.t1"));
+}
+
 #[test]
 fn test_bitvectors() {
     let mut p = PrimitivePool::new();
@@ -725,4 +1738,413 @@ fn test_indexed_operators() {
         assert_eq!(proof.commands.len(), 1);
         assert_eq!(&proof.commands[0], &expected_value);
     }
+
+    let cases = [
+        ("((_ repeat 3) #b10)", 6u32),
+        ("((_ rotate_left 1) #x1F)", 8),
+        ("((_ rotate_right 9) #b101)", 3),
+        ("(concat ((_ extract 3 2) #x0F) #b1)", 3),
+    ];
+    for (input, width) in cases {
+        let term = parse_term(&mut p, input);
+        assert_eq!(
+            &Sort::BitVec(width.into()),
+            p.sort(&term).as_sort().unwrap()
+        );
+    }
+    assert!(matches!(
+        parse_term_err("((_ repeat 0) #b1)"),
+        Error::Parser(ParserError::WrongValueOfArgs(..), _)
+    ));
+
+    let [divisible, int_to_bv, bv_to_nat] = parse_terms(
+        &mut p,
+        "(declare-fun x () Int)",
+        [
+            "((_ divisible 3) x)",
+            "((_ int2bv 8) x)",
+            "(bv2nat ((_ int2bv 4) 5))",
+        ],
+    );
+    assert_eq!(&Sort::Bool, p.sort(&divisible).as_sort().unwrap());
+    assert_eq!(
+        &Sort::BitVec(8u32.into()),
+        p.sort(&int_to_bv).as_sort().unwrap()
+    );
+    assert_eq!(&Sort::Int, p.sort(&bv_to_nat).as_sort().unwrap());
+
+    // Indexed operators must be printed back with their indices
+    assert_eq!("((_ divisible 3) x)", divisible.to_string());
+    assert_eq!("((_ int2bv 8) x)", int_to_bv.to_string());
+
+    assert!(matches!(
+        parse_term_err("((_ divisible 0) 4)"),
+        Error::Parser(ParserError::WrongValueOfArgs(..), _)
+    ));
+    assert!(matches!(
+        parse_term_err("((_ unknown 1 2) 4)"),
+        Error::Parser(ParserError::UndefinedIden(_), _)
+    ));
+}
+
+#[test]
+fn test_qualified_identifiers() {
+    let mut p = PrimitivePool::new();
+    let [const_array, select, real, var] = parse_terms(
+        &mut p,
+        "(declare-fun x () Int)",
+        [
+            "((as const (Array Int Bool)) true)",
+            "(select ((as const (Array Int Int)) 0) x)",
+            "(as 1 Real)",
+            "(as x Int)",
+        ],
+    );
+    let int_sort = p.add(Term::Sort(Sort::Int));
+    let bool_sort = p.add(Term::Sort(Sort::Bool));
+    let array_sort = p.add(Term::Sort(Sort::Array(int_sort.clone(), bool_sort)));
+    assert_eq!(
+        Term::ParamOp {
+            op: ParamOperator::ArrayConst,
+            op_args: vec![array_sort.clone()],
+            args: vec![p.bool_true()],
+        },
+        *const_array
+    );
+    assert_eq!(array_sort, p.sort(&const_array));
+    assert_eq!(&Sort::Int, p.sort(&select).as_sort().unwrap());
+    assert_eq!(p.add(Term::new_real(1)), real);
+    assert_eq!(p.add(Term::new_var("x", int_sort)), var);
+
+    // Qualified identifiers must be printed back with their sorts
+    assert_eq!(
+        "((as const (Array Int Bool)) true)",
+        const_array.to_string()
+    );
+
+    assert!(matches!(
+        parse_term_err("((as const (Array Int Int)) true)"),
+        Error::Parser(ParserError::SortError(_), _)
+    ));
+    assert!(matches!(
+        parse_term_err("((as const Int) 0)"),
+        Error::Parser(ParserError::ExpectedArraySort(_), _)
+    ));
+    assert!(matches!(
+        parse_term_err("(as const (Array Int Int))"),
+        Error::Parser(ParserError::WrongNumberOfArgs(..), _)
+    ));
+    assert!(matches!(
+        parse_term_err("(as 1.5 Int)"),
+        Error::Parser(ParserError::SortError(_), _)
+    ));
+}
+
+#[test]
+fn test_strings() {
+    let mut p = PrimitivePool::new();
+    let [a, b, loop_term, power_term] = parse_terms(
+        &mut p,
+        "(declare-fun x () String) (declare-fun r () RegLan)",
+        [
+            "(= (str.len (str.++ x \"ab\")) (str.indexof x (str.substr x 0 1) 0))",
+            "(str.in_re (str.replace_all x \"a\" \"b\") (re.union (re.* r) re.allchar))",
+            "((_ re.loop 1 3) (str.to_re \"a\"))",
+            "((_ re.^ 2) r)",
+        ],
+    );
+    assert_eq!(&Sort::Bool, p.sort(&a).as_sort().unwrap());
+    assert_eq!(&Sort::Bool, p.sort(&b).as_sort().unwrap());
+    assert_eq!(&Sort::RegLan, p.sort(&loop_term).as_sort().unwrap());
+    assert_eq!(&Sort::RegLan, p.sort(&power_term).as_sort().unwrap());
+
+    let cases = [
+        ("\"\"", ""),
+        ("\"say \"\"hi\"\"\"", "say \"hi\""),
+        ("(_ char #x41)", "A"),
+        ("(_ char #x0a)", "\\u{a}"),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(Term::new_string(expected), *parse_term(&mut p, input));
+    }
+
+    assert!(matches!(
+        parse_term_err("(_ char #x30000)"),
+        Error::Parser(ParserError::InvalidCodePoint(_), _)
+    ));
+    assert!(matches!(
+        parse_term_err("(str.len 0)"),
+        Error::Parser(ParserError::SortError(_), _)
+    ));
+}
+
+#[test]
+fn test_floating_point() {
+    let mut p = PrimitivePool::new();
+    let float32 = Sort::FloatingPoint(8.into(), 24.into());
+    let [add, lit, inf, conv, cmp, to_bv] = parse_terms(
+        &mut p,
+        "(declare-fun x () Float32)
+        (declare-fun y () (_ FloatingPoint 8 24))
+        (declare-fun r () RoundingMode)",
+        [
+            "(fp.add RNE x (fp.neg y))",
+            "(fp #b0 #b01111111 #b00000000000000000000000)",
+            "(_ +oo 8 24)",
+            "((_ to_fp 11 53) roundTowardZero x)",
+            "(fp.leq (fp.fma r x y x) ((_ to_fp 8 24) #x3f800000) (_ NaN 8 24))",
+            "((_ fp.to_sbv 32) RTZ (fp.roundToIntegral r y))",
+        ],
+    );
+    assert_eq!(&float32, p.sort(&add).as_sort().unwrap());
+    assert_eq!(&float32, p.sort(&lit).as_sort().unwrap());
+    assert_eq!(&float32, p.sort(&inf).as_sort().unwrap());
+    assert_eq!(
+        &Sort::FloatingPoint(11.into(), 53.into()),
+        p.sort(&conv).as_sort().unwrap()
+    );
+    assert_eq!(&Sort::Bool, p.sort(&cmp).as_sort().unwrap());
+    assert_eq!(&Sort::BitVec(32.into()), p.sort(&to_bv).as_sort().unwrap());
+
+    // Rounding modes are always printed using their abbreviated names
+    assert_eq!("((_ to_fp 11 53) RTZ x)", conv.to_string());
+    assert_eq!("(_ +oo 8 24)", inf.to_string());
+
+    assert!(matches!(
+        parse_term_err("(fp.abs 1.0)"),
+        Error::Parser(ParserError::ExpectedFpSort(_), _)
+    ));
+    assert!(matches!(
+        parse_term_err("(fp.add (_ NaN 8 24) (_ NaN 8 24) (_ NaN 8 24))"),
+        Error::Parser(ParserError::SortError(_), _)
+    ));
+    assert!(matches!(
+        parse_term_err("(fp.min (_ NaN 8 24) (_ NaN 11 53))"),
+        Error::Parser(ParserError::SortError(_), _)
+    ));
+    assert!(matches!(
+        parse_term_err("((_ to_fp 8 24) #x3f80)"),
+        Error::Parser(ParserError::SortError(_), _)
+    ));
+    assert!(matches!(
+        parse_term_err("(_ -zero 1 24)"),
+        Error::Parser(ParserError::WrongValueOfArgs(_, 1), _)
+    ));
+    assert!(matches!(
+        parse_term_err("(fp #b00 #b01111111 #b0)"),
+        Error::Parser(ParserError::SortError(_), _)
+    ));
+}
+
+#[test]
+fn test_malformed_inputs() {
+    // These inputs used to make the parser panic
+    let terms = [
+        "(_ bvx 4)",
+        "((_ extract 99999999999999999999999 0) #b1)",
+        "((_ zero_extend 99999999999999999999999) #b1)",
+        "((_ unknown 1) 4)",
+    ];
+    for input in terms {
+        assert!(
+            matches!(parse_term_err(input), Error::Parser(..)),
+            "{}",
+            input
+        );
+    }
+
+    let problems = [
+        "(declare-fun x () (_ Foo 3))",
+        "(declare-fun x () (_ BitVec 0))",
+    ];
+    for input in problems {
+        let mut pool = PrimitivePool::new();
+        let result = Parser::new(&mut pool, TEST_CONFIG, input.as_bytes())
+            .and_then(|mut parser| parser.parse_problem());
+        assert!(matches!(result, Err(Error::Parser(..))), "{}", input);
+    }
+}
+
+#[test]
+fn test_infer_declarations() {
+    let proof = "(assume h1 (< (+ x 1) (f x y)))
+        (step t2 (cl (= (g x) b)) :rule hole)
+        (anchor :step t3 :args ((z Real)))
+        (step t3.t1 (cl (= z w)) :rule hole)
+        (step t3 (cl) :rule hole)
+        (step t4 (cl (= w 1.0)) :rule hole)";
+    let mut pool = PrimitivePool::new();
+    let mut parser = Parser::new(&mut pool, TEST_CONFIG, proof.as_bytes()).unwrap();
+    parser.infer_declarations(Sort::Bool);
+    parser.parse_proof().unwrap();
+    let declarations: Vec<_> = parser
+        .inferred_declarations()
+        .iter()
+        .map(|(name, sort)| format!("{} {}", name, sort))
+        .collect();
+    assert_eq!(
+        declarations,
+        [
+            "x Int",
+            "y Bool",
+            "f (-> Int Bool Int)",
+            "g (-> Int Bool)",
+            "b Bool",
+            "w Real",
+        ]
+    );
+
+    // Without inference, undeclared symbols are still an error
+    let mut parser = Parser::new(&mut pool, TEST_CONFIG, proof.as_bytes()).unwrap();
+    assert!(matches!(
+        parser.parse_proof(),
+        Err(Error::Parser(ParserError::UndefinedIden(_), _)),
+    ));
+}
+
+#[test]
+fn test_parser_limits() {
+    fn run(limits: ParserLimits, input: &str) -> CarcaraResult<Rc<Term>> {
+        let mut pool = PrimitivePool::new();
+        let config = Config { limits, ..TEST_CONFIG };
+        Parser::new(&mut pool, config, input.as_bytes()).and_then(|mut p| p.parse_term())
+    }
+    fn is_exceeded(result: CarcaraResult<Rc<Term>>, kind: ParserLimitKind) -> bool {
+        matches!(
+            result,
+            Err(Error::Parser(ParserError::LimitExceeded(k, _), _)) if k == kind
+        )
+    }
+
+    // This term has depth 3, and 8 terms are parsed
+    let input = "(and (or true false) (not (and true false)))";
+    assert!(run(ParserLimits::default(), input).is_ok());
+
+    let depth = |max| ParserLimits {
+        max_term_depth: Some(max),
+        ..Default::default()
+    };
+    assert!(run(depth(3), input).is_ok());
+    assert!(is_exceeded(
+        run(depth(2), input),
+        ParserLimitKind::TermDepth
+    ));
+
+    let count = |max| ParserLimits {
+        max_term_count: Some(max),
+        ..Default::default()
+    };
+    assert!(run(count(8), input).is_ok());
+    assert!(is_exceeded(
+        run(count(7), input),
+        ParserLimitKind::TermCount
+    ));
+
+    let size = |max| ParserLimits {
+        max_file_size: Some(max),
+        ..Default::default()
+    };
+    assert!(run(size(input.len()), input).is_ok());
+    assert!(is_exceeded(
+        run(size(input.len() - 1), input),
+        ParserLimitKind::FileSize
+    ));
+
+    // The size limit also applies to inputs with many lines
+    let input = "(and\n  true\n  false)\n";
+    assert!(run(size(input.len()), input).is_ok());
+    assert!(is_exceeded(run(size(10), input), ParserLimitKind::FileSize));
+}
+
+#[test]
+fn test_progress_callback() {
+    use std::{
+        ops::ControlFlow,
+        sync::{Arc, Mutex},
+    };
+
+    let proof = "(assume h1 true)
+        (anchor :step t2)
+        (step t2.t1 (cl true) :rule hole)
+        (step t2 (cl true) :rule hole)
+        (step t3 (cl) :rule hole)";
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let mut pool = PrimitivePool::new();
+    let mut parser = Parser::new(&mut pool, TEST_CONFIG, proof.as_bytes()).unwrap();
+    let sink = reports.clone();
+    parser.on_progress(move |progress| {
+        sink.lock().unwrap().push(progress);
+        ControlFlow::Continue(())
+    });
+    parser.parse_proof().unwrap();
+
+    let reports = reports.lock().unwrap();
+    let counts: Vec<_> = reports.iter().map(|p| p.commands_parsed).collect();
+    assert_eq!(counts, [1, 2, 3, 4]);
+    assert!(reports
+        .windows(2)
+        .all(|w| w[0].bytes_consumed < w[1].bytes_consumed));
+    assert_eq!(reports[3].bytes_consumed, proof.len());
+}
+
+#[test]
+fn test_flatten_assoc_ops() {
+    let mut pool = PrimitivePool::new();
+    let config = Config {
+        flatten_assoc_ops: true,
+        ..TEST_CONFIG
+    };
+    let input = "(declare-fun p () Bool) (declare-fun x () Int)";
+    let mut parser = Parser::new(&mut pool, config, input.as_bytes()).unwrap();
+    parser.parse_problem().unwrap();
+
+    let mut parse = |input: &'static str| {
+        parser.reset(input.as_bytes()).unwrap();
+        format!("{:#}", parser.parse_term().unwrap())
+    };
+    assert_eq!(parse("(and p (and p (and true p)))"), "(and p p true p)");
+    assert_eq!(
+        parse("(or (or p p) (and p (and p p)))"),
+        "(or p p (and p p p))"
+    );
+    assert_eq!(parse("(+ (+ x 1) (* x (* 2 x)))"), "(+ x 1 (* x 2 x))");
+    assert_eq!(parse("(- (- x 1) 2)"), "(- (- x 1) 2)");
+
+    // The flattened terms are recorded with the terms as they were written
+    let flattened = parser.take_flattened();
+    let original = flattened
+        .iter()
+        .find(|(t, _)| format!("{:#}", t) == "(and p p true p)")
+        .map(|(_, original)| format!("{:#}", original));
+    assert_eq!(original.as_deref(), Some("(and p (and p true p))"));
+}
+
+#[test]
+fn test_extract_proof_from_log() {
+    let log = "solver 1.0\n  unsat  \n(assume h1 true)\n(step t1 (cl) :rule hole)\nend\nstats\n";
+
+    let proof = extract_proof_from_log(log.as_bytes(), "unsat", Some("end")).unwrap();
+    assert_eq!(proof, b"(assume h1 true)\n(step t1 (cl) :rule hole)\n");
+
+    let proof = extract_proof_from_log(log.as_bytes(), "unsat", None).unwrap();
+    assert_eq!(
+        proof,
+        b"(assume h1 true)\n(step t1 (cl) :rule hole)\nend\nstats\n"
+    );
+
+    let proof = extract_proof_from_log(log.as_bytes(), "end", None).unwrap();
+    assert_eq!(proof, b"stats\n");
+
+    assert!(extract_proof_from_log(log.as_bytes(), "sat", None).is_err());
+    assert!(extract_proof_from_log(log.as_bytes(), "unsat", Some("done")).is_err());
+}
+
+#[test]
+fn test_split_combined_input() {
+    let input = "(assert false)\n(check-sat)\n  unsat\n(assume h1 false)\nunsat\n";
+    let (problem, rest) = split_combined_input(input.as_bytes(), "unsat").unwrap();
+    assert_eq!(problem, b"(assert false)\n(check-sat)\n");
+    assert_eq!(rest, b"(assume h1 false)\nunsat\n");
+
+    assert!(split_combined_input(input.as_bytes(), "sat").is_err());
 }
@@ -1,11 +1,6 @@
-use crate::ast::{BindingList, Quantifier, Rc, Term};
+use crate::ast::{BindingList, PrettyPrinter, Quantifier, Rc, Term};
 use indexmap::{IndexMap, IndexSet};
-use std::{
-    borrow::Borrow,
-    fmt,
-    hash::{Hash, Hasher},
-    ops,
-};
+use std::{borrow::Borrow, fmt, hash::Hash, ops};
 
 /// Returns `true` if the character is a valid symbol character in the SMT-LIB and Alethe formats.
 pub fn is_symbol_character(ch: char) -> bool {
@@ -63,44 +58,7 @@ impl<T, I: Iterator<Item = T>> DedupIterator<T> for I {
     }
 }
 
-pub struct HashCache<T> {
-    hash: u64,
-    value: T,
-}
-
-impl<T: PartialEq> PartialEq for HashCache<T> {
-    fn eq(&self, other: &Self) -> bool {
-        self.value == other.value
-    }
-}
-
-impl<T: Eq> Eq for HashCache<T> {}
-
-impl<T: Hash> Hash for HashCache<T> {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        state.write_u64(self.hash);
-    }
-}
-
-impl<T: Eq + Hash> HashCache<T> {
-    pub fn new(value: T) -> Self {
-        let mut hasher = std::collections::hash_map::DefaultHasher::default();
-        value.hash(&mut hasher);
-        Self { hash: hasher.finish(), value }
-    }
-
-    pub fn unwrap(self) -> T {
-        self.value
-    }
-}
-
-impl<T> AsRef<T> for HashCache<T> {
-    fn as_ref(&self) -> &T {
-        &self.value
-    }
-}
-
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct HashMapStack<K, V> {
     scopes: Vec<IndexMap<K, V>>,
 }
@@ -110,17 +68,20 @@ impl<K, V> HashMapStack<K, V> {
         Self { scopes: vec![IndexMap::new()] }
     }
 
+    pub fn num_scopes(&self) -> usize {
+        self.scopes.len()
+    }
+
     pub fn push_scope(&mut self) {
         self.scopes.push(IndexMap::new());
     }
 
-    pub fn pop_scope(&mut self) {
+    /// Removes the innermost scope, returning its entries.
+    pub fn pop_scope(&mut self) -> IndexMap<K, V> {
         match self.scopes.len() {
             0 => unreachable!(),
             1 => panic!("trying to pop last scope in `HashMapStack`"),
-            _ => {
-                self.scopes.pop().unwrap();
-            }
+            _ => self.scopes.pop().unwrap(),
         }
     }
 }
@@ -135,9 +96,9 @@ impl<K: Eq + Hash, V> HashMapStack<K, V> {
         // bottleneck. As currently implemented, this function needs to hash the key once for every
         // scope. The ideal way of solving this would be to hash the key once, and reuse that hash
         // to access the entry in each scope. To do that, we could use the `HashMap::raw_entry`
-        // method, but it is currently nightly-only. Another way to mitigate this issue is to use
-        // the `HashCache<T>` struct to wrap the key values in the symbol table. This allows the key
-        // to only be hashed once, and that value is stored and reused in the struct.
+        // method, but it is currently nightly-only. To mitigate this issue, the parser symbol table
+        // uses interned `Symbol`s as keys, which are hashed by reference, so hashing them once for
+        // every scope is cheap.
         self.scopes.iter().rev().find_map(|scope| scope.get(key))
     }
 
@@ -156,6 +117,12 @@ impl<K: Eq + Hash, V> HashMapStack<K, V> {
     pub fn insert(&mut self, key: K, value: V) {
         self.scopes.last_mut().unwrap().insert(key, value);
     }
+
+    /// Inserts a value in the outermost scope, so it is not removed when the current scopes are
+    /// popped.
+    pub fn insert_global(&mut self, key: K, value: V) {
+        self.scopes[0].insert(key, value);
+    }
 }
 
 impl<K, V> Default for HashMapStack<K, V> {
@@ -217,13 +184,22 @@ impl From<ops::RangeTo<usize>> for Range {
 }
 
 /// Provides a pretty displayable name for a type. For example, the type name for `Rc<Term>` is
-/// "term".
-pub trait TypeName {
+/// "term". This also defines how values of the type are written in error messages.
+pub trait TypeName: fmt::Display {
     const NAME: &'static str;
+
+    /// Writes the value in an error message. By default, this uses its `Display` implementation.
+    fn fmt_in_error(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
 }
 
 impl TypeName for Rc<Term> {
     const NAME: &'static str = "term";
+
+    fn fmt_in_error(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", PrettyPrinter::new().term_to_string(self))
+    }
 }
 
 impl TypeName for Quantifier {
@@ -30,40 +30,69 @@ fn run_parallel_checker_test(
     Ok(())
 }
 
-fn run_test(problem_path: &Path, proof_path: &Path) -> CarcaraResult<()> {
+#[cfg(feature = "elaborator")]
+fn run_elaborator_test(
+    pool: &mut ast::PrimitivePool,
+    prelude: &ast::ProblemPrelude,
+    proof: ast::Proof,
+) -> CarcaraResult<()> {
     use checker::Config;
 
-    let (prelude, proof, mut pool) = parser::parse_instance(
-        io::BufReader::new(fs::File::open(problem_path)?),
-        io::BufReader::new(fs::File::open(proof_path)?),
-        parser::Config::new(),
-    )?;
-
-    // First, we check the proof normally
-    checker::ProofChecker::new(&mut pool, Config::new(), &prelude).check(&proof)?;
-
-    // Then, we check it while elaborating the proof
-    let mut checker = checker::ProofChecker::new(&mut pool, Config::new(), &prelude);
+    // We check the proof while elaborating it
+    let mut checker = checker::ProofChecker::new(pool, Config::new(), prelude);
     let (_, elaborated) = checker.check_and_elaborate(proof)?;
 
     // After that, we check the elaborated proof normally, to make sure it is valid
-    checker::ProofChecker::new(&mut pool, Config::new().strict(true), &prelude)
-        .check(&elaborated)?;
+    checker::ProofChecker::new(pool, Config::new().strict(true), prelude).check(&elaborated)?;
 
-    // Finally, we elaborate the already elaborated proof, to make sure the elaboration step is
+    // Then, we elaborate the already elaborated proof, to make sure the elaboration step is
     // idempotent
-    let mut checker = checker::ProofChecker::new(&mut pool, Config::new().strict(true), &prelude);
+    let mut checker = checker::ProofChecker::new(pool, Config::new().strict(true), prelude);
     let (_, elaborated_twice) = checker.check_and_elaborate(elaborated.clone())?;
     assert!(
         elaborated.commands == elaborated_twice.commands,
         "elaboration was not idempotent!"
     );
+    Ok(())
+}
+
+fn run_test(problem_path: &Path, proof_path: &Path) -> CarcaraResult<()> {
+    use checker::Config;
+
+    let (prelude, proof, mut pool) = parser::parse_instance(
+        io::BufReader::new(fs::File::open(problem_path)?),
+        io::BufReader::new(fs::File::open(proof_path)?),
+        parser::Config::new(),
+    )?;
+
+    // First, we check the proof normally
+    checker::ProofChecker::new(&mut pool, Config::new(), &prelude).check(&proof)?;
+
+    #[cfg(feature = "elaborator")]
+    run_elaborator_test(&mut pool, &prelude, proof)?;
 
     // We also test the parallel checker, with different values for the number of threads
     run_parallel_checker_test(problem_path, proof_path, 1)?;
     run_parallel_checker_test(problem_path, proof_path, 4)?;
     run_parallel_checker_test(problem_path, proof_path, 16)?;
 
+    // And the streaming checker, that checks each command while the proof is parsed
+    check_streaming(
+        io::BufReader::new(fs::File::open(problem_path)?),
+        io::BufReader::new(fs::File::open(proof_path)?),
+        CarcaraOptions::new(),
+    )?;
+
+    // Finally, we check the proof after parsing it in parallel
+    check(
+        io::BufReader::new(fs::File::open(problem_path)?),
+        io::BufReader::new(fs::File::open(proof_path)?),
+        CarcaraOptions {
+            parsing_threads: 4,
+            ..CarcaraOptions::new()
+        },
+    )?;
+
     Ok(())
 }
 
@@ -84,6 +113,12 @@ fn test_file(proof_path: &str) {
             Error::Parser(_, (line, column)) => format!("parser error at {}:{}", line, column),
             Error::Checker { rule, step, .. } => format!("checker error at '{}' ({})", step, rule),
             Error::DoesNotReachEmptyClause => format!("{}", e), // This one is already pretty short
+            Error::NoValidProof(_)
+            | Error::InPart { .. }
+            | Error::UnknownStep(_)
+            | Error::Interrupted => format!("{}", e),
+            #[cfg(feature = "drat")]
+            Error::Drat(_) => format!("{}", e),
         };
         panic!(
             "\"{}\" returned error: {}",
@@ -1,11 +1,12 @@
+use crate::compression::open_file;
 use carcara::{
-    benchmarking::{CollectResults, CsvBenchmarkResults, RunMeasurement},
-    checker, parser, CarcaraOptions,
+    benchmarking::{run_sampled, CollectResults, CsvBenchmarkResults, RunMeasurement, StepProbe},
+    checker::{self, RuleCoverage},
+    parser, CarcaraOptions,
 };
 use crossbeam_queue::ArrayQueue;
 use std::{
-    fs::File,
-    io::{self, BufReader},
+    fmt, io,
     path::{Path, PathBuf},
     thread,
     time::{Duration, Instant},
@@ -18,13 +19,183 @@ struct JobDescriptor<'a> {
     run_index: usize,
 }
 
+/// A run that was invalid or errored, in a benchmark summary.
+#[derive(Debug, Clone)]
+pub struct FailedRun {
+    pub proof_file: String,
+    pub run_index: usize,
+
+    /// If the run failed on an invalid step, the id and rule of that step.
+    pub step: Option<(String, String)>,
+
+    /// The annotations of the step the run failed on, like the id the step has inside the solver
+    /// that produced it. See [`carcara::ast::Proof::annotations`].
+    pub annotations: Option<String>,
+
+    pub message: String,
+}
+
+/// The number of runs with each outcome in a benchmark, and the total time it took. If each proof
+/// is run more than once, each run is counted separately.
+#[derive(Debug, Default, Clone)]
+pub struct BenchmarkSummary {
+    pub valid: usize,
+    pub holey: usize,
+    pub invalid: usize,
+
+    /// The number of runs that failed before the proof could be checked, for example, because of
+    /// a parser error.
+    pub errored: usize,
+
+    pub total_time: Duration,
+
+    /// The invalid and errored runs.
+    pub failures: Vec<FailedRun>,
+}
+
+impl BenchmarkSummary {
+    fn register(&mut self, job: JobDescriptor, result: &Result<bool, carcara::Error>) {
+        let error = match result {
+            Ok(false) => {
+                self.valid += 1;
+                return;
+            }
+            Ok(true) => {
+                self.holey += 1;
+                return;
+            }
+            Err(e) => e,
+        };
+        let (step, annotations) = match error {
+            carcara::Error::Checker { rule, step, annotations, .. } => {
+                (Some((step.clone(), rule.clone())), annotations.clone())
+            }
+            _ => (None, None),
+        };
+        match error {
+            carcara::Error::Checker { .. } | carcara::Error::DoesNotReachEmptyClause => {
+                self.invalid += 1;
+            }
+            _ => self.errored += 1,
+        }
+        self.failures.push(FailedRun {
+            proof_file: job.proof_file.display().to_string(),
+            run_index: job.run_index,
+            step,
+            annotations,
+            message: error.to_string(),
+        });
+    }
+
+    fn combine(self, other: Self) -> Self {
+        Self {
+            valid: self.valid + other.valid,
+            holey: self.holey + other.holey,
+            invalid: self.invalid + other.invalid,
+            errored: self.errored + other.errored,
+            total_time: self.total_time.max(other.total_time),
+            failures: [self.failures, other.failures].concat(),
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.valid + self.holey + self.invalid + self.errored
+    }
+
+    /// Writes the summary as a JSON object. The total time is given in seconds.
+    pub fn write_json(&self, dest: &mut dyn io::Write) -> io::Result<()> {
+        write!(
+            dest,
+            "{{\"valid\": {}, \"holey\": {}, \"invalid\": {}, \"errored\": {}, \"total\": {}, \
+            \"total_time\": {:.6}, \"failures\": [",
+            self.valid,
+            self.holey,
+            self.invalid,
+            self.errored,
+            self.total(),
+            self.total_time.as_secs_f64(),
+        )?;
+        for (i, failure) in self.failures.iter().enumerate() {
+            if i > 0 {
+                write!(dest, ", ")?;
+            }
+            let (step, rule) = match &failure.step {
+                Some((step, rule)) => (json_string(step), json_string(rule)),
+                None => ("null".to_owned(), "null".to_owned()),
+            };
+            write!(
+                dest,
+                "{{\"proof_file\": {}, \"run\": {}, \"step\": {}, \"rule\": {}, \
+                \"annotations\": {}, \"message\": {}}}",
+                json_string(&failure.proof_file),
+                failure.run_index,
+                step,
+                rule,
+                failure
+                    .annotations
+                    .as_deref()
+                    .map_or("null".to_owned(), json_string),
+                json_string(&failure.message),
+            )?;
+        }
+        writeln!(dest, "]}}")
+    }
+}
+
+/// Writes a string as a JSON string literal, escaping it as needed.
+fn json_string(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            c if c.is_control() => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+impl fmt::Display for BenchmarkSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} runs: {} succeeded ({} valid, {} holey), {} failed ({} invalid, {} errored)",
+            self.total(),
+            self.valid + self.holey,
+            self.valid,
+            self.holey,
+            self.invalid + self.errored,
+            self.invalid,
+            self.errored,
+        )
+    }
+}
+
 fn run_job<T: CollectResults + Default + Send>(
     results: &mut T,
     job: JobDescriptor,
     options: &CarcaraOptions,
     elaborate: bool,
+    sample_interval: Option<Duration>,
 ) -> Result<bool, carcara::Error> {
     let proof_file_name = job.proof_file.to_str().unwrap();
+    let total = Instant::now();
+
+    let parsing = Instant::now();
+    let config = options.parser_config();
+    let (prelude, proof, mut pool) = parser::parse_instance(
+        open_file(job.problem_file)?,
+        open_file(job.proof_file)?,
+        config,
+    )?;
+    let parsing = parsing.elapsed();
+
+    // The results are only taken after parsing, so they are not lost if parsing fails
+    let probe = StepProbe::default();
     let mut checker_stats = checker::CheckerStatistics {
         file_name: proof_file_name,
         elaboration_time: Duration::ZERO,
@@ -32,40 +203,39 @@ fn run_job<T: CollectResults + Default + Send>(
         assume_time: Duration::ZERO,
         assume_core_time: Duration::ZERO,
         results: std::mem::take(results),
+        probe: sample_interval.map(|_| &probe),
     };
 
-    let total = Instant::now();
-
-    let parsing = Instant::now();
-    let config = parser::Config {
-        apply_function_defs: options.apply_function_defs,
-        expand_lets: options.expand_lets,
-        allow_int_real_subtyping: options.allow_int_real_subtyping,
-    };
-    let (prelude, proof, mut pool) = parser::parse_instance(
-        BufReader::new(File::open(job.problem_file)?),
-        BufReader::new(File::open(job.proof_file)?),
-        config,
-    )?;
-    let parsing = parsing.elapsed();
-
-    let config = checker::Config::new()
-        .strict(options.strict)
-        .ignore_unknown_rules(options.ignore_unknown_rules)
-        .lia_options(options.lia_options.clone());
+    let config = options.checker_config();
     let mut checker = checker::ProofChecker::new(&mut pool, config, &prelude);
 
+    // The samples refer to the commands of the original proof, which is consumed when elaborating
+    let sampled_proof = sample_interval.map(|_| proof.clone());
+
     let checking = Instant::now();
 
-    let checking_result = if elaborate {
-        checker
-            .check_and_elaborate_with_stats(proof, &mut checker_stats)
-            .map(|(is_holey, _)| is_holey)
-    } else {
-        checker.check_with_stats(&proof, &mut checker_stats)
+    let check = || {
+        if elaborate {
+            checker
+                .check_and_elaborate_with_stats(proof, &mut checker_stats)
+                .map(|(is_holey, _)| is_holey)
+        } else {
+            checker.check_with_stats(&proof, &mut checker_stats)
+        }
+    };
+    let (checking_result, samples) = match sample_interval {
+        Some(interval) => {
+            let (result, samples) = run_sampled(&probe, interval, check);
+            (result, Some(samples))
+        }
+        None => (check(), None),
     };
     let checking = checking.elapsed();
 
+    if let (Some(samples), Some(proof)) = (samples, &sampled_proof) {
+        samples.report(proof, proof_file_name, &mut checker_stats.results);
+    }
+
     let total = total.elapsed();
 
     checker_stats.results.add_run_measurement(
@@ -89,21 +259,30 @@ fn worker_thread<T: CollectResults + Default + Send>(
     jobs_queue: &ArrayQueue<JobDescriptor>,
     options: &CarcaraOptions,
     elaborate: bool,
-) -> T {
+    sample_interval: Option<Duration>,
+) -> (T, BenchmarkSummary) {
     let mut results = T::default();
+    let mut summary = BenchmarkSummary::default();
 
     while let Some(job) = jobs_queue.pop() {
-        match run_job(&mut results, job, options, elaborate) {
+        let result = run_job(&mut results, job, options, elaborate, sample_interval);
+        summary.register(job, &result);
+        match result {
             Ok(true) => results.register_holey(),
             Err(e) => {
-                log::error!("encountered error in file '{}'", job.proof_file.display());
-                results.register_error(&e);
+                log::error!(
+                    "encountered error in file '{}': {}",
+                    job.proof_file.display(),
+                    e
+                );
+                let id = (job.proof_file.to_str().unwrap().to_owned(), job.run_index);
+                results.register_error(&id, &e);
             }
             _ => (),
         }
     }
 
-    results
+    (results, summary)
 }
 
 pub fn run_benchmark<T: CollectResults + Default + Send>(
@@ -112,7 +291,8 @@ pub fn run_benchmark<T: CollectResults + Default + Send>(
     num_jobs: usize,
     options: &CarcaraOptions,
     elaborate: bool,
-) -> T {
+    sample_interval: Option<Duration>,
+) -> (T, BenchmarkSummary) {
     const STACK_SIZE: usize = 128 * 1024 * 1024;
 
     let jobs_queue = ArrayQueue::new(instances.len() * num_runs);
@@ -127,7 +307,8 @@ pub fn run_benchmark<T: CollectResults + Default + Send>(
         }
     }
 
-    thread::scope(|s| {
+    let start = Instant::now();
+    let (results, summary) = thread::scope(|s| {
         let jobs_queue = &jobs_queue; // So we don't try to move the queue into the thread closure
 
         // We of course need to `collect` here to ensure we spawn all threads before starting to
@@ -137,7 +318,9 @@ pub fn run_benchmark<T: CollectResults + Default + Send>(
             .map(|_| {
                 thread::Builder::new()
                     .stack_size(STACK_SIZE)
-                    .spawn_scoped(s, move || worker_thread(jobs_queue, options, elaborate))
+                    .spawn_scoped(s, move || {
+                        worker_thread(jobs_queue, options, elaborate, sample_interval)
+                    })
                     .unwrap()
             })
             .collect();
@@ -145,26 +328,41 @@ pub fn run_benchmark<T: CollectResults + Default + Send>(
         workers
             .into_iter()
             .map(|w| w.join().unwrap())
-            .reduce(T::combine)
+            .reduce(|(a, a_summary), (b, b_summary)| {
+                (T::combine(a, b), a_summary.combine(b_summary))
+            })
             .unwrap()
-    })
+    });
+    let summary = BenchmarkSummary {
+        total_time: start.elapsed(),
+        ..summary
+    };
+    (results, summary)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn run_csv_benchmark(
     instances: &[(PathBuf, PathBuf)],
     num_runs: usize,
     num_jobs: usize,
     options: &CarcaraOptions,
     elaborate: bool,
+    sample_interval: Option<Duration>,
     runs_dest: &mut dyn io::Write,
     by_rule_dest: &mut dyn io::Write,
-) -> io::Result<()> {
-    let result: CsvBenchmarkResults =
-        run_benchmark(instances, num_runs, num_jobs, options, elaborate);
-    println!(
-        "{} errors encountered during benchmark",
-        result.num_errors()
+) -> io::Result<BenchmarkSummary> {
+    let (result, summary): (CsvBenchmarkResults, _) = run_benchmark(
+        instances,
+        num_runs,
+        num_jobs,
+        options,
+        elaborate,
+        sample_interval,
     );
+    println!("{}", summary);
+    for (file, run_index, error) in result.failures() {
+        println!("failed: {} (run {}): {}", file, run_index, error);
+    }
     if result.num_errors() > 0 {
         println!("invalid");
     } else if result.is_holey() {
@@ -172,5 +370,42 @@ pub fn run_csv_benchmark(
     } else {
         println!("valid");
     }
-    result.write_csv(runs_dest, by_rule_dest)
+    result.write_csv(runs_dest, by_rule_dest)?;
+    Ok(summary)
+}
+
+fn coverage_job(
+    problem_file: &Path,
+    proof_file: &Path,
+    options: &CarcaraOptions,
+) -> Result<RuleCoverage, carcara::Error> {
+    let config = options.parser_config();
+    let (prelude, proof, mut pool) =
+        parser::parse_instance(open_file(problem_file)?, open_file(proof_file)?, config)?;
+    checker::ProofChecker::new(&mut pool, options.checker_config(), &prelude).check_coverage(&proof)
+}
+
+/// Checks every step of each proof, without stopping at invalid steps, and reports how many steps
+/// of each rule were checked, skipped or failed. Also returns the number of proofs that couldn't be
+/// checked, for example, because of a parser error.
+pub fn run_coverage(
+    instances: &[(PathBuf, PathBuf)],
+    options: &CarcaraOptions,
+) -> (RuleCoverage, usize) {
+    let mut coverage = RuleCoverage::new();
+    let mut errored = 0;
+    for (problem_file, proof_file) in instances {
+        match coverage_job(problem_file, proof_file, options) {
+            Ok(c) => coverage.combine(&c),
+            Err(e) => {
+                log::error!(
+                    "encountered error in file '{}': {}",
+                    proof_file.display(),
+                    e
+                );
+                errored += 1;
+            }
+        }
+    }
+    (coverage, errored)
 }
@@ -0,0 +1,121 @@
+use std::{
+    ffi::OsStr,
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::Path,
+    process::{Child, ChildStdout, Command, Stdio},
+};
+
+/// A compression format that is decompressed transparently when opening input files.
+struct Format {
+    extension: &'static str,
+    magic_bytes: &'static [u8],
+
+    /// The external program used to decompress files in this format. It is called with the `-dc`
+    /// flags, and should write the decompressed contents to its standard output.
+    program: &'static str,
+}
+
+const FORMATS: [Format; 2] = [
+    Format {
+        extension: "gz",
+        magic_bytes: &[0x1f, 0x8b],
+        program: "gzip",
+    },
+    Format {
+        extension: "zst",
+        magic_bytes: &[0x28, 0xb5, 0x2f, 0xfd],
+        program: "zstd",
+    },
+];
+
+/// Returns the extension of the path if it is the extension of a supported compression format.
+pub fn compression_extension(path: &Path) -> Option<&'static str> {
+    let extension = path.extension().and_then(OsStr::to_str)?;
+    FORMATS
+        .iter()
+        .find(|f| f.extension == extension)
+        .map(|f| f.extension)
+}
+
+/// Opens a file for reading. If the file is compressed in a supported format, detected by its first
+/// bytes, it is decompressed on the fly by an external program. The file extension is only used in
+/// warnings, since it may not match the actual contents of the file.
+pub fn open_file(path: impl AsRef<Path>) -> io::Result<Box<dyn BufRead + Send>> {
+    let path = path.as_ref();
+    let mut reader = BufReader::new(File::open(path)?);
+    let start = reader.fill_buf()?;
+    let format = FORMATS.iter().find(|f| start.starts_with(f.magic_bytes));
+    match (format, compression_extension(path)) {
+        (Some(format), _) => Ok(Box::new(BufReader::new(Decompressor::spawn(format, path)?))),
+        (None, Some(extension)) => {
+            log::warn!(
+                "'{}' has extension `.{}`, but is not compressed; reading it as plain text",
+                path.display(),
+                extension
+            );
+            Ok(Box::new(reader))
+        }
+        (None, None) => Ok(Box::new(reader)),
+    }
+}
+
+/// A reader for the output of an external decompression program.
+struct Decompressor {
+    program: &'static str,
+    child: Child,
+    stdout: ChildStdout,
+}
+
+impl Decompressor {
+    fn spawn(format: &Format, path: &Path) -> io::Result<Self> {
+        let mut child = Command::new(format.program)
+            .arg("-dc")
+            .arg("--")
+            .arg(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| {
+                let message = format!(
+                    "could not run `{}` to decompress '{}': {}",
+                    format.program,
+                    path.display(),
+                    e
+                );
+                io::Error::new(e.kind(), message)
+            })?;
+        let stdout = child.stdout.take().unwrap();
+        Ok(Self {
+            program: format.program,
+            child,
+            stdout,
+        })
+    }
+}
+
+impl Read for Decompressor {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.stdout.read(buf)?;
+
+        // If the decompression fails, its output may be truncated, so we must check the exit
+        // status of the program when its output ends
+        if n == 0 && !buf.is_empty() {
+            let status = self.child.wait()?;
+            if !status.success() {
+                let message = format!("`{}` failed with {}", self.program, status);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl Drop for Decompressor {
+    fn drop(&mut self) {
+        // If the input was not read until the end, the program may still be running
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -6,6 +6,11 @@ pub enum CliError {
     CantInferProblemFile(PathBuf),
     InvalidSliceId(String),
     BothFilesStdin,
+    ProofCacheStdin,
+    InvalidManifestLine(usize, String),
+    ManifestWithoutProblem,
+    ParserErrors(usize),
+    WorkerError(carcara::isolation::WorkerError),
 }
 
 pub type CliResult<T> = Result<T, CliError>;
@@ -22,6 +27,12 @@ impl From<carcara::Error> for CliError {
     }
 }
 
+impl From<carcara::isolation::WorkerError> for CliError {
+    fn from(e: carcara::isolation::WorkerError) -> Self {
+        Self::WorkerError(e)
+    }
+}
+
 impl fmt::Display for CliError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -30,7 +41,14 @@ impl fmt::Display for CliError {
                 write!(f, "can't infer problem file: {}", p.display())
             }
             CliError::BothFilesStdin => write!(f, "problem and proof files can't both be `-`"),
+            CliError::ProofCacheStdin => write!(f, "can't cache a proof that is read from stdin"),
             CliError::InvalidSliceId(id) => write!(f, "invalid id for slice: {}", id),
+            CliError::InvalidManifestLine(line, content) => {
+                write!(f, "invalid manifest line {}: '{}'", line, content)
+            }
+            CliError::ManifestWithoutProblem => write!(f, "manifest has no `problem` line"),
+            CliError::ParserErrors(n) => write!(f, "found {} errors while parsing proof", n),
+            CliError::WorkerError(e) => write!(f, "{}", e),
         }
     }
 }
@@ -1,21 +1,39 @@
+#[cfg(feature = "benchmarking")]
 mod benchmarking;
+mod compression;
 mod error;
 mod logger;
 mod path_args;
 
+#[cfg(feature = "benchmarking")]
+use carcara::benchmarking::OnlineBenchmarkResults;
 use carcara::{
-    ast::print_proof, benchmarking::OnlineBenchmarkResults, check, check_and_elaborate,
-    check_parallel, parser, CarcaraOptions, LiaGenericOptions,
+    ast::{
+        print_proof_for_version, AletheVersion, NegativeNumberStyle, NumberFormat, ProofCommand,
+        RationalStyle, Sort,
+    },
+    chc_certificate, check, check_and_elaborate, check_and_elaborate_explained,
+    check_and_elaborate_streaming, check_any, check_composed, check_drat, check_parallel,
+    check_proof_only, check_queries, check_streaming, check_with_frontier,
+    check_with_leniency_report, check_with_proof_cache, check_with_resolution_pivots,
+    elaborator::StepExplanation,
+    extract_theory_lemmas, isolation,
+    parser::{self, ParserLimits},
+    splice_completion, translate_drat, AssumeNormalizations, CarcaraOptions, LiaGenericOptions,
+    LogicValidation, Provenance, StepLimits,
 };
-use clap::{AppSettings, ArgEnum, Args, Parser, Subcommand};
+use clap::{AppSettings, ArgEnum, Args, FromArgMatches, Parser, Subcommand};
+use compression::open_file;
 use const_format::{formatcp, str_index};
 use error::{CliError, CliResult};
 use git_version::git_version;
 use path_args::{get_instances_from_paths, infer_problem_path};
 use std::{
-    fs::File,
-    io::{self, BufRead, IsTerminal},
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, BufRead, IsTerminal, Read, Write},
     path::Path,
+    time::Duration,
 };
 
 // `git describe --all` will try to find any ref (including tags) that describes the current commit.
@@ -64,24 +82,83 @@ enum Command {
     /// Checks a proof file.
     Check(CheckCommandOptions),
 
+    /// Checks many candidate proofs of the same problem concurrently, and reports the first one
+    /// found to be valid.
+    CheckAny(CheckAnyCommandOptions),
+
+    /// Checks a proof that is split across many files, as described by a manifest file.
+    CheckComposed(CheckComposedCommandOptions),
+
+    /// Checks a proof for each query of an incremental problem, and reports the result of each
+    /// one.
+    CheckQueries(CheckQueriesCommandOptions),
+
     /// Checks and elaborates a proof file.
     Elaborate(ElaborateCommandOptions),
 
     /// Checks a series of proof files and records performance statistics.
+    #[cfg(feature = "benchmarking")]
     Bench(BenchCommandOptions),
 
-    /// Given a step, takes a slice of a proof consisting of all its transitive premises.
+    /// Checks every step of a series of proof files, and reports how many steps of each rule were
+    /// checked, skipped or failed, and which rules are not implemented.
+    #[cfg(feature = "benchmarking")]
+    Coverage(CoverageCommandOptions),
+
+    /// Checks a series of proof files, and writes each of their theory lemmas to a directory as a
+    /// standalone SMT-LIB problem that asserts the negation of the lemma. Each such problem is
+    /// unsatisfiable, so the directory can be used as a regression suite for SMT solvers.
+    ExportLemmas(ExportLemmasCommandOptions),
+
+    /// Given a step, takes a slice of a proof consisting of all its transitive premises. If the
+    /// step is inside a subproof, the subproofs that contain it are kept, and closed right after it.
     Slice(SliceCommandOption),
+
+    /// Renames all user symbols in a problem and its proof, so they can be shared without
+    /// revealing the original names. The anonymized proof is printed to standard output.
+    Anonymize(AnonymizeCommandOptions),
+
+    /// Splices a proof of the frontier of an invalid proof, written by `check --frontier`, into
+    /// the original proof. The complete proof is printed to standard output.
+    Splice(SpliceCommandOptions),
+
+    /// Translates a proof into a system of constrained Horn clauses, which is printed to standard
+    /// output, for use with CHC solvers and software model checking tools.
+    Chc(ChcCommandOptions),
+
+    /// Checks a DRAT refutation of a DIMACS CNF formula, by translating it into an Alethe proof.
+    Drat(DratCommandOptions),
 }
 
 #[derive(Args)]
 struct Input {
-    /// The proof file to be checked
+    /// The proof file to be checked. If this is `-`, the proof is read from stdin.
     proof_file: String,
 
     /// The original problem file. If this argument is not present, it will be inferred from the
     /// proof file.
     problem_file: Option<String>,
+
+    /// The original problem file, given as an option instead of a positional argument. This allows
+    /// reading the proof from stdin with, for example, `carcara check --problem foo.smt2 -`.
+    #[clap(long, conflicts_with = "problem-file")]
+    problem: Option<String>,
+
+    /// Read both the problem and the proof from the proof file, or from stdin if it is `-`. The
+    /// problem is taken to be all lines before the first line equal to the proof start marker
+    /// (`unsat` by default), and the proof all lines after it.
+    #[clap(long, conflicts_with_all = &["problem-file", "problem", "proof-end-marker"])]
+    combined: bool,
+
+    /// Treat the proof file as a full solver log, and take as the proof only the lines after the
+    /// first line equal to this marker.
+    #[clap(long)]
+    proof_start_marker: Option<String>,
+
+    /// When extracting the proof from a solver log, stop at the first line equal to this marker.
+    /// If this is not present, all lines until the end of the log are taken.
+    #[clap(long, requires = "proof-start-marker")]
+    proof_end_marker: Option<String>,
 }
 
 #[derive(Args)]
@@ -115,6 +192,50 @@ struct ParsingOptions {
     /// to arithmetic operators that are expecting a term of sort `Real`.
     #[clap(long)]
     allow_int_real_subtyping: bool,
+
+    /// In incremental problems, check the proof against the premises of the given query, counting
+    /// `check-sat` and `check-sat-assuming` commands from zero. By default, the last query is used.
+    #[clap(long)]
+    query: Option<usize>,
+
+    /// Number of threads used to parse the proof. If this is greater than one, the proof is split
+    /// in chunks that are parsed in parallel.
+    #[clap(long, default_value = "1")]
+    parsing_threads: usize,
+
+    /// Reject inputs with terms nested more deeply than this.
+    #[clap(long)]
+    max_term_depth: Option<usize>,
+
+    /// Reject inputs with more terms than this, counting each occurrence of a subterm separately.
+    #[clap(long)]
+    max_term_count: Option<usize>,
+
+    /// Reject problem or proof files larger than this, in bytes.
+    #[clap(long)]
+    max_file_size: Option<usize>,
+
+    /// Flatten nested applications of associative operators when parsing, so `(and a (and b c))`
+    /// becomes `(and a b c)`. Elaborated proofs are printed with the terms as they were written.
+    /// This is ignored when checking with `--strict`, or when any rule is checked strictly.
+    #[clap(long)]
+    flatten_assoc_ops: bool,
+
+    /// The version of the Alethe format the proof is written in. Constructs and rule names from
+    /// other versions are rejected. By default, both versions are accepted. This is independent of
+    /// the version in which elaborated proofs are printed, given by `--alethe-version`.
+    #[clap(arg_enum, long)]
+    input_alethe_version: Option<TargetVersion>,
+
+    /// Accept some non-standard constructs emitted by veriT, such as anchors with the `:args`
+    /// attribute before `:step`, and conclusions written as a single term instead of a clause.
+    #[clap(long)]
+    verit_compat: bool,
+
+    /// Accept some non-standard constructs emitted by SMTInterpol, such as numerals as step ids,
+    /// empty `:premises` and `:args` lists, and step attributes in any order.
+    #[clap(long)]
+    smtinterpol_compat: bool,
 }
 
 #[derive(Args, Clone)]
@@ -123,6 +244,29 @@ struct CheckingOptions {
     #[clap(short, long)]
     strict: bool,
 
+    /// Enables the strict checking only for the given rules. This should be a comma-separated list
+    /// of rule names, and may include `assume` and `anchor`.
+    #[clap(long, value_delimiter = ',', conflicts_with("strict"))]
+    strict_rules: Vec<String>,
+
+    /// A configuration file with checking options. Each non-empty line in the file is of the form
+    /// `<key> <value>`, and lines starting with `;` are comments. Currently, the only key is
+    /// `strict-rules`, whose value is a comma-separated list of rule names, like in the
+    /// `--strict-rules` option. These rules are checked strictly in addition to the ones given in
+    /// the command line.
+    #[clap(long, parse(try_from_str = parse_config_file))]
+    config: Option<ConfigFile>,
+
+    /// Defines aliases for rule names, so steps using an alias are checked using the aliased rule.
+    /// This should be a comma-separated list of `alias=rule` pairs.
+    #[clap(long = "rule-alias", value_delimiter = ',', parse(try_from_str = parse_rule_alias))]
+    rule_aliases: Vec<(String, String)>,
+
+    /// Accepts the steps of the given rules without checking them, considering them as holes.
+    /// This should be a comma-separated list of rule names.
+    #[clap(long, value_delimiter = ',')]
+    trusted_rules: Vec<String>,
+
     /// Allow steps with rules that are not known by the checker, and consider them as holes.
     #[clap(short, long)]
     ignore_unknown_rules: bool,
@@ -148,6 +292,73 @@ struct CheckingOptions {
     /// Check `lia_generic` steps by calling into cvc5 (deprecated).
     #[clap(long, conflicts_with("lia-solver"))]
     lia_via_cvc5: bool,
+
+    /// Quickly reject invalid `la_generic` steps using floating-point interval arithmetic before
+    /// checking them with exact arithmetic.
+    #[clap(long, conflicts_with("la-infer-args"))]
+    numeric_precheck: bool,
+
+    /// Cache the literals of premises used in resolution steps, so they are not recomputed when
+    /// the same premise is used in many steps.
+    #[clap(long)]
+    resolution_cache: bool,
+
+    /// When a `la_generic` step is rejected, show the contribution of each clause literal to the
+    /// final disequality.
+    #[clap(long)]
+    la_certificates: bool,
+
+    /// Infer the coefficients of `la_generic` steps when they are missing or wrong. When
+    /// elaborating, the inferred coefficients replace the original arguments.
+    #[clap(long)]
+    la_infer_args: bool,
+
+    /// Only check resolution and subproof steps, considering all other steps as holes. This
+    /// quickly checks the structure of the proof.
+    #[clap(long)]
+    skeleton_only: bool,
+
+    /// Only check the given steps and the commands they transitively depend on, considering all
+    /// other steps as holes. This should be a comma-separated list of step ids.
+    #[clap(long = "only", value_delimiter = ',')]
+    only_steps: Vec<String>,
+
+    /// Validate the terms in the proof against the logic declared by the problem's `set-logic`
+    /// command, before checking it. Terms outside the logic are reported as warnings or errors.
+    #[clap(arg_enum, long, default_value_t = LogicValidationMode::Off)]
+    validate_logic: LogicValidationMode,
+
+    /// When elaborating, sort the premises of `cong` and `ho_cong` steps in the order of the
+    /// arguments whose equality they justify.
+    #[clap(long)]
+    sort_premises: bool,
+
+    /// When matching `assume` commands with the problem assertions, flatten nested `and` terms.
+    #[clap(long)]
+    assume_flatten_and: bool,
+
+    /// When matching `assume` commands with the problem assertions, ignore the order of the
+    /// arguments of `or` terms.
+    #[clap(long)]
+    assume_sort_or: bool,
+
+    /// When matching `assume` commands with the problem assertions, eliminate double negations.
+    #[clap(long)]
+    assume_normalize_negations: bool,
+
+    /// Reject any step whose conclusion clause has more than this many literals.
+    #[clap(long)]
+    max_clause_len: Option<usize>,
+
+    /// Reject any step whose conclusion clause and arguments have more than this many distinct
+    /// term nodes.
+    #[clap(long)]
+    max_term_nodes: Option<usize>,
+
+    /// Sets limits for specific rules, overriding `--max-clause-len` and `--max-term-nodes`. This
+    /// should be a comma-separated list of `rule:clause-len=N` or `rule:term-nodes=N` entries.
+    #[clap(long = "rule-limit", value_delimiter = ',', parse(try_from_str = parse_rule_limit))]
+    rule_limits: Vec<(String, StepLimits)>,
 }
 
 #[derive(Args)]
@@ -155,6 +366,97 @@ struct PrintingOptions {
     /// Use sharing when printing proof terms.
     #[clap(long = "print-with-sharing")]
     use_sharing: bool,
+
+    /// The version of the Alethe format whose syntax should be used when printing proofs.
+    #[clap(arg_enum, long, default_value_t = TargetVersion::Legacy)]
+    alethe_version: TargetVersion,
+
+    /// How negative numbers are printed: as `(- 1)` or as `-1`.
+    #[clap(arg_enum, long, default_value_t = NegativeNumbers::Application)]
+    negative_numbers: NegativeNumbers,
+
+    /// How non-integer rationals are printed: as `0.5` or as `(/ 1 2)`.
+    #[clap(arg_enum, long, default_value_t = Rationals::Decimal)]
+    rationals: Rationals,
+}
+
+impl PrintingOptions {
+    fn number_format(&self) -> NumberFormat {
+        NumberFormat {
+            negative_numbers: self.negative_numbers.into(),
+            rationals: self.rationals.into(),
+        }
+    }
+}
+
+impl Default for CheckingOptions {
+    /// Returns the checking options used when none are given in the command line, for commands
+    /// that only parse the proof.
+    fn default() -> Self {
+        let command = Self::augment_args(clap::Command::new("carcara"));
+        Self::from_arg_matches(&command.get_matches_from(["carcara"])).unwrap()
+    }
+}
+
+fn parse_rule_alias(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((alias, rule)) if !alias.is_empty() && !rule.is_empty() => {
+            Ok((alias.to_owned(), rule.to_owned()))
+        }
+        _ => Err(format!("expected `alias=rule`, found `{s}`")),
+    }
+}
+
+/// The checking options read from a configuration file, given by the `--config` option.
+#[derive(Debug, Clone, Default)]
+struct ConfigFile {
+    strict_rules: Vec<String>,
+}
+
+fn parse_config_file(path: &str) -> Result<ConfigFile, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let mut config = ConfigFile::default();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        let invalid = || format!("{}: invalid line {}: `{}`", path, i + 1, line);
+        let (key, value) = line.split_once(char::is_whitespace).ok_or_else(invalid)?;
+        match key {
+            "strict-rules" => config.strict_rules.extend(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|r| !r.is_empty())
+                    .map(str::to_owned),
+            ),
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(config)
+}
+
+fn parse_rule_limit(s: &str) -> Result<(String, StepLimits), String> {
+    let error = || format!("expected `rule:clause-len=N` or `rule:term-nodes=N`, found `{s}`");
+    let (rule, limit) = s.split_once(':').ok_or_else(error)?;
+    let (kind, value) = limit.split_once('=').ok_or_else(error)?;
+    let value = value.parse().map_err(|_| error())?;
+    let limits = match kind {
+        "clause-len" => StepLimits {
+            max_clause_len: Some(value),
+            ..Default::default()
+        },
+        "term-nodes" => StepLimits {
+            max_term_nodes: Some(value),
+            ..Default::default()
+        },
+        _ => return Err(error()),
+    };
+    if rule.is_empty() {
+        return Err(error());
+    }
+    Ok((rule.to_owned(), limits))
 }
 
 fn build_carcara_options(
@@ -162,14 +464,41 @@ fn build_carcara_options(
         apply_function_defs,
         expand_let_bindings,
         allow_int_real_subtyping,
+        query,
+        parsing_threads,
+        max_term_depth,
+        max_term_count,
+        max_file_size,
+        flatten_assoc_ops,
+        input_alethe_version,
+        verit_compat,
+        smtinterpol_compat,
     }: ParsingOptions,
     CheckingOptions {
         strict,
+        mut strict_rules,
+        config,
+        rule_aliases,
+        trusted_rules,
         ignore_unknown_rules,
         skip_unknown_rules,
         lia_solver,
         lia_via_cvc5,
         lia_solver_args,
+        numeric_precheck,
+        resolution_cache,
+        la_certificates,
+        la_infer_args,
+        skeleton_only,
+        only_steps,
+        validate_logic,
+        sort_premises,
+        assume_flatten_and,
+        assume_sort_or,
+        assume_normalize_negations,
+        max_clause_len,
+        max_term_nodes,
+        rule_limits,
     }: CheckingOptions,
     StatsOptions { stats }: StatsOptions,
 ) -> CarcaraOptions {
@@ -180,13 +509,46 @@ fn build_carcara_options(
         solver: solver.into(),
         arguments: lia_solver_args.split_whitespace().map(Into::into).collect(),
     });
+    if let Some(config) = config {
+        strict_rules.extend(config.strict_rules);
+    }
     CarcaraOptions {
         apply_function_defs,
         expand_lets: expand_let_bindings,
         allow_int_real_subtyping,
+        query,
+        parsing_threads,
+        parser_limits: ParserLimits {
+            max_term_depth,
+            max_term_count,
+            max_file_size,
+        },
+        flatten_assoc_ops,
+        alethe_version: input_alethe_version.map(Into::into),
+        verit_compat,
+        smtinterpol_compat,
+        infer_declarations: None,
         lia_options,
         strict,
+        strict_rules,
+        rule_aliases,
+        trusted_rules,
         ignore_unknown_rules: ignore_unknown_rules || skip_unknown_rules,
+        numeric_precheck,
+        resolution_cache,
+        la_certificates,
+        la_infer_args,
+        skeleton_only,
+        sort_premises,
+        assume_normalizations: AssumeNormalizations {
+            flatten_and: assume_flatten_and,
+            sort_or: assume_sort_or,
+            normalize_negations: assume_normalize_negations,
+        },
+        step_limits: StepLimits { max_clause_len, max_term_nodes },
+        rule_step_limits: rule_limits,
+        only_steps,
+        logic_validation: validate_logic.into(),
         stats,
     }
 }
@@ -201,6 +563,11 @@ struct ParseCommandOptions {
 
     #[clap(flatten)]
     printing: PrintingOptions,
+
+    /// Recover from errors in proof commands, and report all errors found in the proof, instead
+    /// of only the first one.
+    #[clap(long)]
+    recover_errors: bool,
 }
 
 #[derive(Args)]
@@ -233,6 +600,129 @@ struct CheckCommandOptions {
 
     #[clap(flatten)]
     stack: StackOptions,
+
+    /// Check each command while the proof is parsed, discarding it afterwards, instead of keeping
+    /// the whole proof in memory. This always uses a single thread.
+    #[clap(long, conflicts_with("stats"))]
+    streaming: bool,
+
+    /// If the proof is invalid, write its frontier to this file. This is a problem that asserts the
+    /// premises and the clauses derived by the steps checked before the error, and a proof of it
+    /// can be spliced into the original proof with the `splice` command. This always uses a single
+    /// thread.
+    #[clap(long, conflicts_with("streaming"))]
+    frontier: Option<String>,
+
+    /// Check the proof without the original problem. The `assume` commands of the proof are
+    /// accepted without being checked, and are reported as unchecked premises. Any declarations
+    /// needed to parse the proof must be included in the proof file, unless
+    /// `--infer-declarations` is used. This always uses a single thread.
+    #[clap(
+        long,
+        conflicts_with_all = &["problem-file", "problem", "combined", "streaming", "frontier"]
+    )]
+    proof_only: bool,
+
+    /// When checking without the problem, declare any symbol that is used without being declared.
+    /// The sort of each symbol is inferred from the term in which it is first used.
+    #[clap(long, requires("proof-only"))]
+    infer_declarations: bool,
+
+    /// The sort given to symbols declared by `--infer-declarations` when it can't be inferred.
+    #[clap(arg_enum, long, default_value_t = InferredSort::Bool)]
+    inferred_sort: InferredSort,
+
+    /// Cache the parsed proof in a binary file next to the proof file, with a `.cache` extension
+    /// appended to its name, and re-use it in later runs if the proof file was not modified. The
+    /// problem is always parsed again. This always uses a single thread.
+    #[clap(long, conflicts_with_all = &["streaming", "frontier", "proof-only"])]
+    proof_cache: bool,
+
+    /// Report every command that was only accepted using lenient reasoning that a strict checker
+    /// would reject, such as implicitly flipped equalities or double negations, and how many there
+    /// are for each rule. This always uses a single thread.
+    #[clap(
+        long,
+        conflicts_with_all = &["streaming", "frontier", "proof-only", "proof-cache"]
+    )]
+    report_leniency: bool,
+
+    #[clap(flatten)]
+    isolation: IsolationOptions,
+}
+
+#[derive(Args)]
+struct IsolationOptions {
+    /// Parse and check the proof in a separate worker process, so a pathological proof cannot
+    /// crash or exhaust the resources of this process. The problem and proof are read by this
+    /// process, and passed to the worker through its standard input.
+    #[clap(long, conflicts_with_all = &["proof-only", "proof-cache"])]
+    isolated: bool,
+
+    /// The maximum memory the worker process may use, in megabytes. Only used with `--isolated`.
+    #[clap(long, requires = "isolated")]
+    memory_limit: Option<u64>,
+
+    /// The maximum CPU time the worker process may use, in seconds. Only used with `--isolated`.
+    #[clap(long, requires = "isolated")]
+    cpu_time_limit: Option<u64>,
+
+    /// The maximum wall-clock time to wait for the worker process, in seconds. Only used with
+    /// `--isolated`.
+    #[clap(long, requires = "isolated")]
+    worker_timeout: Option<u64>,
+}
+
+#[derive(Args)]
+struct CheckAnyCommandOptions {
+    /// The original problem file.
+    problem_file: String,
+
+    /// The candidate proof files. Each one is checked in its own thread.
+    #[clap(required = true)]
+    proof_files: Vec<String>,
+
+    #[clap(flatten)]
+    parsing: ParsingOptions,
+
+    #[clap(flatten)]
+    checking: CheckingOptions,
+
+    #[clap(flatten)]
+    stack: StackOptions,
+}
+
+#[derive(Args)]
+struct CheckComposedCommandOptions {
+    /// The manifest file describing the composed proof. Each non-empty line in the manifest is
+    /// either `problem <file>`, giving the original problem file, or `part <file>`, giving a part
+    /// of the proof. There must be exactly one `problem` line, and the parts are parsed in the
+    /// order they appear. Paths are relative to the directory containing the manifest. Lines
+    /// starting with `;` are comments.
+    manifest_file: String,
+
+    #[clap(flatten)]
+    parsing: ParsingOptions,
+
+    #[clap(flatten)]
+    checking: CheckingOptions,
+}
+
+#[derive(Args)]
+struct CheckQueriesCommandOptions {
+    /// The original problem file.
+    problem_file: String,
+
+    /// The proof files, one for each `check-sat` or `check-sat-assuming` command in the problem,
+    /// in the same order.
+    #[clap(required = true)]
+    proof_files: Vec<String>,
+
+    #[clap(flatten)]
+    parsing: ParsingOptions,
+
+    #[clap(flatten)]
+    checking: CheckingOptions,
 }
 
 #[derive(Args)]
@@ -251,8 +741,30 @@ struct ElaborateCommandOptions {
 
     #[clap(flatten)]
     stats: StatsOptions,
+
+    /// Print each command of the elaborated proof as soon as it is built, instead of keeping the
+    /// whole elaborated proof in memory. In this mode, the elaborated proof is not pruned.
+    #[clap(long, conflicts_with("stats"))]
+    streaming: bool,
+
+    /// Don't print the header comment that records the Carcara version, a hash of the options,
+    /// the elaboration passes and the external solvers used to produce the elaborated proof.
+    #[clap(long)]
+    no_provenance: bool,
+
+    /// Write a report to the given file listing each command that the elaborator changed or
+    /// removed, with the commands that replace it and the reason for the change.
+    #[clap(long, conflicts_with_all = &["stats", "streaming"])]
+    explain_elaboration: Option<String>,
+
+    /// Instead of elaborating the proof, only add the pivots found by the checker as the
+    /// arguments of `resolution` and `th_resolution` steps that don't have them. All other
+    /// commands are printed unchanged.
+    #[clap(long, conflicts_with_all = &["stats", "streaming", "explain-elaboration"])]
+    resolution_pivots_only: bool,
 }
 
+#[cfg(feature = "benchmarking")]
 #[derive(Args)]
 struct BenchCommandOptions {
     #[clap(flatten)]
@@ -281,12 +793,62 @@ struct BenchCommandOptions {
     #[clap(long = "dump-to-csv")]
     dump_to_csv: bool,
 
+    /// Instead of measuring the time taken by each step, sample which step is being checked at a
+    /// fixed interval, given in microseconds, and estimate the step statistics from the samples.
+    /// This has less overhead, so it perturbs the results less for very fast steps.
+    #[clap(long)]
+    sample_interval: Option<u64>,
+
+    /// Write a summary of the results, with the number of valid, holey, invalid and errored runs
+    /// and the total time taken, as a JSON object to the given file.
+    #[clap(long)]
+    summary: Option<String>,
+
     /// The proof files on which the benchmark will be run. If a directory is passed, the checker
     /// will recursively find all proof files in the directory. The problem files will be
     /// inferred from the proof files.
     files: Vec<String>,
 }
 
+#[cfg(feature = "benchmarking")]
+#[derive(Args)]
+struct CoverageCommandOptions {
+    #[clap(flatten)]
+    parsing: ParsingOptions,
+
+    #[clap(flatten)]
+    checking: CheckingOptions,
+
+    /// Write the counts for each rule to the given CSV file, instead of printing them.
+    #[clap(long)]
+    csv: Option<String>,
+
+    /// The proof files to check. If a directory is passed, the checker will recursively find all
+    /// proof files in the directory. The problem files will be inferred from the proof files.
+    files: Vec<String>,
+}
+
+#[derive(Args)]
+struct ExportLemmasCommandOptions {
+    #[clap(flatten)]
+    parsing: ParsingOptions,
+
+    #[clap(flatten)]
+    checking: CheckingOptions,
+
+    /// The directory where the problems will be written. It is created if it doesn't exist.
+    #[clap(long, short = 'o')]
+    output_dir: String,
+
+    /// Write a lemma even if an identical problem was already written for another step.
+    #[clap(long)]
+    keep_duplicates: bool,
+
+    /// The proof files from which to export lemmas. If a directory is passed, all proof files in
+    /// it are found recursively. The problem files will be inferred from the proof files.
+    files: Vec<String>,
+}
+
 #[derive(Args)]
 struct SliceCommandOption {
     #[clap(flatten)]
@@ -305,6 +867,154 @@ struct SliceCommandOption {
     max_distance: Option<usize>,
 }
 
+#[derive(Args)]
+struct AnonymizeCommandOptions {
+    #[clap(flatten)]
+    input: Input,
+
+    #[clap(flatten)]
+    parsing: ParsingOptions,
+
+    #[clap(flatten)]
+    printing: PrintingOptions,
+
+    /// The file where the anonymized problem will be written.
+    #[clap(long, short = 'o')]
+    problem_output: String,
+}
+
+#[derive(Args)]
+struct SpliceCommandOptions {
+    #[clap(flatten)]
+    input: Input,
+
+    /// The proof of the frontier problem that will be spliced into the original proof.
+    #[clap(long)]
+    completion: String,
+
+    #[clap(flatten)]
+    parsing: ParsingOptions,
+
+    #[clap(flatten)]
+    checking: CheckingOptions,
+
+    #[clap(flatten)]
+    printing: PrintingOptions,
+}
+
+#[derive(Args)]
+struct ChcCommandOptions {
+    #[clap(flatten)]
+    input: Input,
+
+    #[clap(flatten)]
+    parsing: ParsingOptions,
+
+    /// The file where the model of the Horn clause system, derived from the proof, will be
+    /// written.
+    #[clap(long)]
+    model_output: Option<String>,
+}
+
+#[derive(Args)]
+struct DratCommandOptions {
+    /// The DIMACS CNF file.
+    cnf_file: String,
+
+    /// The DRAT refutation file, in the textual DRAT format.
+    drat_file: String,
+
+    #[clap(flatten)]
+    checking: CheckingOptions,
+
+    #[clap(flatten)]
+    printing: PrintingOptions,
+
+    /// Instead of checking the refutation, write the translated SMT-LIB problem to this file, and
+    /// print the translated Alethe proof to standard output.
+    #[clap(long)]
+    translate: Option<String>,
+}
+
+#[derive(ArgEnum, Clone, Copy)]
+enum TargetVersion {
+    Legacy,
+    Current,
+}
+
+impl From<TargetVersion> for AletheVersion {
+    fn from(v: TargetVersion) -> Self {
+        match v {
+            TargetVersion::Legacy => Self::Legacy,
+            TargetVersion::Current => Self::Current,
+        }
+    }
+}
+
+#[derive(ArgEnum, Clone, Copy)]
+enum NegativeNumbers {
+    Application,
+    Literal,
+}
+
+impl From<NegativeNumbers> for NegativeNumberStyle {
+    fn from(n: NegativeNumbers) -> Self {
+        match n {
+            NegativeNumbers::Application => Self::Application,
+            NegativeNumbers::Literal => Self::Literal,
+        }
+    }
+}
+
+#[derive(ArgEnum, Clone, Copy)]
+enum Rationals {
+    Decimal,
+    Division,
+}
+
+impl From<Rationals> for RationalStyle {
+    fn from(r: Rationals) -> Self {
+        match r {
+            Rationals::Decimal => Self::Decimal,
+            Rationals::Division => Self::Division,
+        }
+    }
+}
+
+#[derive(ArgEnum, Clone, Copy)]
+enum InferredSort {
+    Bool,
+    Int,
+    Real,
+}
+
+impl From<InferredSort> for Sort {
+    fn from(s: InferredSort) -> Self {
+        match s {
+            InferredSort::Bool => Self::Bool,
+            InferredSort::Int => Self::Int,
+            InferredSort::Real => Self::Real,
+        }
+    }
+}
+
+#[derive(ArgEnum, Clone, Copy)]
+enum LogicValidationMode {
+    Off,
+    Warn,
+    Error,
+}
+
+impl From<LogicValidationMode> for LogicValidation {
+    fn from(m: LogicValidationMode) -> Self {
+        match m {
+            LogicValidationMode::Off => Self::Off,
+            LogicValidationMode::Warn => Self::Warn,
+            LogicValidationMode::Error => Self::Error,
+        }
+    }
+}
+
 #[derive(ArgEnum, Clone)]
 enum LogLevel {
     Off,
@@ -329,10 +1039,21 @@ fn main() {
     let colors_enabled = !cli.no_color && std::io::stderr().is_terminal();
     logger::init(cli.log_level.into(), colors_enabled);
 
-    if let Command::Check(CheckCommandOptions { checking, .. })
-    | Command::Elaborate(ElaborateCommandOptions { checking, .. })
-    | Command::Bench(BenchCommandOptions { checking, .. }) = &cli.command
-    {
+    let checking = match &cli.command {
+        Command::Check(CheckCommandOptions { checking, .. })
+        | Command::CheckAny(CheckAnyCommandOptions { checking, .. })
+        | Command::CheckComposed(CheckComposedCommandOptions { checking, .. })
+        | Command::CheckQueries(CheckQueriesCommandOptions { checking, .. })
+        | Command::Elaborate(ElaborateCommandOptions { checking, .. })
+        | Command::ExportLemmas(ExportLemmasCommandOptions { checking, .. })
+        | Command::Splice(SpliceCommandOptions { checking, .. })
+        | Command::Drat(DratCommandOptions { checking, .. }) => Some(checking),
+        #[cfg(feature = "benchmarking")]
+        Command::Bench(BenchCommandOptions { checking, .. })
+        | Command::Coverage(CoverageCommandOptions { checking, .. }) => Some(checking),
+        _ => None,
+    };
+    if let Some(checking) = checking {
         if checking.skip_unknown_rules {
             log::warn!(
                 "the `--skip-unknown-rules` option is deprecated, please use \
@@ -349,7 +1070,45 @@ fn main() {
     let result = match cli.command {
         Command::Parse(options) => parse_command(options),
         Command::Check(options) => {
+            // Without the problem, the result only holds if the problem implies the assumptions
+            let suffix = if options.proof_only {
+                " relative to assumptions"
+            } else {
+                ""
+            };
             match check_command(options) {
+                Ok(false) => println!("valid{}", suffix),
+                Ok(true) => println!("holey{}", suffix),
+                Err(e) => {
+                    log::error!("{}", e);
+                    println!("invalid");
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Command::CheckAny(options) => {
+            let proof_files = options.proof_files.clone();
+            match check_any_command(options) {
+                Ok((i, false)) => println!("valid {}", proof_files[i]),
+                Ok((i, true)) => println!("holey {}", proof_files[i]),
+                Err(CliError::CarcaraError(carcara::Error::NoValidProof(errors))) => {
+                    for (i, e) in errors {
+                        log::error!("{}: {}", proof_files[i], e);
+                    }
+                    println!("invalid");
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    log::error!("{}", e);
+                    println!("invalid");
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Command::CheckComposed(options) => {
+            match check_composed_command(options) {
                 Ok(false) => println!("valid"),
                 Ok(true) => println!("holey"),
                 Err(e) => {
@@ -360,9 +1119,57 @@ fn main() {
             }
             return;
         }
+        Command::CheckQueries(options) => {
+            let proof_files = options.proof_files.clone();
+            match check_queries_command(options) {
+                Ok(results) => {
+                    let mut all_valid = true;
+                    for (file, result) in proof_files.iter().zip(results) {
+                        match result {
+                            Ok(false) => println!("valid {}", file),
+                            Ok(true) => println!("holey {}", file),
+                            Err(e) => {
+                                log::error!("{}: {}", file, e);
+                                println!("invalid {}", file);
+                                all_valid = false;
+                            }
+                        }
+                    }
+                    if !all_valid {
+                        std::process::exit(1);
+                    }
+                }
+                Err(e) => {
+                    log::error!("{}", e);
+                    println!("invalid");
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
         Command::Elaborate(options) => elaborate_command(options),
+        #[cfg(feature = "benchmarking")]
         Command::Bench(options) => bench_command(options),
+        #[cfg(feature = "benchmarking")]
+        Command::Coverage(options) => coverage_command(options),
+        Command::ExportLemmas(options) => export_lemmas_command(options),
         Command::Slice(options) => slice_command(options),
+        Command::Anonymize(options) => anonymize_command(options),
+        Command::Splice(options) => splice_command(options),
+        Command::Chc(options) => chc_command(options),
+        Command::Drat(options) if options.translate.is_some() => translate_drat_command(options),
+        Command::Drat(options) => {
+            match drat_command(options) {
+                Ok(false) => println!("valid"),
+                Ok(true) => println!("holey"),
+                Err(e) => {
+                    log::error!("{}", e);
+                    println!("invalid");
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
     };
     if let Err(e) = result {
         log::error!("{}", e);
@@ -372,41 +1179,184 @@ fn main() {
 
 fn get_instance(options: &Input) -> CliResult<(Box<dyn BufRead>, Box<dyn BufRead>)> {
     fn reader_from_path<P: AsRef<Path>>(path: P) -> CliResult<Box<dyn BufRead>> {
-        Ok(Box::new(io::BufReader::new(File::open(path)?)))
+        Ok(open_file(path)?)
+    }
+
+    if options.combined {
+        let input: Box<dyn BufRead> = match options.proof_file.as_str() {
+            "-" => Box::new(io::stdin().lock()),
+            path => reader_from_path(path)?,
+        };
+        let separator = options.proof_start_marker.as_deref().unwrap_or("unsat");
+        let (problem, proof) = parser::split_combined_input(input, separator)?;
+        return Ok((Box::new(io::Cursor::new(problem)), proof));
     }
 
-    match (options.problem_file.as_deref(), options.proof_file.as_str()) {
-        (Some("-"), "-") | (None, "-") => Err(CliError::BothFilesStdin),
-        (Some(problem), "-") => Ok((reader_from_path(problem)?, Box::new(io::stdin().lock()))),
-        (Some("-"), proof) => Ok((Box::new(io::stdin().lock()), reader_from_path(proof)?)),
-        (Some(problem), proof) => Ok((reader_from_path(problem)?, reader_from_path(proof)?)),
-        (None, proof) => Ok((
+    let problem_file = options.problem_file.as_ref().or(options.problem.as_ref());
+    let (problem, proof): (_, Box<dyn BufRead>) = match (
+        problem_file.map(String::as_str),
+        options.proof_file.as_str(),
+    ) {
+        (Some("-"), "-") | (None, "-") => return Err(CliError::BothFilesStdin),
+        (Some(problem), "-") => (reader_from_path(problem)?, Box::new(io::stdin().lock())),
+        (Some("-"), proof) => (Box::new(io::stdin().lock()), reader_from_path(proof)?),
+        (Some(problem), proof) => (reader_from_path(problem)?, reader_from_path(proof)?),
+        (None, proof) => (
             reader_from_path(infer_problem_path(proof)?)?,
             reader_from_path(proof)?,
-        )),
+        ),
+    };
+
+    match &options.proof_start_marker {
+        Some(start) => {
+            let end = options.proof_end_marker.as_deref();
+            let proof = parser::extract_proof_from_log(proof, start, end)?;
+            Ok((problem, Box::new(io::Cursor::new(proof))))
+        }
+        None => Ok((problem, proof)),
+    }
+}
+
+/// Returns only the proof of an instance, for when it is checked without its problem.
+fn get_proof(options: &Input) -> CliResult<Box<dyn BufRead>> {
+    let proof: Box<dyn BufRead> = match options.proof_file.as_str() {
+        "-" => Box::new(io::stdin().lock()),
+        path => open_file(path)?,
+    };
+    match &options.proof_start_marker {
+        Some(start) => {
+            let end = options.proof_end_marker.as_deref();
+            let proof = parser::extract_proof_from_log(proof, start, end)?;
+            Ok(Box::new(io::Cursor::new(proof)))
+        }
+        None => Ok(proof),
     }
 }
 
 fn parse_command(options: ParseCommandOptions) -> CliResult<()> {
     let (problem, proof) = get_instance(&options.input)?;
-    let (_, proof, _) = parser::parse_instance(
-        problem,
-        proof,
-        parser::Config {
-            apply_function_defs: options.parsing.apply_function_defs,
-            expand_lets: options.parsing.expand_let_bindings,
-            allow_int_real_subtyping: options.parsing.allow_int_real_subtyping,
-        },
+    let config = build_carcara_options(
+        options.parsing,
+        CheckingOptions::default(),
+        StatsOptions { stats: false },
     )
-    .map_err(carcara::Error::from)?;
-    print_proof(&proof.commands, options.printing.use_sharing)?;
+    .parser_config();
+    let proof = if options.recover_errors {
+        let mut pool = carcara::ast::PrimitivePool::new();
+        let mut parser = parser::Parser::new(&mut pool, config, problem)?;
+        let (_, premises) = parser.parse_problem()?;
+        parser.reset(proof)?;
+        let (commands, errors) = parser.parse_proof_recovering();
+        if !errors.is_empty() {
+            for e in &errors {
+                log::error!("{}", e);
+            }
+            return Err(CliError::ParserErrors(errors.len()));
+        }
+        let attributes = parser.take_attributes();
+        let term_attributes = parser.take_term_attributes();
+        let spans = parser.take_spans();
+        let flattened = parser.take_flattened();
+        carcara::ast::Proof {
+            premises,
+            commands,
+            attributes,
+            term_attributes,
+            spans,
+            flattened,
+        }
+    } else {
+        let (_, proof, _) = parser::parse_instance(problem, proof, config)?;
+        proof
+    };
+    print_proof_for_version(
+        &proof.commands,
+        &proof.attributes,
+        &proof.term_attributes,
+        options.printing.use_sharing,
+        options.printing.alethe_version.into(),
+        options.printing.number_format(),
+    )?;
     Ok(())
 }
 
 fn check_command(options: CheckCommandOptions) -> CliResult<bool> {
+    if options.isolation.isolated {
+        return check_isolated_command(options);
+    }
+    if options.proof_only {
+        let proof = get_proof(&options.input)?;
+        let carc_options = CarcaraOptions {
+            infer_declarations: options
+                .infer_declarations
+                .then(|| options.inferred_sort.into()),
+            ..build_carcara_options(options.parsing, options.checking, options.stats)
+        };
+        let result = check_proof_only(proof, carc_options)?;
+        for (name, sort) in &result.inferred_declarations {
+            let (params, sort) = match sort.as_sort() {
+                Some(Sort::Function(sorts)) => sorts.split_last().map(|(s, p)| (p, s)).unwrap(),
+                _ => (&[][..], sort),
+            };
+            let params: Vec<_> = params.iter().map(ToString::to_string).collect();
+            log::info!(
+                "inferred declaration: (declare-fun {} ({}) {})",
+                name,
+                params.join(" "),
+                sort
+            );
+        }
+        if !result.unchecked_premises.is_empty() {
+            log::warn!(
+                "{} premises were accepted without being checked against a problem",
+                result.unchecked_premises.len()
+            );
+        }
+        for (id, term) in &result.unchecked_premises {
+            log::info!("unchecked premise: (assume {} {})", id, term);
+        }
+        return Ok(result.is_holey);
+    }
     let (problem, proof) = get_instance(&options.input)?;
     let carc_options = build_carcara_options(options.parsing, options.checking, options.stats);
-    if options.num_threads == 1 {
+    if let Some(path) = &options.frontier {
+        return match check_with_frontier(problem, proof, carc_options)? {
+            Ok(holey) => Ok(holey),
+            Err(frontier) => {
+                std::fs::write(path, frontier.problem)?;
+                Err(frontier.error.into())
+            }
+        };
+    }
+    if options.report_leniency {
+        let (is_holey, report) = check_with_leniency_report(problem, proof, carc_options)?;
+        for step in &report.steps {
+            let location = step
+                .span
+                .map(|s| format!(" (on line {})", s.start.0))
+                .unwrap_or_default();
+            log::warn!(
+                "{} '{}'{} uses lenient reasoning: {}",
+                step.rule,
+                step.id,
+                location,
+                step.leniency
+            );
+        }
+        for ((rule, leniency), count) in report.summary() {
+            log::warn!("{}: {} commands use {}", rule, count, leniency);
+        }
+        return Ok(is_holey);
+    }
+    if options.proof_cache {
+        if options.input.proof_file == "-" {
+            return Err(CliError::ProofCacheStdin);
+        }
+        let proof_path = Path::new(&options.input.proof_file);
+        check_with_proof_cache(problem, proof, proof_path, carc_options)
+    } else if options.streaming {
+        check_streaming(problem, proof, carc_options)
+    } else if options.num_threads == 1 {
         check(problem, proof, carc_options)
     } else {
         check_parallel(
@@ -420,18 +1370,371 @@ fn check_command(options: CheckCommandOptions) -> CliResult<bool> {
     .map_err(Into::into)
 }
 
+/// Runs the current `check` command in a worker process. The problem and proof are read by this
+/// process and written to the standard input of the worker, which is a `check` command with the
+/// same parsing and checking options.
+fn check_isolated_command(options: CheckCommandOptions) -> CliResult<bool> {
+    let (mut problem_reader, mut proof_reader) = get_instance(&options.input)?;
+    let mut problem = Vec::new();
+    problem_reader.read_to_end(&mut problem)?;
+    let mut proof = Vec::new();
+    proof_reader.read_to_end(&mut proof)?;
+
+    let limits = isolation::WorkerLimits {
+        memory_limit: options.isolation.memory_limit,
+        cpu_time_limit: options.isolation.cpu_time_limit,
+        timeout: options.isolation.worker_timeout.map(Duration::from_secs),
+    };
+    Ok(isolation::check_isolated(
+        std::env::current_exe()?,
+        worker_args(options),
+        &problem,
+        &proof,
+        limits,
+    )?)
+}
+
+/// Builds the arguments of a worker process that runs a `check` command with the given options,
+/// reading the problem and the proof from its standard input.
+fn worker_args(
+    CheckCommandOptions {
+        input: _,
+        parsing:
+            ParsingOptions {
+                apply_function_defs,
+                expand_let_bindings,
+                allow_int_real_subtyping,
+                query,
+                parsing_threads,
+                max_term_depth,
+                max_term_count,
+                max_file_size,
+                flatten_assoc_ops,
+                input_alethe_version,
+                verit_compat,
+                smtinterpol_compat,
+            },
+        checking:
+            CheckingOptions {
+                strict,
+                mut strict_rules,
+                config,
+                rule_aliases,
+                trusted_rules,
+                ignore_unknown_rules,
+                skip_unknown_rules,
+                lia_solver,
+                lia_via_cvc5,
+                lia_solver_args,
+                numeric_precheck,
+                resolution_cache,
+                la_certificates,
+                la_infer_args,
+                skeleton_only,
+                only_steps,
+                validate_logic,
+                sort_premises,
+                assume_flatten_and,
+                assume_sort_or,
+                assume_normalize_negations,
+                max_clause_len,
+                max_term_nodes,
+                rule_limits,
+            },
+        num_threads,
+        stats: StatsOptions { stats },
+        stack: StackOptions { stack_size },
+        streaming,
+        frontier,
+        // These can't be used together with `--isolated`
+        proof_only: _,
+        infer_declarations: _,
+        inferred_sort: _,
+        proof_cache: _,
+        report_leniency,
+        isolation: _,
+    }: CheckCommandOptions,
+) -> Vec<String> {
+    let log_level = match log::max_level() {
+        log::LevelFilter::Off => LogLevel::Off,
+        log::LevelFilter::Error => LogLevel::Error,
+        log::LevelFilter::Warn => LogLevel::Warn,
+        _ => LogLevel::Info,
+    };
+    let mut args = WorkerArgs(Vec::new());
+    args.value("log", Some(arg_enum_name(log_level)));
+    args.0
+        .extend(["check", "--combined", "-"].map(String::from));
+
+    args.flag("apply-function-defs", apply_function_defs);
+    args.flag("expand-let-bindings", expand_let_bindings);
+    args.flag("allow-int-real-subtyping", allow_int_real_subtyping);
+    args.value("query", query);
+    args.value("parsing-threads", Some(parsing_threads));
+    args.value("max-term-depth", max_term_depth);
+    args.value("max-term-count", max_term_count);
+    args.value("max-file-size", max_file_size);
+    args.flag("flatten-assoc-ops", flatten_assoc_ops);
+    args.value(
+        "input-alethe-version",
+        input_alethe_version.map(arg_enum_name),
+    );
+    args.flag("verit-compat", verit_compat);
+    args.flag("smtinterpol-compat", smtinterpol_compat);
+
+    // The rules from the configuration file are passed directly, so the worker doesn't need to
+    // read it again
+    if let Some(config) = config {
+        strict_rules.extend(config.strict_rules);
+    }
+    args.flag("strict", strict);
+    if !strict {
+        args.list("strict-rules", strict_rules);
+    }
+    args.list(
+        "rule-alias",
+        rule_aliases
+            .into_iter()
+            .map(|(alias, rule)| format!("{alias}={rule}")),
+    );
+    args.list("trusted-rules", trusted_rules);
+    args.flag(
+        "ignore-unknown-rules",
+        ignore_unknown_rules || skip_unknown_rules,
+    );
+    if let Some(solver) = lia_solver.or_else(|| lia_via_cvc5.then(|| "cvc5".into())) {
+        args.value("lia-solver", Some(solver));
+        args.value("lia-solver-args", Some(lia_solver_args));
+    }
+    args.flag("numeric-precheck", numeric_precheck);
+    args.flag("resolution-cache", resolution_cache);
+    args.flag("la-certificates", la_certificates);
+    args.flag("la-infer-args", la_infer_args);
+    args.flag("skeleton-only", skeleton_only);
+    args.list("only", only_steps);
+    args.value("validate-logic", Some(arg_enum_name(validate_logic)));
+    args.flag("sort-premises", sort_premises);
+    args.flag("assume-flatten-and", assume_flatten_and);
+    args.flag("assume-sort-or", assume_sort_or);
+    args.flag("assume-normalize-negations", assume_normalize_negations);
+    args.value("max-clause-len", max_clause_len);
+    args.value("max-term-nodes", max_term_nodes);
+    args.list(
+        "rule-limit",
+        rule_limits.into_iter().flat_map(|(rule, limits)| {
+            let clause_len = limits
+                .max_clause_len
+                .map(|n| format!("{rule}:clause-len={n}"));
+            let term_nodes = limits
+                .max_term_nodes
+                .map(|n| format!("{rule}:term-nodes={n}"));
+            clause_len.into_iter().chain(term_nodes)
+        }),
+    );
+
+    args.value("num-threads", Some(num_threads));
+    args.flag("stats", stats);
+    args.value("stack-size", Some(stack_size));
+    args.flag("streaming", streaming);
+    args.value("frontier", frontier);
+    args.flag("report-leniency", report_leniency);
+    args.0
+}
+
+/// The command-line arguments of a worker process, built by [`worker_args`].
+struct WorkerArgs(Vec<String>);
+
+impl WorkerArgs {
+    fn flag(&mut self, name: &str, value: bool) {
+        if value {
+            self.0.push(format!("--{name}"));
+        }
+    }
+
+    fn value(&mut self, name: &str, value: Option<impl std::fmt::Display>) {
+        if let Some(value) = value {
+            self.0.push(format!("--{name}={value}"));
+        }
+    }
+
+    fn list(&mut self, name: &str, values: impl IntoIterator<Item = String>) {
+        let values: Vec<_> = values.into_iter().collect();
+        if !values.is_empty() {
+            self.0.push(format!("--{name}={}", values.join(",")));
+        }
+    }
+}
+
+/// Returns the name of an `ArgEnum` value, as it is written in the command line.
+fn arg_enum_name<T: ArgEnum>(value: T) -> String {
+    value.to_possible_value().unwrap().get_name().to_owned()
+}
+
+fn check_any_command(options: CheckAnyCommandOptions) -> CliResult<(usize, bool)> {
+    let problem = open_file(&options.problem_file)?;
+    let proofs = options
+        .proof_files
+        .iter()
+        .map(|path| Ok(open_file(path)?))
+        .collect::<CliResult<Vec<_>>>()?;
+    let carc_options = build_carcara_options(
+        options.parsing,
+        options.checking,
+        StatsOptions { stats: false },
+    );
+    check_any(problem, proofs, carc_options, options.stack.stack_size).map_err(Into::into)
+}
+
+fn check_composed_command(options: CheckComposedCommandOptions) -> CliResult<bool> {
+    let manifest_path = Path::new(&options.manifest_file);
+    let base = manifest_path.parent().unwrap_or(Path::new(""));
+    let manifest = std::fs::read_to_string(manifest_path)?;
+
+    let mut problem = None;
+    let mut parts = Vec::new();
+    for (i, line) in manifest.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        let invalid = || CliError::InvalidManifestLine(i + 1, line.to_owned());
+        let (kind, file) = line.split_once(char::is_whitespace).ok_or_else(invalid)?;
+        let path = base.join(file.trim());
+        match kind {
+            "problem" if problem.is_none() => problem = Some(path),
+            "part" => parts.push(path),
+            _ => return Err(invalid()),
+        }
+    }
+    let problem = problem.ok_or(CliError::ManifestWithoutProblem)?;
+
+    let problem = open_file(problem)?;
+    let parts = parts
+        .into_iter()
+        .map(|path| {
+            let reader = open_file(&path)?;
+            Ok((path.display().to_string(), reader))
+        })
+        .collect::<CliResult<Vec<_>>>()?;
+    let carc_options = build_carcara_options(
+        options.parsing,
+        options.checking,
+        StatsOptions { stats: false },
+    );
+    check_composed(problem, parts, carc_options).map_err(Into::into)
+}
+
+fn check_queries_command(
+    options: CheckQueriesCommandOptions,
+) -> CliResult<Vec<Result<bool, carcara::Error>>> {
+    let problem = open_file(&options.problem_file)?;
+    let proofs = options
+        .proof_files
+        .iter()
+        .map(|path| Ok(open_file(path)?))
+        .collect::<CliResult<Vec<_>>>()?;
+    let carc_options = build_carcara_options(
+        options.parsing,
+        options.checking,
+        StatsOptions { stats: false },
+    );
+    check_queries(problem, proofs, carc_options).map_err(Into::into)
+}
+
 fn elaborate_command(options: ElaborateCommandOptions) -> CliResult<()> {
     let (problem, proof) = get_instance(&options.input)?;
+    let carc_options = build_carcara_options(options.parsing, options.checking, options.stats);
+    let provenance = (!options.no_provenance).then(|| Provenance {
+        version: VERSION_STRING.to_owned(),
+        ..Provenance::new(&carc_options, options.streaming)
+    });
+
+    if options.streaming {
+        if let Some(provenance) = &provenance {
+            print!("{}", provenance);
+        }
+        check_and_elaborate_streaming(
+            problem,
+            proof,
+            carc_options,
+            &mut io::stdout(),
+            options.printing.use_sharing,
+            options.printing.alethe_version.into(),
+            options.printing.number_format(),
+        )?;
+        return Ok(());
+    }
 
-    let (_, elaborated) = check_and_elaborate(
-        problem,
-        proof,
-        build_carcara_options(options.parsing, options.checking, options.stats),
+    if options.resolution_pivots_only {
+        let (_, proof) = check_with_resolution_pivots(problem, proof, carc_options)?;
+        print_proof_for_version(
+            &proof.commands,
+            &proof.attributes,
+            &proof.term_attributes,
+            options.printing.use_sharing,
+            options.printing.alethe_version.into(),
+            options.printing.number_format(),
+        )?;
+        return Ok(());
+    }
+
+    let elaborated = match &options.explain_elaboration {
+        Some(path) => {
+            let (_, elaborated, explanations) =
+                check_and_elaborate_explained(problem, proof, carc_options)?;
+            let mut dest = io::BufWriter::new(File::create(path)?);
+            write_elaboration_report(&mut dest, &explanations)?;
+            dest.flush()?;
+            elaborated
+        }
+        None => check_and_elaborate(problem, proof, carc_options)?.1,
+    };
+    if let Some(provenance) = &provenance {
+        print!("{}", provenance);
+    }
+    print_proof_for_version(
+        &elaborated.commands,
+        &elaborated.attributes,
+        &elaborated.term_attributes,
+        options.printing.use_sharing,
+        options.printing.alethe_version.into(),
+        options.printing.number_format(),
     )?;
-    print_proof(&elaborated.commands, options.printing.use_sharing)?;
     Ok(())
 }
 
+/// Writes the changes made by the elaborator, with the original command prefixed by `-` and the
+/// commands that replace it prefixed by `+`.
+fn write_elaboration_report(
+    dest: &mut dyn io::Write,
+    explanations: &[StepExplanation],
+) -> io::Result<()> {
+    fn describe(command: &ProofCommand) -> String {
+        match command {
+            ProofCommand::Assume { id, term } => format!("(assume {} {})", id, term),
+            ProofCommand::Step(s) => {
+                let clause: String = s.clause.iter().map(|t| format!(" {}", t)).collect();
+                format!("(step {} (cl{}) :rule {})", s.id, clause, s.rule)
+            }
+            ProofCommand::Subproof(s) => format!(
+                "(subproof {} with {} commands)",
+                command.id(),
+                s.commands.len()
+            ),
+        }
+    }
+
+    for e in explanations {
+        writeln!(dest, "{}: {}", e.original.id(), e.reason)?;
+        writeln!(dest, "  - {}", describe(&e.original))?;
+        for c in &e.replacement {
+            writeln!(dest, "  + {}", describe(c))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "benchmarking")]
 fn bench_command(options: BenchCommandOptions) -> CliResult<()> {
     let instances = get_instances_from_paths(options.files.iter().map(|s| s.as_str()))?;
     if instances.is_empty() {
@@ -450,26 +1753,39 @@ fn bench_command(options: BenchCommandOptions) -> CliResult<()> {
         options.checking,
         StatsOptions { stats: false },
     );
+    let sample_interval = options.sample_interval.map(Duration::from_micros);
     if options.dump_to_csv {
-        benchmarking::run_csv_benchmark(
+        let summary = benchmarking::run_csv_benchmark(
             &instances,
             options.num_runs,
             options.num_jobs,
             &carc_options,
             options.elaborate,
+            sample_interval,
             &mut File::create("runs.csv")?,
             &mut File::create("by-rule.csv")?,
         )?;
+        if let Some(path) = &options.summary {
+            summary.write_json(&mut File::create(path)?)?;
+        }
         return Ok(());
     }
 
-    let results: OnlineBenchmarkResults = benchmarking::run_benchmark(
+    let (results, summary): (OnlineBenchmarkResults, _) = benchmarking::run_benchmark(
         &instances,
         options.num_runs,
         options.num_jobs,
         &carc_options,
         options.elaborate,
+        sample_interval,
     );
+    if let Some(path) = &options.summary {
+        summary.write_json(&mut File::create(path)?)?;
+    }
+    println!("{}", summary);
+    for ((file, run_index), error) in &results.failures {
+        println!("failed: {} (run {}): {}", file, run_index, error);
+    }
     if results.is_empty() {
         println!("no benchmark data collected");
         return Ok(());
@@ -482,29 +1798,298 @@ fn bench_command(options: BenchCommandOptions) -> CliResult<()> {
     } else {
         println!("valid");
     }
+    if let Some(interval) = sample_interval {
+        println!(
+            "step statistics estimated from samples taken every {:?}",
+            interval
+        );
+    }
     results.print(options.sort_by_total);
     Ok(())
 }
 
 fn slice_command(options: SliceCommandOption) -> CliResult<()> {
     let (problem, proof) = get_instance(&options.input)?;
-    let config = parser::Config {
-        apply_function_defs: options.parsing.apply_function_defs,
-        expand_lets: options.parsing.expand_let_bindings,
-        allow_int_real_subtyping: options.parsing.allow_int_real_subtyping,
-    };
-    let (_, proof, _) =
-        parser::parse_instance(problem, proof, config).map_err(carcara::Error::from)?;
+    let config = build_carcara_options(
+        options.parsing,
+        CheckingOptions::default(),
+        StatsOptions { stats: false },
+    )
+    .parser_config();
+    let (_, proof, _) = parser::parse_instance(problem, proof, config)?;
 
-    let source_index = proof
-        .commands
-        .iter()
-        .position(|c| c.id() == options.from)
+    let index = proof.index();
+    let source_path = index
+        .path(&options.from)
         .ok_or_else(|| CliError::InvalidSliceId(options.from.to_owned()))?;
 
     let diff =
-        carcara::elaborator::slice_proof(&proof.commands, source_index, options.max_distance);
+        carcara::elaborator::slice_proof_at(&proof.commands, source_path, options.max_distance);
     let slice = carcara::elaborator::apply_diff(diff, proof.commands);
-    print_proof(&slice, options.printing.use_sharing)?;
+    print_proof_for_version(
+        &slice,
+        &proof.attributes,
+        &proof.term_attributes,
+        options.printing.use_sharing,
+        options.printing.alethe_version.into(),
+        options.printing.number_format(),
+    )?;
+    Ok(())
+}
+
+fn splice_command(options: SpliceCommandOptions) -> CliResult<()> {
+    let (problem, proof) = get_instance(&options.input)?;
+    let completion: Box<dyn BufRead> = open_file(&options.completion)?;
+    let carc_options = build_carcara_options(
+        options.parsing,
+        options.checking,
+        StatsOptions { stats: false },
+    );
+    let proof = splice_completion(problem, proof, completion, carc_options)?;
+    print_proof_for_version(
+        &proof.commands,
+        &proof.attributes,
+        &proof.term_attributes,
+        options.printing.use_sharing,
+        options.printing.alethe_version.into(),
+        options.printing.number_format(),
+    )?;
+    Ok(())
+}
+
+fn chc_command(options: ChcCommandOptions) -> CliResult<()> {
+    let (problem, proof) = get_instance(&options.input)?;
+    let carc_options = build_carcara_options(
+        options.parsing,
+        CheckingOptions::default(),
+        StatsOptions { stats: false },
+    );
+    let certificate = chc_certificate(problem, proof, carc_options)?;
+    if let Some(path) = &options.model_output {
+        let mut model_output = io::BufWriter::new(File::create(path)?);
+        write!(model_output, "{}", certificate.model)?;
+        model_output.flush()?;
+    }
+    print!("{}", certificate.system);
+    Ok(())
+}
+
+#[cfg(feature = "benchmarking")]
+fn coverage_command(options: CoverageCommandOptions) -> CliResult<()> {
+    let instances = get_instances_from_paths(options.files.iter().map(|s| s.as_str()))?;
+    if instances.is_empty() {
+        log::warn!("no files passed");
+        return Ok(());
+    }
+    let carc_options = build_carcara_options(
+        options.parsing,
+        options.checking,
+        StatsOptions { stats: false },
+    );
+    let (coverage, errored) = benchmarking::run_coverage(&instances, &carc_options);
+    if errored > 0 {
+        log::warn!(
+            "{} of {} proofs could not be checked",
+            errored,
+            instances.len()
+        );
+    }
+
+    if let Some(path) = &options.csv {
+        let mut dest = io::BufWriter::new(File::create(path)?);
+        writeln!(dest, "rule,checked,skipped,failed,unimplemented")?;
+        for (rule, c) in coverage.by_frequency() {
+            writeln!(
+                dest,
+                "{},{},{},{},{}",
+                rule, c.checked, c.skipped, c.failed, c.unimplemented
+            )?;
+        }
+        dest.flush()?;
+        return Ok(());
+    }
+
+    let width = coverage
+        .rules
+        .keys()
+        .map(String::len)
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    println!(
+        "{:<width$} {:>10} {:>10} {:>10} {:>13}",
+        "rule", "checked", "skipped", "failed", "unimplemented"
+    );
+    for (rule, c) in coverage.by_frequency() {
+        println!(
+            "{:<width$} {:>10} {:>10} {:>10} {:>13}",
+            rule, c.checked, c.skipped, c.failed, c.unimplemented
+        );
+    }
+    let unimplemented = coverage.unimplemented();
+    if !unimplemented.is_empty() {
+        println!();
+        println!("unimplemented rules, by number of steps:");
+        for (rule, count) in unimplemented {
+            let examples = coverage
+                .unknown_rules
+                .rules
+                .get(rule)
+                .map(|u| {
+                    let ids: Vec<_> = u.examples.iter().map(|id| id.as_str()).collect();
+                    format!(" (like {})", ids.join(", "))
+                })
+                .unwrap_or_default();
+            println!("    {}: {}{}", rule, count, examples);
+        }
+    }
+    Ok(())
+}
+
+fn export_lemmas_command(options: ExportLemmasCommandOptions) -> CliResult<()> {
+    let instances = get_instances_from_paths(options.files.iter().map(|s| s.as_str()))?;
+    if instances.is_empty() {
+        log::warn!("no files passed");
+        return Ok(());
+    }
+    let carc_options = build_carcara_options(
+        options.parsing,
+        options.checking,
+        StatsOptions { stats: false },
+    );
+    let output_dir = Path::new(&options.output_dir);
+    fs::create_dir_all(output_dir)?;
+
+    let mut seen = HashSet::new();
+    let (mut written, mut errored) = (0, 0);
+    for (problem_file, proof_file) in &instances {
+        let lemmas = extract_theory_lemmas(
+            open_file(problem_file)?,
+            open_file(proof_file)?,
+            carc_options.clone(),
+        );
+        let lemmas = match lemmas {
+            Ok(l) => l,
+            Err(e) => {
+                log::error!(
+                    "encountered error in file '{}': {}",
+                    proof_file.display(),
+                    e
+                );
+                errored += 1;
+                continue;
+            }
+        };
+
+        let stem = proof_file.file_stem().unwrap_or_default().to_string_lossy();
+        for lemma in lemmas {
+            if !options.keep_duplicates && !seen.insert(lemma.problem.clone()) {
+                continue;
+            }
+            // Step ids may contain characters that are not allowed in file names, so we replace them
+            let name: String = format!("{}.{}.{}.smt2", stem, lemma.id, lemma.rule)
+                .chars()
+                .map(|c| match c {
+                    'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '_' | '-' => c,
+                    _ => '_',
+                })
+                .collect();
+            let mut dest = io::BufWriter::new(File::create(output_dir.join(name))?);
+            writeln!(dest, "(set-info :status unsat)")?;
+            write!(dest, "{}", lemma.problem)?;
+            dest.flush()?;
+            written += 1;
+        }
+    }
+    if errored > 0 {
+        log::warn!(
+            "{} of {} proofs could not be checked",
+            errored,
+            instances.len()
+        );
+    }
+    log::info!("wrote {} lemmas to '{}'", written, output_dir.display());
+    Ok(())
+}
+
+fn drat_command(options: DratCommandOptions) -> CliResult<bool> {
+    // None of the parsing options apply to DIMACS and DRAT files
+    let parsing = ParsingOptions {
+        apply_function_defs: false,
+        expand_let_bindings: false,
+        allow_int_real_subtyping: false,
+        query: None,
+        parsing_threads: 1,
+        max_term_depth: None,
+        max_term_count: None,
+        max_file_size: None,
+        flatten_assoc_ops: false,
+        input_alethe_version: None,
+        verit_compat: false,
+        smtinterpol_compat: false,
+    };
+    let carc_options =
+        build_carcara_options(parsing, options.checking, StatsOptions { stats: false });
+    let (cnf, drat) = (
+        open_file(&options.cnf_file)?,
+        open_file(&options.drat_file)?,
+    );
+    Ok(check_drat(cnf, drat, carc_options)?)
+}
+
+fn translate_drat_command(options: DratCommandOptions) -> CliResult<()> {
+    let (cnf, drat) = (
+        open_file(&options.cnf_file)?,
+        open_file(&options.drat_file)?,
+    );
+    let (prelude, proof, _) = translate_drat(cnf, drat)?;
+
+    let mut problem_output = io::BufWriter::new(File::create(options.translate.unwrap())?);
+    write!(problem_output, "{}", prelude)?;
+    for premise in &proof.premises {
+        writeln!(problem_output, "(assert {})", premise)?;
+    }
+    writeln!(problem_output, "(check-sat)")?;
+    problem_output.flush()?;
+
+    print_proof_for_version(
+        &proof.commands,
+        &proof.attributes,
+        &proof.term_attributes,
+        options.printing.use_sharing,
+        options.printing.alethe_version.into(),
+        options.printing.number_format(),
+    )?;
+    Ok(())
+}
+
+fn anonymize_command(options: AnonymizeCommandOptions) -> CliResult<()> {
+    let (problem, proof) = get_instance(&options.input)?;
+    let config = build_carcara_options(
+        options.parsing,
+        CheckingOptions::default(),
+        StatsOptions { stats: false },
+    )
+    .parser_config();
+    let (prelude, proof, mut pool) = parser::parse_instance(problem, proof, config)?;
+
+    let (prelude, proof) = carcara::elaborator::anonymize(&mut pool, &prelude, &proof);
+
+    let mut problem_output = io::BufWriter::new(File::create(&options.problem_output)?);
+    write!(problem_output, "{}", prelude)?;
+    for premise in &proof.premises {
+        writeln!(problem_output, "(assert {})", premise)?;
+    }
+    writeln!(problem_output, "(check-sat)")?;
+    problem_output.flush()?;
+
+    print_proof_for_version(
+        &proof.commands,
+        &proof.attributes,
+        &proof.term_attributes,
+        options.printing.use_sharing,
+        options.printing.alethe_version.into(),
+        options.printing.number_format(),
+    )?;
     Ok(())
 }
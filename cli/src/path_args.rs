@@ -1,6 +1,6 @@
 #![allow(dead_code)]
 
-use crate::error::CliError;
+use crate::{compression::compression_extension, error::CliError};
 use std::{ffi::OsStr, fs, path::PathBuf};
 
 const SMT_FILE_EXTENSIONS: [&str; 3] = ["smt", "smt2", "smt_in"];
@@ -14,7 +14,20 @@ pub fn infer_problem_path(proof_path: impl Into<PathBuf>) -> Result<PathBuf, Cli
         Some(path)
     }
     let proof_path: PathBuf = proof_path.into();
-    inner(proof_path.clone()).ok_or(CliError::CantInferProblemFile(proof_path))
+    let problem_path = inner(proof_path.clone())
+        .ok_or_else(|| CliError::CantInferProblemFile(proof_path.clone()))?;
+
+    // If the proof file is compressed, the problem file may be compressed in the same format
+    if let Some(extension) = compression_extension(&proof_path) {
+        let mut compressed = problem_path.clone().into_os_string();
+        compressed.push(".");
+        compressed.push(extension);
+        let compressed = PathBuf::from(compressed);
+        if !problem_path.exists() && compressed.exists() {
+            return Ok(compressed);
+        }
+    }
+    Ok(problem_path)
 }
 
 fn get_instances_from_dir(
@@ -23,7 +36,11 @@ fn get_instances_from_dir(
 ) -> Result<(), CliError> {
     let file_type = fs::metadata(&path)?.file_type();
     if file_type.is_file() {
-        let is_proof_file = path
+        let mut uncompressed = path.clone();
+        if compression_extension(&path).is_some() {
+            uncompressed.set_extension("");
+        }
+        let is_proof_file = uncompressed
             .extension()
             .and_then(OsStr::to_str)
             .is_some_and(|ext| ALETHE_FILE_EXTENSIONS.contains(&ext));